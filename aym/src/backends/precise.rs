@@ -350,12 +350,13 @@ impl AymPrecise {
         self.envelope
     }
 
-    fn update_mixer(&mut self) {
+    /// Advances the chip by one tick, returns DAC table index of each
+    /// channel output
+    fn update_outputs(&mut self) -> [usize; TONE_CHANNELS] {
         let noise = self.update_noise();
         let envelope = self.update_envelope();
-        self.left = 0.0;
-        self.right = 0.0;
-        for i in 0..TONE_CHANNELS {
+        let mut outputs = [0; TONE_CHANNELS];
+        for (i, output) in outputs.iter_mut().enumerate() {
             let mut out = (self.update_tone(i) | self.channels[i].tone_off_bit)
                 & (noise | self.channels[i].noise_off_bit);
             out *= if self.channels[i].envelope_enabled {
@@ -364,8 +365,18 @@ impl AymPrecise {
                 self.channels[i].volume * 2 + 1
             };
             assert!(out < 32);
-            self.left += self.dac_table[out] * self.channels[i].pan_left;
-            self.right += self.dac_table[out] * self.channels[i].pan_right;
+            *output = out;
+        }
+        outputs
+    }
+
+    fn update_mixer(&mut self) {
+        let outputs = self.update_outputs();
+        self.left = 0.0;
+        self.right = 0.0;
+        for (channel, out) in self.channels.iter().zip(outputs) {
+            self.left += self.dac_table[out] * channel.pan_left;
+            self.right += self.dac_table[out] * channel.pan_right;
         }
     }
 }
@@ -477,6 +488,21 @@ impl AymPrecise {
     pub fn enable_dc_filter(&mut self) {
         self.dc_filter = true;
     }
+
+    /// Advances the chip by one tick of its internal clock (chip clock / 8)
+    /// and returns output level (`0.0..=1.0`) of each channel. Unlike
+    /// [AymBackend::next_sample], the output is neither resampled nor
+    /// filtered, so hosts can synthesize the sound from the level changes
+    pub fn tick(&mut self) -> [f64; 3] {
+        self.update_outputs().map(|out| self.dac_table[out])
+    }
+
+    /// Returns left and right gain of the channel (0..3), as set by the
+    /// stereo mode of the chip
+    pub fn channel_pan(&self, index: usize) -> (f64, f64) {
+        let channel = &self.channels[index];
+        (channel.pan_left, channel.pan_right)
+    }
 }
 
 impl AymBackend for AymPrecise {
@@ -580,3 +606,179 @@ impl AymBackend for AymPrecise {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns chip with the register writes applied in order
+    fn ay(script: &[(u8, u8)]) -> AymPrecise {
+        let mut ay = <AymPrecise as AymBackend>::new(SoundChip::AY, AyMode::Mono, 1773400, 44100);
+        for &(address, value) in script {
+            ay.write_register(address, value);
+        }
+        ay
+    }
+
+    /// Runs the chip for `N` ticks of its internal clock (chip clock / 8),
+    /// returns envelope level (0..=31) after each of them
+    fn envelope<const N: usize>(ay: &mut AymPrecise) -> [usize; N] {
+        core::array::from_fn(|_| {
+            ay.update_mixer();
+            ay.envelope
+        })
+    }
+
+    /// Same as [envelope], but returns tone output of the channel A
+    fn tone<const N: usize>(ay: &mut AymPrecise) -> [usize; N] {
+        core::array::from_fn(|_| {
+            ay.update_mixer();
+            ay.channels[0].tone
+        })
+    }
+
+    /// Envelope level `step` steps after the shape register write, as the
+    /// shapes are described in the AY-3-8910 and YM2149 datasheets (with
+    /// 32 steps per cycle)
+    fn datasheet_envelope(shape: u8, step: usize) -> usize {
+        let hold = shape & 0x01 != 0;
+        let alternate = shape & 0x02 != 0;
+        let attack = shape & 0x04 != 0;
+        let cont = shape & 0x08 != 0;
+        let (cycle, position) = (step / 32, step % 32);
+        let level = |up: bool| if up { position } else { 31 - position };
+        if cycle == 0 {
+            return level(attack);
+        }
+        if !cont {
+            return 0;
+        }
+        if hold {
+            return if attack != alternate { 31 } else { 0 };
+        }
+        level(attack != (alternate && cycle % 2 == 1))
+    }
+
+    #[test]
+    fn tone_period_zero_acts_as_period_one() {
+        // Unused bits of the coarse period register are ignored
+        let mut zero = ay(&[(0, 0), (1, 0xF0)]);
+        let mut one = ay(&[(0, 1), (1, 0)]);
+        let tone_one = tone::<8>(&mut one);
+        assert_eq!(tone_one, [1, 0, 1, 0, 1, 0, 1, 0]);
+        assert_eq!(tone::<8>(&mut zero), tone_one);
+
+        let mut two = ay(&[(0, 2), (1, 0)]);
+        assert_eq!(tone::<8>(&mut two), [0, 1, 1, 0, 0, 1, 1, 0]);
+    }
+
+    #[test]
+    fn envelope_period_zero_acts_as_period_one() {
+        let mut zero = ay(&[(11, 0), (12, 0), (13, 0x0C)]);
+        let mut one = ay(&[(11, 1), (12, 0), (13, 0x0C)]);
+        let envelope_one = envelope::<64>(&mut one);
+        assert_eq!(envelope_one[..4], [1, 2, 3, 4]);
+        assert_eq!(envelope::<64>(&mut zero), envelope_one);
+    }
+
+    #[test]
+    fn envelope_shapes_match_datasheet() {
+        for shape in 0..16 {
+            let mut ay = ay(&[(11, 1), (12, 0), (13, shape)]);
+            let expected: [usize; 128] =
+                core::array::from_fn(|tick| datasheet_envelope(shape, tick + 1));
+            assert_eq!(envelope::<128>(&mut ay), expected, "shape {}", shape);
+        }
+    }
+
+    #[test]
+    fn envelope_period_scales_step_length() {
+        let mut ay = ay(&[(11, 3), (12, 0), (13, 0x0E)]);
+        let levels = envelope::<288>(&mut ay);
+        for (tick, level) in levels.iter().enumerate() {
+            assert_eq!(
+                *level,
+                datasheet_envelope(0x0E, (tick + 1) / 3),
+                "tick {}",
+                tick
+            );
+        }
+    }
+
+    #[test]
+    fn shape_write_retriggers_envelope() {
+        // Attack, then hold at the top
+        let mut ay = ay(&[(11, 1), (12, 0), (13, 0x0D)]);
+        let first = envelope::<40>(&mut ay);
+        assert_eq!(first[39], 31);
+
+        // Writing the same shape again restarts the envelope
+        ay.write_register(13, 0x0D);
+        assert_eq!(ay.envelope, 0);
+        assert_eq!(envelope::<40>(&mut ay), first);
+    }
+
+    #[test]
+    fn envelope_retriggered_at_audio_rate_is_periodic() {
+        // Fast envelope bass: decay shape restarted each 8 ticks produces a
+        // sawtooth of 8 ticks period, which never reaches the bottom
+        let mut ay = ay(&[(11, 1), (12, 0), (13, 0x00)]);
+        for _ in 0..16 {
+            assert_eq!(envelope::<8>(&mut ay), [30, 29, 28, 27, 26, 25, 24, 23]);
+            ay.write_register(13, 0x00);
+        }
+    }
+
+    #[test]
+    fn tone_and_noise_are_anded() {
+        // Channel A with tone and noise enabled, B and C silent
+        let mut ay = ay(&[(0, 3), (1, 0), (6, 1), (7, 0x36), (8, 0x0F)]);
+        let mut tone_without_noise = 0;
+        for _ in 0..1000 {
+            ay.update_mixer();
+            let tone = ay.channels[0].tone;
+            let noise = ay.noise & 1;
+            if tone == 1 && noise == 0 {
+                tone_without_noise += 1;
+            }
+            assert_eq!(ay.left > 0.0, tone & noise == 1);
+        }
+        assert!(tone_without_noise > 0);
+    }
+
+    #[test]
+    fn tick_returns_panned_channel_levels() {
+        let script = [
+            (0, 3),
+            (1, 0),
+            (2, 5),
+            (3, 0),
+            (7, 0x3C),
+            (8, 0x0F),
+            (9, 0x08),
+        ];
+        let mut mixed = ay(&script);
+        let mut ticked = ay(&script);
+        for _ in 0..100 {
+            mixed.update_mixer();
+            let levels = ticked.tick();
+            let left = (0..3)
+                .map(|index| levels[index] * ticked.channel_pan(index).0)
+                .sum::<f64>();
+            assert_eq!(left, mixed.left);
+        }
+    }
+
+    #[test]
+    fn disabled_tone_and_noise_output_volume_level() {
+        // Output is the constant volume level, used for sample playback
+        let mut ay = ay(&[(0, 3), (1, 0), (6, 1), (7, 0x3F), (8, 0x0F)]);
+        ay.update_mixer();
+        let level = ay.left;
+        assert!(level > 0.0);
+        for _ in 0..1000 {
+            ay.update_mixer();
+            assert_eq!(ay.left, level);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Subsystem diagnostics via the `log` facade. Each subsystem uses its own
+//! target, so a single one can be enabled in the host logger (e.g.
+//! `rustzx::tape=debug`). Without the `log` feature all diagnostics are
+//! compiled out
+
+pub(crate) const TAPE: &str = "rustzx::tape";
+pub(crate) const SNAPSHOT: &str = "rustzx::snapshot";
+pub(crate) const SCREEN: &str = "rustzx::screen";
+pub(crate) const ROM: &str = "rustzx::rom";
+pub(crate) const MEMORY: &str = "rustzx::memory";
+pub(crate) const MACHINE: &str = "rustzx::machine";
+pub(crate) const PRINTER: &str = "rustzx::printer";
+#[cfg(any(feature = "ide", feature = "plusd", feature = "betadisk"))]
+pub(crate) const DISK: &str = "rustzx::disk";
+#[cfg(feature = "ay")]
+pub(crate) const SERIAL: &str = "rustzx::serial";
+#[cfg(feature = "spectranet")]
+pub(crate) const NETWORK: &str = "rustzx::network";
+
+/// Logs message with the given level and subsystem target:
+/// `diag!(debug, TAPE, "block {}", index)`
+macro_rules! diag {
+    ($level:ident, $target:expr, $($arg:tt)+) => {
+        #[cfg(feature = "log")]
+        ::log::$level!(target: $target, $($arg)+);
+        // Keep arguments type-checked and used when logging is compiled out
+        #[cfg(not(feature = "log"))]
+        if false {
+            let _ = ($target, format_args!($($arg)+));
+        }
+    };
+}
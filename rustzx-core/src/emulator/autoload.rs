@@ -0,0 +1,95 @@
+//! Tape autoload by typing the loading command, used when the built-in
+//! autoload snapshots do not match the loaded ROM (snapshots contain the
+//! state of the built-in ROM, which would crash with the custom one)
+use crate::{diagnostics::TAPE, emulator::Emulator, host::Host, zx::machine::MemoryModel};
+
+/// FRAMES system variable, frame counter incremented by ROM interrupt handler
+const ADDR_SYSVAR_FRAMES: u16 = 0x5C78;
+/// `LOAD` keyword token
+const TOKEN_LOAD: u8 = 0xEF;
+const KEY_ENTER: u8 = 0x0D;
+/// `LOAD ""` typed at the 48K BASIC prompt
+const KEYS_48K: &[u8] = &[TOKEN_LOAD, b'"', b'"', KEY_ENTER];
+/// "Tape Loader" is the first (selected) option of the 128K menu
+const KEYS_128K: &[u8] = &[KEY_ENTER];
+/// Count of frames during which ROM should service interrupts before typing.
+/// Boot time varies between ROMs, so readiness is detected by the running
+/// interrupt handler (RAM test and initialization run with interrupts
+/// disabled) rather than by the fixed boot time
+const READY_FRAMES: usize = 25;
+/// Autoload is cancelled if ROM does not get ready or does not take the keys
+/// in this count of frames (15 seconds)
+const TIMEOUT_FRAMES: usize = 50 * 15;
+
+pub(crate) struct TypedAutoload {
+    keys: &'static [u8],
+    typed: usize,
+    last_frames_sysvar: Option<u16>,
+    ready_frames: usize,
+    elapsed_frames: usize,
+}
+
+impl TypedAutoload {
+    pub fn new(memory_model: MemoryModel) -> Self {
+        let keys = match memory_model {
+            MemoryModel::Sinclair48K => KEYS_48K,
+            // +3 menu also starts with "Loader"
+            MemoryModel::Sinclair128K | MemoryModel::SinclairPlus3 => KEYS_128K,
+        };
+        Self {
+            keys,
+            typed: 0,
+            last_frames_sysvar: None,
+            ready_frames: 0,
+            elapsed_frames: 0,
+        }
+    }
+
+    /// Advances autoload on the frame start. Returns false when autoload is
+    /// finished or cancelled
+    pub fn process<H: Host>(&mut self, emulator: &mut Emulator<H>) -> bool {
+        self.elapsed_frames += 1;
+        if self.elapsed_frames > TIMEOUT_FRAMES {
+            diag!(
+                warn,
+                TAPE,
+                "autoload cancelled, ROM took {} of {} keys",
+                self.typed,
+                self.keys.len()
+            );
+            return false;
+        }
+
+        // FRAMES system variable is incremented by ROM interrupt handler
+        let frames = u16::from_le_bytes([
+            emulator.peek(ADDR_SYSVAR_FRAMES),
+            emulator.peek(ADDR_SYSVAR_FRAMES + 1),
+        ]);
+        if self.last_frames_sysvar.map(|last| last.wrapping_add(1)) == Some(frames) {
+            self.ready_frames += 1;
+        } else if self.typed == 0 {
+            self.ready_frames = 0;
+        }
+        self.last_frames_sysvar = Some(frames);
+        if self.ready_frames < READY_FRAMES || emulator.is_key_char_pending() {
+            return true;
+        }
+
+        match self.keys.get(self.typed) {
+            Some(&key) => {
+                emulator.inject_key_char(key);
+                self.typed += 1;
+                true
+            }
+            None => {
+                diag!(
+                    debug,
+                    TAPE,
+                    "autoload typed after {} frames",
+                    self.elapsed_frames
+                );
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Export of the BASIC program from memory to TAP blocks, same as
+//! `SAVE "name"` would produce
+
+use crate::{
+    diagnostics::TAPE,
+    emulator::Emulator,
+    error::BasicExportError,
+    host::{DataRecorder, Host},
+    zx::constants::{ADDR_SYSVAR_E_LINE, ADDR_SYSVAR_PROG, ADDR_SYSVAR_VARS},
+    Result,
+};
+use alloc::vec::Vec;
+
+const FLAG_HEADER: u8 = 0x00;
+const FLAG_DATA: u8 = 0xFF;
+const HEADER_TYPE_PROGRAM: u8 = 0x00;
+const HEADER_SIZE: usize = 17;
+const NAME_SIZE: usize = 10;
+/// Start line values of 32768 and above mean no autostart
+const NO_AUTOSTART: u16 = 0x8000;
+
+/// Writes header and data TAP blocks of the program located by PROG, VARS
+/// and E LINE system variables. Program variables are saved too, the end
+/// marker of the variables area is not (same as ROM SAVE routine does)
+pub(crate) fn export_tap<H, R>(emulator: &Emulator<H>, mut recorder: R, name: &str) -> Result<()>
+where
+    H: Host,
+    R: DataRecorder,
+{
+    let name = name.as_bytes();
+    if name.len() > NAME_SIZE || !name.iter().all(|c| (0x20..0x7F).contains(c)) {
+        return Err(BasicExportError::InvalidName.into());
+    }
+
+    let read_sysvar =
+        |addr: u16| u16::from_le_bytes([emulator.peek(addr), emulator.peek(addr.wrapping_add(1))]);
+    let prog = read_sysvar(ADDR_SYSVAR_PROG);
+    let vars = read_sysvar(ADDR_SYSVAR_VARS);
+    let e_line = read_sysvar(ADDR_SYSVAR_E_LINE);
+    if prog < 0x4000 || prog > vars || vars >= e_line {
+        diag!(
+            warn,
+            TAPE,
+            "invalid program area PROG={:#06X} VARS={:#06X} E_LINE={:#06X}",
+            prog,
+            vars,
+            e_line
+        );
+        return Err(BasicExportError::InvalidProgramArea.into());
+    }
+    let data_length = e_line - prog - 1;
+    let program_length = vars - prog;
+
+    let mut header = [0u8; HEADER_SIZE];
+    header[0] = HEADER_TYPE_PROGRAM;
+    header[1..1 + NAME_SIZE].fill(b' ');
+    header[1..1 + name.len()].copy_from_slice(name);
+    header[11..13].copy_from_slice(&data_length.to_le_bytes());
+    header[13..15].copy_from_slice(&NO_AUTOSTART.to_le_bytes());
+    header[15..17].copy_from_slice(&program_length.to_le_bytes());
+    write_block(&mut recorder, FLAG_HEADER, &header)?;
+
+    let data = (prog..prog + data_length)
+        .map(|addr| emulator.peek(addr))
+        .collect::<Vec<_>>();
+    write_block(&mut recorder, FLAG_DATA, &data)
+}
+
+/// Writes TAP block with its length, flag and checksum
+fn write_block(recorder: &mut impl DataRecorder, flag: u8, data: &[u8]) -> Result<()> {
+    let length = data.len() as u16 + 2;
+    let checksum = data.iter().fold(flag, |acc, byte| acc ^ byte);
+    recorder.write_all(&length.to_le_bytes())?;
+    recorder.write_all(&[flag])?;
+    recorder.write_all(data)?;
+    recorder.write_all(&[checksum])?;
+    Ok(())
+}
@@ -0,0 +1,357 @@
+//! Disassembler of the single Z80 instruction, see
+//! [crate::Emulator::current_instruction]. Numbers are written in hex,
+//! relative jumps show the target address
+use alloc::{format, string::String};
+
+const PREFIX_CB: u8 = 0xCB;
+const PREFIX_ED: u8 = 0xED;
+const PREFIX_IX: u8 = 0xDD;
+const PREFIX_IY: u8 = 0xFD;
+
+const REGS: [&str; 8] = ["B", "C", "D", "E", "H", "L", "(HL)", "A"];
+const PAIRS_SP: [&str; 4] = ["BC", "DE", "HL", "SP"];
+const PAIRS_AF: [&str; 4] = ["BC", "DE", "HL", "AF"];
+const CONDITIONS: [&str; 8] = ["NZ", "Z", "NC", "C", "PO", "PE", "P", "M"];
+const ALU: [&str; 8] = [
+    "ADD A,", "ADC A,", "SUB ", "SBC A,", "AND ", "XOR ", "OR ", "CP ",
+];
+const ROTATIONS: [&str; 8] = ["RLC", "RRC", "RL", "RR", "SLA", "SRA", "SLL", "SRL"];
+const ACCUMULATOR_OPS: [&str; 8] = ["RLCA", "RRCA", "RLA", "RRA", "DAA", "CPL", "SCF", "CCF"];
+const INTERRUPT_MODES: [u8; 8] = [0, 0, 1, 2, 0, 0, 1, 2];
+/// Block instructions by repeat/direction (`y - 4`) and operation (`z`)
+const BLOCK_OPS: [[&str; 4]; 4] = [
+    ["LDI", "CPI", "INI", "OUTI"],
+    ["LDD", "CPD", "IND", "OUTD"],
+    ["LDIR", "CPIR", "INIR", "OTIR"],
+    ["LDDR", "CPDR", "INDR", "OTDR"],
+];
+const REG_HL_INDIRECT: usize = 6;
+
+struct Decoder<F> {
+    pc: u16,
+    len: u16,
+    read: F,
+    /// `IX` or `IY` when the instruction has index prefix
+    index: Option<&'static str>,
+    /// Displacement of `(IX+d)`, read before the opcode in `DD CB` instructions
+    displacement: Option<i8>,
+}
+
+impl<F: Fn(u16) -> u8> Decoder<F> {
+    fn byte(&mut self) -> u8 {
+        let byte = (self.read)(self.pc.wrapping_add(self.len));
+        self.len += 1;
+        byte
+    }
+
+    fn word(&mut self) -> u16 {
+        let low = self.byte();
+        u16::from_le_bytes([low, self.byte()])
+    }
+
+    fn imm8(&mut self) -> String {
+        format!("0x{:02X}", self.byte())
+    }
+
+    fn imm16(&mut self) -> String {
+        format!("0x{:04X}", self.word())
+    }
+
+    /// Target of the relative jump, displacement is counted from the next
+    /// instruction
+    fn relative(&mut self) -> String {
+        let offset = self.byte() as i8;
+        let target = self.pc.wrapping_add(self.len).wrapping_add(offset as u16);
+        format!("0x{:04X}", target)
+    }
+
+    fn hl(&self) -> &'static str {
+        self.index.unwrap_or("HL")
+    }
+
+    /// Returns 8-bit register operand. With index prefix `(HL)` becomes
+    /// `(IX+d)`, and `H`/`L` become `IXH`/`IXL` unless `keep_hl` is set
+    /// (instructions which use `(IX+d)` access real `H` and `L`)
+    fn reg(&mut self, reg: usize, keep_hl: bool) -> String {
+        let Some(index) = self.index else {
+            return REGS[reg].into();
+        };
+        match reg {
+            REG_HL_INDIRECT => {
+                let d = match self.displacement {
+                    Some(d) => d,
+                    None => self.byte() as i8,
+                };
+                let sign = if d < 0 { '-' } else { '+' };
+                format!("({}{}0x{:02X})", index, sign, d.unsigned_abs())
+            }
+            4 | 5 if !keep_hl => format!("{}{}", index, REGS[reg]),
+            _ => REGS[reg].into(),
+        }
+    }
+
+    fn pair(&self, pairs: &[&'static str; 4], p: usize) -> &'static str {
+        if p == 2 {
+            self.hl()
+        } else {
+            pairs[p]
+        }
+    }
+
+    fn decode(&mut self) -> String {
+        let opcode = self.byte();
+        let index = match opcode {
+            PREFIX_IX => "IX",
+            PREFIX_IY => "IY",
+            PREFIX_ED => return self.decode_extended(),
+            PREFIX_CB => return self.decode_bits(),
+            _ => return self.decode_normal(opcode),
+        };
+        // Index prefix followed by another prefix has no effect, it is
+        // decoded on its own so prefix chains of any length are listed
+        // one prefix per instruction
+        let next = (self.read)(self.pc.wrapping_add(self.len));
+        if matches!(next, PREFIX_IX | PREFIX_IY | PREFIX_ED) {
+            return "NOP".into();
+        }
+        self.index = Some(index);
+        let opcode = self.byte();
+        match opcode {
+            PREFIX_CB => self.decode_bits(),
+            _ => self.decode_normal(opcode),
+        }
+    }
+
+    fn decode_normal(&mut self, opcode: u8) -> String {
+        let (x, y, z) = split_opcode(opcode);
+        let (p, q) = (y >> 1, y & 1);
+        match (x, z) {
+            (0, 0) => match y {
+                0 => "NOP".into(),
+                1 => "EX AF,AF'".into(),
+                2 => format!("DJNZ {}", self.relative()),
+                3 => format!("JR {}", self.relative()),
+                _ => {
+                    let target = self.relative();
+                    format!("JR {},{}", CONDITIONS[y - 4], target)
+                }
+            },
+            (0, 1) if q == 0 => {
+                let pair = self.pair(&PAIRS_SP, p);
+                format!("LD {},{}", pair, self.imm16())
+            }
+            (0, 1) => format!("ADD {},{}", self.hl(), self.pair(&PAIRS_SP, p)),
+            (0, 2) => match (p, q) {
+                (0, 0) => "LD (BC),A".into(),
+                (1, 0) => "LD (DE),A".into(),
+                (2, 0) => format!("LD ({}),{}", self.imm16(), self.hl()),
+                (3, 0) => format!("LD ({}),A", self.imm16()),
+                (0, _) => "LD A,(BC)".into(),
+                (1, _) => "LD A,(DE)".into(),
+                (2, _) => format!("LD {},({})", self.hl(), self.imm16()),
+                _ => format!("LD A,({})", self.imm16()),
+            },
+            (0, 3) => {
+                let op = if q == 0 { "INC" } else { "DEC" };
+                format!("{} {}", op, self.pair(&PAIRS_SP, p))
+            }
+            (0, 4) => format!("INC {}", self.reg(y, false)),
+            (0, 5) => format!("DEC {}", self.reg(y, false)),
+            (0, 6) => {
+                let reg = self.reg(y, false);
+                format!("LD {},{}", reg, self.imm8())
+            }
+            (0, _) => ACCUMULATOR_OPS[y].into(),
+            (1, _) if y == REG_HL_INDIRECT && z == REG_HL_INDIRECT => "HALT".into(),
+            (1, _) => {
+                let keep_hl = y == REG_HL_INDIRECT || z == REG_HL_INDIRECT;
+                let dst = self.reg(y, keep_hl);
+                let src = self.reg(z, keep_hl);
+                format!("LD {},{}", dst, src)
+            }
+            (2, _) => format!("{}{}", ALU[y], self.reg(z, false)),
+            (_, 0) => format!("RET {}", CONDITIONS[y]),
+            (_, 1) => match (q, p) {
+                (0, _) => format!("POP {}", self.pair(&PAIRS_AF, p)),
+                (_, 0) => "RET".into(),
+                (_, 1) => "EXX".into(),
+                (_, 2) => format!("JP ({})", self.hl()),
+                _ => format!("LD SP,{}", self.hl()),
+            },
+            (_, 2) => format!("JP {},{}", CONDITIONS[y], self.imm16()),
+            (_, 3) => match y {
+                0 => format!("JP {}", self.imm16()),
+                2 => format!("OUT ({}),A", self.imm8()),
+                3 => format!("IN A,({})", self.imm8()),
+                4 => format!("EX (SP),{}", self.hl()),
+                5 => "EX DE,HL".into(),
+                6 => "DI".into(),
+                // CB prefix is handled by the caller
+                _ => "EI".into(),
+            },
+            (_, 4) => format!("CALL {},{}", CONDITIONS[y], self.imm16()),
+            (_, 5) if q == 0 => format!("PUSH {}", self.pair(&PAIRS_AF, p)),
+            // Prefixes are handled by the caller
+            (_, 5) => format!("CALL {}", self.imm16()),
+            (_, 6) => format!("{}{}", ALU[y], self.imm8()),
+            _ => format!("RST 0x{:02X}", y * 8),
+        }
+    }
+
+    fn decode_bits(&mut self) -> String {
+        if self.index.is_some() {
+            // DD CB d op
+            self.displacement = Some(self.byte() as i8);
+        }
+        let (x, y, z) = split_opcode(self.byte());
+        let target = self.reg(if self.index.is_some() { 6 } else { z }, true);
+        let op = match x {
+            0 => format!("{} {}", ROTATIONS[y], target),
+            1 => return format!("BIT {},{}", y, target),
+            2 => format!("RES {},{}", y, target),
+            _ => format!("SET {},{}", y, target),
+        };
+        // Indexed operation also stores the result into the register
+        if self.index.is_some() && z != REG_HL_INDIRECT {
+            format!("{},{}", op, REGS[z])
+        } else {
+            op
+        }
+    }
+
+    fn decode_extended(&mut self) -> String {
+        let (x, y, z) = split_opcode(self.byte());
+        let (p, q) = (y >> 1, y & 1);
+        match (x, z) {
+            (1, 0) if y == REG_HL_INDIRECT => "IN (C)".into(),
+            (1, 0) => format!("IN {},(C)", REGS[y]),
+            (1, 1) if y == REG_HL_INDIRECT => "OUT (C),0".into(),
+            (1, 1) => format!("OUT (C),{}", REGS[y]),
+            (1, 2) => {
+                let op = if q == 0 { "SBC" } else { "ADC" };
+                format!("{} HL,{}", op, PAIRS_SP[p])
+            }
+            (1, 3) if q == 0 => format!("LD ({}),{}", self.imm16(), PAIRS_SP[p]),
+            (1, 3) => format!("LD {},({})", PAIRS_SP[p], self.imm16()),
+            (1, 4) => "NEG".into(),
+            (1, 5) if y == 1 => "RETI".into(),
+            (1, 5) => "RETN".into(),
+            (1, 6) => format!("IM {}", INTERRUPT_MODES[y]),
+            (1, _) => match y {
+                0 => "LD I,A".into(),
+                1 => "LD R,A".into(),
+                2 => "LD A,I".into(),
+                3 => "LD A,R".into(),
+                4 => "RRD".into(),
+                5 => "RLD".into(),
+                _ => "NOP".into(),
+            },
+            (2, 0..=3) if y >= 4 => BLOCK_OPS[y - 4][z].into(),
+            // Invalid extended instructions are executed as NOP
+            _ => "NOP".into(),
+        }
+    }
+}
+
+/// Splits opcode to `x` (bits 7-6), `y` (bits 5-3) and `z` (bits 2-0)
+fn split_opcode(opcode: u8) -> (usize, usize, usize) {
+    (
+        (opcode >> 6) as usize,
+        ((opcode >> 3) & 7) as usize,
+        (opcode & 7) as usize,
+    )
+}
+
+/// Returns text and length of the instruction at `pc`, memory is read via
+/// `read`. `DD`/`FD` prefix followed by another prefix is ignored by the CPU
+/// and is decoded as 1-byte `NOP`
+pub(crate) fn disassemble(pc: u16, read: impl Fn(u16) -> u8) -> (String, u16) {
+    let mut decoder = Decoder {
+        pc,
+        len: 0,
+        read,
+        index: None,
+        displacement: None,
+    };
+    let text = decoder.decode();
+    (text, decoder.len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disasm(code: &[u8]) -> (String, u16) {
+        disassemble(0x8000, |addr| {
+            code.get((addr - 0x8000) as usize).copied().unwrap_or(0)
+        })
+    }
+
+    #[test]
+    fn unprefixed_instructions() {
+        let cases: &[(&[u8], &str)] = &[
+            (&[0x00], "NOP"),
+            (&[0x7E], "LD A,(HL)"),
+            (&[0x21, 0x34, 0x12], "LD HL,0x1234"),
+            (&[0x32, 0x00, 0x90], "LD (0x9000),A"),
+            (&[0x36, 0xAA], "LD (HL),0xAA"),
+            (&[0x18, 0xFE], "JR 0x8000"),
+            (&[0x20, 0x05], "JR NZ,0x8007"),
+            (&[0x10, 0x80], "DJNZ 0x7F82"),
+            (&[0x08], "EX AF,AF'"),
+            (&[0x76], "HALT"),
+            (&[0x96], "SUB (HL)"),
+            (&[0xCE, 0x01], "ADC A,0x01"),
+            (&[0xD4, 0x00, 0x80], "CALL NC,0x8000"),
+            (&[0xF1], "POP AF"),
+            (&[0xE9], "JP (HL)"),
+            (&[0xDB, 0xFE], "IN A,(0xFE)"),
+            (&[0xFF], "RST 0x38"),
+            (&[0x1F], "RRA"),
+        ];
+        for (code, text) in cases {
+            assert_eq!(disasm(code), ((*text).into(), code.len() as u16));
+        }
+    }
+
+    #[test]
+    fn prefixed_instructions() {
+        let cases: &[(&[u8], &str)] = &[
+            (&[0xCB, 0x7E], "BIT 7,(HL)"),
+            (&[0xCB, 0x31], "SLL C"),
+            (&[0xED, 0xB0], "LDIR"),
+            (&[0xED, 0x43, 0x00, 0x90], "LD (0x9000),BC"),
+            (&[0xED, 0x78], "IN A,(C)"),
+            (&[0xED, 0x71], "OUT (C),0"),
+            (&[0xED, 0x5E], "IM 2"),
+            (&[0xED, 0x4D], "RETI"),
+            (&[0xED, 0x00], "NOP"),
+            (&[0xDD, 0x21, 0x00, 0x5C], "LD IX,0x5C00"),
+            (&[0xDD, 0x7E, 0x05], "LD A,(IX+0x05)"),
+            (&[0xFD, 0x66, 0xFE], "LD H,(IY-0x02)"),
+            (&[0xDD, 0x36, 0x01, 0x42], "LD (IX+0x01),0x42"),
+            (&[0xDD, 0x65], "LD IXH,IXL"),
+            (&[0xFD, 0x2C], "INC IYL"),
+            (&[0xDD, 0x09], "ADD IX,BC"),
+            (&[0xFD, 0xE9], "JP (IY)"),
+            (&[0xDD, 0xEB], "EX DE,HL"),
+            (&[0xDD, 0xCB, 0x03, 0x46], "BIT 0,(IX+0x03)"),
+            (&[0xFD, 0xCB, 0xFF, 0xC0], "SET 0,(IY-0x01),B"),
+            (&[0xFD, 0xCB, 0x02, 0x16], "RL (IY+0x02)"),
+        ];
+        for (code, text) in cases {
+            assert_eq!(disasm(code), ((*text).into(), code.len() as u16));
+        }
+    }
+
+    #[test]
+    fn ignored_prefixes() {
+        // Prefix followed by another prefix has no effect
+        assert_eq!(disasm(&[0xDD, 0xFD, 0x23]), ("NOP".into(), 1));
+        assert_eq!(disasm(&[0xFD, 0xFD, 0x23]), ("NOP".into(), 1));
+        assert_eq!(disasm(&[0xDD, 0xED, 0x44]), ("NOP".into(), 1));
+        assert_eq!(disasm(&[0xFD, 0x23]), ("INC IY".into(), 2));
+        // Memory filled with prefixes
+        assert_eq!(disassemble(0, |_| PREFIX_IX), ("NOP".into(), 1));
+    }
+}
@@ -1,5 +1,5 @@
 // emulator
-use crate::{emulator::Emulator, host::Host, zx::tape::TapeImpl, Result};
+use crate::{diagnostics::TAPE, emulator::Emulator, host::Host, zx::tape::TapeImpl, Result};
 use rustzx_z80::{RegName16, Z80Bus, FLAG_CARRY, FLAG_ZERO};
 
 pub fn fast_load_tap<H: Host>(emulator: &mut Emulator<H>) -> Result<()> {
@@ -17,8 +17,22 @@ pub fn fast_load_tap<H: Host>(emulator: &mut Emulator<H>) -> Result<()> {
     let mut length = emulator.cpu.regs.get_reg_16(RegName16::DE);
     // parity accumulator and current byte (h, l) regs
     let (mut parity_acc, mut current_byte) = (0, 0);
+    diag!(
+        debug,
+        TAPE,
+        "fast load trap hit: {} {} bytes to {:#06X}, expected flag {:#04X}",
+        if (f & FLAG_CARRY) != 0 {
+            "load"
+        } else {
+            "verify"
+        },
+        length,
+        dest,
+        acc
+    );
     // move to next block
     if !emulator.controller.tape.next_block()? {
+        diag!(debug, TAPE, "fast load trap hit at the end of tape");
         return Ok(());
     }
 
@@ -81,6 +95,17 @@ pub fn fast_load_tap<H: Host>(emulator: &mut Emulator<H>) -> Result<()> {
         .regs
         .set_hl(u16::from_le_bytes([current_byte, parity_acc]));
     emulator.cpu.regs.set_acc(acc);
+    diag!(
+        debug,
+        TAPE,
+        "fast load finished: {} bytes left, {}",
+        length,
+        match result_flags {
+            Some(FLAG_CARRY) => "success",
+            Some(FLAG_ZERO) => "block is shorter than requested",
+            _ => "flag, verify or parity error",
+        }
+    );
     // set new flag, if something changed
     if let Some(new_flags) = result_flags {
         f = new_flags;
@@ -0,0 +1,100 @@
+//! Detection of the idle program (e.g. title screen or BASIC editor waiting
+//! for a key), for hosts which pause emulation or dim sound in attract or
+//! kiosk setups. Program is idle when for the whole window it executes code
+//! from a few 256-byte pages and writes no ports. Port reads are keyboard and
+//! joystick polling, so they are not counted as activity. Detection is purely
+//! observational, state changes are reported via
+//! [crate::host::DebugInterface::idle_changed]
+
+/// Count of 256-byte pages in the address space
+const PAGES: usize = 256;
+
+/// Thresholds of the idle detection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IdleConfig {
+    /// Frames without activity after which the program is reported as idle
+    pub window_frames: u32,
+    /// Maximal count of distinct 256-byte PC pages executed during the
+    /// window, including interrupt handlers
+    pub max_pc_pages: u32,
+    /// Maximal count of port writes during the window
+    pub max_port_writes: u32,
+}
+
+impl Default for IdleConfig {
+    /// 5 seconds on 50Hz machines. ROM waiting for a key executes about 8
+    /// pages, including the keyboard scanning interrupt handler
+    fn default() -> Self {
+        Self {
+            window_frames: 250,
+            max_pc_pages: 8,
+            max_port_writes: 0,
+        }
+    }
+}
+
+pub(crate) struct IdleDetector {
+    config: IdleConfig,
+    /// Executed PC pages since the window start, bit per page
+    pages: [u32; PAGES / 32],
+    page_count: u32,
+    port_writes: u32,
+    frames: u32,
+    idle: bool,
+}
+
+impl IdleDetector {
+    pub fn new(config: IdleConfig) -> Self {
+        Self {
+            config,
+            pages: [0; PAGES / 32],
+            page_count: 0,
+            port_writes: 0,
+            frames: 0,
+            idle: false,
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// Starts new window after the activity
+    fn restart_window(&mut self) {
+        self.pages = [0; PAGES / 32];
+        self.page_count = 0;
+        self.port_writes = 0;
+        self.frames = 0;
+    }
+
+    /// Observes executed instruction. Returns new idle state if it has changed
+    pub fn instruction(&mut self, pc: u16, port_writes: u32, frame_started: bool) -> Option<bool> {
+        let page = (pc >> 8) as usize;
+        let mask = 1 << (page % 32);
+        if self.pages[page / 32] & mask == 0 {
+            self.pages[page / 32] |= mask;
+            self.page_count += 1;
+        }
+        self.port_writes += port_writes;
+        if frame_started {
+            self.frames += 1;
+        }
+
+        let active = self.page_count > self.config.max_pc_pages
+            || self.port_writes > self.config.max_port_writes;
+        if active {
+            self.restart_window();
+        }
+
+        let idle = match (self.idle, active) {
+            (_, true) => false,
+            (true, false) => true,
+            (false, false) => self.frames >= self.config.window_frames,
+        };
+        if idle == self.idle {
+            return None;
+        }
+        self.idle = idle;
+        Some(idle)
+    }
+}
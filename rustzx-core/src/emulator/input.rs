@@ -0,0 +1,54 @@
+//! Input events which could be scheduled for the specific frame, see
+//! [crate::Emulator::queue_input]
+use crate::zx::{
+    joy::{
+        fuller::FullerKey,
+        kempston::KempstonKey,
+        sinclair::{SinclairJoyNum, SinclairKey},
+    },
+    keys::{CompoundKey, ZXKey},
+    mouse::kempston::{KempstonMouseButton, KempstonMouseWheelDirection},
+};
+use alloc::vec::Vec;
+
+#[derive(Clone, Copy, Debug)]
+pub enum InputEvent {
+    Key(ZXKey, bool),
+    CompoundKey(CompoundKey, bool),
+    Kempston(KempstonKey, bool),
+    Fuller(FullerKey, bool),
+    Sinclair(SinclairJoyNum, SinclairKey, bool),
+    MouseButton(KempstonMouseButton, bool),
+    MouseWheel(KempstonMouseWheelDirection),
+    /// Wheel movement by the given count of steps, positive is up
+    MouseWheelDelta(i8),
+    MouseMove {
+        x: i8,
+        y: i8,
+    },
+}
+
+/// Input changes for the single [crate::Emulator::run_frame] call, applied in
+/// order at the start of the frame
+#[derive(Clone, Debug, Default)]
+pub struct InputState {
+    events: Vec<InputEvent>,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: InputEvent) {
+        self.events.push(event);
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn events(&self) -> &[InputEvent] {
+        &self.events
+    }
+}
@@ -1,41 +1,114 @@
 //! Platform-independent high-level Emulator interaction module
+#[cfg(feature = "autoload")]
+mod autoload;
+mod basic;
+mod disasm;
 mod fastload;
+pub mod idle;
+pub mod input;
+pub mod perf;
 pub mod poke;
+mod reload;
 mod screenshot;
+pub mod search;
 mod snapshot;
+pub mod state;
+mod step;
+pub mod trap;
 
 use crate::{
-    error::RomLoadError,
+    diagnostics::{MACHINE, MEMORY, ROM},
+    error::{BootError, CartridgeLoadError, IoError, MachineMismatchError, RomLoadError},
     host::{
-        DataRecorder, Host, LoadableAsset, RomFormat, RomSet, Screen, ScreenAsset, Snapshot,
-        SnapshotAsset, SnapshotRecorder, Stopwatch, Tape,
+        Cartridge, DataRecorder, DebugInterface, Host, HostContext, LoadableAsset, RomFormat,
+        RomSet, Screen, ScreenAsset, Snapshot, SnapshotAsset, SnapshotRecorder, Stopwatch, Tape,
     },
     settings::RustzxSettings,
     utils::EmulationMode,
     zx::{
+        centronics::CentronicsPort,
+        constants::{ADDR_SYSVAR_FLAGS, ADDR_SYSVAR_LAST_K, ATTR_MAX_REL, FLAGS_NEW_KEY},
         controller::ZXController,
+        dck::{self, BankId, MAX_DCK_SIZE},
         events::EmulationEvents,
         joy::{
+            fuller::FullerKey,
             kempston::KempstonKey,
             sinclair::{SinclairJoyNum, SinclairKey},
+            JoyButton, JoyInterface,
         },
         keys::{CompoundKey, ZXKey},
+        machine::{MachinePolicy, MemoryModel, ZXMachine},
         mouse::kempston::{KempstonMouseButton, KempstonMouseWheelDirection},
-        tape::{Tap, TapeImpl},
-        video::colors::ZXColor,
+        ports::{self, Peripheral, PortLogger},
+        rom_info::RomInfo,
+        storage::{DriveStatus, StorageActivity, StorageDevice},
+        tape::{Tap, TapeImpl, ZXTape},
+        video::{colors::ZXColor, screen_snapshot::ScreenSnapshot, ulaplus::ULAPLUS_PALETTE_SIZE},
+        TapeProgress,
     },
     Result,
 };
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    string::String,
+    vec::Vec,
+};
 use core::time::Duration;
-use rustzx_z80::Z80;
+pub use reload::ReloadMode;
+
+use idle::{IdleConfig, IdleDetector};
+use input::{InputEvent, InputState};
+use perf::{FrameSkipper, PerfStats, PerfTracker};
+use reload::PendingReload;
+use rustzx_z80::{Z80Bus, Z80};
+use search::{MemorySearch, RamAddress};
+use state::{CpuRegisters, MachineState};
+use trap::{TrapAction, TrapCallback, TrapMemory};
 
-#[cfg(feature = "sound")]
-use crate::zx::sound::sample::SoundSample;
 #[cfg(feature = "autoload")]
-use crate::{host::BufferCursor, zx::machine::ZXMachine};
+use crate::host::BufferCursor;
+#[cfg(feature = "sound")]
+use crate::zx::sound::{
+    filter::{SoundFilterState, SoundFilters},
+    sample::SoundSample,
+};
+#[cfg(feature = "betadisk")]
+use crate::zx::{
+    betadisk::{
+        BetaDisk, BetaDiskState, DRIVE_COUNT as BETADISK_DRIVE_COUNT, ROM_SIZE as BETADISK_ROM_SIZE,
+    },
+    fdc::trd::{scl_to_trd, trd_disk, MAX_IMAGE_SIZE as TRD_MAX_IMAGE_SIZE},
+};
+#[cfg(feature = "plusd")]
+use crate::zx::{
+    fdc::disk::{DiskGeometry, TrackOrder},
+    plusd::{PlusD, PlusDState, DRIVE_COUNT as PLUSD_DRIVE_COUNT, ROM_SIZE as PLUSD_ROM_SIZE},
+};
+#[cfg(feature = "ay")]
+use crate::zx::{
+    keypad::{Keypad, KeypadKey},
+    rs232::Rs232Port,
+};
+#[cfg(any(feature = "plusd", feature = "betadisk"))]
+use crate::{
+    diagnostics::DISK,
+    error::DiskLoadError,
+    host::Disk,
+    zx::fdc::disk::{FloppyDisk, MAX_UDI_SIZE},
+};
+#[cfg(feature = "spectranet")]
+use crate::{
+    diagnostics::NETWORK,
+    zx::spectranet::{Spectranet, FLASH_SIZE as SPECTRANET_FLASH_SIZE},
+};
+
+/// [Emulator::boot_to_basic] fails if ROM does not get to the editor in this
+/// count of frames (15 seconds)
+const BOOT_TIMEOUT_FRAMES: u32 = 50 * 15;
 
 /// Represents emulator stop reason
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EmulationStopReason {
     /// Requested frames count have been emulated successfully
     Completed,
@@ -53,46 +126,380 @@ pub struct EmulationInfo {
     pub stop_reason: EmulationStopReason,
 }
 
+/// Output of the single [Emulator::run_frame] call. Sound samples and border
+/// changes belong to exactly the same frame as the screen
+pub struct FrameOutput<'a, F> {
+    /// Frame number, see [Emulator::frame_number]
+    pub frame_number: u64,
+    pub screen: &'a F,
+    #[cfg(feature = "precise-border")]
+    pub border: &'a F,
+    /// Sound samples of the frame. Count is `sample_rate / frames_per_second`
+    /// rounded down or up: the fractional part is carried over to the next
+    /// frames, so counts of each `frames_per_second` frames sum up exactly to
+    /// the sample rate. Empty when sound is disabled
+    #[cfg(feature = "sound")]
+    pub audio: &'a [SoundSample<f32>],
+    /// Border color changes, see [Emulator::border_events]
+    pub border_events: &'a [(usize, ZXColor)],
+    /// Status changes of the storage devices, see [Emulator::storage_events]
+    pub storage_events: &'a [StorageActivity],
+    /// Flash state used to render `screen`, see [Emulator::flash_state]
+    pub flash_state: bool,
+}
+
+/// Video parameters of the emulated machine, see [Emulator::video_params]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VideoParams {
+    /// `(width, height)` of the [Emulator::screen_buffer] in pixels
+    pub screen_dimensions: (usize, usize),
+    /// `(width, height)` of the [Emulator::border_buffer] in pixels
+    #[cfg(feature = "precise-border")]
+    pub border_dimensions: (usize, usize),
+    /// CPU clocks per frame
+    pub clocks_frame: usize,
+    pub frames_per_second: usize,
+}
+
+/// Results of the [Emulator::run_bounded] run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoundedResult {
+    /// Consumed CPU clocks, budget is exceeded by at most one instruction
+    pub tstates: u64,
+    /// Count of executed instructions, each prefix and each `NOP` cycle of
+    /// the halted CPU is counted separately
+    pub instructions: u64,
+    /// Count of frames started during the run
+    pub frames: u64,
+}
+
+/// Results of the [Emulator::benchmark] run
+#[derive(Clone, Copy, Debug)]
+pub struct BenchmarkReport {
+    /// Count of emulated frames
+    pub frames: u64,
+    /// Count of executed CPU instructions
+    pub instructions: u64,
+    /// Real (host) time spent on emulation
+    pub wall_time: Duration,
+    /// Emulated machine time
+    pub emulated_time: Duration,
+}
+
+impl BenchmarkReport {
+    /// Returns count of emulated seconds per one real second, `None` if wall
+    /// time is too short to be measured
+    pub fn speed_factor(&self) -> Option<f64> {
+        if self.wall_time.is_zero() {
+            return None;
+        }
+        Some(self.emulated_time.as_secs_f64() / self.wall_time.as_secs_f64())
+    }
+
+    /// Returns average count of executed instructions per frame, `None` if
+    /// no frames were emulated
+    pub fn instructions_per_frame(&self) -> Option<f64> {
+        if self.frames == 0 {
+            return None;
+        }
+        Some(self.instructions as f64 / self.frames as f64)
+    }
+}
+
 /// Represents main Emulator structure
+///
+/// Emulation is deterministic: emulating N frames from the same state (e.g.
+/// loaded snapshot) with the same input queued via [Emulator::queue_input]
+/// always produces identical memory, screen and sound. Host time only limits
+/// how many frames are emulated per call and never affects emulated state.
+/// Initial state is reproducible too, as RAM is either zeroed or filled from
+/// [RustzxSettings::ram_init_seed], and CPU registers (including `R`) are
+/// zeroed on power on. Input sent directly (e.g. [Emulator::send_key]) is
+/// applied at the arbitrary point of the frame, and devices backed by the host
+/// (io extender, hard disk, network) are outside of this guarantee
 pub struct Emulator<H: Host> {
     settings: RustzxSettings,
     cpu: Z80,
     controller: ZXController<H>,
+    context: H::Context,
     mode: EmulationMode,
+    // Emulation calls skipped since the last emulated frame in slow motion mode
+    slow_motion_skipped: usize,
+    // Frames started since emulator creation or the last snapshot load
+    frame_number: u64,
+    // Scheduled input, ordered by frame number
+    input_queue: VecDeque<(u64, InputEvent)>,
     fast_load: bool,
+    // Binary which will be patched into memory at the next frame start
+    pending_reload: Option<PendingReload>,
+    // Tape autoload in progress, when it is done by typing the command
+    #[cfg(feature = "autoload")]
+    typed_autoload: Option<autoload::TypedAutoload>,
+    idle_detector: Option<IdleDetector>,
+    // Custom traps by instruction address
+    traps: BTreeMap<u16, TrapCallback>,
+    // Candidates of the memory search
+    memory_search: MemorySearch,
+    // Host time since emulator creation, used for frame time statistics
+    perf_clock: H::EmulationStopwatch,
+    perf: PerfTracker,
+    frame_skipper: FrameSkipper,
     #[cfg(feature = "sound")]
     sound_enabled: bool,
+    // Sound samples of the last frame emulated via `run_frame`
+    #[cfg(feature = "sound")]
+    frame_audio: Vec<SoundSample<f32>>,
+    // CPU clocks spent per 256-byte PC range since the last profile reset
+    #[cfg(feature = "pc-profiler")]
+    pc_profile: [u64; 256],
 }
 
 impl<H: Host> Emulator<H> {
-    /// Constructs new emulator
+    /// Constructs new emulator. Fails if specs of the custom machine are invalid
     /// # Arguments
     /// `settings` - emulator settings
     pub fn new(settings: RustzxSettings, context: H::Context) -> Result<Self> {
+        settings.machine.specs().validate()?;
         let mode = settings.emulation_mode;
         let fast_load = settings.tape_fastload_enabled;
         #[cfg(feature = "sound")]
         let sound_enabled = settings.sound_enabled;
 
         let cpu = Z80::default();
-        let controller = ZXController::<H>::new(&settings, context);
+        let controller = ZXController::<H>::new(&settings, &context);
 
         let this = Self {
             settings,
             cpu,
             controller,
+            context,
             mode,
+            slow_motion_skipped: 0,
+            frame_number: 0,
+            input_queue: VecDeque::new(),
             fast_load,
+            pending_reload: None,
+            #[cfg(feature = "autoload")]
+            typed_autoload: None,
+            idle_detector: None,
+            traps: BTreeMap::new(),
+            memory_search: MemorySearch::default(),
+            perf_clock: H::EmulationStopwatch::new(),
+            perf: PerfTracker::default(),
+            frame_skipper: FrameSkipper::default(),
             #[cfg(feature = "sound")]
             sound_enabled,
+            #[cfg(feature = "sound")]
+            frame_audio: Vec::new(),
+            #[cfg(feature = "pc-profiler")]
+            pc_profile: [0; 256],
         };
+        this.report_port_conflicts();
 
         Ok(this)
     }
 
+    /// Replaces host context, e.g. to apply the adjusted palette of the host
+    /// frame buffers. Screen and border frame buffers are re-created with the
+    /// new context, so any references to the previous [Emulator::screen_buffer]
+    /// should be re-obtained. Buffers stay blank until the next completed
+    /// frame, which is fully redrawn and reported as fully dirty (see
+    /// [Emulator::dirty_rect]). Context is kept for [Emulator::switch_machine]
+    pub fn set_context(&mut self, context: H::Context) {
+        let controller = &mut self.controller;
+        controller
+            .screen
+            .recreate_buffers(context.frame_buffer_context());
+        #[cfg(feature = "precise-border")]
+        controller
+            .border
+            .recreate_buffer(context.frame_buffer_context());
+        self.context = context;
+    }
+
+    /// Enables or disables paper patterns at runtime, see
+    /// [RustzxSettings::paper_patterns]. The next rendered frame is fully
+    /// redrawn and reported as fully dirty
+    pub fn set_paper_patterns(&mut self, enabled: bool) {
+        self.settings.paper_patterns = enabled;
+        self.controller.screen.set_paper_patterns(enabled);
+    }
+
+    /// Switches emulated machine model. Memory map, screen, border and sound
+    /// devices are rebuilt for the new machine and CPU is reset. Inserted tape
+    /// and cartridge, connected joystick/mouse, io extender, debug interface,
+    /// port logger and [Emulator::set_isr_border_debug] color are kept.
+    ///
+    /// Default ROM is reloaded if enabled in settings, custom ROM should be
+    /// loaded again by the host. Frame buffers are re-created, therefore any
+    /// references to the previous [Emulator::screen_buffer] should be
+    /// re-obtained. Returns video parameters of the new machine, so the host
+    /// can re-create its textures and adjust frame timing. Fails if specs of
+    /// the custom machine are invalid
+    pub fn switch_machine(&mut self, machine: ZXMachine) -> Result<VideoParams> {
+        machine.specs().validate()?;
+        diag!(
+            debug,
+            MACHINE,
+            "switching machine {:?} -> {:?}",
+            self.settings.machine,
+            machine
+        );
+        self.settings.machine = machine;
+        let mut controller = ZXController::<H>::new(&self.settings, &self.context);
+        let old = &mut self.controller;
+        controller.tape = core::mem::take(&mut old.tape);
+        controller.kempston = old.kempston.take();
+        controller.fuller = old.fuller.take();
+        controller.mouse = old.mouse.take();
+        controller.io_extender = old.io_extender.take();
+        controller.debug_interface = old.debug_interface.take();
+        controller.port_logger = old.port_logger.take();
+        controller.memory.take_dock_from(&mut old.memory);
+        controller.set_isr_border(old.isr_border());
+        #[cfg(feature = "ide")]
+        {
+            controller.ide = old.ide.take();
+        }
+        #[cfg(feature = "spectranet")]
+        {
+            controller.spectranet = old.spectranet.take();
+        }
+        #[cfg(feature = "plusd")]
+        {
+            controller.plusd = old.plusd.take();
+            if let Some(plusd) = &mut controller.plusd {
+                plusd.set_freq_cpu(machine.specs().freq_cpu);
+            }
+        }
+        #[cfg(feature = "betadisk")]
+        {
+            controller.betadisk = old.betadisk.take();
+            if let Some(betadisk) = &mut controller.betadisk {
+                betadisk.set_freq_cpu(machine.specs().freq_cpu);
+            }
+        }
+        #[cfg(feature = "ay")]
+        {
+            controller.ay_port_a = core::mem::take(&mut old.ay_port_a);
+            if let Some(rs232) = &mut controller.ay_port_a.rs232 {
+                rs232.set_freq_cpu(machine.specs().freq_cpu);
+            }
+        }
+        #[cfg(feature = "sound")]
+        {
+            controller.mixer.copy_polarity(&old.mixer);
+            controller.mixer.copy_filters(&old.mixer);
+        }
+        self.controller = controller;
+        self.cpu.reset();
+        Ok(self.video_params())
+    }
+
+    /// Returns frame buffer dimensions and frame timing of the emulated machine
+    pub fn video_params(&self) -> VideoParams {
+        let specs = self.settings.machine.specs();
+        VideoParams {
+            screen_dimensions: self.screen_buffer_dimensions(),
+            #[cfg(feature = "precise-border")]
+            border_dimensions: self.border_buffer_dimensions(),
+            clocks_frame: specs.clocks_frame,
+            frames_per_second: specs.frames_per_second,
+        }
+    }
+
+    /// Applies [RustzxSettings::machine_policy] before loading media which
+    /// requires `required` memory model. Switching is allowed only when the
+    /// loaded media replaces the machine state anyway (`can_switch`),
+    /// otherwise the mismatch is just reported
+    fn apply_machine_policy(&mut self, required: MemoryModel, can_switch: bool) -> Result<()> {
+        if self.settings.machine.memory_model().runs(required) {
+            return Ok(());
+        }
+        match self.settings.machine_policy {
+            MachinePolicy::AutoSwitch if can_switch => self
+                .switch_machine(ZXMachine::with_memory_model(required))
+                .map(|_| ()),
+            MachinePolicy::AutoSwitch | MachinePolicy::WarnOnly => {
+                diag!(
+                    warn,
+                    MACHINE,
+                    "media requires {:?} memory model, loading into {:?}",
+                    required,
+                    self.settings.machine
+                );
+                Ok(())
+            }
+            MachinePolicy::Strict => Err(match required {
+                MemoryModel::Sinclair48K => MachineMismatchError::Requires48K,
+                MemoryModel::Sinclair128K => MachineMismatchError::Requires128K,
+                MemoryModel::SinclairPlus3 => MachineMismatchError::RequiresPlus3,
+            }
+            .into()),
+        }
+    }
+
+    /// Returns pairs of enabled peripherals which decode the same I/O ports.
+    /// Both peripherals receive accesses to such ports, values read from
+    /// them are ANDed as on the real data bus
+    pub fn port_conflicts(&self) -> Vec<(Peripheral, Peripheral)> {
+        ports::conflicts(&self.controller.peripherals())
+    }
+
+    fn report_port_conflicts(&self) {
+        for (a, b) in self.port_conflicts() {
+            diag!(warn, MACHINE, "{:?} and {:?} decode the same ports", a, b);
+        }
+    }
+
+    /// Enables detection of the idle program with the given thresholds, or
+    /// disables it with `None`. Changes of the idle state are reported to
+    /// the debug interface, see [idle]
+    pub fn set_idle_detection(&mut self, config: Option<IdleConfig>) {
+        self.idle_detector = config.map(IdleDetector::new);
+    }
+
+    /// Returns true if the program is idle, false if idle detection is disabled
+    pub fn is_idle(&self) -> bool {
+        self.idle_detector
+            .as_ref()
+            .is_some_and(IdleDetector::is_idle)
+    }
+
+    /// Returns currently emulated machine
+    pub fn machine(&self) -> ZXMachine {
+        self.settings.machine
+    }
+
+    /// Returns true if AY ports are decoded: the machine has built-in AY chip
+    /// (see [ZXMachine::has_ay]), or it is attached to 48K machine via
+    /// `ay_enabled` of [RustzxSettings]. OUTs to AY ports of the machine
+    /// without AY are ignored and reads return the floating bus value
+    pub fn has_ay(&self) -> bool {
+        self.controller.has_ay()
+    }
+
+    /// Returns current ULA delay in clocks, see [Emulator::set_contention_offset]
+    pub fn contention_offset(&self) -> i32 {
+        self.controller.contention_offset()
+    }
+
+    /// Shifts memory contention window and screen memory reads by `tstates`
+    /// relative to the machine's first pixel timing. Each machine revision
+    /// has its own preset (e.g. -1 for early 48K ULA, see
+    /// [crate::zx::machine::Timings]), this method overrides it for
+    /// experimenting with timings of a specific hardware. Preset is restored
+    /// on [Emulator::switch_machine]. Returns error and keeps the current
+    /// delay if the shifted timings do not fit into the frame
+    pub fn set_contention_offset(&mut self, tstates: i32) -> Result<()> {
+        self.controller.set_contention_offset(tstates)?;
+        Ok(())
+    }
+
     /// changes emulation speed
     pub fn set_speed(&mut self, new_speed: EmulationMode) {
         self.mode = new_speed;
+        self.slow_motion_skipped = 0;
     }
 
     /// changes fast loading flag
@@ -106,6 +513,48 @@ impl<H: Host> Emulator<H> {
         self.sound_enabled = value;
     }
 
+    /// Inverts beeper output when `invert` is true, e.g. to match recordings
+    /// from hardware with inverted beeper signal. Not inverted by default
+    #[cfg(feature = "sound")]
+    pub fn set_beeper_polarity(&mut self, invert: bool) {
+        self.controller.mixer.set_beeper_polarity(invert);
+    }
+
+    /// Inverts AY output when `invert` is true. Not inverted by default
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub fn set_ay_polarity(&mut self, invert: bool) {
+        self.controller.mixer.set_ay_polarity(invert);
+    }
+
+    /// Changes output sound filters, initial ones are set by
+    /// [RustzxSettings::sound_filters]. Filter history is kept, so the
+    /// change does not click
+    #[cfg(feature = "sound")]
+    pub fn set_sound_filters(&mut self, filters: SoundFilters) {
+        self.controller.mixer.set_filters(filters);
+    }
+
+    #[cfg(feature = "sound")]
+    pub fn sound_filters(&self) -> SoundFilters {
+        self.controller.mixer.filters()
+    }
+
+    /// Returns history of the output sound filters. Snapshot formats have no
+    /// place for it, so hosts which keep in-memory save states (e.g. for
+    /// rewind) should store it alongside the snapshot and restore it with
+    /// [Emulator::set_sound_filter_state], otherwise filters start from the
+    /// state left by the previously played sound and the restore may click.
+    /// Filter state is kept on snapshot load and machine switch
+    #[cfg(feature = "sound")]
+    pub fn sound_filter_state(&self) -> SoundFilterState {
+        self.controller.mixer.filter_state()
+    }
+
+    #[cfg(feature = "sound")]
+    pub fn set_sound_filter_state(&mut self, state: SoundFilterState) {
+        self.controller.mixer.set_filter_state(state);
+    }
+
     /// function for sound generation request check
     #[cfg(feature = "sound")]
     pub fn have_sound(&self) -> bool {
@@ -117,7 +566,19 @@ impl<H: Host> Emulator<H> {
         }
     }
 
-    pub fn load_snapshot(&mut self, snapshot: Snapshot<impl SnapshotAsset>) -> Result<()> {
+    /// Loads snapshot. When the snapshot was saved on a machine with other
+    /// memory model, [RustzxSettings::machine_policy] is applied first
+    pub fn load_snapshot(&mut self, mut snapshot: Snapshot<impl SnapshotAsset>) -> Result<()> {
+        let required = match &mut snapshot {
+            Snapshot::Sna(asset) => snapshot::sna::required_memory_model(asset)?,
+        };
+        self.apply_machine_policy(required, true)?;
+        self.frame_number = 0;
+        self.controller.leave_all_isrs();
+        #[cfg(feature = "autoload")]
+        {
+            self.typed_autoload = None;
+        }
         match snapshot {
             Snapshot::Sna(asset) => snapshot::sna::load(self, asset),
         }
@@ -132,44 +593,212 @@ impl<H: Host> Emulator<H> {
         }
     }
 
-    pub fn load_tape(&mut self, tape: Tape<H::TapeAsset>) -> Result<()> {
-        match tape {
-            Tape::Tap(asset) => {
-                self.controller.tape = Tap::from_asset(asset)?.into();
-            }
+    /// Writes BASIC program currently in memory (with its variables) as a
+    /// TAP file, without running `SAVE`. `name` is the tape file name of up
+    /// to 10 printable ASCII characters, padded with spaces in the header.
+    /// Program extent is taken from PROG, VARS and E LINE system variables,
+    /// so BASIC should be initialized by the ROM
+    pub fn export_basic_tap<R>(&self, recorder: R, name: &str) -> Result<()>
+    where
+        R: DataRecorder,
+    {
+        basic::export_tap(self, recorder, name)
+    }
+
+    /// Inserts new tape, replacing the current one. New tape is rewound to the
+    /// beginning and stopped. Can be called on the running emulator (e.g. for
+    /// multi-load games) - when autoload is disabled, machine state is kept intact.
+    /// When the tape content requires other machine (e.g. 128K BASIC loader),
+    /// [RustzxSettings::machine_policy] is applied, machine is switched only
+    /// if the tape is autoloaded. Asset is converted to [Host::TapeAsset],
+    /// which allows to load tapes held in memory (see [Tape::tap_from_bytes])
+    /// when the host asset type supports it
+    pub fn load_tape<A>(&mut self, tape: Tape<A>) -> Result<()>
+    where
+        A: LoadableAsset + Into<H::TapeAsset>,
+    {
+        let tape: ZXTape<H::TapeAsset> = match tape {
+            Tape::Tap(asset) => Tap::from_asset(asset.into())?.into(),
+        };
+
+        #[cfg(feature = "autoload")]
+        let resets_machine = self.settings.autoload_enabled;
+        #[cfg(not(feature = "autoload"))]
+        let resets_machine = false;
+        if let Some(required) = tape.machine_hint() {
+            self.apply_machine_policy(required, resets_machine)?;
         }
+        self.controller.tape = tape;
 
         #[cfg(feature = "autoload")]
         if self.settings.autoload_enabled {
-            let snapshot = match self.settings.machine {
-                ZXMachine::Sinclair48K => &snapshot::autoload::tape::SNAPSHOT_SNA_48K,
-                ZXMachine::Sinclair128K => &snapshot::autoload::tape::SNAPSHOT_SNA_128K,
-            };
+            self.autoload_tape()?;
+        }
+
+        Ok(())
+    }
 
-            self.load_snapshot(Snapshot::Sna(BufferCursor::new(snapshot)))?;
+    /// Starts tape loading. Built-in snapshot with `LOAD ""` already typed (or
+    /// "Tape Loader" selected on 128K) is used when the ROM matches the one
+    /// of the snapshot, otherwise machine is reset and the loading command is
+    /// typed when the ROM gets ready
+    #[cfg(feature = "autoload")]
+    fn autoload_tape(&mut self) -> Result<()> {
+        use crate::{diagnostics::TAPE, zx::rom_info::KnownRom};
+
+        let memory_model = self.settings.machine.memory_model();
+        let (snapshot, snapshot_roms): (&[u8], &[KnownRom]) = match memory_model {
+            MemoryModel::Sinclair48K => (
+                snapshot::autoload::tape::SNAPSHOT_SNA_48K,
+                &[KnownRom::Sinclair48K],
+            ),
+            MemoryModel::Sinclair128K => (
+                snapshot::autoload::tape::SNAPSHOT_SNA_128K,
+                &[KnownRom::Sinclair128KEditor, KnownRom::Sinclair128KBasic],
+            ),
+            // No built-in +3 snapshot, LOAD is always typed
+            MemoryModel::SinclairPlus3 => (&[], &[]),
+        };
+        let snapshot_rom_loaded = !snapshot.is_empty()
+            && self
+                .rom_info()
+                .pages()
+                .iter()
+                .map(|page| page.rom)
+                .eq(snapshot_roms.iter().copied().map(Some));
+        if snapshot_rom_loaded {
+            return self.load_snapshot(Snapshot::Sna(BufferCursor::new(snapshot)));
         }
 
+        diag!(
+            debug,
+            TAPE,
+            "ROM differs from autoload snapshot, typing LOAD"
+        );
+        self.reset_machine();
+        self.typed_autoload = Some(autoload::TypedAutoload::new(memory_model));
         Ok(())
     }
 
+    /// Resets CPU and machine devices, as on the reset button press, see
+    /// [ZXController::reset]
+    fn reset_machine(&mut self) {
+        self.cpu.reset();
+        self.controller.reset();
+        // Frame cut short by the reset is counted right away
+        if self
+            .controller
+            .take_events()
+            .contains(EmulationEvents::FRAME_STARTED)
+        {
+            self.frame_number += 1;
+        }
+    }
+
+    /// Resets the machine and runs it until ROM gets to the editor waiting for
+    /// a key (copyright message on 48K, menu on 128K). Returns count of
+    /// emulated frames. Boot does not depend on the host timing or on the
+    /// previous machine state, so the resulting screen is the same for each
+    /// call, which makes it a reproducible starting point for screenshots.
+    /// Fails if the editor address of the loaded ROM is unknown
+    pub fn boot_to_basic(&mut self) -> Result<u32> {
+        if self.rom_info().traps(0).key_wait.is_none() {
+            return Err(BootError::UnknownRom.into());
+        }
+        #[cfg(feature = "autoload")]
+        {
+            self.typed_autoload = None;
+        }
+        self.reset_machine();
+        let start_frame = self.frame_number;
+        loop {
+            let frames = (self.frame_number - start_frame) as u32;
+            if self.controller.active_rom_traps().key_wait == Some(self.cpu.regs.get_pc()) {
+                return Ok(frames);
+            }
+            if frames > BOOT_TIMEOUT_FRAMES {
+                return Err(BootError::Timeout.into());
+            }
+            self.emulate_instruction()?;
+        }
+    }
+
     fn load_rom_binary_16k_pages(&mut self, mut rom: impl RomSet) -> Result<()> {
         let page_count = self.settings.machine.specs().rom_pages;
 
         for page_index in 0..page_count {
-            let mut page_asset = rom.next_asset().ok_or(RomLoadError::MoreAssetsRequired)?;
+            let mut page_asset = match rom.next_asset() {
+                Some(asset) => asset,
+                None => {
+                    diag!(
+                        warn,
+                        ROM,
+                        "ROM set provides {} of {} pages",
+                        page_index,
+                        page_count
+                    );
+                    return Err(RomLoadError::MoreAssetsRequired.into());
+                }
+            };
             let page_buffer = self.controller.memory.rom_page_data_mut(page_index);
-            page_asset.read_exact(page_buffer)?;
+            if let Err(e) = page_asset.read_exact(page_buffer) {
+                diag!(
+                    warn,
+                    ROM,
+                    "failed to read ROM page {} at offset {}: {:?}",
+                    page_index,
+                    page_index as usize * 0x4000,
+                    e
+                );
+                return Err(e.into());
+            }
         }
+        diag!(debug, ROM, "loaded {} ROM pages", page_count);
 
         Ok(())
     }
 
     pub fn load_rom(&mut self, rom: impl RomSet) -> Result<()> {
         match rom.format() {
-            RomFormat::Binary16KPages => self.load_rom_binary_16k_pages(rom),
+            RomFormat::Binary16KPages => self.load_rom_binary_16k_pages(rom)?,
+        }
+        self.identify_rom();
+        Ok(())
+    }
+
+    fn identify_rom(&mut self) {
+        let fast_load = self.fast_load;
+        let info = self.controller.identify_rom();
+        for (index, page) in info.pages().iter().enumerate() {
+            let name = page.rom.map(|rom| rom.name()).unwrap_or("unknown");
+            diag!(
+                debug,
+                ROM,
+                "ROM page {}: {}, CRC32 {:08X}",
+                index,
+                name,
+                page.crc32
+            );
+        }
+        let basic_page_known = info
+            .pages()
+            .get(info.basic_page() as usize)
+            .is_some_and(|page| page.rom.is_some());
+        if fast_load && !basic_page_known {
+            diag!(
+                warn,
+                ROM,
+                "tape fast load is enabled with unknown ROM, assuming 48K ROM trap addresses"
+            );
         }
     }
 
+    /// Returns identification of the loaded ROM pages. Tape fast load trap
+    /// addresses are selected based on it
+    pub fn rom_info(&self) -> &RomInfo {
+        &self.controller.rom_info
+    }
+
     pub fn load_screen(&mut self, screen: Screen<impl ScreenAsset>) -> Result<()> {
         match screen {
             Screen::Scr(asset) => screenshot::scr::load(self, asset)?,
@@ -178,6 +807,44 @@ impl<H: Host> Emulator<H> {
         Ok(())
     }
 
+    /// Inserts Timex cartridge and restarts the machine from it. Timex
+    /// memory management unit is not emulated, so instead of being selected
+    /// via 0xF4 port, present chunks of the cartridge DOCK bank are mapped
+    /// over the address space as long as the cartridge is inserted. This is
+    /// enough to run cartridges which contain the whole program in ROM
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge<impl LoadableAsset>) -> Result<()> {
+        let Cartridge::Dck(mut asset) = cartridge;
+        // Extra byte detects images larger than three full banks
+        let image = read_asset(&mut asset, MAX_DCK_SIZE + 1)?;
+        let mut banks = dck::parse(&image)?;
+        if banks.iter().any(|bank| bank.id != BankId::Dock) {
+            return Err(CartridgeLoadError::UnsupportedBank.into());
+        }
+        let bank = banks.remove(0);
+        let memory = &mut self.controller.memory;
+        memory.insert_dock(bank.chunks, bank.data);
+        memory.set_dock_mask(0xFF);
+        diag!(
+            debug,
+            MEMORY,
+            "cartridge inserted, chunks mask {:02X}",
+            memory.dock_mask()
+        );
+        #[cfg(feature = "autoload")]
+        {
+            self.typed_autoload = None;
+        }
+        self.reset_machine();
+        Ok(())
+    }
+
+    /// Removes Timex cartridge, returns false if there was none. Machine is
+    /// not restarted, as on the real hardware the program which was running
+    /// from the cartridge is likely to crash
+    pub fn eject_cartridge(&mut self) -> bool {
+        self.controller.memory.remove_dock()
+    }
+
     pub fn play_tape(&mut self) {
         self.controller.tape.play();
     }
@@ -186,6 +853,37 @@ impl<H: Host> Emulator<H> {
         self.controller.tape.stop();
     }
 
+    /// Prepares emulator to be dropped when the host exits: stops the tape
+    /// and flushes attached storage devices. Outputs buffered by the
+    /// emulator ([Emulator::take_rs232_output] and others) should be taken
+    /// by the host after the call. Emulation can be continued after it
+    pub fn shutdown(&mut self) -> Result<()> {
+        self.stop_tape();
+        #[cfg(feature = "ide")]
+        if let Some(ide) = self.controller.ide.as_mut() {
+            ide.flush().map_err(crate::error::Error::DeviceFlush)?;
+        }
+        Ok(())
+    }
+
+    /// Returns read position in the blocks of the inserted tape image, `None`
+    /// if there is no tape or it has no blocks. Position is measured in image
+    /// bytes rather than playback time, see [Emulator::tape_time] for the
+    /// latter
+    pub fn tape_progress(&self) -> Option<TapeProgress> {
+        self.controller.tape.progress()
+    }
+
+    /// Returns elapsed and total time of the inserted tape in seconds, like
+    /// a counter of the real tape deck. Returns `(0.0, 0.0)` if there is no
+    /// tape. Tape timings are counted in CPU clocks, so time depends on the
+    /// CPU frequency of the emulated machine
+    pub fn tape_time(&self) -> (f32, f32) {
+        let (elapsed, total) = self.controller.tape.time_clocks();
+        let freq = self.settings.machine.specs().freq_cpu as f64;
+        ((elapsed as f64 / freq) as f32, (total as f64 / freq) as f32)
+    }
+
     /// Rewinds tape. May return error if underlying tape asset failed to
     /// perform seek operation to go back to the the beginning of the tape
     pub fn rewind_tape(&mut self) -> Result<()> {
@@ -196,11 +894,53 @@ impl<H: Host> Emulator<H> {
         self.controller.screen.frame_buffer()
     }
 
+    /// Returns `(width, height)` of the [Emulator::screen_buffer] in pixels,
+    /// swapped when [RustzxSettings::orientation] rotates the image by 90 or
+    /// 270 degrees
+    pub fn screen_buffer_dimensions(&self) -> (usize, usize) {
+        let screen = &self.controller.screen;
+        (screen.width(), screen.height())
+    }
+
+    /// Returns bounding rect `(x, y, width, height)` of the [Emulator::screen_buffer]
+    /// pixels changed during the last completed frame, or `None` if the frame is
+    /// the same as the previous one. Flash attribute toggling is taken into
+    /// account. Frontends can use it to upload only the changed part of the
+    /// screen texture. Rect is in the frame buffer coordinates, i.e. rotated
+    /// along with the image
+    pub fn dirty_rect(&self) -> Option<(usize, usize, usize, usize)> {
+        self.controller.screen.dirty_rect()
+    }
+
+    /// Returns text representation of the screen (without border) for
+    /// terminal debugging and diffable test output: 64x48 characters, each
+    /// one is chosen by the average brightness of 4x4 pixels. It is built
+    /// from the screen memory directly, regardless of the host frame buffer
+    pub fn to_ascii(&self) -> String {
+        self.controller.screen.to_ascii()
+    }
+
     #[cfg(feature = "precise-border")]
     pub fn border_buffer(&self) -> &H::FrameBuffer {
         self.controller.border.frame_buffer()
     }
 
+    /// Returns `(width, height)` of the [Emulator::border_buffer] in pixels,
+    /// see [Emulator::screen_buffer_dimensions]
+    #[cfg(feature = "precise-border")]
+    pub fn border_buffer_dimensions(&self) -> (usize, usize) {
+        let (width, height) = crate::host::FrameBufferSource::Border.dimensions();
+        self.settings.orientation.dimensions(width, height)
+    }
+
+    /// Enables or disables rendering of [Emulator::border_buffer]. Frontends
+    /// which draw the border from [Emulator::border_events] can disable it to
+    /// save time spent on border pixels
+    #[cfg(feature = "precise-border")]
+    pub fn set_border_rendering(&mut self, value: bool) {
+        self.controller.border.set_rendering_enabled(value);
+    }
+
     pub fn set_io_extender(&mut self, extender: H::IoExtender) {
         self.controller.io_extender = Some(extender);
     }
@@ -209,25 +949,711 @@ impl<H: Host> Emulator<H> {
         self.controller.io_extender.as_mut()
     }
 
+    /// Attaches hard disk to the IDE interface, replacing previous one
+    #[cfg(feature = "ide")]
+    pub fn attach_hard_disk(&mut self, device: H::BlockDevice) {
+        self.controller.ide = Some(crate::zx::ide::IdeInterface::new(device));
+        self.report_port_conflicts();
+    }
+
+    /// Detaches hard disk from the IDE interface and returns it
+    #[cfg(feature = "ide")]
+    pub fn detach_hard_disk(&mut self) -> Option<H::BlockDevice> {
+        self.controller.ide.take().map(|ide| ide.into_device())
+    }
+
+    /// Attaches Spectranet interface with the given flash ROM image (up to
+    /// 128K) and host network stack, replacing previous one
+    #[cfg(feature = "spectranet")]
+    pub fn attach_spectranet(
+        &mut self,
+        mut rom: impl LoadableAsset,
+        network: H::NetworkStack,
+    ) -> Result<()> {
+        let data = read_asset(&mut rom, SPECTRANET_FLASH_SIZE)?;
+        diag!(
+            debug,
+            NETWORK,
+            "loaded {} bytes of Spectranet flash",
+            data.len()
+        );
+        self.controller.spectranet = Some(Spectranet::new(&data, network));
+        self.report_port_conflicts();
+        Ok(())
+    }
+
+    /// Detaches Spectranet interface and returns its network stack
+    #[cfg(feature = "spectranet")]
+    pub fn detach_spectranet(&mut self) -> Option<H::NetworkStack> {
+        self.controller
+            .spectranet
+            .take()
+            .map(|spectranet| spectranet.into_network())
+    }
+
+    /// Attaches +D disk and printer interface with the given ROM image (up
+    /// to 8K, e.g. G+DOS), replacing previous one. Drives are empty
+    #[cfg(feature = "plusd")]
+    pub fn attach_plusd(&mut self, mut rom: impl LoadableAsset) -> Result<()> {
+        let data = read_asset(&mut rom, PLUSD_ROM_SIZE)?;
+        diag!(debug, DISK, "loaded {} bytes of +D ROM", data.len());
+        let freq_cpu = self.settings.machine.specs().freq_cpu;
+        self.controller.plusd = Some(PlusD::new(&data, freq_cpu));
+        self.report_port_conflicts();
+        Ok(())
+    }
+
+    /// Detaches +D interface, returns false if it was not attached
+    #[cfg(feature = "plusd")]
+    pub fn detach_plusd(&mut self) -> bool {
+        self.controller.plusd.take().is_some()
+    }
+
+    /// Inserts disk into the +D drive (0 or 1), replacing previous one
+    #[cfg(feature = "plusd")]
+    pub fn insert_plusd_disk(
+        &mut self,
+        drive: usize,
+        disk: Disk<impl LoadableAsset>,
+    ) -> Result<()> {
+        if drive >= PLUSD_DRIVE_COUNT {
+            return Err(DiskLoadError::InvalidDrive.into());
+        }
+        let plusd = self
+            .controller
+            .plusd
+            .as_mut()
+            .ok_or(DiskLoadError::NoDiskInterface)?;
+        let geometry = DiskGeometry::PLUSD;
+        // Extra byte detects images larger than expected
+        let disk = match disk {
+            Disk::Mgt(mut asset) => {
+                let data = read_asset(&mut asset, geometry.image_size() + 1)?;
+                FloppyDisk::new(geometry, TrackOrder::SidesInterleaved, data)?
+            }
+            Disk::Img(mut asset) => {
+                let data = read_asset(&mut asset, geometry.image_size() + 1)?;
+                FloppyDisk::new(geometry, TrackOrder::SidesSequential, data)?
+            }
+            Disk::Udi(asset) => read_udi(asset)?,
+            Disk::Trd(_) | Disk::Scl(_) => return Err(DiskLoadError::UnsupportedFormat.into()),
+        };
+        diag!(debug, DISK, "disk inserted into +D drive {}", drive + 1);
+        plusd.insert_disk(drive, disk);
+        Ok(())
+    }
+
+    /// Ejects disk from the +D drive and returns its image in the format
+    /// it was inserted with, including changes made by the emulated software
+    #[cfg(feature = "plusd")]
+    pub fn eject_plusd_disk(&mut self, drive: usize) -> Option<Vec<u8>> {
+        self.controller
+            .plusd
+            .as_mut()
+            .and_then(|plusd| plusd.eject_disk(drive))
+            .map(FloppyDisk::into_data)
+    }
+
+    /// Sets write protection of the disk in the +D drive, returns false if
+    /// the drive is empty
+    #[cfg(feature = "plusd")]
+    pub fn set_plusd_disk_write_protected(&mut self, drive: usize, protected: bool) -> bool {
+        let disk = self
+            .controller
+            .plusd
+            .as_mut()
+            .and_then(|plusd| plusd.disk_mut(drive));
+        match disk {
+            Some(disk) => {
+                disk.set_write_protected(protected);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns +D interface state: paging, RAM, WD1772 registers with the
+    /// command and data transfer in progress, motor and head positions, and
+    /// the disks in the drives. Snapshot formats have no place for it, so
+    /// hosts which keep save states should store it alongside the snapshot
+    /// (see [PlusDState::save]) and restore it with
+    /// [Emulator::set_plusd_state]. Returns `None` if +D is not attached
+    #[cfg(feature = "plusd")]
+    pub fn plusd_state(&self) -> Option<PlusDState> {
+        self.controller.plusd.as_ref().map(PlusD::state)
+    }
+
+    /// Restores +D interface state. Disks are not part of the state: each
+    /// drive should contain the disk inserted from the same image as when
+    /// the state was saved (or be empty), changes made to the disks since
+    /// then are replaced with the ones from the state. On mismatch
+    /// [DiskLoadError::DiskMismatch] is returned and +D is left unchanged
+    #[cfg(feature = "plusd")]
+    pub fn set_plusd_state(&mut self, state: &PlusDState) -> Result<()> {
+        let plusd = self
+            .controller
+            .plusd
+            .as_mut()
+            .ok_or(DiskLoadError::NoDiskInterface)?;
+        plusd.restore(state)?;
+        Ok(())
+    }
+
+    /// Presses the +D snapshot button, which pages +D in via NMI
+    #[cfg(feature = "plusd")]
+    pub fn press_plusd_snapshot_button(&mut self) {
+        if let Some(plusd) = &mut self.controller.plusd {
+            plusd.press_snapshot_button();
+        }
+    }
+
+    /// Returns bytes sent to the +D printer port since the last call
+    #[cfg(feature = "plusd")]
+    pub fn take_plusd_printer_output(&mut self) -> Vec<u8> {
+        self.controller
+            .plusd
+            .as_mut()
+            .map(PlusD::take_printer_output)
+            .unwrap_or_default()
+    }
+
+    /// Returns bytes printed via the built-in printer port of +2A/+3 since
+    /// the last call, empty for other machines
+    pub fn take_printer_output(&mut self) -> Vec<u8> {
+        self.controller
+            .printer
+            .as_mut()
+            .map(CentronicsPort::take_output)
+            .unwrap_or_default()
+    }
+
+    /// Attaches Beta Disk interface with the given TR-DOS ROM image (up to
+    /// 16K), replacing previous one. Drives are empty
+    #[cfg(feature = "betadisk")]
+    pub fn attach_betadisk(&mut self, mut rom: impl LoadableAsset) -> Result<()> {
+        let data = read_asset(&mut rom, BETADISK_ROM_SIZE)?;
+        diag!(debug, DISK, "loaded {} bytes of TR-DOS ROM", data.len());
+        let freq_cpu = self.settings.machine.specs().freq_cpu;
+        self.controller.betadisk = Some(BetaDisk::new(&data, freq_cpu));
+        Ok(())
+    }
+
+    /// Detaches Beta Disk interface, returns false if it was not attached
+    #[cfg(feature = "betadisk")]
+    pub fn detach_betadisk(&mut self) -> bool {
+        self.controller.betadisk.take().is_some()
+    }
+
+    /// Inserts TRD, SCL or UDI disk into the Beta Disk drive (0 to 3),
+    /// replacing previous one. SCL files are written to the empty 80-track
+    /// disk
+    #[cfg(feature = "betadisk")]
+    pub fn insert_betadisk_disk(
+        &mut self,
+        drive: usize,
+        disk: Disk<impl LoadableAsset>,
+    ) -> Result<()> {
+        if drive >= BETADISK_DRIVE_COUNT {
+            return Err(DiskLoadError::InvalidDrive.into());
+        }
+        let betadisk = self
+            .controller
+            .betadisk
+            .as_mut()
+            .ok_or(DiskLoadError::NoDiskInterface)?;
+        let disk = match disk {
+            // Extra byte detects images larger than expected
+            Disk::Trd(mut asset) => trd_disk(read_asset(&mut asset, TRD_MAX_IMAGE_SIZE + 1)?)?,
+            Disk::Scl(mut asset) => {
+                trd_disk(scl_to_trd(&read_asset(&mut asset, TRD_MAX_IMAGE_SIZE)?)?)?
+            }
+            Disk::Udi(asset) => read_udi(asset)?,
+            Disk::Mgt(_) | Disk::Img(_) => return Err(DiskLoadError::UnsupportedFormat.into()),
+        };
+        diag!(
+            debug,
+            DISK,
+            "disk inserted into Beta Disk drive {}",
+            drive + 1
+        );
+        betadisk.insert_disk(drive, disk);
+        Ok(())
+    }
+
+    /// Ejects disk from the Beta Disk drive and returns its TRD image,
+    /// including changes made by the emulated software
+    #[cfg(feature = "betadisk")]
+    pub fn eject_betadisk_disk(&mut self, drive: usize) -> Option<Vec<u8>> {
+        self.controller
+            .betadisk
+            .as_mut()
+            .and_then(|betadisk| betadisk.eject_disk(drive))
+            .map(FloppyDisk::into_data)
+    }
+
+    /// Sets write protection of the disk in the Beta Disk drive, returns
+    /// false if the drive is empty
+    #[cfg(feature = "betadisk")]
+    pub fn set_betadisk_disk_write_protected(&mut self, drive: usize, protected: bool) -> bool {
+        let disk = self
+            .controller
+            .betadisk
+            .as_mut()
+            .and_then(|betadisk| betadisk.disk_mut(drive));
+        match disk {
+            Some(disk) => {
+                disk.set_write_protected(protected);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns Beta Disk interface state: TR-DOS paging, system register,
+    /// WD1793 registers with the command and data transfer in progress,
+    /// motor and head positions, and the disks in the drives. Snapshot
+    /// formats have no place for it, so hosts which keep save states should
+    /// store it alongside the snapshot (see [BetaDiskState::save]) and
+    /// restore it with [Emulator::set_betadisk_state]. Returns `None` if
+    /// Beta Disk is not attached
+    #[cfg(feature = "betadisk")]
+    pub fn betadisk_state(&self) -> Option<BetaDiskState> {
+        self.controller.betadisk.as_ref().map(BetaDisk::state)
+    }
+
+    /// Restores Beta Disk interface state. Disks are not part of the state:
+    /// each drive should contain the disk inserted from the same image as
+    /// when the state was saved (or be empty). On mismatch
+    /// [DiskLoadError::DiskMismatch] is returned and Beta Disk is left
+    /// unchanged
+    #[cfg(feature = "betadisk")]
+    pub fn set_betadisk_state(&mut self, state: &BetaDiskState) -> Result<()> {
+        let betadisk = self
+            .controller
+            .betadisk
+            .as_mut()
+            .ok_or(DiskLoadError::NoDiskInterface)?;
+        betadisk.restore(state)?;
+        Ok(())
+    }
+
+    /// Attaches serial device with the given baud rate to the RS232 port of
+    /// 128K models (e.g. a serial printer for `LLIST` in 128 BASIC),
+    /// replacing previous one. Baud rate should match the one set via
+    /// `FORMAT "p";baud`, see [crate::zx::rs232::DEFAULT_BAUD_RATE]
+    #[cfg(feature = "ay")]
+    pub fn attach_rs232(&mut self, baud_rate: u32) {
+        self.controller.ay_port_a.rs232 = Some(Rs232Port::new(
+            baud_rate,
+            self.settings.machine.specs().freq_cpu,
+        ));
+    }
+
+    /// Detaches RS232 device, returns false if no device was attached
+    #[cfg(feature = "ay")]
+    pub fn detach_rs232(&mut self) -> bool {
+        self.controller.ay_port_a.rs232.take().is_some()
+    }
+
+    /// Returns bytes sent by the Spectrum to the attached RS232 device since
+    /// the last call. Bytes are decoded from the serial line, so a byte is
+    /// returned only after its stop bit was sent
+    #[cfg(feature = "ay")]
+    pub fn take_rs232_output(&mut self) -> Vec<u8> {
+        self.controller
+            .ay_port_a
+            .rs232
+            .as_mut()
+            .map(|rs232| rs232.take_output())
+            .unwrap_or_default()
+    }
+
+    /// Queues bytes which are sent by the attached RS232 device to the
+    /// Spectrum when it is ready to receive (CTS line is active)
+    #[cfg(feature = "ay")]
+    pub fn send_rs232_input(&mut self, data: &[u8]) {
+        self.controller.send_rs232(data);
+    }
+
+    /// Returns count of queued RS232 input bytes which were not sent yet
+    #[cfg(feature = "ay")]
+    pub fn rs232_pending_input(&self) -> usize {
+        self.controller
+            .ay_port_a
+            .rs232
+            .as_ref()
+            .map(|rs232| rs232.pending_input())
+            .unwrap_or(0)
+    }
+
+    /// Returns count of bytes from the Spectrum dropped by the RS232 device
+    /// because of the invalid stop bit, which usually means mismatched
+    /// baud rate
+    #[cfg(feature = "ay")]
+    pub fn rs232_framing_errors(&self) -> usize {
+        self.controller
+            .ay_port_a
+            .rs232
+            .as_ref()
+            .map(|rs232| rs232.framing_errors())
+            .unwrap_or(0)
+    }
+
+    /// Attaches numeric keypad to the keypad socket of 128K models. The keypad
+    /// is detected by the 128K ROM in about 3 seconds after attachment and
+    /// then polled every other frame
+    #[cfg(feature = "ay")]
+    pub fn attach_keypad(&mut self) {
+        self.controller.ay_port_a.keypad = Some(Keypad::default());
+    }
+
+    /// Detaches keypad, returns false if keypad was not attached
+    #[cfg(feature = "ay")]
+    pub fn detach_keypad(&mut self) -> bool {
+        self.controller.ay_port_a.keypad.take().is_some()
+    }
+
+    /// Presses key of the attached keypad
+    #[cfg(feature = "ay")]
+    pub fn keypad_key_down(&mut self, key: KeypadKey) {
+        self.send_keypad_key(key, true);
+    }
+
+    /// Releases key of the attached keypad
+    #[cfg(feature = "ay")]
+    pub fn keypad_key_up(&mut self, key: KeypadKey) {
+        self.send_keypad_key(key, false);
+    }
+
+    #[cfg(feature = "ay")]
+    fn send_keypad_key(&mut self, key: KeypadKey, pressed: bool) {
+        if let Some(keypad) = &mut self.controller.ay_port_a.keypad {
+            keypad.send_key(key, pressed);
+        }
+    }
+
+    /// Returns ULAplus palette (GRB 3:3:2 colors), `None` if ULAplus is
+    /// disabled. Palette is used for the screen only while palette mode is
+    /// enabled by the software
+    pub fn ulaplus_palette(&self) -> Option<&[u8; ULAPLUS_PALETTE_SIZE]> {
+        self.controller
+            .screen
+            .ulaplus()
+            .map(|ulaplus| ulaplus.palette())
+    }
+
     /// Sets [Host::DebugInterface] for the emulator instance
     pub fn set_debug_interface(&mut self, debug_interface: H::DebugInterface) {
         self.controller.debug_interface = Some(debug_interface);
     }
 
+    /// Sets callback which receives every IN and OUT performed by the CPU,
+    /// including accesses handled by the io extender, or removes it
+    pub fn set_port_logger(&mut self, logger: Option<PortLogger>) {
+        self.controller.port_logger = logger;
+    }
+
     /// Returns current [Host::DebugInterface] instance
     pub fn debug_interface(&mut self) -> Option<&mut H::DebugInterface> {
         self.controller.debug_interface.as_mut()
     }
 
+    /// Returns per-opcode execution counters of the CPU
+    #[cfg(feature = "opcode-coverage")]
+    pub fn opcode_coverage(&self) -> &crate::OpcodeCoverage {
+        self.cpu.opcode_coverage()
+    }
+
+    /// Resets per-opcode execution counters of the CPU
+    #[cfg(feature = "opcode-coverage")]
+    pub fn reset_opcode_coverage(&mut self) {
+        self.cpu.reset_opcode_coverage();
+    }
+
+    /// Returns CPU clocks spent since the last [Emulator::reset_profile] per
+    /// 256-byte range of the instruction address: bucket `N` covers addresses
+    /// `N * 256..=N * 256 + 255`. Clocks of the whole instruction (including
+    /// contention) go to the bucket of its first byte, interrupt acceptance is
+    /// accounted to the interrupted address.
+    /// Call [Emulator::reset_profile] after each frame to get per-frame profile
+    #[cfg(feature = "pc-profiler")]
+    pub fn profile_buckets(&self) -> [u64; 256] {
+        self.pc_profile
+    }
+
+    /// Clears counters of [Emulator::profile_buckets]
+    #[cfg(feature = "pc-profiler")]
+    pub fn reset_profile(&mut self) {
+        self.pc_profile = [0; 256];
+    }
+
+    /// Shows border in the given color (0-7, as written to port 0xFE) while
+    /// CPU executes an interrupt handler: from the interrupt acceptance to
+    /// the return to the interrupted code. This visualises handler duration
+    /// as a colored band, as demo coders do by changing the border at the
+    /// start and the end of the handler. Border color read by the emulated
+    /// software and saved to snapshots is not affected. `None` disables
+    pub fn set_isr_border_debug(&mut self, color: Option<u8>) {
+        let color = color.map(|color| ZXColor::from_bits(color & 0x07));
+        self.controller.set_isr_border(color);
+    }
+
+    /// Changes state of the INT line driven by external devices. While asserted,
+    /// maskable interrupt is accepted by the CPU on any instruction boundary with
+    /// enabled interrupts, in addition to the ULA frame interrupt
+    pub fn set_int_line(&mut self, active: bool) {
+        self.controller.int_line_external = active;
+    }
+
     /// Reads byte from memory
     pub fn peek(&self, addr: u16) -> u8 {
         self.controller.memory.read(addr)
     }
 
+    /// Returns disassembly and length of the instruction at PC, which will
+    /// be executed next (e.g. `("LD A,(HL)", 1)`). Memory is read through
+    /// the current paging, including paged in interfaces, without contention
+    /// or other side effects. When the emulation stopped in the middle of a
+    /// prefix chain, the already fetched prefix is included in the text, but
+    /// not in the length. Prefix followed by another prefix has no effect and
+    /// is shown as 1-byte `NOP`
+    pub fn current_instruction(&self) -> (String, u16) {
+        let pc = self.cpu.regs.get_pc();
+        let controller = &self.controller;
+        if let Some(prefix) = self.cpu.pending_prefix() {
+            let start = pc.wrapping_sub(1);
+            let (text, len) = disasm::disassemble(start, |addr| {
+                if addr == start {
+                    prefix
+                } else {
+                    controller.peek(addr)
+                }
+            });
+            // Otherwise the fetched prefix is cancelled by the next one
+            if len > 1 {
+                return (text, len - 1);
+            }
+        }
+        disasm::disassemble(pc, |addr| controller.peek(addr))
+    }
+
+    /// Returns RAM bank which is currently displayed. On 128K this is the normal
+    /// screen (bank 5) or the shadow screen (bank 7), selected by bit 3 of port
+    /// 0x7FFD. 48K machines always display bank 0 (RAM at 0x4000)
+    pub fn display_bank(&self) -> u8 {
+        self.controller.display_bank()
+    }
+
+    /// Returns bitmap and attributes (6912 bytes, as in `*.scr` files) of the
+    /// currently displayed screen, regardless of the memory paging
+    pub fn display_file(&self) -> &[u8] {
+        let bank = self.controller.display_bank();
+        &self.controller.memory.ram_page_data(bank)[..=ATTR_MAX_REL as usize]
+    }
+
+    /// Copies bitmap and attributes of the currently displayed screen (~7K),
+    /// see [Emulator::diff_screen_cells]
+    pub fn screen_snapshot(&self) -> ScreenSnapshot {
+        self.controller.screen.snapshot()
+    }
+
+    /// Returns `(row, col)` of the 8x8 character cells (24 rows of 32
+    /// columns) of the displayed screen which changed since the `previous`
+    /// snapshot was taken, e.g. to detect movement in some screen region
+    pub fn diff_screen_cells(&self, previous: &ScreenSnapshot) -> Vec<(usize, usize)> {
+        self.controller.screen.diff_cells(previous)
+    }
+
+    /// Returns contents of the 16K RAM bank regardless of the memory paging,
+    /// `None` if the machine has no such bank. On 48K banks 0, 1 and 2 are
+    /// mapped at 0x4000, 0x8000 and 0xC000
+    pub fn ram_bank(&self, bank: u8) -> Option<&[u8]> {
+        if bank >= self.controller.memory.ram_pages() {
+            return None;
+        }
+        Some(self.controller.memory.ram_page_data(bank))
+    }
+
+    /// Returns current values of the CPU registers
+    pub fn registers(&self) -> CpuRegisters {
+        let regs = &self.cpu.regs;
+        let pair = |high: u8, low: u8| u16::from_le_bytes([low, high]);
+        CpuRegisters {
+            af: regs.get_af(),
+            bc: regs.get_bc(),
+            de: regs.get_de(),
+            hl: regs.get_hl(),
+            af_alt: pair(regs.get_acc_alt(), regs.get_flags_alt()),
+            bc_alt: pair(regs.get_b_alt(), regs.get_c_alt()),
+            de_alt: pair(regs.get_d_alt(), regs.get_e_alt()),
+            hl_alt: pair(regs.get_h_alt(), regs.get_l_alt()),
+            ix: regs.get_ix(),
+            iy: regs.get_iy(),
+            sp: regs.get_sp(),
+            pc: regs.get_pc(),
+            i: regs.get_i(),
+            r: regs.get_r(),
+            iff1: regs.get_iff1(),
+            iff2: regs.get_iff2(),
+            im: self.cpu.get_im().into(),
+        }
+    }
+
+    /// Captures CPU registers and all RAM banks, e.g. to find memory changed
+    /// by some game event via [MachineState::diff]
+    pub fn capture_state(&self) -> MachineState {
+        let memory = &self.controller.memory;
+        MachineState::new(
+            self.controller.machine.memory_model(),
+            self.registers(),
+            (0..memory.ram_pages()).map(|bank| memory.ram_page_data(bank)),
+        )
+    }
+
+    /// Starts memory search (e.g. for the lives counter) with all RAM bytes
+    /// equal to `value`, including banks which are paged out on 128K. Returns
+    /// sorted CPU addresses of the found bytes visible through the current
+    /// paging, all of them are returned by [Emulator::search_candidates]
+    pub fn search_equal(&mut self, value: u8) -> Vec<u16> {
+        self.memory_search.start(&self.controller.memory, value);
+        self.memory_search
+            .visible_addresses(&self.controller.memory)
+    }
+
+    /// Keeps search candidates changed since the previous search step
+    pub fn search_refine_changed(&mut self) -> Vec<u16> {
+        self.refine_search(|previous, current| previous != current)
+    }
+
+    /// Keeps search candidates not changed since the previous search step
+    pub fn search_refine_unchanged(&mut self) -> Vec<u16> {
+        self.refine_search(|previous, current| previous == current)
+    }
+
+    /// Keeps search candidates currently equal to `value`
+    pub fn search_refine_equal(&mut self, value: u8) -> Vec<u16> {
+        self.refine_search(|_, current| current == value)
+    }
+
+    fn refine_search(&mut self, keep: impl Fn(u8, u8) -> bool) -> Vec<u16> {
+        self.memory_search.refine(&self.controller.memory, keep);
+        self.memory_search
+            .visible_addresses(&self.controller.memory)
+    }
+
+    /// Returns all candidates of the memory search regardless of the paging
+    pub fn search_candidates(&self) -> Vec<RamAddress> {
+        self.memory_search.candidates()
+    }
+
+    /// Returns contents of the 16K ROM page, `None` if the machine has no such
+    /// page. 48K BASIC ROM is page 0 on 48K and page 1 on 128K
+    pub fn rom_page(&self, page: u8) -> Option<&[u8]> {
+        self.controller.memory.rom_page_data(page)
+    }
+
     pub fn border_color(&self) -> ZXColor {
         self.controller.border_color
     }
 
+    /// Returns border color changes of the last completed frame as pairs of
+    /// CPU clocks from the frame start and the new color. First entry is
+    /// always the color at the frame start (clock 0), so frontends which
+    /// draw the border themselves can reconstruct the whole frame from it
+    pub fn border_events(&self) -> &[(usize, ZXColor)] {
+        self.controller.border_events()
+    }
+
+    /// Returns status changes of the storage devices (tape, disk drives)
+    /// detected at the end of the last completed frame, at most one per
+    /// device. Frontends can use them to drive activity indicators
+    pub fn storage_events(&self) -> &[StorageActivity] {
+        self.controller.storage.events()
+    }
+
+    /// Returns status of the storage device at the end of the last completed
+    /// frame, `None` if the device is not attached
+    pub fn drive_status(&self, device: StorageDevice) -> Option<DriveStatus> {
+        self.controller.storage.status(device)
+    }
+
+    /// Returns count of frames started since emulator creation or the last
+    /// snapshot load
+    pub fn frame_number(&self) -> u64 {
+        self.frame_number
+    }
+
+    /// Returns true while flashing attributes are rendered with swapped ink
+    /// and paper in the current frame. ULA toggles it each 16 frames, see
+    /// [Emulator::screen_frame_counter]
+    pub fn flash_state(&self) -> bool {
+        self.controller.screen.flash_state()
+    }
+
+    /// Returns count of frames started by the ULA since the machine was
+    /// created (unlike [Emulator::frame_number], it is not reset by snapshot
+    /// load). Flash state toggles when the counter is incremented from a
+    /// multiple of 16, so it can be used to sync external effects with the
+    /// flash cadence
+    pub fn screen_frame_counter(&self) -> u64 {
+        self.controller.screen.frame_counter()
+    }
+
+    /// Schedules input event to be applied right at the start of the given
+    /// frame (see [Emulator::frame_number]), before any instruction of that
+    /// frame is executed. Events for already started frames are applied before
+    /// the next instruction. Events for the same frame are applied in the
+    /// order of queueing
+    pub fn queue_input(&mut self, frame: u64, event: InputEvent) {
+        let index = self.input_queue.partition_point(|(f, _)| *f <= frame);
+        self.input_queue.insert(index, (frame, event));
+    }
+
+    /// Applies input event immediately
+    pub fn send_input(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::Key(key, pressed) => self.send_key(key, pressed),
+            InputEvent::CompoundKey(key, pressed) => self.send_compound_key(key, pressed),
+            InputEvent::Kempston(key, pressed) => self.send_kempston_key(key, pressed),
+            InputEvent::Fuller(key, pressed) => self.send_fuller_key(key, pressed),
+            InputEvent::Sinclair(num, key, pressed) => self.send_sinclair_key(num, key, pressed),
+            InputEvent::MouseButton(button, pressed) => self.send_mouse_button(button, pressed),
+            InputEvent::MouseWheel(dir) => self.send_mouse_wheel(dir),
+            InputEvent::MouseWheelDelta(delta) => self.send_mouse_wheel_delta(delta),
+            InputEvent::MouseMove { x, y } => self.send_mouse_pos_diff(x, y),
+        }
+    }
+
+    fn apply_queued_input(&mut self) {
+        while let Some(&(frame, event)) = self.input_queue.front() {
+            if frame > self.frame_number {
+                break;
+            }
+            self.input_queue.pop_front();
+            self.send_input(event);
+        }
+    }
+
+    /// Places character code to LAST K system variable and marks it as a new
+    /// key in FLAGS, so ROM picks it up on the next key read as if it was
+    /// typed. Unlike [Emulator::send_key] this bypasses the keyboard matrix, so
+    /// it only works for the software which reads keys via ROM routines
+    /// (e.g. BASIC), not for games scanning the matrix directly. The previous
+    /// character is overwritten if it was not consumed yet, see
+    /// [Emulator::is_key_char_pending]
+    pub fn inject_key_char(&mut self, ch: u8) {
+        let memory = &mut self.controller.memory;
+        memory.write(ADDR_SYSVAR_LAST_K, ch);
+        let flags = memory.read(ADDR_SYSVAR_FLAGS);
+        memory.write(ADDR_SYSVAR_FLAGS, flags | FLAGS_NEW_KEY);
+    }
+
+    /// Checks if character injected via [Emulator::inject_key_char] is still
+    /// waiting to be read by ROM
+    pub fn is_key_char_pending(&self) -> bool {
+        self.controller.memory.read(ADDR_SYSVAR_FLAGS) & FLAGS_NEW_KEY != 0
+    }
+
     pub fn send_key(&mut self, key: ZXKey, pressed: bool) {
         self.controller.send_key(key, pressed);
     }
@@ -242,6 +1668,22 @@ impl<H: Host> Emulator<H> {
         }
     }
 
+    pub fn send_fuller_key(&mut self, key: FullerKey, pressed: bool) {
+        if let Some(joy) = &mut self.controller.fuller {
+            joy.key(key, pressed);
+        }
+    }
+
+    /// Sends button of the joystick attached to the given interface. Buttons
+    /// of the interfaces which are not enabled in settings are ignored
+    pub fn send_joy_button(&mut self, interface: JoyInterface, button: JoyButton, pressed: bool) {
+        match interface {
+            JoyInterface::Kempston => self.send_kempston_key(button.into(), pressed),
+            JoyInterface::Fuller => self.send_fuller_key(button.into(), pressed),
+            JoyInterface::Sinclair(num) => self.send_sinclair_key(num, button.into(), pressed),
+        }
+    }
+
     pub fn send_sinclair_key(&mut self, num: SinclairJoyNum, key: SinclairKey, pressed: bool) {
         self.controller.send_sinclair_key(num, key, pressed);
     }
@@ -254,6 +1696,14 @@ impl<H: Host> Emulator<H> {
         self.controller.send_mouse_wheel(dir);
     }
 
+    /// Moves mouse wheel by `delta` steps (positive is up), e.g. for host
+    /// wheels which report several steps at once
+    pub fn send_mouse_wheel_delta(&mut self, delta: i8) {
+        self.controller.send_mouse_wheel_delta(delta);
+    }
+
+    /// Moves mouse by the host distance, which is scaled according to
+    /// [RustzxSettings::mouse_config]. Positive `y` is downwards
     pub fn send_mouse_pos_diff(&mut self, x: i8, y: i8) {
         self.controller.send_mouse_pos_diff(x, y);
     }
@@ -263,6 +1713,16 @@ impl<H: Host> Emulator<H> {
         self.controller.mixer.pop()
     }
 
+    /// Returns true if the sound of the last emulated frame was silent, e.g.
+    /// frontend may skip sending it to the audio device. Constant level
+    /// (DC offset) is considered as silence too. Frames whose samples were
+    /// not generated because previous ones were not taken via
+    /// [Emulator::next_audio_sample] are reported as silent
+    #[cfg(feature = "sound")]
+    pub fn was_silent_last_frame(&self) -> bool {
+        self.controller.mixer.was_silent_last_frame()
+    }
+
     fn process_fast_load_event(&mut self) -> Result<()> {
         if self.controller.tape.can_fast_load() && self.fast_load {
             fastload::tap::fast_load_tap(self)?;
@@ -279,36 +1739,416 @@ impl<H: Host> Emulator<H> {
                 }
             }
         }
+        // Pokes may target screen memory
+        self.controller.refresh_memory_dependent_devices();
+    }
+
+    /// Re-reads development binary and patches it into memory at `addr`
+    /// at the next frame start (a newer reload requested before that replaces
+    /// the pending one). Bytes are written as by the CPU, so screen area is
+    /// updated and ROM is left untouched. With [ReloadMode::Restart] the CPU
+    /// is reset and jumps to `addr` after patching
+    pub fn reload_binary(
+        &mut self,
+        binary: impl LoadableAsset,
+        addr: u16,
+        mode: ReloadMode,
+    ) -> Result<()> {
+        self.pending_reload = Some(PendingReload::read(binary, addr, mode)?);
+        Ok(())
+    }
+
+    /// Returns true if binary reload is waiting for the next frame start
+    pub fn is_reload_pending(&self) -> bool {
+        self.pending_reload.is_some()
+    }
+
+    /// Sets trap, which is called before execution of the instruction at
+    /// `addr` (regardless of the memory paging), replacing previous trap at
+    /// this address. Trap is not called for the instruction prefix chain
+    /// continuation and while CPU is halted. If interrupt is accepted right
+    /// after the trap, the trap is called again on return from the interrupt.
+    ///
+    /// Skipped routine takes time of `RET` instruction. Traps are called in
+    /// all emulation modes, including stepping via debugger
+    pub fn set_trap(&mut self, addr: u16, trap: TrapCallback) {
+        self.traps.insert(addr, trap);
+    }
+
+    /// Removes trap at `addr`, returns false if there was no trap
+    pub fn remove_trap(&mut self, addr: u16) -> bool {
+        self.traps.remove(&addr).is_some()
+    }
+
+    /// Calls trap at the current PC, returns true if the instruction was
+    /// skipped
+    fn process_trap(&mut self) -> bool {
+        let pc = self.cpu.regs.get_pc();
+        if !self.traps.contains_key(&pc) || self.cpu.is_prefix_pending() || self.cpu.is_halted() {
+            return false;
+        }
+        let original = self.registers();
+        let mut registers = original;
+        let mut memory = TrapMemory::new(&mut self.controller);
+        let action = (self.traps.get_mut(&pc).unwrap())(&mut registers, &mut memory);
+        let memory_modified = memory.modified();
+        if registers != original {
+            trap::set_cpu_registers(&mut self.cpu, &registers);
+        }
+        if memory_modified {
+            self.controller.refresh_memory_dependent_devices();
+        }
+        match action {
+            TrapAction::Continue => return false,
+            TrapAction::Skip => {}
+            TrapAction::ReplaceWithValueInA(value) => {
+                self.cpu.regs.set_acc(value);
+            }
+        }
+        self.cpu.pop_pc_from_stack(&mut self.controller);
+        // RET timing, stack reads are not timed by the CPU
+        self.controller.wait_internal(10);
+        true
+    }
+
+    /// Executes single CPU instruction and processes instant events (e.g. fast
+    /// tape loading). Returns events which should be handled by the caller
+    fn emulate_instruction(&mut self) -> Result<EmulationEvents> {
+        self.apply_queued_input();
+        let pc = self.cpu.regs.get_pc();
+        let port_writes = self.controller.port_writes();
+        #[cfg(feature = "pc-profiler")]
+        let start_clocks = self.controller.clocks_since_reset();
+        let skipped = !self.traps.is_empty() && self.process_trap();
+        if !skipped {
+            self.cpu.emulate(&mut self.controller);
+        }
+        if let Some((return_addr, sp)) = self.controller.isr_return() {
+            let regs = &self.cpu.regs;
+            // Return address was popped from the stack
+            if regs.get_pc() == return_addr && (regs.get_sp().wrapping_sub(sp) as i16) > 0 {
+                self.controller.leave_isr();
+            }
+        }
+        #[cfg(feature = "pc-profiler")]
+        {
+            let clocks = self
+                .controller
+                .clocks_since_reset()
+                .saturating_sub(start_clocks);
+            self.pc_profile[(pc >> 8) as usize] += clocks;
+        }
+        if let Some(e) = self.controller.take_last_emulation_error() {
+            return Err(e);
+        }
+
+        let events = self.controller.take_events();
+        if let Some(detector) = &mut self.idle_detector {
+            let port_writes = self.controller.port_writes().wrapping_sub(port_writes);
+            let frame_started = events.contains(EmulationEvents::FRAME_STARTED);
+            if let Some(idle) = detector.instruction(pc, port_writes, frame_started) {
+                if let Some(debug) = &mut self.controller.debug_interface {
+                    debug.idle_changed(idle);
+                }
+            }
+        }
+        if events.contains(EmulationEvents::FRAME_STARTED) {
+            self.frame_number += 1;
+            if let Some(reload) = self.pending_reload.take() {
+                reload.apply(self);
+            }
+            #[cfg(feature = "autoload")]
+            if let Some(mut autoload) = self.typed_autoload.take() {
+                if autoload.process(self) {
+                    self.typed_autoload = Some(autoload);
+                }
+            }
+        }
+        if events.contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED) {
+            self.process_fast_load_event()?;
+        }
+        Ok(events)
+    }
+
+    /// Executes exactly `count` instructions and returns count of consumed
+    /// CPU clocks. Breakpoints and emulation speed settings are ignored.
+    ///
+    /// Instruction is counted as follows:
+    /// - Prefixed instruction (including chains like `DD FD` with all
+    ///   prefixes but the last ignored) is a single instruction
+    /// - Interrupt acceptance is not an instruction by itself, but its clocks
+    ///   are added to the first instruction of the interrupt handler
+    /// - Each internal `NOP` cycle while CPU is halted is an instruction, as
+    ///   it performs an opcode fetch (so the count matches RZX fetch counter)
+    pub fn step_instructions(&mut self, count: u64) -> Result<u64> {
+        let start = self.controller.clocks_since_reset();
+        for _ in 0..count {
+            self.emulate_prefixed_instruction()?;
+        }
+        Ok(self.controller.clocks_since_reset() - start)
+    }
+
+    /// Executes instructions until `max_tstates` CPU clocks are consumed,
+    /// ignoring breakpoints and emulation speed settings. Unlike the other
+    /// run methods, the budget is checked after each opcode fetch, so the run
+    /// terminates for any memory contents: `HALT` with interrupts disabled
+    /// and endless `DD`/`FD` prefix chains included. This makes it suitable
+    /// for fuzzing and for running untrusted snapshots.
+    ///
+    /// Instruction length is not known before it is executed, so the run
+    /// may exceed the budget by one instruction (together with the interrupt
+    /// acceptance preceding it); the run stops right after the instruction
+    /// which reached the budget
+    pub fn run_bounded(&mut self, max_tstates: u64) -> Result<BoundedResult> {
+        let start_clocks = self.controller.clocks_since_reset();
+        let start_frame = self.frame_number;
+        let mut result = BoundedResult {
+            tstates: 0,
+            instructions: 0,
+            frames: 0,
+        };
+        while result.tstates < max_tstates {
+            self.emulate_instruction()?;
+            result.instructions += 1;
+            result.tstates = self.controller.clocks_since_reset() - start_clocks;
+        }
+        result.frames = self.frame_number - start_frame;
+        Ok(result)
+    }
+
+    /// Emulates instruction with all its prefixes, see
+    /// [Emulator::step_instructions]
+    fn emulate_prefixed_instruction(&mut self) -> Result<EmulationEvents> {
+        let mut events = self.emulate_instruction()?;
+        while self.cpu.is_prefix_pending() {
+            events |= self.emulate_instruction()?;
+        }
+        Ok(events)
+    }
+
+    /// Executes single instruction like [Emulator::step_instructions], but
+    /// CALL and RST are executed together with the called routine, up to the
+    /// return to the next instruction. Return is detected by the stack depth
+    /// as well, so recursive calls returning to the same address do not stop
+    /// the step.
+    ///
+    /// Stops with [EmulationStopReason::Breakpoint] if a breakpoint is hit
+    /// inside the routine, or with [EmulationStopReason::Timeout] if the
+    /// routine does not return in 10 seconds of emulated time
+    pub fn step_over(&mut self) -> Result<EmulationStopReason> {
+        let controller = &self.controller;
+        let return_addr =
+            match step::call_return_address(self.cpu.regs.get_pc(), |addr| controller.peek(addr)) {
+                Some(addr) => addr,
+                None => {
+                    self.step_instructions(1)?;
+                    return Ok(EmulationStopReason::Completed);
+                }
+            };
+        let sp = self.cpu.regs.get_sp();
+        let start_frame = self.frame_number;
+        let mut first_instruction = true;
+        loop {
+            let events = self.emulate_prefixed_instruction()?;
+            if self.cpu.regs.get_pc() == return_addr && self.cpu.regs.get_sp() >= sp {
+                return Ok(EmulationStopReason::Completed);
+            }
+            // Breakpoint at the stepped instruction itself is already reached
+            if !first_instruction && events.contains(EmulationEvents::PC_BREAKPOINT) {
+                return Ok(EmulationStopReason::Breakpoint);
+            }
+            first_instruction = false;
+            if self.frame_number - start_frame >= step::STEP_TIMEOUT_FRAMES {
+                return Ok(EmulationStopReason::Timeout);
+            }
+        }
+    }
+
+    /// Runs until the return from the current routine, i.e. until RET, RETI
+    /// or RETN which pops the return address stored at or above the current
+    /// SP. Returns from nested calls and interrupt handlers pop addresses
+    /// pushed below it, so they do not stop the run.
+    ///
+    /// Stops with [EmulationStopReason::Breakpoint] if a breakpoint is hit
+    /// before the return, or with [EmulationStopReason::Timeout] if the
+    /// routine does not return in 10 seconds of emulated time
+    pub fn step_out(&mut self) -> Result<EmulationStopReason> {
+        let frame_sp = self.cpu.regs.get_sp();
+        let start_frame = self.frame_number;
+        let mut first_instruction = true;
+        loop {
+            let (pc, sp) = (self.cpu.regs.get_pc(), self.cpu.regs.get_sp());
+            let controller = &self.controller;
+            let is_return = step::is_return(pc, |addr| controller.peek(addr));
+            let events = self.emulate_prefixed_instruction()?;
+            // Not taken conditional return does not change SP
+            if is_return && sp >= frame_sp && self.cpu.regs.get_sp() == sp.wrapping_add(2) {
+                return Ok(EmulationStopReason::Completed);
+            }
+            if !first_instruction && events.contains(EmulationEvents::PC_BREAKPOINT) {
+                return Ok(EmulationStopReason::Breakpoint);
+            }
+            first_instruction = false;
+            if self.frame_number - start_frame >= step::STEP_TIMEOUT_FRAMES {
+                return Ok(EmulationStopReason::Timeout);
+            }
+        }
+    }
+
+    /// Applies `input` and emulates until the end of the current frame,
+    /// ignoring emulation speed settings and breakpoints. Sound samples
+    /// produced before the call (e.g. by [Emulator::emulate_frames] without
+    /// reading [Emulator::next_audio_sample]) are discarded
+    pub fn run_frame(&mut self, input: &InputState) -> Result<FrameOutput<'_, H::FrameBuffer>> {
+        #[cfg(feature = "sound")]
+        while self.controller.mixer.pop().is_some() {}
+        for event in input.events() {
+            self.send_input(*event);
+        }
+
+        self.perf.resume(self.perf_clock.measure());
+        let start_frame = self.frame_number;
+        while self.frame_number == start_frame {
+            self.emulate_instruction()?;
+        }
+        self.frame_finished();
+
+        #[cfg(feature = "sound")]
+        {
+            self.frame_audio.clear();
+            while let Some(sample) = self.controller.mixer.pop() {
+                if self.have_sound() {
+                    self.frame_audio.push(sample);
+                }
+            }
+        }
+
+        Ok(FrameOutput {
+            frame_number: self.frame_number,
+            screen: self.controller.screen.frame_buffer(),
+            #[cfg(feature = "precise-border")]
+            border: self.border_buffer(),
+            #[cfg(feature = "sound")]
+            audio: &self.frame_audio,
+            border_events: self.controller.border_events(),
+            storage_events: self.controller.storage.events(),
+            flash_state: self.controller.screen.frame_buffer_flash_state(),
+        })
+    }
+
+    /// Runs `frames` frames as fast as possible, ignoring emulation speed settings
+    /// and breakpoints, and reports measured emulation performance. Produced sound
+    /// samples are discarded
+    pub fn benchmark(&mut self, frames: u64) -> Result<BenchmarkReport> {
+        let specs = *self.settings.machine.specs();
+        let mut instructions = 0;
+        let stopwatch = H::EmulationStopwatch::new();
+        for _ in 0..frames {
+            self.controller.reset_frame_counter();
+            while self.controller.frames_count() == 0 {
+                self.emulate_instruction()?;
+                instructions += 1;
+            }
+            #[cfg(feature = "sound")]
+            while self.controller.mixer.pop().is_some() {}
+        }
+        let wall_time = stopwatch.measure();
+
+        let emulated_clocks = frames * specs.clocks_frame as u64;
+        let emulated_time = Duration::from_secs_f64(emulated_clocks as f64 / specs.freq_cpu as f64);
+
+        Ok(BenchmarkReport {
+            frames,
+            instructions,
+            wall_time,
+            emulated_time,
+        })
     }
 
     /// Perform emulatio up to `emulation_limit` duration, returns actual elapsed duration
     pub fn emulate_frames(&mut self, emulation_limit: Duration) -> Result<EmulationInfo> {
+        self.perf.resume(self.perf_clock.measure());
+        let result = self.emulate_frames_until_limit(emulation_limit);
+        self.perf.pause(self.perf_clock.measure());
+        result
+    }
+
+    /// Returns host time statistics of the recent frames emulated via
+    /// [Emulator::emulate_frames] or [Emulator::run_frame]
+    pub fn perf_stats(&self) -> PerfStats {
+        self.perf.stats()
+    }
+
+    /// Enables skipping of up to `max` consecutive frames while the average
+    /// host time of the recent frames (see [Emulator::perf_stats]) exceeds
+    /// the emulated frame duration. Skipped frames run the CPU and produce
+    /// sound, but screen and border buffers keep the last rendered frame.
+    /// The first rendered frame after skipping reports the whole screen as
+    /// dirty. Zero `max` disables frame skipping
+    pub fn set_auto_frameskip(&mut self, max: u32) {
+        self.frame_skipper.set_max(max);
+    }
+
+    /// Returns maximal count of consecutive skipped frames, see
+    /// [Emulator::set_auto_frameskip]
+    pub fn auto_frameskip(&self) -> u32 {
+        self.frame_skipper.max()
+    }
+
+    /// Returns count of frames skipped by [Emulator::set_auto_frameskip]
+    pub fn skipped_frames(&self) -> u64 {
+        self.frame_skipper.skipped_total()
+    }
+
+    /// Records frame time and decides whether the started frame is rendered
+    fn frame_finished(&mut self) {
+        self.perf.frame_finished(self.perf_clock.measure());
+        let skip = self.frame_skipper.max() != 0 && {
+            let specs = self.settings.machine.specs();
+            let frame_ms = specs.clocks_frame as f64 * 1000.0 / specs.freq_cpu as f64;
+            self.frame_skipper.next_frame(&self.perf.stats(), frame_ms)
+        };
+        self.controller.set_rendering_skipped(skip);
+    }
+
+    fn emulate_frames_until_limit(&mut self, emulation_limit: Duration) -> Result<EmulationInfo> {
         let stopwatch = H::EmulationStopwatch::new();
+        if let EmulationMode::SlowMotion(divisor) = self.mode {
+            self.slow_motion_skipped += 1;
+            if self.slow_motion_skipped < divisor {
+                return Ok(EmulationInfo {
+                    duration: stopwatch.measure(),
+                    stop_reason: EmulationStopReason::Completed,
+                });
+            }
+            self.slow_motion_skipped = 0;
+        }
         // frame loop
         loop {
             // reset controller internal frame counter
             self.controller.reset_frame_counter();
             'cpu: loop {
                 // Emulation step. if instant event happened then accept in and execute
-                self.cpu.emulate(&mut self.controller);
-                if let Some(e) = self.controller.take_last_emulation_error() {
-                    return Err(e);
+                let events = self.emulate_instruction()?;
+                if events.contains(EmulationEvents::FRAME_STARTED) {
+                    self.frame_finished();
                 }
-
-                let events = self.controller.take_events();
-                if !events.is_empty() {
-                    if events.contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED) {
-                        self.process_fast_load_event()?;
-                    }
-                    if events.contains(EmulationEvents::PC_BREAKPOINT) {
-                        return Ok(EmulationInfo {
-                            duration: stopwatch.measure(),
-                            stop_reason: EmulationStopReason::Breakpoint,
-                        });
-                    }
+                if events.contains(EmulationEvents::PC_BREAKPOINT) {
+                    return Ok(EmulationInfo {
+                        duration: stopwatch.measure(),
+                        stop_reason: EmulationStopReason::Breakpoint,
+                    });
                 }
 
                 match self.mode {
+                    EmulationMode::SlowMotion(_) => {
+                        if self.controller.frames_count() != 0 {
+                            return Ok(EmulationInfo {
+                                duration: stopwatch.measure(),
+                                stop_reason: EmulationStopReason::Completed,
+                            });
+                        }
+                    }
                     EmulationMode::FrameCount(frames) => {
                         if self.controller.frames_count() >= frames {
                             return Ok(EmulationInfo {
@@ -334,3 +2174,29 @@ impl<H: Host> Emulator<H> {
         }
     }
 }
+
+/// Reads up to `limit` bytes of the asset
+fn read_asset(asset: &mut impl LoadableAsset, limit: usize) -> Result<Vec<u8>> {
+    let mut data = alloc::vec![0u8; limit];
+    let mut size = 0;
+    while size < data.len() {
+        match asset.read(&mut data[size..]) {
+            Ok(0) | Err(IoError::UnexpectedEof) => break,
+            Ok(read) => size += read,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    data.truncate(size);
+    Ok(data)
+}
+
+/// Reads Ultra Disk Image, which is accepted by all disk interfaces
+#[cfg(any(feature = "plusd", feature = "betadisk"))]
+fn read_udi(mut asset: impl LoadableAsset) -> Result<FloppyDisk> {
+    // Extra byte detects images larger than expected
+    let data = read_asset(&mut asset, MAX_UDI_SIZE + 1)?;
+    if data.len() > MAX_UDI_SIZE {
+        return Err(DiskLoadError::InvalidImageSize.into());
+    }
+    Ok(FloppyDisk::from_udi(data)?)
+}
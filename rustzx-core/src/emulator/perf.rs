@@ -0,0 +1,183 @@
+//! Wall-clock frame time statistics of the recent frames, see
+//! [crate::Emulator::perf_stats]
+use core::time::Duration;
+
+/// Count of the recent frames used for statistics
+const FRAME_HISTORY: usize = 32;
+
+/// Emulation performance of the recent frames
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PerfStats {
+    /// Average host time spent on emulation of a frame, in milliseconds
+    pub avg_ms: f64,
+    /// Maximal host time spent on emulation of a frame, in milliseconds
+    pub max_ms: f64,
+    /// Count of frames emulated per host second, including time spent by
+    /// the host between emulation calls
+    pub fps: f64,
+}
+
+/// Collects frame times, timestamps are host times since some fixed moment
+#[derive(Default)]
+pub(crate) struct PerfTracker {
+    // Start of the currently measured emulation interval
+    mark: Duration,
+    // Emulation time of the current frame so far
+    busy: Duration,
+    frame_times: [Duration; FRAME_HISTORY],
+    // End timestamps of the frames
+    frame_ends: [Duration; FRAME_HISTORY],
+    len: usize,
+    next: usize,
+}
+
+impl PerfTracker {
+    /// Starts measured emulation interval
+    pub fn resume(&mut self, now: Duration) {
+        self.mark = now;
+    }
+
+    /// Ends measured emulation interval, time until the next
+    /// [PerfTracker::resume] is not counted as frame emulation time
+    pub fn pause(&mut self, now: Duration) {
+        self.busy += now.saturating_sub(self.mark);
+        self.mark = now;
+    }
+
+    /// Finishes frame, emulation interval continues with the next frame
+    pub fn frame_finished(&mut self, now: Duration) {
+        self.pause(now);
+        self.frame_times[self.next] = core::mem::take(&mut self.busy);
+        self.frame_ends[self.next] = now;
+        self.next = (self.next + 1) % FRAME_HISTORY;
+        self.len = (self.len + 1).min(FRAME_HISTORY);
+    }
+
+    pub fn stats(&self) -> PerfStats {
+        if self.len == 0 {
+            return PerfStats::default();
+        }
+        let times = if self.len < FRAME_HISTORY {
+            &self.frame_times[..self.len]
+        } else {
+            &self.frame_times[..]
+        };
+        let total: Duration = times.iter().sum();
+        let max = times.iter().max().copied().unwrap_or_default();
+        let newest = self.frame_ends[(self.next + FRAME_HISTORY - 1) % FRAME_HISTORY];
+        let oldest = self.frame_ends[(self.next + FRAME_HISTORY - self.len) % FRAME_HISTORY];
+        let span = newest.saturating_sub(oldest).as_secs_f64();
+        let fps = if span > 0.0 {
+            (self.len - 1) as f64 / span
+        } else {
+            0.0
+        };
+        PerfStats {
+            avg_ms: total.as_secs_f64() * 1000.0 / self.len as f64,
+            max_ms: max.as_secs_f64() * 1000.0,
+            fps,
+        }
+    }
+}
+
+/// Chooses frames emulated without rendering while the average frame time
+/// exceeds the emulated frame duration, see [crate::Emulator::set_auto_frameskip]
+#[derive(Default)]
+pub(crate) struct FrameSkipper {
+    max: u32,
+    skipped_in_row: u32,
+    skipped_total: u64,
+}
+
+impl FrameSkipper {
+    /// Sets maximal count of consecutive skipped frames, 0 disables skipping
+    pub fn set_max(&mut self, max: u32) {
+        self.max = max;
+        self.skipped_in_row = 0;
+    }
+
+    pub fn max(&self) -> u32 {
+        self.max
+    }
+
+    /// Returns total count of skipped frames
+    pub fn skipped_total(&self) -> u64 {
+        self.skipped_total
+    }
+
+    /// Called when a new frame starts, returns true if the frame should be
+    /// skipped. Frame time of the skipped frames lowers `stats.avg_ms`, so
+    /// rendering resumes when the host catches up
+    pub fn next_frame(&mut self, stats: &PerfStats, frame_ms: f64) -> bool {
+        if self.skipped_in_row < self.max && stats.avg_ms > frame_ms {
+            self.skipped_in_row += 1;
+            self.skipped_total += 1;
+            true
+        } else {
+            self.skipped_in_row = 0;
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    #[test]
+    fn frame_times_exclude_pauses() {
+        let mut perf = PerfTracker::default();
+        assert_eq!(perf.stats(), PerfStats::default());
+        // Frame emulated by two calls, time between them is not counted
+        perf.resume(ms(0));
+        perf.pause(ms(2));
+        perf.resume(ms(10));
+        perf.frame_finished(ms(13));
+        // Frames start each 20ms
+        for (start, time) in [(20, 5), (40, 5), (60, 8)] {
+            perf.resume(ms(start));
+            perf.frame_finished(ms(start + time));
+        }
+        let stats = perf.stats();
+        assert!((stats.avg_ms - 5.75).abs() < 1e-9);
+        assert!((stats.max_ms - 8.0).abs() < 1e-9);
+        assert!((stats.fps - 3.0 / 0.055).abs() < 1e-6);
+    }
+
+    #[test]
+    fn old_frames_are_dropped() {
+        let mut perf = PerfTracker::default();
+        perf.resume(ms(0));
+        perf.frame_finished(ms(100));
+        for frame in 1..=FRAME_HISTORY as u64 {
+            perf.frame_finished(ms(100 + frame * 10));
+        }
+        let stats = perf.stats();
+        assert!((stats.max_ms - 10.0).abs() < 1e-9);
+        assert!((stats.fps - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn frame_skipper_limits_consecutive_skips() {
+        let slow = PerfStats {
+            avg_ms: 25.0,
+            ..Default::default()
+        };
+        let fast = PerfStats {
+            avg_ms: 15.0,
+            ..Default::default()
+        };
+        let mut skipper = FrameSkipper::default();
+        assert!(!skipper.next_frame(&slow, 20.0));
+
+        skipper.set_max(2);
+        let skipped = [(); 6].map(|_| skipper.next_frame(&slow, 20.0));
+        assert_eq!(skipped, [true, true, false, true, true, false]);
+        assert!(!skipper.next_frame(&fast, 20.0));
+        assert_eq!(skipper.skipped_total(), 4);
+    }
+}
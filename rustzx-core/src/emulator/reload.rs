@@ -0,0 +1,72 @@
+//! Reloading of the development binary into the running machine. Binary is
+//! read immediately, but patched into memory at the next frame start, so the
+//! running code never sees a partially written binary in the middle of an
+//! instruction or a frame
+use crate::{
+    diagnostics::MACHINE,
+    emulator::Emulator,
+    error::{BinaryLoadError, IoError},
+    host::{Host, LoadableAsset},
+    Result,
+};
+use alloc::vec::Vec;
+use rustzx_z80::Z80Bus;
+
+/// Chunk size used to read the binary asset
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// Machine state handling on binary reload, see [Emulator::reload_binary]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReloadMode {
+    /// Binary is patched into memory while the machine keeps running
+    PreserveState,
+    /// CPU is reset to the power-on state and jumps to the load address.
+    /// Memory outside the binary and paging are kept, so ROM system
+    /// variables stay valid for the restarted code
+    Restart,
+}
+
+pub(crate) struct PendingReload {
+    addr: u16,
+    data: Vec<u8>,
+    mode: ReloadMode,
+}
+
+impl PendingReload {
+    pub fn read(mut binary: impl LoadableAsset, addr: u16, mode: ReloadMode) -> Result<Self> {
+        let mut data = Vec::new();
+        let mut chunk = [0u8; READ_CHUNK_SIZE];
+        loop {
+            match binary.read(&mut chunk) {
+                Ok(0) | Err(IoError::UnexpectedEof) => break,
+                Ok(read) => data.extend_from_slice(&chunk[..read]),
+                Err(e) => return Err(e.into()),
+            }
+            if addr as usize + data.len() > 0x10000 {
+                return Err(BinaryLoadError::TooLarge.into());
+            }
+        }
+        Ok(Self { addr, data, mode })
+    }
+
+    pub fn apply<H: Host>(self, emulator: &mut Emulator<H>) {
+        // Written via bus to update the screen, ROM is left untouched
+        for (offset, value) in self.data.iter().enumerate() {
+            emulator
+                .controller
+                .write_internal(self.addr + offset as u16, *value);
+        }
+        if self.mode == ReloadMode::Restart {
+            emulator.cpu.reset();
+            emulator.cpu.regs.set_pc(self.addr);
+        }
+        diag!(
+            info,
+            MACHINE,
+            "binary reloaded at {:#06X}, {} bytes, {:?}",
+            self.addr,
+            self.data.len(),
+            self.mode
+        );
+    }
+}
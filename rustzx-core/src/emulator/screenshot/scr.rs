@@ -1,4 +1,5 @@
 use crate::{
+    diagnostics::SCREEN,
     emulator::Emulator,
     error::ScreenLoadError,
     host::{Host, LoadableAsset, SeekFrom, SeekableAsset},
@@ -21,6 +22,13 @@ where
     let file_size = asset.seek(SeekFrom::End(0))?;
 
     if file_size != PRIMARY_SCREEN_MEMORY_SIZE {
+        diag!(
+            warn,
+            SCREEN,
+            "SCR file size is {} bytes, expected {}",
+            file_size,
+            PRIMARY_SCREEN_MEMORY_SIZE
+        );
         return Err(ScreenLoadError::InvalidScrFile.into());
     }
 
@@ -30,6 +38,12 @@ where
         Page::Ram(page) => page,
         Page::Rom(_) => {
             // Machine with such memory map is not implemented yet
+            diag!(
+                warn,
+                SCREEN,
+                "screen address {:#06X} is mapped to ROM",
+                SCREEN_ADDR
+            );
             return Err(ScreenLoadError::MachineNotSupported.into());
         }
     };
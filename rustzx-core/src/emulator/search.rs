@@ -0,0 +1,73 @@
+//! Memory search for cheat hunting, e.g. finding the lives counter by
+//! searching for the current count and refining the results after each lost
+//! life. Search is started via [crate::Emulator::search_equal] and refined
+//! across frames by other `search_refine_*` methods of the emulator
+use crate::zx::memory::ZXMemory;
+use alloc::vec::Vec;
+
+/// RAM byte regardless of the memory paging, `offset` is relative to the bank
+/// start. On 48K banks 0, 1 and 2 are mapped at 0x4000, 0x8000 and 0xC000
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RamAddress {
+    pub bank: u8,
+    pub offset: u16,
+}
+
+/// Candidate set of the search with values seen at the last scan
+#[derive(Default)]
+pub(crate) struct MemorySearch {
+    candidates: Vec<(RamAddress, u8)>,
+}
+
+impl MemorySearch {
+    /// Starts new search with all RAM bytes equal to `value`
+    pub fn start(&mut self, memory: &ZXMemory, value: u8) {
+        self.candidates = (0..memory.ram_pages())
+            .flat_map(|bank| {
+                memory
+                    .ram_page_data(bank)
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, byte)| **byte == value)
+                    .map(move |(offset, _)| {
+                        let offset = offset as u16;
+                        (RamAddress { bank, offset }, value)
+                    })
+            })
+            .collect();
+    }
+
+    /// Keeps candidates for which `keep(previous, current)` returns true and
+    /// remembers their current values. Candidates in banks missing after the
+    /// machine switch are dropped
+    pub fn refine(&mut self, memory: &ZXMemory, keep: impl Fn(u8, u8) -> bool) {
+        let banks = memory.ram_pages();
+        self.candidates.retain_mut(|(address, value)| {
+            if address.bank >= banks {
+                return false;
+            }
+            let current = memory.ram_page_data(address.bank)[address.offset as usize];
+            let previous = core::mem::replace(value, current);
+            keep(previous, current)
+        });
+    }
+
+    pub fn candidates(&self) -> Vec<RamAddress> {
+        self.candidates
+            .iter()
+            .map(|(address, _)| *address)
+            .collect()
+    }
+
+    /// Returns sorted CPU addresses of the candidates visible through the
+    /// current memory paging
+    pub fn visible_addresses(&self, memory: &ZXMemory) -> Vec<u16> {
+        let mut addresses: Vec<u16> = self
+            .candidates
+            .iter()
+            .filter_map(|(address, _)| memory.ram_address(address.bank, address.offset))
+            .collect();
+        addresses.sort_unstable();
+        addresses
+    }
+}
@@ -1,8 +1,11 @@
+use alloc::vec::Vec;
+
 use crate::{
+    diagnostics::SNAPSHOT,
     emulator::Emulator,
-    error::IoError,
+    error::{SnapshotLoadError, SnapshotSaveError},
     host::{DataRecorder, Host, LoadableAsset, SeekFrom, SeekableAsset},
-    zx::{machine::ZXMachine, video::colors::ZXColor},
+    zx::{machine::MemoryModel, memory::Page, video::colors::ZXColor},
     Result,
 };
 
@@ -19,8 +22,50 @@ const SNA_BORDER_COLOR_MASK: u8 = 0x07;
 const SNA_128K_TAIL_BANKS: &[u8] = &[0, 1, 3, 4, 6, 7];
 const SNA_PAGINATED_PAGED_BANK_ADDRESS: u16 = 0xFFFF;
 const SNA_48K_RAM_PAGES_COUNT: u8 = 3;
+/// 0x7FFD value which keeps 48K ROM paged in and locks paging
+const SNA_48K_PORT_7FFD: u8 = 0x30;
+const SNA_48K_RAM_START: u16 = 0x4000;
+// Tail contains 5 banks when paged bank is one of the head banks
+const SNA_128K_MIN_SIZE: usize = SNA_128K_SECONDARY_TAIL_BANKS_OFFSET + 5 * 0x4000;
+
+/// Returns memory model of the machine which saved the snapshot, 48K and
+/// 128K snapshots differ only in size
+pub fn required_memory_model<A>(asset: &mut A) -> Result<MemoryModel>
+where
+    A: SeekableAsset,
+{
+    let size = asset.seek(SeekFrom::End(0))?;
+    asset.seek(SeekFrom::Start(0))?;
+    Ok(if size > SNA_48K_SIZE {
+        MemoryModel::Sinclair128K
+    } else {
+        MemoryModel::Sinclair48K
+    })
+}
+
+/// Returns RAM banks mapped at 0x4000, 0x8000 and 0xC000
+fn mapped_ram_banks<H: Host>(emulator: &Emulator<H>) -> [u8; 3] {
+    [0x4000, 0x8000, 0xC000].map(|addr| match emulator.controller.memory.get_page(addr) {
+        Page::Ram(bank) => bank,
+        Page::Rom(_) => 0,
+    })
+}
 
-/// SNA snapshot loading function
+/// Unlocks paging, which could be locked by the previously running program,
+/// and writes 0x7FFD value. +2A/+3 has 48K BASIC in ROM 3, so the ROM high
+/// bit in 0x1FFD follows the 128K ROM select bit
+fn restore_paging<H: Host>(emulator: &mut Emulator<H>, port_7ffd: u8) {
+    emulator.controller.unlock_paging();
+    if emulator.settings.machine.memory_model() == MemoryModel::SinclairPlus3 {
+        emulator.controller.write_1ffd((port_7ffd >> 2) & 0x04);
+    }
+    emulator.controller.write_7ffd(port_7ffd);
+}
+
+/// SNA snapshot loading function. Snapshot of other memory model (allowed by
+/// [crate::zx::machine::MachinePolicy::WarnOnly]) is loaded partially: only
+/// the 48K of RAM visible to the CPU is restored, on 128K machine 48K ROM is
+/// paged in and paging is locked
 pub fn load<H, A>(emulator: &mut Emulator<H>, mut asset: A) -> Result<()>
 where
     H: Host,
@@ -30,13 +75,42 @@ where
     asset.seek(SeekFrom::Start(0))?;
 
     let is_128k = size > SNA_48K_SIZE;
+    let machine_is_48k = emulator.settings.machine.is_48k();
 
     if !is_128k && size < SNA_48K_SIZE {
-        return Err(IoError::UnexpectedEof.into());
+        diag!(
+            warn,
+            SNAPSHOT,
+            "SNA file is truncated at offset {}, expected {} bytes",
+            size,
+            SNA_48K_SIZE
+        );
+        return Err(SnapshotLoadError::TruncatedSnapshot.into());
+    }
+
+    if is_128k && size < SNA_128K_MIN_SIZE {
+        diag!(
+            warn,
+            SNAPSHOT,
+            "128K SNA file is truncated at offset {}, expected at least {} bytes",
+            size,
+            SNA_128K_MIN_SIZE
+        );
+        return Err(SnapshotLoadError::TruncatedSnapshot.into());
     }
 
     let mut header = [0u8; SNA_HEADER_SIZE];
     asset.read_exact(&mut header)?;
+    // Validated before any state is changed
+    if header[25] & SNA_INTERRUPT_MODE_MASK > 2 {
+        diag!(
+            warn,
+            SNAPSHOT,
+            "SNA header has invalid interrupt mode {}",
+            header[25]
+        );
+        return Err(SnapshotLoadError::InvalidInterruptMode.into());
+    }
 
     // i-reg
     emulator.cpu.regs.set_i(header[0]);
@@ -114,91 +188,91 @@ where
             .regs
             .set_pc(u16::from_le_bytes([tmp[0], tmp[1]]));
         let port_7ffd = tmp[2];
-        let _trdos_paged = tmp[3];
-        // This will alsto setup required memory map before banks restore
-        emulator.controller.write_7ffd(port_7ffd);
+        #[cfg(feature = "betadisk")]
+        if let Some(betadisk) = &mut emulator.controller.betadisk {
+            betadisk.set_paged_in(tmp[3] != 0);
+        }
+        // This will alsto setup required memory map before banks restore.
+        restore_paging(emulator, port_7ffd);
 
         // Go to the previous position
         asset.seek(SeekFrom::Start(SNA_HEADER_SIZE))?;
-        let paginated_bank = match emulator
-            .controller
-            .memory
-            .get_page(SNA_PAGINATED_PAGED_BANK_ADDRESS)
-        {
-            crate::zx::memory::Page::Ram(bank) => bank,
-            crate::zx::memory::Page::Rom(_) => 0,
-        };
-
-        // write 3 head banks
-        let head_banks = &[
-            SNA_128K_PERSISTENT_BANK_0,
-            SNA_128K_PERSISTENT_BANK_1,
-            paginated_bank,
-        ];
+        let head_banks = mapped_ram_banks(emulator);
         for bank in head_banks {
-            let page = emulator.controller.memory.ram_page_data_mut(*bank);
+            let page = emulator.controller.memory.ram_page_data_mut(bank);
             asset.read_exact(page)?;
         }
 
         // tail banks
-        asset.seek(SeekFrom::Start(SNA_128K_SECONDARY_TAIL_BANKS_OFFSET))?;
-        for bank in SNA_128K_TAIL_BANKS {
-            if *bank == paginated_bank {
-                continue;
+        if !machine_is_48k {
+            asset.seek(SeekFrom::Start(SNA_128K_SECONDARY_TAIL_BANKS_OFFSET))?;
+            for bank in SNA_128K_TAIL_BANKS {
+                if *bank == head_banks[2] {
+                    continue;
+                }
+                let page = emulator.controller.memory.ram_page_data_mut(*bank);
+                asset.read_exact(page)?;
             }
-            let page = emulator.controller.memory.ram_page_data_mut(*bank);
-            asset.read_exact(page)?;
         }
     } else {
-        for page_index in 0..SNA_48K_RAM_PAGES_COUNT {
-            let page = emulator.controller.memory.ram_page_data_mut(page_index);
+        if !machine_is_48k {
+            restore_paging(emulator, SNA_48K_PORT_7FFD);
+        }
+        for bank in mapped_ram_banks(emulator) {
+            let page = emulator.controller.memory.ram_page_data_mut(bank);
             asset.read_exact(page)?;
         }
 
         emulator.cpu.pop_pc_from_stack(&mut emulator.controller);
     }
 
+    diag!(
+        debug,
+        SNAPSHOT,
+        "loaded {} SNA: PC={:#06X} SP={:#06X} IM={} IFF={} border={} 7FFD={:#04X}",
+        if is_128k { "128K" } else { "48K" },
+        emulator.cpu.regs.get_pc(),
+        emulator.cpu.regs.get_sp(),
+        header[25] & SNA_INTERRUPT_MODE_MASK,
+        iff,
+        header[26] & SNA_BORDER_COLOR_MASK,
+        emulator.controller.read_7ffd()
+    );
+
     // Refresh screen and other memory-dependent peripheral
     emulator.controller.refresh_memory_dependent_devices();
 
     Ok(())
 }
 
-/// Helper class to place emulator in the state required for
-/// snapshoting and return to normal state afterwards
-struct ScopedSnapshotState<'a, H: Host> {
-    pub emulator: &'a mut Emulator<H>,
-    pub is_48k: bool,
-}
-
-impl<'a, H: Host> ScopedSnapshotState<'a, H> {
-    fn enter(emulator: &'a mut Emulator<H>) -> Self {
-        let is_48k = emulator.settings.machine == ZXMachine::Sinclair48K;
-        if is_48k {
-            emulator.cpu.push_pc_to_stack(&mut emulator.controller);
-        }
-
-        Self { emulator, is_48k }
-    }
-}
-
-impl<'a, H: Host> Drop for ScopedSnapshotState<'a, H> {
-    fn drop(&mut self) {
-        if self.is_48k {
-            self.emulator
-                .cpu
-                .pop_pc_from_stack(&mut self.emulator.controller);
-        }
-    }
-}
-
+/// SNA snapshot saving function.
+///
+/// 48K snapshots have no field for PC, so it is pushed to the stack in the
+/// saved RAM image (emulator state itself is left untouched). Saving is refused
+/// when the pushed value would land in ROM (SP in 0x0001..=0x4001). When SP is
+/// in the screen area the pushed PC is visible in the saved screen, same as
+/// with any other SNA saver. 128K snapshots store PC in the extended header
 pub fn save<H, R>(emulator: &mut Emulator<H>, mut recorder: R) -> Result<()>
 where
     H: Host,
     R: DataRecorder,
 {
-    let state = ScopedSnapshotState::enter(emulator);
-    let ScopedSnapshotState { emulator, is_48k } = &state;
+    let is_48k = emulator.settings.machine.is_48k();
+    let pc = emulator.cpu.regs.get_pc();
+    let mut sp = emulator.cpu.regs.get_sp();
+    if is_48k {
+        sp = sp.wrapping_sub(2);
+        if sp < SNA_48K_RAM_START || sp == 0xFFFF {
+            diag!(
+                warn,
+                SNAPSHOT,
+                "can't push PC={:#06X} to the stack at SP={:#06X}",
+                pc,
+                emulator.cpu.regs.get_sp()
+            );
+            return Err(SnapshotSaveError::StackInRom.into());
+        }
+    }
 
     let mut header = [0u8; SNA_HEADER_SIZE];
     // interrupt register
@@ -235,7 +309,7 @@ where
     header[21] = emulator.cpu.regs.get_flags();
     header[22] = emulator.cpu.regs.get_acc();
     // SP
-    let [spl, sph] = emulator.cpu.regs.get_sp().to_le_bytes();
+    let [spl, sph] = sp.to_le_bytes();
     header[23] = spl;
     header[24] = sph;
     // Interrupt mode
@@ -245,11 +319,16 @@ where
 
     recorder.write_all(&header)?;
 
-    if *is_48k {
+    if is_48k {
+        let mut ram = Vec::with_capacity(SNA_48K_SIZE - SNA_HEADER_SIZE);
         for page_index in 0..SNA_48K_RAM_PAGES_COUNT {
-            let page = emulator.controller.memory.ram_page_data(page_index);
-            recorder.write_all(page)?;
+            ram.extend_from_slice(emulator.controller.memory.ram_page_data(page_index));
         }
+        let stack_offset = (sp - SNA_48K_RAM_START) as usize;
+        let [pcl, pch] = pc.to_le_bytes();
+        ram[stack_offset] = pcl;
+        ram[stack_offset + 1] = pch;
+        recorder.write_all(&ram)?;
     } else {
         let paginated_bank = match emulator
             .controller
@@ -270,8 +349,15 @@ where
         }
 
         // PC, 7ffd, trdos
-        let [pcl, pch] = emulator.cpu.regs.get_pc().to_le_bytes();
+        let [pcl, pch] = pc.to_le_bytes();
         let port_7ffd = emulator.controller.read_7ffd();
+        #[cfg(feature = "betadisk")]
+        let trdos_paged = emulator
+            .controller
+            .betadisk
+            .as_ref()
+            .is_some_and(|betadisk| betadisk.paged_in()) as u8;
+        #[cfg(not(feature = "betadisk"))]
         let trdos_paged = 0x00;
         recorder.write_all(&[pcl, pch, port_7ffd, trdos_paged])?;
 
@@ -0,0 +1,252 @@
+//! Comparison of the machine states for reverse-engineering, e.g. finding
+//! the lives counter by diffing states captured before and after losing a
+//! life. States are captured via [crate::Emulator::capture_state], snapshot
+//! files can be compared by loading them into the emulator first
+use crate::zx::{constants::ATTR_MAX_REL, machine::MemoryModel, memory::PAGE_SIZE};
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+
+/// Display file of the screen bank (bitmap and attributes)
+const DISPLAY_FILE: (u16, u16) = (0x0000, ATTR_MAX_REL + 1);
+/// 48K system variables area at 0x5C00..0x5CB6, relative to bank start
+const SYSVARS_48K: (u16, u16) = (0x1C00, 0x1CB6);
+/// 128K system variables at 0x5B00 (in place of the printer buffer) and the
+/// 48K ones, relative to bank start
+const SYSVARS_128K: (u16, u16) = (0x1B00, 0x1CB6);
+
+/// CPU registers, alternative registers have `_alt` suffix
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CpuRegisters {
+    pub af: u16,
+    pub bc: u16,
+    pub de: u16,
+    pub hl: u16,
+    pub af_alt: u16,
+    pub bc_alt: u16,
+    pub de_alt: u16,
+    pub hl_alt: u16,
+    pub ix: u16,
+    pub iy: u16,
+    pub sp: u16,
+    pub pc: u16,
+    pub i: u8,
+    pub r: u8,
+    pub iff1: bool,
+    pub iff2: bool,
+    /// Interrupt mode (0, 1 or 2)
+    pub im: u8,
+}
+
+impl CpuRegisters {
+    /// Returns registers as `(name, value)` pairs in the display order
+    fn named(&self) -> [(&'static str, u16); 17] {
+        [
+            ("AF", self.af),
+            ("BC", self.bc),
+            ("DE", self.de),
+            ("HL", self.hl),
+            ("AF'", self.af_alt),
+            ("BC'", self.bc_alt),
+            ("DE'", self.de_alt),
+            ("HL'", self.hl_alt),
+            ("IX", self.ix),
+            ("IY", self.iy),
+            ("SP", self.sp),
+            ("PC", self.pc),
+            ("I", self.i as u16),
+            ("R", self.r as u16),
+            ("IFF1", self.iff1 as u16),
+            ("IFF2", self.iff2 as u16),
+            ("IM", self.im as u16),
+        ]
+    }
+}
+
+/// CPU registers and contents of all RAM banks
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MachineState {
+    memory_model: MemoryModel,
+    registers: CpuRegisters,
+    ram: Vec<u8>,
+}
+
+impl MachineState {
+    pub(crate) fn new<'a>(
+        memory_model: MemoryModel,
+        registers: CpuRegisters,
+        banks: impl Iterator<Item = &'a [u8]>,
+    ) -> Self {
+        Self {
+            memory_model,
+            registers,
+            ram: banks.flatten().copied().collect(),
+        }
+    }
+
+    pub fn memory_model(&self) -> MemoryModel {
+        self.memory_model
+    }
+
+    pub fn registers(&self) -> &CpuRegisters {
+        &self.registers
+    }
+
+    /// Returns count of 16K RAM banks
+    pub fn ram_banks(&self) -> u8 {
+        (self.ram.len() / PAGE_SIZE) as u8
+    }
+
+    /// Returns contents of the RAM bank, `None` if the machine has no such
+    /// bank. On 48K banks 0, 1 and 2 are mapped at 0x4000, 0x8000 and 0xC000
+    pub fn ram_bank(&self, bank: u8) -> Option<&[u8]> {
+        let shift = bank as usize * PAGE_SIZE;
+        self.ram.get(shift..shift + PAGE_SIZE)
+    }
+
+    /// Compares this (older) state with the `other` (newer) one
+    pub fn diff(&self, other: &MachineState, options: DiffOptions) -> StateDiff {
+        let registers = self
+            .registers
+            .named()
+            .iter()
+            .zip(other.registers.named().iter())
+            .filter(|(old, new)| old.1 != new.1)
+            .map(|(old, new)| RegisterDiff {
+                name: old.0,
+                old: old.1,
+                new: new.1,
+            })
+            .collect();
+
+        let ignored = self.ignored_ranges(options);
+        let mut memory = Vec::new();
+        for bank in 0..self.ram_banks().max(other.ram_banks()) {
+            match (self.ram_bank(bank), other.ram_bank(bank)) {
+                (Some(old), Some(new)) => {
+                    diff_bank(bank, old, new, &ignored, &mut memory);
+                }
+                // Banks missing in one of the states (different machines)
+                // are reported as changed entirely
+                _ => memory.push(MemoryRange {
+                    bank,
+                    start: 0,
+                    length: PAGE_SIZE,
+                }),
+            }
+        }
+
+        StateDiff { memory, registers }
+    }
+
+    /// Returns `(bank, start, end)` areas skipped by the diff
+    fn ignored_ranges(&self, options: DiffOptions) -> Vec<(u8, u16, u16)> {
+        // Bank at 0x4000 contains screen and system variables
+        let (screen_banks, sysvars): (&[u8], _) = match self.memory_model {
+            MemoryModel::Sinclair48K => (&[0], SYSVARS_48K),
+            MemoryModel::Sinclair128K | MemoryModel::SinclairPlus3 => (&[5, 7], SYSVARS_128K),
+        };
+        let mut ranges = Vec::new();
+        if options.ignore_display_file {
+            ranges.extend(
+                screen_banks
+                    .iter()
+                    .map(|bank| (*bank, DISPLAY_FILE.0, DISPLAY_FILE.1)),
+            );
+        }
+        if options.ignore_sysvars {
+            ranges.push((screen_banks[0], sysvars.0, sysvars.1));
+        }
+        ranges
+    }
+}
+
+/// Appends differing runs of the bank to `result`
+fn diff_bank(
+    bank: u8,
+    old: &[u8],
+    new: &[u8],
+    ignored: &[(u8, u16, u16)],
+    result: &mut Vec<MemoryRange>,
+) {
+    let is_ignored = |offset: usize| {
+        ignored.iter().any(|(ignored_bank, start, end)| {
+            *ignored_bank == bank && (*start as usize..*end as usize).contains(&offset)
+        })
+    };
+    let mut run: Option<usize> = None;
+    for offset in 0..=PAGE_SIZE {
+        let differs = offset < PAGE_SIZE && old[offset] != new[offset] && !is_ignored(offset);
+        match (run, differs) {
+            (None, true) => run = Some(offset),
+            (Some(start), false) => {
+                result.push(MemoryRange {
+                    bank,
+                    start: start as u16,
+                    length: offset - start,
+                });
+                run = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Areas excluded from the memory diff. Both are changed constantly by ROM
+/// and screen updates, hiding the interesting changes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiffOptions {
+    /// Skip bitmap and attributes of the screen banks (bank 0 on 48K, banks 5
+    /// and 7 on 128K)
+    pub ignore_display_file: bool,
+    /// Skip system variables (including 128K ones at 0x5B00)
+    pub ignore_sysvars: bool,
+}
+
+/// Run of differing bytes, `start` is relative to the bank start
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryRange {
+    pub bank: u8,
+    pub start: u16,
+    pub length: usize,
+}
+
+/// Changed register, 8-bit registers and flags are widened to `u16`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegisterDiff {
+    pub name: &'static str,
+    pub old: u16,
+    pub new: u16,
+}
+
+/// Result of [MachineState::diff]. Displayed as text with one line per
+/// register or memory range
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub memory: Vec<MemoryRange>,
+    pub registers: Vec<RegisterDiff>,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_empty() && self.registers.is_empty()
+    }
+}
+
+impl Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for reg in &self.registers {
+            writeln!(f, "{:<4} {:04X} -> {:04X}", reg.name, reg.old, reg.new)?;
+        }
+        for range in &self.memory {
+            writeln!(
+                f,
+                "bank {} {:04X}..{:04X} ({} bytes)",
+                range.bank,
+                range.start,
+                range.start as usize + range.length,
+                range.length
+            )?;
+        }
+        Ok(())
+    }
+}
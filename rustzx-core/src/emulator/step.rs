@@ -0,0 +1,96 @@
+//! Decoding helpers of the debugger stepping commands
+
+/// Opcode prefixes which are ignored by CALL/RST instructions
+const PREFIX_IX: u8 = 0xDD;
+const PREFIX_IY: u8 = 0xFD;
+const OPCODE_CALL: u8 = 0xCD;
+/// Conditional `CALL cc, nn` is `11ccc100`
+const OPCODE_CALL_CC: (u8, u8) = (0xC7, 0xC4);
+/// `RST p` is `11ppp111`
+const OPCODE_RST: (u8, u8) = (0xC7, 0xC7);
+const OPCODE_RET: u8 = 0xC9;
+/// Conditional `RET cc` is `11ccc000`
+const OPCODE_RET_CC: (u8, u8) = (0xC7, 0xC0);
+const PREFIX_ED: u8 = 0xED;
+/// `RETN` and `RETI` (with undocumented mirrors) are `ED 01xxx101`
+const OPCODE_ED_RETN: (u8, u8) = (0xC7, 0x45);
+/// Step over and step out give up when the routine does not return in this
+/// count of frames (e.g. routine which drops its return address)
+pub(crate) const STEP_TIMEOUT_FRAMES: u64 = 50 * 10;
+
+/// Returns address of the opcode at `pc`, skipping `DD`/`FD` prefixes
+fn skip_index_prefixes(pc: u16, read: &impl Fn(u16) -> u8) -> u16 {
+    let mut addr = pc;
+    // Prefix chain can't cover the whole address space, but is bounded anyway
+    while matches!(read(addr), PREFIX_IX | PREFIX_IY) && addr.wrapping_sub(pc) < u16::MAX {
+        addr = addr.wrapping_add(1);
+    }
+    addr
+}
+
+/// Returns address of the instruction following the CALL or RST at `pc`,
+/// `None` if the instruction at `pc` is not a call. `DD`/`FD` prefixes before
+/// the call are executed as `NOP`s and are counted in the instruction length
+pub(crate) fn call_return_address(pc: u16, read: impl Fn(u16) -> u8) -> Option<u16> {
+    let addr = skip_index_prefixes(pc, &read);
+    let opcode = read(addr);
+    let operands = if opcode == OPCODE_CALL || opcode & OPCODE_CALL_CC.0 == OPCODE_CALL_CC.1 {
+        2
+    } else if opcode & OPCODE_RST.0 == OPCODE_RST.1 {
+        0
+    } else {
+        return None;
+    };
+    Some(addr.wrapping_add(1 + operands))
+}
+
+/// Returns true if the instruction at `pc` is RET (including conditional
+/// one), RETI or RETN
+pub(crate) fn is_return(pc: u16, read: impl Fn(u16) -> u8) -> bool {
+    let addr = skip_index_prefixes(pc, &read);
+    match read(addr) {
+        OPCODE_RET => true,
+        PREFIX_ED => read(addr.wrapping_add(1)) & OPCODE_ED_RETN.0 == OPCODE_ED_RETN.1,
+        opcode => opcode & OPCODE_RET_CC.0 == OPCODE_RET_CC.1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn return_address(code: &[u8]) -> Option<u16> {
+        call_return_address(0x8000, |addr| {
+            code.get((addr - 0x8000) as usize).copied().unwrap_or(0)
+        })
+    }
+
+    #[test]
+    fn call_instruction_lengths() {
+        assert_eq!(return_address(&[0xCD, 0x00, 0x90]), Some(0x8003));
+        assert_eq!(return_address(&[0xDC, 0x00, 0x90]), Some(0x8003));
+        assert_eq!(return_address(&[0xFF]), Some(0x8001));
+        assert_eq!(
+            return_address(&[0xDD, 0xFD, 0xCD, 0x00, 0x90]),
+            Some(0x8005)
+        );
+        assert_eq!(return_address(&[0xC3, 0x00, 0x90]), None);
+        assert_eq!(return_address(&[0xDD, 0x21, 0x00, 0x90]), None);
+    }
+
+    #[test]
+    fn return_instructions() {
+        let is_return = |code: &[u8]| {
+            super::is_return(0x8000, |addr| {
+                code.get((addr - 0x8000) as usize).copied().unwrap_or(0)
+            })
+        };
+        assert!(is_return(&[0xC9]));
+        assert!(is_return(&[0xD8]));
+        assert!(is_return(&[0xED, 0x4D]));
+        assert!(is_return(&[0xED, 0x45]));
+        assert!(is_return(&[0xFD, 0xC9]));
+        assert!(!is_return(&[0xED, 0x44]));
+        assert!(!is_return(&[0xC3, 0x00, 0x90]));
+    }
+}
@@ -0,0 +1,96 @@
+//! Custom traps, which are called before execution of the instruction at
+//! the given address, see [crate::Emulator::set_trap]. Traps can patch the
+//! registers and memory, or replace the whole routine (e.g. to implement
+//! instant loaders or debugging hooks)
+use crate::{emulator::state::CpuRegisters, host::Host, zx::controller::ZXController};
+use alloc::boxed::Box;
+use rustzx_z80::{Z80Bus, Z80};
+
+/// Action performed after the trap callback returns
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrapAction {
+    /// Execute instruction at PC (which may be changed by the callback)
+    Continue,
+    /// Skip the routine at the trap address, returning to the caller as
+    /// `RET` instruction does
+    Skip,
+    /// Same as [TrapAction::Skip], but A register is set to the given value
+    /// first, e.g. to return result of the replaced routine
+    ReplaceWithValueInA(u8),
+}
+
+/// Memory view passed to the trap callback, addresses are mapped as seen by
+/// the CPU
+pub struct TrapMemory<'a> {
+    bus: &'a mut dyn TrapBus,
+    modified: bool,
+}
+
+/// Memory access of the controller without contention, hides its host type
+/// from the callback
+pub(crate) trait TrapBus {
+    fn peek(&self, addr: u16) -> u8;
+    fn poke(&mut self, addr: u16, value: u8);
+}
+
+impl<H: Host> TrapBus for ZXController<H> {
+    fn peek(&self, addr: u16) -> u8 {
+        ZXController::peek(self, addr)
+    }
+
+    fn poke(&mut self, addr: u16, value: u8) {
+        self.write_internal(addr, value);
+    }
+}
+
+impl<'a> TrapMemory<'a> {
+    pub(crate) fn new(bus: &'a mut dyn TrapBus) -> Self {
+        Self {
+            bus,
+            modified: false,
+        }
+    }
+
+    /// Reads memory, including ROM of the paged in interfaces
+    pub fn read(&self, addr: u16) -> u8 {
+        self.bus.peek(addr)
+    }
+
+    /// Writes to memory, ROM is left untouched
+    pub fn write(&mut self, addr: u16, value: u8) {
+        self.bus.poke(addr, value);
+        self.modified = true;
+    }
+
+    pub(crate) fn modified(&self) -> bool {
+        self.modified
+    }
+}
+
+/// Trap callback, which receives CPU registers (PC is set to the trap
+/// address) and memory
+pub type TrapCallback = Box<dyn FnMut(&mut CpuRegisters, &mut TrapMemory) -> TrapAction>;
+
+/// Loads all registers to the CPU
+pub(crate) fn set_cpu_registers(cpu: &mut Z80, registers: &CpuRegisters) {
+    let regs = &mut cpu.regs;
+    regs.set_af(registers.af_alt);
+    regs.swap_af_alt();
+    regs.set_af(registers.af);
+    regs.set_bc(registers.bc_alt);
+    regs.set_de(registers.de_alt);
+    regs.set_hl(registers.hl_alt);
+    regs.exx();
+    regs.set_bc(registers.bc);
+    regs.set_de(registers.de);
+    regs.set_hl(registers.hl);
+    regs.set_ix(registers.ix);
+    regs.set_iy(registers.iy);
+    regs.set_sp(registers.sp);
+    regs.set_pc(registers.pc);
+    regs.set_i(registers.i);
+    regs.set_r(registers.r);
+    regs.set_iff1(registers.iff1);
+    regs.set_iff2(registers.iff2);
+    cpu.set_im(registers.im.min(2));
+}
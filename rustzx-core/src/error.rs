@@ -11,6 +11,27 @@ pub enum Error {
     TapeLoad(TapeLoadError),
     /// Failed to load screen
     ScreenLoad(ScreenLoadError),
+    /// Failed to load snapshot
+    SnapshotLoad(SnapshotLoadError),
+    /// Failed to save snapshot
+    SnapshotSave(SnapshotSaveError),
+    /// Invalid machine specs
+    MachineSpecs(MachineSpecsError),
+    /// Failed to load binary
+    BinaryLoad(BinaryLoadError),
+    /// Loaded media requires other machine
+    MachineMismatch(MachineMismatchError),
+    /// Failed to load disk
+    DiskLoad(DiskLoadError),
+    /// Failed to load cartridge
+    CartridgeLoad(CartridgeLoadError),
+    /// Failed to flush host device
+    #[from_variants(skip)]
+    DeviceFlush(IoError),
+    /// Failed to export BASIC program
+    BasicExport(BasicExportError),
+    /// Failed to boot to BASIC
+    Boot(BootError),
 }
 
 #[derive(Debug, Display)]
@@ -35,6 +56,44 @@ pub enum RomLoadError {
 pub enum TapeLoadError {
     /// Provided tap file is invalid
     InvalidTapFile,
+    /// Tape ends before the end of the block
+    TruncatedBlock,
+}
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum DiskLoadError {
+    /// Disk image size does not match its format
+    InvalidImageSize,
+    /// Disk image header is invalid
+    InvalidImageHeader,
+    /// Disk image format is not supported by the interface
+    UnsupportedFormat,
+    /// Disk image structure is invalid
+    InvalidImage,
+    /// Disk interface is not attached
+    NoDiskInterface,
+    /// Drive with the given number does not exist
+    InvalidDrive,
+    /// Disk in the drive is missing or differs from the disk of the saved state
+    DiskMismatch,
+    /// Saved disk interface state is damaged or has unsupported version
+    InvalidState,
+}
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum CartridgeLoadError {
+    /// Cartridge image contains no banks
+    EmptyImage,
+    /// Image ends before the data of the chunks described by the bank header
+    TruncatedImage,
+    /// Bank header contains unknown bank id
+    InvalidBank,
+    /// Bank header contains unknown chunk type
+    InvalidChunkType,
+    /// Same bank is described more than once
+    DuplicateBank,
+    /// HOME and EXROM banks require Timex machine, which is not emulated
+    UnsupportedBank,
 }
 
 #[derive(Debug, Display)]
@@ -44,3 +103,81 @@ pub enum ScreenLoadError {
     /// Selected machine can't be used to load given screen file
     MachineNotSupported,
 }
+
+#[derive(Debug, Display)]
+pub enum BinaryLoadError {
+    /// Binary does not fit into memory from the load address
+    TooLarge,
+}
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum MachineMismatchError {
+    /// Media requires 48K machine
+    Requires48K,
+    /// Media requires 128K machine
+    Requires128K,
+    /// Media requires +2A/+3 machine
+    RequiresPlus3,
+}
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotLoadError {
+    /// Snapshot header contains invalid interrupt mode
+    InvalidInterruptMode,
+    /// Snapshot is shorter than required by its format
+    TruncatedSnapshot,
+}
+
+#[derive(Debug, Display)]
+pub enum SnapshotSaveError {
+    /// 48K SNA snapshot can't be saved when PC should be pushed to ROM
+    StackInRom,
+}
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum BasicExportError {
+    /// Tape file name should be at most 10 printable ASCII characters
+    InvalidName,
+    /// System variables do not describe a valid program area
+    InvalidProgramArea,
+}
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum BootError {
+    /// Editor of the loaded ROM is unknown
+    UnknownRom,
+    /// ROM did not reach the editor in time
+    Timeout,
+}
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum MachineSpecsError {
+    /// CPU frequency is zero
+    ZeroCpuFrequency,
+    /// Screen area should be 128 clocks (256 pixels) wide and 192 lines high
+    InvalidScreenSize,
+    /// Border is smaller than the border rendered by the emulator
+    BorderTooSmall,
+    /// Line clocks are not equal to the sum of border, screen and retrace clocks
+    InvalidLineClocks,
+    /// Line count is not equal to the sum of border and screen lines
+    InvalidLineCount,
+    /// Frame clocks are not equal to line clocks multiplied by line count
+    InvalidFrameClocks,
+    /// Frame rate does not match CPU frequency and frame clocks
+    InvalidFrameRate,
+    /// First pixel clocks do not leave space for the border in the frame
+    FirstPixelOutOfFrame,
+    /// ULA read, contention and beam origins do not match first pixel clocks
+    InvalidUlaOrigins,
+    /// Interrupt length should be non-zero and shorter than the frame
+    InvalidInterruptLength,
+    /// ROM pages count does not match memory model
+    InvalidRomPages,
+}
+
+#[derive(Debug, Display)]
+pub enum BorderSizeError {
+    /// Requested border is larger than the border rendered by the emulator
+    TooLarge,
+}
@@ -0,0 +1,36 @@
+use crate::error::IoError;
+
+/// Size of the single block device sector in bytes
+pub const SECTOR_SIZE: usize = 512;
+
+/// Sector-based storage for emulated mass storage devices (e.g. IDE hard disk)
+pub trait BlockDevice {
+    /// Returns count of available sectors
+    fn sector_count(&self) -> u32;
+    /// Reads sector with the given LBA address into `buf`
+    fn read_sector(&mut self, lba: u32, buf: &mut [u8; SECTOR_SIZE]) -> Result<(), IoError>;
+    /// Writes sector with the given LBA address from `buf`. Read-only
+    /// implementations should return error
+    fn write_sector(&mut self, lba: u32, buf: &[u8; SECTOR_SIZE]) -> Result<(), IoError>;
+    /// Makes all written sectors persistent, called on emulator shutdown
+    fn flush(&mut self) -> Result<(), IoError> {
+        Ok(())
+    }
+}
+
+/// Block device without any sectors
+pub struct StubBlockDevice;
+
+impl BlockDevice for StubBlockDevice {
+    fn sector_count(&self) -> u32 {
+        0
+    }
+
+    fn read_sector(&mut self, _lba: u32, _buf: &mut [u8; SECTOR_SIZE]) -> Result<(), IoError> {
+        Err(IoError::UnexpectedEof)
+    }
+
+    fn write_sector(&mut self, _lba: u32, _buf: &[u8; SECTOR_SIZE]) -> Result<(), IoError> {
+        Err(IoError::WriteZero)
+    }
+}
@@ -1,14 +1,71 @@
-use crate::zx::video::colors::{ZXBrightness, ZXColor};
+use crate::zx::{
+    constants::{CANVAS_HEIGHT, CANVAS_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH},
+    video::{
+        colors::{ZXBrightness, ZXColor},
+        ulaplus,
+    },
+};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum FrameBufferSource {
     Screen,
     Border,
 }
 
+impl FrameBufferSource {
+    /// Count of the frame buffers of this kind created by the emulator:
+    /// screen is double-buffered, border is not
+    pub const fn buffer_count(self) -> usize {
+        match self {
+            Self::Screen => 2,
+            Self::Border => 1,
+        }
+    }
+
+    /// Dimensions `(width, height)` in pixels of the frame buffers of this
+    /// kind, the same as passed to [FrameBuffer::new] unless the image is
+    /// rotated (see [crate::zx::video::orientation]). Storage required by
+    /// the frame buffer is `width * height * bytes_per_pixel`
+    pub const fn dimensions(self) -> (usize, usize) {
+        match self {
+            Self::Screen => (CANVAS_WIDTH, CANVAS_HEIGHT),
+            Self::Border => (SCREEN_WIDTH, SCREEN_HEIGHT),
+        }
+    }
+}
+
 pub trait FrameBuffer {
     type Context: Clone;
     /// Creates canvas size with required dimensions (`width`, `height`)
     fn new(width: usize, height: usize, source: FrameBufferSource, context: Self::Context) -> Self;
     /// Set `color` with `brightness` for pixel on canvas at (`x`, `y`)
     fn set_color(&mut self, x: usize, y: usize, color: ZXColor, brightness: ZXBrightness);
+    /// Set 8x1 pixels block starting at (`x`, `y`), pixels with set `bitmap`
+    /// bits (from the most significant one) get `ink` color, others `paper`.
+    /// Frame buffers may override it to write the whole block at once
+    fn set_block(
+        &mut self,
+        x: usize,
+        y: usize,
+        bitmap: u8,
+        ink: ZXColor,
+        paper: ZXColor,
+        brightness: ZXBrightness,
+    ) {
+        for pixel in 0..8 {
+            let color = if (bitmap << pixel) & 0x80 != 0 {
+                ink
+            } else {
+                paper
+            };
+            self.set_color(x + pixel, y, color, brightness);
+        }
+    }
+    /// Set ULAplus `color` (GRB 3:3:2, see [ulaplus::grb_to_rgb]) for pixel on
+    /// canvas at (`x`, `y`). Frame buffers without true color support get the
+    /// closest standard color by default
+    fn set_ulaplus_color(&mut self, x: usize, y: usize, color: u8) {
+        let (color, brightness) = ulaplus::nearest_standard_color(color);
+        self.set_color(x, y, color, brightness);
+    }
 }
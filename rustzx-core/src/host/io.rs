@@ -1,5 +1,5 @@
 use crate::error::IoError;
-use core::usize;
+use alloc::vec::Vec;
 
 type Result<T> = core::result::Result<T, IoError>;
 
@@ -49,7 +49,7 @@ impl<T: AsRef<[u8]>> LoadableAsset for BufferCursor<T> {
         let data = self.data.as_ref();
 
         if self.pos >= data.len() {
-            return Err(IoError::UnexpectedEof);
+            return Ok(0);
         }
         let bytes_to_read = buf.len().min(data.len() - self.pos);
         buf[0..bytes_to_read].copy_from_slice(&data[self.pos..self.pos + bytes_to_read]);
@@ -110,6 +110,19 @@ pub trait DataRecorder {
     }
 }
 
+impl<R: DataRecorder + ?Sized> DataRecorder for &mut R {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+}
+
+impl DataRecorder for Vec<u8> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,9 +1,13 @@
+mod block_device;
 mod frame_buffer;
 mod io;
+mod network;
 
+pub use block_device::{BlockDevice, StubBlockDevice, SECTOR_SIZE};
 pub use core::time::Duration;
 pub use frame_buffer::{FrameBuffer, FrameBufferSource};
 pub use io::{BufferCursor, DataRecorder, LoadableAsset, SeekFrom, SeekableAsset};
+pub use network::{NetworkStack, SocketHandle, StubNetworkStack};
 
 pub trait Stopwatch {
     fn new() -> Self;
@@ -15,6 +19,13 @@ pub enum Snapshot<LoadableAssetImpl: LoadableAsset> {
     // TODO(#55): Implement SLT snapshot format support
 }
 
+impl<T: AsRef<[u8]>> Snapshot<BufferCursor<T>> {
+    /// SNA snapshot held in memory
+    pub fn sna_from_bytes(data: T) -> Self {
+        Self::Sna(BufferCursor::new(data))
+    }
+}
+
 pub enum SnapshotRecorder<DataRecorderImpl: DataRecorder> {
     Sna(DataRecorderImpl),
 }
@@ -24,10 +35,46 @@ pub enum Tape<LoadableAssetImpl: LoadableAsset> {
     // TODO(#56): Implement TZX tape format support
 }
 
+impl<T: AsRef<[u8]>> Tape<BufferCursor<T>> {
+    /// TAP tape held in memory. Tape stays inserted after loading, so the
+    /// data should be owned (e.g. `Vec<u8>` or `&'static [u8]`) and
+    /// convertible to [Host::TapeAsset]
+    pub fn tap_from_bytes(data: T) -> Self {
+        Self::Tap(BufferCursor::new(data))
+    }
+}
+
+/// Floppy disk image
+pub enum Disk<LoadableAssetImpl: LoadableAsset> {
+    /// +D/DISCiPLE image with sides of each track following each other
+    Mgt(LoadableAssetImpl),
+    /// +D/DISCiPLE image with all tracks of side 0 followed by side 1
+    Img(LoadableAssetImpl),
+    /// TR-DOS sector dump, may be truncated after the last used track
+    Trd(LoadableAssetImpl),
+    /// TR-DOS file archive, files are written to the empty 80-track disk
+    Scl(LoadableAssetImpl),
+    /// Ultra Disk Image with raw track contents, preserves nonstandard
+    /// sector layouts and CRC errors of copy-protected disks
+    Udi(LoadableAssetImpl),
+}
+
+/// Timex TC2068/TS2068 cartridge
+pub enum Cartridge<LoadableAssetImpl: LoadableAsset> {
+    Dck(LoadableAssetImpl),
+}
+
 pub enum Screen<LoadableAssetImpl: LoadableAsset> {
     Scr(LoadableAssetImpl),
 }
 
+impl<T: AsRef<[u8]>> Screen<BufferCursor<T>> {
+    /// SCR screen held in memory
+    pub fn scr_from_bytes(data: T) -> Self {
+        Self::Scr(BufferCursor::new(data))
+    }
+}
+
 pub enum RomFormat {
     Binary16KPages,
 }
@@ -81,6 +128,9 @@ impl IoExtender for StubIoExtender {
 pub trait DebugInterface {
     /// Returns true if breakpoint at given address is set and emulation should be stopped
     fn check_pc_breakpoint(&mut self, addr: u16) -> bool;
+    /// Called when the program becomes idle or active again, see
+    /// [crate::idle]
+    fn idle_changed(&mut self, _idle: bool) {}
 }
 
 /// Debug interface which does nothing
@@ -108,4 +158,10 @@ pub trait Host {
     type IoExtender: IoExtender;
     /// Debug interface logic (e.g. breakpoints)
     type DebugInterface: DebugInterface;
+    /// Storage for the hard disk attached to IDE interface, hosts built
+    /// without `ide` feature can use [StubBlockDevice]
+    type BlockDevice: BlockDevice;
+    /// Host network access for Spectranet interface, hosts built without
+    /// `spectranet` feature can use [StubNetworkStack]
+    type NetworkStack: NetworkStack;
 }
@@ -0,0 +1,89 @@
+use crate::error::IoError;
+
+/// Host socket identifier, assigned by [NetworkStack] implementation
+pub type SocketHandle = usize;
+
+/// Host network access for emulated network interfaces (e.g. Spectranet).
+/// All operations should be non-blocking, except connection establishment
+pub trait NetworkStack {
+    /// Opens TCP connection to the given remote address
+    fn tcp_connect(&mut self, ip: [u8; 4], port: u16) -> Result<SocketHandle, IoError>;
+    /// Starts listening for the single incoming TCP connection on the given port
+    fn tcp_listen(&mut self, port: u16) -> Result<SocketHandle, IoError>;
+    /// Checks listening socket for the incoming connection. Returns true when
+    /// connection was accepted, after that socket is used for data transfer
+    fn tcp_accept(&mut self, socket: SocketHandle) -> Result<bool, IoError>;
+    /// Opens UDP socket bound to the given local port
+    fn udp_bind(&mut self, port: u16) -> Result<SocketHandle, IoError>;
+    /// Sends data over the connected TCP socket
+    fn send(&mut self, socket: SocketHandle, data: &[u8]) -> Result<(), IoError>;
+    /// Sends UDP datagram to the given remote address
+    fn send_to(
+        &mut self,
+        socket: SocketHandle,
+        ip: [u8; 4],
+        port: u16,
+        data: &[u8],
+    ) -> Result<(), IoError>;
+    /// Receives data from TCP socket. Returns `None` if no data is available
+    /// yet and `Some(0)` if connection was closed by the remote side
+    fn recv(&mut self, socket: SocketHandle, buf: &mut [u8]) -> Result<Option<usize>, IoError>;
+    /// Receives UDP datagram, returns its size and source address. Returns
+    /// `None` if no datagram is available yet
+    fn recv_from(
+        &mut self,
+        socket: SocketHandle,
+        buf: &mut [u8],
+    ) -> Result<Option<(usize, [u8; 4], u16)>, IoError>;
+    /// Closes socket, handle becomes invalid
+    fn close(&mut self, socket: SocketHandle);
+}
+
+/// Network stack without connectivity, all socket operations fail
+pub struct StubNetworkStack;
+
+impl NetworkStack for StubNetworkStack {
+    fn tcp_connect(&mut self, _ip: [u8; 4], _port: u16) -> Result<SocketHandle, IoError> {
+        Err(IoError::HostAssetImplFailed)
+    }
+
+    fn tcp_listen(&mut self, _port: u16) -> Result<SocketHandle, IoError> {
+        Err(IoError::HostAssetImplFailed)
+    }
+
+    fn tcp_accept(&mut self, _socket: SocketHandle) -> Result<bool, IoError> {
+        Err(IoError::HostAssetImplFailed)
+    }
+
+    fn udp_bind(&mut self, _port: u16) -> Result<SocketHandle, IoError> {
+        Err(IoError::HostAssetImplFailed)
+    }
+
+    fn send(&mut self, _socket: SocketHandle, _data: &[u8]) -> Result<(), IoError> {
+        Err(IoError::HostAssetImplFailed)
+    }
+
+    fn send_to(
+        &mut self,
+        _socket: SocketHandle,
+        _ip: [u8; 4],
+        _port: u16,
+        _data: &[u8],
+    ) -> Result<(), IoError> {
+        Err(IoError::HostAssetImplFailed)
+    }
+
+    fn recv(&mut self, _socket: SocketHandle, _buf: &mut [u8]) -> Result<Option<usize>, IoError> {
+        Err(IoError::HostAssetImplFailed)
+    }
+
+    fn recv_from(
+        &mut self,
+        _socket: SocketHandle,
+        _buf: &mut [u8],
+    ) -> Result<Option<(usize, [u8; 4], u16)>, IoError> {
+        Err(IoError::HostAssetImplFailed)
+    }
+
+    fn close(&mut self, _socket: SocketHandle) {}
+}
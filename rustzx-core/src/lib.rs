@@ -1,5 +1,7 @@
 #![no_std]
 
+#[macro_use]
+pub(crate) mod diagnostics;
 pub(crate) mod emulator;
 pub(crate) mod settings;
 pub(crate) mod utils;
@@ -8,10 +10,15 @@ pub mod error;
 pub mod host;
 pub mod zx;
 
-pub use emulator::{poke, EmulationInfo, EmulationStopReason, Emulator};
+pub use emulator::{
+    idle, input, perf, poke, search, state, trap, BenchmarkReport, BoundedResult, EmulationInfo,
+    EmulationStopReason, Emulator, FrameOutput, ReloadMode, VideoParams,
+};
 pub use settings::RustzxSettings;
 pub use utils::EmulationMode;
 
+#[cfg(feature = "opcode-coverage")]
+pub use rustzx_z80::OpcodeCoverage;
 #[cfg(feature = "strum")]
 pub use strum::IntoEnumIterator as IterableEnum;
 
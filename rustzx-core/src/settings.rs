@@ -1,18 +1,65 @@
-use crate::{utils::EmulationMode, zx::machine::ZXMachine};
+use crate::{
+    utils::EmulationMode,
+    zx::{
+        machine::{MachinePolicy, Timings, UlaIssue, ZXMachine},
+        mouse::kempston::KempstonMouseConfig,
+        video::orientation::Orientation,
+    },
+};
 
 #[cfg(all(feature = "sound", feature = "ay"))]
 use crate::zx::sound::ay::ZXAYMode;
+#[cfg(feature = "sound")]
+use crate::zx::sound::filter::SoundFilters;
 
 pub struct RustzxSettings {
     pub machine: ZXMachine,
+    /// Action taken when a snapshot or tape requires other machine
+    pub machine_policy: MachinePolicy,
+    /// ULA timings variant, only 48K machine has the early one
+    pub timings: Timings,
+    /// Board revision of the 48K machine, defines EAR input level when no
+    /// tape is playing
+    pub ula_issue: UlaIssue,
+    /// Overrides length of the ULA interrupt pulse in clocks, interrupt is
+    /// accepted only if it is enabled while the pulse is active. `None`
+    /// uses the machine default (32 clocks on 48K, 36 on 128K)
+    pub interrupt_length: Option<usize>,
     pub emulation_mode: EmulationMode,
     pub tape_fastload_enabled: bool,
     pub kempston_enabled: bool,
+    pub fuller_enabled: bool,
     pub mouse_enabled: bool,
+    /// Sensitivity and axis directions of the Kempston mouse
+    pub mouse_config: KempstonMouseConfig,
+    /// Emulates ghost key presses of the diode-less keyboard matrix: when
+    /// three pressed keys form an L-shape across rows and columns, the key
+    /// at the fourth corner reads as pressed too
+    pub keyboard_ghosting: bool,
+    /// Enables ULAplus palette extension (64 colors from 256), see
+    /// [crate::zx::video::ulaplus]
+    pub ulaplus_enabled: bool,
+    /// Rotation and mirroring of the screen and border frame buffers, see
+    /// [crate::zx::video::orientation]
+    pub orientation: Orientation,
+    /// Draws paper with the subtle per-color pattern of pixels with the
+    /// flipped brightness, so colors stay distinguishable for color-blind
+    /// users and in grayscale. Ink pixels and ULAplus colors are not affected
+    pub paper_patterns: bool,
+    /// Fills RAM with pseudo-random values generated from the given seed on
+    /// power on, as real hardware does not start with zeroed RAM. `None` gives
+    /// zeroed RAM. Both options are reproducible
+    pub ram_init_seed: Option<u64>,
     #[cfg(all(feature = "sound", feature = "ay"))]
     pub ay_mode: ZXAYMode,
+    /// Mixes AY output into the sound. On 48K machines, which have no
+    /// built-in AY, it also attaches the AY chip to its ports
     #[cfg(all(feature = "sound", feature = "ay"))]
     pub ay_enabled: bool,
+    /// Enables AY chip of the Fuller Box on ports 0x3F and 0x5F, see
+    /// [crate::zx::joy::fuller]. Fuller joystick is enabled separately
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub fuller_ay_enabled: bool,
     #[cfg(feature = "sound")]
     pub beeper_enabled: bool,
     #[cfg(feature = "sound")]
@@ -21,6 +68,10 @@ pub struct RustzxSettings {
     pub sound_volume: u8,
     #[cfg(feature = "sound")]
     pub sound_sample_rate: usize,
+    /// Output filters applied to the mixed sound, see
+    /// [crate::zx::sound::filter]
+    #[cfg(feature = "sound")]
+    pub sound_filters: SoundFilters,
     #[cfg(feature = "embedded-roms")]
     pub load_default_rom: bool,
     #[cfg(feature = "autoload")]
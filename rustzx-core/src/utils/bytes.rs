@@ -0,0 +1,137 @@
+//! Little-endian encoding of the device states which snapshot formats have
+//! no place for, see [crate::zx::PlusDState::save]. Each state starts with
+//! the magic and format version, and ends with CRC32 of the preceding data
+use crate::{host::LoadableAsset, utils::crc::crc32};
+use alloc::vec::Vec;
+
+#[derive(Default)]
+pub(crate) struct ByteWriter {
+    data: Vec<u8>,
+}
+
+impl ByteWriter {
+    /// Starts the state with the given magic and format version
+    pub fn with_header(magic: &[u8; 4], version: u8) -> Self {
+        let mut writer = Self::default();
+        writer.raw(magic);
+        writer.u8(version);
+        writer
+    }
+
+    pub fn raw(&mut self, data: &[u8]) {
+        self.data.extend_from_slice(data);
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.raw(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.raw(&value.to_le_bytes());
+    }
+
+    /// Writes sizes and offsets, which never exceed `u32` range
+    pub fn usize(&mut self, value: usize) {
+        self.u32(value as u32);
+    }
+
+    /// Writes length-prefixed data
+    pub fn bytes(&mut self, data: &[u8]) {
+        self.usize(data.len());
+        self.raw(data);
+    }
+
+    /// Returns the data followed by its CRC32
+    pub fn into_sealed(self) -> Vec<u8> {
+        let mut data = self.data;
+        let crc = crc32(&data);
+        data.extend_from_slice(&crc.to_le_bytes());
+        data
+    }
+}
+
+/// Reads data written by [ByteWriter], each method returns `None` if the
+/// data ends before the value or the value is invalid
+pub(crate) struct ByteReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    /// Returns reader of the state written via [ByteWriter::with_header] and
+    /// [ByteWriter::into_sealed], positioned after the header. `None` is
+    /// returned if CRC32, magic or version does not match
+    pub fn unseal(data: &'a [u8], magic: &[u8; 4], version: u8) -> Option<Self> {
+        let (data, crc) = data.split_at_checked(data.len().checked_sub(4)?)?;
+        if crc32(data).to_le_bytes() != crc {
+            return None;
+        }
+        let mut reader = Self { data };
+        if reader.raw(magic.len())? != magic || reader.u8()? != version {
+            return None;
+        }
+        Some(reader)
+    }
+
+    pub fn raw(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.data.len() {
+            return None;
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Some(head)
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.raw(1).map(|bytes| bytes[0])
+    }
+
+    pub fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        self.raw(4)?.try_into().ok().map(u32::from_le_bytes)
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        self.raw(8)?.try_into().ok().map(u64::from_le_bytes)
+    }
+
+    pub fn usize(&mut self) -> Option<usize> {
+        self.u32().map(|value| value as usize)
+    }
+
+    pub fn bytes(&mut self) -> Option<Vec<u8>> {
+        let len = self.usize()?;
+        self.raw(len).map(<[u8]>::to_vec)
+    }
+
+    /// Returns true if all data was read
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+/// Reads the whole asset
+pub(crate) fn read_to_end(mut asset: impl LoadableAsset) -> crate::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut chunk = [0; 1024];
+    loop {
+        match asset.read(&mut chunk)? {
+            0 => return Ok(data),
+            count => data.extend_from_slice(&chunk[..count]),
+        }
+    }
+}
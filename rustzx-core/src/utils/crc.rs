@@ -0,0 +1,51 @@
+//! Checksums of ROM, disk images and on-disk fields
+
+/// Calculates CRC32 (IEEE 802.3, same as in ZIP files) of the data
+pub fn crc32(data: &[u8]) -> u32 {
+    const POLY: u32 = 0xEDB88320;
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Calculates CRC-CCITT (polynomial 0x1021, initial value 0xFFFF), as used
+/// by WD177x/WD179x floppy controllers for address and data fields
+#[cfg(any(feature = "plusd", feature = "betadisk"))]
+pub fn crc16<'a>(data: impl IntoIterator<Item = &'a u8>) -> u16 {
+    data.into_iter().fold(0xFFFF, |crc, byte| {
+        (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    #[cfg(any(feature = "plusd", feature = "betadisk"))]
+    fn crc16_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(crc16(&[]), 0xFFFF);
+    }
+}
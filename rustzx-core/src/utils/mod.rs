@@ -1,9 +1,27 @@
 //! Some emulator-related utils
 
+use alloc::boxed::Box;
+
+#[cfg(any(feature = "plusd", feature = "betadisk"))]
+pub(crate) mod bytes;
+pub(crate) mod crc;
 pub mod screen;
 
 #[derive(Copy, Clone)]
 pub enum EmulationMode {
     FrameCount(usize),
     Max,
+    /// Slow motion, single frame is emulated per each `n` emulation calls,
+    /// other calls return immediately. Sound is muted in this mode
+    SlowMotion(usize),
+}
+
+/// Allocates array filled with `value` directly on the heap. Unlike
+/// `Box::new([value; N])` this never places the array on the stack, which
+/// is important for embedded targets with small stacks
+pub(crate) fn boxed_array<T: Clone, const N: usize>(value: T) -> Box<[T; N]> {
+    match alloc::vec![value; N].into_boxed_slice().try_into() {
+        Ok(array) => array,
+        Err(_) => unreachable!(),
+    }
 }
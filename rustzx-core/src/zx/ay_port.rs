@@ -0,0 +1,45 @@
+//! Devices attached to the I/O port A of the AY chip. On 128K models the port
+//! is shared by the RS232 port and the keypad socket, each device drives its
+//! own input lines of the port
+use crate::zx::{keypad::Keypad, rs232::Rs232Port};
+
+/// Device connected to the lines of the AY port A. Times are in CPU clocks
+/// relative to the current frame start
+pub(crate) trait PortADevice {
+    /// Handles write to the port register
+    fn write_port_a(&mut self, clocks: usize, value: u8);
+    /// Returns port value with the device input lines applied to `value`
+    fn read_port_a(&mut self, clocks: usize, value: u8) -> u8;
+    /// Finishes the frame of `frame_clocks` length
+    fn new_frame(&mut self, _frame_clocks: usize) {}
+}
+
+/// Devices attached to the AY port A
+#[derive(Default)]
+pub(crate) struct PortADevices {
+    pub rs232: Option<Rs232Port>,
+    pub keypad: Option<Keypad>,
+}
+
+impl PortADevices {
+    fn devices(&mut self) -> impl Iterator<Item = &mut dyn PortADevice> {
+        let rs232 = self.rs232.iter_mut().map(|d| d as &mut dyn PortADevice);
+        let keypad = self.keypad.iter_mut().map(|d| d as &mut dyn PortADevice);
+        rs232.chain(keypad)
+    }
+
+    pub fn write(&mut self, clocks: usize, value: u8) {
+        self.devices()
+            .for_each(|device| device.write_port_a(clocks, value));
+    }
+
+    pub fn read(&mut self, clocks: usize, latched: u8) -> u8 {
+        self.devices()
+            .fold(latched, |value, device| device.read_port_a(clocks, value))
+    }
+
+    pub fn new_frame(&mut self, frame_clocks: usize) {
+        self.devices()
+            .for_each(|device| device.new_frame(frame_clocks));
+    }
+}
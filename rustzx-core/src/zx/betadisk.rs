@@ -0,0 +1,346 @@
+//! Beta Disk (Beta 128) interface with TR-DOS. When paged in, 16K TR-DOS ROM
+//! replaces the lower 16K of the address space. It is paged in by the
+//! instruction fetch from `0x3D00..0x3E00` while 48 BASIC ROM is active, and
+//! paged out by the instruction fetch from RAM (0x4000 and above). Ports
+//! (only the low address byte is decoded) are active only while TR-DOS is
+//! paged in, so they do not clash with the Kempston joystick and the Fuller
+//! Box ones and the interface is not reported as a port conflict:
+//! - `0x1F`, `0x3F`, `0x5F`, `0x7F` - WD1793 status/command, track, sector
+//!   and data registers
+//! - `0xFF` - system register: bits 0 and 1 select drive, bit 2 resets the
+//!   controller when low, bit 4 selects side 0 when set. On read bit 6 is
+//!   DRQ and bit 7 is INTRQ of the controller
+use crate::{
+    diagnostics::DISK,
+    error::DiskLoadError,
+    host::{DataRecorder, LoadableAsset},
+    utils::bytes::{read_to_end, ByteReader, ByteWriter},
+    zx::{
+        fdc::{disk::FloppyDisk, FdcModel, Wd17xx, Wd17xxState},
+        storage::{DriveStatus, BETADISK_DRIVE_COUNT},
+    },
+};
+use alloc::{vec, vec::Vec};
+
+pub(crate) const ROM_SIZE: usize = 0x4000;
+pub(crate) const DRIVE_COUNT: usize = BETADISK_DRIVE_COUNT;
+
+const PORT_SYSTEM: u8 = 0xFF;
+
+const SYSTEM_DRIVE_MASK: u8 = 0x03;
+const SYSTEM_NOT_RESET: u8 = 0x04;
+const SYSTEM_SIDE_0: u8 = 0x10;
+const SYSTEM_DRQ: u8 = 0x40;
+const SYSTEM_INTRQ: u8 = 0x80;
+
+/// TR-DOS entry points in 48 BASIC ROM address space
+const TRDOS_ENTRY: core::ops::RangeInclusive<u16> = 0x3D00..=0x3DFF;
+const RAM_START: u16 = 0x4000;
+
+/// Identifies serialized [BetaDiskState]
+const STATE_MAGIC: &[u8; 4] = b"RZBD";
+const STATE_VERSION: u8 = 2;
+
+pub(crate) struct BetaDisk {
+    rom: Vec<u8>,
+    fdc: Wd17xx,
+    paged_in: bool,
+    system: u8,
+}
+
+/// Beta Disk interface state which snapshot formats have no place for, see
+/// [crate::Emulator::betadisk_state]. Contents are opaque; disks are
+/// identified by the CRC32 of the inserted image, sectors changed since the
+/// insertion are stored in the state. State can be stored as bytes via
+/// [BetaDiskState::save]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BetaDiskState {
+    paged_in: bool,
+    system: u8,
+    fdc: Wd17xxState,
+}
+
+impl BetaDisk {
+    /// Creates interface with the given TR-DOS ROM image (up to 16K),
+    /// TR-DOS is paged out
+    pub fn new(rom: &[u8], freq_cpu: usize) -> Self {
+        let mut rom_data = vec![0xFF; ROM_SIZE];
+        let size = rom.len().min(ROM_SIZE);
+        rom_data[..size].copy_from_slice(&rom[..size]);
+        let mut betadisk = Self {
+            rom: rom_data,
+            fdc: Wd17xx::new(FdcModel::Wd1793, DRIVE_COUNT, freq_cpu),
+            paged_in: false,
+            system: 0,
+        };
+        betadisk.write_system(SYSTEM_NOT_RESET | SYSTEM_SIDE_0);
+        betadisk
+    }
+
+    pub fn paged_in(&self) -> bool {
+        self.paged_in
+    }
+
+    /// Returns true if port belongs to the interface, ports are decoded
+    /// only while TR-DOS is paged in
+    pub fn handles_port(&self, port: u16) -> bool {
+        self.paged_in
+            && matches!(
+                port.to_le_bytes()[0],
+                0x1F | 0x3F | 0x5F | 0x7F | PORT_SYSTEM
+            )
+    }
+
+    /// Returns WD1793 register of the port, register is selected by A5 and A6
+    fn fdc_register(port: u8) -> Option<u8> {
+        (port != PORT_SYSTEM).then_some((port >> 5) & 0x03)
+    }
+
+    pub fn set_paged_in(&mut self, value: bool) {
+        if self.paged_in != value {
+            diag!(
+                trace,
+                DISK,
+                "TR-DOS paged {}",
+                if value { "in" } else { "out" }
+            );
+        }
+        self.paged_in = value;
+    }
+
+    /// Processes instruction fetch address, should be called before each
+    /// instruction fetch. `basic_rom` is true when 48 BASIC ROM is mapped
+    /// at 0x0000
+    pub fn check_fetch(&mut self, addr: u16, basic_rom: bool) {
+        if TRDOS_ENTRY.contains(&addr) && basic_rom {
+            self.set_paged_in(true);
+        } else if addr >= RAM_START {
+            self.set_paged_in(false);
+        }
+    }
+
+    /// Pages TR-DOS out on the machine reset
+    pub fn reset(&mut self) {
+        self.set_paged_in(false);
+    }
+
+    /// Changes CPU frequency of the machine, which disk timings in clocks
+    /// depend on
+    pub fn set_freq_cpu(&mut self, freq_cpu: usize) {
+        self.fdc.set_freq_cpu(freq_cpu);
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        self.rom[addr as usize]
+    }
+
+    pub fn read_port(&mut self, port: u16, clocks: usize) -> u8 {
+        let [low, _] = port.to_le_bytes();
+        if let Some(reg) = Self::fdc_register(low) {
+            return self.fdc.read(reg, clocks);
+        }
+        let mut value = !(SYSTEM_DRQ | SYSTEM_INTRQ);
+        if self.fdc.drq() {
+            value |= SYSTEM_DRQ;
+        }
+        if self.fdc.intrq(clocks) {
+            value |= SYSTEM_INTRQ;
+        }
+        value
+    }
+
+    pub fn write_port(&mut self, port: u16, data: u8, clocks: usize) {
+        let [low, _] = port.to_le_bytes();
+        match Self::fdc_register(low) {
+            Some(reg) => self.fdc.write(reg, data, clocks),
+            None => self.write_system(data),
+        }
+    }
+
+    fn write_system(&mut self, data: u8) {
+        self.fdc
+            .select_drive(Some((data & SYSTEM_DRIVE_MASK) as usize));
+        self.fdc.set_side((data & SYSTEM_SIDE_0 == 0) as u8);
+        if data & SYSTEM_NOT_RESET == 0 {
+            self.fdc.reset();
+        }
+        self.system = data;
+    }
+
+    /// Finishes the frame of `frame_clocks` length
+    pub fn new_frame(&mut self, frame_clocks: usize) {
+        self.fdc.new_frame(frame_clocks);
+    }
+
+    /// Returns status of the drive (0 to 3) at the end of the last frame
+    pub fn drive_status(&self, drive: usize) -> Option<DriveStatus> {
+        self.fdc.drive_status(drive)
+    }
+
+    /// Inserts disk into the drive (0 to 3), returns previous disk
+    pub fn insert_disk(&mut self, drive: usize, disk: FloppyDisk) -> Option<FloppyDisk> {
+        self.fdc.drive_mut(drive)?.disk.replace(disk)
+    }
+
+    pub fn eject_disk(&mut self, drive: usize) -> Option<FloppyDisk> {
+        self.fdc.drive_mut(drive)?.disk.take()
+    }
+
+    pub fn disk_mut(&mut self, drive: usize) -> Option<&mut FloppyDisk> {
+        self.fdc.drive_mut(drive)?.disk.as_mut()
+    }
+
+    pub fn state(&self) -> BetaDiskState {
+        BetaDiskState {
+            paged_in: self.paged_in,
+            system: self.system,
+            fdc: self.fdc.state(),
+        }
+    }
+
+    /// Restores state, interface is left unchanged if disks in the drives
+    /// do not match the disks of the state
+    pub fn restore(&mut self, state: &BetaDiskState) -> Result<(), DiskLoadError> {
+        self.fdc.restore(&state.fdc)?;
+        self.paged_in = state.paged_in;
+        self.system = state.system;
+        Ok(())
+    }
+}
+
+impl BetaDiskState {
+    /// Writes the state to `recorder`, it is read back with
+    /// [BetaDiskState::load]
+    pub fn save(&self, mut recorder: impl DataRecorder) -> crate::Result<()> {
+        let mut writer = ByteWriter::with_header(STATE_MAGIC, STATE_VERSION);
+        writer.bool(self.paged_in);
+        writer.u8(self.system);
+        self.fdc.write(&mut writer);
+        recorder.write_all(&writer.into_sealed())?;
+        Ok(())
+    }
+
+    /// Reads the state written by [BetaDiskState::save], damaged data is
+    /// rejected with [DiskLoadError::InvalidState]
+    pub fn load(asset: impl LoadableAsset) -> crate::Result<Self> {
+        let data = read_to_end(asset)?;
+        Self::parse(&data).ok_or_else(|| DiskLoadError::InvalidState.into())
+    }
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader::unseal(data, STATE_MAGIC, STATE_VERSION)?;
+        let state = Self {
+            paged_in: reader.bool()?,
+            system: reader.u8()?,
+            fdc: Wd17xxState::read(&mut reader)?,
+        };
+        reader.is_empty().then_some(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::Error, host::BufferCursor, zx::fdc::trd::trd_disk};
+
+    fn betadisk() -> BetaDisk {
+        let rom = (0..ROM_SIZE)
+            .map(|idx| (idx >> 8) as u8)
+            .collect::<Vec<_>>();
+        BetaDisk::new(&rom, 3_500_000)
+    }
+
+    /// TRD image with each sector filled with its logical index
+    fn disk() -> FloppyDisk {
+        let data = (0..80 * 2 * 16 * 256)
+            .map(|idx| (idx / 256) as u8)
+            .collect();
+        trd_disk(data).unwrap()
+    }
+
+    #[test]
+    fn paging_and_ports() {
+        let mut betadisk = betadisk();
+        assert!(!betadisk.handles_port(0x1F));
+        betadisk.check_fetch(0x3D2F, false);
+        assert!(!betadisk.paged_in(), "48 BASIC ROM is not active");
+        betadisk.check_fetch(0x3D2F, true);
+        assert!(betadisk.paged_in());
+        assert_eq!(betadisk.read(0x3D2F), 0x3D);
+        betadisk.check_fetch(0x0100, false);
+        assert!(betadisk.paged_in(), "TR-DOS ROM calls stay paged in");
+        for port in [0x1F, 0x3F, 0x5F, 0x7F, 0xFF, 0x12FF] {
+            assert!(betadisk.handles_port(port));
+        }
+        assert!(!betadisk.handles_port(0xFE));
+        betadisk.check_fetch(0x4000, true);
+        assert!(!betadisk.paged_in());
+        assert!(!betadisk.handles_port(0x1F));
+    }
+
+    #[test]
+    fn sector_read_via_ports() {
+        let mut betadisk = betadisk();
+        assert!(betadisk.insert_disk(1, disk()).is_none());
+        betadisk.check_fetch(0x3D00, true);
+        // Drive B, side 1
+        betadisk.write_port(0xFF, SYSTEM_NOT_RESET | 0x01, 0);
+        betadisk.write_port(0x7F, 3, 0);
+        betadisk.write_port(0x1F, 0x18, 0);
+        assert_eq!(betadisk.read_port(0x3F, 0), 3);
+        assert_eq!(betadisk.read_port(0xFF, 0) & SYSTEM_INTRQ, SYSTEM_INTRQ);
+        betadisk.write_port(0x5F, 2, 0);
+        betadisk.write_port(0x1F, 0x80, 0);
+        assert_eq!(
+            betadisk.read_port(0xFF, 0) & (SYSTEM_DRQ | SYSTEM_INTRQ),
+            SYSTEM_DRQ
+        );
+        // Logical track 7 (cylinder 3, side 1), sector 2
+        assert_eq!(betadisk.read_port(0x7F, 0), 7 * 16 + 1);
+
+        // Reset aborts the transfer
+        betadisk.write_port(0xFF, 0x01, 0);
+        assert_eq!(
+            betadisk.read_port(0xFF, 0) & (SYSTEM_DRQ | SYSTEM_INTRQ),
+            SYSTEM_INTRQ
+        );
+        assert!(betadisk.eject_disk(1).is_some());
+        assert!(betadisk.eject_disk(4).is_none());
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let mut betadisk = betadisk();
+        betadisk.insert_disk(0, disk());
+        betadisk.check_fetch(0x3D13, true);
+        // Multi-sector read in progress
+        betadisk.write_port(0x5F, 1, 0);
+        betadisk.write_port(0x1F, 0x90, 0);
+        for _ in 0..300 {
+            betadisk.read_port(0x7F, 0);
+        }
+        let state = betadisk.state();
+
+        let mut bytes = Vec::new();
+        state.save(&mut bytes).unwrap();
+        assert_eq!(
+            BetaDiskState::load(BufferCursor::new(&bytes)).unwrap(),
+            state
+        );
+
+        let mut restored = BetaDisk::new(&[], 3_500_000);
+        assert_eq!(restored.restore(&state), Err(DiskLoadError::DiskMismatch));
+        assert!(!restored.paged_in());
+        restored.insert_disk(0, disk());
+        restored.restore(&state).unwrap();
+        assert!(restored.paged_in());
+        assert_eq!(restored.read_port(0x7F, 0), betadisk.read_port(0x7F, 0));
+        assert_eq!(restored.state(), betadisk.state());
+
+        bytes[6] ^= 0x01;
+        assert!(matches!(
+            BetaDiskState::load(BufferCursor::new(&bytes)),
+            Err(Error::DiskLoad(DiskLoadError::InvalidState))
+        ));
+    }
+}
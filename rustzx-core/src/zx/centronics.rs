@@ -0,0 +1,96 @@
+//! +2A/+3 Centronics parallel printer port. Data lines are latched by a
+//! write to `0x0FFD`, the printer takes the byte on the falling edge of the
+//! strobe (bit 4 of `0x1FFD`) and then reports BUSY (bit 0 of `0x0FFD`
+//! read) while it processes the byte. Software waits for BUSY to go low
+//! before sending the next byte
+use crate::diagnostics::PRINTER;
+use alloc::vec::Vec;
+
+/// Printer processes a byte in about 10 us after the strobe
+const BUSY_PER_SECOND: i64 = 100_000;
+
+/// Printer attached to the parallel port. Times are in CPU clocks relative
+/// to the current frame start
+pub(crate) struct CentronicsPort {
+    data: u8,
+    strobe: bool,
+    busy_clocks: i64,
+    /// End time of the BUSY signal for the last taken byte
+    busy_until: i64,
+    output: Vec<u8>,
+}
+
+impl CentronicsPort {
+    pub fn new(freq_cpu: usize) -> Self {
+        Self {
+            data: 0,
+            strobe: false,
+            busy_clocks: (freq_cpu as i64 / BUSY_PER_SECOND).max(1),
+            busy_until: 0,
+            output: Vec::new(),
+        }
+    }
+
+    pub fn write_data(&mut self, data: u8) {
+        self.data = data;
+    }
+
+    /// Sets strobe line level, printer takes the byte on the falling edge.
+    /// Bytes strobed while the printer is busy are still taken, as printers
+    /// have input buffers
+    pub fn set_strobe(&mut self, strobe: bool, clocks: usize) {
+        if self.strobe && !strobe {
+            if self.busy(clocks) {
+                diag!(debug, PRINTER, "byte {:#04X} strobed while busy", self.data);
+            }
+            self.output.push(self.data);
+            self.busy_until = clocks as i64 + self.busy_clocks;
+        }
+        self.strobe = strobe;
+    }
+
+    pub fn busy(&self, clocks: usize) -> bool {
+        (clocks as i64) < self.busy_until
+    }
+
+    /// Finishes the frame of `frame_clocks` length
+    pub fn new_frame(&mut self, frame_clocks: usize) {
+        self.busy_until = (self.busy_until - frame_clocks as i64).max(0);
+    }
+
+    /// Returns bytes taken by the printer since the last call
+    pub fn take_output(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_taken_on_strobe_falling_edge() {
+        let mut port = CentronicsPort::new(3_500_000);
+        port.write_data(b'A');
+        port.set_strobe(false, 0);
+        assert!(port.take_output().is_empty());
+        port.set_strobe(true, 10);
+        assert!(port.take_output().is_empty());
+        port.set_strobe(false, 20);
+        assert_eq!(port.take_output(), b"A");
+
+        // Printer is busy for 10 us
+        assert!(port.busy(20));
+        assert!(port.busy(54));
+        assert!(!port.busy(55));
+        port.write_data(b'B');
+        port.set_strobe(true, 30);
+        port.set_strobe(false, 40);
+        assert_eq!(port.take_output(), b"B");
+
+        // Busy time continues in the next frame
+        port.new_frame(50);
+        assert!(port.busy(24));
+        assert!(!port.busy(25));
+    }
+}
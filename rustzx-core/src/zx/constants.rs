@@ -8,7 +8,7 @@ pub const CANVAS_Y: usize = 24;
 /// canvas (emulated screen) constants
 pub const SCREEN_WIDTH: usize = CANVAS_WIDTH + BORDER_COLS * 8 * 2;
 pub const SCREEN_HEIGHT: usize = CANVAS_HEIGHT + BORDER_ROWS * 8 * 2;
-/// Frames per second
+/// Frames per second (PAL machines)
 pub const FPS: usize = 50;
 
 /// relative addresses
@@ -17,7 +17,7 @@ pub(crate) const ATTR_BASE_REL: u16 = 0x1800;
 pub(crate) const ATTR_MAX_REL: u16 = 0x1AFF;
 /// on all spectrums these values are fixed
 pub(crate) const CLOCKS_PER_COL: usize = 4;
-#[cfg(feature = "precise-border")]
+#[cfg(any(test, feature = "precise-border"))]
 pub(crate) const PIXELS_PER_CLOCK: usize = 2;
 /// size of screen in rows, cols
 pub(crate) const ATTR_COLS: usize = CANVAS_WIDTH / 8;
@@ -26,3 +26,20 @@ pub(crate) const BORDER_COLS: usize = 4;
 pub(crate) const BORDER_ROWS: usize = 3;
 /// Tape loading trap at LD-BREAK routine in ROM
 pub(crate) const ADDR_LD_BREAK: u16 = 0x056B;
+/// WAIT-KEY1 loop of the 48 BASIC editor
+pub(crate) const ADDR_WAIT_KEY_48K: u16 = 0x15DE;
+/// Key wait loop of the 128K ROM 0 menu and editor
+pub(crate) const ADDR_WAIT_KEY_128K: u16 = 0x3683;
+/// LAST K system variable, code of the last pressed key
+pub(crate) const ADDR_SYSVAR_LAST_K: u16 = 0x5C08;
+/// FLAGS system variable
+pub(crate) const ADDR_SYSVAR_FLAGS: u16 = 0x5C3B;
+/// VARS system variable, address of BASIC variables
+pub(crate) const ADDR_SYSVAR_VARS: u16 = 0x5C4B;
+/// PROG system variable, address of BASIC program
+pub(crate) const ADDR_SYSVAR_PROG: u16 = 0x5C53;
+/// E LINE system variable, address of the command being typed in, follows
+/// the end marker of variables area
+pub(crate) const ADDR_SYSVAR_E_LINE: u16 = 0x5C59;
+/// Bit of FLAGS which is set when new key code is placed to LAST K
+pub(crate) const FLAGS_NEW_KEY: u8 = 0x20;
@@ -1,54 +1,110 @@
 //! Contains ZX Spectrum System controller (like ula or so) of emulator
 use crate::{
-    error::Error,
+    diagnostics::MEMORY,
+    error::{Error, MachineSpecsError},
     host::{DebugInterface, Host, HostContext, IoExtender},
     settings::RustzxSettings,
-    utils::screen::bitmap_line_addr,
     zx::{
-        constants::{ADDR_LD_BREAK, CANVAS_HEIGHT, CLOCKS_PER_COL},
+        centronics::CentronicsPort,
         events::EmulationEvents,
         joy::{
+            fuller::{FullerJoy, PORT_FULLER_AY_REGISTER},
             kempston::KempstonJoy,
             sinclair::{self, SinclairJoyNum, SinclairKey},
         },
         keys::{CompoundKey, ZXKey},
-        machine::ZXMachine,
+        machine::{MemoryModel, UlaIssue, ZXMachine, ZXSpecs},
         memory::{Page, RamType, RomType, ZXMemory, PAGE_SIZE},
         mouse::kempston::{KempstonMouse, KempstonMouseButton, KempstonMouseWheelDirection},
+        ports::{self, BusRead, Peripheral, PortAccess, PortDirection, PortLogger},
+        rom_info::{RomInfo, RomTraps},
+        storage::{DriveActivity, DriveStatus, StorageActivity, StorageDevice, StorageMonitor},
         tape::{TapeImpl, ZXTape},
-        video::{colors::ZXColor, screen::ZXScreen},
+        video::{colors::ZXColor, screen::ZXScreen, ulaplus::UlaPlus},
     },
 };
+use alloc::{vec, vec::Vec};
 use rustzx_z80::Z80Bus;
 
+#[cfg(feature = "betadisk")]
+use crate::zx::betadisk::BetaDisk;
+#[cfg(feature = "ide")]
+use crate::zx::ide::IdeInterface;
+#[cfg(feature = "plusd")]
+use crate::zx::plusd::PlusD;
 #[cfg(feature = "embedded-roms")]
 use crate::zx::roms;
 #[cfg(feature = "sound")]
 use crate::zx::sound::mixer::ZXMixer;
+#[cfg(feature = "spectranet")]
+use crate::zx::spectranet::Spectranet;
 #[cfg(feature = "precise-border")]
 use crate::zx::video::border::ZXBorder;
+#[cfg(feature = "ay")]
+use crate::zx::{ay_port::PortADevices, rs232::AY_REG_PORT_A};
+
+/// Nesting depth of the interrupt handlers tracked for the debug border,
+/// the outermost handler is dropped when exceeded
+const MAX_TRACKED_ISRS: usize = 8;
 
 /// ZX System controller
 pub(crate) struct ZXController<H: Host> {
     // parts of ZX Spectrum.
     pub machine: ZXMachine,
+    specs: ZXSpecs,
     pub memory: ZXMemory,
+    // identification of the loaded ROM, selects addresses of ROM traps
+    pub rom_info: RomInfo,
     pub screen: ZXScreen<H::FrameBuffer>,
     pub tape: ZXTape<H::TapeAsset>,
+    pub storage: StorageMonitor,
     #[cfg(feature = "precise-border")]
     pub border: ZXBorder<H::FrameBuffer>,
     pub kempston: Option<KempstonJoy>,
+    pub fuller: Option<FullerJoy>,
     pub mouse: Option<KempstonMouse>,
     pub io_extender: Option<H::IoExtender>,
     pub debug_interface: Option<H::DebugInterface>,
+    pub port_logger: Option<PortLogger>,
+    #[cfg(feature = "ide")]
+    pub ide: Option<IdeInterface<H::BlockDevice>>,
+    #[cfg(feature = "spectranet")]
+    pub spectranet: Option<Spectranet<H::NetworkStack>>,
+    #[cfg(feature = "plusd")]
+    pub plusd: Option<PlusD>,
+    #[cfg(feature = "betadisk")]
+    pub betadisk: Option<BetaDisk>,
+    /// Built-in printer port of +2A/+3
+    pub printer: Option<CentronicsPort>,
+    // AY chip is built into the machine or attached via settings, AY ports
+    // are not decoded otherwise
+    has_ay: bool,
+    /// RS232 port and keypad of 128K models
+    #[cfg(feature = "ay")]
+    pub ay_port_a: PortADevices,
     #[cfg(feature = "sound")]
     pub mixer: ZXMixer,
     pub keyboard: [u8; 8],
     pub keyboard_extended: [u8; 8],
     pub keyboard_sinclair: [u8; 8],
     pub caps_shift_modifier_mask: u32,
+    keyboard_ghosting: bool,
+    ula_issue: UlaIssue,
+    // EAR input level without the tape signal, follows the last write to
+    // 0xFE port
+    ear_input_idle: bool,
     // current border color
     pub border_color: ZXColor,
+    // border color changes of the current and the last completed frame
+    pending_border_events: Vec<(usize, ZXColor)>,
+    border_events: Vec<(usize, ZXColor)>,
+    // border color shown while CPU executes interrupt handler
+    isr_border: Option<ZXColor>,
+    // return address and SP after its push of the handlers being executed,
+    // innermost last, tracked only while `isr_border` is set
+    isr_returns: Vec<(u16, u16)>,
+    // INT line state, asserted by external devices
+    pub int_line_external: bool,
     // clocls count from frame start
     frame_clocks: usize,
     // frames count, which passed during emulation invocation
@@ -57,6 +113,9 @@ pub(crate) struct ZXController<H: Host> {
     paging_enabled: bool,
     screen_bank: u8,
     current_port_7ffd: u8,
+    current_port_1ffd: u8,
+    // Count of port writes since the controller creation, wraps on overflow
+    port_writes: u32,
     // Z80 module expected controller implementation without errors,
     // so we need to store the internal errors manually. For sake of simplicity,
     // Only last error is saved
@@ -66,19 +125,24 @@ pub(crate) struct ZXController<H: Host> {
 impl<H: Host> ZXController<H> {
     /// Returns new ZXController from settings
     #[allow(clippy::let_and_return)]
-    pub fn new(settings: &RustzxSettings, host_context: H::Context) -> Self {
+    pub fn new(settings: &RustzxSettings, host_context: &H::Context) -> Self {
         let (memory, paging, screen_bank);
-        match settings.machine {
-            ZXMachine::Sinclair48K => {
+        match settings.machine.memory_model() {
+            MemoryModel::Sinclair48K => {
                 memory = ZXMemory::new(RomType::K16, RamType::K48);
                 paging = false;
                 screen_bank = 0;
             }
-            ZXMachine::Sinclair128K => {
+            MemoryModel::Sinclair128K => {
                 memory = ZXMemory::new(RomType::K32, RamType::K128);
                 paging = true;
                 screen_bank = 5;
             }
+            MemoryModel::SinclairPlus3 => {
+                memory = ZXMemory::new(RomType::K64, RamType::K128);
+                paging = true;
+                screen_bank = 5;
+            }
         };
 
         let kempston = if settings.kempston_enabled {
@@ -87,56 +151,138 @@ impl<H: Host> ZXController<H> {
             None
         };
 
+        let fuller = if settings.fuller_enabled {
+            Some(FullerJoy::default())
+        } else {
+            None
+        };
+
         let mouse = if settings.mouse_enabled {
-            Some(KempstonMouse::default())
+            Some(KempstonMouse::new(settings.mouse_config))
         } else {
             None
         };
 
-        let screen = ZXScreen::new(settings.machine, host_context.frame_buffer_context());
+        let mut specs = *settings.machine.specs_with_timings(settings.timings);
+        if let Some(interrupt_length) = settings.interrupt_length {
+            specs.interrupt_length = interrupt_length;
+        }
+        let mut screen = ZXScreen::new(
+            settings.machine,
+            specs,
+            settings.orientation,
+            host_context.frame_buffer_context(),
+        );
+        screen.set_ulaplus_enabled(settings.ulaplus_enabled);
+        screen.set_paper_patterns(settings.paper_patterns);
         #[cfg(feature = "precise-border")]
-        let border = ZXBorder::new(settings.machine, host_context.frame_buffer_context());
+        let border = ZXBorder::new(
+            specs,
+            settings.orientation,
+            host_context.frame_buffer_context(),
+        );
 
         #[cfg(feature = "sound")]
         let mixer = Self::create_mixer(settings);
 
-        let out = ZXController {
+        let mut out = ZXController {
             machine: settings.machine,
+            specs,
             memory,
+            rom_info: RomInfo::default(),
             screen,
             #[cfg(feature = "precise-border")]
             border,
             kempston,
+            fuller,
             mouse,
             io_extender: None,
             debug_interface: None,
+            port_logger: None,
+            #[cfg(feature = "ide")]
+            ide: None,
+            #[cfg(feature = "spectranet")]
+            spectranet: None,
+            #[cfg(feature = "plusd")]
+            plusd: None,
+            #[cfg(feature = "betadisk")]
+            betadisk: None,
+            printer: (settings.machine.memory_model() == MemoryModel::SinclairPlus3)
+                .then(|| CentronicsPort::new(specs.freq_cpu)),
+            has_ay: Self::ay_attached(settings),
+            #[cfg(feature = "ay")]
+            ay_port_a: PortADevices::default(),
             #[cfg(feature = "sound")]
             mixer,
             keyboard: [0xFF; 8],
             keyboard_extended: [0xFF; 8],
             keyboard_sinclair: [0xFF; 8],
             caps_shift_modifier_mask: 0,
+            keyboard_ghosting: settings.keyboard_ghosting,
+            ula_issue: if settings.machine.is_48k() {
+                settings.ula_issue
+            } else {
+                UlaIssue::Issue3
+            },
+            ear_input_idle: false,
             border_color: ZXColor::Black,
+            pending_border_events: vec![(0, ZXColor::Black)],
+            border_events: Vec::new(),
+            isr_border: None,
+            isr_returns: Vec::new(),
+            int_line_external: false,
             frame_clocks: 0,
             passed_frames: 0,
             tape: Default::default(),
+            storage: Default::default(),
             events: Default::default(),
             paging_enabled: paging,
             screen_bank,
             current_port_7ffd: 0,
+            current_port_1ffd: 0,
+            port_writes: 0,
             last_emulation_error: None,
         };
+        out.update_storage_status();
+
+        if let Some(seed) = settings.ram_init_seed {
+            out.memory.fill_ram_pseudo_random(seed);
+            out.refresh_memory_dependent_devices();
+        }
 
         #[cfg(feature = "embedded-roms")]
         if settings.load_default_rom {
-            let mut out = out;
             out.load_default_rom();
-            return out;
         }
+        out.identify_rom();
 
         out
     }
 
+    /// Identifies loaded ROM, should be called after ROM change
+    pub fn identify_rom(&mut self) -> &RomInfo {
+        let pages = (0..self.machine.memory_model().rom_pages())
+            .filter_map(|page| self.memory.rom_page_data(page));
+        self.rom_info = RomInfo::identify(self.machine.memory_model(), pages);
+        &self.rom_info
+    }
+
+    /// Returns true if AY chip is built into the machine, or attached to the
+    /// machine without one (e.g. Melodik interface of 48K) when AY is enabled
+    /// in settings
+    fn ay_attached(settings: &RustzxSettings) -> bool {
+        #[cfg(all(feature = "sound", feature = "ay"))]
+        if settings.ay_enabled {
+            return true;
+        }
+        settings.machine.has_ay()
+    }
+
+    /// Returns true if AY ports are decoded, see [ZXController::ay_attached]
+    pub fn has_ay(&self) -> bool {
+        self.has_ay
+    }
+
     #[cfg(feature = "sound")]
     fn create_mixer(settings: &RustzxSettings) -> ZXMixer {
         let mut mixer = ZXMixer::new(
@@ -145,8 +291,18 @@ impl<H: Host> ZXController<H> {
             settings.ay_enabled,
             #[cfg(feature = "ay")]
             settings.ay_mode,
+            settings.sound_filters,
             settings.sound_sample_rate,
+            settings.machine.specs().frames_per_second,
         );
+        #[cfg(feature = "ay")]
+        if settings.fuller_ay_enabled {
+            mixer.fuller_ay = Some(crate::zx::sound::ay::ZXAyChip::new(
+                settings.sound_sample_rate,
+                mixer.samples_per_frame(),
+                settings.ay_mode,
+            ));
+        }
         mixer.volume(settings.sound_volume as f64 / 200.0);
         mixer
     }
@@ -154,7 +310,7 @@ impl<H: Host> ZXController<H> {
     /// returns current frame emulation pos in percents
     #[cfg(feature = "sound")]
     fn frame_pos(&self) -> f64 {
-        let val = self.frame_clocks as f64 / self.machine.specs().clocks_frame as f64;
+        let val = self.frame_clocks as f64 / self.specs.clocks_frame as f64;
         if val > 1.0 {
             1.0
         } else {
@@ -165,20 +321,49 @@ impl<H: Host> ZXController<H> {
     /// loads builted-in ROM
     #[cfg(feature = "embedded-roms")]
     fn load_default_rom(&mut self) {
-        match self.machine {
-            ZXMachine::Sinclair48K => {
+        match self.machine.memory_model() {
+            MemoryModel::Sinclair48K => {
                 let page = self.memory.rom_page_data_mut(0);
                 page.copy_from_slice(roms::ROM_48K);
             }
-            ZXMachine::Sinclair128K => {
+            MemoryModel::Sinclair128K => {
                 let page = self.memory.rom_page_data_mut(0);
                 page.copy_from_slice(roms::ROM_128K_0);
                 let page = self.memory.rom_page_data_mut(1);
                 page.copy_from_slice(roms::ROM_128K_1);
             }
+            MemoryModel::SinclairPlus3 => {
+                diag!(warn, MEMORY, "+3 ROM is not embedded, ROM is left empty");
+            }
         }
     }
 
+    /// Returns pressed key columns (active low) of the half-rows selected by
+    /// the low bits of `rows`
+    fn read_keyboard_matrix(&self, rows: u8) -> u8 {
+        let mut row_keys = [0xFFu8; 8];
+        for (n, keys) in row_keys.iter_mut().enumerate() {
+            *keys = self.keyboard[n] & self.keyboard_extended[n] & self.keyboard_sinclair[n];
+        }
+        let mut selected = !rows;
+        if self.keyboard_ghosting {
+            // Keyboard matrix has no diodes, so pressed keys short rows with
+            // columns. Half-row which shares pressed column with selected
+            // half-row is effectively selected too
+            loop {
+                let columns = pressed_columns(&row_keys, selected);
+                let connected = (0..8)
+                    .filter(|n| !row_keys[*n] & columns & 0x1F != 0)
+                    .fold(selected, |acc, n| acc | (1 << n));
+                if connected == selected {
+                    break;
+                }
+                selected = connected;
+            }
+        }
+        !pressed_columns(&row_keys, selected)
+    }
+
     /// Changes key state in controller
     pub fn send_key(&mut self, key: ZXKey, pressed: bool) {
         if pressed {
@@ -231,46 +416,57 @@ impl<H: Host> ZXController<H> {
         }
     }
 
+    pub fn send_mouse_wheel_delta(&mut self, delta: i8) {
+        if let Some(mouse) = &mut self.mouse {
+            mouse.send_wheel_delta(delta);
+        }
+    }
+
     pub fn send_mouse_pos_diff(&mut self, x: i8, y: i8) {
         if let Some(mouse) = &mut self.mouse {
             mouse.send_pos_diff(x, y);
         }
     }
 
+    pub fn contention_offset(&self) -> i32 {
+        self.specs.contention_offset
+    }
+
+    /// Changes ULA delay for contention, floating bus and screen rendering
+    pub fn set_contention_offset(&mut self, offset: i32) -> Result<(), MachineSpecsError> {
+        self.specs.set_contention_offset(offset)?;
+        self.screen.set_specs(self.specs);
+        #[cfg(feature = "precise-border")]
+        self.border.set_specs(self.specs);
+        Ok(())
+    }
+
     /// Returns current bus floating value
     fn floating_bus_value(&self) -> u8 {
-        let specs = self.machine.specs();
-        let clocks = self.frame_clocks;
-        if clocks < specs.clocks_first_pixel + 2 {
+        // +2A/+3 gate array does not leave screen reads on the data bus
+        if self.machine.memory_model() == MemoryModel::SinclairPlus3 {
             return 0xFF;
         }
-        let clocks = clocks - (specs.clocks_first_pixel + 2);
-        let row = clocks / specs.clocks_line;
-        let clocks = clocks % specs.clocks_line;
-        let col = (clocks / 8) * 2 + (clocks % 8) / 2;
-        if row < CANVAS_HEIGHT
-            && clocks < specs.clocks_screen_row - CLOCKS_PER_COL
-            && ((clocks & 0x04) == 0)
-        {
-            if clocks % 2 == 0 {
-                return self.memory.read(bitmap_line_addr(row) + col as u16);
-            } else {
-                let byte = (row / 8) * 32 + col;
-                return self.memory.read(0x5800 + byte as u16);
-            };
+        match self.specs.ula_fetch(self.frame_clocks) {
+            // ULA reads the displayed bank directly, regardless of the CPU
+            // memory map and the cartridge overlay
+            Some(fetch) => {
+                let offset = fetch.addr() as usize % PAGE_SIZE;
+                self.memory.ram_page_data(self.screen_bank)[offset]
+            }
+            None => 0xFF,
         }
-        0xFF
     }
 
     /// make contention
     fn do_contention(&mut self) {
-        let contention = self.machine.contention_clocks(self.frame_clocks);
+        let contention = self.specs.contention_clocks(self.frame_clocks);
         self.wait_internal(contention);
     }
 
     /// make contention + wait some clocks
     fn do_contention_and_wait(&mut self, wait_time: usize) {
-        let contention = self.machine.contention_clocks(self.frame_clocks);
+        let contention = self.specs.contention_clocks(self.frame_clocks);
         self.wait_internal(contention + wait_time);
     }
 
@@ -285,7 +481,7 @@ impl<H: Host> ZXController<H> {
 
     /// Returns early IO contention clocks
     fn io_contention_first(&mut self, port: u16) {
-        if self.addr_is_contended(port) {
+        if self.machine.io_is_contended() && self.addr_is_contended(port) {
             self.do_contention();
         };
         self.wait_internal(1);
@@ -295,7 +491,7 @@ impl<H: Host> ZXController<H> {
     fn io_contention_last(&mut self, port: u16) {
         if self.machine.port_is_contended(port) {
             self.do_contention_and_wait(2);
-        } else if self.addr_is_contended(port) {
+        } else if self.machine.io_is_contended() && self.addr_is_contended(port) {
             self.do_contention_and_wait(1);
             self.do_contention_and_wait(1);
             self.do_contention();
@@ -306,12 +502,77 @@ impl<H: Host> ZXController<H> {
 
     /// Starts a new frame
     fn new_frame(&mut self) {
-        self.frame_clocks -= self.machine.specs().clocks_frame;
+        self.frame_clocks -= self.specs.clocks_frame;
+        self.events |= EmulationEvents::FRAME_STARTED;
         self.screen.new_frame();
         #[cfg(feature = "precise-border")]
         self.border.new_frame();
+        core::mem::swap(&mut self.pending_border_events, &mut self.border_events);
+        self.pending_border_events.clear();
+        self.pending_border_events
+            .push((0, self.displayed_border_color()));
         #[cfg(feature = "sound")]
         self.mixer.new_frame();
+        #[cfg(feature = "ay")]
+        self.ay_port_a.new_frame(self.specs.clocks_frame);
+        #[cfg(feature = "spectranet")]
+        if let Some(spectranet) = &mut self.spectranet {
+            spectranet.poll();
+        }
+        #[cfg(feature = "plusd")]
+        if let Some(plusd) = &mut self.plusd {
+            plusd.new_frame(self.specs.clocks_frame);
+        }
+        #[cfg(feature = "betadisk")]
+        if let Some(betadisk) = &mut self.betadisk {
+            betadisk.new_frame(self.specs.clocks_frame);
+        }
+        if let Some(printer) = &mut self.printer {
+            printer.new_frame(self.specs.clocks_frame);
+        }
+        self.update_storage_status();
+    }
+
+    /// Samples status of the storage devices at the frame end
+    fn update_storage_status(&mut self) {
+        let playing = self.tape.is_playing();
+        let tape = StorageActivity {
+            device: StorageDevice::Tape,
+            status: DriveStatus {
+                motor_on: playing,
+                activity: if playing {
+                    DriveActivity::Read
+                } else {
+                    DriveActivity::Idle
+                },
+            },
+        };
+        #[cfg(feature = "plusd")]
+        let plusd = self.plusd.as_ref();
+        #[cfg(feature = "plusd")]
+        let drives = (0..crate::zx::plusd::DRIVE_COUNT).filter_map(|drive| {
+            let status = plusd?.drive_status(drive)?;
+            Some(StorageActivity {
+                device: StorageDevice::PlusD(drive),
+                status,
+            })
+        });
+        #[cfg(not(feature = "plusd"))]
+        let drives = core::iter::empty();
+        #[cfg(feature = "betadisk")]
+        let betadisk = self.betadisk.as_ref();
+        #[cfg(feature = "betadisk")]
+        let betadisk_drives = (0..crate::zx::betadisk::DRIVE_COUNT).filter_map(|drive| {
+            let status = betadisk?.drive_status(drive)?;
+            Some(StorageActivity {
+                device: StorageDevice::BetaDisk(drive),
+                status,
+            })
+        });
+        #[cfg(not(feature = "betadisk"))]
+        let betadisk_drives = core::iter::empty();
+        self.storage
+            .frame_finished(core::iter::once(tape).chain(drives).chain(betadisk_drives));
     }
 
     /// Collects all events from the last emulation step
@@ -324,47 +585,241 @@ impl<H: Host> ZXController<H> {
         self.passed_frames
     }
 
+    /// Queues bytes to be sent to the Spectrum via attached RS232 device
+    #[cfg(feature = "ay")]
+    pub fn send_rs232(&mut self, data: &[u8]) {
+        if let Some(rs232) = &mut self.ay_port_a.rs232 {
+            rs232.send(self.frame_clocks, data);
+        }
+    }
+
+    /// Returns count of clocks passed since the last frame counter reset
+    pub fn clocks_since_reset(&self) -> u64 {
+        // Long runs (e.g. [crate::Emulator::run_bounded]) could overflow usize
+        // on 32-bit targets
+        self.passed_frames as u64 * self.specs.clocks_frame as u64 + self.frame_clocks as u64
+    }
+
     pub fn reset_frame_counter(&mut self) {
         self.passed_frames = 0;
     }
 
     pub fn write_7ffd(&mut self, val: u8) {
         if !self.paging_enabled {
+            diag!(
+                trace,
+                MEMORY,
+                "ignored 7FFD write {:#04X}, paging is locked",
+                val
+            );
             return;
         }
-        self.current_port_7ffd = val;
-        // remap top 16K of the ram
-        self.memory.remap(3, Page::Ram(val & 0x07));
-        // third block is not pageable
         // second block is screen buffer, not pageable. but we need to change active buffer
         let new_screen_bank = if val & 0x08 == 0 { 5 } else { 7 };
-        self.screen.switch_bank(new_screen_bank as usize);
+        diag!(
+            debug,
+            MEMORY,
+            "7FFD={:#04X}: RAM bank {}, screen bank {}, ROM {}{}",
+            val,
+            val & 0x07,
+            new_screen_bank,
+            (val >> 4) & 0x01,
+            if val & 0x20 != 0 {
+                ", paging locked"
+            } else {
+                ""
+            }
+        );
+        self.current_port_7ffd = val;
         self.screen_bank = new_screen_bank;
-        // remap ROM
-        self.memory.remap(0, Page::Rom((val >> 4) & 0x01));
+        self.screen.switch_bank(self.screen_bank as usize);
+        self.update_memory_map();
         // check paging allow bit
         if val & 0x20 != 0 {
             self.paging_enabled = false;
         }
     }
 
+    /// Writes +2A/+3 0x1FFD port: bit 0 enables special (all-RAM) paging
+    /// with the config selected by bits 1-2, otherwise bit 2 is the high bit
+    /// of the ROM page. Bit 3 (disk motor) and bit 4 (printer strobe) do not
+    /// affect paging. Paging bits are locked together with 0x7FFD, the
+    /// printer strobe is not
+    pub fn write_1ffd(&mut self, val: u8) {
+        let clocks = self.frame_clocks;
+        if let Some(printer) = &mut self.printer {
+            printer.set_strobe(val & 0x10 != 0, clocks);
+        }
+        if !self.paging_enabled {
+            diag!(
+                trace,
+                MEMORY,
+                "ignored 1FFD write {:#04X}, paging is locked",
+                val
+            );
+            return;
+        }
+        if val & 0x01 != 0 {
+            diag!(
+                debug,
+                MEMORY,
+                "1FFD={:#04X}: special paging config {}",
+                val,
+                (val >> 1) & 0x03
+            );
+        } else {
+            diag!(
+                debug,
+                MEMORY,
+                "1FFD={:#04X}: ROM high bit {}",
+                val,
+                (val >> 2) & 0x01
+            );
+        }
+        self.current_port_1ffd = val;
+        self.update_memory_map();
+    }
+
+    /// Rebuilds memory map from the values of the paging ports
+    fn update_memory_map(&mut self) {
+        if self.current_port_1ffd & 0x01 != 0 {
+            self.memory
+                .set_special_paging((self.current_port_1ffd >> 1) & 0x03);
+            return;
+        }
+        // 0x1FFD is never written on 128K, so ROM high bit stays 0
+        let rom = ((self.current_port_1ffd >> 1) & 0x02) | ((self.current_port_7ffd >> 4) & 0x01);
+        self.memory
+            .remap(0, Page::Rom(rom))
+            .remap(1, Page::Ram(5))
+            .remap(2, Page::Ram(2))
+            .remap(3, Page::Ram(self.current_port_7ffd & 0x07));
+    }
+
+    /// Returns count of port writes since the controller creation, wraps on
+    /// overflow
+    pub(crate) fn port_writes(&self) -> u32 {
+        self.port_writes
+    }
+
+    /// Returns traps of the ROM page mapped at 0x0000 .. 0x3FFF, no traps
+    /// when ROM is paged out by the interface or cartridge memory
+    pub(crate) fn active_rom_traps(&self) -> RomTraps {
+        #[cfg(feature = "spectranet")]
+        let spectranet_paged_in = self
+            .spectranet
+            .as_ref()
+            .is_some_and(|spectranet| spectranet.paged_in());
+        #[cfg(not(feature = "spectranet"))]
+        let spectranet_paged_in = false;
+        #[cfg(feature = "plusd")]
+        let plusd_paged_in = self.plusd.as_ref().is_some_and(|plusd| plusd.paged_in());
+        #[cfg(not(feature = "plusd"))]
+        let plusd_paged_in = false;
+        #[cfg(feature = "betadisk")]
+        let trdos_paged_in = self.betadisk.as_ref().is_some_and(BetaDisk::paged_in);
+        #[cfg(not(feature = "betadisk"))]
+        let trdos_paged_in = false;
+        let dock_paged_in = self.memory.dock_mask() & 0b11 != 0;
+        let interface_paged_in = spectranet_paged_in || plusd_paged_in || trdos_paged_in;
+        match self.memory.get_bank_type(0) {
+            Page::Rom(page) if !interface_paged_in && !dock_paged_in => self.rom_info.traps(page),
+            _ => RomTraps::NONE,
+        }
+    }
+
+    /// Re-enables 0x7FFD paging after it was locked by bit 5
+    pub(crate) fn unlock_paging(&mut self) {
+        self.paging_enabled = !self.machine.is_48k();
+    }
+
+    /// Resets the machine as the reset button does: paging is unlocked and
+    /// cleared, AY registers are cleared, Spectranet is paged in and +D is
+    /// paged out. Current frame is cut short and flash phase starts over, so
+    /// the machine runs the same way regardless of the state before the
+    /// reset. Memory contents, media and attached devices are kept
+    pub fn reset(&mut self) {
+        self.isr_returns.clear();
+        self.unlock_paging();
+        if self.paging_enabled {
+            self.write_7ffd(0);
+        }
+        if self.machine.memory_model() == MemoryModel::SinclairPlus3 {
+            self.write_1ffd(0);
+        }
+        #[cfg(feature = "ay")]
+        {
+            let frame_pos = self.frame_pos();
+            self.mixer.ay.reset(frame_pos);
+            if let Some(ay) = &mut self.mixer.fuller_ay {
+                ay.reset(frame_pos);
+            }
+        }
+        #[cfg(feature = "spectranet")]
+        if let Some(spectranet) = &mut self.spectranet {
+            spectranet.reset();
+        }
+        #[cfg(feature = "plusd")]
+        if let Some(plusd) = &mut self.plusd {
+            plusd.reset();
+        }
+        #[cfg(feature = "betadisk")]
+        if let Some(betadisk) = &mut self.betadisk {
+            betadisk.reset();
+        }
+        self.ear_input_idle = false;
+        self.frame_clocks = self.specs.clocks_frame;
+        self.new_frame();
+        self.passed_frames += 1;
+        self.screen.reset();
+        self.pending_border_events.clear();
+        self.set_border_color(0, ZXColor::Black);
+    }
+
     pub fn read_7ffd(&self) -> u8 {
         self.current_port_7ffd
     }
 
+    /// Returns RAM bank which is currently displayed by the ULA
+    pub fn display_bank(&self) -> u8 {
+        self.screen_bank
+    }
+
+    /// Enables or disables screen and border rendering during the frames
+    /// started after the call
+    pub fn set_rendering_skipped(&mut self, value: bool) {
+        self.screen.set_rendering_skipped(value);
+        #[cfg(feature = "precise-border")]
+        self.border.set_rendering_skipped(value);
+    }
+
+    /// Returns border color changes of the last completed frame
+    pub fn border_events(&self) -> &[(usize, ZXColor)] {
+        &self.border_events
+    }
+
     #[cfg(all(feature = "sound", feature = "ay"))]
-    fn read_ay_port(&mut self) -> u8 {
-        self.mixer.ay.read()
+    fn read_ay_port(&mut self) -> Option<u8> {
+        let value = self.mixer.ay.read();
+        if self.mixer.ay.selected_reg() == AY_REG_PORT_A {
+            Some(self.ay_port_a.read(self.frame_clocks, value))
+        } else {
+            Some(value)
+        }
     }
 
     #[cfg(not(all(feature = "sound", feature = "ay")))]
-    fn read_ay_port(&mut self) -> u8 {
-        self.floating_bus_value()
+    fn read_ay_port(&mut self) -> Option<u8> {
+        None
     }
 
     #[cfg(all(feature = "sound", feature = "ay"))]
     fn write_ay_port(&mut self, value: u8) {
-        self.mixer.ay.write(value);
+        if self.mixer.ay.selected_reg() == AY_REG_PORT_A {
+            self.ay_port_a.write(self.frame_clocks, value);
+        }
+        let frame_pos = self.frame_pos();
+        self.mixer.ay.write(value, frame_pos);
     }
 
     #[cfg(not(all(feature = "sound", feature = "ay")))]
@@ -378,29 +833,398 @@ impl<H: Host> ZXController<H> {
     #[cfg(not(all(feature = "sound", feature = "ay")))]
     fn select_ay_reg(&mut self, _: u8) {}
 
-    pub(crate) fn set_border_color(
-        &mut self,
-        #[cfg(feature = "precise-border")] clocks: usize,
-        #[cfg(not(feature = "precise-border"))] _clocks: usize,
-        color: ZXColor,
-    ) {
+    /// Returns true if port is handled by the AY of the Fuller Box
+    fn fuller_ay_handles_port(&self, _port: u16) -> bool {
+        #[cfg(all(feature = "sound", feature = "ay"))]
+        if self.mixer.fuller_ay.is_some() {
+            use crate::zx::joy::fuller::PORT_FULLER_AY_DATA;
+            let [low, _] = _port.to_le_bytes();
+            return low == PORT_FULLER_AY_REGISTER || low == PORT_FULLER_AY_DATA;
+        }
+        false
+    }
+
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    fn read_fuller_ay(&mut self, port: u16) -> Option<u8> {
+        match (&self.mixer.fuller_ay, port.to_le_bytes()) {
+            (Some(ay), [PORT_FULLER_AY_REGISTER, _]) => Some(ay.read()),
+            // Data port is write-only
+            _ => None,
+        }
+    }
+
+    #[cfg(not(all(feature = "sound", feature = "ay")))]
+    fn read_fuller_ay(&mut self, _: u16) -> Option<u8> {
+        None
+    }
+
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    fn write_fuller_ay(&mut self, port: u16, value: u8) {
+        let frame_pos = self.frame_pos();
+        if let Some(ay) = &mut self.mixer.fuller_ay {
+            match port.to_le_bytes() {
+                [PORT_FULLER_AY_REGISTER, _] => ay.select_reg(value),
+                _ => ay.write(value, frame_pos),
+            }
+        }
+    }
+
+    #[cfg(not(all(feature = "sound", feature = "ay")))]
+    fn write_fuller_ay(&mut self, _: u16, _: u8) {}
+
+    /// Reads the port from all devices which decode it, in the port
+    /// handling order
+    fn read_bus(&mut self, port: u16) -> BusRead {
+        let mut bus = BusRead::default();
+
+        // Active TR-DOS blocks other devices on its ports
+        #[cfg(feature = "betadisk")]
+        {
+            let clocks = self.frame_clocks;
+            if let Some(betadisk) = self.betadisk.as_mut().filter(|b| b.handles_port(port)) {
+                bus.respond(betadisk.read_port(port, clocks));
+                return bus;
+            }
+        }
+
+        #[cfg(feature = "ide")]
+        if let Some(ide) = self
+            .ide
+            .as_mut()
+            .filter(|_| IdeInterface::<H::BlockDevice>::handles_port(port))
+        {
+            bus.respond(ide.read(port));
+        }
+
+        #[cfg(feature = "spectranet")]
+        if let Some(spectranet) = self
+            .spectranet
+            .as_mut()
+            .filter(|_| Spectranet::<H::NetworkStack>::handles_port(port))
+        {
+            bus.respond(spectranet.read_port(port));
+        }
+
+        #[cfg(feature = "plusd")]
+        {
+            let clocks = self.frame_clocks;
+            if let Some(plusd) = self.plusd.as_mut().filter(|_| PlusD::handles_port(port)) {
+                bus.respond(plusd.read_port(port, clocks));
+            }
+        }
+
+        if let Some(ulaplus) = self
+            .screen
+            .ulaplus()
+            .filter(|_| UlaPlus::handles_port(port))
+        {
+            bus.respond(ulaplus.read(port));
+        }
+
+        if ports::ULA.matches(port) {
+            let [_, h] = port.to_le_bytes();
+            let mut tmp = self.read_keyboard_matrix(h);
+            // Stopped tape leaves EAR input at the level of the outputs, so
+            // the bit does not depend on where the tape signal was stopped
+            let ear = if self.tape.is_playing() {
+                self.tape.current_bit()
+            } else {
+                self.ear_input_idle
+            };
+            if !ear {
+                tmp ^= 0x40;
+            }
+            // 5 and 7 bits are unused
+            bus.respond(tmp);
+        }
+
+        if let Some(mouse) = &self.mouse {
+            let values = [mouse.buttons_port, mouse.x_pos_port, mouse.y_pos_port];
+            for (decode, value) in Peripheral::KempstonMouse.ports().iter().zip(values) {
+                if decode.matches(port) {
+                    bus.respond(value);
+                }
+            }
+        }
+
+        if self.fuller_ay_handles_port(port) {
+            if let Some(value) = self.read_fuller_ay(port) {
+                bus.respond(value);
+            }
+        }
+
+        if self.has_ay && ports::AY_REGISTER.matches(port) {
+            if let Some(value) = self.read_ay_port() {
+                bus.respond(value);
+            }
+        }
+
+        if let (Some(joy), true) = (&self.kempston, Peripheral::KempstonJoystick.decodes(port)) {
+            bus.respond(joy.read());
+        }
+
+        if let (Some(joy), true) = (&self.fuller, Peripheral::FullerJoystick.decodes(port)) {
+            bus.respond(joy.read());
+        }
+
+        // Only BUSY is driven, other bits are floating
+        if let (Some(printer), true) = (&self.printer, ports::PRINTER_PLUS3.matches(port)) {
+            bus.respond(0xFE | printer.busy(self.frame_clocks) as u8);
+        }
+
+        bus
+    }
+
+    /// Writes value to all devices which decode the port
+    fn write_bus(&mut self, port: u16, data: u8) {
+        #[cfg(feature = "ide")]
+        if let Some(ide) = self
+            .ide
+            .as_mut()
+            .filter(|_| IdeInterface::<H::BlockDevice>::handles_port(port))
+        {
+            ide.write(port, data);
+        }
+
+        #[cfg(feature = "spectranet")]
+        if let Some(spectranet) = self
+            .spectranet
+            .as_mut()
+            .filter(|_| Spectranet::<H::NetworkStack>::handles_port(port))
+        {
+            spectranet.write_port(port, data);
+        }
+
+        #[cfg(feature = "plusd")]
+        {
+            let clocks = self.frame_clocks;
+            if let Some(plusd) = self.plusd.as_mut().filter(|_| PlusD::handles_port(port)) {
+                plusd.write_port(port, data, clocks);
+            }
+        }
+
+        #[cfg(feature = "betadisk")]
+        {
+            let clocks = self.frame_clocks;
+            if let Some(betadisk) = self.betadisk.as_mut().filter(|b| b.handles_port(port)) {
+                betadisk.write_port(port, data, clocks);
+            }
+        }
+
+        if let (Some(ulaplus), true) = (self.screen.ulaplus_mut(), UlaPlus::handles_port(port)) {
+            ulaplus.write(port, data);
+        }
+        if self.fuller_ay_handles_port(port) {
+            self.write_fuller_ay(port, data);
+        }
+        if self.has_ay && ports::AY_REGISTER.matches(port) {
+            self.select_ay_reg(data);
+        }
+        if self.has_ay && ports::AY_DATA.matches(port) {
+            self.write_ay_port(data);
+        }
+        if ports::ULA.matches(port) {
+            self.set_border_color(self.frame_clocks, ZXColor::from_bits(data & 0x07));
+            let outputs_mask = match self.ula_issue {
+                UlaIssue::Issue2 => 0x18,
+                UlaIssue::Issue3 => 0x10,
+            };
+            self.ear_input_idle = data & outputs_mask != 0;
+            #[cfg(feature = "sound")]
+            {
+                let mic = data & 0x08 != 0;
+                let ear = data & 0x10 != 0;
+                let pos = self.frame_pos();
+                self.mixer.beeper.change_state(ear, mic, pos);
+            }
+        }
+        match self.machine.memory_model() {
+            MemoryModel::Sinclair48K => {}
+            MemoryModel::Sinclair128K => {
+                if ports::PAGING_128K.matches(port) {
+                    self.write_7ffd(data);
+                }
+            }
+            MemoryModel::SinclairPlus3 => {
+                if ports::PAGING_PLUS3.matches(port) {
+                    self.write_7ffd(data);
+                }
+                if ports::PAGING_PLUS3_SPECIAL.matches(port) {
+                    self.write_1ffd(data);
+                }
+                if let (Some(printer), true) =
+                    (&mut self.printer, ports::PRINTER_PLUS3.matches(port))
+                {
+                    printer.write_data(data);
+                }
+            }
+        }
+    }
+
+    /// Returns enabled peripherals which decode I/O ports
+    pub fn peripherals(&self) -> Vec<Peripheral> {
+        let mut peripherals = Vec::new();
+        if self.kempston.is_some() {
+            peripherals.push(Peripheral::KempstonJoystick);
+        }
+        if self.mouse.is_some() {
+            peripherals.push(Peripheral::KempstonMouse);
+        }
+        if self.fuller.is_some() {
+            peripherals.push(Peripheral::FullerJoystick);
+        }
+        if self.fuller_ay_handles_port(PORT_FULLER_AY_REGISTER as u16) {
+            peripherals.push(Peripheral::FullerAy);
+        }
+        if self.screen.ulaplus().is_some() {
+            peripherals.push(Peripheral::UlaPlus);
+        }
+        #[cfg(feature = "ide")]
+        if self.ide.is_some() {
+            peripherals.push(Peripheral::Ide);
+        }
+        #[cfg(feature = "spectranet")]
+        if self.spectranet.is_some() {
+            peripherals.push(Peripheral::Spectranet);
+        }
+        #[cfg(feature = "plusd")]
+        if self.plusd.is_some() {
+            peripherals.push(Peripheral::PlusD);
+        }
+        peripherals
+    }
+
+    pub(crate) fn set_border_color(&mut self, clocks: usize, color: ZXColor) {
         self.border_color = color;
+        if self.isr_returns.is_empty() {
+            self.show_border_color(clocks, color);
+        }
+    }
+
+    fn show_border_color(&mut self, clocks: usize, color: ZXColor) {
+        self.pending_border_events.push((clocks, color));
         #[cfg(feature = "precise-border")]
         self.border.set_border(clocks, color);
     }
 
+    /// Returns border color which is currently shown, see
+    /// [ZXController::set_isr_border]
+    fn displayed_border_color(&self) -> ZXColor {
+        match self.isr_border {
+            Some(color) if !self.isr_returns.is_empty() => color,
+            _ => self.border_color,
+        }
+    }
+
+    /// Sets border color shown while CPU executes interrupt handler, `None`
+    /// shows the real border color
+    pub fn set_isr_border(&mut self, color: Option<ZXColor>) {
+        match color {
+            None => self.leave_all_isrs(),
+            Some(color) if !self.isr_returns.is_empty() => {
+                self.show_border_color(self.frame_clocks, color);
+            }
+            Some(_) => {}
+        }
+        self.isr_border = color;
+    }
+
+    /// Returns border color shown while CPU executes interrupt handler, see
+    /// [ZXController::set_isr_border]
+    pub fn isr_border(&self) -> Option<ZXColor> {
+        self.isr_border
+    }
+
+    /// Returns return address and SP after its push of the innermost
+    /// interrupt handler being executed, `None` when no handler is tracked
+    pub fn isr_return(&self) -> Option<(u16, u16)> {
+        self.isr_returns.last().copied()
+    }
+
+    /// Stops tracking of the innermost interrupt handler, real border color
+    /// is shown again when no handlers are left
+    pub fn leave_isr(&mut self) {
+        if self.isr_returns.pop().is_some() && self.isr_returns.is_empty() {
+            self.show_border_color(self.frame_clocks, self.border_color);
+        }
+    }
+
+    /// Stops tracking of all interrupt handlers, see [ZXController::leave_isr]
+    pub fn leave_all_isrs(&mut self) {
+        if !self.isr_returns.is_empty() {
+            self.isr_returns.clear();
+            self.show_border_color(self.frame_clocks, self.border_color);
+        }
+    }
+
+    /// Returns +D if it is attached and currently replaces lower 16K
+    #[cfg(feature = "plusd")]
+    fn paged_in_plusd(&mut self, addr: u16) -> Option<&mut PlusD> {
+        self.plusd
+            .as_mut()
+            .filter(|p| addr < PAGE_SIZE as u16 && p.paged_in())
+    }
+
+    /// Returns Spectranet if it is attached and currently replaces lower 16K
+    #[cfg(feature = "spectranet")]
+    fn paged_in_spectranet(&mut self, addr: u16) -> Option<&mut Spectranet<H::NetworkStack>> {
+        self.spectranet
+            .as_mut()
+            .filter(|s| addr < PAGE_SIZE as u16 && s.paged_in())
+    }
+
+    /// Reads memory as seen by the CPU, including paged in interfaces,
+    /// without contention and other side effects
+    pub fn peek(&self, addr: u16) -> u8 {
+        #[cfg(feature = "spectranet")]
+        if let Some(spectranet) = &self.spectranet {
+            if addr < PAGE_SIZE as u16 && spectranet.paged_in() {
+                return spectranet.read(addr);
+            }
+        }
+        #[cfg(feature = "plusd")]
+        if let Some(plusd) = &self.plusd {
+            if addr < PAGE_SIZE as u16 && plusd.paged_in() {
+                return plusd.read(addr);
+            }
+        }
+        #[cfg(feature = "betadisk")]
+        if let Some(betadisk) = &self.betadisk {
+            if addr < PAGE_SIZE as u16 && betadisk.paged_in() {
+                return betadisk.read(addr);
+            }
+        }
+        self.memory.read(addr)
+    }
+
+    /// Returns true while ULA frame interrupt is asserted
+    fn ula_int_active(&self) -> bool {
+        self.frame_clocks % self.specs.clocks_frame < self.specs.interrupt_length
+    }
+
+    fn log_port_access(&mut self, port: u16, value: u8, direction: PortDirection) {
+        if let Some(logger) = &mut self.port_logger {
+            logger(PortAccess {
+                clocks: self.frame_clocks,
+                port,
+                value,
+                direction,
+            });
+        }
+    }
+
     pub(crate) fn take_last_emulation_error(&mut self) -> Option<Error> {
         self.last_emulation_error.take()
     }
 
     pub(crate) fn refresh_memory_dependent_devices(&mut self) {
-        match self.machine {
-            ZXMachine::Sinclair48K => {
+        match self.machine.memory_model() {
+            MemoryModel::Sinclair48K => {
                 for (idx, data) in self.memory.ram_page_data(0).iter().enumerate() {
                     self.screen.update(idx as u16, 0, *data);
                 }
             }
-            ZXMachine::Sinclair128K => {
+            MemoryModel::Sinclair128K | MemoryModel::SinclairPlus3 => {
                 for (idx, data) in self.memory.ram_page_data(5).iter().enumerate() {
                     self.screen.update(idx as u16, 5, *data);
                 }
@@ -416,15 +1240,23 @@ impl<H: Host> Z80Bus for ZXController<H> {
     /// we need to check different breakpoints like tape
     /// loading detection breakpoint
     fn pc_callback(&mut self, addr: u16) {
-        // check mapped memory page at 0x0000 .. 0x3FFF
-        let check_fast_load = match self.machine {
-            ZXMachine::Sinclair48K if self.memory.get_bank_type(0) == Page::Rom(0) => true,
-            ZXMachine::Sinclair128K if self.memory.get_bank_type(0) == Page::Rom(1) => true,
-            _ => false,
-        };
-        if check_fast_load {
+        #[cfg(feature = "spectranet")]
+        if let Some(spectranet) = &mut self.spectranet {
+            spectranet.check_fetch(addr);
+        }
+        #[cfg(feature = "plusd")]
+        if let Some(plusd) = &mut self.plusd {
+            plusd.check_fetch(addr);
+        }
+        #[cfg(feature = "betadisk")]
+        if let Some(betadisk) = &mut self.betadisk {
+            let basic_rom = self.memory.get_bank_type(0) == Page::Rom(self.rom_info.basic_page());
+            betadisk.check_fetch(addr, basic_rom);
+        }
+        let traps = self.active_rom_traps();
+        if let Some(ld_break) = traps.ld_break {
             // Tape LOAD/VERIFY
-            if addr == ADDR_LD_BREAK {
+            if addr == ld_break {
                 // Add event (Fast tape loading request) it must be executed
                 // by emulator immediately
                 self.events |= EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED;
@@ -439,11 +1271,26 @@ impl<H: Host> Z80Bus for ZXController<H> {
 
     /// read data without taking onto account contention
     fn read_internal(&mut self, addr: u16) -> u8 {
-        self.memory.read(addr)
+        self.peek(addr)
     }
 
     /// write data without taking onto account contention
     fn write_internal(&mut self, addr: u16, data: u8) {
+        #[cfg(feature = "spectranet")]
+        if let Some(spectranet) = self.paged_in_spectranet(addr) {
+            spectranet.write(addr, data);
+            return;
+        }
+        #[cfg(feature = "plusd")]
+        if let Some(plusd) = self.paged_in_plusd(addr) {
+            plusd.write(addr, data);
+            return;
+        }
+        // TR-DOS has no RAM, writes to its ROM are ignored
+        #[cfg(feature = "betadisk")]
+        if addr < PAGE_SIZE as u16 && self.betadisk.as_ref().is_some_and(BetaDisk::paged_in) {
+            return;
+        }
         self.memory.write(addr, data);
         // if ram then compare bank to screen bank
         if let Page::Ram(bank) = self.memory.get_page(addr) {
@@ -464,7 +1311,7 @@ impl<H: Host> Z80Bus for ZXController<H> {
             self.mixer.process(pos);
         }
         self.screen.process_clocks(self.frame_clocks);
-        if self.frame_clocks >= self.machine.specs().clocks_frame {
+        if self.frame_clocks >= self.specs.clocks_frame {
             self.new_frame();
             self.passed_frames += 1;
         }
@@ -472,20 +1319,20 @@ impl<H: Host> Z80Bus for ZXController<H> {
 
     // wait with memory request pin active
     fn wait_mreq(&mut self, addr: u16, clk: usize) {
-        match self.machine {
-            ZXMachine::Sinclair48K | ZXMachine::Sinclair128K => {
-                // contention in low 16k RAM
-                if self.addr_is_contended(addr) {
-                    self.do_contention();
-                }
-            }
+        // contention in low 16k RAM
+        if self.addr_is_contended(addr) {
+            self.do_contention();
         }
         self.wait_internal(clk);
     }
 
     /// wait without memory request pin active
     fn wait_no_mreq(&mut self, addr: u16, clk: usize) {
-        // only for 48 K!
+        // +2A/+3 gate array contends only memory requests
+        if self.machine.memory_model() == MemoryModel::SinclairPlus3 {
+            self.wait_internal(clk);
+            return;
+        }
         self.wait_mreq(addr, clk);
     }
 
@@ -495,48 +1342,19 @@ impl<H: Host> Z80Bus for ZXController<H> {
         self.io_contention_first(port);
         self.io_contention_last(port);
 
-        let io_extender_value = self
+        // Host extension takes the port exclusively
+        let extender_value = self
             .io_extender
             .as_mut()
             .and_then(|e| e.extends_port(port).then(|| e.read(port)));
-
-        // find out what we need to do
-        let [_, h] = port.to_le_bytes();
-        let output = if let Some(value) = io_extender_value {
-            value
-        } else if port & 0x0001 == 0 {
-            // ULA port
-            let mut tmp: u8 = 0xFF;
-            for n in 0..8 {
-                // if bit of row reset
-                if ((h >> n) & 0x01) == 0 {
-                    let keyboard_byte =
-                        self.keyboard[n] & self.keyboard_extended[n] & self.keyboard_sinclair[n];
-                    tmp &= keyboard_byte;
-                }
-            }
-
-            // Emulate zx spectrum "issue 2" model.
-            // For future "issue 3" implementation condition will be `!self.ear`, but
-            // different zx spectrum "issues" emulation is not planned yet
-            if !self.tape.current_bit() {
-                tmp ^= 0x40;
-            }
-            // 5 and 7 bits are unused
-            tmp
-        } else if self.mouse.is_some() && (port & 0x0121 == 0x0001) {
-            self.mouse.as_ref().unwrap().buttons_port
-        } else if self.mouse.is_some() && (port & 0x0521 == 0x0101) {
-            self.mouse.as_ref().unwrap().x_pos_port
-        } else if self.mouse.is_some() && (port & 0x0521 == 0x0501) {
-            self.mouse.as_ref().unwrap().y_pos_port
-        } else if port & 0xC002 == 0xC000 {
-            self.read_ay_port()
-        } else if self.kempston.is_some() && (port & 0x00E0 == 0) {
-            self.kempston.as_ref().unwrap().read()
-        } else {
-            self.floating_bus_value()
+        let output = match extender_value {
+            Some(value) => value,
+            None => self
+                .read_bus(port)
+                .value()
+                .unwrap_or_else(|| self.floating_bus_value()),
         };
+        self.log_port_access(port, output, PortDirection::In);
         // add one clock after operation
         self.wait_internal(1);
         output
@@ -544,30 +1362,17 @@ impl<H: Host> Z80Bus for ZXController<H> {
 
     /// write value to hardware port
     fn write_io(&mut self, port: u16, data: u8) {
+        self.port_writes = self.port_writes.wrapping_add(1);
         // first contention
         self.io_contention_first(port);
+        self.log_port_access(port, data, PortDirection::Out);
 
-        // find active port
-        if self
-            .io_extender
-            .as_ref()
-            .map_or(false, |e| e.extends_port(port))
-        {
-            self.io_extender.as_mut().unwrap().write(port, data);
-        } else if port & 0xC002 == 0xC000 {
-            self.select_ay_reg(data);
-        } else if port & 0xC002 == 0x8000 {
-            self.write_ay_port(data);
-        } else if port & 0x0001 == 0 {
-            self.set_border_color(self.frame_clocks, ZXColor::from_bits(data & 0x07));
-            #[cfg(feature = "sound")]
-            {
-                let mic = data & 0x08 != 0;
-                let ear = data & 0x10 != 0;
-                self.mixer.beeper.change_state(ear, mic);
-            }
-        } else if (port & 0x8002 == 0) && (self.machine == ZXMachine::Sinclair128K) {
-            self.write_7ffd(data);
+        // Host extension takes the port exclusively, other devices decoding
+        // the port all receive the value
+        if let Some(extender) = self.io_extender.as_mut().filter(|e| e.extends_port(port)) {
+            extender.write(port, data);
+        } else {
+            self.write_bus(port, data);
         }
         // last contention after byte write
         self.io_contention_last(port);
@@ -580,20 +1385,66 @@ impl<H: Host> Z80Bus for ZXController<H> {
         0xFF
     }
 
-    /// checks system maskable interrupt pin state
+    /// checks system maskable interrupt pin state. INT line is shared between ULA,
+    /// which asserts it at the beginning of each frame, and external devices
     fn int_active(&self) -> bool {
-        self.frame_clocks % self.machine.specs().clocks_frame
-            < self.machine.specs().interrupt_length
+        self.ula_int_active() || self.int_line_external
     }
 
     /// checks non-maskable interrupt pin state
     fn nmi_active(&self) -> bool {
+        #[cfg(feature = "spectranet")]
+        if self.spectranet.as_ref().is_some_and(|s| s.nmi_pending()) {
+            return true;
+        }
+        #[cfg(feature = "plusd")]
+        if self.plusd.as_ref().is_some_and(|p| p.nmi_pending()) {
+            return true;
+        }
         false
     }
 
+    /// CPU calls it when NMI was accepted, the request of the device which
+    /// caused it is cleared
+    fn nmi_accepted(&mut self) {
+        #[cfg(feature = "spectranet")]
+        if let Some(spectranet) = self.spectranet.as_mut().filter(|s| s.nmi_pending()) {
+            spectranet.acknowledge_nmi();
+        }
+        #[cfg(feature = "plusd")]
+        if let Some(plusd) = &mut self.plusd {
+            plusd.acknowledge_nmi();
+            // Handler fetch from 0x0066 is not reported via `pc_callback`
+            plusd.check_fetch(0x0066);
+        }
+    }
+
     /// CPU calls it when RETI instruction was processed
     fn reti(&mut self) {}
 
+    fn interrupt_accepted(&mut self, return_addr: u16, sp: u16) {
+        if let Some(color) = self.isr_border {
+            if self.isr_returns.is_empty() {
+                self.show_border_color(self.frame_clocks, color);
+            }
+            // Handlers with the return address above the current stack were
+            // left without return (e.g. stack was reset)
+            self.isr_returns
+                .retain(|&(_, handler_sp)| (handler_sp.wrapping_sub(sp) as i16) > 0);
+            if self.isr_returns.len() == MAX_TRACKED_ISRS {
+                self.isr_returns.remove(0);
+            }
+            self.isr_returns.push((return_addr, sp));
+        }
+    }
+
     /// CPU calls when was being halted
     fn halt(&mut self, _: bool) {}
 }
+
+/// Returns pressed columns (active high) of the selected half-rows
+fn pressed_columns(row_keys: &[u8; 8], selected: u8) -> u8 {
+    (0..8)
+        .filter(|n| (selected >> n) & 0x01 != 0)
+        .fold(0, |acc, n| acc | !row_keys[n])
+}
@@ -0,0 +1,86 @@
+//! Timex TC2068/TS2068 cartridge (.dck) images. Image is a sequence of bank
+//! blocks, each one consists of the bank id byte, 8 chunk type bytes (one
+//! per 8K chunk of the address space) and 8K of data for each chunk with
+//! contents (ROM or initialized RAM)
+use crate::{
+    error::CartridgeLoadError,
+    zx::memory::{ChunkType, CHUNKS, CHUNK_SIZE},
+};
+use alloc::{vec, vec::Vec};
+
+const BANK_DOCK: u8 = 0;
+const BANK_EXROM: u8 = 254;
+const BANK_HOME: u8 = 255;
+
+const CHUNK_ABSENT: u8 = 0;
+const CHUNK_RAM_EMPTY: u8 = 1;
+const CHUNK_ROM: u8 = 2;
+const CHUNK_RAM: u8 = 3;
+
+const BANK_HEADER_SIZE: usize = 1 + CHUNKS;
+/// Size of the image with all three banks fully populated
+pub(crate) const MAX_DCK_SIZE: usize = 3 * (BANK_HEADER_SIZE + CHUNKS * CHUNK_SIZE);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BankId {
+    /// Cartridge bank
+    Dock,
+    /// Extension ROM bank of the machine
+    Exrom,
+    /// Main bank of the machine (ROM and RAM)
+    Home,
+}
+
+pub(crate) struct DockBank {
+    pub id: BankId,
+    pub chunks: [ChunkType; CHUNKS],
+    /// 8K of each chunk, zeroes for absent and empty RAM chunks
+    pub data: Vec<u8>,
+}
+
+/// Parses banks of the image, count of the chunks with contents described
+/// in each bank header should match the data following it
+pub(crate) fn parse(mut image: &[u8]) -> Result<Vec<DockBank>, CartridgeLoadError> {
+    let mut banks: Vec<DockBank> = vec![];
+    while !image.is_empty() {
+        if image.len() < BANK_HEADER_SIZE {
+            return Err(CartridgeLoadError::TruncatedImage);
+        }
+        let (header, rest) = image.split_at(BANK_HEADER_SIZE);
+        image = rest;
+        let id = match header[0] {
+            BANK_DOCK => BankId::Dock,
+            BANK_EXROM => BankId::Exrom,
+            BANK_HOME => BankId::Home,
+            _ => return Err(CartridgeLoadError::InvalidBank),
+        };
+        if banks.iter().any(|bank| bank.id == id) {
+            return Err(CartridgeLoadError::DuplicateBank);
+        }
+        let mut chunks = [ChunkType::Absent; CHUNKS];
+        let mut data = vec![0; CHUNKS * CHUNK_SIZE];
+        for (index, chunk_type) in header[1..].iter().enumerate() {
+            let (chunk, has_data) = match *chunk_type {
+                CHUNK_ABSENT => (ChunkType::Absent, false),
+                CHUNK_RAM_EMPTY => (ChunkType::Ram, false),
+                CHUNK_ROM => (ChunkType::Rom, true),
+                CHUNK_RAM => (ChunkType::Ram, true),
+                _ => return Err(CartridgeLoadError::InvalidChunkType),
+            };
+            chunks[index] = chunk;
+            if has_data {
+                if image.len() < CHUNK_SIZE {
+                    return Err(CartridgeLoadError::TruncatedImage);
+                }
+                let (contents, rest) = image.split_at(CHUNK_SIZE);
+                image = rest;
+                data[index * CHUNK_SIZE..(index + 1) * CHUNK_SIZE].copy_from_slice(contents);
+            }
+        }
+        banks.push(DockBank { id, chunks, data });
+    }
+    if banks.is_empty() {
+        return Err(CartridgeLoadError::EmptyImage);
+    }
+    Ok(banks)
+}
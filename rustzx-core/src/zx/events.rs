@@ -8,6 +8,8 @@ bitflags! {
         const TAPE_FAST_LOAD_TRIGGER_DETECTED = 0b00000001;
         /// Set when PC breakpoint is reached
         const PC_BREAKPOINT = 0b00000010;
+        /// Set when new frame is started
+        const FRAME_STARTED = 0b00000100;
     }
 }
 
@@ -0,0 +1,837 @@
+//! Floppy disk images. Sector-based images (.MGT, .IMG) have the fixed
+//! geometry with sector IDs on each track numbered from 1 and ID track and
+//! side matching the physical ones. UDI images contain raw contents of each
+//! track, so sectors are found by their address marks and may have any IDs,
+//! sizes and CRC errors, or no data field at all
+use crate::{
+    diagnostics::DISK,
+    error::DiskLoadError,
+    utils::{
+        bytes::{ByteReader, ByteWriter},
+        crc::{crc16, crc32},
+    },
+};
+use alloc::vec::Vec;
+
+/// Sync byte preceding address marks, written with the missing clock bit
+pub(crate) const SYNC: u8 = 0xA1;
+/// Address marks written after three sync bytes
+pub(crate) const MARK_ID: u8 = 0xFE;
+pub(crate) const MARK_DATA: u8 = 0xFB;
+pub(crate) const MARK_DELETED_DATA: u8 = 0xF8;
+/// Index mark sync byte, written with the missing clock bit
+const INDEX_SYNC: u8 = 0xC2;
+/// Bytes with special meaning in the write track command
+pub(crate) const WRITE_TRACK_SYNC: u8 = 0xF5;
+const WRITE_TRACK_INDEX_SYNC: u8 = 0xF6;
+pub(crate) const WRITE_TRACK_CRC: u8 = 0xF7;
+pub(crate) const GAP: u8 = 0x4E;
+
+/// Data address mark should follow the ID field within this count of bytes
+const DATA_MARK_WINDOW: usize = 43;
+
+/// Larger UDI images are rejected, real ones of 80-86 cylinders are about
+/// 1.1 MB
+pub(crate) const MAX_UDI_SIZE: usize = 4 * 1024 * 1024;
+const UDI_SIGNATURE: &[u8] = b"UDI!";
+const UDI_HEADER_SIZE: usize = 16;
+const UDI_TRACK_MFM: u8 = 0x00;
+
+/// Order of the tracks in the image file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TrackOrder {
+    /// Track 0 side 0, track 0 side 1, track 1 side 0, ... (.MGT)
+    SidesInterleaved,
+    /// All tracks of side 0, then all tracks of side 1 (.IMG)
+    SidesSequential,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct DiskGeometry {
+    pub tracks: u8,
+    pub sides: u8,
+    pub sectors: u8,
+    /// Sector length code, sector size is `128 << size_code`
+    pub size_code: u8,
+}
+
+impl DiskGeometry {
+    /// +D/DISCiPLE disk: 80 tracks, 2 sides, 10 sectors of 512 bytes
+    #[cfg_attr(not(any(feature = "plusd", test)), allow(dead_code))]
+    pub const PLUSD: Self = Self {
+        tracks: 80,
+        sides: 2,
+        sectors: 10,
+        size_code: 2,
+    };
+
+    /// TR-DOS disk: 80 tracks, 2 sides, 16 sectors of 256 bytes
+    #[cfg_attr(not(feature = "betadisk"), allow(dead_code))]
+    pub const TRDOS: Self = Self {
+        tracks: 80,
+        sides: 2,
+        sectors: 16,
+        size_code: 1,
+    };
+
+    pub fn sector_size(&self) -> usize {
+        128 << self.size_code
+    }
+
+    pub fn image_size(&self) -> usize {
+        self.tracks as usize * self.sides as usize * self.sectors as usize * self.sector_size()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DiskFormat {
+    Sectors(TrackOrder),
+    Udi,
+}
+
+/// Raw track contents in the UDI image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct RawTrack {
+    start: usize,
+    len: usize,
+    /// Offset of the bitmap of the bytes written with the missing clock bit
+    clock: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Sector {
+    /// Track, side, sector and size code from the ID field
+    pub id: [u8; 4],
+    /// CRC of the ID field as stored on the disk
+    pub id_crc: [u8; 2],
+    pub id_crc_error: bool,
+    /// Position of the data field on the track, `None` if the ID field is
+    /// not followed by the data address mark
+    data: Option<usize>,
+    /// Data field has the deleted data address mark
+    pub deleted: bool,
+}
+
+impl Sector {
+    /// Size of the data field, only two low bits of the size code are used
+    pub fn size(&self) -> usize {
+        128 << (self.id[3] & 0x03)
+    }
+
+    pub fn has_data(&self) -> bool {
+        self.data.is_some()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Track {
+    /// Sectors in the order of their ID fields on the track
+    pub sectors: Vec<Sector>,
+    raw: Option<RawTrack>,
+}
+
+impl Track {
+    /// Returns image offset of the byte at the track position. Raw track
+    /// positions wrap around, as fields may continue past the index hole
+    fn offset(&self, pos: usize) -> usize {
+        match self.raw {
+            Some(raw) => raw.start + pos % raw.len,
+            None => pos,
+        }
+    }
+}
+
+pub(crate) struct FloppyDisk {
+    format: DiskFormat,
+    sides: u8,
+    tracks: Vec<Track>,
+    data: Vec<u8>,
+    write_protected: bool,
+    /// CRC32 of the image the disk was created from
+    image_crc: u32,
+    /// Image contents before the first change made by the emulated software
+    original: Option<Vec<u8>>,
+}
+
+/// Disk identity and changes made since its insertion, see
+/// [FloppyDisk::state]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct DiskState {
+    format: DiskFormat,
+    image_crc: u32,
+    write_protected: bool,
+    /// Changed ranges of the image as their offsets and contents
+    modified: Vec<(usize, Vec<u8>)>,
+}
+
+impl FloppyDisk {
+    /// Creates disk from the sector-based image file contents, image size
+    /// should match the geometry
+    pub fn new(
+        geometry: DiskGeometry,
+        order: TrackOrder,
+        data: Vec<u8>,
+    ) -> Result<Self, DiskLoadError> {
+        if data.len() != geometry.image_size() {
+            return Err(DiskLoadError::InvalidImageSize);
+        }
+        let g = geometry;
+        let mut tracks = Vec::with_capacity(g.tracks as usize * g.sides as usize);
+        for track in 0..g.tracks {
+            for side in 0..g.sides {
+                let track_index = match order {
+                    TrackOrder::SidesInterleaved => {
+                        track as usize * g.sides as usize + side as usize
+                    }
+                    TrackOrder::SidesSequential => {
+                        side as usize * g.tracks as usize + track as usize
+                    }
+                };
+                let sectors = (0..g.sectors)
+                    .map(|idx| {
+                        let id = [track, side, idx + 1, g.size_code];
+                        let offset =
+                            (track_index * g.sectors as usize + idx as usize) * g.sector_size();
+                        Sector {
+                            id,
+                            id_crc: crc16([SYNC, SYNC, SYNC, MARK_ID].iter().chain(&id))
+                                .to_be_bytes(),
+                            id_crc_error: false,
+                            data: Some(offset),
+                            deleted: false,
+                        }
+                    })
+                    .collect();
+                tracks.push(Track { sectors, raw: None });
+            }
+        }
+        Ok(Self::with_tracks(
+            DiskFormat::Sectors(order),
+            g.sides,
+            tracks,
+            data,
+        ))
+    }
+
+    /// Creates disk from the UDI (Ultra Disk Image) file contents. Only MFM
+    /// tracks are decoded, tracks of other types have no sectors
+    pub fn from_udi(data: Vec<u8>) -> Result<Self, DiskLoadError> {
+        if data.len() < UDI_HEADER_SIZE + 4 || !data.starts_with(UDI_SIGNATURE) {
+            return Err(DiskLoadError::InvalidImage);
+        }
+        let read_u32 = |offset: usize| {
+            u32::from_le_bytes([
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+            ]) as usize
+        };
+        // File size excludes trailing CRC32
+        if read_u32(4) != data.len() - 4 {
+            return Err(DiskLoadError::InvalidImageSize);
+        }
+        if udi_crc32(&data[..data.len() - 4]) != read_u32(data.len() - 4) as u32 {
+            diag!(debug, DISK, "UDI image CRC mismatch");
+        }
+        let cylinders = data[9] as usize + 1;
+        let sides = data[10] as usize + 1;
+        if sides > 2 {
+            return Err(DiskLoadError::InvalidImage);
+        }
+        let mut pos = UDI_HEADER_SIZE
+            .checked_add(read_u32(12))
+            .ok_or(DiskLoadError::InvalidImage)?;
+        let mut tracks = Vec::with_capacity(cylinders * sides);
+        for _ in 0..cylinders * sides {
+            let header = data.get(pos..pos + 3).ok_or(DiskLoadError::InvalidImage)?;
+            let (kind, len) = (
+                header[0],
+                u16::from_le_bytes([header[1], header[2]]) as usize,
+            );
+            let raw = RawTrack {
+                start: pos + 3,
+                len,
+                clock: pos + 3 + len,
+            };
+            pos = raw.clock + len.div_ceil(8);
+            if pos > data.len() - 4 {
+                return Err(DiskLoadError::InvalidImage);
+            }
+            let track = if kind == UDI_TRACK_MFM && len > 0 {
+                parse_track(&data, raw)
+            } else {
+                Track {
+                    sectors: Vec::new(),
+                    raw: Some(raw),
+                }
+            };
+            tracks.push(track);
+        }
+        Ok(Self::with_tracks(
+            DiskFormat::Udi,
+            sides as u8,
+            tracks,
+            data,
+        ))
+    }
+
+    fn with_tracks(format: DiskFormat, sides: u8, tracks: Vec<Track>, data: Vec<u8>) -> Self {
+        Self {
+            format,
+            sides,
+            tracks,
+            image_crc: crc32(&data),
+            data,
+            write_protected: false,
+            original: None,
+        }
+    }
+
+    pub fn write_protected(&self) -> bool {
+        self.write_protected
+    }
+
+    pub fn set_write_protected(&mut self, value: bool) {
+        self.write_protected = value;
+    }
+
+    /// Returns image file contents, including changes made by the emulated
+    /// software
+    pub fn into_data(mut self) -> Vec<u8> {
+        if self.format == DiskFormat::Udi && self.original.is_some() {
+            let len = self.data.len() - 4;
+            let crc = udi_crc32(&self.data[..len]);
+            self.data[len..].copy_from_slice(&crc.to_le_bytes());
+        }
+        self.data
+    }
+
+    fn track_index(&self, track: u8, side: u8) -> Option<usize> {
+        let index = track as usize * self.sides as usize + side as usize;
+        (side < self.sides && index < self.tracks.len()).then_some(index)
+    }
+
+    /// Returns physical track under the head, `None` if the image has no
+    /// such track
+    pub fn track(&self, track: u8, side: u8) -> Option<&Track> {
+        self.tracks.get(self.track_index(track, side)?)
+    }
+
+    /// Returns index of the sector with the given ID track and sector on
+    /// the physical track, ignoring sectors with ID CRC errors
+    pub fn find_sector(&self, track: u8, side: u8, id_track: u8, id_sector: u8) -> Option<usize> {
+        self.track(track, side)?.sectors.iter().position(|sector| {
+            !sector.id_crc_error && sector.id[0] == id_track && sector.id[2] == id_sector
+        })
+    }
+
+    /// Returns contents of the data field of the sector, `None` if the
+    /// sector has no data field
+    pub fn sector_data(&self, track: u8, side: u8, index: usize) -> Option<Vec<u8>> {
+        let track = self.track(track, side)?;
+        let sector = track.sectors.get(index)?;
+        let pos = sector.data?;
+        Some(
+            (pos..pos + sector.size())
+                .map(|pos| self.data[track.offset(pos)])
+                .collect(),
+        )
+    }
+
+    /// Returns true if the data field CRC stored on the disk does not match
+    /// its contents. Sector-based images have no CRC errors
+    pub fn data_crc_error(&self, track: u8, side: u8, index: usize) -> bool {
+        let Some(t) = self.track(track, side).filter(|t| t.raw.is_some()) else {
+            return false;
+        };
+        let Some((sector, pos)) = t.sectors.get(index).and_then(|s| Some((s, s.data?))) else {
+            return false;
+        };
+        let mark = self.data[t.offset(pos - 1)];
+        let data = self.sector_data(track, side, index).unwrap_or_default();
+        let crc_pos = pos + sector.size();
+        let stored = [
+            self.data[t.offset(crc_pos)],
+            self.data[t.offset(crc_pos + 1)],
+        ];
+        crc16([SYNC, SYNC, SYNC, mark].iter().chain(&data)).to_be_bytes() != stored
+    }
+
+    /// Writes data field of the sector with the new data address mark and
+    /// CRC. Data of the size other than the sector size is ignored
+    pub fn write_sector(&mut self, track: u8, side: u8, index: usize, data: &[u8], deleted: bool) {
+        let Some(track_index) = self.track_index(track, side) else {
+            return;
+        };
+        let t = &self.tracks[track_index];
+        let Some(pos) = t
+            .sectors
+            .get(index)
+            .filter(|s| s.size() == data.len())
+            .and_then(|s| s.data)
+        else {
+            return;
+        };
+        let raw = t.raw;
+        let offsets = (pos..pos + data.len())
+            .map(|pos| t.offset(pos))
+            .collect::<Vec<_>>();
+        let mut field = Vec::new();
+        if raw.is_some() {
+            let mark = if deleted {
+                MARK_DELETED_DATA
+            } else {
+                MARK_DATA
+            };
+            let crc = crc16([SYNC, SYNC, SYNC, mark].iter().chain(data)).to_be_bytes();
+            field.push((t.offset(pos - 1), mark));
+            field.push((t.offset(pos + data.len()), crc[0]));
+            field.push((t.offset(pos + data.len() + 1), crc[1]));
+        }
+        self.keep_original();
+        for (offset, byte) in offsets.into_iter().zip(data) {
+            self.data[offset] = *byte;
+        }
+        for (offset, byte) in field {
+            self.data[offset] = byte;
+        }
+        if let Some(raw) = raw {
+            // Long sector may overwrite fields of the following ones
+            self.tracks[track_index] = parse_track(&self.data, raw);
+        }
+    }
+
+    /// Writes raw track contents from the write track command data, where
+    /// 0xF5-0xF7 bytes are replaced with sync bytes and CRC. Returns false
+    /// if the image stores sectors only, without the raw tracks
+    pub fn write_raw_track(&mut self, track: u8, side: u8, written: &[u8]) -> bool {
+        let Some(track_index) = self.track_index(track, side) else {
+            return false;
+        };
+        let Some(raw) = self.tracks[track_index].raw else {
+            return false;
+        };
+        let mut bytes = Vec::with_capacity(raw.len);
+        let mut clock = Vec::with_capacity(raw.len);
+        let mut crc_start = 0;
+        for (idx, byte) in written.iter().enumerate() {
+            match *byte {
+                WRITE_TRACK_SYNC => {
+                    if written.get(idx.wrapping_sub(1)) != Some(&WRITE_TRACK_SYNC) {
+                        crc_start = bytes.len();
+                    }
+                    bytes.push(SYNC);
+                    clock.push(true);
+                }
+                WRITE_TRACK_INDEX_SYNC => {
+                    bytes.push(INDEX_SYNC);
+                    clock.push(true);
+                }
+                WRITE_TRACK_CRC => {
+                    let crc = crc16(&bytes[crc_start..]);
+                    bytes.extend(crc.to_be_bytes());
+                    clock.extend([false; 2]);
+                }
+                byte => {
+                    bytes.push(byte);
+                    clock.push(false);
+                }
+            }
+        }
+        bytes.resize(raw.len, GAP);
+        clock.resize(raw.len, false);
+        self.keep_original();
+        self.data[raw.start..raw.start + raw.len].copy_from_slice(&bytes[..raw.len]);
+        let bitmap = &mut self.data[raw.clock..raw.clock + raw.len.div_ceil(8)];
+        bitmap.fill(0);
+        for (idx, _) in clock.iter().enumerate().filter(|(_, missing)| **missing) {
+            bitmap[idx / 8] |= 1 << (idx % 8);
+        }
+        self.tracks[track_index] = parse_track(&self.data, raw);
+        true
+    }
+
+    /// Returns raw track contents, `None` if the image stores sectors only
+    pub fn raw_track(&self, track: u8, side: u8) -> Option<&[u8]> {
+        let raw = self.track(track, side)?.raw?;
+        Some(&self.data[raw.start..raw.start + raw.len])
+    }
+
+    fn keep_original(&mut self) {
+        if self.original.is_none() {
+            self.original = Some(self.data.clone());
+        }
+    }
+
+    /// Captures identity of the disk image and changes made since the disk
+    /// creation
+    pub fn state(&self) -> DiskState {
+        let mut modified = Vec::new();
+        if let Some(original) = &self.original {
+            let mut offset = 0;
+            while offset < self.data.len() {
+                if self.data[offset] == original[offset] {
+                    offset += 1;
+                    continue;
+                }
+                let len = self.data[offset..]
+                    .iter()
+                    .zip(&original[offset..])
+                    .take_while(|(current, original)| current != original)
+                    .count();
+                modified.push((offset, self.data[offset..offset + len].to_vec()));
+                offset += len;
+            }
+        }
+        DiskState {
+            format: self.format,
+            image_crc: self.image_crc,
+            write_protected: self.write_protected,
+            modified,
+        }
+    }
+
+    /// Returns true if the state was captured from the disk created from
+    /// the same image
+    pub fn matches(&self, state: &DiskState) -> bool {
+        self.format == state.format
+            && self.image_crc == state.image_crc
+            && state
+                .modified
+                .iter()
+                .all(|(offset, data)| offset + data.len() <= self.data.len())
+    }
+
+    /// Reverts changes made since the disk creation and applies the changes
+    /// of the state, which should match the disk (see [FloppyDisk::matches])
+    pub fn restore(&mut self, state: &DiskState) {
+        if let Some(original) = self.original.take() {
+            self.data = original;
+        }
+        if !state.modified.is_empty() {
+            self.keep_original();
+        }
+        for (offset, data) in &state.modified {
+            self.data[*offset..*offset + data.len()].copy_from_slice(data);
+        }
+        for track in &mut self.tracks {
+            if let Some(raw) = track.raw {
+                *track = parse_track(&self.data, raw);
+            }
+        }
+        self.write_protected = state.write_protected;
+    }
+}
+
+impl DiskState {
+    pub fn write(&self, writer: &mut ByteWriter) {
+        writer.u8(match self.format {
+            DiskFormat::Sectors(TrackOrder::SidesInterleaved) => 0,
+            DiskFormat::Sectors(TrackOrder::SidesSequential) => 1,
+            DiskFormat::Udi => 2,
+        });
+        writer.u32(self.image_crc);
+        writer.bool(self.write_protected);
+        writer.usize(self.modified.len());
+        for (offset, data) in &self.modified {
+            writer.usize(*offset);
+            writer.bytes(data);
+        }
+    }
+
+    /// Reads state written by [DiskState::write], changed ranges are
+    /// validated against the disk on restore (see [FloppyDisk::matches])
+    pub fn read(reader: &mut ByteReader) -> Option<Self> {
+        let format = match reader.u8()? {
+            0 => DiskFormat::Sectors(TrackOrder::SidesInterleaved),
+            1 => DiskFormat::Sectors(TrackOrder::SidesSequential),
+            2 => DiskFormat::Udi,
+            _ => return None,
+        };
+        let image_crc = reader.u32()?;
+        let write_protected = reader.bool()?;
+        let count = reader.usize()?;
+        let modified = (0..count)
+            .map(|_| Some((reader.usize()?, reader.bytes()?)))
+            .collect::<Option<_>>()?;
+        Some(Self {
+            format,
+            image_crc,
+            write_protected,
+            modified,
+        })
+    }
+}
+
+/// Finds ID and data fields of the raw MFM track. Address mark is
+/// recognized after the sync byte written with the missing clock bit, or
+/// after three sync bytes if the image has no clock bitmap for them
+fn parse_track(data: &[u8], raw: RawTrack) -> Track {
+    let bytes = &data[raw.start..raw.start + raw.len];
+    let byte = |pos: usize| bytes[pos % raw.len];
+    let missing_clock = |pos: usize| data[raw.clock + pos / 8] & (1 << (pos % 8)) != 0;
+    let marks = (1..raw.len)
+        .filter(|&pos| {
+            let sync = pos - 1;
+            byte(sync) == SYNC
+                && (missing_clock(sync)
+                    || (sync >= 2 && byte(sync - 1) == SYNC && byte(sync - 2) == SYNC))
+                && matches!(byte(pos), MARK_ID | MARK_DELETED_DATA..=MARK_DATA)
+        })
+        .collect::<Vec<_>>();
+    let mut sectors = Vec::new();
+    for (idx, &pos) in marks.iter().enumerate() {
+        if byte(pos) != MARK_ID {
+            continue;
+        }
+        let id = [byte(pos + 1), byte(pos + 2), byte(pos + 3), byte(pos + 4)];
+        let id_crc = [byte(pos + 5), byte(pos + 6)];
+        let id_end = pos + 7;
+        // Data mark should be the next mark on the track, possibly after
+        // the index hole
+        let data_mark = marks
+            .get(idx + 1)
+            .copied()
+            .or_else(|| marks.first().map(|first| first + raw.len))
+            .filter(|&next| {
+                next >= id_end && next - id_end <= DATA_MARK_WINDOW && byte(next) != MARK_ID
+            });
+        sectors.push(Sector {
+            id,
+            id_crc,
+            id_crc_error: crc16([SYNC, SYNC, SYNC, MARK_ID].iter().chain(&id)).to_be_bytes()
+                != id_crc,
+            data: data_mark.map(|mark| mark + 1),
+            deleted: data_mark.is_some_and(|mark| byte(mark) <= 0xF9),
+        });
+    }
+    Track {
+        sectors,
+        raw: Some(raw),
+    }
+}
+
+/// CRC32 of the UDI file, which differs from the IEEE one by the initial
+/// value
+fn udi_crc32(data: &[u8]) -> u32 {
+    const POLY: u32 = 0xEDB88320;
+    let mut crc = 0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// Builds raw MFM track of the UDI image
+    pub(crate) struct TrackBuilder {
+        bytes: Vec<u8>,
+        clock: Vec<bool>,
+    }
+
+    impl TrackBuilder {
+        pub fn new() -> Self {
+            Self {
+                bytes: vec![GAP; 80],
+                clock: vec![false; 80],
+            }
+        }
+
+        /// Appends field with the sync bytes and address mark, CRC is
+        /// corrupted if `crc_ok` is false
+        pub fn field(mut self, mark: u8, data: &[u8], crc_ok: bool) -> Self {
+            let mut crc = crc16([SYNC, SYNC, SYNC, mark].iter().chain(data));
+            if !crc_ok {
+                crc ^= 0x5555;
+            }
+            self.bytes.extend([0x00; 12]);
+            self.bytes.extend([SYNC, SYNC, SYNC, mark]);
+            self.bytes.extend_from_slice(data);
+            self.bytes.extend(crc.to_be_bytes());
+            self.bytes.extend([GAP; 22]);
+            self.clock.extend([false; 12]);
+            self.clock.extend([true, true, true]);
+            self.clock.resize(self.bytes.len(), false);
+            self
+        }
+    }
+
+    /// Creates UDI image of one cylinder from the tracks of each side
+    pub(crate) fn udi_image(tracks: &[TrackBuilder]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(UDI_SIGNATURE);
+        data.extend([0; 4]);
+        data.extend([0, 0, tracks.len() as u8 - 1, 0]);
+        data.extend([0; 4]);
+        for track in tracks {
+            let mut bytes = track.bytes.clone();
+            bytes.resize(6250, GAP);
+            data.push(UDI_TRACK_MFM);
+            data.extend((bytes.len() as u16).to_le_bytes());
+            data.extend(&bytes);
+            let mut clock = vec![0; bytes.len().div_ceil(8)];
+            for (idx, _) in track.clock.iter().enumerate().filter(|(_, c)| **c) {
+                clock[idx / 8] |= 1 << (idx % 8);
+            }
+            data.extend(clock);
+        }
+        let size = data.len() as u32;
+        data[4..8].copy_from_slice(&size.to_le_bytes());
+        data.extend(udi_crc32(&data).to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn track_orders() {
+        let g = DiskGeometry::PLUSD;
+        let mut data = vec![0; g.image_size()];
+        // Sector 3 of track 1 side 1
+        let mgt_offset = ((2 + 1) * 10 + 2) * 512;
+        let img_offset = ((80 + 1) * 10 + 2) * 512;
+        data[mgt_offset] = 0xAA;
+        data[img_offset] = 0xBB;
+
+        let mgt = FloppyDisk::new(g, TrackOrder::SidesInterleaved, data.clone()).unwrap();
+        let index = mgt.find_sector(1, 1, 1, 3).unwrap();
+        assert_eq!(mgt.sector_data(1, 1, index).unwrap()[0], 0xAA);
+        let img = FloppyDisk::new(g, TrackOrder::SidesSequential, data).unwrap();
+        assert_eq!(img.sector_data(1, 1, index).unwrap()[0], 0xBB);
+
+        assert!(mgt.find_sector(1, 1, 2, 3).is_none(), "ID track mismatch");
+        assert!(mgt.find_sector(1, 1, 1, 0).is_none());
+        assert!(mgt.find_sector(1, 1, 1, 11).is_none());
+        assert!(mgt.track(80, 0).is_none());
+        assert!(!mgt.data_crc_error(1, 1, index));
+    }
+
+    #[test]
+    fn state_restores_changed_sectors() {
+        let g = DiskGeometry::PLUSD;
+        let mut disk =
+            FloppyDisk::new(g, TrackOrder::SidesInterleaved, vec![0; g.image_size()]).unwrap();
+        disk.write_sector(0, 0, 0, &[0x11; 512], false);
+        let state = disk.state();
+        disk.write_sector(0, 0, 0, &[0x22; 512], false);
+        disk.write_sector(2, 1, 4, &[0x33; 512], false);
+        disk.set_write_protected(true);
+
+        assert!(disk.matches(&state));
+        disk.restore(&state);
+        assert_eq!(disk.sector_data(0, 0, 0).unwrap(), [0x11; 512]);
+        assert_eq!(disk.sector_data(2, 1, 4).unwrap(), [0; 512]);
+        assert!(!disk.write_protected());
+        assert_eq!(disk.state(), state);
+
+        let mut other = vec![0; g.image_size()];
+        other[0] = 1;
+        let other = FloppyDisk::new(g, TrackOrder::SidesInterleaved, other).unwrap();
+        assert!(!other.matches(&state));
+        let data = vec![0; g.image_size()];
+        let img = FloppyDisk::new(g, TrackOrder::SidesSequential, data).unwrap();
+        assert!(!img.matches(&state));
+    }
+
+    #[test]
+    fn invalid_image_size() {
+        let result = FloppyDisk::new(
+            DiskGeometry::PLUSD,
+            TrackOrder::SidesInterleaved,
+            vec![0; 100],
+        );
+        assert!(matches!(result, Err(DiskLoadError::InvalidImageSize)));
+    }
+
+    #[test]
+    fn udi_sectors_with_protection_layout() {
+        let track = TrackBuilder::new()
+            .field(MARK_ID, &[0, 0, 1, 1], true)
+            .field(MARK_DATA, &[0x11; 256], true)
+            .field(MARK_ID, &[0, 0, 2, 0], true)
+            .field(MARK_DATA, &[0x22; 128], false)
+            .field(MARK_ID, &[0, 0, 3, 0], false)
+            .field(MARK_DATA, &[0x33; 128], true)
+            .field(MARK_ID, &[0, 0, 4, 0], true)
+            .field(MARK_ID, &[0x55, 1, 5, 0], true)
+            .field(MARK_DELETED_DATA, &[0x55; 128], true);
+        let disk = FloppyDisk::from_udi(udi_image(&[track])).unwrap();
+
+        let sectors = &disk.track(0, 0).unwrap().sectors;
+        assert_eq!(sectors.len(), 5);
+        assert_eq!(disk.sector_data(0, 0, 0).unwrap(), [0x11; 256]);
+        assert!(!disk.data_crc_error(0, 0, 0));
+        assert_eq!(disk.sector_data(0, 0, 1).unwrap(), [0x22; 128]);
+        assert!(disk.data_crc_error(0, 0, 1));
+        assert!(sectors[2].id_crc_error);
+        assert!(!sectors[3].has_data(), "ID without data mark");
+        assert_eq!(sectors[4].id, [0x55, 1, 5, 0]);
+        assert!(sectors[4].deleted);
+        assert_eq!(disk.find_sector(0, 0, 0x55, 5), Some(4));
+        assert!(disk.find_sector(0, 0, 0, 3).is_none());
+        assert!(disk.track(0, 1).is_none());
+        assert!(disk.track(1, 0).is_none());
+    }
+
+    #[test]
+    fn udi_write_sector_updates_crc() {
+        let track = TrackBuilder::new()
+            .field(MARK_ID, &[0, 0, 1, 0], true)
+            .field(MARK_DATA, &[0x11; 128], false);
+        let image = udi_image(&[track]);
+        let mut disk = FloppyDisk::from_udi(image.clone()).unwrap();
+        assert!(disk.data_crc_error(0, 0, 0));
+
+        disk.write_sector(0, 0, 0, &[0x22; 128], true);
+        assert_eq!(disk.sector_data(0, 0, 0).unwrap(), [0x22; 128]);
+        assert!(!disk.data_crc_error(0, 0, 0));
+        assert!(disk.track(0, 0).unwrap().sectors[0].deleted);
+
+        let state = disk.state();
+        let mut restored = FloppyDisk::from_udi(image).unwrap();
+        assert!(restored.matches(&state));
+        restored.restore(&state);
+        assert!(restored.track(0, 0).unwrap().sectors[0].deleted);
+        assert_eq!(restored.sector_data(0, 0, 0).unwrap(), [0x22; 128]);
+
+        // Saved image has valid CRC32 and the same layout
+        let data = disk.into_data();
+        let len = data.len() - 4;
+        assert_eq!(udi_crc32(&data[..len]).to_le_bytes(), data[len..]);
+        let reloaded = FloppyDisk::from_udi(data).unwrap();
+        assert_eq!(reloaded.sector_data(0, 0, 0).unwrap(), [0x22; 128]);
+    }
+
+    #[test]
+    fn invalid_udi_images() {
+        let image = udi_image(&[TrackBuilder::new()]);
+        let mut signature = image.clone();
+        signature[0] = b'X';
+        assert!(matches!(
+            FloppyDisk::from_udi(signature),
+            Err(DiskLoadError::InvalidImage)
+        ));
+        let mut truncated = image.clone();
+        truncated.truncate(image.len() - 100);
+        assert!(matches!(
+            FloppyDisk::from_udi(truncated),
+            Err(DiskLoadError::InvalidImageSize)
+        ));
+        let mut cylinders = image;
+        cylinders[9] = 1;
+        assert!(matches!(
+            FloppyDisk::from_udi(cylinders),
+            Err(DiskLoadError::InvalidImage)
+        ));
+    }
+}
@@ -0,0 +1,1309 @@
+//! WD177x/WD179x floppy disk controller. Commands are executed instantly,
+//! data of the sector or track is transferred byte by byte via the data
+//! register with DRQ set for each byte. Disk rotation is emulated only for
+//! the index pulse reported in the type I status and for the record not
+//! found reported after the search timeout. Registers are selected by index:
+//! 0 - status/command, 1 - track, 2 - sector, 3 - data. Models differ only
+//! in the status bits, see [FdcModel]
+pub(crate) mod disk;
+#[cfg(feature = "betadisk")]
+pub(crate) mod trd;
+
+use crate::{
+    diagnostics::DISK,
+    error::DiskLoadError,
+    utils::{
+        bytes::{ByteReader, ByteWriter},
+        crc::crc16,
+    },
+    zx::storage::{DriveActivity, DriveStatus},
+};
+use alloc::{vec, vec::Vec};
+use disk::{
+    DiskState, FloppyDisk, GAP, MARK_DATA, MARK_DELETED_DATA, MARK_ID, SYNC, WRITE_TRACK_CRC,
+    WRITE_TRACK_SYNC,
+};
+
+pub(crate) const REG_STATUS_COMMAND: u8 = 0;
+pub(crate) const REG_TRACK: u8 = 1;
+pub(crate) const REG_SECTOR: u8 = 2;
+pub(crate) const REG_DATA: u8 = 3;
+
+const STATUS_BUSY: u8 = 0x01;
+/// Index pulse in type I status, data request in other ones
+const STATUS_INDEX_DRQ: u8 = 0x02;
+/// Head at track 0 in type I status
+const STATUS_TRACK0: u8 = 0x04;
+/// CRC error in the ID field or in the read sector data
+const STATUS_CRC_ERROR: u8 = 0x08;
+/// Seek error in type I status, record not found in other ones
+const STATUS_SEEK_ERROR_RNF: u8 = 0x10;
+/// Spin-up completed in WD1772 type I status, head loaded in WD1793 one
+const STATUS_SPIN_UP_HEAD_LOADED: u8 = 0x20;
+/// Deleted data mark in the read sector status
+const STATUS_RECORD_TYPE: u8 = 0x20;
+const STATUS_WRITE_PROTECT: u8 = 0x40;
+/// Motor on in WD1772 status, drive not ready in WD1793 one
+const STATUS_MOTOR_ON_NOT_READY: u8 = 0x80;
+
+const CMD_STEP_UPDATE: u8 = 0x10;
+const CMD_VERIFY: u8 = 0x04;
+const CMD_MULTI_SECTOR: u8 = 0x10;
+/// Write sector with the deleted data mark
+const CMD_DELETED_MARK: u8 = 0x01;
+
+/// Disk speed is 300 RPM
+const REVOLUTIONS_PER_SECOND: usize = 5;
+/// Index pulse lasts about 4 ms
+const INDEX_PULSES_PER_SECOND: usize = 250;
+/// Motor is turned off after 10 revolutions without commands
+const MOTOR_OFF_REVOLUTIONS: u64 = 10;
+/// Record not found is reported after searching for the sector ID during
+/// 5 revolutions
+const SEARCH_REVOLUTIONS: u64 = 5;
+/// Physical stop of the head
+const MAX_HEAD_POSITION: u8 = 83;
+/// Raw bytes of the MFM track at 250 kbit/s
+const TRACK_LENGTH: usize = 6250;
+
+/// Controller chip, which defines the meaning of the status bits 5 and 7
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FdcModel {
+    /// Controls the drive motor, reports motor on and spin-up (+D)
+    #[cfg_attr(not(any(feature = "plusd", test)), allow(dead_code))]
+    Wd1772,
+    /// Loads the head, reports head loaded and drive not ready (Beta Disk)
+    #[cfg_attr(not(any(feature = "betadisk", test)), allow(dead_code))]
+    Wd1793,
+}
+
+#[derive(Default)]
+pub(crate) struct FloppyDrive {
+    pub disk: Option<FloppyDisk>,
+    head: u8,
+    /// Data transfer of the current frame
+    activity: DriveActivity,
+    /// Data transfer of the last completed frame
+    frame_activity: DriveActivity,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Transfer {
+    None,
+    /// Buffer is read by the CPU
+    Read {
+        multi: bool,
+    },
+    /// Buffer is written by the CPU
+    WriteSector {
+        multi: bool,
+        deleted: bool,
+    },
+    WriteTrack,
+}
+
+pub(crate) struct Wd17xx {
+    model: FdcModel,
+    drives: Vec<FloppyDrive>,
+    selected: Option<usize>,
+    side: u8,
+    track: u8,
+    sector: u8,
+    data: u8,
+    /// Status bits which are not computed on read
+    status: u8,
+    /// Status of the last command is type I (restore, seek, step)
+    type_i_status: bool,
+    step_in: bool,
+    transfer: Transfer,
+    buffer: Vec<u8>,
+    buffer_pos: usize,
+    /// Index of the sector being transferred on the track
+    sector_index: usize,
+    /// Data of the sector being read has CRC error, reported after the
+    /// transfer
+    crc_error: bool,
+    /// Index of the next ID field on the track returned by the read address
+    /// command
+    next_id: usize,
+    clocks_per_second: u64,
+    frame_start: u64,
+    motor_off_time: u64,
+    /// Sector is being searched until this time, then the error is reported
+    busy_until: u64,
+}
+
+/// Controller registers, command phase, motor and drives state, see
+/// [Wd17xx::state]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Wd17xxState {
+    /// Head position and disk of each drive
+    drives: Vec<(u8, Option<DiskState>)>,
+    selected: Option<usize>,
+    side: u8,
+    track: u8,
+    sector: u8,
+    data: u8,
+    status: u8,
+    type_i_status: bool,
+    step_in: bool,
+    transfer: Transfer,
+    buffer: Vec<u8>,
+    buffer_pos: usize,
+    sector_index: usize,
+    crc_error: bool,
+    next_id: usize,
+    frame_start: u64,
+    motor_off_time: u64,
+    busy_until: u64,
+}
+
+impl Transfer {
+    fn write(self, writer: &mut ByteWriter) {
+        match self {
+            Transfer::None => writer.u8(0),
+            Transfer::Read { multi } => {
+                writer.u8(1);
+                writer.bool(multi);
+            }
+            Transfer::WriteSector { multi, deleted } => {
+                writer.u8(2);
+                writer.bool(multi);
+                writer.bool(deleted);
+            }
+            Transfer::WriteTrack => writer.u8(3),
+        }
+    }
+
+    fn read(reader: &mut ByteReader) -> Option<Self> {
+        match reader.u8()? {
+            0 => Some(Transfer::None),
+            1 => Some(Transfer::Read {
+                multi: reader.bool()?,
+            }),
+            2 => Some(Transfer::WriteSector {
+                multi: reader.bool()?,
+                deleted: reader.bool()?,
+            }),
+            3 => Some(Transfer::WriteTrack),
+            _ => None,
+        }
+    }
+}
+
+impl Wd17xxState {
+    pub fn write(&self, writer: &mut ByteWriter) {
+        writer.usize(self.drives.len());
+        for (head, disk) in &self.drives {
+            writer.u8(*head);
+            writer.bool(disk.is_some());
+            if let Some(disk) = disk {
+                disk.write(writer);
+            }
+        }
+        writer.bool(self.selected.is_some());
+        writer.usize(self.selected.unwrap_or_default());
+        writer.u8(self.side);
+        writer.u8(self.track);
+        writer.u8(self.sector);
+        writer.u8(self.data);
+        writer.u8(self.status);
+        writer.bool(self.type_i_status);
+        writer.bool(self.step_in);
+        self.transfer.write(writer);
+        writer.bytes(&self.buffer);
+        writer.usize(self.buffer_pos);
+        writer.usize(self.sector_index);
+        writer.bool(self.crc_error);
+        writer.usize(self.next_id);
+        writer.u64(self.frame_start);
+        writer.u64(self.motor_off_time);
+        writer.u64(self.busy_until);
+    }
+
+    /// Reads state written by [Wd17xxState::write]
+    pub fn read(reader: &mut ByteReader) -> Option<Self> {
+        let drive_count = reader.usize()?;
+        let drives = (0..drive_count)
+            .map(|_| {
+                let head = reader.u8()?;
+                let disk = if reader.bool()? {
+                    Some(DiskState::read(reader)?)
+                } else {
+                    None
+                };
+                Some((head, disk))
+            })
+            .collect::<Option<_>>()?;
+        let selected = reader.bool()?;
+        let selected = Some(reader.usize()?).filter(|_| selected);
+        let state = Self {
+            drives,
+            selected,
+            side: reader.u8()?,
+            track: reader.u8()?,
+            sector: reader.u8()?,
+            data: reader.u8()?,
+            status: reader.u8()?,
+            type_i_status: reader.bool()?,
+            step_in: reader.bool()?,
+            transfer: Transfer::read(reader)?,
+            buffer: reader.bytes()?,
+            buffer_pos: reader.usize()?,
+            sector_index: reader.usize()?,
+            crc_error: reader.bool()?,
+            next_id: reader.usize()?,
+            frame_start: reader.u64()?,
+            motor_off_time: reader.u64()?,
+            busy_until: reader.u64()?,
+        };
+        // Transfer in progress accesses the buffer at `buffer_pos`
+        let valid = (state.transfer == Transfer::None || state.buffer_pos < state.buffer.len())
+            && state
+                .selected
+                .is_none_or(|drive| drive < state.drives.len());
+        valid.then_some(state)
+    }
+}
+
+impl Wd17xx {
+    pub fn new(model: FdcModel, drive_count: usize, freq_cpu: usize) -> Self {
+        Self {
+            model,
+            drives: (0..drive_count).map(|_| FloppyDrive::default()).collect(),
+            selected: None,
+            side: 0,
+            track: 0,
+            sector: 1,
+            data: 0,
+            status: 0,
+            type_i_status: true,
+            step_in: true,
+            transfer: Transfer::None,
+            buffer: Vec::new(),
+            buffer_pos: 0,
+            sector_index: 0,
+            crc_error: false,
+            next_id: 0,
+            clocks_per_second: freq_cpu as u64,
+            frame_start: 0,
+            motor_off_time: 0,
+            busy_until: 0,
+        }
+    }
+
+    /// Changes CPU frequency of the machine, which motor and index pulse
+    /// timings in clocks depend on
+    pub fn set_freq_cpu(&mut self, freq_cpu: usize) {
+        self.clocks_per_second = freq_cpu as u64;
+    }
+
+    pub fn drive_mut(&mut self, drive: usize) -> Option<&mut FloppyDrive> {
+        self.drives.get_mut(drive)
+    }
+
+    /// Selects drive by index, `None` deselects all drives
+    pub fn select_drive(&mut self, drive: Option<usize>) {
+        self.selected = drive.filter(|d| *d < self.drives.len());
+    }
+
+    pub fn set_side(&mut self, side: u8) {
+        self.side = side;
+    }
+
+    /// Finishes the frame of `frame_clocks` length
+    pub fn new_frame(&mut self, frame_clocks: usize) {
+        self.frame_start += frame_clocks as u64;
+        for drive in &mut self.drives {
+            drive.frame_activity = core::mem::take(&mut drive.activity);
+        }
+    }
+
+    /// Returns status of the drive at the end of the last completed frame
+    pub fn drive_status(&self, drive: usize) -> Option<DriveStatus> {
+        let activity = self.drives.get(drive)?.frame_activity;
+        Some(DriveStatus {
+            motor_on: self.selected == Some(drive) && self.motor_on(self.frame_start),
+            activity,
+        })
+    }
+
+    fn mark_activity(&mut self, activity: DriveActivity) {
+        if let Some(drive) = self.selected {
+            let drive = &mut self.drives[drive];
+            drive.activity = drive.activity.max(activity);
+        }
+    }
+
+    /// Captures controller state, including the data transfer in progress
+    pub fn state(&self) -> Wd17xxState {
+        Wd17xxState {
+            drives: self
+                .drives
+                .iter()
+                .map(|drive| (drive.head, drive.disk.as_ref().map(FloppyDisk::state)))
+                .collect(),
+            selected: self.selected,
+            side: self.side,
+            track: self.track,
+            sector: self.sector,
+            data: self.data,
+            status: self.status,
+            type_i_status: self.type_i_status,
+            step_in: self.step_in,
+            transfer: self.transfer,
+            buffer: self.buffer.clone(),
+            buffer_pos: self.buffer_pos,
+            sector_index: self.sector_index,
+            crc_error: self.crc_error,
+            next_id: self.next_id,
+            frame_start: self.frame_start,
+            motor_off_time: self.motor_off_time,
+            busy_until: self.busy_until,
+        }
+    }
+
+    /// Restores controller state. Each drive should contain the disk created
+    /// from the same image as the one in the state (or be empty if it was
+    /// empty), otherwise the controller and disks are left unchanged
+    pub fn restore(&mut self, state: &Wd17xxState) -> Result<(), DiskLoadError> {
+        if state.drives.len() != self.drives.len() {
+            return Err(DiskLoadError::InvalidDrive);
+        }
+        let disks_match = self.drives.iter().zip(&state.drives).all(|(drive, saved)| {
+            match (&drive.disk, &saved.1) {
+                (Some(disk), Some(saved)) => disk.matches(saved),
+                (None, None) => true,
+                _ => false,
+            }
+        });
+        if !disks_match {
+            return Err(DiskLoadError::DiskMismatch);
+        }
+        for (drive, (head, saved)) in self.drives.iter_mut().zip(&state.drives) {
+            drive.head = *head;
+            if let (Some(disk), Some(saved)) = (&mut drive.disk, saved) {
+                disk.restore(saved);
+            }
+        }
+        self.selected = state.selected;
+        self.side = state.side;
+        self.track = state.track;
+        self.sector = state.sector;
+        self.data = state.data;
+        self.status = state.status;
+        self.type_i_status = state.type_i_status;
+        self.step_in = state.step_in;
+        self.transfer = state.transfer;
+        self.buffer.clone_from(&state.buffer);
+        self.buffer_pos = state.buffer_pos;
+        self.sector_index = state.sector_index;
+        self.crc_error = state.crc_error;
+        self.next_id = state.next_id;
+        self.frame_start = state.frame_start;
+        self.motor_off_time = state.motor_off_time;
+        self.busy_until = state.busy_until;
+        Ok(())
+    }
+
+    fn now(&self, clocks: usize) -> u64 {
+        self.frame_start + clocks as u64
+    }
+
+    fn drive(&self) -> Option<&FloppyDrive> {
+        self.selected.map(|d| &self.drives[d])
+    }
+
+    fn disk(&self) -> Option<&FloppyDisk> {
+        self.drive().and_then(|d| d.disk.as_ref())
+    }
+
+    fn head(&self) -> u8 {
+        self.drive().map_or(0, |d| d.head)
+    }
+
+    fn motor_on(&self, now: u64) -> bool {
+        now < self.motor_off_time
+    }
+
+    fn index_pulse(&self, now: u64) -> bool {
+        if !self.motor_on(now) || self.disk().is_none() {
+            return false;
+        }
+        let revolution = self.clocks_per_second / REVOLUTIONS_PER_SECOND as u64;
+        let pulse = self.clocks_per_second / INDEX_PULSES_PER_SECOND as u64;
+        now % revolution.max(1) < pulse
+    }
+
+    fn revolution(&self) -> u64 {
+        self.clocks_per_second / REVOLUTIONS_PER_SECOND as u64
+    }
+
+    fn restart_motor(&mut self, now: u64) {
+        self.motor_off_time = now + self.revolution() * MOTOR_OFF_REVOLUTIONS;
+    }
+
+    /// Returns true while command is executed
+    fn busy(&self, now: u64) -> bool {
+        self.transfer != Transfer::None || now < self.busy_until
+    }
+
+    fn read_status(&self, now: u64) -> u8 {
+        // Errors are reported after the search
+        let mut status = if now < self.busy_until {
+            STATUS_BUSY
+        } else {
+            self.status
+        };
+        match self.model {
+            FdcModel::Wd1772 => {
+                if self.motor_on(now) {
+                    status |= STATUS_MOTOR_ON_NOT_READY;
+                }
+                if self.type_i_status {
+                    status |= STATUS_SPIN_UP_HEAD_LOADED;
+                }
+            }
+            FdcModel::Wd1793 => {
+                if self.disk().is_none() {
+                    status |= STATUS_MOTOR_ON_NOT_READY;
+                }
+                if self.type_i_status && self.motor_on(now) {
+                    status |= STATUS_SPIN_UP_HEAD_LOADED;
+                }
+            }
+        }
+        if self.disk().is_some_and(FloppyDisk::write_protected) {
+            status |= STATUS_WRITE_PROTECT;
+        }
+        if self.type_i_status {
+            if self.head() == 0 {
+                status |= STATUS_TRACK0;
+            }
+            if self.index_pulse(now) {
+                status |= STATUS_INDEX_DRQ;
+            }
+        } else if self.transfer != Transfer::None {
+            status |= STATUS_BUSY | STATUS_INDEX_DRQ;
+        }
+        status
+    }
+
+    /// Returns true while the data register waits for the CPU
+    #[cfg(any(feature = "betadisk", test))]
+    pub fn drq(&self) -> bool {
+        self.transfer != Transfer::None
+    }
+
+    /// Returns true when no command is in progress. Commands without the
+    /// sector search are executed instantly, so the interrupt request is
+    /// never cleared by the status read and is raised again right after the
+    /// command write
+    #[cfg(any(feature = "betadisk", test))]
+    pub fn intrq(&self, clocks: usize) -> bool {
+        !self.busy(self.now(clocks))
+    }
+
+    /// Aborts the command in progress, as the master reset input does
+    #[cfg(any(feature = "betadisk", test))]
+    pub fn reset(&mut self) {
+        self.transfer = Transfer::None;
+        self.busy_until = 0;
+        self.type_i_status = true;
+        self.status = 0;
+        self.sector = 1;
+    }
+
+    pub fn read(&mut self, reg: u8, clocks: usize) -> u8 {
+        match reg {
+            REG_STATUS_COMMAND => self.read_status(self.now(clocks)),
+            REG_TRACK => self.track,
+            REG_SECTOR => self.sector,
+            REG_DATA => {
+                if let Transfer::Read { .. } = self.transfer {
+                    self.mark_activity(DriveActivity::Read);
+                    self.data = self.buffer[self.buffer_pos];
+                    self.buffer_pos += 1;
+                    if self.buffer_pos == self.buffer.len() {
+                        self.finish_transfer(clocks);
+                    }
+                }
+                self.data
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn write(&mut self, reg: u8, value: u8, clocks: usize) {
+        match reg {
+            REG_STATUS_COMMAND => self.execute_command(value, clocks),
+            REG_TRACK => self.track = value,
+            REG_SECTOR => self.sector = value,
+            REG_DATA => {
+                self.data = value;
+                match self.transfer {
+                    Transfer::WriteSector { .. } | Transfer::WriteTrack => {
+                        self.mark_activity(DriveActivity::Write);
+                        self.buffer[self.buffer_pos] = value;
+                        self.buffer_pos += 1;
+                    }
+                    _ => return,
+                }
+                if self.buffer_pos == self.buffer.len() {
+                    match self.transfer {
+                        Transfer::WriteSector { deleted, .. } => self.store_sector(deleted),
+                        _ => self.format_track(),
+                    }
+                    self.finish_transfer(clocks);
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute_command(&mut self, command: u8, clocks: usize) {
+        let now = self.now(clocks);
+        if command & 0xF0 == 0xD0 {
+            // Force interrupt, type I status is reported if no command is
+            // in progress
+            diag!(trace, DISK, "{:?} force interrupt", self.model);
+            if !self.busy(now) {
+                self.type_i_status = true;
+                self.status = 0;
+            } else if now < self.busy_until {
+                // Search is interrupted before its error is reported
+                self.status = 0;
+            }
+            self.transfer = Transfer::None;
+            self.busy_until = 0;
+            self.restart_motor(now);
+            return;
+        }
+        if self.busy(now) {
+            diag!(
+                debug,
+                DISK,
+                "{:?} command {:#04X} ignored, controller is busy",
+                self.model,
+                command
+            );
+            return;
+        }
+        diag!(
+            debug,
+            DISK,
+            "{:?} command {:#04X}, track {} sector {} side {}",
+            self.model,
+            command,
+            self.track,
+            self.sector,
+            self.side
+        );
+        self.restart_motor(now);
+        self.status = 0;
+        self.type_i_status = command & 0x80 == 0;
+        match command >> 4 {
+            0x0 => {
+                self.track = 0;
+                self.move_head(-(MAX_HEAD_POSITION as i16));
+                self.verify(command);
+            }
+            0x1 => {
+                let steps = self.data as i16 - self.track as i16;
+                self.track = self.data;
+                self.move_head(steps);
+                self.verify(command);
+            }
+            0x2..=0x7 => {
+                match command >> 5 {
+                    2 => self.step_in = true,
+                    3 => self.step_in = false,
+                    _ => {}
+                }
+                let step = if self.step_in { 1 } else { -1 };
+                if command & CMD_STEP_UPDATE != 0 {
+                    self.track = (self.track as i16 + step) as u8;
+                }
+                self.move_head(step);
+                self.verify(command);
+            }
+            0x8 | 0x9 => self.read_sector(command & CMD_MULTI_SECTOR != 0, now),
+            0xA | 0xB => self.write_sector(
+                command & CMD_MULTI_SECTOR != 0,
+                command & CMD_DELETED_MARK != 0,
+                now,
+            ),
+            0xC => self.read_address(now),
+            0xE => self.read_track(),
+            _ => self.write_track(),
+        }
+    }
+
+    fn move_head(&mut self, steps: i16) {
+        if let Some(drive) = self.selected.map(|d| &mut self.drives[d]) {
+            drive.head = (drive.head as i16 + steps).clamp(0, MAX_HEAD_POSITION as i16) as u8;
+        }
+    }
+
+    /// Checks that the head is on the track from the track register
+    fn verify(&mut self, command: u8) {
+        if command & CMD_VERIFY == 0 {
+            return;
+        }
+        let (head, side, track) = (self.head(), self.side, self.track);
+        let found = self
+            .disk()
+            .and_then(|disk| disk.track(head, side))
+            .is_some_and(|t| {
+                t.sectors
+                    .iter()
+                    .any(|sector| !sector.id_crc_error && sector.id[0] == track)
+            });
+        if !found {
+            self.status |= STATUS_SEEK_ERROR_RNF;
+        }
+    }
+
+    fn start_transfer(&mut self, transfer: Transfer, buffer: Vec<u8>) {
+        self.buffer = buffer;
+        self.buffer_pos = 0;
+        self.transfer = transfer;
+    }
+
+    fn finish_transfer(&mut self, clocks: usize) {
+        let now = self.now(clocks);
+        let finished = core::mem::replace(&mut self.transfer, Transfer::None);
+        self.restart_motor(now);
+        // Data is transferred even if it has CRC error, which terminates
+        // the multi-sector command
+        if core::mem::take(&mut self.crc_error) {
+            self.status |= STATUS_CRC_ERROR;
+            return;
+        }
+        // Multi-sector command ends with record not found after the last
+        // sector of the track
+        match finished {
+            Transfer::Read { multi: true } => {
+                self.sector = self.sector.wrapping_add(1);
+                self.read_sector(true, now);
+            }
+            Transfer::WriteSector {
+                multi: true,
+                deleted,
+            } => {
+                self.sector = self.sector.wrapping_add(1);
+                self.write_sector(true, deleted, now);
+            }
+            _ => {}
+        }
+    }
+
+    /// Reports the error after searching for the sector during several
+    /// revolutions. Without disk there are no index pulses, so the error is
+    /// reported immediately instead of never
+    fn fail_search(&mut self, error: u8, now: u64) {
+        self.status |= error;
+        if self.disk().is_some() {
+            self.busy_until = now + self.revolution() * SEARCH_REVOLUTIONS;
+            self.restart_motor(self.busy_until);
+        }
+    }
+
+    /// Finds sector addressed by the track and sector registers. ID fields
+    /// with CRC errors are skipped, as well as the ones without the data
+    /// field. Side is not compared by WD1772
+    fn search_sector(&mut self, now: u64) -> Option<usize> {
+        let (head, side, track, sector) = (self.head(), self.side, self.track, self.sector);
+        let mut id_crc_error = false;
+        let found = self
+            .disk()
+            .and_then(|disk| disk.track(head, side))
+            .and_then(|t| {
+                t.sectors.iter().position(|s| {
+                    if s.id[0] != track || s.id[2] != sector {
+                        return false;
+                    }
+                    id_crc_error |= s.id_crc_error;
+                    !s.id_crc_error && s.has_data()
+                })
+            });
+        match found {
+            Some(index) => {
+                self.sector_index = index;
+                Some(index)
+            }
+            None => {
+                let crc = if id_crc_error { STATUS_CRC_ERROR } else { 0 };
+                self.fail_search(STATUS_SEEK_ERROR_RNF | crc, now);
+                None
+            }
+        }
+    }
+
+    fn read_sector(&mut self, multi: bool, now: u64) {
+        let Some(index) = self.search_sector(now) else {
+            return;
+        };
+        let (head, side) = (self.head(), self.side);
+        let Some(disk) = self.disk() else {
+            return;
+        };
+        let data = disk.sector_data(head, side, index).unwrap_or_default();
+        let crc_error = disk.data_crc_error(head, side, index);
+        let deleted = disk
+            .track(head, side)
+            .is_some_and(|t| t.sectors[index].deleted);
+        self.crc_error = crc_error;
+        if deleted {
+            self.status |= STATUS_RECORD_TYPE;
+        }
+        self.start_transfer(Transfer::Read { multi }, data);
+    }
+
+    fn write_sector(&mut self, multi: bool, deleted: bool, now: u64) {
+        let Some(index) = self.search_sector(now) else {
+            return;
+        };
+        let (head, side) = (self.head(), self.side);
+        let Some(disk) = self.disk() else {
+            return;
+        };
+        if disk.write_protected() {
+            return;
+        }
+        let size = disk
+            .track(head, side)
+            .map_or(0, |t| t.sectors[index].size());
+        self.start_transfer(Transfer::WriteSector { multi, deleted }, vec![0; size]);
+    }
+
+    fn store_sector(&mut self, deleted: bool) {
+        let (head, side, index) = (self.head(), self.side, self.sector_index);
+        if let Some(disk) = self.selected.and_then(|d| self.drives[d].disk.as_mut()) {
+            disk.write_sector(head, side, index, &self.buffer, deleted);
+        }
+    }
+
+    /// Returns the next ID field of the track with its CRC as stored on
+    /// the disk
+    fn read_address(&mut self, now: u64) {
+        let (head, side) = (self.head(), self.side);
+        let Some(track) = self.disk().and_then(|disk| disk.track(head, side)) else {
+            self.fail_search(STATUS_SEEK_ERROR_RNF, now);
+            return;
+        };
+        if track.sectors.is_empty() {
+            self.fail_search(STATUS_SEEK_ERROR_RNF, now);
+            return;
+        }
+        let index = self.next_id % track.sectors.len();
+        let sector = &track.sectors[index];
+        let mut address = sector.id.to_vec();
+        address.extend(sector.id_crc);
+        let id_crc_error = sector.id_crc_error;
+        self.next_id = index + 1;
+        if id_crc_error {
+            self.status |= STATUS_CRC_ERROR;
+        }
+        // Track from the ID field is loaded into the sector register
+        self.sector = address[0];
+        self.start_transfer(Transfer::Read { multi: false }, address);
+    }
+
+    /// Returns raw track contents, which are generated in the standard IBM
+    /// format for images without raw tracks
+    fn read_track(&mut self) {
+        let (head, side) = (self.head(), self.side);
+        let Some(disk) = self.disk() else {
+            self.status |= STATUS_SEEK_ERROR_RNF;
+            return;
+        };
+        if let Some(raw) = disk.raw_track(head, side) {
+            let raw = raw.to_vec();
+            self.start_transfer(Transfer::Read { multi: false }, raw);
+            return;
+        }
+        let mut track = vec![GAP; 80];
+        let sectors = disk.track(head, side).map_or(&[][..], |t| &t.sectors);
+        for (index, sector) in sectors.iter().enumerate() {
+            append_field(&mut track, MARK_ID, &sector.id);
+            track.extend([GAP; 22]);
+            if let Some(data) = disk.sector_data(head, side, index) {
+                append_field(&mut track, MARK_DATA, &data);
+            }
+            track.extend([GAP; 24]);
+        }
+        track.resize(TRACK_LENGTH.max(track.len()), GAP);
+        self.start_transfer(Transfer::Read { multi: false }, track);
+    }
+
+    fn write_track(&mut self) {
+        if self.disk().is_none() {
+            self.status |= STATUS_SEEK_ERROR_RNF;
+            return;
+        }
+        if self.disk().is_some_and(FloppyDisk::write_protected) {
+            return;
+        }
+        self.start_transfer(Transfer::WriteTrack, vec![0; TRACK_LENGTH]);
+    }
+
+    /// Stores the track written by the write track command. Images without
+    /// raw tracks keep only the sectors which match the disk layout
+    fn format_track(&mut self) {
+        let (head, side) = (self.head(), self.side);
+        let Some(disk) = self.selected.and_then(|d| self.drives[d].disk.as_mut()) else {
+            return;
+        };
+        if disk.write_raw_track(head, side, &self.buffer) {
+            return;
+        }
+        let mut id = None;
+        let mut pos = 0;
+        while pos < self.buffer.len() {
+            let byte = self.buffer[pos];
+            pos += 1;
+            if byte != WRITE_TRACK_SYNC {
+                continue;
+            }
+            while self.buffer.get(pos) == Some(&WRITE_TRACK_SYNC) {
+                pos += 1;
+            }
+            match self.buffer.get(pos) {
+                Some(&MARK_ID) => {
+                    id = self
+                        .buffer
+                        .get(pos + 1..pos + 5)
+                        .map(|f| (f[0], f[2], 128 << (f[3] & 0x03)));
+                    pos += 5;
+                }
+                Some(&MARK_DATA) | Some(&MARK_DELETED_DATA) => {
+                    let Some((id_track, id_sector, size)) = id.take() else {
+                        pos += 1;
+                        continue;
+                    };
+                    let data = self.buffer.get(pos + 1..pos + 1 + size);
+                    let index = disk.find_sector(head, side, id_track, id_sector);
+                    if let (Some(index), Some(data)) = (index, data) {
+                        disk.write_sector(head, side, index, data, false);
+                    }
+                    pos += 1 + size;
+                }
+                _ => {}
+            }
+            if self.buffer.get(pos) == Some(&WRITE_TRACK_CRC) {
+                pos += 1;
+            }
+        }
+    }
+}
+
+/// Appends address mark with the sync bytes, field data and CRC
+fn append_field(track: &mut Vec<u8>, mark: u8, data: &[u8]) {
+    track.extend([0x00; 12]);
+    track.extend([SYNC, SYNC, SYNC, mark]);
+    track.extend_from_slice(data);
+    track.extend(crc16([SYNC, SYNC, SYNC, mark].iter().chain(data)).to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        disk::{
+            tests::{udi_image, TrackBuilder},
+            DiskGeometry, TrackOrder,
+        },
+        *,
+    };
+
+    const FREQ: usize = 3_500_000;
+
+    fn controller() -> Wd17xx {
+        let geometry = DiskGeometry::PLUSD;
+        let data = (0..geometry.image_size())
+            .map(|idx| (idx / geometry.sector_size()) as u8)
+            .collect();
+        let mut fdc = Wd17xx::new(FdcModel::Wd1772, 2, FREQ);
+        fdc.drive_mut(0).unwrap().disk =
+            Some(FloppyDisk::new(geometry, TrackOrder::SidesInterleaved, data).unwrap());
+        fdc.select_drive(Some(0));
+        fdc
+    }
+
+    fn read_data(fdc: &mut Wd17xx) -> Vec<u8> {
+        let mut data = Vec::new();
+        while fdc.read(REG_STATUS_COMMAND, 0) & STATUS_INDEX_DRQ != 0 {
+            data.push(fdc.read(REG_DATA, 0));
+        }
+        data
+    }
+
+    #[test]
+    fn seek_and_restore() {
+        let mut fdc = controller();
+        fdc.write(REG_DATA, 5, 0);
+        fdc.write(REG_STATUS_COMMAND, 0x10 | CMD_VERIFY, 0);
+        assert_eq!(fdc.read(REG_TRACK, 0), 5);
+        let status = fdc.read(REG_STATUS_COMMAND, 0);
+        assert_eq!(
+            status & (STATUS_BUSY | STATUS_TRACK0 | STATUS_SEEK_ERROR_RNF),
+            0
+        );
+        assert_eq!(
+            status & STATUS_MOTOR_ON_NOT_READY,
+            STATUS_MOTOR_ON_NOT_READY
+        );
+
+        // Step-out with track register update
+        fdc.write(REG_STATUS_COMMAND, 0x70, 0);
+        assert_eq!(fdc.read(REG_TRACK, 0), 4);
+
+        // Track register does not match the head position
+        fdc.write(REG_TRACK, 10, 0);
+        fdc.write(REG_STATUS_COMMAND, 0x40 | CMD_VERIFY, 0);
+        let status = fdc.read(REG_STATUS_COMMAND, 0);
+        assert_eq!(status & STATUS_SEEK_ERROR_RNF, STATUS_SEEK_ERROR_RNF);
+
+        fdc.write(REG_STATUS_COMMAND, 0x00, 0);
+        assert_eq!(fdc.read(REG_TRACK, 0), 0);
+        let status = fdc.read(REG_STATUS_COMMAND, 0);
+        assert_eq!(status & STATUS_TRACK0, STATUS_TRACK0);
+    }
+
+    #[test]
+    fn index_pulse_while_motor_is_on() {
+        let mut fdc = controller();
+        fdc.write(REG_STATUS_COMMAND, 0x00, 0);
+        let revolution = FREQ / REVOLUTIONS_PER_SECOND;
+        assert_eq!(
+            fdc.read(REG_STATUS_COMMAND, 10) & STATUS_INDEX_DRQ,
+            STATUS_INDEX_DRQ
+        );
+        assert_eq!(
+            fdc.read(REG_STATUS_COMMAND, revolution / 2) & STATUS_INDEX_DRQ,
+            0
+        );
+        fdc.new_frame(revolution * MOTOR_OFF_REVOLUTIONS as usize);
+        let status = fdc.read(REG_STATUS_COMMAND, 10);
+        assert_eq!(status & (STATUS_INDEX_DRQ | STATUS_MOTOR_ON_NOT_READY), 0);
+    }
+
+    #[test]
+    fn read_and_write_sector() {
+        let mut fdc = controller();
+        fdc.write(REG_DATA, 1, 0);
+        fdc.write(REG_STATUS_COMMAND, 0x10, 0);
+        fdc.set_side(1);
+        fdc.write(REG_SECTOR, 3, 0);
+        fdc.write(REG_STATUS_COMMAND, 0x80, 0);
+        // Sector 3 of track 1 side 1 in the MGT image
+        assert_eq!(read_data(&mut fdc), vec![32; 512]);
+        assert_eq!(fdc.read(REG_STATUS_COMMAND, 0) & STATUS_BUSY, 0);
+
+        fdc.write(REG_STATUS_COMMAND, 0xA0, 0);
+        for idx in 0..512 {
+            assert_eq!(
+                fdc.read(REG_STATUS_COMMAND, 0) & STATUS_INDEX_DRQ,
+                STATUS_INDEX_DRQ
+            );
+            fdc.write(REG_DATA, idx as u8, 0);
+        }
+        assert_eq!(fdc.read(REG_STATUS_COMMAND, 0) & STATUS_BUSY, 0);
+        fdc.write(REG_STATUS_COMMAND, 0x80, 0);
+        let expected = (0..512).map(|idx| idx as u8).collect::<Vec<_>>();
+        assert_eq!(read_data(&mut fdc), expected);
+    }
+
+    #[test]
+    fn multi_sector_read_ends_with_record_not_found() {
+        let mut fdc = controller();
+        fdc.write(REG_SECTOR, 9, 0);
+        fdc.write(REG_STATUS_COMMAND, 0x90, 0);
+        let data = read_data(&mut fdc);
+        assert_eq!(data.len(), 1024);
+        assert_eq!(data[0], 8);
+        assert_eq!(data[512], 9);
+        let status = fdc.read(REG_STATUS_COMMAND, 0);
+        assert_eq!(status & (STATUS_SEEK_ERROR_RNF | STATUS_BUSY), STATUS_BUSY);
+        let timeout = FREQ / REVOLUTIONS_PER_SECOND * SEARCH_REVOLUTIONS as usize;
+        let status = fdc.read(REG_STATUS_COMMAND, timeout);
+        assert_eq!(
+            status & (STATUS_SEEK_ERROR_RNF | STATUS_BUSY),
+            STATUS_SEEK_ERROR_RNF
+        );
+        assert_eq!(fdc.read(REG_SECTOR, 0), 11);
+    }
+
+    #[test]
+    fn missing_sector_and_disk() {
+        let mut fdc = controller();
+        fdc.write(REG_SECTOR, 11, 0);
+        fdc.write(REG_STATUS_COMMAND, 0x80, 0);
+        // Sector is searched during 5 revolutions
+        let timeout = FREQ / REVOLUTIONS_PER_SECOND * SEARCH_REVOLUTIONS as usize;
+        let status = fdc.read(REG_STATUS_COMMAND, timeout - 1);
+        assert_eq!(status & (STATUS_SEEK_ERROR_RNF | STATUS_BUSY), STATUS_BUSY);
+        fdc.write(REG_STATUS_COMMAND, 0x80, timeout - 1);
+        let status = fdc.read(REG_STATUS_COMMAND, timeout);
+        assert_eq!(
+            status & (STATUS_SEEK_ERROR_RNF | STATUS_BUSY),
+            STATUS_SEEK_ERROR_RNF
+        );
+
+        // Without disk there are no index pulses to count
+        fdc.select_drive(Some(1));
+        fdc.write(REG_SECTOR, 1, timeout);
+        fdc.write(REG_STATUS_COMMAND, 0x80, timeout);
+        let status = fdc.read(REG_STATUS_COMMAND, timeout);
+        assert_eq!(
+            status & (STATUS_SEEK_ERROR_RNF | STATUS_BUSY),
+            STATUS_SEEK_ERROR_RNF
+        );
+    }
+
+    #[test]
+    fn read_address_returns_next_id() {
+        let mut fdc = controller();
+        fdc.write(REG_DATA, 2, 0);
+        fdc.write(REG_STATUS_COMMAND, 0x10, 0);
+        fdc.write(REG_STATUS_COMMAND, 0xC0, 0);
+        let first = read_data(&mut fdc);
+        assert_eq!(first[..4], [2, 0, 1, 2]);
+        assert_eq!(
+            u16::from_be_bytes([first[4], first[5]]),
+            crc16([SYNC, SYNC, SYNC, MARK_ID].iter().chain(&first[..4]))
+        );
+        assert_eq!(fdc.read(REG_SECTOR, 0), 2);
+        fdc.write(REG_STATUS_COMMAND, 0xC0, 0);
+        assert_eq!(read_data(&mut fdc)[2], 2);
+    }
+
+    #[test]
+    fn write_track_formats_sectors() {
+        let mut fdc = controller();
+        fdc.write(REG_STATUS_COMMAND, 0xF0, 0);
+        let mut track = vec![GAP; 80];
+        for id in 1..=10u8 {
+            track.extend([0x00; 12]);
+            track.extend([WRITE_TRACK_SYNC; 3]);
+            track.extend([MARK_ID, 0, 0, id, 2, WRITE_TRACK_CRC]);
+            track.extend([GAP; 22]);
+            track.extend([0x00; 12]);
+            track.extend([WRITE_TRACK_SYNC; 3]);
+            track.push(MARK_DATA);
+            track.extend([0xE5 ^ id; 512]);
+            track.push(WRITE_TRACK_CRC);
+            track.extend([GAP; 24]);
+        }
+        track.resize(TRACK_LENGTH, GAP);
+        for byte in track {
+            fdc.write(REG_DATA, byte, 0);
+        }
+        assert_eq!(fdc.read(REG_STATUS_COMMAND, 0) & STATUS_BUSY, 0);
+
+        fdc.write(REG_SECTOR, 7, 0);
+        fdc.write(REG_STATUS_COMMAND, 0x80, 0);
+        assert_eq!(read_data(&mut fdc), vec![0xE5 ^ 7; 512]);
+    }
+
+    #[test]
+    fn read_track_contains_sectors() {
+        let mut fdc = controller();
+        fdc.write(REG_STATUS_COMMAND, 0xE0, 0);
+        let track = read_data(&mut fdc);
+        assert_eq!(track.len(), TRACK_LENGTH);
+        let data_marks = track
+            .windows(4)
+            .filter(|w| *w == [SYNC, SYNC, SYNC, MARK_DATA])
+            .count();
+        assert_eq!(data_marks, 10);
+    }
+
+    #[test]
+    fn multi_sector_read_continues_after_restore() {
+        let mut fdc = controller();
+        fdc.write(REG_SECTOR, 2, 0);
+        fdc.write(REG_STATUS_COMMAND, 0x90, 0);
+        for _ in 0..700 {
+            fdc.read(REG_DATA, 0);
+        }
+        let state = fdc.state();
+        let expected = read_data(&mut fdc);
+
+        let mut restored = controller();
+        restored.restore(&state).unwrap();
+        assert_eq!(read_data(&mut restored), expected);
+        assert_eq!(restored.read(REG_SECTOR, 0), 11);
+
+        // Disk created from other image or missing disk is rejected
+        let mut other = controller();
+        let disk = other.drive_mut(0).unwrap().disk.take().unwrap();
+        let mut data = disk.into_data();
+        data[0] ^= 0xFF;
+        let geometry = DiskGeometry::PLUSD;
+        other.drive_mut(0).unwrap().disk =
+            Some(FloppyDisk::new(geometry, TrackOrder::SidesInterleaved, data).unwrap());
+        assert_eq!(other.restore(&state), Err(DiskLoadError::DiskMismatch));
+        assert_eq!(other.read(REG_STATUS_COMMAND, 0) & STATUS_BUSY, 0);
+        other.drive_mut(0).unwrap().disk = None;
+        assert_eq!(other.restore(&state), Err(DiskLoadError::DiskMismatch));
+    }
+
+    #[test]
+    fn drive_status_reports_frame_activity() {
+        let mut fdc = controller();
+        assert_eq!(fdc.drive_status(0), Some(DriveStatus::default()));
+        assert_eq!(fdc.drive_status(2), None);
+
+        fdc.write(REG_STATUS_COMMAND, 0x80, 0);
+        fdc.read(REG_DATA, 0);
+        fdc.write(REG_STATUS_COMMAND, 0xD0, 0);
+        fdc.write(REG_STATUS_COMMAND, 0xA0, 0);
+        fdc.write(REG_DATA, 0, 0);
+        fdc.new_frame(70000);
+        let status = fdc.drive_status(0).unwrap();
+        assert!(status.motor_on);
+        assert_eq!(status.activity, DriveActivity::Write);
+        assert!(!fdc.drive_status(1).unwrap().motor_on);
+
+        fdc.new_frame(70000);
+        assert_eq!(fdc.drive_status(0).unwrap().activity, DriveActivity::Idle);
+        let revolution = FREQ / REVOLUTIONS_PER_SECOND;
+        fdc.new_frame(revolution * MOTOR_OFF_REVOLUTIONS as usize);
+        assert!(!fdc.drive_status(0).unwrap().motor_on);
+    }
+
+    #[test]
+    fn wd1793_reports_head_loaded_and_not_ready() {
+        let mut fdc = controller();
+        fdc.model = FdcModel::Wd1793;
+        fdc.write(REG_STATUS_COMMAND, 0x00, 0);
+        let status = fdc.read(REG_STATUS_COMMAND, 0);
+        assert_eq!(
+            status & (STATUS_MOTOR_ON_NOT_READY | STATUS_SPIN_UP_HEAD_LOADED),
+            STATUS_SPIN_UP_HEAD_LOADED
+        );
+
+        fdc.select_drive(Some(1));
+        let status = fdc.read(REG_STATUS_COMMAND, 0);
+        assert_eq!(
+            status & STATUS_MOTOR_ON_NOT_READY,
+            STATUS_MOTOR_ON_NOT_READY
+        );
+    }
+
+    #[test]
+    fn reset_aborts_transfer() {
+        let mut fdc = controller();
+        fdc.write(REG_STATUS_COMMAND, 0x80, 0);
+        assert!(fdc.drq() && !fdc.intrq(0));
+        fdc.reset();
+        assert!(!fdc.drq() && fdc.intrq(0));
+        assert_eq!(fdc.read(REG_STATUS_COMMAND, 0) & STATUS_BUSY, 0);
+    }
+
+    #[test]
+    fn protected_disk_sectors() {
+        let track = TrackBuilder::new()
+            .field(MARK_ID, &[0, 0, 1, 3], true)
+            .field(MARK_DATA, &[0x11; 1024], true)
+            .field(MARK_ID, &[0, 0, 2, 2], true)
+            .field(MARK_DATA, &[0x22; 512], false)
+            .field(MARK_ID, &[0, 0, 3, 2], true)
+            .field(MARK_ID, &[0, 0, 4, 2], false)
+            .field(MARK_DATA, &[0x44; 512], true);
+        let mut fdc = controller();
+        fdc.drive_mut(0).unwrap().disk = Some(FloppyDisk::from_udi(udi_image(&[track])).unwrap());
+        let timeout = FREQ / REVOLUTIONS_PER_SECOND * SEARCH_REVOLUTIONS as usize;
+        let errors = STATUS_BUSY | STATUS_CRC_ERROR | STATUS_SEEK_ERROR_RNF;
+
+        // Sector of nonstandard size
+        fdc.write(REG_STATUS_COMMAND, 0x80, 0);
+        assert_eq!(read_data(&mut fdc), vec![0x11; 1024]);
+        assert_eq!(fdc.read(REG_STATUS_COMMAND, 0) & errors, 0);
+
+        // Data with CRC error is transferred, then multi-sector read stops
+        fdc.write(REG_SECTOR, 2, 0);
+        fdc.write(REG_STATUS_COMMAND, 0x90, 0);
+        assert_eq!(read_data(&mut fdc), vec![0x22; 512]);
+        assert_eq!(fdc.read(REG_STATUS_COMMAND, 0) & errors, STATUS_CRC_ERROR);
+        assert_eq!(fdc.read(REG_SECTOR, 0), 2);
+
+        // Missing data address mark
+        fdc.write(REG_SECTOR, 3, 0);
+        fdc.write(REG_STATUS_COMMAND, 0x80, 0);
+        assert_eq!(read_data(&mut fdc), []);
+        assert_eq!(
+            fdc.read(REG_STATUS_COMMAND, timeout - 1) & errors,
+            STATUS_BUSY
+        );
+        let status = fdc.read(REG_STATUS_COMMAND, timeout);
+        assert_eq!(status & errors, STATUS_SEEK_ERROR_RNF);
+
+        // ID field with CRC error
+        fdc.write(REG_SECTOR, 4, timeout);
+        fdc.write(REG_STATUS_COMMAND, 0x80, timeout);
+        let status = fdc.read(REG_STATUS_COMMAND, timeout * 2);
+        assert_eq!(status & errors, STATUS_SEEK_ERROR_RNF | STATUS_CRC_ERROR);
+
+        // Read address returns stored ID fields in the track order
+        let mut ids = Vec::new();
+        for _ in 0..5 {
+            fdc.write(REG_STATUS_COMMAND, 0xC0, timeout * 2);
+            let crc_error = fdc.read(REG_STATUS_COMMAND, timeout * 2) & STATUS_CRC_ERROR != 0;
+            ids.push((read_data(&mut fdc)[2], crc_error));
+        }
+        assert_eq!(
+            ids,
+            [(1, false), (2, false), (3, false), (4, true), (1, false)]
+        );
+
+        fdc.write(REG_STATUS_COMMAND, 0xE0, timeout * 2);
+        let track = read_data(&mut fdc);
+        assert_eq!(track.len(), 6250);
+        assert_eq!(track[80 + 12..80 + 18], [SYNC, SYNC, SYNC, MARK_ID, 0, 0]);
+    }
+
+    #[test]
+    fn write_track_on_raw_track_image() {
+        let mut fdc = controller();
+        fdc.drive_mut(0).unwrap().disk =
+            Some(FloppyDisk::from_udi(udi_image(&[TrackBuilder::new()])).unwrap());
+        fdc.write(REG_STATUS_COMMAND, 0xF0, 0);
+        let mut track = vec![GAP; 80];
+        track.extend([0x00; 12]);
+        track.extend([WRITE_TRACK_SYNC; 3]);
+        track.extend([MARK_ID, 0, 0, 9, 1, WRITE_TRACK_CRC]);
+        track.extend([GAP; 22]);
+        track.extend([0x00; 12]);
+        track.extend([WRITE_TRACK_SYNC; 3]);
+        track.push(MARK_DELETED_DATA);
+        track.extend([0xE5; 256]);
+        track.push(WRITE_TRACK_CRC);
+        track.resize(TRACK_LENGTH, GAP);
+        for byte in track {
+            fdc.write(REG_DATA, byte, 0);
+        }
+
+        fdc.write(REG_SECTOR, 9, 0);
+        fdc.write(REG_STATUS_COMMAND, 0x80, 0);
+        let status = fdc.read(REG_STATUS_COMMAND, 0);
+        assert_eq!(status & STATUS_RECORD_TYPE, STATUS_RECORD_TYPE);
+        assert_eq!(read_data(&mut fdc), vec![0xE5; 256]);
+        let status = fdc.read(REG_STATUS_COMMAND, 0);
+        assert_eq!(status & (STATUS_BUSY | STATUS_CRC_ERROR), 0);
+    }
+
+    #[test]
+    fn write_protected_disk() {
+        let mut fdc = controller();
+        let disk = fdc.drive_mut(0).unwrap().disk.as_mut().unwrap();
+        disk.set_write_protected(true);
+        fdc.write(REG_STATUS_COMMAND, 0xA0, 0);
+        let status = fdc.read(REG_STATUS_COMMAND, 0);
+        assert_eq!(
+            status & (STATUS_WRITE_PROTECT | STATUS_BUSY),
+            STATUS_WRITE_PROTECT
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! TR-DOS disk images. TRD is a dump of the logical tracks (side 0 and side
+//! 1 of each cylinder), each of 16 sectors of 256 bytes. SCL is a list of
+//! files, which is laid out on the empty 80-track double-sided disk the way
+//! TR-DOS writes them: catalog and disk info on track 0, file data from
+//! track 1 without gaps
+use super::disk::{DiskGeometry, FloppyDisk, TrackOrder};
+use crate::error::DiskLoadError;
+use alloc::{vec, vec::Vec};
+
+const SECTOR_SIZE: usize = 256;
+const SECTORS_PER_TRACK: usize = 16;
+const CYLINDER_SIZE: usize = 2 * SECTORS_PER_TRACK * SECTOR_SIZE;
+/// Head can't move further, see [super::MAX_HEAD_POSITION]
+const MAX_CYLINDERS: usize = super::MAX_HEAD_POSITION as usize + 1;
+/// Largest TRD image, SCL images are always smaller
+pub(crate) const MAX_IMAGE_SIZE: usize = MAX_CYLINDERS * CYLINDER_SIZE;
+
+const SCL_SIGNATURE: &[u8; 8] = b"SINCLAIR";
+const SCL_HEADER_SIZE: usize = 9;
+/// Name, extension, parameters and length in sectors
+const SCL_ENTRY_SIZE: usize = 14;
+const MAX_FILES: usize = 128;
+/// Catalog entry is SCL entry followed by the first sector and track
+const CATALOG_ENTRY_SIZE: usize = 16;
+/// Disk info is in the logical sector 8 of track 0
+const INFO_OFFSET: usize = 8 * SECTOR_SIZE;
+const INFO_FIRST_FREE_SECTOR: usize = 0xE1;
+const INFO_FIRST_FREE_TRACK: usize = 0xE2;
+const INFO_DISK_TYPE: usize = 0xE3;
+const INFO_FILE_COUNT: usize = 0xE4;
+const INFO_FREE_SECTORS: usize = 0xE5;
+const INFO_TRDOS_ID: usize = 0xE7;
+const INFO_PASSWORD: usize = 0xEA;
+const INFO_LABEL: usize = 0xF5;
+/// 80 tracks, 2 sides
+const DISK_TYPE_80_DS: u8 = 0x16;
+const TRDOS_ID: u8 = 0x10;
+
+/// Creates disk from the TRD image, truncated image is padded with zeros
+/// to the whole cylinder
+pub(crate) fn trd_disk(mut data: Vec<u8>) -> Result<FloppyDisk, DiskLoadError> {
+    let cylinders = data.len().div_ceil(CYLINDER_SIZE);
+    if cylinders == 0 || cylinders > MAX_CYLINDERS {
+        return Err(DiskLoadError::InvalidImageSize);
+    }
+    data.resize(cylinders * CYLINDER_SIZE, 0);
+    let geometry = DiskGeometry {
+        tracks: cylinders as u8,
+        ..DiskGeometry::TRDOS
+    };
+    FloppyDisk::new(geometry, TrackOrder::SidesInterleaved, data)
+}
+
+/// Converts SCL image to TRD one. Checksum at the end of the image is not
+/// checked, as some tools do not calculate it
+pub(crate) fn scl_to_trd(scl: &[u8]) -> Result<Vec<u8>, DiskLoadError> {
+    let header = scl
+        .get(..SCL_HEADER_SIZE)
+        .ok_or(DiskLoadError::InvalidImageSize)?;
+    let count = header[8] as usize;
+    if header[..8] != *SCL_SIGNATURE || count > MAX_FILES {
+        return Err(DiskLoadError::InvalidImageHeader);
+    }
+    let entries_end = SCL_HEADER_SIZE + count * SCL_ENTRY_SIZE;
+    let entries = scl
+        .get(SCL_HEADER_SIZE..entries_end)
+        .ok_or(DiskLoadError::InvalidImageSize)?;
+
+    let geometry = DiskGeometry::TRDOS;
+    let mut trd = vec![0; geometry.image_size()];
+    let mut data_pos = entries_end;
+    // Logical sector index from the disk start
+    let mut next_sector = SECTORS_PER_TRACK;
+    for (index, entry) in entries.chunks(SCL_ENTRY_SIZE).enumerate() {
+        let size = entry[13] as usize * SECTOR_SIZE;
+        let data = scl
+            .get(data_pos..data_pos + size)
+            .ok_or(DiskLoadError::InvalidImageSize)?;
+        let offset = next_sector * SECTOR_SIZE;
+        trd.get_mut(offset..offset + size)
+            .ok_or(DiskLoadError::InvalidImageSize)?
+            .copy_from_slice(data);
+        let catalog = &mut trd[index * CATALOG_ENTRY_SIZE..(index + 1) * CATALOG_ENTRY_SIZE];
+        catalog[..SCL_ENTRY_SIZE].copy_from_slice(entry);
+        catalog[14] = (next_sector % SECTORS_PER_TRACK) as u8;
+        catalog[15] = (next_sector / SECTORS_PER_TRACK) as u8;
+        next_sector += entry[13] as usize;
+        data_pos += size;
+    }
+
+    let total_sectors = trd.len() / SECTOR_SIZE;
+    let info = &mut trd[INFO_OFFSET..INFO_OFFSET + SECTOR_SIZE];
+    info[INFO_FIRST_FREE_SECTOR] = (next_sector % SECTORS_PER_TRACK) as u8;
+    info[INFO_FIRST_FREE_TRACK] = (next_sector / SECTORS_PER_TRACK) as u8;
+    info[INFO_DISK_TYPE] = DISK_TYPE_80_DS;
+    info[INFO_FILE_COUNT] = count as u8;
+    let free = (total_sectors - next_sector) as u16;
+    info[INFO_FREE_SECTORS..INFO_FREE_SECTORS + 2].copy_from_slice(&free.to_le_bytes());
+    info[INFO_TRDOS_ID] = TRDOS_ID;
+    info[INFO_PASSWORD..INFO_PASSWORD + 9].fill(b' ');
+    info[INFO_LABEL..INFO_LABEL + 8].fill(b' ');
+    Ok(trd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scl(files: &[(&[u8; 8], u8)]) -> Vec<u8> {
+        let mut scl = SCL_SIGNATURE.to_vec();
+        scl.push(files.len() as u8);
+        for (name, sectors) in files {
+            scl.extend_from_slice(*name);
+            scl.extend([b'C', 0x00, 0x80, 0x00, 0x01, *sectors]);
+        }
+        for (index, (_, sectors)) in files.iter().enumerate() {
+            scl.extend(vec![index as u8 + 1; *sectors as usize * SECTOR_SIZE]);
+        }
+        // Checksum
+        scl.extend([0; 4]);
+        scl
+    }
+
+    #[test]
+    fn scl_files_are_laid_out_from_track_1() {
+        let trd = scl_to_trd(&scl(&[(b"first   ", 2), (b"second  ", 15)])).unwrap();
+        assert_eq!(&trd[..8], b"first   ");
+        // First file at track 1 sector 0, second one right after it
+        assert_eq!(trd[14..16], [0, 1]);
+        assert_eq!(&trd[16..24], b"second  ");
+        assert_eq!(trd[30..32], [2, 1]);
+        let track_1 = SECTORS_PER_TRACK * SECTOR_SIZE;
+        assert_eq!(trd[track_1], 1);
+        assert_eq!(trd[track_1 + 2 * SECTOR_SIZE], 2);
+        assert_eq!(trd[track_1 + 17 * SECTOR_SIZE - 1], 2);
+        assert_eq!(trd[track_1 + 17 * SECTOR_SIZE], 0);
+
+        let info = &trd[INFO_OFFSET..];
+        // First free sector 1 of track 2
+        assert_eq!(info[INFO_FIRST_FREE_SECTOR..INFO_FILE_COUNT], [1, 2, 0x16]);
+        assert_eq!(info[INFO_FILE_COUNT], 2);
+        let free = u16::from_le_bytes([info[INFO_FREE_SECTORS], info[INFO_FREE_SECTORS + 1]]);
+        assert_eq!(free, 2560 - 16 - 17);
+        assert_eq!(info[INFO_TRDOS_ID], TRDOS_ID);
+    }
+
+    #[test]
+    fn invalid_scl() {
+        let mut image = scl(&[(b"file    ", 2)]);
+        assert!(matches!(
+            scl_to_trd(&image[..image.len() - 10]),
+            Err(DiskLoadError::InvalidImageSize)
+        ));
+        image[0] = b'X';
+        assert!(matches!(
+            scl_to_trd(&image),
+            Err(DiskLoadError::InvalidImageHeader)
+        ));
+    }
+
+    #[test]
+    fn truncated_trd_is_padded() {
+        let mut data = vec![0; CYLINDER_SIZE + 100];
+        // Track 1 side 0 sector 1
+        data[CYLINDER_SIZE] = 0x42;
+        let disk = trd_disk(data).unwrap();
+        let index = disk.find_sector(1, 0, 1, 1).unwrap();
+        assert_eq!(disk.sector_data(1, 0, index).unwrap()[0], 0x42);
+        assert!(disk.track(1, 1).is_some() && disk.track(2, 0).is_none());
+        assert!(matches!(
+            trd_disk(Vec::new()),
+            Err(DiskLoadError::InvalidImageSize)
+        ));
+    }
+}
@@ -0,0 +1,452 @@
+//! Minimal 8-bit IDE interface with DivIDE-compatible port addressing.
+//! Only single (master) PIO device is emulated, supported commands are
+//! IDENTIFY DEVICE, READ SECTORS and WRITE SECTORS
+use crate::{
+    diagnostics::DISK,
+    error::IoError,
+    host::{BlockDevice, SECTOR_SIZE},
+};
+
+const PORT_MASK: u16 = 0x00E3;
+const PORT_VALUE: u16 = 0x00A3;
+
+const REG_DATA: u8 = 0;
+const REG_ERROR_FEATURES: u8 = 1;
+const REG_SECTOR_COUNT: u8 = 2;
+const REG_LBA0: u8 = 3;
+const REG_LBA1: u8 = 4;
+const REG_LBA2: u8 = 5;
+const REG_DRIVE_HEAD: u8 = 6;
+const REG_STATUS_COMMAND: u8 = 7;
+
+const STATUS_ERR: u8 = 0x01;
+const STATUS_DRQ: u8 = 0x08;
+const STATUS_DSC: u8 = 0x10;
+const STATUS_DRDY: u8 = 0x40;
+
+const ERROR_ABRT: u8 = 0x04;
+const ERROR_IDNF: u8 = 0x10;
+const ERROR_UNC: u8 = 0x40;
+
+const DRIVE_HEAD_SLAVE: u8 = 0x10;
+const DRIVE_HEAD_LBA: u8 = 0x40;
+
+const CMD_READ_SECTORS: u8 = 0x20;
+const CMD_READ_SECTORS_NO_RETRY: u8 = 0x21;
+const CMD_WRITE_SECTORS: u8 = 0x30;
+const CMD_WRITE_SECTORS_NO_RETRY: u8 = 0x31;
+const CMD_INIT_DEVICE_PARAMS: u8 = 0x91;
+const CMD_FLUSH_CACHE: u8 = 0xE7;
+const CMD_IDENTIFY_DEVICE: u8 = 0xEC;
+const CMD_SET_FEATURES: u8 = 0xEF;
+
+// Default CHS geometry, reported via IDENTIFY DEVICE
+const GEOMETRY_HEADS: u32 = 16;
+const GEOMETRY_SECTORS: u32 = 63;
+const GEOMETRY_MAX_CYLINDERS: u32 = 16383;
+
+const MODEL_NAME: &[u8] = b"RUSTZX HARD DISK";
+const SERIAL_NUMBER: &[u8] = b"RZX0001";
+const FIRMWARE_REVISION: &[u8] = b"1.0";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Transfer {
+    None,
+    Read { sectors_left: u16 },
+    Write { sectors_left: u16 },
+}
+
+/// IDE interface with attached block device
+pub(crate) struct IdeInterface<B: BlockDevice> {
+    device: B,
+    buffer: [u8; SECTOR_SIZE],
+    buffer_pos: usize,
+    transfer: Transfer,
+    error: u8,
+    sector_count: u8,
+    lba: [u8; 3],
+    drive_head: u8,
+    status: u8,
+}
+
+impl<B: BlockDevice> IdeInterface<B> {
+    pub fn new(device: B) -> Self {
+        Self {
+            device,
+            buffer: [0; SECTOR_SIZE],
+            buffer_pos: 0,
+            transfer: Transfer::None,
+            error: 0,
+            sector_count: 1,
+            lba: [1, 0, 0],
+            drive_head: 0,
+            status: STATUS_DRDY | STATUS_DSC,
+        }
+    }
+
+    /// Returns attached block device
+    pub fn into_device(self) -> B {
+        self.device
+    }
+
+    pub fn flush(&mut self) -> Result<(), IoError> {
+        self.device.flush()
+    }
+
+    /// Returns true if port belongs to the interface
+    pub fn handles_port(port: u16) -> bool {
+        port & PORT_MASK == PORT_VALUE
+    }
+
+    fn register(port: u16) -> u8 {
+        ((port >> 2) & 0x07) as u8
+    }
+
+    fn slave_selected(&self) -> bool {
+        self.drive_head & DRIVE_HEAD_SLAVE != 0
+    }
+
+    pub fn read(&mut self, port: u16) -> u8 {
+        // Slave device is absent
+        if self.slave_selected() {
+            return 0x00;
+        }
+
+        match Self::register(port) {
+            REG_DATA => self.read_data(),
+            REG_ERROR_FEATURES => self.error,
+            REG_SECTOR_COUNT => self.sector_count,
+            REG_LBA0 => self.lba[0],
+            REG_LBA1 => self.lba[1],
+            REG_LBA2 => self.lba[2],
+            REG_DRIVE_HEAD => self.drive_head,
+            REG_STATUS_COMMAND => self.status,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn write(&mut self, port: u16, data: u8) {
+        match Self::register(port) {
+            REG_DATA => self.write_data(data),
+            // SET FEATURES arguments are ignored
+            REG_ERROR_FEATURES => {}
+            REG_SECTOR_COUNT => self.sector_count = data,
+            REG_LBA0 => self.lba[0] = data,
+            REG_LBA1 => self.lba[1] = data,
+            REG_LBA2 => self.lba[2] = data,
+            REG_DRIVE_HEAD => self.drive_head = data,
+            REG_STATUS_COMMAND => {
+                if !self.slave_selected() {
+                    self.execute_command(data)
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn cylinders(&self) -> u32 {
+        (self.device.sector_count() / (GEOMETRY_HEADS * GEOMETRY_SECTORS))
+            .min(GEOMETRY_MAX_CYLINDERS)
+    }
+
+    /// Returns currently selected sector address, taking into account addressing mode
+    fn current_lba(&self) -> u32 {
+        if self.drive_head & DRIVE_HEAD_LBA != 0 {
+            u32::from_le_bytes([
+                self.lba[0],
+                self.lba[1],
+                self.lba[2],
+                self.drive_head & 0x0F,
+            ])
+        } else {
+            let sector = self.lba[0] as u32;
+            let cylinder = u16::from_le_bytes([self.lba[1], self.lba[2]]) as u32;
+            let head = (self.drive_head & 0x0F) as u32;
+            (cylinder * GEOMETRY_HEADS + head) * GEOMETRY_SECTORS + sector.saturating_sub(1)
+        }
+    }
+
+    /// Moves task file registers to the next sector
+    fn advance_lba(&mut self) {
+        let next = self.current_lba() + 1;
+        if self.drive_head & DRIVE_HEAD_LBA != 0 {
+            let [b0, b1, b2, b3] = next.to_le_bytes();
+            self.lba = [b0, b1, b2];
+            self.drive_head = (self.drive_head & 0xF0) | (b3 & 0x0F);
+        } else {
+            let sector = next % GEOMETRY_SECTORS + 1;
+            let head = (next / GEOMETRY_SECTORS) % GEOMETRY_HEADS;
+            let [c0, c1, _, _] = (next / (GEOMETRY_SECTORS * GEOMETRY_HEADS)).to_le_bytes();
+            self.lba = [sector as u8, c0, c1];
+            self.drive_head = (self.drive_head & 0xF0) | head as u8;
+        }
+    }
+
+    fn requested_sectors(&self) -> u16 {
+        if self.sector_count == 0 {
+            256
+        } else {
+            self.sector_count as u16
+        }
+    }
+
+    fn fail(&mut self, error: u8) {
+        diag!(
+            warn,
+            DISK,
+            "command failed at LBA {}: error {:#04X}",
+            self.current_lba(),
+            error
+        );
+        self.transfer = Transfer::None;
+        self.error = error;
+        self.status = STATUS_DRDY | STATUS_DSC | STATUS_ERR;
+    }
+
+    fn start_data_phase(&mut self, transfer: Transfer) {
+        self.transfer = transfer;
+        self.buffer_pos = 0;
+        self.error = 0;
+        self.status = STATUS_DRDY | STATUS_DSC | STATUS_DRQ;
+    }
+
+    fn finish_command(&mut self) {
+        self.transfer = Transfer::None;
+        self.status = STATUS_DRDY | STATUS_DSC;
+    }
+
+    fn load_sector(&mut self) -> bool {
+        let lba = self.current_lba();
+        if lba >= self.device.sector_count() {
+            self.fail(ERROR_IDNF);
+            return false;
+        }
+        if self.device.read_sector(lba, &mut self.buffer).is_err() {
+            self.fail(ERROR_UNC);
+            return false;
+        }
+        true
+    }
+
+    fn execute_command(&mut self, command: u8) {
+        diag!(
+            debug,
+            DISK,
+            "command {:#04X}: LBA {}, {} sectors",
+            command,
+            self.current_lba(),
+            self.requested_sectors()
+        );
+        match command {
+            CMD_IDENTIFY_DEVICE => {
+                self.fill_identify_data();
+                self.start_data_phase(Transfer::Read { sectors_left: 1 });
+            }
+            CMD_READ_SECTORS | CMD_READ_SECTORS_NO_RETRY => {
+                if self.load_sector() {
+                    let sectors_left = self.requested_sectors();
+                    self.start_data_phase(Transfer::Read { sectors_left });
+                }
+            }
+            CMD_WRITE_SECTORS | CMD_WRITE_SECTORS_NO_RETRY => {
+                if self.current_lba() >= self.device.sector_count() {
+                    self.fail(ERROR_IDNF);
+                } else {
+                    let sectors_left = self.requested_sectors();
+                    self.start_data_phase(Transfer::Write { sectors_left });
+                }
+            }
+            CMD_INIT_DEVICE_PARAMS | CMD_FLUSH_CACHE | CMD_SET_FEATURES => {
+                self.error = 0;
+                self.finish_command();
+            }
+            _ => self.fail(ERROR_ABRT),
+        }
+    }
+
+    fn read_data(&mut self) -> u8 {
+        let sectors_left = match self.transfer {
+            Transfer::Read { sectors_left } => sectors_left,
+            _ => return 0xFF,
+        };
+
+        let value = self.buffer[self.buffer_pos];
+        self.buffer_pos += 1;
+        if self.buffer_pos == SECTOR_SIZE {
+            self.buffer_pos = 0;
+            let sectors_left = sectors_left - 1;
+            if sectors_left == 0 {
+                self.finish_command();
+            } else {
+                self.advance_lba();
+                self.sector_count = self.sector_count.wrapping_sub(1);
+                self.transfer = Transfer::Read { sectors_left };
+                self.load_sector();
+            }
+        }
+        value
+    }
+
+    fn write_data(&mut self, data: u8) {
+        let sectors_left = match self.transfer {
+            Transfer::Write { sectors_left } => sectors_left,
+            _ => return,
+        };
+
+        self.buffer[self.buffer_pos] = data;
+        self.buffer_pos += 1;
+        if self.buffer_pos == SECTOR_SIZE {
+            self.buffer_pos = 0;
+            if self
+                .device
+                .write_sector(self.current_lba(), &self.buffer)
+                .is_err()
+            {
+                self.fail(ERROR_ABRT);
+                return;
+            }
+            let sectors_left = sectors_left - 1;
+            if sectors_left == 0 {
+                self.finish_command();
+            } else {
+                self.advance_lba();
+                self.sector_count = self.sector_count.wrapping_sub(1);
+                self.transfer = Transfer::Write { sectors_left };
+                if self.current_lba() >= self.device.sector_count() {
+                    self.fail(ERROR_IDNF);
+                }
+            }
+        }
+    }
+
+    fn fill_identify_data(&mut self) {
+        let total_sectors = self.device.sector_count();
+        let cylinders = self.cylinders();
+        let mut words = [0u16; SECTOR_SIZE / 2];
+        // Fixed, non-removable device
+        words[0] = 0x0040;
+        words[1] = cylinders as u16;
+        words[3] = GEOMETRY_HEADS as u16;
+        words[6] = GEOMETRY_SECTORS as u16;
+        put_string(&mut words[10..20], SERIAL_NUMBER);
+        put_string(&mut words[23..27], FIRMWARE_REVISION);
+        put_string(&mut words[27..47], MODEL_NAME);
+        // LBA supported
+        words[49] = 0x0200;
+        // Words 54-58 are valid
+        words[53] = 0x0001;
+        words[54] = cylinders as u16;
+        words[55] = GEOMETRY_HEADS as u16;
+        words[56] = GEOMETRY_SECTORS as u16;
+        let chs_sectors = cylinders * GEOMETRY_HEADS * GEOMETRY_SECTORS;
+        words[57] = chs_sectors as u16;
+        words[58] = (chs_sectors >> 16) as u16;
+        words[60] = total_sectors as u16;
+        words[61] = (total_sectors >> 16) as u16;
+
+        for (idx, word) in words.iter().enumerate() {
+            let [lo, hi] = word.to_le_bytes();
+            self.buffer[idx * 2] = lo;
+            self.buffer[idx * 2 + 1] = hi;
+        }
+    }
+}
+
+/// Writes ATA string (space-padded, first character in the high byte of each word)
+fn put_string(words: &mut [u16], s: &[u8]) {
+    for (idx, word) in words.iter_mut().enumerate() {
+        let hi = s.get(idx * 2).copied().unwrap_or(b' ');
+        let lo = s.get(idx * 2 + 1).copied().unwrap_or(b' ');
+        *word = u16::from_be_bytes([hi, lo]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    struct MemoryBlockDevice {
+        sectors: Vec<[u8; SECTOR_SIZE]>,
+    }
+
+    impl MemoryBlockDevice {
+        fn new(count: usize) -> Self {
+            let sectors = (0..count).map(|idx| [idx as u8; SECTOR_SIZE]).collect();
+            Self { sectors }
+        }
+    }
+
+    impl BlockDevice for MemoryBlockDevice {
+        fn sector_count(&self) -> u32 {
+            self.sectors.len() as u32
+        }
+
+        fn read_sector(&mut self, lba: u32, buf: &mut [u8; SECTOR_SIZE]) -> Result<(), IoError> {
+            buf.copy_from_slice(&self.sectors[lba as usize]);
+            Ok(())
+        }
+
+        fn write_sector(&mut self, lba: u32, buf: &[u8; SECTOR_SIZE]) -> Result<(), IoError> {
+            self.sectors[lba as usize].copy_from_slice(buf);
+            Ok(())
+        }
+    }
+
+    fn port(register: u8) -> u16 {
+        PORT_VALUE | ((register as u16) << 2)
+    }
+
+    fn select_lba(ide: &mut IdeInterface<MemoryBlockDevice>, lba: u32, count: u8) {
+        let [b0, b1, b2, b3] = lba.to_le_bytes();
+        ide.write(port(REG_SECTOR_COUNT), count);
+        ide.write(port(REG_LBA0), b0);
+        ide.write(port(REG_LBA1), b1);
+        ide.write(port(REG_LBA2), b2);
+        ide.write(port(REG_DRIVE_HEAD), 0xE0 | b3);
+    }
+
+    #[test]
+    fn identify_reports_capacity() {
+        let mut ide = IdeInterface::new(MemoryBlockDevice::new(4));
+        ide.write(port(REG_DRIVE_HEAD), 0xE0);
+        ide.write(port(REG_STATUS_COMMAND), CMD_IDENTIFY_DEVICE);
+        assert_eq!(ide.read(port(REG_STATUS_COMMAND)) & STATUS_DRQ, STATUS_DRQ);
+
+        let data = (0..SECTOR_SIZE)
+            .map(|_| ide.read(port(REG_DATA)))
+            .collect::<Vec<_>>();
+        assert_eq!(&data[54..70], b"URTSXZH RA DIDKS");
+        assert_eq!(u16::from_le_bytes([data[120], data[121]]), 4);
+        assert_eq!(ide.read(port(REG_STATUS_COMMAND)), STATUS_DRDY | STATUS_DSC);
+    }
+
+    #[test]
+    fn read_and_write_sectors() {
+        let mut ide = IdeInterface::new(MemoryBlockDevice::new(4));
+        select_lba(&mut ide, 1, 2);
+        ide.write(port(REG_STATUS_COMMAND), CMD_READ_SECTORS);
+        let data = (0..SECTOR_SIZE * 2)
+            .map(|_| ide.read(port(REG_DATA)))
+            .collect::<Vec<_>>();
+        assert!(data[..SECTOR_SIZE].iter().all(|b| *b == 1));
+        assert!(data[SECTOR_SIZE..].iter().all(|b| *b == 2));
+        assert_eq!(ide.read(port(REG_STATUS_COMMAND)) & STATUS_DRQ, 0);
+
+        select_lba(&mut ide, 3, 1);
+        ide.write(port(REG_STATUS_COMMAND), CMD_WRITE_SECTORS);
+        for _ in 0..SECTOR_SIZE {
+            ide.write(port(REG_DATA), 0xAA);
+        }
+        assert_eq!(ide.into_device().sectors[3], [0xAA; SECTOR_SIZE]);
+    }
+
+    #[test]
+    fn out_of_range_sector_fails() {
+        let mut ide = IdeInterface::new(MemoryBlockDevice::new(4));
+        select_lba(&mut ide, 4, 1);
+        ide.write(port(REG_STATUS_COMMAND), CMD_READ_SECTORS);
+        assert_eq!(ide.read(port(REG_STATUS_COMMAND)) & STATUS_ERR, STATUS_ERR);
+        assert_eq!(ide.read(port(REG_ERROR_FEATURES)), ERROR_IDNF);
+    }
+}
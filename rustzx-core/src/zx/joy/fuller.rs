@@ -0,0 +1,44 @@
+//! Fuller Box: joystick on port 0x7F and AY chip on ports 0x3F (register
+//! select, register read) and 0x5F (register write). Only the low address
+//! byte is decoded
+
+/// Joystick port
+pub const PORT_FULLER_JOY: u8 = 0x7F;
+/// AY register select port, selected register is read from it
+pub const PORT_FULLER_AY_REGISTER: u8 = 0x3F;
+/// AY register write port
+pub const PORT_FULLER_AY_DATA: u8 = 0x5F;
+
+/// Fuller joystick key type. Port bit encoded in enum values
+#[cfg_attr(feature = "strum", derive(strum::EnumIter))]
+#[derive(Clone, Copy, Debug)]
+pub enum FullerKey {
+    Up = 0x01,
+    Down = 0x02,
+    Left = 0x04,
+    Right = 0x08,
+    Fire = 0x80,
+}
+
+/// Fuller joystick, mapped to port 0x7F. Unlike Kempston, port bits are
+/// active-low: pressed key resets its bit, unused bits are always set
+#[derive(Default)]
+pub(crate) struct FullerJoy {
+    state: u8,
+}
+
+impl FullerJoy {
+    /// Simulates key press/release
+    pub fn key(&mut self, key: FullerKey, state: bool) {
+        if state {
+            self.state |= key as u8;
+        } else {
+            self.state &= !(key as u8);
+        }
+    }
+
+    /// Reads joy value
+    pub fn read(&self) -> u8 {
+        !self.state
+    }
+}
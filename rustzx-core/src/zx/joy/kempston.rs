@@ -1,6 +1,6 @@
 /// Kempston key type. Port bit encoded in enum values
 #[cfg_attr(feature = "strum", derive(strum::EnumIter))]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum KempstonKey {
     Right = 0x01,
     Left = 0x02,
@@ -1,2 +1,65 @@
+//! Joystick interfaces. All enabled interfaces are attached at once, each on
+//! its own port (Sinclair joysticks are mapped to the keyboard matrix), so
+//! e.g. two players can use Kempston and Sinclair joysticks simultaneously
+pub mod fuller;
 pub mod kempston;
 pub mod sinclair;
+
+use fuller::FullerKey;
+use kempston::KempstonKey;
+use sinclair::{SinclairJoyNum, SinclairKey};
+
+/// Joystick interface which host input device is assigned to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoyInterface {
+    Kempston,
+    Fuller,
+    Sinclair(SinclairJoyNum),
+}
+
+/// Joystick button, common for all interfaces
+#[cfg_attr(feature = "strum", derive(strum::EnumIter))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoyButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    Fire,
+}
+
+impl From<JoyButton> for KempstonKey {
+    fn from(button: JoyButton) -> Self {
+        match button {
+            JoyButton::Up => KempstonKey::Up,
+            JoyButton::Down => KempstonKey::Down,
+            JoyButton::Left => KempstonKey::Left,
+            JoyButton::Right => KempstonKey::Right,
+            JoyButton::Fire => KempstonKey::Fire,
+        }
+    }
+}
+
+impl From<JoyButton> for FullerKey {
+    fn from(button: JoyButton) -> Self {
+        match button {
+            JoyButton::Up => FullerKey::Up,
+            JoyButton::Down => FullerKey::Down,
+            JoyButton::Left => FullerKey::Left,
+            JoyButton::Right => FullerKey::Right,
+            JoyButton::Fire => FullerKey::Fire,
+        }
+    }
+}
+
+impl From<JoyButton> for SinclairKey {
+    fn from(button: JoyButton) -> Self {
+        match button {
+            JoyButton::Up => SinclairKey::Up,
+            JoyButton::Down => SinclairKey::Down,
+            JoyButton::Left => SinclairKey::Left,
+            JoyButton::Right => SinclairKey::Right,
+            JoyButton::Fire => SinclairKey::Fire,
+        }
+    }
+}
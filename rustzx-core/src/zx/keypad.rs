@@ -0,0 +1,287 @@
+//! Numeric keypad of the Spectrum 128, connected to the AY port A. The keypad
+//! is polled by the 128K ROM every other frame using a serial protocol, in
+//! which the Spectrum clocks every bit via its CTS line and the keypad
+//! answers on the DATA line:
+//!
+//! - Presence check: CTS goes low and the keypad pulls DATA low, CTS goes
+//!   high and the keypad releases DATA. After the next CTS fall the keypad
+//!   sends the ID nibble
+//! - Bit transfer: CTS goes high and the keypad pulls DATA low to
+//!   acknowledge, CTS goes low and the keypad puts the bit on DATA, which is
+//!   sampled by the Spectrum. Second CTS pulse finishes the bit, DATA returns
+//!   to high
+//! - Scan: for each of 5 rows the keypad sends a bit which is set if the row
+//!   state follows, and then 4 bits of pressed keys in the row
+use crate::zx::ay_port::PortADevice;
+
+/// Keypad CTS output of the Spectrum
+const PORT_A_CTS: u8 = 0x01;
+/// Keypad DATA input of the Spectrum
+const PORT_A_DATA: u8 = 0x20;
+/// ID nibble sent after the presence check, third bit marks the keypad
+/// which can be scanned
+const KEYPAD_ID: u32 = 0b0100;
+const ID_BITS: u8 = 4;
+const ROWS: u8 = 5;
+const ROW_BITS: u8 = 4;
+
+/// Keys of the keypad, named by the characters typed in 128 BASIC. In the
+/// 128K editor some keys perform editing functions instead
+#[cfg_attr(feature = "strum", derive(strum::EnumIter))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeypadKey {
+    N0,
+    N1,
+    N2,
+    N3,
+    N4,
+    N5,
+    N6,
+    N7,
+    N8,
+    N9,
+    Dot,
+    Enter,
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    OpenParen,
+    CloseParen,
+}
+
+impl KeypadKey {
+    /// Returns index of the key bit in the scan data (row * 4 + column)
+    fn scan_bit(self) -> u8 {
+        match self {
+            Self::Dot => 1,
+            Self::N0 => 3,
+            Self::Enter => 4,
+            Self::N3 => 5,
+            Self::N2 => 6,
+            Self::N1 => 7,
+            Self::CloseParen => 8,
+            Self::OpenParen => 9,
+            Self::Multiply => 10,
+            Self::Divide => 11,
+            Self::Minus => 12,
+            Self::N9 => 13,
+            Self::N8 => 14,
+            Self::N7 => 15,
+            Self::Plus => 16,
+            Self::N6 => 17,
+            Self::N5 => 18,
+            Self::N4 => 19,
+        }
+    }
+}
+
+/// Step of the single bit transfer, driven by CTS edges
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BitPhase {
+    /// Waiting for CTS rise to acknowledge the bit request
+    Request,
+    /// Waiting for CTS fall to put the bit on DATA
+    Data,
+    /// Waiting for the second CTS pulse which finishes the bit
+    FinishRise,
+    FinishFall,
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    /// Keypad was not detected by the Spectrum yet, or the transfer was
+    /// aborted
+    Idle,
+    /// CTS went low during presence check, waiting for its rise
+    PresenceCheck,
+    /// Presence is acknowledged, ID follows after CTS fall
+    PresenceAck,
+    /// Bits are sent from LSB
+    Transfer {
+        bits: u32,
+        remaining: u8,
+        phase: BitPhase,
+    },
+    /// Keypad is detected, waiting for the next scan
+    Ready,
+}
+
+pub(crate) struct Keypad {
+    /// Pressed keys, bit per [KeypadKey::scan_bit]
+    keys: u32,
+    state: State,
+    cts: bool,
+    data: bool,
+}
+
+impl Default for Keypad {
+    fn default() -> Self {
+        Self {
+            keys: 0,
+            state: State::Idle,
+            cts: true,
+            data: true,
+        }
+    }
+}
+
+impl Keypad {
+    pub fn send_key(&mut self, key: KeypadKey, pressed: bool) {
+        let mask = 1 << key.scan_bit();
+        if pressed {
+            self.keys |= mask;
+        } else {
+            self.keys &= !mask;
+        }
+    }
+
+    /// Returns scan data with the row state bit before each row
+    fn scan_bits(&self) -> u32 {
+        (0..ROWS).fold(0, |bits, row| {
+            let keys = (self.keys >> (row * ROW_BITS)) & 0x0F;
+            bits | ((keys << 1 | 1) << (row * (ROW_BITS + 1)))
+        })
+    }
+
+    fn cts_edge(&mut self, cts: bool) {
+        if let State::Transfer {
+            bits,
+            remaining,
+            phase,
+        } = self.state
+        {
+            if let Some(state) = self.transfer_edge(bits, remaining, phase, cts) {
+                self.state = state;
+                return;
+            }
+            // Transfer aborted by the Spectrum
+            self.data = true;
+            self.state = State::Idle;
+        }
+
+        self.state = match (self.state, cts) {
+            (State::PresenceAck, false) => State::Transfer {
+                bits: KEYPAD_ID,
+                remaining: ID_BITS,
+                phase: BitPhase::Request,
+            },
+            (_, false) => {
+                self.data = false;
+                State::PresenceCheck
+            }
+            (State::PresenceCheck, true) => {
+                self.data = true;
+                State::PresenceAck
+            }
+            (State::Ready, true) => {
+                self.data = false;
+                State::Transfer {
+                    bits: self.scan_bits(),
+                    remaining: ROWS * (ROW_BITS + 1),
+                    phase: BitPhase::Data,
+                }
+            }
+            (state, true) => state,
+        };
+    }
+
+    /// Returns the next state of the bit transfer, `None` if the edge does
+    /// not belong to the transfer
+    fn transfer_edge(
+        &mut self,
+        bits: u32,
+        remaining: u8,
+        phase: BitPhase,
+        cts: bool,
+    ) -> Option<State> {
+        let phase = match (phase, cts) {
+            (BitPhase::Request, true) => {
+                self.data = false;
+                BitPhase::Data
+            }
+            (BitPhase::Data, false) => {
+                self.data = bits & 1 != 0;
+                BitPhase::FinishRise
+            }
+            (BitPhase::FinishRise, true) => BitPhase::FinishFall,
+            (BitPhase::FinishFall, false) => {
+                self.data = true;
+                return Some(if remaining > 1 {
+                    State::Transfer {
+                        bits: bits >> 1,
+                        remaining: remaining - 1,
+                        phase: BitPhase::Request,
+                    }
+                } else {
+                    State::Ready
+                });
+            }
+            _ => return None,
+        };
+        Some(State::Transfer {
+            bits,
+            remaining,
+            phase,
+        })
+    }
+}
+
+impl PortADevice for Keypad {
+    fn write_port_a(&mut self, _clocks: usize, value: u8) {
+        let cts = value & PORT_A_CTS != 0;
+        if cts != self.cts {
+            self.cts = cts;
+            self.cts_edge(cts);
+        }
+    }
+
+    fn read_port_a(&mut self, _clocks: usize, value: u8) -> u8 {
+        if self.data {
+            value | PORT_A_DATA
+        } else {
+            value & !PORT_A_DATA
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn set_cts(keypad: &mut Keypad, cts: bool) -> bool {
+        keypad.write_port_a(0, if cts { 0xFF } else { !PORT_A_CTS });
+        keypad.read_port_a(0, 0xFF) & PORT_A_DATA != 0
+    }
+
+    /// Reads bits in the same way as the 128K ROM
+    fn read_bits(keypad: &mut Keypad, count: u8) -> u32 {
+        (0..count).fold(0, |value, bit| {
+            assert!(!set_cts(keypad, true));
+            let data = set_cts(keypad, false);
+            set_cts(keypad, true);
+            assert!(set_cts(keypad, false));
+            value | (data as u32) << bit
+        })
+    }
+
+    #[test]
+    fn presence_check_and_scan() {
+        let mut keypad = Keypad::default();
+        assert!(!set_cts(&mut keypad, false));
+        assert!(set_cts(&mut keypad, true));
+        assert!(set_cts(&mut keypad, false));
+        assert_eq!(read_bits(&mut keypad, ID_BITS), KEYPAD_ID);
+
+        keypad.send_key(KeypadKey::N1, true);
+        keypad.send_key(KeypadKey::N4, true);
+        let rows: Vec<u32> = (0..ROWS)
+            .map(|_| {
+                assert_eq!(read_bits(&mut keypad, 1), 1);
+                read_bits(&mut keypad, ROW_BITS)
+            })
+            .collect();
+        assert_eq!(rows, [0b0000, 0b1000, 0b0000, 0b0000, 0b1000]);
+    }
+}
@@ -3,7 +3,7 @@
 /// Struct, which contains mast and port of key
 #[rustfmt::skip]
 #[cfg_attr(feature = "strum", derive(strum::EnumIter))]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ZXKey {
     // Port 0xFEFE
     Shift, Z, X, C, V,
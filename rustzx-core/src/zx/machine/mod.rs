@@ -1,96 +1,228 @@
 //! Module with machine specifications
-
-// Allow outer modules to use ZXSpecs struct, but not construct
 mod specs;
 
-use lazy_static::lazy_static;
-use specs::ZXSpecsBuilder;
-
-pub(crate) use specs::ZXSpecs;
-
-lazy_static! {
-    /// ZX Spectrum 48K Specs
-    pub(crate) static ref SPECS_48K: ZXSpecs = {
-        ZXSpecsBuilder::new()
-            .freq_cpu(3_500_000)
-            .clocks_first_pixel(14336)
-            .clocks_ula_read_shift(2)
-            .clocks_ula_beam_shift(1)
-            .clocks_row(24, 128, 24, 48)
-            .lines(48, 192, 48, 24)
-            .contention([6, 5, 4, 3, 2, 1, 0, 0], 1)
-            .interrupt_length(32)
-            .rom_pages(1)
-            .build()
-        };
+pub use specs::{MemoryModel, UlaFetch, ZXSpecs, ZXSpecsBuilder};
+
+use crate::error::MachineSpecsError;
+
+/// ZX Spectrum 48K Specs
+pub const SPECS_48K: ZXSpecs = ZXSpecsBuilder::new()
+    .freq_cpu(3_500_000)
+    .clocks_first_pixel(14336)
+    .clocks_ula_read_shift(2)
+    .clocks_ula_beam_shift(1)
+    .clocks_row(24, 128, 24, 48)
+    .lines(48, 192, 48, 24)
+    .contention([6, 5, 4, 3, 2, 1, 0, 0])
+    .contention_offset(0)
+    .frames_per_second(50)
+    .interrupt_length(32)
+    .memory_model(MemoryModel::Sinclair48K)
+    .build();
+
+/// ZX Spectrum 48K Specs, early ULA timings
+pub const SPECS_48K_EARLY: ZXSpecs = ZXSpecsBuilder::new()
+    .freq_cpu(3_500_000)
+    .clocks_first_pixel(14336)
+    .clocks_ula_read_shift(2)
+    .clocks_ula_beam_shift(1)
+    .clocks_row(24, 128, 24, 48)
+    .lines(48, 192, 48, 24)
+    .contention([6, 5, 4, 3, 2, 1, 0, 0])
+    .contention_offset(-1)
+    .frames_per_second(50)
+    .interrupt_length(32)
+    .memory_model(MemoryModel::Sinclair48K)
+    .build();
+
+/// ZX Spectrum 48K NTSC Specs (60Hz, 264 lines)
+pub const SPECS_48K_NTSC: ZXSpecs = ZXSpecsBuilder::new()
+    .freq_cpu(3_527_500)
+    .clocks_first_pixel(8960)
+    .clocks_ula_read_shift(2)
+    .clocks_ula_beam_shift(1)
+    .clocks_row(24, 128, 24, 48)
+    .lines(24, 192, 24, 24)
+    .contention([6, 5, 4, 3, 2, 1, 0, 0])
+    .contention_offset(0)
+    .frames_per_second(60)
+    .interrupt_length(32)
+    .memory_model(MemoryModel::Sinclair48K)
+    .build();
+
+/// ZX Spectrum 128K Specs
+pub const SPECS_128K: ZXSpecs = ZXSpecsBuilder::new()
+    .freq_cpu(3_546_900)
+    .clocks_first_pixel(14362)
+    .clocks_ula_read_shift(2)
+    .clocks_ula_beam_shift(1)
+    .clocks_row(24, 128, 24, 52)
+    .lines(48, 192, 48, 23)
+    .contention([6, 5, 4, 3, 2, 1, 0, 0])
+    .contention_offset(0)
+    .frames_per_second(50)
+    .interrupt_length(36)
+    .memory_model(MemoryModel::Sinclair128K)
+    .build();
+
+/// ZX Spectrum +2A/+3 Specs. Frame timings are the same as of 128K, but gate
+/// array contends memory with the other pattern and does not contend I/O
+pub const SPECS_PLUS3: ZXSpecs = ZXSpecsBuilder::new()
+    .freq_cpu(3_546_900)
+    .clocks_first_pixel(14362)
+    .clocks_ula_read_shift(2)
+    .clocks_ula_beam_shift(1)
+    .clocks_row(24, 128, 24, 52)
+    .lines(48, 192, 48, 23)
+    .contention([1, 0, 7, 6, 5, 4, 3, 2])
+    .contention_offset(0)
+    .frames_per_second(50)
+    .interrupt_length(36)
+    .memory_model(MemoryModel::SinclairPlus3)
+    .build();
+
+/// ULA timing variant. 48K machines were produced with two ULA revisions
+/// which start contention and screen memory reads one clock apart relative to
+/// the interrupt. Some precise demos and tests (e.g. fusetest) detect it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Timings {
+    Early,
+    /// Most common variant
+    #[default]
+    Late,
 }
 
-lazy_static! {
-    /// ZX Spectrum 128K Specs
-    pub static ref SPECS_128K: ZXSpecs = {
-        ZXSpecsBuilder::new()
-            .freq_cpu(3_546_900)
-            .clocks_first_pixel(14362)
-            .clocks_ula_read_shift(2)
-            .clocks_ula_beam_shift(1)
-            .clocks_row(24, 128, 24, 52)
-            .lines(48, 192, 48, 23)
-            .contention([6, 5, 4, 3, 2, 1, 0, 0], 1)
-            .interrupt_length(32)
-            .rom_pages(2)
-            .build()
-    };
+/// Board revision of the 48K machine, which defines the level of the EAR
+/// input (bit 6 of 0xFE port) without the tape signal. The input is
+/// connected to the EAR and MIC outputs, so it follows the last write to
+/// 0xFE port: issue 2 reads it high when either EAR (bit 4) or MIC (bit 3)
+/// output is set, issue 3 only when EAR output is set. Some old programs
+/// depend on it, e.g. read keyboard expecting the issue 2 level. 128K
+/// machines behave as issue 3
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UlaIssue {
+    Issue2,
+    /// Most common variant
+    #[default]
+    Issue3,
+}
+
+/// Action taken when loaded media (snapshot or tape) requires other memory
+/// model than the one of the emulated machine
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MachinePolicy {
+    /// Switch to the built-in machine of the required memory model before
+    /// the media is loaded
+    #[default]
+    AutoSwitch,
+    /// Load media into the current machine, mismatch is only reported via
+    /// diagnostics
+    WarnOnly,
+    /// Refuse to load the media with [crate::error::MachineMismatchError]
+    Strict,
 }
 
 /// Machine type
+// Specs are kept inline, so machine stays `Copy` and can be used without `alloc`
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ZXMachine {
     Sinclair48K,
+    /// 60Hz variant of Sinclair 48K
+    Sinclair48KNtsc,
     Sinclair128K,
+    /// Amstrad +2A/+3, ROM is not embedded and should be provided by the host
+    SinclairPlus3,
+    /// Machine with user-defined timings (e.g. clones), memory and ports
+    /// behave as in the machine of the selected [MemoryModel]. Prefer
+    /// [ZXMachine::custom] to construct it, specs are validated on emulator
+    /// construction anyway
+    Custom(ZXSpecs),
 }
 
 impl ZXMachine {
-    /// Returns current machine specs as ref to static value
-    pub fn specs(self) -> &'static ZXSpecs {
+    /// Returns custom machine with validated specs
+    pub fn custom(specs: ZXSpecs) -> Result<Self, MachineSpecsError> {
+        specs.validate()?;
+        Ok(ZXMachine::Custom(specs))
+    }
+
+    /// Returns current machine specs
+    pub fn specs(&self) -> &ZXSpecs {
         match self {
             ZXMachine::Sinclair48K => &SPECS_48K,
+            ZXMachine::Sinclair48KNtsc => &SPECS_48K_NTSC,
             ZXMachine::Sinclair128K => &SPECS_128K,
+            ZXMachine::SinclairPlus3 => &SPECS_PLUS3,
+            ZXMachine::Custom(specs) => specs,
         }
     }
 
-    /// Returns contention during specified time
-    pub fn contention_clocks(self, clocks: usize) -> usize {
-        let specs = self.specs();
-        if (clocks < (specs.clocks_first_pixel - 1))
-            || (clocks >= (specs.clocks_first_pixel - 1) + specs.lines_screen * specs.clocks_line)
-        {
-            return 0;
+    /// Returns built-in machine with the given memory model
+    pub fn with_memory_model(model: MemoryModel) -> Self {
+        match model {
+            MemoryModel::Sinclair48K => ZXMachine::Sinclair48K,
+            MemoryModel::Sinclair128K => ZXMachine::Sinclair128K,
+            MemoryModel::SinclairPlus3 => ZXMachine::SinclairPlus3,
         }
-        let clocks_trough_line = (clocks - (specs.clocks_first_pixel - 1)) % specs.clocks_line;
-        if clocks_trough_line >= specs.clocks_screen_row {
-            return 0;
+    }
+
+    /// Returns machine specs for the given ULA timings variant. Only 48K
+    /// (PAL) machine has the early variant, other machines ignore `timings`
+    pub(crate) fn specs_with_timings(&self, timings: Timings) -> &ZXSpecs {
+        match (self, timings) {
+            (ZXMachine::Sinclair48K, Timings::Early) => &SPECS_48K_EARLY,
+            _ => self.specs(),
         }
-        return self.specs().contention_pattern[clocks_trough_line % 8];
+    }
+
+    /// Returns count of frames emulated per second
+    pub fn frames_per_second(&self) -> usize {
+        self.specs().frames_per_second
+    }
+
+    /// Returns memory layout of the machine
+    pub fn memory_model(&self) -> MemoryModel {
+        self.specs().memory_model
+    }
+
+    /// Returns true if machine has 48K memory model (no paging, single ROM)
+    pub fn is_48k(&self) -> bool {
+        self.memory_model() == MemoryModel::Sinclair48K
+    }
+
+    /// Returns true if machine has built-in AY chip, which is the case for the
+    /// 128K and +3 memory models
+    pub fn has_ay(&self) -> bool {
+        !self.is_48k()
+    }
+
+    /// Returns contention during specified time
+    pub fn contention_clocks(&self, clocks: usize) -> usize {
+        self.specs().contention_clocks(clocks)
+    }
+
+    /// Returns true if I/O accesses are contended, +2A/+3 gate array
+    /// contends memory accesses only
+    pub fn io_is_contended(&self) -> bool {
+        self.memory_model() != MemoryModel::SinclairPlus3
     }
 
     /// Checks port contention on machine
-    pub fn port_is_contended(self, port: u16) -> bool {
-        match self {
-            ZXMachine::Sinclair48K | ZXMachine::Sinclair128K => {
-                // every even port
-                (port & 0x0001) == 0
-            }
-        }
+    pub fn port_is_contended(&self, port: u16) -> bool {
+        // every even port
+        self.io_is_contended() && (port & 0x0001) == 0
     }
 
     /// Returns contention status of bank
-    pub fn bank_is_contended(self, page: usize) -> bool {
-        match self {
-            ZXMachine::Sinclair48K => page == 0,
-            ZXMachine::Sinclair128K => {
+    pub fn bank_is_contended(&self, page: usize) -> bool {
+        match self.memory_model() {
+            MemoryModel::Sinclair48K => page == 0,
+            MemoryModel::Sinclair128K => {
                 let contended_pages = [1, 3, 5, 7];
-                contended_pages.iter().any(|&x| x == page)
+                contended_pages.contains(&page)
             }
+            MemoryModel::SinclairPlus3 => page >= 4,
         }
     }
 }
@@ -1,3 +1,63 @@
+use crate::{
+    error::MachineSpecsError,
+    utils::screen::bitmap_line_addr,
+    zx::constants::{ATTR_COLS, BORDER_COLS, BORDER_ROWS, CANVAS_HEIGHT, CLOCKS_PER_COL},
+};
+
+/// Memory layout, paging and contended memory of the machine
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryModel {
+    /// Single 16K ROM and 48K RAM without paging, RAM at 0x4000..=0x7FFF
+    /// is contended
+    Sinclair48K,
+    /// Two 16K ROMs and 8 RAM banks paged via port 0x7FFD, odd banks are
+    /// contended
+    Sinclair128K,
+    /// Four 16K ROMs and 8 RAM banks of +2A/+3, paged via ports 0x7FFD and
+    /// 0x1FFD which also selects all-RAM (special) paging. Banks 4-7 are
+    /// contended
+    SinclairPlus3,
+}
+
+impl MemoryModel {
+    /// Returns count of 16K ROM pages
+    pub const fn rom_pages(self) -> u8 {
+        match self {
+            MemoryModel::Sinclair48K => 1,
+            MemoryModel::Sinclair128K => 2,
+            MemoryModel::SinclairPlus3 => 4,
+        }
+    }
+
+    /// Returns true if software written for the `required` memory model
+    /// runs on this one, +2A/+3 runs 128K software
+    pub fn runs(self, required: MemoryModel) -> bool {
+        self == required
+            || (self == MemoryModel::SinclairPlus3 && required == MemoryModel::Sinclair128K)
+    }
+}
+
+/// Screen memory byte read by the ULA, see [ZXSpecs::ula_fetch]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UlaFetch {
+    /// Bitmap byte at the given address
+    Bitmap(u16),
+    /// Attribute byte at the given address
+    Attribute(u16),
+}
+
+impl UlaFetch {
+    pub fn addr(self) -> u16 {
+        match self {
+            Self::Bitmap(addr) | Self::Attribute(addr) => addr,
+        }
+    }
+}
+
+/// Machine timings and memory model. Built-in machines are available as
+/// constants (e.g. [super::SPECS_48K]), custom machines can be defined via
+/// [ZXSpecsBuilder] and used as [super::ZXMachine::Custom]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct ZXSpecs {
     // frequencies
     pub freq_cpu: usize,
@@ -14,8 +74,10 @@ pub struct ZXSpecs {
     pub clocks_ula_read_origin: usize,
     pub clocks_ula_contention_origin: usize,
     pub clocks_ula_beam_shift: usize,
+    pub clocks_ula_beam_origin: usize,
     // frame
     pub clocks_frame: usize,
+    pub frames_per_second: usize,
     // lines metrics
     pub lines_vsync: usize,
     pub lines_top_border: usize,
@@ -25,18 +87,165 @@ pub struct ZXSpecs {
     // interrupt
     pub interrupt_length: usize,
     // contention
-    pub contention_offset: usize,
+    /// Delay of the ULA memory access (contention and screen reads) relative
+    /// to `clocks_first_pixel`, differs between machine revisions
+    pub contention_offset: i32,
     pub contention_pattern: [usize; 8],
+    // memory
+    pub memory_model: MemoryModel,
     pub rom_pages: u8,
 }
 
-/// Specs builder, used to make static valiables with machines specs
+impl ZXSpecs {
+    /// Returns contention during specified time
+    pub fn contention_clocks(&self, clocks: usize) -> usize {
+        let origin = self.clocks_ula_contention_origin;
+        if (clocks < origin) || (clocks >= origin + self.lines_screen * self.clocks_line) {
+            return 0;
+        }
+        let clocks_trough_line = (clocks - origin) % self.clocks_line;
+        if clocks_trough_line >= self.clocks_screen_row {
+            return 0;
+        }
+        self.contention_pattern[clocks_trough_line % 8]
+    }
+
+    /// Returns clocks when the beam draws the top left pixel of the rendered
+    /// screen (canvas with the border of [SCREEN_WIDTH] x [SCREEN_HEIGHT]
+    /// pixels)
+    pub fn clocks_beam_origin(&self) -> usize {
+        self.clocks_ula_beam_origin
+            - 8 * BORDER_ROWS * self.clocks_line
+            - BORDER_COLS * CLOCKS_PER_COL
+    }
+
+    /// Returns screen memory byte read by the ULA at the given clocks. During
+    /// each 8-clock cycle of the screen row the ULA reads bitmap and
+    /// attribute bytes of two columns in the first 4 clocks and leaves the
+    /// bus idle in the last 4. Returns `None` when the ULA does not read
+    /// (border, retrace and idle clocks), so the floating bus reads 0xFF
+    pub fn ula_fetch(&self, clocks: usize) -> Option<UlaFetch> {
+        let clocks = clocks.checked_sub(self.clocks_ula_read_origin)?;
+        let row = clocks / self.clocks_line;
+        let clocks = clocks % self.clocks_line;
+        if row >= CANVAS_HEIGHT || clocks >= self.clocks_screen_row || clocks & 0x04 != 0 {
+            return None;
+        }
+        let col = (clocks / 8) * 2 + (clocks % 8) / 2;
+        Some(if clocks.is_multiple_of(2) {
+            UlaFetch::Bitmap(bitmap_line_addr(row) + col as u16)
+        } else {
+            UlaFetch::Attribute(0x5800 + ((row / 8) * ATTR_COLS + col) as u16)
+        })
+    }
+
+    /// Changes ULA delay and recalculates all ULA timings which depend on it.
+    /// Specs are left unchanged if they become invalid with the new delay
+    pub fn set_contention_offset(&mut self, offset: i32) -> Result<(), MachineSpecsError> {
+        let mut specs = *self;
+        specs.contention_offset = offset;
+        specs.update_ula_origins();
+        specs.validate()?;
+        *self = specs;
+        Ok(())
+    }
+
+    /// Checks that timings are consistent and can be emulated. Fields derived
+    /// by [ZXSpecsBuilder] (line and frame clocks, ULA origins) should match
+    /// the values they are derived from
+    pub fn validate(&self) -> Result<(), MachineSpecsError> {
+        if self.freq_cpu == 0 {
+            return Err(MachineSpecsError::ZeroCpuFrequency);
+        }
+        if self.clocks_screen_row != 128 || self.lines_screen != 192 {
+            return Err(MachineSpecsError::InvalidScreenSize);
+        }
+        if self.clocks_left_border < BORDER_COLS * CLOCKS_PER_COL
+            || self.clocks_right_border < BORDER_COLS * CLOCKS_PER_COL
+            || self.lines_top_border < BORDER_ROWS * 8
+            || self.lines_bottom_border < BORDER_ROWS * 8
+        {
+            return Err(MachineSpecsError::BorderTooSmall);
+        }
+        let clocks_line = self.clocks_left_border
+            + self.clocks_screen_row
+            + self.clocks_right_border
+            + self.clocks_retrace;
+        if self.clocks_line != clocks_line {
+            return Err(MachineSpecsError::InvalidLineClocks);
+        }
+        if self.lines_all != self.lines_top_border + self.lines_screen + self.lines_bottom_border {
+            return Err(MachineSpecsError::InvalidLineCount);
+        }
+        if self.clocks_frame != (self.lines_all + self.lines_vsync) * self.clocks_line {
+            return Err(MachineSpecsError::InvalidFrameClocks);
+        }
+        if self.frames_per_second == 0
+            || (self.freq_cpu as f64 / self.clocks_frame as f64 - self.frames_per_second as f64)
+                .abs()
+                >= 1.0
+        {
+            return Err(MachineSpecsError::InvalidFrameRate);
+        }
+        // Border rendering starts the rendered border lines and columns
+        // before the first pixel, screen rendering ends in the same frame
+        let first_pixel = self.clocks_first_pixel as i64 + self.contention_offset as i64;
+        let first_rendered =
+            (8 * BORDER_ROWS * self.clocks_line + BORDER_COLS * CLOCKS_PER_COL) as i64;
+        let last_rendered = first_pixel
+            + ((self.lines_screen + 8 * BORDER_ROWS) * self.clocks_line) as i64
+            + self.clocks_ula_beam_shift.max(self.clocks_ula_read_shift) as i64;
+        if first_pixel < first_rendered || last_rendered > self.clocks_frame as i64 {
+            return Err(MachineSpecsError::FirstPixelOutOfFrame);
+        }
+        let mut origins = *self;
+        origins.update_ula_origins();
+        if origins != *self {
+            return Err(MachineSpecsError::InvalidUlaOrigins);
+        }
+        if self.interrupt_length == 0 || self.interrupt_length >= self.clocks_frame {
+            return Err(MachineSpecsError::InvalidInterruptLength);
+        }
+        if self.rom_pages != self.memory_model.rom_pages() {
+            return Err(MachineSpecsError::InvalidRomPages);
+        }
+        Ok(())
+    }
+
+    /// Derives ULA origins from the first pixel timing and the ULA delay.
+    /// Origins are left unchanged if the delayed first pixel is out of the
+    /// address range, such specs are rejected by [ZXSpecs::validate]
+    const fn update_ula_origins(&mut self) {
+        let first_pixel = match self
+            .clocks_first_pixel
+            .checked_add_signed(self.contention_offset as isize)
+        {
+            Some(clocks) if clocks > 0 => clocks,
+            _ => return,
+        };
+        self.clocks_ula_read_origin = first_pixel.saturating_add(self.clocks_ula_read_shift);
+        // contention starts one clock before the first pixel fetch
+        self.clocks_ula_contention_origin = first_pixel - 1;
+        self.clocks_ula_beam_origin = first_pixel.saturating_add(self.clocks_ula_beam_shift);
+    }
+}
+
+/// Specs builder, used to make constants with built-in machines specs and to
+/// define custom machines. Line, frame and ULA timings are derived from the
+/// given values
 pub struct ZXSpecsBuilder {
     specs: ZXSpecs,
 }
+
+impl Default for ZXSpecsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ZXSpecsBuilder {
     /// Returns new ZXSpecsBuilder
-    pub fn new() -> ZXSpecsBuilder {
+    pub const fn new() -> ZXSpecsBuilder {
         ZXSpecsBuilder {
             specs: ZXSpecs {
                 // frequencies
@@ -54,8 +263,10 @@ impl ZXSpecsBuilder {
                 clocks_ula_read_origin: 0,
                 clocks_ula_contention_origin: 0,
                 clocks_ula_beam_shift: 0,
+                clocks_ula_beam_origin: 0,
                 // frame clocks
                 clocks_frame: 0,
+                frames_per_second: 0,
                 // lines metrics
                 lines_vsync: 0,
                 lines_top_border: 0,
@@ -68,30 +279,35 @@ impl ZXSpecsBuilder {
                 contention_offset: 0,
                 contention_pattern: [0; 8],
                 // memory
-                rom_pages: 0,
+                memory_model: MemoryModel::Sinclair48K,
+                rom_pages: 1,
             },
         }
     }
 
-    /// Builds new ZXSpecs
-    pub fn build(mut self) -> ZXSpecs {
+    /// Builds new ZXSpecs without validation, used for built-in machines
+    pub const fn build(mut self) -> ZXSpecs {
         self.specs.clocks_frame =
             (self.specs.lines_all + self.specs.lines_vsync) * self.specs.clocks_line;
-        self.specs.clocks_ula_read_origin =
-            self.specs.clocks_first_pixel + self.specs.clocks_ula_read_shift;
-        self.specs.clocks_ula_contention_origin =
-            self.specs.clocks_first_pixel - self.specs.contention_offset;
+        self.specs.update_ula_origins();
         self.specs
     }
 
+    /// Builds new ZXSpecs and checks them via [ZXSpecs::validate]
+    pub fn try_build(self) -> Result<ZXSpecs, MachineSpecsError> {
+        let specs = self.build();
+        specs.validate()?;
+        Ok(specs)
+    }
+
     /// Changes CPU frequency
-    pub fn freq_cpu(mut self, value: usize) -> Self {
+    pub const fn freq_cpu(mut self, value: usize) -> Self {
         self.specs.freq_cpu = value;
         self
     }
 
     /// Changes Clocks per left border, screen render, left border and retrace
-    pub fn clocks_row(
+    pub const fn clocks_row(
         mut self,
         lborder: usize,
         screen: usize,
@@ -107,25 +323,31 @@ impl ZXSpecsBuilder {
     }
 
     /// Changes first pixel clocks
-    pub fn clocks_first_pixel(mut self, value: usize) -> Self {
+    pub const fn clocks_first_pixel(mut self, value: usize) -> Self {
         self.specs.clocks_first_pixel = value;
         self
     }
 
     /// Changes shift of time, when ula reads data from memory
-    pub fn clocks_ula_read_shift(mut self, value: usize) -> Self {
+    pub const fn clocks_ula_read_shift(mut self, value: usize) -> Self {
         self.specs.clocks_ula_read_shift = value;
         self
     }
 
     /// Changes shift of electron beam pixel rendering
-    pub fn clocks_ula_beam_shift(mut self, value: usize) -> Self {
+    pub const fn clocks_ula_beam_shift(mut self, value: usize) -> Self {
         self.specs.clocks_ula_beam_shift = value;
         self
     }
 
     /// Changes lines per top border, screen, bottom border and vsync
-    pub fn lines(mut self, tborder: usize, screen: usize, bborder: usize, vsync: usize) -> Self {
+    pub const fn lines(
+        mut self,
+        tborder: usize,
+        screen: usize,
+        bborder: usize,
+        vsync: usize,
+    ) -> Self {
         self.specs.lines_vsync = vsync;
         self.specs.lines_top_border = tborder;
         self.specs.lines_screen = screen;
@@ -135,20 +357,134 @@ impl ZXSpecsBuilder {
     }
 
     /// Changes contention pattern
-    pub fn contention(mut self, pattern: [usize; 8], offset: usize) -> Self {
+    pub const fn contention(mut self, pattern: [usize; 8]) -> Self {
         self.specs.contention_pattern = pattern;
-        self.specs.contention_offset = offset;
+        self
+    }
+
+    /// Changes ULA delay relative to the first pixel clocks
+    pub const fn contention_offset(mut self, value: i32) -> Self {
+        self.specs.contention_offset = value;
+        self
+    }
+
+    /// Changes frame rate, used to derive host-side timings (e.g. samples per frame)
+    pub const fn frames_per_second(mut self, value: usize) -> Self {
+        self.specs.frames_per_second = value;
         self
     }
 
     /// changes interrupt length
-    pub fn interrupt_length(mut self, value: usize) -> Self {
+    pub const fn interrupt_length(mut self, value: usize) -> Self {
         self.specs.interrupt_length = value;
         self
     }
 
-    pub fn rom_pages(mut self, value: u8) -> Self {
-        self.specs.rom_pages = value;
+    /// Changes memory model, ROM pages count is derived from it
+    pub const fn memory_model(mut self, value: MemoryModel) -> Self {
+        self.specs.memory_model = value;
+        self.specs.rom_pages = value.rom_pages();
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zx::{
+        constants::{PIXELS_PER_CLOCK, SCREEN_HEIGHT, SCREEN_WIDTH},
+        machine::{SPECS_128K, SPECS_48K, SPECS_48K_EARLY, SPECS_48K_NTSC, SPECS_PLUS3},
+    };
+    use core::ops::Range;
+
+    /// Beam timing of the rendered border, used to check that border
+    /// rendering and ULA fetches agree on the line layout
+    impl ZXSpecs {
+        /// Returns clocks when the beam draws the given pixel of the rendered
+        /// screen. Border color set before this time is visible at the pixel
+        fn clocks_beam(&self, x: usize, y: usize) -> usize {
+            self.clocks_beam_origin() + y * self.clocks_line + x / PIXELS_PER_CLOCK
+        }
+
+        /// Returns clocks range of the rendered top border lines (above the
+        /// canvas, including horizontal retrace after each line)
+        fn clocks_top_border(&self) -> Range<usize> {
+            let start = self.clocks_beam(0, 0);
+            start..start + 8 * BORDER_ROWS * self.clocks_line
+        }
+
+        /// Returns clocks range of the rendered bottom border lines (below the
+        /// canvas, including horizontal retrace after each line)
+        fn clocks_bottom_border(&self) -> Range<usize> {
+            let start = self.clocks_beam(0, SCREEN_HEIGHT - 8 * BORDER_ROWS);
+            start..start + 8 * BORDER_ROWS * self.clocks_line
+        }
+
+        /// Returns true if the beam draws visible pixels of the rendered screen at
+        /// the given clocks (it is not in the horizontal retrace, vertical
+        /// retrace or the border outside of the rendered area)
+        fn is_beam_visible(&self, clocks: usize) -> bool {
+            let origin = self.clocks_beam_origin();
+            clocks >= origin
+                && clocks < origin + SCREEN_HEIGHT * self.clocks_line
+                && (clocks - origin) % self.clocks_line < SCREEN_WIDTH / PIXELS_PER_CLOCK
+        }
+    }
+
+    #[test]
+    fn border_clock_ranges() {
+        for specs in [
+            SPECS_48K,
+            SPECS_48K_EARLY,
+            SPECS_48K_NTSC,
+            SPECS_128K,
+            SPECS_PLUS3,
+        ] {
+            let top = specs.clocks_top_border();
+            let bottom = specs.clocks_bottom_border();
+            // Top border ends on the first canvas line, the canvas starts after
+            // the left border columns
+            assert_eq!(top.end + 16, specs.clocks_ula_beam_origin);
+            assert_eq!(bottom.start, top.end + 192 * specs.clocks_line);
+            assert!(bottom.end <= specs.clocks_frame);
+            assert_eq!(top.start, specs.clocks_beam(0, 0));
+            assert!(specs.is_beam_visible(top.start));
+            assert!(!specs.is_beam_visible(top.start - 1));
+            // Last pixel of the line and horizontal retrace
+            assert!(specs.is_beam_visible(specs.clocks_beam(319, 0)));
+            assert!(!specs.is_beam_visible(specs.clocks_beam(319, 0) + 1));
+            assert!(specs.is_beam_visible(bottom.end - specs.clocks_line));
+            assert!(!specs.is_beam_visible(bottom.end));
+        }
+    }
+
+    #[test]
+    fn ula_fetch_in_border_and_retrace() {
+        for specs in [
+            SPECS_48K,
+            SPECS_48K_EARLY,
+            SPECS_48K_NTSC,
+            SPECS_128K,
+            SPECS_PLUS3,
+        ] {
+            let origin = specs.clocks_ula_read_origin;
+            assert_eq!(specs.ula_fetch(0), None);
+            assert_eq!(specs.ula_fetch(origin - 1), None);
+            for clocks in specs
+                .clocks_top_border()
+                .chain(specs.clocks_bottom_border())
+            {
+                assert_eq!(specs.ula_fetch(clocks), None);
+            }
+            // Right border, horizontal retrace and left border of each line
+            for line in 0..192 {
+                let line_start = origin + line * specs.clocks_line;
+                assert!(specs.ula_fetch(line_start).is_some());
+                for clocks in line_start + 128..line_start + specs.clocks_line {
+                    assert_eq!(specs.ula_fetch(clocks), None);
+                }
+            }
+            assert_eq!(specs.ula_fetch(origin + 192 * specs.clocks_line), None);
+        }
+    }
+}
@@ -5,17 +5,28 @@ pub const PAGE_SIZE: usize = 16 * 1024;
 // different memory blocks size's
 pub const SIZE_16K: usize = PAGE_SIZE;
 pub const SIZE_32K: usize = PAGE_SIZE * 2;
+pub const SIZE_64K: usize = PAGE_SIZE * 4;
 pub const SIZE_48K: usize = PAGE_SIZE * 3;
 pub const SIZE_128K: usize = PAGE_SIZE * 8;
 // count of all memory blocks
 pub const MEM_BLOCKS: usize = 4;
+/// Size of the chunk of the Timex horizontal memory management unit
+pub const CHUNK_SIZE: usize = 8 * 1024;
+/// Count of 8K chunks in the address space
+pub const CHUNKS: usize = 8;
+/// RAM banks mapped to 0x0000, 0x4000, 0x8000 and 0xC000 in the +2A/+3 special
+/// (all-RAM) paging mode, indexed by bits 1-2 of the 0x1FFD port value
+pub const SPECIAL_PAGING_BANKS: [[u8; MEM_BLOCKS]; 4] =
+    [[0, 1, 2, 3], [4, 5, 6, 7], [4, 5, 6, 3], [4, 7, 6, 3]];
 
 /// Rom can be:
 /// - 16K (Sinclair48K)
 /// - 32K (Sinclair128K, 2+)
+/// - 64K (Amstrad 2A+, 3+)
 pub enum RomType {
     K16,
     K32,
+    K64,
 }
 
 /// Ram can be:
@@ -33,12 +44,27 @@ pub enum Page {
     Rom(u8),
 }
 
+/// Contents of the 8K chunk of the cartridge (DOCK) bank
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkType {
+    Absent,
+    Rom,
+    Ram,
+}
+
 // Memory struct
 pub struct ZXMemory {
     rom: Vec<u8>,
     ram: Vec<u8>,
     // 4 x 16K blocks  map
     map: [Page; 4],
+    // Cartridge (DOCK) bank of the Timex horizontal MMU, 8 x 8K chunks,
+    // empty when no cartridge is inserted
+    dock: Vec<u8>,
+    dock_chunks: [ChunkType; CHUNKS],
+    // Chunks mapped from the DOCK bank over the blocks map, bit per chunk as
+    // in Timex 0xF4 port. Only bits of the present chunks are set
+    dock_mask: u8,
 }
 
 impl ZXMemory {
@@ -60,16 +86,36 @@ impl ZXMemory {
         let rom_size = match rom_type {
             RomType::K16 => SIZE_16K,
             RomType::K32 => SIZE_32K,
+            RomType::K64 => SIZE_64K,
         };
         ZXMemory {
             rom: vec![0; rom_size],
             ram: vec![0; ram_size],
             map: mem_map,
+            dock: Vec::new(),
+            dock_chunks: [ChunkType::Absent; CHUNKS],
+            dock_mask: 0,
+        }
+    }
+
+    /// Fills whole RAM with values from splitmix64 generator seeded with `seed`
+    pub fn fill_ram_pseudo_random(&mut self, seed: u64) {
+        let mut state = seed;
+        for chunk in self.ram.chunks_mut(8) {
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut value = state;
+            value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            value ^= value >> 31;
+            chunk.copy_from_slice(&value.to_le_bytes()[..chunk.len()]);
         }
     }
 
     /// Returns value form memory
     pub fn read(&self, addr: u16) -> u8 {
+        if self.dock_chunk(addr).is_some() {
+            return self.dock[addr as usize];
+        }
         let (page, offset) = self.paged_address(addr);
         match page {
             Page::Rom(page) => self.rom[(page as usize) * PAGE_SIZE + offset],
@@ -79,6 +125,14 @@ impl ZXMemory {
 
     /// Writes value to writable memory
     pub fn write(&mut self, addr: u16, value: u8) {
+        match self.dock_chunk(addr) {
+            Some(ChunkType::Ram) => {
+                self.dock[addr as usize] = value;
+                return;
+            }
+            Some(_) => return,
+            None => {}
+        }
         let (page, offset) = self.paged_address(addr);
         if let Page::Ram(page) = page {
             self.ram[(page as usize) * PAGE_SIZE + offset] = value;
@@ -87,6 +141,10 @@ impl ZXMemory {
 
     /// Writes to memory space, overriding even ROM routines. Useful for testing and ROM poke's
     pub(crate) fn force_write(&mut self, addr: u16, value: u8) {
+        if self.dock_chunk(addr).is_some() {
+            self.dock[addr as usize] = value;
+            return;
+        }
         let (page, offset) = self.paged_address(addr);
         match page {
             Page::Ram(page) => self.ram[(page as usize) * PAGE_SIZE + offset] = value,
@@ -112,6 +170,61 @@ impl ZXMemory {
         self
     }
 
+    /// Maps RAM to all four blocks as in +2A/+3 special paging mode (used e.g.
+    /// by CP/M), `config` is the 0-3 value of 0x1FFD port bits 1-2
+    pub fn set_special_paging(&mut self, config: u8) -> &mut ZXMemory {
+        for (block, bank) in SPECIAL_PAGING_BANKS[config as usize & 0x03]
+            .iter()
+            .enumerate()
+        {
+            self.remap(block, Page::Ram(*bank));
+        }
+        self
+    }
+
+    /// Installs cartridge (DOCK) bank, `data` contains 8K of each chunk,
+    /// contents of absent chunks are ignored. Bank is not mapped until
+    /// [ZXMemory::set_dock_mask] is called
+    pub fn insert_dock(&mut self, chunks: [ChunkType; CHUNKS], data: Vec<u8>) {
+        assert_eq!(data.len(), CHUNKS * CHUNK_SIZE);
+        self.dock = data;
+        self.dock_chunks = chunks;
+        self.dock_mask = 0;
+    }
+
+    /// Removes cartridge (DOCK) bank, returns false if there was none
+    pub fn remove_dock(&mut self) -> bool {
+        self.dock_chunks = [ChunkType::Absent; CHUNKS];
+        self.dock_mask = 0;
+        !core::mem::take(&mut self.dock).is_empty()
+    }
+
+    /// Moves cartridge (DOCK) bank and its mapping from `other`, e.g. when
+    /// memory is rebuilt for another machine
+    pub fn take_dock_from(&mut self, other: &mut ZXMemory) {
+        self.dock_chunks = other.dock_chunks;
+        self.dock_mask = other.dock_mask;
+        self.dock = core::mem::take(&mut other.dock);
+        other.remove_dock();
+    }
+
+    /// Selects chunks mapped from the DOCK bank, bit per 8K chunk. Absent
+    /// chunks are never mapped
+    pub fn set_dock_mask(&mut self, mask: u8) {
+        let present = self
+            .dock_chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| **chunk != ChunkType::Absent)
+            .fold(0, |present, (index, _)| present | (1 << index));
+        self.dock_mask = mask & present;
+    }
+
+    /// Returns chunks mapped from the DOCK bank, bit per 8K chunk
+    pub fn dock_mask(&self) -> u8 {
+        self.dock_mask
+    }
+
     /// Returns bank type of mapped page
     pub fn get_bank_type(&self, block: usize) -> Page {
         assert!(block < MEM_BLOCKS);
@@ -141,6 +254,27 @@ impl ZXMemory {
         &mut self.ram[shift..shift + PAGE_SIZE]
     }
 
+    /// Returns slice to rom page, `None` if page does not exist
+    pub fn rom_page_data(&self, page: u8) -> Option<&[u8]> {
+        let shift = page as usize * PAGE_SIZE;
+        self.rom.get(shift..shift + PAGE_SIZE)
+    }
+
+    /// Returns count of 16K RAM pages
+    pub fn ram_pages(&self) -> u8 {
+        (self.ram.len() / PAGE_SIZE) as u8
+    }
+
+    /// Returns the lowest CPU address at which byte of the RAM page is
+    /// visible, `None` if the page is not mapped or the byte is covered by
+    /// the DOCK bank
+    pub fn ram_address(&self, page: u8, offset: u16) -> Option<u16> {
+        (0..MEM_BLOCKS)
+            .filter(|block| self.map[*block] == Page::Ram(page))
+            .map(|block| (block * PAGE_SIZE) as u16 + offset)
+            .find(|addr| self.dock_chunk(*addr).is_none())
+    }
+
     /// Returns slice to ram page
     pub fn ram_page_data(&self, page: u8) -> &[u8] {
         if (page as usize + 1) * PAGE_SIZE > self.ram.len() {
@@ -150,6 +284,12 @@ impl ZXMemory {
         &self.ram[shift..shift + PAGE_SIZE]
     }
 
+    /// Returns type of the DOCK bank chunk if it is mapped at the address
+    fn dock_chunk(&self, addr: u16) -> Option<ChunkType> {
+        let chunk = addr as usize / CHUNK_SIZE;
+        (self.dock_mask & (1 << chunk) != 0).then(|| self.dock_chunks[chunk])
+    }
+
     /// Calculates [Page] and local offset from memory address
     fn paged_address(&self, addr: u16) -> (Page, usize) {
         let page = self.map[(addr as usize) / PAGE_SIZE];
@@ -157,3 +297,113 @@ impl ZXMemory {
         (page, offset)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_with_marked_banks() -> ZXMemory {
+        let mut memory = ZXMemory::new(RomType::K32, RamType::K128);
+        for bank in 0..8 {
+            memory.ram_page_data_mut(bank).fill(0xB0 | bank);
+        }
+        memory
+    }
+
+    #[test]
+    fn rom_writes_are_ignored() {
+        let mut memory = ZXMemory::new(RomType::K16, RamType::K48);
+        memory.rom_page_data_mut(0).fill(0xC9);
+        memory.write(0x0000, 0x42);
+        memory.write(0x3FFF, 0x42);
+        assert_eq!(memory.read(0x0000), 0xC9);
+        assert_eq!(memory.read(0x3FFF), 0xC9);
+        assert!(memory.ram.iter().all(|byte| *byte == 0));
+
+        let mut memory = memory_with_marked_banks();
+        for page in 0..2 {
+            memory.rom_page_data_mut(page).fill(0xC0 | page);
+            memory.remap(0, Page::Rom(page));
+            memory.write(0x1234, 0x42);
+            assert_eq!(memory.read(0x1234), 0xC0 | page);
+        }
+        for bank in 0..8 {
+            assert!(memory.ram_page_data(bank).iter().all(|b| *b == 0xB0 | bank));
+        }
+    }
+
+    #[test]
+    fn ram_address_follows_paging() {
+        let mut memory = memory_with_marked_banks();
+        assert_eq!(memory.ram_address(5, 0x0123), Some(0x4123));
+        assert_eq!(memory.ram_address(0, 0x0123), Some(0xC123));
+        assert_eq!(memory.ram_address(3, 0x0123), None);
+        memory.remap(3, Page::Ram(5));
+        assert_eq!(memory.ram_address(5, 0x0123), Some(0x4123));
+        assert_eq!(memory.ram_address(0, 0x0123), None);
+        memory.set_special_paging(0);
+        assert_eq!(memory.ram_address(0, 0x0123), Some(0x0123));
+    }
+
+    #[test]
+    fn dock_chunks_overlay_blocks_map() {
+        let mut memory = memory_with_marked_banks();
+        let mut data = vec![0; CHUNKS * CHUNK_SIZE];
+        data[..CHUNK_SIZE].fill(0xD0);
+        data[CHUNK_SIZE * 7..].fill(0xD7);
+        let mut chunks = [ChunkType::Absent; CHUNKS];
+        chunks[0] = ChunkType::Rom;
+        chunks[7] = ChunkType::Ram;
+        memory.insert_dock(chunks, data);
+        // Not mapped until selected
+        assert_eq!(memory.read(0xE000), 0xB0);
+
+        memory.set_dock_mask(0xFF);
+        assert_eq!(memory.dock_mask(), 0x81);
+        assert_eq!(memory.read(0x0000), 0xD0);
+        assert_eq!(memory.read(0x2000), 0x00);
+        assert_eq!(memory.read(0xDFFF), 0xB0);
+        assert_eq!(memory.read(0xE000), 0xD7);
+
+        // ROM chunk is read-only, RAM chunk hides the bank under it
+        memory.write(0x0000, 0x42);
+        memory.write(0xE000, 0x42);
+        assert_eq!(memory.read(0x0000), 0xD0);
+        assert_eq!(memory.read(0xE000), 0x42);
+        assert_eq!(memory.ram_page_data(0)[0x2000], 0xB0);
+
+        assert!(memory.remove_dock());
+        assert!(!memory.remove_dock());
+        assert_eq!(memory.read(0xE000), 0xB0);
+    }
+
+    #[test]
+    fn special_paging_maps_ram_to_all_blocks() {
+        let mut memory = memory_with_marked_banks();
+        for (config, banks) in SPECIAL_PAGING_BANKS.iter().enumerate() {
+            memory.set_special_paging(config as u8);
+            for (block, bank) in banks.iter().enumerate() {
+                let addr = (block * PAGE_SIZE) as u16;
+                assert!(memory.get_bank_type(block) == Page::Ram(*bank));
+                assert_eq!(memory.read(addr), 0xB0 | bank, "config {}", config);
+            }
+        }
+    }
+
+    #[test]
+    fn special_paging_low_memory_is_writable() {
+        let mut memory = memory_with_marked_banks();
+        memory.set_special_paging(1);
+        memory.write(0x0000, 0x42);
+        assert_eq!(memory.read(0x0000), 0x42);
+        assert_eq!(memory.ram_page_data(4)[0], 0x42);
+
+        // Same bank is visible through the other config
+        memory.set_special_paging(3);
+        assert_eq!(memory.read(0x0000), 0x42);
+        // Back to normal paging, low memory is ROM again
+        memory.remap(0, Page::Rom(0));
+        memory.write(0x0000, 0x24);
+        assert_eq!(memory.read(0x0000), 0x00);
+    }
+}
@@ -1,18 +1,45 @@
 //! Module with ZX Spectrum related things
 //! One of core platform-independent modules
+#[cfg(feature = "betadisk")]
+pub(crate) mod betadisk;
+pub(crate) mod centronics;
 pub(crate) mod controller;
+pub(crate) mod dck;
 pub(crate) mod events;
+#[cfg(any(feature = "plusd", feature = "betadisk"))]
+pub(crate) mod fdc;
+#[cfg(feature = "ide")]
+pub(crate) mod ide;
 pub(crate) mod memory;
+#[cfg(feature = "plusd")]
+pub(crate) mod plusd;
 #[cfg(feature = "embedded-roms")]
 pub(crate) mod roms;
+#[cfg(feature = "spectranet")]
+pub(crate) mod spectranet;
 pub(crate) mod tape;
 
+#[cfg(feature = "ay")]
+pub(crate) mod ay_port;
 pub mod constants;
 pub mod joy;
+#[cfg(feature = "ay")]
+pub mod keypad;
 pub mod keys;
 pub mod machine;
 pub mod mouse;
+pub mod ports;
+pub mod rom_info;
+#[cfg(feature = "ay")]
+pub mod rs232;
 
 #[cfg(feature = "sound")]
 pub mod sound;
+pub mod storage;
 pub mod video;
+
+#[cfg(feature = "betadisk")]
+pub use betadisk::BetaDiskState;
+#[cfg(feature = "plusd")]
+pub use plusd::PlusDState;
+pub use tape::TapeProgress;
@@ -1,21 +1,46 @@
 const WHEEL_MASK: u8 = 0xF0;
 const WHEEL_SHIFT: usize = 4;
+/// Sensitivity which moves the mouse by the host distance
+pub const SENSITIVITY_NORMAL: u16 = 100;
+
+/// Scaling of the host mouse movement
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KempstonMouseConfig {
+    /// Movement scale of X axis in percents
+    pub x_sensitivity: u16,
+    /// Movement scale of Y axis in percents
+    pub y_sensitivity: u16,
+    /// Inverts X axis, positive host movement is to the right by default
+    pub invert_x: bool,
+    /// Inverts Y axis. Positive host movement is downwards, while Kempston
+    /// Y coordinate grows upwards, so it is decremented by default
+    pub invert_y: bool,
+}
+
+impl Default for KempstonMouseConfig {
+    fn default() -> Self {
+        Self {
+            x_sensitivity: SENSITIVITY_NORMAL,
+            y_sensitivity: SENSITIVITY_NORMAL,
+            invert_x: false,
+            invert_y: false,
+        }
+    }
+}
 
-// non_exhaustive allows to restrict struct instantiation only to `Default::defaault`
-#[non_exhaustive]
 pub(crate) struct KempstonMouse {
     pub buttons_port: u8,
     pub x_pos_port: u8,
     pub y_pos_port: u8,
+    config: KempstonMouseConfig,
+    // Scaled movement below single port step, in 1/100 of the step
+    x_remainder: i32,
+    y_remainder: i32,
 }
 
 impl Default for KempstonMouse {
     fn default() -> Self {
-        Self {
-            buttons_port: 0xFF,
-            x_pos_port: 0xFF,
-            y_pos_port: 0xFF,
-        }
+        Self::new(KempstonMouseConfig::default())
     }
 }
 
@@ -37,6 +62,17 @@ pub enum KempstonMouseWheelDirection {
 }
 
 impl KempstonMouse {
+    pub fn new(config: KempstonMouseConfig) -> Self {
+        Self {
+            buttons_port: 0xFF,
+            x_pos_port: 0xFF,
+            y_pos_port: 0xFF,
+            config,
+            x_remainder: 0,
+            y_remainder: 0,
+        }
+    }
+
     pub fn send_button(&mut self, button: KempstonMouseButton, pressed: bool) {
         if pressed {
             self.buttons_port &= !(button as u8);
@@ -47,14 +83,34 @@ impl KempstonMouse {
     }
 
     pub fn send_wheel(&mut self, dir: KempstonMouseWheelDirection) {
-        let mut current = (self.buttons_port & WHEEL_MASK) >> WHEEL_SHIFT;
-        current = ((current as i8) + (dir as i8)) as u8;
+        self.send_wheel_delta(dir as i8);
+    }
+
+    /// Adds `delta` steps to the 4-bit wheel counter in the upper nibble of
+    /// the buttons port, counter wraps around
+    pub fn send_wheel_delta(&mut self, delta: i8) {
+        let current = (self.buttons_port & WHEEL_MASK) >> WHEEL_SHIFT;
+        let current = current.wrapping_add(delta as u8);
         self.buttons_port =
             (self.buttons_port & (!WHEEL_MASK)) | ((current << WHEEL_SHIFT) & WHEEL_MASK);
     }
 
     pub fn send_pos_diff(&mut self, x: i8, y: i8) {
-        self.x_pos_port = ((self.x_pos_port as i16) + x as i16) as u8;
-        self.y_pos_port = ((self.y_pos_port as i16) - y as i16) as u8;
+        let config = self.config;
+        let x = scale(x, config.x_sensitivity, &mut self.x_remainder);
+        let y = scale(y, config.y_sensitivity, &mut self.y_remainder);
+        let x = if config.invert_x { -x } else { x };
+        let y = if config.invert_y { y } else { -y };
+        self.x_pos_port = self.x_pos_port.wrapping_add(x as u8);
+        self.y_pos_port = self.y_pos_port.wrapping_add(y as u8);
     }
 }
+
+/// Scales movement by `sensitivity` percents, fractional part is kept in
+/// `remainder` and added to the next movement, so slow movement is not lost
+fn scale(delta: i8, sensitivity: u16, remainder: &mut i32) -> i32 {
+    let scaled = delta as i32 * sensitivity as i32 + *remainder;
+    let steps = scaled / SENSITIVITY_NORMAL as i32;
+    *remainder = scaled % SENSITIVITY_NORMAL as i32;
+    steps
+}
@@ -0,0 +1,425 @@
+//! MGT +D disk and printer interface. When paged in, +D memory replaces the
+//! lower 16K of the address space:
+//! - `0x0000..0x2000` - 8K ROM (G+DOS)
+//! - `0x2000..0x4000` - 8K RAM
+//!
+//! +D is paged in by the instruction fetch from 0x0008 (RST 8), 0x003A
+//! (interrupt handler) or 0x0066 (NMI, generated by the snapshot button), and
+//! by reading port 0xE7. Writing port 0xE7 pages it out. Ports (only the low
+//! address byte is decoded):
+//! - `0xE3`, `0xEB`, `0xF3`, `0xFB` - WD1772 status/command, track, sector
+//!   and data registers
+//! - `0xEF` - control: bits 0 and 1 select drive 1 and 2, bit 6 is printer
+//!   strobe, bit 7 selects disk side
+//! - `0xF7` - printer data on write, busy flag (bit 7) on read
+//!
+//! Bytes are delivered to the printer on the strobe falling edge. The
+//! DISCiPLE uses other ports and is not emulated
+use crate::{
+    diagnostics::DISK,
+    error::DiskLoadError,
+    host::{DataRecorder, LoadableAsset},
+    utils::bytes::{read_to_end, ByteReader, ByteWriter},
+    zx::{
+        fdc::{disk::FloppyDisk, FdcModel, Wd17xx, Wd17xxState},
+        storage::{DriveStatus, PLUSD_DRIVE_COUNT},
+    },
+};
+use alloc::{vec, vec::Vec};
+
+pub(crate) const ROM_SIZE: usize = 0x2000;
+const RAM_SIZE: usize = 0x2000;
+pub(crate) const DRIVE_COUNT: usize = PLUSD_DRIVE_COUNT;
+
+const PORT_FDC_FIRST: u8 = 0xE3;
+const PORT_PAGING: u8 = 0xE7;
+const PORT_CONTROL: u8 = 0xEF;
+const PORT_PRINTER: u8 = 0xF7;
+
+const CONTROL_DRIVE_1: u8 = 0x01;
+const CONTROL_DRIVE_2: u8 = 0x02;
+const CONTROL_PRINTER_STROBE: u8 = 0x40;
+const CONTROL_SIDE: u8 = 0x80;
+
+const ADDR_RST8: u16 = 0x0008;
+const ADDR_INTERRUPT: u16 = 0x003A;
+const ADDR_NMI: u16 = 0x0066;
+
+/// Identifies serialized [PlusDState]
+const STATE_MAGIC: &[u8; 4] = b"RZPD";
+const STATE_VERSION: u8 = 2;
+
+pub(crate) struct PlusD {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    fdc: Wd17xx,
+    paged_in: bool,
+    control: u8,
+    printer_data: u8,
+    printer_output: Vec<u8>,
+    nmi_pending: bool,
+}
+
+/// +D interface state which snapshot formats have no place for, see
+/// [crate::Emulator::plusd_state]. Contents are opaque; disks are identified
+/// by the CRC32 of the inserted image, sectors changed since the insertion
+/// are stored in the state. State can be stored as bytes via
+/// [PlusDState::save]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlusDState {
+    paged_in: bool,
+    nmi_pending: bool,
+    ram: Vec<u8>,
+    control: u8,
+    printer_data: u8,
+    fdc: Wd17xxState,
+}
+
+impl PlusD {
+    /// Creates +D with the given ROM image (up to 8K), +D is paged out
+    pub fn new(rom: &[u8], freq_cpu: usize) -> Self {
+        let mut rom_data = vec![0xFF; ROM_SIZE];
+        let size = rom.len().min(ROM_SIZE);
+        rom_data[..size].copy_from_slice(&rom[..size]);
+        Self {
+            rom: rom_data,
+            ram: vec![0; RAM_SIZE],
+            fdc: Wd17xx::new(FdcModel::Wd1772, DRIVE_COUNT, freq_cpu),
+            paged_in: false,
+            control: 0,
+            printer_data: 0,
+            printer_output: Vec::new(),
+            nmi_pending: false,
+        }
+    }
+
+    pub fn paged_in(&self) -> bool {
+        self.paged_in
+    }
+
+    /// Returns true if port belongs to the interface
+    pub fn handles_port(port: u16) -> bool {
+        matches!(
+            port.to_le_bytes()[0],
+            0xE3 | PORT_PAGING | 0xEB | PORT_CONTROL | 0xF3 | PORT_PRINTER | 0xFB
+        )
+    }
+
+    /// Returns WD1772 register of the port, ports are 8 apart
+    fn fdc_register(port: u8) -> Option<u8> {
+        let offset = port.wrapping_sub(PORT_FDC_FIRST);
+        (offset.is_multiple_of(8) && offset < 32).then_some(offset / 8)
+    }
+
+    fn set_paged_in(&mut self, value: bool) {
+        if self.paged_in != value {
+            diag!(trace, DISK, "+D paged {}", if value { "in" } else { "out" });
+        }
+        self.paged_in = value;
+    }
+
+    /// Processes instruction fetch address, should be called before each
+    /// instruction fetch
+    pub fn check_fetch(&mut self, addr: u16) {
+        if matches!(addr, ADDR_RST8 | ADDR_INTERRUPT | ADDR_NMI) {
+            self.set_paged_in(true);
+        }
+    }
+
+    /// Presses the snapshot button, which generates NMI
+    pub fn press_snapshot_button(&mut self) {
+        self.nmi_pending = true;
+    }
+
+    /// Returns true after the snapshot button was pressed, until the NMI is
+    /// acknowledged via [PlusD::acknowledge_nmi]
+    pub fn nmi_pending(&self) -> bool {
+        self.nmi_pending
+    }
+
+    pub fn acknowledge_nmi(&mut self) {
+        self.nmi_pending = false;
+    }
+
+    /// Pages the interface out on the machine reset
+    pub fn reset(&mut self) {
+        self.set_paged_in(false);
+        self.nmi_pending = false;
+    }
+
+    /// Changes CPU frequency of the machine, which disk timings in clocks
+    /// depend on
+    pub fn set_freq_cpu(&mut self, freq_cpu: usize) {
+        self.fdc.set_freq_cpu(freq_cpu);
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        let addr = addr as usize;
+        if addr < ROM_SIZE {
+            self.rom[addr]
+        } else {
+            self.ram[addr - ROM_SIZE]
+        }
+    }
+
+    /// Writes RAM, ROM is read-only
+    pub fn write(&mut self, addr: u16, data: u8) {
+        if let Some(offset) = (addr as usize).checked_sub(ROM_SIZE) {
+            self.ram[offset] = data;
+        }
+    }
+
+    pub fn read_port(&mut self, port: u16, clocks: usize) -> u8 {
+        let [low, _] = port.to_le_bytes();
+        if let Some(reg) = Self::fdc_register(low) {
+            return self.fdc.read(reg, clocks);
+        }
+        match low {
+            PORT_PAGING => {
+                self.set_paged_in(true);
+                0xFF
+            }
+            // Printer is never busy
+            PORT_PRINTER => 0x00,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_port(&mut self, port: u16, data: u8, clocks: usize) {
+        let [low, _] = port.to_le_bytes();
+        if let Some(reg) = Self::fdc_register(low) {
+            self.fdc.write(reg, data, clocks);
+            return;
+        }
+        match low {
+            PORT_PAGING => self.set_paged_in(false),
+            PORT_PRINTER => self.printer_data = data,
+            PORT_CONTROL => self.write_control(data),
+            _ => {}
+        }
+    }
+
+    fn write_control(&mut self, data: u8) {
+        let drive = match data & (CONTROL_DRIVE_1 | CONTROL_DRIVE_2) {
+            CONTROL_DRIVE_1 => Some(0),
+            CONTROL_DRIVE_2 => Some(1),
+            _ => None,
+        };
+        self.fdc.select_drive(drive);
+        self.fdc.set_side((data & CONTROL_SIDE != 0) as u8);
+        let strobe = data & CONTROL_PRINTER_STROBE != 0;
+        if !strobe && self.control & CONTROL_PRINTER_STROBE != 0 {
+            self.printer_output.push(self.printer_data);
+        }
+        self.control = data;
+    }
+
+    /// Finishes the frame of `frame_clocks` length
+    pub fn new_frame(&mut self, frame_clocks: usize) {
+        self.fdc.new_frame(frame_clocks);
+    }
+
+    /// Returns status of the drive (0 or 1) at the end of the last frame
+    pub fn drive_status(&self, drive: usize) -> Option<DriveStatus> {
+        self.fdc.drive_status(drive)
+    }
+
+    /// Inserts disk into the drive (0 or 1), returns previous disk
+    pub fn insert_disk(&mut self, drive: usize, disk: FloppyDisk) -> Option<FloppyDisk> {
+        self.fdc.drive_mut(drive)?.disk.replace(disk)
+    }
+
+    pub fn eject_disk(&mut self, drive: usize) -> Option<FloppyDisk> {
+        self.fdc.drive_mut(drive)?.disk.take()
+    }
+
+    pub fn disk_mut(&mut self, drive: usize) -> Option<&mut FloppyDisk> {
+        self.fdc.drive_mut(drive)?.disk.as_mut()
+    }
+
+    pub fn state(&self) -> PlusDState {
+        PlusDState {
+            paged_in: self.paged_in,
+            nmi_pending: self.nmi_pending,
+            ram: self.ram.clone(),
+            control: self.control,
+            printer_data: self.printer_data,
+            fdc: self.fdc.state(),
+        }
+    }
+
+    /// Restores state, interface is left unchanged if disks in the drives
+    /// do not match the disks of the state
+    pub fn restore(&mut self, state: &PlusDState) -> Result<(), DiskLoadError> {
+        self.fdc.restore(&state.fdc)?;
+        self.paged_in = state.paged_in;
+        self.nmi_pending = state.nmi_pending;
+        self.ram.clone_from(&state.ram);
+        self.control = state.control;
+        self.printer_data = state.printer_data;
+        Ok(())
+    }
+
+    /// Returns bytes sent to the printer since the last call
+    pub fn take_printer_output(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.printer_output)
+    }
+}
+
+impl PlusDState {
+    /// Writes the state to `recorder`, it is read back with
+    /// [PlusDState::load]
+    pub fn save(&self, mut recorder: impl DataRecorder) -> crate::Result<()> {
+        let mut writer = ByteWriter::with_header(STATE_MAGIC, STATE_VERSION);
+        writer.bool(self.paged_in);
+        writer.bool(self.nmi_pending);
+        writer.bytes(&self.ram);
+        writer.u8(self.control);
+        writer.u8(self.printer_data);
+        self.fdc.write(&mut writer);
+        recorder.write_all(&writer.into_sealed())?;
+        Ok(())
+    }
+
+    /// Reads the state written by [PlusDState::save], damaged data is
+    /// rejected with [DiskLoadError::InvalidState]
+    pub fn load(asset: impl LoadableAsset) -> crate::Result<Self> {
+        let data = read_to_end(asset)?;
+        Self::parse(&data).ok_or_else(|| DiskLoadError::InvalidState.into())
+    }
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader::unseal(data, STATE_MAGIC, STATE_VERSION)?;
+        let state = Self {
+            paged_in: reader.bool()?,
+            nmi_pending: reader.bool()?,
+            ram: reader.bytes()?,
+            control: reader.u8()?,
+            printer_data: reader.u8()?,
+            fdc: Wd17xxState::read(&mut reader)?,
+        };
+        (reader.is_empty() && state.ram.len() == RAM_SIZE).then_some(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::Error,
+        host::BufferCursor,
+        zx::fdc::{
+            disk::{DiskGeometry, TrackOrder},
+            REG_SECTOR,
+        },
+    };
+
+    fn plusd() -> PlusD {
+        let rom = (0..ROM_SIZE)
+            .map(|idx| (idx >> 8) as u8)
+            .collect::<Vec<_>>();
+        PlusD::new(&rom, 3_500_000)
+    }
+
+    #[test]
+    fn paging() {
+        let mut plusd = plusd();
+        assert!(!plusd.paged_in());
+        plusd.check_fetch(0x0038);
+        assert!(!plusd.paged_in());
+        plusd.check_fetch(ADDR_INTERRUPT);
+        assert!(plusd.paged_in());
+        plusd.write_port(PORT_PAGING as u16, 0, 0);
+        assert!(!plusd.paged_in());
+        plusd.read_port(PORT_PAGING as u16, 0);
+        assert!(plusd.paged_in());
+
+        assert_eq!(plusd.read(0x1234), 0x12);
+        plusd.write(0x1234, 0xAA);
+        plusd.write(0x2345, 0xBB);
+        assert_eq!(plusd.read(0x1234), 0x12);
+        assert_eq!(plusd.read(0x2345), 0xBB);
+    }
+
+    #[test]
+    fn snapshot_button_requests_nmi_until_acknowledged() {
+        let mut plusd = plusd();
+        assert!(!plusd.nmi_pending());
+        plusd.press_snapshot_button();
+        assert!(plusd.nmi_pending());
+        assert!(plusd.nmi_pending());
+        plusd.acknowledge_nmi();
+        assert!(!plusd.nmi_pending());
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let mut plusd = plusd();
+        let geometry = DiskGeometry::PLUSD;
+        let data = vec![0; geometry.image_size()];
+        let disk = FloppyDisk::new(geometry, TrackOrder::SidesInterleaved, data).unwrap();
+        plusd.insert_disk(0, disk);
+        plusd.check_fetch(ADDR_NMI);
+        plusd.press_snapshot_button();
+        plusd.write(0x2345, 0xBB);
+        // Write sector 1 in progress
+        plusd.write_port(PORT_CONTROL as u16, CONTROL_DRIVE_1, 0);
+        plusd.write_port(0xF3, 1, 0);
+        plusd.write_port(0xE3, 0xA0, 0);
+        plusd.write_port(0xFB, 0x42, 0);
+        let state = plusd.state();
+
+        let mut bytes = Vec::new();
+        state.save(&mut bytes).unwrap();
+        assert_eq!(PlusDState::load(BufferCursor::new(&bytes)).unwrap(), state);
+
+        let mut restored = PlusD::new(&[], 3_500_000);
+        let data = vec![0; geometry.image_size()];
+        let disk = FloppyDisk::new(geometry, TrackOrder::SidesInterleaved, data).unwrap();
+        restored.insert_disk(0, disk);
+        restored.restore(&state).unwrap();
+        assert!(restored.paged_in());
+        assert!(restored.nmi_pending());
+        assert_eq!(restored.read(0x2345), 0xBB);
+        assert_eq!(restored.state(), state);
+
+        bytes[10] ^= 0x01;
+        assert!(matches!(
+            PlusDState::load(BufferCursor::new(&bytes)),
+            Err(Error::DiskLoad(DiskLoadError::InvalidState))
+        ));
+        assert!(PlusDState::load(BufferCursor::new(&bytes[..3])).is_err());
+    }
+
+    #[test]
+    fn printer_bytes_on_strobe() {
+        let mut plusd = plusd();
+        for byte in b"OK" {
+            plusd.write_port(PORT_PRINTER as u16, *byte, 0);
+            plusd.write_port(PORT_CONTROL as u16, CONTROL_PRINTER_STROBE, 0);
+            plusd.write_port(PORT_CONTROL as u16, 0, 0);
+        }
+        assert_eq!(plusd.take_printer_output(), b"OK");
+        assert!(plusd.take_printer_output().is_empty());
+    }
+
+    #[test]
+    fn fdc_ports_and_drive_select() {
+        let mut plusd = plusd();
+        let geometry = DiskGeometry::PLUSD;
+        let mut data = vec![0; geometry.image_size()];
+        // Track 0 side 1 sector 1
+        data[10 * 512] = 0x42;
+        let disk = FloppyDisk::new(geometry, TrackOrder::SidesInterleaved, data).unwrap();
+        assert!(plusd.insert_disk(1, disk).is_none());
+
+        assert_eq!(PlusD::fdc_register(0xF3), Some(REG_SECTOR));
+        plusd.write_port(PORT_CONTROL as u16, CONTROL_DRIVE_2 | CONTROL_SIDE, 0);
+        plusd.write_port(0xF3, 1, 0);
+        plusd.write_port(0xE3, 0x80, 0);
+        assert_eq!(plusd.read_port(0xE3, 0) & 0x03, 0x03);
+        assert_eq!(plusd.read_port(0xFB, 0), 0x42);
+        assert!(plusd.eject_disk(1).is_some());
+        assert!(plusd.eject_disk(2).is_none());
+    }
+}
@@ -0,0 +1,166 @@
+//! Port decoding of the peripherals. Most of the Spectrum peripherals decode
+//! only a few address lines, so two of them may respond to the same port
+//! (e.g. Kempston joystick and mouse, or the Fuller Box and disk interfaces
+//! which use 0x7F). As on the real hardware, all devices decoding the port
+//! receive the access: writes are passed to each of them, values read from
+//! several devices are combined by [BusRead]
+use alloc::{boxed::Box, vec::Vec};
+
+/// Peripheral which decodes I/O ports
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Peripheral {
+    KempstonJoystick,
+    KempstonMouse,
+    FullerJoystick,
+    FullerAy,
+    UlaPlus,
+    Ide,
+    Spectranet,
+    PlusD,
+}
+
+/// Direction of the port access
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortDirection {
+    In,
+    Out,
+}
+
+/// Single I/O port access performed by the CPU
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PortAccess {
+    /// CPU clocks since the frame start
+    pub clocks: usize,
+    pub port: u16,
+    /// Value read or written
+    pub value: u8,
+    pub direction: PortDirection,
+}
+
+/// Callback which receives all port accesses, see
+/// [crate::Emulator::set_port_logger]
+pub type PortLogger = Box<dyn FnMut(PortAccess)>;
+
+/// Port is decoded when `port & mask == value`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PortDecode {
+    pub mask: u16,
+    pub value: u16,
+}
+
+const fn decode(mask: u16, value: u16) -> PortDecode {
+    PortDecode { mask, value }
+}
+
+/// ULA decodes only A0, so any even port reads the keyboard and writes the
+/// border, beeper and MIC
+pub(crate) const ULA: PortDecode = decode(0x0001, 0x0000);
+/// AY register select/read (0xFFFD and mirrors)
+pub(crate) const AY_REGISTER: PortDecode = decode(0xC002, 0xC000);
+/// AY data write (0xBFFD and mirrors)
+pub(crate) const AY_DATA: PortDecode = decode(0xC002, 0x8000);
+/// 128K memory paging (0x7FFD and mirrors)
+pub(crate) const PAGING_128K: PortDecode = decode(0x8002, 0x0000);
+/// +2A/+3 memory paging (0x7FFD and mirrors), A14 is decoded too, so
+/// 0x1FFD and 0x0FFD do not reach it
+pub(crate) const PAGING_PLUS3: PortDecode = decode(0xC002, 0x4000);
+/// +2A/+3 special paging and ROM high bit (0x1FFD and mirrors)
+pub(crate) const PAGING_PLUS3_SPECIAL: PortDecode = decode(0xF002, 0x1000);
+/// +2A/+3 printer data latch on write, BUSY in bit 0 on read (0x0FFD and
+/// mirrors)
+pub(crate) const PRINTER_PLUS3: PortDecode = decode(0xF002, 0x0000);
+
+const KEMPSTON_JOYSTICK: &[PortDecode] = &[decode(0x00E0, 0x0000)];
+const KEMPSTON_MOUSE: &[PortDecode] = &[
+    decode(0x0121, 0x0001),
+    decode(0x0521, 0x0101),
+    decode(0x0521, 0x0501),
+];
+const FULLER_JOYSTICK: &[PortDecode] = &[decode(0x00FF, 0x007F)];
+const FULLER_AY: &[PortDecode] = &[decode(0x00FF, 0x003F), decode(0x00FF, 0x005F)];
+const ULAPLUS: &[PortDecode] = &[decode(0xFFFF, 0xBF3B), decode(0xFFFF, 0xFF3B)];
+const IDE: &[PortDecode] = &[decode(0x00E3, 0x00A3)];
+const SPECTRANET: &[PortDecode] = &[
+    decode(0xFFFF, 0x003B),
+    decode(0xFFFF, 0x013B),
+    decode(0xFFFF, 0x023B),
+    decode(0xFFFF, 0x033B),
+];
+const PLUSD: &[PortDecode] = &[
+    decode(0x00FF, 0x00E3),
+    decode(0x00FF, 0x00E7),
+    decode(0x00FF, 0x00EB),
+    decode(0x00FF, 0x00EF),
+    decode(0x00FF, 0x00F3),
+    decode(0x00FF, 0x00F7),
+    decode(0x00FF, 0x00FB),
+];
+
+impl PortDecode {
+    pub fn matches(&self, port: u16) -> bool {
+        port & self.mask == self.value
+    }
+
+    /// Returns true if some port is decoded by both `self` and `other`
+    pub fn overlaps(&self, other: &PortDecode) -> bool {
+        (self.value ^ other.value) & self.mask & other.mask == 0
+    }
+}
+
+impl Peripheral {
+    /// Returns decoded ports of the peripheral
+    pub fn ports(self) -> &'static [PortDecode] {
+        match self {
+            Self::KempstonJoystick => KEMPSTON_JOYSTICK,
+            Self::KempstonMouse => KEMPSTON_MOUSE,
+            Self::FullerJoystick => FULLER_JOYSTICK,
+            Self::FullerAy => FULLER_AY,
+            Self::UlaPlus => ULAPLUS,
+            Self::Ide => IDE,
+            Self::Spectranet => SPECTRANET,
+            Self::PlusD => PLUSD,
+        }
+    }
+
+    /// Returns true if the peripheral decodes `port`
+    pub fn decodes(self, port: u16) -> bool {
+        self.ports().iter().any(|decode| decode.matches(port))
+    }
+
+    /// Returns true if some port is decoded by both peripherals
+    pub fn conflicts_with(self, other: Peripheral) -> bool {
+        self.ports()
+            .iter()
+            .any(|a| other.ports().iter().any(|b| a.overlaps(b)))
+    }
+}
+
+/// Value on the data bus during the port read. Devices can only pull data
+/// lines low, so when several devices respond their values are ANDed
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct BusRead(Option<u8>);
+
+impl BusRead {
+    pub fn respond(&mut self, value: u8) {
+        self.0 = Some(self.0.map_or(value, |current| current & value));
+    }
+
+    /// Returns combined value, `None` if no device responded and the bus
+    /// floats
+    pub fn value(self) -> Option<u8> {
+        self.0
+    }
+}
+
+/// Returns pairs of peripherals which decode the same ports
+pub fn conflicts(peripherals: &[Peripheral]) -> Vec<(Peripheral, Peripheral)> {
+    let mut result = Vec::new();
+    for (n, a) in peripherals.iter().enumerate() {
+        for b in &peripherals[n + 1..] {
+            if a.conflicts_with(*b) {
+                result.push((*a, *b));
+            }
+        }
+    }
+    result
+}
@@ -0,0 +1,249 @@
+//! Identification of the loaded ROM pages by CRC32 checksum. Tape traps are
+//! tied to the routine addresses of the specific ROM, so they are selected
+//! by the identified ROM instead of the page number. Custom ROMs (e.g. with
+//! moved routines) would be broken silently otherwise
+use crate::{
+    utils::crc::crc32,
+    zx::{
+        constants::{ADDR_LD_BREAK, ADDR_WAIT_KEY_128K, ADDR_WAIT_KEY_48K},
+        machine::MemoryModel,
+    },
+};
+use alloc::vec::Vec;
+
+/// ROM known by its checksum
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KnownRom {
+    /// 48K BASIC ROM
+    Sinclair48K,
+    /// 128K ROM 0 (128 BASIC and menu)
+    Sinclair128KEditor,
+    /// 128K ROM 1 (48 BASIC)
+    Sinclair128KBasic,
+    /// Spanish 48K BASIC ROM
+    Spanish48K,
+    /// Spanish 128K ROM 0 (128 BASIC and menu)
+    Spanish128KEditor,
+    /// Spanish 128K ROM 1 (48 BASIC)
+    Spanish128KBasic,
+    /// Gosh Wonderful v0.3, 48K ROM with moved routines
+    GoshWonderful,
+    /// +2 ROM 0 (128 BASIC and menu)
+    Plus2Editor,
+    /// +2 ROM 1 (48 BASIC)
+    Plus2Basic,
+    /// +3 v4.0 ROM 0 (128 BASIC and menu)
+    Plus3Editor,
+    /// +3 v4.0 ROM 1 (128 syntax checker)
+    Plus3Syntax,
+    /// +3 v4.0 ROM 2 (+3DOS)
+    Plus3Dos,
+    /// +3 v4.0 ROM 3 (48 BASIC)
+    Plus3Basic,
+    /// ZX Diagnostics ROM v56
+    DiagRom,
+}
+
+impl KnownRom {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sinclair48K => "Sinclair 48K BASIC",
+            Self::Sinclair128KEditor => "Sinclair 128K ROM 0",
+            Self::Sinclair128KBasic => "Sinclair 128K ROM 1",
+            Self::Spanish48K => "Sinclair 48K BASIC (Spanish)",
+            Self::Spanish128KEditor => "Sinclair 128K ROM 0 (Spanish)",
+            Self::Spanish128KBasic => "Sinclair 128K ROM 1 (Spanish)",
+            Self::GoshWonderful => "Gosh Wonderful v0.3",
+            Self::Plus2Editor => "Amstrad +2 ROM 0",
+            Self::Plus2Basic => "Amstrad +2 ROM 1",
+            Self::Plus3Editor => "Amstrad +3 v4.0 ROM 0",
+            Self::Plus3Syntax => "Amstrad +3 v4.0 ROM 1",
+            Self::Plus3Dos => "Amstrad +3 v4.0 ROM 2",
+            Self::Plus3Basic => "Amstrad +3 v4.0 ROM 3",
+            Self::DiagRom => "ZX Diagnostics v56",
+        }
+    }
+
+    /// Returns trap addresses of the ROM routines
+    pub fn traps(self) -> RomTraps {
+        match self {
+            // 48 BASIC tape routines are at the same addresses in all models
+            Self::Sinclair48K
+            | Self::Sinclair128KBasic
+            | Self::Spanish48K
+            | Self::Spanish128KBasic
+            | Self::Plus2Basic
+            | Self::Plus3Basic => RomTraps::BASIC_48K,
+            Self::Sinclair128KEditor => RomTraps::EDITOR_128K,
+            _ => RomTraps::NONE,
+        }
+    }
+}
+
+/// CRC32 checksums of known 16K ROM pages
+const KNOWN_ROMS: [(u32, KnownRom); 15] = [
+    (0xDDEE531F, KnownRom::Sinclair48K),
+    // Built-in 48K ROM
+    (0x246B0125, KnownRom::Sinclair48K),
+    (0xE76799D2, KnownRom::Sinclair128KEditor),
+    (0xB96A36BE, KnownRom::Sinclair128KBasic),
+    (0xF051746E, KnownRom::Spanish48K),
+    (0x453D86B2, KnownRom::Spanish128KEditor),
+    (0x6010E796, KnownRom::Spanish128KBasic),
+    (0x5AD5D9C5, KnownRom::GoshWonderful),
+    (0x5D2E8C66, KnownRom::Plus2Editor),
+    (0x98B1320B, KnownRom::Plus2Basic),
+    (0x17373DA2, KnownRom::Plus3Editor),
+    (0xF1D1D99E, KnownRom::Plus3Syntax),
+    (0x3DBF351D, KnownRom::Plus3Dos),
+    (0x04448EAA, KnownRom::Plus3Basic),
+    (0x0ED22F7A, KnownRom::DiagRom),
+];
+
+fn known_rom(crc32: u32) -> Option<KnownRom> {
+    KNOWN_ROMS
+        .iter()
+        .find(|(known_crc32, _)| *known_crc32 == crc32)
+        .map(|(_, rom)| *rom)
+}
+
+/// Addresses of the ROM routines which are trapped by the emulator, `None`
+/// if the routine is absent in the ROM
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RomTraps {
+    /// LD-BREAK of the tape loading routine, used for tape fast load
+    pub ld_break: Option<u16>,
+    /// Loop of the editor waiting for a key, used to detect the end of boot
+    pub key_wait: Option<u16>,
+}
+
+impl RomTraps {
+    pub const NONE: Self = Self {
+        ld_break: None,
+        key_wait: None,
+    };
+    /// Traps of the 48 BASIC ROM
+    pub const BASIC_48K: Self = Self {
+        ld_break: Some(ADDR_LD_BREAK),
+        key_wait: Some(ADDR_WAIT_KEY_48K),
+    };
+    /// Traps of the 128K ROM 0, menu and 128 BASIC editor
+    pub const EDITOR_128K: Self = Self {
+        ld_break: None,
+        key_wait: Some(ADDR_WAIT_KEY_128K),
+    };
+}
+
+/// Identification of the single ROM page
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RomPageInfo {
+    pub crc32: u32,
+    /// `None` if the page checksum is not in the table of known ROMs
+    pub rom: Option<KnownRom>,
+    /// Traps used for the page. Unknown 48 BASIC page (see
+    /// [RomInfo::basic_page]) uses traps of the original ROM, as most custom
+    /// ROMs are patched versions of it
+    pub traps: RomTraps,
+}
+
+/// Identification of the loaded ROM, see [crate::Emulator::rom_info]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RomInfo {
+    pages: Vec<RomPageInfo>,
+}
+
+impl RomInfo {
+    pub(crate) fn identify<'a>(
+        memory_model: MemoryModel,
+        pages: impl Iterator<Item = &'a [u8]>,
+    ) -> Self {
+        // 48 BASIC is the last ROM page in all models
+        let basic_page = memory_model.rom_pages() - 1;
+        let pages = pages
+            .enumerate()
+            .map(|(index, data)| {
+                let crc32 = crc32(data);
+                let rom = known_rom(crc32);
+                let traps = match rom {
+                    Some(rom) => rom.traps(),
+                    None if index == basic_page as usize => RomTraps::BASIC_48K,
+                    None => RomTraps::NONE,
+                };
+                RomPageInfo { crc32, rom, traps }
+            })
+            .collect();
+        Self { pages }
+    }
+
+    pub fn pages(&self) -> &[RomPageInfo] {
+        &self.pages
+    }
+
+    /// Returns true if all ROM pages are known
+    pub fn is_known(&self) -> bool {
+        self.pages.iter().all(|page| page.rom.is_some())
+    }
+
+    /// Returns index of the page which is expected to contain 48 BASIC
+    pub fn basic_page(&self) -> u8 {
+        (self.pages.len() as u8).saturating_sub(1)
+    }
+
+    /// Returns traps of the ROM page, no traps for non-existing pages
+    pub fn traps(&self, page: u8) -> RomTraps {
+        self.pages
+            .get(page as usize)
+            .map(|page| page.traps)
+            .unwrap_or(RomTraps::NONE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_known_rom_is_identified() {
+        let all = [
+            KnownRom::Sinclair48K,
+            KnownRom::Sinclair128KEditor,
+            KnownRom::Sinclair128KBasic,
+            KnownRom::Spanish48K,
+            KnownRom::Spanish128KEditor,
+            KnownRom::Spanish128KBasic,
+            KnownRom::GoshWonderful,
+            KnownRom::Plus2Editor,
+            KnownRom::Plus2Basic,
+            KnownRom::Plus3Editor,
+            KnownRom::Plus3Syntax,
+            KnownRom::Plus3Dos,
+            KnownRom::Plus3Basic,
+            KnownRom::DiagRom,
+        ];
+        for (crc32, rom) in KNOWN_ROMS {
+            assert_eq!(known_rom(crc32), Some(rom), "{:08X}", crc32);
+        }
+        for rom in all {
+            assert!(
+                KNOWN_ROMS.iter().any(|(_, known)| *known == rom),
+                "{}",
+                rom.name()
+            );
+        }
+        assert_eq!(known_rom(0), None);
+    }
+
+    #[test]
+    fn unknown_basic_page_uses_default_traps() {
+        let editor = [0u8; 16];
+        let basic = [1u8; 16];
+        let info = RomInfo::identify(
+            MemoryModel::Sinclair128K,
+            [&editor[..], &basic[..]].into_iter(),
+        );
+        assert!(!info.is_known());
+        assert_eq!(info.traps(0), RomTraps::NONE);
+        assert_eq!(info.traps(1), RomTraps::BASIC_48K);
+        assert_eq!(info.traps(2), RomTraps::NONE);
+    }
+}
@@ -0,0 +1,320 @@
+//! RS232 port of the 128K models, which is bit-banged by ROM via the I/O port
+//! A of the AY chip (register 14). Serial lines are emulated at the bit
+//! level: bytes sent by the Spectrum (e.g. `LLIST`/`LPRINT` in 128 BASIC)
+//! are decoded by the UART of the attached host device, and bytes provided
+//! by the host are sent back to the Spectrum on its TXD line.
+//!
+//! Frames are 8N1 with logic high idle level. Both sides are paced by the
+//! device baud rate, which should match the one set in 128 BASIC via
+//! `FORMAT "p";baud` (9600 by default, stored in BAUD system variable).
+//!
+//! Printer channel of 128 BASIC works as the text channel ("t") of the
+//! Interface 1: keywords are expanded and lines end with CR LF. Interface 1
+//! itself is not emulated
+use crate::{diagnostics::SERIAL, zx::ay_port::PortADevice};
+use alloc::{collections::VecDeque, vec::Vec};
+
+/// AY register with the I/O port A, which is wired to the RS232 and keypad
+pub const AY_REG_PORT_A: u8 = 14;
+/// Default baud rate of the 128K ROM
+pub const DEFAULT_BAUD_RATE: u32 = 9600;
+
+/// RS232 CTS output, low when the Spectrum is ready to receive
+const PORT_A_CTS: u8 = 0x04;
+/// RS232 RXD output, serial data sent by the Spectrum
+const PORT_A_RXD: u8 = 0x08;
+/// RS232 DTR input, low when the device is ready to receive
+const PORT_A_DTR: u8 = 0x40;
+/// RS232 TXD input, serial data sent by the device
+const PORT_A_TXD: u8 = 0x80;
+
+/// Start bit, 8 data bits and stop bit
+const FRAME_BITS: i64 = 10;
+const STOP_BIT: u8 = 9;
+
+/// Decoding state of the frame sent by the Spectrum
+#[derive(Clone, Copy)]
+enum Receiver {
+    Idle,
+    Frame {
+        /// Time of the next bit sample (middle of the bit)
+        next_sample: i64,
+        /// Index of the next sampled bit, 0 is the start bit
+        bit: u8,
+        data: u8,
+    },
+}
+
+/// Host device attached to the RS232 port. Times are in CPU clocks relative
+/// to the current frame start
+pub(crate) struct Rs232Port {
+    baud_rate: u32,
+    bit_clocks: i64,
+    /// Current level of the Spectrum RXD line (data from the Spectrum)
+    rx_level: bool,
+    receiver: Receiver,
+    output: Vec<u8>,
+    framing_errors: usize,
+    /// Time since the Spectrum is ready to receive, `None` if CTS is inactive
+    cts_since: Option<i64>,
+    input: VecDeque<u8>,
+    /// Byte which is being sent to the Spectrum and its start time
+    transmitting: Option<(i64, u8)>,
+    /// End time of the last byte sent to the Spectrum
+    last_transmit_end: i64,
+}
+
+impl Rs232Port {
+    pub fn new(baud_rate: u32, freq_cpu: usize) -> Self {
+        Self {
+            baud_rate,
+            bit_clocks: Self::bit_clocks(baud_rate, freq_cpu),
+            rx_level: true,
+            receiver: Receiver::Idle,
+            output: Vec::new(),
+            framing_errors: 0,
+            cts_since: None,
+            input: VecDeque::new(),
+            transmitting: None,
+            last_transmit_end: 0,
+        }
+    }
+
+    fn bit_clocks(baud_rate: u32, freq_cpu: usize) -> i64 {
+        (freq_cpu as i64 / baud_rate.max(1) as i64).max(1)
+    }
+
+    /// Changes CPU frequency of the machine, which the bit length in clocks
+    /// depends on
+    pub fn set_freq_cpu(&mut self, freq_cpu: usize) {
+        self.bit_clocks = Self::bit_clocks(self.baud_rate, freq_cpu);
+    }
+
+    /// Samples RXD line at the bit times before `clocks`. The line level
+    /// did not change since the last write, so it is the same for all samples
+    fn receive_until(&mut self, clocks: i64) {
+        while let Receiver::Frame {
+            next_sample,
+            bit,
+            data,
+        } = self.receiver
+        {
+            if next_sample >= clocks {
+                break;
+            }
+            let level = self.rx_level;
+            self.receiver = match bit {
+                // Glitch instead of the start bit
+                0 if level => Receiver::Idle,
+                STOP_BIT => {
+                    if level {
+                        self.output.push(data);
+                    } else {
+                        self.framing_errors += 1;
+                        diag!(warn, SERIAL, "framing error, byte {:#04X} dropped", data);
+                    }
+                    Receiver::Idle
+                }
+                _ => Receiver::Frame {
+                    next_sample: next_sample + self.bit_clocks,
+                    bit: bit + 1,
+                    data: if bit == 0 {
+                        0
+                    } else {
+                        (data >> 1) | ((level as u8) << 7)
+                    },
+                },
+            };
+        }
+    }
+
+    /// Finishes bytes sent to the Spectrum before `clocks` and starts the
+    /// next ones while the Spectrum is ready to receive
+    fn transmit_until(&mut self, clocks: i64) {
+        loop {
+            if let Some((start, _)) = self.transmitting {
+                let end = start + FRAME_BITS * self.bit_clocks;
+                if end > clocks {
+                    break;
+                }
+                self.last_transmit_end = end;
+                self.transmitting = None;
+            }
+            // Flow control is checked before the start of each byte
+            let cts_since = match self.cts_since {
+                Some(since) if !self.input.is_empty() => since,
+                _ => break,
+            };
+            let start = cts_since.max(self.last_transmit_end);
+            if start > clocks {
+                break;
+            }
+            self.transmitting = self.input.pop_front().map(|byte| (start, byte));
+        }
+    }
+
+    /// Returns level of the TXD line (data from the device)
+    fn tx_level(&self, clocks: i64) -> bool {
+        match self.transmitting {
+            Some((start, byte)) => match (clocks - start) / self.bit_clocks {
+                0 => false,
+                bit @ 1..=8 => byte & (1 << (bit - 1)) != 0,
+                _ => true,
+            },
+            None => true,
+        }
+    }
+
+    /// Returns bytes received from the Spectrum since the last call
+    pub fn take_output(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.output)
+    }
+
+    /// Queues bytes to be sent to the Spectrum, the queue is sent not earlier
+    /// than at `clocks`
+    pub fn send(&mut self, clocks: usize, data: &[u8]) {
+        if self.transmitting.is_none() && self.input.is_empty() {
+            self.last_transmit_end = self.last_transmit_end.max(clocks as i64);
+        }
+        self.input.extend(data);
+    }
+
+    /// Returns count of queued bytes which were not sent to the Spectrum yet
+    pub fn pending_input(&self) -> usize {
+        self.input.len()
+    }
+
+    /// Returns count of bytes from the Spectrum dropped because of invalid
+    /// stop bit (usually baud rate mismatch)
+    pub fn framing_errors(&self) -> usize {
+        self.framing_errors
+    }
+}
+
+impl PortADevice for Rs232Port {
+    /// Handles write to the AY port A
+    fn write_port_a(&mut self, clocks: usize, value: u8) {
+        let clocks = clocks as i64;
+        self.receive_until(clocks);
+        self.transmit_until(clocks);
+
+        let rx_level = value & PORT_A_RXD != 0;
+        if self.rx_level && !rx_level {
+            if let Receiver::Idle = self.receiver {
+                // Start bit edge, bits are sampled in their middle
+                self.receiver = Receiver::Frame {
+                    next_sample: clocks + self.bit_clocks / 2,
+                    bit: 0,
+                    data: 0,
+                };
+            }
+        }
+        self.rx_level = rx_level;
+
+        let cts = value & PORT_A_CTS == 0;
+        match (cts, self.cts_since) {
+            (true, None) => self.cts_since = Some(clocks),
+            (false, Some(_)) => self.cts_since = None,
+            _ => {}
+        }
+    }
+
+    /// Returns AY port A value read by the Spectrum, with the device lines
+    /// applied
+    fn read_port_a(&mut self, clocks: usize, value: u8) -> u8 {
+        let clocks = clocks as i64;
+        self.transmit_until(clocks);
+        // Device is always ready to receive, DTR is active low
+        let mut value = value & !(PORT_A_DTR | PORT_A_TXD);
+        if self.tx_level(clocks) {
+            value |= PORT_A_TXD;
+        }
+        value
+    }
+
+    /// Finishes the frame of `frame_clocks` length, times are shifted to
+    /// the new frame start
+    fn new_frame(&mut self, frame_clocks: usize) {
+        let frame_clocks = frame_clocks as i64;
+        self.receive_until(frame_clocks);
+        self.transmit_until(frame_clocks);
+        if let Receiver::Frame { next_sample, .. } = &mut self.receiver {
+            *next_sample -= frame_clocks;
+        }
+        if let Some(since) = &mut self.cts_since {
+            *since -= frame_clocks;
+        }
+        if let Some((start, _)) = &mut self.transmitting {
+            *start -= frame_clocks;
+        }
+        self.last_transmit_end -= frame_clocks;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FREQ: usize = 3_500_000;
+
+    fn send_byte(port: &mut Rs232Port, freq: usize, clocks: &mut usize, byte: u8) {
+        let bit_clocks = freq / DEFAULT_BAUD_RATE as usize;
+        let bits = [false]
+            .into_iter()
+            .chain((0..8).map(|bit| byte & (1 << bit) != 0))
+            .chain([true]);
+        for level in bits {
+            let value = if level { 0xFF } else { !PORT_A_RXD };
+            port.write_port_a(*clocks, value);
+            *clocks += bit_clocks;
+        }
+    }
+
+    #[test]
+    fn decodes_bytes_from_spectrum() {
+        let mut port = Rs232Port::new(DEFAULT_BAUD_RATE, FREQ);
+        let mut clocks = 100;
+        for byte in b"Hi\n" {
+            send_byte(&mut port, FREQ, &mut clocks, *byte);
+        }
+        port.new_frame(clocks + 1000);
+        assert_eq!(port.take_output(), b"Hi\n");
+        assert_eq!(port.framing_errors(), 0);
+    }
+
+    #[test]
+    fn bit_length_follows_cpu_frequency() {
+        let mut port = Rs232Port::new(DEFAULT_BAUD_RATE, FREQ);
+        port.set_freq_cpu(2 * FREQ);
+        let mut clocks = 100;
+        for byte in b"Hi\n" {
+            send_byte(&mut port, 2 * FREQ, &mut clocks, *byte);
+        }
+        port.new_frame(clocks + 1000);
+        assert_eq!(port.take_output(), b"Hi\n");
+        assert_eq!(port.framing_errors(), 0);
+    }
+
+    #[test]
+    fn sends_bytes_when_spectrum_is_ready() {
+        let mut port = Rs232Port::new(DEFAULT_BAUD_RATE, FREQ);
+        let bit_clocks = FREQ / DEFAULT_BAUD_RATE as usize;
+        port.send(0, &[0xA5]);
+        // CTS is inactive, line stays idle
+        port.write_port_a(0, 0xFF);
+        assert_eq!(port.read_port_a(1000, 0xFF) & PORT_A_TXD, PORT_A_TXD);
+        port.write_port_a(2000, !PORT_A_CTS);
+        let mut byte = 0;
+        for bit in 0..10 {
+            let level = port.read_port_a(2000 + bit * bit_clocks + bit_clocks / 2, 0xFF);
+            assert_eq!(level & PORT_A_DTR, 0);
+            match bit {
+                0 => assert_eq!(level & PORT_A_TXD, 0),
+                9 => assert_eq!(level & PORT_A_TXD, PORT_A_TXD),
+                _ => byte |= ((level & PORT_A_TXD) >> 7) << (bit - 1),
+            }
+        }
+        assert_eq!(byte, 0xA5);
+        assert_eq!(port.pending_input(), 0);
+    }
+}
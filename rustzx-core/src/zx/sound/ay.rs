@@ -1,8 +1,22 @@
-use crate::zx::sound::sample::{SampleGenerator, SoundSample};
+use crate::zx::sound::{
+    blip::BlipBuffer,
+    sample::{SampleGenerator, SoundSample},
+};
+use alloc::{boxed::Box, vec, vec::Vec};
 use aym::{AyMode, AymBackend, AymPrecise, SoundChip};
 
 /// AY chip runs on the same frequency on 128K, 2+, 3+
 const AY_FREQ: usize = 1773400;
+/// Tone, noise and envelope counters advance on each tick of the internal
+/// clock, which is the chip clock divided by 8
+const AY_TICK_FREQ: f64 = AY_FREQ as f64 / 8.0;
+/// Count of samples averaged by the DC filter, as in ayumi
+const DC_FILTER_SIZE: usize = 1024;
+/// Implemented bits of each register, unused bits of the coarse tone,
+/// noise, volume and envelope shape registers are read back as zeroes
+const REG_MASKS: [u8; 16] = [
+    0xFF, 0x0F, 0xFF, 0x0F, 0xFF, 0x0F, 0x1F, 0xFF, 0x1F, 0x1F, 0x1F, 0xFF, 0xFF, 0x0F, 0xFF, 0xFF,
+];
 
 /// AY output mode
 #[derive(Clone, Copy)]
@@ -13,27 +27,74 @@ pub enum ZXAYMode {
     ACB,
 }
 
+/// Removes DC offset of the output (channel levels are positive-only) by
+/// subtracting the average of the last [DC_FILTER_SIZE] samples
+struct DcFilter {
+    sum: f64,
+    delay: Vec<f64>,
+    index: usize,
+}
+
+impl DcFilter {
+    fn new() -> Self {
+        Self {
+            sum: 0.0,
+            delay: vec![0.0; DC_FILTER_SIZE],
+            index: 0,
+        }
+    }
+
+    fn process(&mut self, value: f64) -> f64 {
+        self.sum += value - self.delay[self.index];
+        self.delay[self.index] = value;
+        self.index = (self.index + 1) % DC_FILTER_SIZE;
+        value - self.sum / DC_FILTER_SIZE as f64
+    }
+}
+
+/// AY chip, level changes of each channel are synthesized band-limited at
+/// the exact tick of the chip, see [BlipBuffer]
 pub(crate) struct ZXAyChip {
-    ay: AymPrecise,
+    // Chip state takes ~23K, kept on the heap to keep `Emulator` small
+    ay: Box<AymPrecise>,
     current_reg: usize,
     regs: [u8; 16],
+    /// Output level of each channel after the last tick
+    levels: [f64; 3],
+    left: BlipBuffer,
+    right: BlipBuffer,
+    dc_left: DcFilter,
+    dc_right: DcFilter,
+    /// Time of the next tick in output samples since the frame start
+    next_tick: f64,
+    /// Length of the tick in output samples
+    tick_length: f64,
+    samples_per_frame: usize,
+    /// Index of the next output sample of the current frame
+    read_pos: usize,
 }
 
 impl ZXAyChip {
-    pub fn new(sample_rate: usize, mode: ZXAYMode) -> ZXAyChip {
+    pub fn new(sample_rate: usize, samples_per_frame: usize, mode: ZXAYMode) -> ZXAyChip {
         let mode = match mode {
             ZXAYMode::Mono => AyMode::Mono,
             ZXAYMode::ABC => AyMode::ABC,
             ZXAYMode::ACB => AyMode::ACB,
         };
 
-        let mut ay = AymPrecise::new(SoundChip::AY, mode, AY_FREQ, sample_rate);
-        ay.enable_dc_filter();
-
         Self {
-            ay,
+            ay: Box::new(AymPrecise::new(SoundChip::AY, mode, AY_FREQ, sample_rate)),
             current_reg: 0,
             regs: [0; 16],
+            levels: [0.0; 3],
+            left: BlipBuffer::new(samples_per_frame),
+            right: BlipBuffer::new(samples_per_frame),
+            dc_left: DcFilter::new(),
+            dc_right: DcFilter::new(),
+            next_tick: 0.0,
+            tick_length: sample_rate as f64 / AY_TICK_FREQ,
+            samples_per_frame,
+            read_pos: 0,
         }
     }
 
@@ -42,20 +103,109 @@ impl ZXAyChip {
         self.current_reg = (reg & 0x0F) as usize;
     }
 
-    pub fn write(&mut self, data: u8) {
+    pub fn selected_reg(&self) -> u8 {
+        self.current_reg as u8
+    }
+
+    /// Writes selected register at `frame_pos` (`0.0..=1.0` fraction of the
+    /// frame)
+    pub fn write(&mut self, data: u8, frame_pos: f64) {
+        self.run_until(frame_pos * self.samples_per_frame as f64);
         let reg = self.current_reg;
-        self.regs[reg] = data;
+        self.regs[reg] = data & REG_MASKS[reg];
         self.ay.write_register(reg as u8, data);
     }
 
     pub fn read(&self) -> u8 {
         self.regs[self.current_reg]
     }
+
+    /// Clears all registers at `frame_pos`, as the RESET line of the chip
+    /// does
+    pub fn reset(&mut self, frame_pos: f64) {
+        for reg in 0..self.regs.len() as u8 {
+            self.select_reg(reg);
+            self.write(0, frame_pos);
+        }
+        self.current_reg = 0;
+    }
+
+    /// Finishes the frame, next frame is `samples_per_frame` samples long
+    pub fn new_frame(&mut self, samples_per_frame: usize) {
+        self.run_until(self.samples_per_frame as f64);
+        self.next_tick -= self.samples_per_frame as f64;
+        self.left.end_frame(samples_per_frame);
+        self.right.end_frame(samples_per_frame);
+        self.samples_per_frame = samples_per_frame;
+        self.read_pos = 0;
+    }
+
+    /// Runs the chip up to `time` in output samples since the frame start,
+    /// posting level changes of the channels
+    fn run_until(&mut self, time: f64) {
+        while self.next_tick < time {
+            let levels = self.ay.tick();
+            for (index, (level, last)) in levels.iter().zip(&mut self.levels).enumerate() {
+                let delta = level - *last;
+                if delta != 0.0 {
+                    let (pan_left, pan_right) = self.ay.channel_pan(index);
+                    self.left.add_delta(self.next_tick, delta * pan_left);
+                    self.right.add_delta(self.next_tick, delta * pan_right);
+                    *last = *level;
+                }
+            }
+            self.next_tick += self.tick_length;
+        }
+    }
 }
 
 impl SampleGenerator<f64> for ZXAyChip {
     fn gen_sample(&mut self) -> SoundSample<f64> {
-        let sample = self.ay.next_sample();
-        SoundSample::new(sample.left, sample.right)
+        // Changes during the sample affect only the samples after it
+        self.read_pos += 1;
+        self.run_until(self.read_pos as f64);
+        let left = self.dc_left.process(self.left.read_sample());
+        let right = self.dc_right.process(self.right.read_sample());
+        SoundSample::new(left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unused_register_bits_read_as_zero() {
+        let mut ay = ZXAyChip::new(44100, 882, ZXAYMode::Mono);
+        for reg in 0..16 {
+            ay.select_reg(reg);
+            ay.write(0xFF, 0.0);
+            assert_eq!(ay.read(), REG_MASKS[reg as usize], "register {}", reg);
+        }
+    }
+
+    #[test]
+    fn reset_clears_registers() {
+        let mut ay = ZXAyChip::new(44100, 882, ZXAYMode::Mono);
+        for reg in 0..16 {
+            ay.select_reg(reg);
+            ay.write(0xFF, 0.0);
+        }
+        ay.reset(0.5);
+        assert_eq!(ay.selected_reg(), 0);
+        assert_eq!(ay.regs, [0; 16]);
+    }
+
+    #[test]
+    fn level_changes_at_write_position() {
+        let mut ay = ZXAyChip::new(44100, 882, ZXAYMode::Mono);
+        // Tone and noise disabled, channel A outputs its volume level
+        ay.select_reg(7);
+        ay.write(0x3F, 0.0);
+        ay.select_reg(8);
+        ay.write(0x0F, 0.5);
+        let samples = (0..882).map(|_| ay.gen_sample().left).collect::<Vec<_>>();
+        assert!(samples[..441].iter().all(|sample| *sample == 0.0));
+        assert!(samples[460] > 0.3, "{}", samples[460]);
     }
 }
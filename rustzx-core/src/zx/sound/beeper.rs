@@ -1,25 +1,47 @@
-use crate::zx::sound::sample::{SampleGenerator, SoundSample};
+use crate::zx::sound::{
+    blip::BlipBuffer,
+    sample::{SampleGenerator, SoundSample},
+};
 
-/// Simple beeper implementation
-#[derive(Default)]
+/// Beeper, level changes are synthesized band-limited, see [BlipBuffer]
 pub(crate) struct ZXBeeper {
     mic: bool,
     ear: bool,
+    blip: BlipBuffer,
+    samples_per_frame: usize,
 }
 
 impl ZXBeeper {
-    /// Changes next beeper bit
-    pub fn change_state(&mut self, ear: bool, mic: bool) {
+    pub fn new(samples_per_frame: usize) -> Self {
+        Self {
+            mic: false,
+            ear: false,
+            blip: BlipBuffer::new(samples_per_frame),
+            samples_per_frame,
+        }
+    }
+
+    /// Changes beeper bits at `frame_pos` (`0.0..=1.0` fraction of the frame)
+    pub fn change_state(&mut self, ear: bool, mic: bool, frame_pos: f64) {
+        let delta = Self::level(ear, mic) - Self::level(self.ear, self.mic);
         self.ear = ear;
         self.mic = mic;
+        if delta != 0.0 {
+            self.blip
+                .add_delta(frame_pos * self.samples_per_frame as f64, delta);
+        }
     }
-}
 
-impl SampleGenerator<f64> for ZXBeeper {
-    fn gen_sample(&mut self) -> SoundSample<f64> {
+    /// Finishes the frame, next frame is `samples_per_frame` samples long
+    pub fn new_frame(&mut self, samples_per_frame: usize) {
+        self.samples_per_frame = samples_per_frame;
+        self.blip.end_frame(samples_per_frame);
+    }
+
+    fn level(ear: bool, mic: bool) -> f64 {
         // - Beeper intentionally made produce only positive half-wave 0..0.5
-        // range instead of -0.25..0.25) because of current emulator lack of
-        // dc filtering.
+        // range instead of -0.25..0.25) because DC filtering is optional
+        // (see `filter` module) and disabled by default.
         // - Beeper only produces a quarter of available sample
         // range because relatively to AY chip, square wave of a beeper is
         // too loud
@@ -28,13 +50,19 @@ impl SampleGenerator<f64> for ZXBeeper {
         const MIC_SAMPLE_FACTOR: f64 = EAR_SAMPLE_FACTOR / 5.0;
 
         let mut sample = 0.0;
-        if self.ear {
+        if ear {
             sample += EAR_SAMPLE_FACTOR;
         }
-        if self.mic {
+        if mic {
             sample += MIC_SAMPLE_FACTOR;
         }
+        sample
+    }
+}
 
+impl SampleGenerator<f64> for ZXBeeper {
+    fn gen_sample(&mut self) -> SoundSample<f64> {
+        let sample = self.blip.read_sample();
         SoundSample::new(sample, sample)
     }
 }
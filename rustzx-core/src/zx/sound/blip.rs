@@ -0,0 +1,236 @@
+//! Band-limited synthesis of square waves (blip buffer). Sources post level
+//! changes at the exact time within the frame, each change is added to the
+//! buffer as a band-limited impulse (windowed sinc) and output samples are
+//! the running sum of the impulses. Unlike sampling of the current level at
+//! the host sample rate, this does not alias high-pitched tones down to the
+//! audible range. Output is delayed by half of the kernel (8 samples), so
+//! the impulse of a change never lands on the already read samples
+use alloc::{vec, vec::Vec};
+use core::f64::consts::PI;
+
+/// Count of output samples affected by a single level change
+const KERNEL_SIZE: usize = 16;
+/// Count of precomputed sub-sample positions of the change, kernels for the
+/// positions in between are interpolated
+const PHASES: usize = 64;
+/// Cutoff of the kernel relative to the Nyquist frequency, the rest is the
+/// transition band of the window
+const CUTOFF: f64 = 0.85;
+
+pub(crate) struct BlipBuffer {
+    /// Impulse kernels for each phase and for the next sample start, taps of
+    /// each kernel sum up to 1
+    kernels: Vec<[f64; KERNEL_SIZE]>,
+    /// Impulses of the current frame, indexed by output sample. Impulses of
+    /// the changes at the frame end spill over to the next frame
+    deltas: Vec<f64>,
+    /// Length of the current frame in output samples
+    samples_per_frame: usize,
+    /// Index of the next output sample of the current frame
+    read_pos: usize,
+    /// Running sum of the read impulses
+    level: f64,
+    /// Sum of all deltas, which the level settles at
+    settled_level: f64,
+}
+
+impl BlipBuffer {
+    /// Creates buffer for frames of `samples_per_frame` samples, frames may
+    /// be one sample longer when the sample rate is not a multiple of the
+    /// frame rate, see [BlipBuffer::end_frame]
+    pub fn new(samples_per_frame: usize) -> Self {
+        let kernels = (0..=PHASES)
+            .map(|phase| kernel(phase as f64 / PHASES as f64))
+            .collect();
+        Self {
+            kernels,
+            deltas: vec![0.0; samples_per_frame + KERNEL_SIZE + 2],
+            samples_per_frame,
+            read_pos: 0,
+            level: 0.0,
+            settled_level: 0.0,
+        }
+    }
+
+    /// Adds level change by `delta` at `time`, measured in output samples
+    /// since the frame start (`0.0..=samples_per_frame`)
+    pub fn add_delta(&mut self, time: f64, delta: f64) {
+        self.settled_level += delta;
+        let time = time.clamp(0.0, self.samples_per_frame as f64);
+        let sample = time as usize;
+        let position = (time - sample as f64) * PHASES as f64;
+        let phase = (position as usize).min(PHASES - 1);
+        let next_weight = position - phase as f64;
+        let (kernel, next) = (&self.kernels[phase], &self.kernels[phase + 1]);
+        let taps = &mut self.deltas[sample + 1..sample + 1 + KERNEL_SIZE];
+        for (index, tap) in taps.iter_mut().enumerate() {
+            *tap += delta * (kernel[index] + (next[index] - kernel[index]) * next_weight);
+        }
+    }
+
+    /// Returns next output sample of the current frame, samples after the
+    /// frame end repeat the last level
+    pub fn read_sample(&mut self) -> f64 {
+        if self.read_pos < self.samples_per_frame {
+            self.level += core::mem::take(&mut self.deltas[self.read_pos]);
+            self.read_pos += 1;
+        }
+        self.level
+    }
+
+    /// Finishes the frame. Samples which were not read are skipped, impulses
+    /// spilled over the frame end are moved to the start of the next frame,
+    /// which is `next_samples_per_frame` samples long
+    pub fn end_frame(&mut self, next_samples_per_frame: usize) {
+        debug_assert!(next_samples_per_frame + KERNEL_SIZE < self.deltas.len());
+        while self.read_pos < self.samples_per_frame {
+            self.read_sample();
+        }
+        self.deltas.copy_within(self.samples_per_frame.., 0);
+        let tail = self.deltas.len() - self.samples_per_frame;
+        self.deltas[tail..].fill(0.0);
+        self.read_pos = 0;
+        self.samples_per_frame = next_samples_per_frame;
+        // Rounding errors of the impulses would accumulate otherwise, so the
+        // same sound would not produce the same samples in each frame
+        if self.deltas[..=KERNEL_SIZE]
+            .iter()
+            .all(|delta| *delta == 0.0)
+        {
+            self.level = self.settled_level;
+        }
+    }
+}
+
+/// Returns Blackman-windowed sinc kernel for the change at `fraction` of the
+/// sample after the last sample before the change. Kernel is centered half
+/// of its size after the change
+fn kernel(fraction: f64) -> [f64; KERNEL_SIZE] {
+    let half = KERNEL_SIZE as f64 / 2.0;
+    let mut taps = [0.0; KERNEL_SIZE];
+    for (index, tap) in taps.iter_mut().enumerate() {
+        // Distance from the kernel center, first tap is the sample after
+        // the change
+        let x = index as f64 + 1.0 - fraction - half;
+        let sinc = if x == 0.0 {
+            CUTOFF
+        } else {
+            libm::sin(PI * CUTOFF * x) / (PI * x)
+        };
+        let w = PI * x / (half + 1.0);
+        let window = 0.42 + 0.5 * libm::cos(w) + 0.08 * libm::cos(2.0 * w);
+        *tap = sinc * window;
+    }
+    // Normalize, so the level settles exactly at the sum of the deltas
+    let sum: f64 = taps.iter().sum();
+    taps.iter_mut().for_each(|tap| *tap /= sum);
+    taps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 44100.0;
+    const SAMPLES_PER_FRAME: usize = 882;
+    const FRAMES: usize = 5;
+    /// 7 kHz tone, its harmonics from the 5th one are above the Nyquist
+    /// frequency and alias when sampled directly
+    const TONE: f64 = 7000.0;
+
+    /// Square wave of `TONE` frequency, level changes are returned in
+    /// output samples since the first frame start
+    fn square_wave_changes() -> impl Iterator<Item = (f64, f64)> {
+        let half_period = SAMPLE_RATE / TONE / 2.0;
+        (1..)
+            .map(move |n| (n as f64 * half_period, if n % 2 == 1 { 1.0 } else { -1.0 }))
+            .take_while(|(time, _)| *time < (SAMPLES_PER_FRAME * FRAMES) as f64)
+    }
+
+    fn blip_samples() -> Vec<f64> {
+        let mut blip = BlipBuffer::new(SAMPLES_PER_FRAME);
+        let mut changes = square_wave_changes().peekable();
+        let mut samples = vec![];
+        for frame in 0..FRAMES {
+            let frame_start = (frame * SAMPLES_PER_FRAME) as f64;
+            while let Some((time, delta)) =
+                changes.next_if(|(time, _)| *time < frame_start + SAMPLES_PER_FRAME as f64)
+            {
+                blip.add_delta(time - frame_start, delta);
+            }
+            samples.extend((0..SAMPLES_PER_FRAME).map(|_| blip.read_sample()));
+            blip.end_frame(SAMPLES_PER_FRAME);
+        }
+        samples
+    }
+
+    /// Level of the square wave at each sample time
+    fn point_samples() -> Vec<f64> {
+        let half_period = SAMPLE_RATE / TONE / 2.0;
+        (0..SAMPLES_PER_FRAME * FRAMES)
+            .map(|n| ((n as f64 / half_period) as usize % 2) as f64)
+            .collect()
+    }
+
+    /// Power of `frequency` in the samples
+    fn power(samples: &[f64], frequency: f64) -> f64 {
+        let w = 2.0 * PI * frequency / SAMPLE_RATE;
+        let (re, im) = samples
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(re, im), (n, sample)| {
+                let phase = w * n as f64;
+                (
+                    re + sample * libm::cos(phase),
+                    im + sample * libm::sin(phase),
+                )
+            });
+        re * re + im * im
+    }
+
+    /// Ratio of the strongest aliased harmonic to the fundamental in dB
+    fn alias_floor(samples: &[f64]) -> f64 {
+        let fundamental = power(samples, TONE);
+        let strongest_alias = (5..40)
+            .step_by(2)
+            .map(|harmonic| {
+                let frequency = (harmonic as f64 * TONE) % SAMPLE_RATE;
+                frequency.min(SAMPLE_RATE - frequency)
+            })
+            .map(|frequency| power(samples, frequency))
+            .fold(0.0, f64::max);
+        10.0 * libm::log10(strongest_alias / fundamental)
+    }
+
+    #[test]
+    fn kernels_are_normalized() {
+        let blip = BlipBuffer::new(SAMPLES_PER_FRAME);
+        for kernel in &blip.kernels {
+            assert!((kernel.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn level_settles_across_frames() {
+        let mut blip = BlipBuffer::new(SAMPLES_PER_FRAME);
+        // Change at the very end of the frame spills over to the next one
+        blip.add_delta(SAMPLES_PER_FRAME as f64, 0.5);
+        blip.end_frame(SAMPLES_PER_FRAME);
+        let samples = (0..SAMPLES_PER_FRAME)
+            .map(|_| blip.read_sample())
+            .collect::<Vec<_>>();
+        assert_eq!(samples[0], 0.0);
+        assert!((samples[KERNEL_SIZE] - 0.5).abs() < 1e-12);
+        assert!((samples[SAMPLES_PER_FRAME - 1] - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn band_limited_square_wave_does_not_alias() {
+        // First frame is skipped, so the analyzed samples contain whole
+        // periods of the tone without the start transient
+        let point = alias_floor(&point_samples()[SAMPLES_PER_FRAME..]);
+        let blip = alias_floor(&blip_samples()[SAMPLES_PER_FRAME..]);
+        assert!(point > -20.0, "{}", point);
+        assert!(blip < -60.0, "{}", blip);
+    }
+}
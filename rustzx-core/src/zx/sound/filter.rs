@@ -0,0 +1,160 @@
+//! Output filters of the mixer. Both filters run over the mixed output at the
+//! host sample rate and can be bypassed separately:
+//! - DC blocker is a high-pass filter with a very low cutoff. Speaker can't
+//!   hold DC, so without it beeper output (positive-only) and long MIC bit
+//!   manipulation shift the output level
+//! - Low-pass filter is a second order Butterworth biquad, which smooths
+//!   square edges of the beeper output the way TV speaker does
+use crate::zx::sound::sample::SoundSample;
+use core::f64::consts::{PI, SQRT_2};
+
+/// Cutoff frequency of the low-pass filter which approximates TV speaker
+pub const TV_SPEAKER_CUTOFF: u32 = 5000;
+/// Cutoff frequency of the DC blocker, well below the audible range
+const DC_BLOCKER_CUTOFF: f64 = 20.0;
+/// Low-pass cutoff is limited to this fraction of the sample rate, filter
+/// would be unstable at and above the Nyquist frequency
+const MAX_CUTOFF_FRACTION: f64 = 0.45;
+
+/// Configuration of the output filters, all filters are bypassed by default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SoundFilters {
+    /// Enables DC-blocking high-pass filter
+    pub dc_blocker: bool,
+    /// Cutoff frequency of the low-pass filter in Hz, `None` bypasses it
+    pub low_pass_cutoff: Option<u32>,
+}
+
+impl SoundFilters {
+    /// All filters bypassed, raw mixer output
+    pub const NONE: Self = Self {
+        dc_blocker: false,
+        low_pass_cutoff: None,
+    };
+    /// DC blocker and low-pass filter approximating TV speaker response
+    pub const TV_SPEAKER: Self = Self {
+        dc_blocker: true,
+        low_pass_cutoff: Some(TV_SPEAKER_CUTOFF),
+    };
+}
+
+/// State of the filter of a single channel
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct ChannelState {
+    /// Previous input and output of the DC blocker
+    dc_x1: f64,
+    dc_y1: f64,
+    /// Two previous inputs and outputs of the low-pass filter
+    lp_x: [f64; 2],
+    lp_y: [f64; 2],
+}
+
+impl ChannelState {
+    fn process(&mut self, mut value: f64, dc_pole: Option<f64>, low_pass: Option<&Biquad>) -> f64 {
+        if let Some(pole) = dc_pole {
+            let output = value - self.dc_x1 + pole * self.dc_y1;
+            self.dc_x1 = value;
+            self.dc_y1 = output;
+            value = output;
+        }
+        if let Some(f) = low_pass {
+            let output = f.b0 * value + f.b1 * self.lp_x[0] + f.b2 * self.lp_x[1]
+                - f.a1 * self.lp_y[0]
+                - f.a2 * self.lp_y[1];
+            self.lp_x = [value, self.lp_x[0]];
+            self.lp_y = [output, self.lp_y[0]];
+            value = output;
+        }
+        value
+    }
+}
+
+/// Filter history, see [crate::Emulator::sound_filter_state]. Contents are
+/// opaque, the state is only meant to be restored into the emulator with the
+/// same sample rate
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SoundFilterState {
+    left: ChannelState,
+    right: ChannelState,
+}
+
+/// Normalized coefficients of the biquad filter (`a0` is 1)
+#[derive(Clone, Copy)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+impl Biquad {
+    /// Butterworth low-pass filter, from Audio EQ Cookbook by R. Bristow-Johnson
+    fn low_pass(cutoff: f64, sample_rate: f64) -> Self {
+        let cutoff = cutoff.min(sample_rate * MAX_CUTOFF_FRACTION);
+        let w0 = 2.0 * PI * cutoff / sample_rate;
+        let cos_w0 = libm::cos(w0);
+        // Q = 1/sqrt(2)
+        let alpha = libm::sin(w0) / SQRT_2;
+        let a0 = 1.0 + alpha;
+        Self {
+            b0: (1.0 - cos_w0) / 2.0 / a0,
+            b1: (1.0 - cos_w0) / a0,
+            b2: (1.0 - cos_w0) / 2.0 / a0,
+            a1: -2.0 * cos_w0 / a0,
+            a2: (1.0 - alpha) / a0,
+        }
+    }
+}
+
+pub(crate) struct FilterChain {
+    config: SoundFilters,
+    sample_rate: f64,
+    /// Pole of the DC blocker
+    dc_pole: f64,
+    low_pass: Option<Biquad>,
+    state: SoundFilterState,
+}
+
+impl FilterChain {
+    pub fn new(config: SoundFilters, sample_rate: usize) -> Self {
+        let sample_rate = sample_rate as f64;
+        let mut chain = Self {
+            config: SoundFilters::NONE,
+            sample_rate,
+            dc_pole: libm::exp(-2.0 * PI * DC_BLOCKER_CUTOFF / sample_rate),
+            low_pass: None,
+            state: Default::default(),
+        };
+        chain.configure(config);
+        chain
+    }
+
+    /// Changes filters configuration, history of the filters is kept so
+    /// the output does not click
+    pub fn configure(&mut self, config: SoundFilters) {
+        self.config = config;
+        self.low_pass = config
+            .low_pass_cutoff
+            .map(|cutoff| Biquad::low_pass(cutoff as f64, self.sample_rate));
+    }
+
+    pub fn config(&self) -> SoundFilters {
+        self.config
+    }
+
+    pub fn state(&self) -> SoundFilterState {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: SoundFilterState) {
+        self.state = state;
+    }
+
+    pub fn process(&mut self, sample: &mut SoundSample<f64>) {
+        let dc_pole = self.config.dc_blocker.then_some(self.dc_pole);
+        let low_pass = self.low_pass.as_ref();
+        sample.left = self.state.left.process(sample.left, dc_pole, low_pass);
+        sample.right = self.state.right.process(sample.right, dc_pole, low_pass);
+    }
+}
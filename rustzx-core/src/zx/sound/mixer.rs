@@ -1,19 +1,20 @@
 //! Module implements zx spectrum audio devices mixer
-use crate::zx::{
-    constants::FPS,
-    sound::{
-        beeper::ZXBeeper,
-        sample::{SampleGenerator, SoundSample},
-    },
+use crate::zx::sound::{
+    beeper::ZXBeeper,
+    filter::{FilterChain, SoundFilterState, SoundFilters},
+    sample::{SampleGenerator, SoundSample},
 };
 
-// TODO(#117): Implement DC filtering for sound mixing
-
 #[cfg(feature = "ay")]
 use crate::zx::sound::ay::{ZXAYMode, ZXAyChip};
 
 use alloc::collections::VecDeque;
 
+/// Maximal peak-to-peak amplitude of the frame which is still considered as
+/// silence. Peak-to-peak is used instead of the absolute level to ignore DC
+/// offset (e.g. beeper output is positive-only)
+const SILENCE_THRESHOLD: f32 = 1.0 / 256.0;
+
 /// Main sound mixer.
 pub(crate) struct ZXMixer {
     /// direct access to beeper device
@@ -21,6 +22,9 @@ pub(crate) struct ZXMixer {
     /// direct access to AY device
     #[cfg(feature = "ay")]
     pub ay: ZXAyChip,
+    /// AY of the Fuller Box, mixed when present
+    #[cfg(feature = "ay")]
+    pub fuller_ay: Option<ZXAyChip>,
     ring_buffer: VecDeque<SoundSample<f32>>,
     last_pos: usize,
     last_sample: SoundSample<f32>,
@@ -28,7 +32,23 @@ pub(crate) struct ZXMixer {
     #[cfg(feature = "ay")]
     use_ay: bool,
     use_beeper: bool,
+    invert_beeper: bool,
+    #[cfg(feature = "ay")]
+    invert_ay: bool,
+    filters: FilterChain,
     sample_rate: usize,
+    frames_per_second: usize,
+    /// Sample count of the current frame. Sample rate is usually not a
+    /// multiple of the frame rate, so the count alternates between two
+    /// values and the fractional part is carried to the next frames
+    frame_samples: usize,
+    /// Fractional part of the sample count carried over, in units of
+    /// `1 / frames_per_second` sample
+    samples_remainder: usize,
+    /// Minimal and maximal sample values of the current frame
+    frame_min: SoundSample<f32>,
+    frame_max: SoundSample<f32>,
+    last_frame_silent: bool,
 }
 
 impl ZXMixer {
@@ -36,16 +56,22 @@ impl ZXMixer {
     /// # Arguments
     /// - `use_beeper` - process beeper or not
     /// - `use_ay` - process ay chip or not
+    /// - `filters` - output filters configuration
+    /// - `frames_per_second` - emulated machine frame rate
     pub fn new(
         use_beeper: bool,
         #[cfg(feature = "ay")] use_ay: bool,
         #[cfg(feature = "ay")] ay_mode: ZXAYMode,
+        filters: SoundFilters,
         sample_rate: usize,
+        frames_per_second: usize,
     ) -> ZXMixer {
         ZXMixer {
-            beeper: ZXBeeper::default(),
+            beeper: ZXBeeper::new(sample_rate / frames_per_second),
+            #[cfg(feature = "ay")]
+            ay: ZXAyChip::new(sample_rate, sample_rate / frames_per_second, ay_mode),
             #[cfg(feature = "ay")]
-            ay: ZXAyChip::new(sample_rate, ay_mode),
+            fuller_ay: None,
             ring_buffer: VecDeque::with_capacity(sample_rate),
             last_pos: 0,
             last_sample: SoundSample::new(0.0, 0.0),
@@ -53,7 +79,17 @@ impl ZXMixer {
             #[cfg(feature = "ay")]
             use_ay,
             use_beeper,
+            invert_beeper: false,
+            #[cfg(feature = "ay")]
+            invert_ay: false,
+            filters: FilterChain::new(filters, sample_rate),
             sample_rate,
+            frames_per_second,
+            frame_samples: sample_rate / frames_per_second,
+            samples_remainder: sample_rate % frames_per_second,
+            frame_min: SoundSample::new(f32::MAX, f32::MAX),
+            frame_max: SoundSample::new(f32::MIN, f32::MIN),
+            last_frame_silent: true,
         }
     }
 
@@ -64,6 +100,48 @@ impl ZXMixer {
         self.master_volume = volume;
     }
 
+    /// Inverts beeper output before mixing when `invert` is true
+    pub fn set_beeper_polarity(&mut self, invert: bool) {
+        self.invert_beeper = invert;
+    }
+
+    /// Inverts AY output before mixing when `invert` is true
+    #[cfg(feature = "ay")]
+    pub fn set_ay_polarity(&mut self, invert: bool) {
+        self.invert_ay = invert;
+    }
+
+    /// Copies output polarity of all sources from `other` mixer
+    pub fn copy_polarity(&mut self, other: &ZXMixer) {
+        self.invert_beeper = other.invert_beeper;
+        #[cfg(feature = "ay")]
+        {
+            self.invert_ay = other.invert_ay;
+        }
+    }
+
+    pub fn set_filters(&mut self, filters: SoundFilters) {
+        self.filters.configure(filters);
+    }
+
+    pub fn filters(&self) -> SoundFilters {
+        self.filters.config()
+    }
+
+    pub fn filter_state(&self) -> SoundFilterState {
+        self.filters.state()
+    }
+
+    pub fn set_filter_state(&mut self, state: SoundFilterState) {
+        self.filters.set_state(state);
+    }
+
+    /// Copies output filters configuration and history from `other` mixer
+    pub fn copy_filters(&mut self, other: &ZXMixer) {
+        self.filters.configure(other.filters.config());
+        self.filters.set_state(other.filters.state());
+    }
+
     /// Updates internal buffer of mixer and fills it with new samples
     pub fn process(&mut self, current_time: f64) {
         // buffer overflow
@@ -91,8 +169,39 @@ impl ZXMixer {
             for _ in self.ring_buffer.len()..self.samples_per_frame() {
                 self.ring_buffer.push_back(self.last_sample);
             }
+            self.track_amplitude(self.last_sample);
+        }
+        let samples = self.sample_rate + self.samples_remainder;
+        self.frame_samples = samples / self.frames_per_second;
+        self.samples_remainder = samples % self.frames_per_second;
+        self.beeper.new_frame(self.frame_samples);
+        #[cfg(feature = "ay")]
+        {
+            self.ay.new_frame(self.frame_samples);
+            if let Some(ay) = &mut self.fuller_ay {
+                ay.new_frame(self.frame_samples);
+            }
         }
         self.last_pos = 0;
+        // frame without generated samples is silent too
+        self.last_frame_silent = self.frame_max.left - self.frame_min.left <= SILENCE_THRESHOLD
+            && self.frame_max.right - self.frame_min.right <= SILENCE_THRESHOLD
+            || self.frame_max.left < self.frame_min.left;
+        self.frame_min = SoundSample::new(f32::MAX, f32::MAX);
+        self.frame_max = SoundSample::new(f32::MIN, f32::MIN);
+    }
+
+    /// Returns true if peak-to-peak amplitude of the samples generated during
+    /// the last frame is below the silence threshold
+    pub fn was_silent_last_frame(&self) -> bool {
+        self.last_frame_silent
+    }
+
+    fn track_amplitude(&mut self, sample: SoundSample<f32>) {
+        self.frame_min.left = self.frame_min.left.min(sample.left);
+        self.frame_min.right = self.frame_min.right.min(sample.right);
+        self.frame_max.left = self.frame_max.left.max(sample.left);
+        self.frame_max.right = self.frame_max.right.max(sample.right);
     }
 
     pub fn pop(&mut self) -> Option<SoundSample<f32>> {
@@ -101,21 +210,40 @@ impl ZXMixer {
 
     fn gen_sample(&mut self) -> SoundSample<f32> {
         let mut master_float = if self.use_beeper {
-            self.beeper.gen_sample()
+            let mut sample = self.beeper.gen_sample();
+            if self.invert_beeper {
+                sample.mul_eq(-1.0);
+            }
+            sample
         } else {
             SoundSample::new(0.0, 0.0)
         };
         #[cfg(feature = "ay")]
         if self.use_ay {
-            master_float.mix(&self.ay.gen_sample());
+            let mut sample = self.ay.gen_sample();
+            if self.invert_ay {
+                sample.mul_eq(-1.0);
+            }
+            master_float.mix(&sample);
+        }
+        #[cfg(feature = "ay")]
+        if let Some(ay) = &mut self.fuller_ay {
+            let mut sample = ay.gen_sample();
+            if self.invert_ay {
+                sample.mul_eq(-1.0);
+            }
+            master_float.mix(&sample);
         }
+        self.filters.process(&mut master_float);
         let master = master_float.mul_eq(self.master_volume).into_f32();
         self.last_sample = master;
+        self.track_amplitude(master);
         master
     }
 
-    fn samples_per_frame(&self) -> usize {
-        self.sample_rate / FPS
+    /// Returns count of samples of the current frame
+    pub fn samples_per_frame(&self) -> usize {
+        self.frame_samples
     }
 
     fn sample_count_for_frame_fraction(&self, fraction: f64) -> usize {
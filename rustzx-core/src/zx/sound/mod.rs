@@ -1,7 +1,9 @@
 //! Module implements emulation of sound chip AY, Spectrum Beeper and Mixer
 #[cfg(feature = "ay")]
 pub mod ay;
+pub mod filter;
 pub mod sample;
 
 pub(crate) mod beeper;
+pub(crate) mod blip;
 pub(crate) mod mixer;
@@ -0,0 +1,305 @@
+//! Spectranet network interface. When paged in, Spectranet memory replaces the
+//! lower 16K of the address space:
+//! - `0x0000..0x1000` - fixed page 0x00 (first flash ROM page)
+//! - `0x1000..0x2000` - page A, selected via port 0x003B
+//! - `0x2000..0x3000` - page B, selected via port 0x013B
+//! - `0x3000..0x4000` - fixed page 0xC0 (first RAM page)
+//!
+//! Page numbers `0x00..0x20` select flash ROM, `0x40..0x48` select W5100 chip
+//! address space and `0xC0..0xE0` select static RAM.
+//!
+//! Spectranet is paged in by the instruction fetch from 0x0008 (RST 8) or
+//! 0x3FF8..=0x3FFF, and paged out by fetch from 0x007C. Programmable trap
+//! (address is written to port 0x023B low byte first) causes NMI with
+//! Spectranet paged in. Control register at port 0x033B: bit 0 - paged in
+//! state, bit 3 - programmable trap enable. Flash ROM is read-only
+pub(crate) mod w5100;
+
+use crate::{diagnostics::NETWORK, host::NetworkStack};
+use alloc::{vec, vec::Vec};
+use w5100::{ADDRESS_SPACE_SIZE as W5100_ADDRESS_SPACE_SIZE, W5100};
+
+const PAGE_SIZE: usize = 0x1000;
+pub(crate) const FLASH_SIZE: usize = 0x20000;
+const RAM_SIZE: usize = 0x20000;
+
+const PAGE_FLASH_FIRST: u8 = 0x00;
+const PAGE_W5100_FIRST: u8 = 0x40;
+const PAGE_RAM_FIRST: u8 = 0xC0;
+
+const PORT_PAGE_A: u16 = 0x003B;
+const PORT_PAGE_B: u16 = 0x013B;
+const PORT_TRAP_ADDR: u16 = 0x023B;
+const PORT_CONTROL: u16 = 0x033B;
+
+const CONTROL_PAGED_IN: u8 = 0x01;
+const CONTROL_TRAP_ENABLED: u8 = 0x08;
+
+const ADDR_RST8: u16 = 0x0008;
+const ADDR_PAGE_OUT: u16 = 0x007C;
+const ADDR_PAGE_IN_TRAP_START: u16 = 0x3FF8;
+const ADDR_END: u16 = 0x4000;
+
+pub(crate) struct Spectranet<N: NetworkStack> {
+    flash: Vec<u8>,
+    ram: Vec<u8>,
+    w5100: W5100<N>,
+    page_a: u8,
+    page_b: u8,
+    paged_in: bool,
+    control: u8,
+    trap_addr: u16,
+    trap_addr_high_next: bool,
+    nmi_pending: bool,
+}
+
+impl<N: NetworkStack> Spectranet<N> {
+    /// Creates Spectranet with the given flash ROM image (up to 128K) and
+    /// host network access. Spectranet is paged in as after reset
+    pub fn new(rom: &[u8], network: N) -> Self {
+        let mut flash = vec![0xFF; FLASH_SIZE];
+        let size = rom.len().min(FLASH_SIZE);
+        flash[..size].copy_from_slice(&rom[..size]);
+        Self {
+            flash,
+            ram: vec![0; RAM_SIZE],
+            w5100: W5100::new(network),
+            page_a: PAGE_FLASH_FIRST,
+            page_b: PAGE_FLASH_FIRST,
+            paged_in: true,
+            control: 0,
+            trap_addr: 0,
+            trap_addr_high_next: false,
+            nmi_pending: false,
+        }
+    }
+
+    pub fn into_network(self) -> N {
+        self.w5100.into_network()
+    }
+
+    pub fn paged_in(&self) -> bool {
+        self.paged_in
+    }
+
+    pub fn handles_port(port: u16) -> bool {
+        matches!(
+            port,
+            PORT_PAGE_A | PORT_PAGE_B | PORT_TRAP_ADDR | PORT_CONTROL
+        )
+    }
+
+    fn set_paged_in(&mut self, value: bool) {
+        if self.paged_in != value {
+            diag!(
+                trace,
+                NETWORK,
+                "Spectranet paged {}",
+                if value { "in" } else { "out" }
+            );
+        }
+        self.paged_in = value;
+    }
+
+    /// Processes instruction fetch address, should be called before each
+    /// instruction fetch
+    pub fn check_fetch(&mut self, addr: u16) {
+        if self.paged_in {
+            if addr == ADDR_PAGE_OUT {
+                self.set_paged_in(false);
+            }
+            return;
+        }
+        if addr == ADDR_RST8 || (ADDR_PAGE_IN_TRAP_START..ADDR_END).contains(&addr) {
+            self.set_paged_in(true);
+        } else if self.control & CONTROL_TRAP_ENABLED != 0 && addr == self.trap_addr {
+            diag!(debug, NETWORK, "programmable trap at {:#06X}", addr);
+            self.set_paged_in(true);
+            self.nmi_pending = true;
+        }
+    }
+
+    /// Restores paging and trap state after the machine reset, Spectranet
+    /// is paged in to run its ROM initialization
+    pub fn reset(&mut self) {
+        self.page_a = PAGE_FLASH_FIRST;
+        self.page_b = PAGE_FLASH_FIRST;
+        self.set_paged_in(true);
+        self.control = 0;
+        self.trap_addr = 0;
+        self.trap_addr_high_next = false;
+        self.nmi_pending = false;
+    }
+
+    /// Returns true after the programmable trap was triggered, until the NMI
+    /// is acknowledged via [Spectranet::acknowledge_nmi]
+    pub fn nmi_pending(&self) -> bool {
+        self.nmi_pending
+    }
+
+    pub fn acknowledge_nmi(&mut self) {
+        self.nmi_pending = false;
+    }
+
+    /// Returns page number and offset in the page for the address in
+    /// the lower 16K
+    fn map(&self, addr: u16) -> (u8, usize) {
+        let page = match addr as usize / PAGE_SIZE {
+            0 => PAGE_FLASH_FIRST,
+            1 => self.page_a,
+            2 => self.page_b,
+            _ => PAGE_RAM_FIRST,
+        };
+        (page, addr as usize % PAGE_SIZE)
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        let (page, offset) = self.map(addr);
+        let w5100_pages = (W5100_ADDRESS_SPACE_SIZE / PAGE_SIZE) as u8;
+        match page {
+            0x00..=0x1F => self.flash[page as usize * PAGE_SIZE + offset],
+            p if (PAGE_W5100_FIRST..PAGE_W5100_FIRST + w5100_pages).contains(&p) => {
+                let addr = (p - PAGE_W5100_FIRST) as usize * PAGE_SIZE + offset;
+                self.w5100.read(addr as u16)
+            }
+            0xC0..=0xDF => self.ram[(page - PAGE_RAM_FIRST) as usize * PAGE_SIZE + offset],
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, data: u8) {
+        let (page, offset) = self.map(addr);
+        let w5100_pages = (W5100_ADDRESS_SPACE_SIZE / PAGE_SIZE) as u8;
+        match page {
+            p if (PAGE_W5100_FIRST..PAGE_W5100_FIRST + w5100_pages).contains(&p) => {
+                let addr = (p - PAGE_W5100_FIRST) as usize * PAGE_SIZE + offset;
+                self.w5100.write(addr as u16, data);
+            }
+            0xC0..=0xDF => {
+                self.ram[(page - PAGE_RAM_FIRST) as usize * PAGE_SIZE + offset] = data;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn read_port(&mut self, port: u16) -> u8 {
+        match port {
+            PORT_PAGE_A => self.page_a,
+            PORT_PAGE_B => self.page_b,
+            PORT_CONTROL => {
+                let paged_in = if self.paged_in { CONTROL_PAGED_IN } else { 0 };
+                (self.control & !CONTROL_PAGED_IN) | paged_in
+            }
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_port(&mut self, port: u16, data: u8) {
+        match port {
+            PORT_PAGE_A => self.page_a = data,
+            PORT_PAGE_B => self.page_b = data,
+            PORT_TRAP_ADDR => {
+                let [low, high] = self.trap_addr.to_le_bytes();
+                self.trap_addr = if self.trap_addr_high_next {
+                    u16::from_le_bytes([low, data])
+                } else {
+                    u16::from_le_bytes([data, high])
+                };
+                self.trap_addr_high_next = !self.trap_addr_high_next;
+            }
+            PORT_CONTROL => {
+                self.control = data;
+                self.set_paged_in(data & CONTROL_PAGED_IN != 0);
+            }
+            _ => {}
+        }
+    }
+
+    /// Polls host network, should be called periodically (e.g. each frame)
+    pub fn poll(&mut self) {
+        self.w5100.poll();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::StubNetworkStack;
+
+    fn spectranet() -> Spectranet<StubNetworkStack> {
+        let rom = (0..FLASH_SIZE)
+            .map(|idx| (idx / PAGE_SIZE) as u8)
+            .collect::<Vec<_>>();
+        let mut spectranet = Spectranet::new(&rom, StubNetworkStack);
+        spectranet.check_fetch(ADDR_PAGE_OUT);
+        spectranet
+    }
+
+    #[test]
+    fn pages_in_on_rst8_and_out_on_exit_address() {
+        let mut spectranet = spectranet();
+        assert!(!spectranet.paged_in());
+        spectranet.check_fetch(0x0038);
+        assert!(!spectranet.paged_in());
+        spectranet.check_fetch(ADDR_RST8);
+        assert!(spectranet.paged_in());
+        assert_eq!(
+            spectranet.read_port(PORT_CONTROL) & CONTROL_PAGED_IN,
+            CONTROL_PAGED_IN
+        );
+        spectranet.check_fetch(ADDR_PAGE_OUT);
+        assert!(!spectranet.paged_in());
+        spectranet.check_fetch(0x3FFA);
+        assert!(spectranet.paged_in());
+        assert!(!spectranet.nmi_pending());
+    }
+
+    #[test]
+    fn programmable_trap_requests_nmi_until_acknowledged() {
+        let mut spectranet = spectranet();
+        spectranet.write_port(PORT_TRAP_ADDR, 0x34);
+        spectranet.write_port(PORT_TRAP_ADDR, 0x12);
+        spectranet.check_fetch(0x1234);
+        assert!(!spectranet.nmi_pending(), "trap is disabled");
+
+        spectranet.write_port(PORT_CONTROL, CONTROL_TRAP_ENABLED);
+        spectranet.check_fetch(0x1234);
+        assert!(spectranet.paged_in());
+        assert!(spectranet.nmi_pending());
+        spectranet.acknowledge_nmi();
+        assert!(!spectranet.nmi_pending());
+    }
+
+    #[test]
+    fn reset_pages_in_and_disables_trap() {
+        let mut spectranet = spectranet();
+        spectranet.write_port(PORT_PAGE_A, 0x05);
+        spectranet.write_port(PORT_CONTROL, CONTROL_TRAP_ENABLED);
+        spectranet.reset();
+        assert!(spectranet.paged_in());
+        assert_eq!(spectranet.control, 0);
+        assert_eq!(spectranet.page_a, PAGE_FLASH_FIRST);
+    }
+
+    #[test]
+    fn pages_map_flash_and_ram() {
+        let mut spectranet = spectranet();
+        spectranet.write_port(PORT_PAGE_A, 0x05);
+        spectranet.write_port(PORT_PAGE_B, 0xC3);
+        assert_eq!(spectranet.read(0x0123), 0x00);
+        assert_eq!(spectranet.read(0x1123), 0x05);
+
+        // Flash is read-only
+        spectranet.write(0x1123, 0xAA);
+        assert_eq!(spectranet.read(0x1123), 0x05);
+
+        spectranet.write(0x2123, 0xAA);
+        spectranet.write(0x3123, 0xBB);
+        assert_eq!(spectranet.read(0x2123), 0xAA);
+        assert_eq!(spectranet.read(0x3123), 0xBB);
+        spectranet.write_port(PORT_PAGE_A, 0xC3);
+        assert_eq!(spectranet.read(0x1123), 0xAA);
+        spectranet.write_port(PORT_PAGE_A, 0xC0);
+        assert_eq!(spectranet.read(0x1123), 0xBB);
+    }
+}
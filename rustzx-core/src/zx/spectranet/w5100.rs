@@ -0,0 +1,570 @@
+//! WIZnet W5100 hardwired TCP/IP chip. Socket commands are mapped to the host
+//! sockets provided by [NetworkStack], so only TCP and UDP socket modes are
+//! supported (no IP raw/MACRAW modes, no DHCP)
+use crate::{
+    diagnostics::NETWORK,
+    host::{NetworkStack, SocketHandle},
+};
+use alloc::{vec, vec::Vec};
+
+/// Size of the W5100 address space
+pub(crate) const ADDRESS_SPACE_SIZE: usize = 0x8000;
+
+const REG_MODE: u16 = 0x0000;
+const REG_INTERRUPT: u16 = 0x0015;
+const COMMON_REGS_END: u16 = 0x0030;
+const SOCKET_REGS_BASE: u16 = 0x0400;
+const TX_BASE: u16 = 0x4000;
+const RX_BASE: u16 = 0x6000;
+
+const MODE_RESET: u8 = 0x80;
+
+const SOCKETS_COUNT: usize = 4;
+// Default 2K buffer per socket (RMSR/TMSR = 0x55)
+const SOCKET_BUFFER_SIZE: u16 = 0x0800;
+const SOCKET_BUFFER_MASK: u16 = SOCKET_BUFFER_SIZE - 1;
+const UDP_HEADER_SIZE: u16 = 8;
+
+const SN_MR: usize = 0x00;
+const SN_CR: usize = 0x01;
+const SN_IR: usize = 0x02;
+const SN_SR: usize = 0x03;
+const SN_PORT: usize = 0x04;
+const SN_DIPR: usize = 0x0C;
+const SN_DPORT: usize = 0x10;
+const SN_TX_FSR: usize = 0x20;
+const SN_TX_RD: usize = 0x22;
+const SN_TX_WR: usize = 0x24;
+const SN_RX_RSR: usize = 0x26;
+const SN_RX_RD: usize = 0x28;
+const SOCKET_REGS_SIZE: usize = 0x30;
+
+const MR_PROTOCOL_MASK: u8 = 0x0F;
+const MR_TCP: u8 = 0x01;
+const MR_UDP: u8 = 0x02;
+
+const CR_OPEN: u8 = 0x01;
+const CR_LISTEN: u8 = 0x02;
+const CR_CONNECT: u8 = 0x04;
+const CR_DISCON: u8 = 0x08;
+const CR_CLOSE: u8 = 0x10;
+const CR_SEND: u8 = 0x20;
+const CR_RECV: u8 = 0x40;
+
+const IR_CON: u8 = 0x01;
+const IR_DISCON: u8 = 0x02;
+const IR_RECV: u8 = 0x04;
+const IR_TIMEOUT: u8 = 0x08;
+const IR_SEND_OK: u8 = 0x10;
+
+const SR_CLOSED: u8 = 0x00;
+const SR_INIT: u8 = 0x13;
+const SR_LISTEN: u8 = 0x14;
+const SR_ESTABLISHED: u8 = 0x17;
+const SR_CLOSE_WAIT: u8 = 0x1C;
+const SR_UDP: u8 = 0x22;
+
+struct Socket {
+    regs: [u8; SOCKET_REGS_SIZE],
+    handle: Option<SocketHandle>,
+    // Write pointer of the received data, W5100 does not expose it directly
+    rx_write: u16,
+}
+
+impl Default for Socket {
+    fn default() -> Self {
+        Self {
+            regs: [0; SOCKET_REGS_SIZE],
+            handle: None,
+            rx_write: 0,
+        }
+    }
+}
+
+impl Socket {
+    fn reg16(&self, reg: usize) -> u16 {
+        u16::from_be_bytes([self.regs[reg], self.regs[reg + 1]])
+    }
+
+    fn set_reg16(&mut self, reg: usize, value: u16) {
+        self.regs[reg..reg + 2].copy_from_slice(&value.to_be_bytes());
+    }
+
+    fn status(&self) -> u8 {
+        self.regs[SN_SR]
+    }
+
+    fn tx_pending(&self) -> u16 {
+        self.reg16(SN_TX_WR).wrapping_sub(self.reg16(SN_TX_RD))
+    }
+
+    fn tx_free(&self) -> u16 {
+        SOCKET_BUFFER_SIZE.saturating_sub(self.tx_pending())
+    }
+
+    fn rx_received(&self) -> u16 {
+        self.rx_write.wrapping_sub(self.reg16(SN_RX_RD))
+    }
+
+    fn rx_free(&self) -> u16 {
+        SOCKET_BUFFER_SIZE.saturating_sub(self.rx_received())
+    }
+
+    fn reset_pointers(&mut self) {
+        for reg in [SN_TX_RD, SN_TX_WR, SN_RX_RD] {
+            self.set_reg16(reg, 0);
+        }
+        self.rx_write = 0;
+    }
+}
+
+pub(crate) struct W5100<N: NetworkStack> {
+    network: N,
+    common: [u8; COMMON_REGS_END as usize],
+    sockets: [Socket; SOCKETS_COUNT],
+    tx: Vec<u8>,
+    rx: Vec<u8>,
+}
+
+impl<N: NetworkStack> W5100<N> {
+    pub fn new(network: N) -> Self {
+        Self {
+            network,
+            common: [0; COMMON_REGS_END as usize],
+            sockets: Default::default(),
+            tx: vec![0; SOCKETS_COUNT * SOCKET_BUFFER_SIZE as usize],
+            rx: vec![0; SOCKETS_COUNT * SOCKET_BUFFER_SIZE as usize],
+        }
+    }
+
+    pub fn into_network(mut self) -> N {
+        self.reset();
+        self.network
+    }
+
+    fn reset(&mut self) {
+        for index in 0..SOCKETS_COUNT {
+            self.close_socket(index);
+            self.sockets[index] = Socket::default();
+        }
+        self.common = [0; COMMON_REGS_END as usize];
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        match addr {
+            REG_INTERRUPT => self
+                .sockets
+                .iter()
+                .enumerate()
+                .filter(|(_, socket)| socket.regs[SN_IR] != 0)
+                .fold(0, |acc, (index, _)| acc | (1 << index)),
+            0..=0x002F => self.common[addr as usize],
+            SOCKET_REGS_BASE..=0x07FF => {
+                let (index, reg) = socket_reg(addr);
+                let socket = &self.sockets[index];
+                match reg {
+                    SN_CR => 0,
+                    SN_TX_FSR | 0x21 => socket.tx_free().to_be_bytes()[reg - SN_TX_FSR],
+                    SN_RX_RSR | 0x27 => socket.rx_received().to_be_bytes()[reg - SN_RX_RSR],
+                    0..=0x2F => socket.regs[reg],
+                    _ => 0,
+                }
+            }
+            0x4000..=0x5FFF => self.tx[(addr - TX_BASE) as usize],
+            0x6000..=0x7FFF => self.rx[(addr - RX_BASE) as usize],
+            _ => 0,
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            REG_MODE if data & MODE_RESET != 0 => self.reset(),
+            REG_INTERRUPT => {}
+            0..=0x002F => self.common[addr as usize] = data,
+            SOCKET_REGS_BASE..=0x07FF => {
+                let (index, reg) = socket_reg(addr);
+                match reg {
+                    SN_CR => self.execute_command(index, data),
+                    SN_IR => self.sockets[index].regs[SN_IR] &= !data,
+                    // Read-only registers
+                    SN_SR | SN_TX_FSR | 0x21 | SN_TX_RD | 0x23 | SN_RX_RSR | 0x27 => {}
+                    0..=0x2F => self.sockets[index].regs[reg] = data,
+                    _ => {}
+                }
+            }
+            0x4000..=0x5FFF => self.tx[(addr - TX_BASE) as usize] = data,
+            0x6000..=0x7FFF => self.rx[(addr - RX_BASE) as usize] = data,
+            _ => {}
+        }
+    }
+
+    fn close_socket(&mut self, index: usize) {
+        if let Some(handle) = self.sockets[index].handle.take() {
+            self.network.close(handle);
+        }
+    }
+
+    fn execute_command(&mut self, index: usize, command: u8) {
+        let socket = &self.sockets[index];
+        let status = socket.status();
+        let port = socket.reg16(SN_PORT);
+        let mut ip = [0u8; 4];
+        ip.copy_from_slice(&socket.regs[SN_DIPR..SN_DIPR + 4]);
+        let dport = socket.reg16(SN_DPORT);
+        diag!(
+            debug,
+            NETWORK,
+            "socket {}: command {:#04X}, status {:#04X}",
+            index,
+            command,
+            status
+        );
+
+        match command {
+            CR_OPEN => {
+                self.close_socket(index);
+                let socket = &mut self.sockets[index];
+                socket.reset_pointers();
+                socket.regs[SN_SR] = match socket.regs[SN_MR] & MR_PROTOCOL_MASK {
+                    MR_TCP => SR_INIT,
+                    MR_UDP => match self.network.udp_bind(port) {
+                        Ok(handle) => {
+                            socket.handle = Some(handle);
+                            SR_UDP
+                        }
+                        Err(e) => {
+                            diag!(warn, NETWORK, "socket {}: UDP bind failed: {:?}", index, e);
+                            SR_CLOSED
+                        }
+                    },
+                    _ => SR_CLOSED,
+                };
+            }
+            CR_LISTEN if status == SR_INIT => match self.network.tcp_listen(port) {
+                Ok(handle) => {
+                    let socket = &mut self.sockets[index];
+                    socket.handle = Some(handle);
+                    socket.regs[SN_SR] = SR_LISTEN;
+                }
+                Err(e) => {
+                    diag!(warn, NETWORK, "socket {}: listen failed: {:?}", index, e);
+                    let socket = &mut self.sockets[index];
+                    socket.regs[SN_SR] = SR_CLOSED;
+                    socket.regs[SN_IR] |= IR_TIMEOUT;
+                }
+            },
+            CR_CONNECT if status == SR_INIT => {
+                let socket = &mut self.sockets[index];
+                match self.network.tcp_connect(ip, dport) {
+                    Ok(handle) => {
+                        socket.handle = Some(handle);
+                        socket.regs[SN_SR] = SR_ESTABLISHED;
+                        socket.regs[SN_IR] |= IR_CON;
+                    }
+                    Err(e) => {
+                        diag!(
+                            warn,
+                            NETWORK,
+                            "socket {}: connect to {:?}:{} failed: {:?}",
+                            index,
+                            ip,
+                            dport,
+                            e
+                        );
+                        socket.regs[SN_SR] = SR_CLOSED;
+                        socket.regs[SN_IR] |= IR_TIMEOUT;
+                    }
+                }
+            }
+            CR_DISCON => {
+                self.close_socket(index);
+                let socket = &mut self.sockets[index];
+                socket.regs[SN_SR] = SR_CLOSED;
+                socket.regs[SN_IR] |= IR_DISCON;
+            }
+            CR_CLOSE => {
+                self.close_socket(index);
+                self.sockets[index].regs[SN_SR] = SR_CLOSED;
+            }
+            CR_SEND if matches!(status, SR_ESTABLISHED | SR_CLOSE_WAIT | SR_UDP) => {
+                self.send(index, status == SR_UDP, ip, dport)
+            }
+            // Received data is released by moving RX read pointer, which is
+            // already taken into account
+            CR_RECV => {}
+            _ => {}
+        }
+    }
+
+    fn send(&mut self, index: usize, udp: bool, ip: [u8; 4], port: u16) {
+        let socket = &self.sockets[index];
+        let (start, size) = (socket.reg16(SN_TX_RD), socket.tx_pending());
+        let buffer = &self.tx[index * SOCKET_BUFFER_SIZE as usize..][..SOCKET_BUFFER_SIZE as usize];
+        let data = (0..size)
+            .map(|offset| buffer[(start.wrapping_add(offset) & SOCKET_BUFFER_MASK) as usize])
+            .collect::<Vec<_>>();
+
+        let result = match socket.handle {
+            Some(handle) if udp => self.network.send_to(handle, ip, port, &data),
+            Some(handle) => self.network.send(handle, &data),
+            None => return,
+        };
+        let socket = &mut self.sockets[index];
+        match result {
+            Ok(()) => {
+                let written = socket.reg16(SN_TX_WR);
+                socket.set_reg16(SN_TX_RD, written);
+                socket.regs[SN_IR] |= IR_SEND_OK;
+            }
+            Err(e) => {
+                diag!(warn, NETWORK, "socket {}: send failed: {:?}", index, e);
+                socket.regs[SN_IR] |= IR_TIMEOUT;
+            }
+        }
+    }
+
+    /// Polls host sockets for incoming connections and data
+    pub fn poll(&mut self) {
+        for index in 0..SOCKETS_COUNT {
+            let socket = &self.sockets[index];
+            let handle = match socket.handle {
+                Some(handle) => handle,
+                None => continue,
+            };
+            match socket.status() {
+                SR_LISTEN => match self.network.tcp_accept(handle) {
+                    Ok(true) => {
+                        let socket = &mut self.sockets[index];
+                        socket.regs[SN_SR] = SR_ESTABLISHED;
+                        socket.regs[SN_IR] |= IR_CON;
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        diag!(warn, NETWORK, "socket {}: accept failed: {:?}", index, e);
+                        self.close_socket(index);
+                        self.sockets[index].regs[SN_SR] = SR_CLOSED;
+                    }
+                },
+                SR_ESTABLISHED => self.poll_tcp(index, handle),
+                SR_UDP => self.poll_udp(index, handle),
+                _ => {}
+            }
+        }
+    }
+
+    fn poll_tcp(&mut self, index: usize, handle: SocketHandle) {
+        let free = self.sockets[index].rx_free();
+        if free == 0 {
+            return;
+        }
+        let mut buffer = vec![0u8; free as usize];
+        match self.network.recv(handle, &mut buffer) {
+            Ok(None) => {}
+            Ok(Some(0)) => {
+                let socket = &mut self.sockets[index];
+                socket.regs[SN_SR] = SR_CLOSE_WAIT;
+                socket.regs[SN_IR] |= IR_DISCON;
+            }
+            Ok(Some(size)) => self.push_rx(index, &buffer[..size]),
+            Err(e) => {
+                diag!(warn, NETWORK, "socket {}: receive failed: {:?}", index, e);
+                self.close_socket(index);
+                let socket = &mut self.sockets[index];
+                socket.regs[SN_SR] = SR_CLOSED;
+                socket.regs[SN_IR] |= IR_DISCON;
+            }
+        }
+    }
+
+    fn poll_udp(&mut self, index: usize, handle: SocketHandle) {
+        let free = self.sockets[index].rx_free();
+        if free <= UDP_HEADER_SIZE {
+            return;
+        }
+        let mut buffer = vec![0u8; (free - UDP_HEADER_SIZE) as usize];
+        match self.network.recv_from(handle, &mut buffer) {
+            Ok(None) => {}
+            Ok(Some((size, ip, port))) => {
+                let mut header = [0u8; UDP_HEADER_SIZE as usize];
+                header[0..4].copy_from_slice(&ip);
+                header[4..6].copy_from_slice(&port.to_be_bytes());
+                header[6..8].copy_from_slice(&(size as u16).to_be_bytes());
+                self.push_rx(index, &header);
+                self.push_rx(index, &buffer[..size]);
+            }
+            Err(e) => {
+                diag!(warn, NETWORK, "socket {}: receive failed: {:?}", index, e);
+            }
+        }
+    }
+
+    fn push_rx(&mut self, index: usize, data: &[u8]) {
+        let buffer =
+            &mut self.rx[index * SOCKET_BUFFER_SIZE as usize..][..SOCKET_BUFFER_SIZE as usize];
+        let socket = &mut self.sockets[index];
+        for byte in data {
+            buffer[(socket.rx_write & SOCKET_BUFFER_MASK) as usize] = *byte;
+            socket.rx_write = socket.rx_write.wrapping_add(1);
+        }
+        socket.regs[SN_IR] |= IR_RECV;
+    }
+}
+
+fn socket_reg(addr: u16) -> (usize, usize) {
+    let offset = addr - SOCKET_REGS_BASE;
+    ((offset >> 8) as usize, (offset & 0xFF) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::IoError;
+
+    /// Network stack with a single TCP connection, which echoes sent data back
+    #[derive(Default)]
+    struct EchoNetworkStack {
+        remote: Option<([u8; 4], u16)>,
+        pending: Vec<u8>,
+    }
+
+    impl NetworkStack for EchoNetworkStack {
+        fn tcp_connect(&mut self, ip: [u8; 4], port: u16) -> Result<SocketHandle, IoError> {
+            self.remote = Some((ip, port));
+            Ok(7)
+        }
+
+        fn tcp_listen(&mut self, _port: u16) -> Result<SocketHandle, IoError> {
+            Err(IoError::HostAssetImplFailed)
+        }
+
+        fn tcp_accept(&mut self, _socket: SocketHandle) -> Result<bool, IoError> {
+            Err(IoError::HostAssetImplFailed)
+        }
+
+        fn udp_bind(&mut self, _port: u16) -> Result<SocketHandle, IoError> {
+            Err(IoError::HostAssetImplFailed)
+        }
+
+        fn send(&mut self, socket: SocketHandle, data: &[u8]) -> Result<(), IoError> {
+            assert_eq!(socket, 7);
+            self.pending.extend_from_slice(data);
+            Ok(())
+        }
+
+        fn send_to(
+            &mut self,
+            _socket: SocketHandle,
+            _ip: [u8; 4],
+            _port: u16,
+            _data: &[u8],
+        ) -> Result<(), IoError> {
+            Err(IoError::HostAssetImplFailed)
+        }
+
+        fn recv(
+            &mut self,
+            _socket: SocketHandle,
+            buf: &mut [u8],
+        ) -> Result<Option<usize>, IoError> {
+            if self.pending.is_empty() {
+                return Ok(None);
+            }
+            let size = self.pending.len().min(buf.len());
+            buf[..size].copy_from_slice(&self.pending[..size]);
+            self.pending.drain(..size);
+            Ok(Some(size))
+        }
+
+        fn recv_from(
+            &mut self,
+            _socket: SocketHandle,
+            _buf: &mut [u8],
+        ) -> Result<Option<(usize, [u8; 4], u16)>, IoError> {
+            Err(IoError::HostAssetImplFailed)
+        }
+
+        fn close(&mut self, _socket: SocketHandle) {
+            self.remote = None;
+        }
+    }
+
+    const SOCKET1: u16 = SOCKET_REGS_BASE + 0x100;
+
+    fn write16(chip: &mut W5100<EchoNetworkStack>, addr: u16, value: u16) {
+        let [high, low] = value.to_be_bytes();
+        chip.write(addr, high);
+        chip.write(addr + 1, low);
+    }
+
+    fn read16(chip: &mut W5100<EchoNetworkStack>, addr: u16) -> u16 {
+        u16::from_be_bytes([chip.read(addr), chip.read(addr + 1)])
+    }
+
+    fn connect(chip: &mut W5100<EchoNetworkStack>) {
+        chip.write(SOCKET1 + SN_MR as u16, MR_TCP);
+        chip.write(SOCKET1 + SN_CR as u16, CR_OPEN);
+        assert_eq!(chip.read(SOCKET1 + SN_SR as u16), SR_INIT);
+        for (offset, byte) in [10, 0, 0, 1].into_iter().enumerate() {
+            chip.write(SOCKET1 + (SN_DIPR + offset) as u16, byte);
+        }
+        write16(chip, SOCKET1 + SN_DPORT as u16, 2323);
+        chip.write(SOCKET1 + SN_CR as u16, CR_CONNECT);
+    }
+
+    #[test]
+    fn tcp_connect_uses_destination_registers() {
+        let mut chip = W5100::new(EchoNetworkStack::default());
+        connect(&mut chip);
+        assert_eq!(chip.read(SOCKET1 + SN_SR as u16), SR_ESTABLISHED);
+        assert_eq!(chip.read(SOCKET1 + SN_IR as u16), IR_CON);
+        assert_eq!(chip.read(REG_INTERRUPT), 0b0010);
+        assert_eq!(chip.network.remote, Some(([10, 0, 0, 1], 2323)));
+
+        chip.write(SOCKET1 + SN_IR as u16, IR_CON);
+        assert_eq!(chip.read(REG_INTERRUPT), 0);
+        chip.write(SOCKET1 + SN_CR as u16, CR_CLOSE);
+        assert_eq!(chip.read(SOCKET1 + SN_SR as u16), SR_CLOSED);
+        assert_eq!(chip.into_network().remote, None);
+    }
+
+    #[test]
+    fn tcp_send_and_receive_wrap_around_buffer() {
+        let mut chip = W5100::new(EchoNetworkStack::default());
+        connect(&mut chip);
+        // Start close to the end of the 2K socket buffer to check wrapping
+        let start = SOCKET_BUFFER_SIZE - 2;
+        write16(&mut chip, SOCKET1 + SN_RX_RD as u16, start);
+        chip.sockets[1].rx_write = start;
+        chip.sockets[1].set_reg16(SN_TX_RD, start);
+        write16(&mut chip, SOCKET1 + SN_TX_WR as u16, start);
+
+        let tx_base = TX_BASE + SOCKET_BUFFER_SIZE;
+        for (offset, byte) in b"ping".iter().enumerate() {
+            let pos = (start + offset as u16) & SOCKET_BUFFER_MASK;
+            chip.write(tx_base + pos, *byte);
+        }
+        write16(&mut chip, SOCKET1 + SN_TX_WR as u16, start.wrapping_add(4));
+        assert_eq!(
+            read16(&mut chip, SOCKET1 + SN_TX_FSR as u16),
+            SOCKET_BUFFER_SIZE - 4
+        );
+        chip.write(SOCKET1 + SN_CR as u16, CR_SEND);
+        assert_eq!(
+            read16(&mut chip, SOCKET1 + SN_TX_FSR as u16),
+            SOCKET_BUFFER_SIZE
+        );
+        assert_ne!(chip.read(SOCKET1 + SN_IR as u16) & IR_SEND_OK, 0);
+
+        chip.poll();
+        assert_eq!(read16(&mut chip, SOCKET1 + SN_RX_RSR as u16), 4);
+        let rx_base = RX_BASE + SOCKET_BUFFER_SIZE;
+        let received = (0..4)
+            .map(|offset| chip.read(rx_base + ((start + offset) & SOCKET_BUFFER_MASK)))
+            .collect::<Vec<_>>();
+        assert_eq!(received, b"ping");
+
+        write16(&mut chip, SOCKET1 + SN_RX_RD as u16, start.wrapping_add(4));
+        chip.write(SOCKET1 + SN_CR as u16, CR_RECV);
+        assert_eq!(read16(&mut chip, SOCKET1 + SN_RX_RSR as u16), 0);
+    }
+}
@@ -0,0 +1,134 @@
+//! Activity of the storage devices for the frontend indicators (e.g. drive
+//! LEDs). Status of each device is sampled at the end of each frame and a
+//! [StorageActivity] event is reported when it differs from the previous
+//! frame, so there is at most one event per frame for each device, see
+//! [crate::Emulator::storage_events]
+use alloc::vec::Vec;
+
+/// Count of the +D floppy drives, see [StorageDevice::PlusD]
+#[cfg(feature = "plusd")]
+pub const PLUSD_DRIVE_COUNT: usize = 2;
+/// Count of the Beta Disk floppy drives, see [StorageDevice::BetaDisk]
+#[cfg(feature = "betadisk")]
+pub const BETADISK_DRIVE_COUNT: usize = 4;
+
+/// Data transfer performed by the device during the frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DriveActivity {
+    #[default]
+    Idle,
+    Read,
+    /// Data was written, possibly along with reading
+    Write,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DriveStatus {
+    pub motor_on: bool,
+    pub activity: DriveActivity,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageDevice {
+    /// Tape player, motor is on while the tape is playing
+    Tape,
+    /// +D floppy drive (`0..PLUSD_DRIVE_COUNT`), motor is on while the drive is selected
+    /// and the controller motor is running
+    #[cfg(feature = "plusd")]
+    PlusD(usize),
+    /// Beta Disk floppy drive (`0..BETADISK_DRIVE_COUNT`, drives A-D), motor is on while the
+    /// drive is selected and the head is loaded
+    #[cfg(feature = "betadisk")]
+    BetaDisk(usize),
+}
+
+/// Status change of the storage device
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StorageActivity {
+    pub device: StorageDevice,
+    pub status: DriveStatus,
+}
+
+/// Collects status changes at the frame end
+#[derive(Default)]
+pub(crate) struct StorageMonitor {
+    statuses: Vec<StorageActivity>,
+    previous: Vec<StorageActivity>,
+    events: Vec<StorageActivity>,
+}
+
+impl StorageMonitor {
+    /// Updates statuses with the ones sampled at the end of the frame.
+    /// Devices missing from `statuses` (e.g. detached) are reported once
+    /// as idle
+    pub fn frame_finished(&mut self, statuses: impl Iterator<Item = StorageActivity>) {
+        core::mem::swap(&mut self.statuses, &mut self.previous);
+        self.statuses.clear();
+        self.statuses.extend(statuses);
+        self.events.clear();
+        let status_of = |list: &[StorageActivity], device| {
+            list.iter()
+                .find(|s| s.device == device)
+                .map(|s| s.status)
+                .unwrap_or_default()
+        };
+        for current in &self.statuses {
+            if status_of(&self.previous, current.device) != current.status {
+                self.events.push(*current);
+            }
+        }
+        for previous in &self.previous {
+            let removed = !self.statuses.iter().any(|s| s.device == previous.device);
+            if removed && previous.status != DriveStatus::default() {
+                self.events.push(StorageActivity {
+                    device: previous.device,
+                    status: DriveStatus::default(),
+                });
+            }
+        }
+    }
+
+    /// Returns status changes detected at the end of the last frame
+    pub fn events(&self) -> &[StorageActivity] {
+        &self.events
+    }
+
+    pub fn status(&self, device: StorageDevice) -> Option<DriveStatus> {
+        self.statuses
+            .iter()
+            .find(|s| s.device == device)
+            .map(|s| s.status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn tape(motor_on: bool, activity: DriveActivity) -> StorageActivity {
+        StorageActivity {
+            device: StorageDevice::Tape,
+            status: DriveStatus { motor_on, activity },
+        }
+    }
+
+    #[test]
+    fn only_changes_are_reported() {
+        let mut monitor = StorageMonitor::default();
+        monitor.frame_finished([tape(false, DriveActivity::Idle)].into_iter());
+        assert!(monitor.events().is_empty());
+
+        let playing = tape(true, DriveActivity::Read);
+        monitor.frame_finished([playing].into_iter());
+        assert_eq!(monitor.events(), [playing]);
+        monitor.frame_finished([playing].into_iter());
+        assert!(monitor.events().is_empty());
+        assert_eq!(monitor.status(StorageDevice::Tape), Some(playing.status));
+
+        // Removed device is reported as idle
+        monitor.frame_finished(vec![].into_iter());
+        assert_eq!(monitor.events(), [tape(false, DriveActivity::Idle)]);
+        assert_eq!(monitor.status(StorageDevice::Tape), None);
+    }
+}
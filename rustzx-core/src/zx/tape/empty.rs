@@ -1,4 +1,10 @@
-use crate::{zx::tape::TapeImpl, Result};
+use crate::{
+    zx::{
+        machine::MemoryModel,
+        tape::{TapeImpl, TapeProgress},
+    },
+    Result,
+};
 
 pub struct Empty;
 
@@ -27,7 +33,23 @@ impl TapeImpl for Empty {
 
     fn play(&mut self) {}
 
+    fn is_playing(&self) -> bool {
+        false
+    }
+
     fn rewind(&mut self) -> Result<()> {
         Ok(())
     }
+
+    fn progress(&self) -> Option<TapeProgress> {
+        None
+    }
+
+    fn machine_hint(&self) -> Option<MemoryModel> {
+        None
+    }
+
+    fn time_clocks(&self) -> (u64, u64) {
+        (0, 0)
+    }
 }
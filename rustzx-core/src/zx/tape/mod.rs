@@ -6,6 +6,7 @@ pub use tap::Tap;
 
 use crate::{
     host::{LoadableAsset, SeekableAsset},
+    zx::machine::MemoryModel,
     Result,
 };
 
@@ -24,6 +25,25 @@ impl<A: LoadableAsset + SeekableAsset> Default for ZXTape<A> {
     }
 }
 
+/// Tape playback position, see [TapeImpl::progress]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TapeProgress {
+    /// Zero-based index of the current block, stays 0 until the first block
+    /// is started
+    pub block: usize,
+    pub total_blocks: usize,
+    /// Count of data bytes of the current block which were read, either by
+    /// the fast loader or by playback
+    pub block_bytes: usize,
+    /// Length of the current block data in bytes, 0 until the first block is
+    /// started
+    pub block_length: usize,
+    /// Share of the tape image bytes (including block length headers) before
+    /// the read position, in range `0.0..=1.0`. It is not proportional to the
+    /// playback time, as pilot tones and pauses take no image bytes
+    pub fraction: f32,
+}
+
 #[enum_dispatch]
 pub trait TapeImpl {
     fn can_fast_load(&self) -> bool;
@@ -37,6 +57,16 @@ pub trait TapeImpl {
     fn process_clocks(&mut self, clocks: usize) -> Result<()>;
     fn stop(&mut self);
     fn play(&mut self);
+    /// Returns true if the tape is playing and drives EAR input
+    fn is_playing(&self) -> bool;
     /// Rewinds tape content to the beginning
     fn rewind(&mut self) -> Result<()>;
+    /// Returns current playback position. Position is advanced by both
+    /// fast loading and normal playback
+    fn progress(&self) -> Option<TapeProgress>;
+    /// Returns memory model required by the tape content, `None` if the tape
+    /// has no hints (e.g. 48K software which also runs on 128K)
+    fn machine_hint(&self) -> Option<MemoryModel>;
+    /// Returns elapsed and total playback time of the tape in T-states
+    fn time_clocks(&self) -> (u64, u64);
 }
@@ -1,9 +1,14 @@
 use crate::{
-    error::TapeLoadError,
+    diagnostics::TAPE,
+    error::{IoError, TapeLoadError},
     host::{LoadableAsset, SeekFrom, SeekableAsset},
-    zx::tape::TapeImpl,
+    zx::{
+        machine::MemoryModel,
+        tape::{TapeImpl, TapeProgress},
+    },
     Result,
 };
+use alloc::vec::Vec;
 
 const PILOT_LENGTH: usize = 2168;
 const PILOT_PULSES_HEADER: usize = 8063;
@@ -14,6 +19,24 @@ const BIT_ONE_LENGTH: usize = 1710;
 const BIT_ZERO_LENGTH: usize = 855;
 const PAUSE_LENGTH: usize = 3_500_000;
 const BUFFER_SIZE: usize = 128;
+/// Flag, 17 bytes of the header and checksum
+const HEADER_BLOCK_SIZE: usize = 19;
+const HEADER_TYPE_PROGRAM: u8 = 0x00;
+/// Offset of the program length (without variables) in the header block
+const HEADER_PROGRAM_LENGTH_OFFSET: usize = 16;
+
+/// 128 BASIC keywords, UDG characters on 48K
+const TOKEN_SPECTRUM: u8 = 0xA3;
+const TOKEN_PLAY: u8 = 0xA4;
+const TOKEN_REM: u8 = 0xEA;
+/// Marker of the hidden 5-byte form of the number after its digits
+const NUMBER_MARKER: u8 = 0x0E;
+const NUMBER_SIZE: u8 = 5;
+/// 32765 (memory paging port 0x7FFD) in the 5-byte form
+const NUMBER_PORT_7FFD: [u8; NUMBER_SIZE as usize] = [0x00, 0x00, 0xFD, 0x7F, 0x00];
+const LINE_END: u8 = 0x0D;
+/// Line number and line length
+const LINE_HEADER_SIZE: u8 = 4;
 
 #[derive(PartialEq, Eq, Clone, Copy)]
 enum TapeState {
@@ -36,6 +59,19 @@ pub struct Tap<A: LoadableAsset + SeekableAsset> {
     block_bytes_read: usize,
     current_block_size: Option<usize>,
     tape_ended: bool,
+    // Index (1-based) and file offset of the current block, used for diagnostics
+    block_index: usize,
+    block_offset: usize,
+    next_block_offset: usize,
+    // Block count and size of the tape data, used for progress reporting
+    total_blocks: usize,
+    // Machine required by the BASIC loaders of the tape
+    machine_hint: Option<MemoryModel>,
+    total_size: usize,
+    // Playback duration of each block in T-states (including the pause after
+    // it) and T-states played in the current block, used for tape counter
+    block_durations: Vec<u64>,
+    block_clocks: u64,
     // Non-fastload related fields
     curr_bit: bool,
     curr_byte: u8,
@@ -44,7 +80,7 @@ pub struct Tap<A: LoadableAsset + SeekableAsset> {
 
 impl<A: LoadableAsset + SeekableAsset> Tap<A> {
     pub fn from_asset(asset: A) -> Result<Self> {
-        let tap = Self {
+        let mut tap = Self {
             prev_state: TapeState::Stop,
             state: TapeState::Stop,
             curr_bit: false,
@@ -56,9 +92,177 @@ impl<A: LoadableAsset + SeekableAsset> Tap<A> {
             delay: 0,
             asset,
             tape_ended: false,
+            block_index: 0,
+            block_offset: 0,
+            next_block_offset: 0,
+            total_blocks: 0,
+            machine_hint: None,
+            total_size: 0,
+            block_durations: Vec::new(),
+            block_clocks: 0,
         };
+        // Asset could be already read partially by the host
+        tap.asset.seek(SeekFrom::Start(0))?;
+        tap.scan_blocks()?;
+        tap.rewind()?;
         Ok(tap)
     }
+
+    /// Walks blocks to find total block count, data size, block durations
+    /// and 128K features of the BASIC programs
+    fn scan_blocks(&mut self) -> Result<()> {
+        let mut block_size_buffer = [0u8; 2];
+        // Length of the BASIC program announced by the previous header
+        let mut program_length = None;
+        while self.asset.read_exact(&mut block_size_buffer).is_ok() {
+            let block_size = u16::from_le_bytes(block_size_buffer) as usize;
+            let mut duration = PAUSE_LENGTH as u64;
+            let mut bytes_left = block_size;
+            let mut program = program_length
+                .take()
+                .map(|length| (length, BasicScanner::new()));
+            while bytes_left > 0 {
+                let chunk_size = bytes_left.min(BUFFER_SIZE);
+                if self
+                    .asset
+                    .read_exact(&mut self.buffer[0..chunk_size])
+                    .is_err()
+                {
+                    // Truncated block is reported when it is played
+                    break;
+                }
+                let chunk_start = block_size - bytes_left;
+                if chunk_start == 0 {
+                    duration += pilot_duration(self.buffer[0]);
+                    if block_size == HEADER_BLOCK_SIZE
+                        && self.buffer[0] == 0x00
+                        && self.buffer[1] == HEADER_TYPE_PROGRAM
+                    {
+                        let offset = HEADER_PROGRAM_LENGTH_OFFSET;
+                        program_length =
+                            Some(
+                                u16::from_le_bytes([self.buffer[offset], self.buffer[offset + 1]])
+                                    as usize,
+                            );
+                    }
+                }
+                if let Some((length, scanner)) = &mut program {
+                    // Flag byte precedes the program
+                    (chunk_start..)
+                        .zip(&self.buffer[0..chunk_size])
+                        .filter(|(offset, _)| (1..=*length).contains(offset))
+                        .for_each(|(_, byte)| scanner.feed(*byte));
+                }
+                duration += self.buffer[0..chunk_size]
+                    .iter()
+                    .map(|&byte| byte_duration(byte))
+                    .sum::<u64>();
+                bytes_left -= chunk_size;
+            }
+            if let Some((_, scanner)) = program {
+                if scanner.is_128k() {
+                    diag!(
+                        debug,
+                        TAPE,
+                        "block #{} is a 128K BASIC program",
+                        self.total_blocks + 1
+                    );
+                    self.machine_hint = Some(MemoryModel::Sinclair128K);
+                }
+            }
+            self.block_durations.push(duration);
+            self.total_blocks += 1;
+            self.total_size += 2 + block_size;
+        }
+        Ok(())
+    }
+
+    /// Reads `len` bytes of the current block to the buffer. Asset ending
+    /// within the block means a damaged tape rather than a failed read
+    fn read_block_data(&mut self, len: usize) -> Result<()> {
+        self.asset
+            .read_exact(&mut self.buffer[0..len])
+            .map_err(|e| match e {
+                IoError::UnexpectedEof => TapeLoadError::TruncatedBlock.into(),
+                e => e.into(),
+            })
+    }
+}
+
+/// Looks for 128K features in the tokenized BASIC program: 128 BASIC
+/// keywords and the memory paging port number (e.g. `OUT 32765,n`)
+struct BasicScanner {
+    /// Bytes of the line header left to skip
+    header_left: u8,
+    /// Bytes of the 5-byte number form left to read
+    number_left: u8,
+    number: [u8; NUMBER_SIZE as usize],
+    in_string: bool,
+    in_comment: bool,
+    is_128k: bool,
+}
+
+impl BasicScanner {
+    fn new() -> Self {
+        Self {
+            header_left: LINE_HEADER_SIZE,
+            number_left: 0,
+            number: [0; NUMBER_SIZE as usize],
+            in_string: false,
+            in_comment: false,
+            is_128k: false,
+        }
+    }
+
+    fn feed(&mut self, byte: u8) {
+        if self.header_left > 0 {
+            self.header_left -= 1;
+            return;
+        }
+        if self.number_left > 0 {
+            self.number[(NUMBER_SIZE - self.number_left) as usize] = byte;
+            self.number_left -= 1;
+            if self.number_left == 0 && self.number == NUMBER_PORT_7FFD {
+                self.is_128k = true;
+            }
+            return;
+        }
+        match byte {
+            LINE_END => {
+                self.header_left = LINE_HEADER_SIZE;
+                self.in_string = false;
+                self.in_comment = false;
+            }
+            _ if self.in_comment => {}
+            b'"' => self.in_string = !self.in_string,
+            _ if self.in_string => {}
+            NUMBER_MARKER => self.number_left = NUMBER_SIZE,
+            TOKEN_REM => self.in_comment = true,
+            TOKEN_SPECTRUM | TOKEN_PLAY => self.is_128k = true,
+            _ => {}
+        }
+    }
+
+    fn is_128k(&self) -> bool {
+        self.is_128k
+    }
+}
+
+/// Returns duration of the pilot tone and sync pulses in T-states
+fn pilot_duration(flag: u8) -> u64 {
+    let pulses = if flag == 0x00 {
+        PILOT_PULSES_HEADER
+    } else {
+        PILOT_PULSES_DATA
+    };
+    (pulses * PILOT_LENGTH + SYNC1_LENGTH + SYNC2_LENGTH) as u64
+}
+
+/// Returns duration of the byte pulses in T-states
+fn byte_duration(byte: u8) -> u64 {
+    let ones = byte.count_ones() as u64;
+    let zeros = 8 - ones;
+    2 * (ones * BIT_ONE_LENGTH as u64 + zeros * BIT_ZERO_LENGTH as u64)
 }
 
 impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
@@ -82,7 +286,7 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
             if buffer_read_pos >= BUFFER_SIZE {
                 let bytes_to_read =
                     (block_size - self.buffer_offset - BUFFER_SIZE).min(BUFFER_SIZE);
-                self.asset.read_exact(&mut self.buffer[0..bytes_to_read])?;
+                self.read_block_data(bytes_to_read)?;
                 self.buffer_offset += BUFFER_SIZE;
                 buffer_read_pos = 0;
             }
@@ -113,16 +317,46 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
 
         let mut block_size_buffer = [0u8; 2];
         if self.asset.read_exact(&mut block_size_buffer).is_err() {
+            diag!(
+                debug,
+                TAPE,
+                "end of tape after {} blocks at offset {}",
+                self.block_index,
+                self.next_block_offset
+            );
             self.tape_ended = true;
             return Ok(false);
         }
         let block_size = u16::from_le_bytes(block_size_buffer) as usize;
         let block_bytes_to_read = block_size.min(BUFFER_SIZE);
-        self.asset
-            .read_exact(&mut self.buffer[0..block_bytes_to_read])?;
+        if let Err(e) = self.read_block_data(block_bytes_to_read) {
+            diag!(
+                warn,
+                TAPE,
+                "truncated block #{} at offset {}: declared size {}, {:?}",
+                self.block_index + 1,
+                self.next_block_offset,
+                block_size,
+                e
+            );
+            return Err(e);
+        }
+
+        self.block_index += 1;
+        self.block_offset = self.next_block_offset;
+        self.next_block_offset += 2 + block_size;
+        diag!(
+            debug,
+            TAPE,
+            "block #{} at offset {}: {} bytes",
+            self.block_index,
+            self.block_offset,
+            block_size
+        );
 
         self.buffer_offset = 0;
         self.block_bytes_read = 0;
+        self.block_clocks = 0;
         self.current_block_size = Some(block_size);
 
         Ok(true)
@@ -136,6 +370,7 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
         if self.state == TapeState::Stop {
             return Ok(());
         }
+        self.block_clocks += clocks as u64;
 
         if self.delay > 0 {
             if clocks > self.delay {
@@ -158,9 +393,19 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
                     if !self.next_block()? {
                         self.state = TapeState::Stop;
                     } else {
-                        let first_byte = self
-                            .next_block_byte()?
-                            .ok_or(TapeLoadError::InvalidTapFile)?;
+                        let first_byte = match self.next_block_byte()? {
+                            Some(byte) => byte,
+                            None => {
+                                diag!(
+                                    warn,
+                                    TAPE,
+                                    "empty block #{} at offset {}",
+                                    self.block_index,
+                                    self.block_offset
+                                );
+                                return Err(TapeLoadError::InvalidTapFile.into());
+                            }
+                        };
 
                         // Select appropriate pulse count for Pilot sequence
                         let pulses_left = if first_byte == 0x00 {
@@ -168,6 +413,17 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
                         } else {
                             PILOT_PULSES_DATA
                         };
+                        diag!(
+                            debug,
+                            TAPE,
+                            "block #{}: flag {:#04X}, pilot {} x {}T, sync {}T + {}T",
+                            self.block_index,
+                            first_byte,
+                            pulses_left,
+                            PILOT_LENGTH,
+                            SYNC1_LENGTH,
+                            SYNC2_LENGTH
+                        );
                         self.curr_byte = first_byte;
                         self.curr_bit = true;
                         self.delay = PILOT_LENGTH;
@@ -233,6 +489,13 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
                     break 'state_machine;
                 }
                 TapeState::Pause => {
+                    diag!(
+                        trace,
+                        TAPE,
+                        "block #{} played, pause {}T",
+                        self.block_index,
+                        PAUSE_LENGTH
+                    );
                     self.curr_bit = !self.curr_bit;
                     self.delay = PAUSE_LENGTH;
                     // Next block or end of the tape
@@ -246,6 +509,9 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
     }
 
     fn stop(&mut self) {
+        if self.state != TapeState::Stop {
+            diag!(debug, TAPE, "stopped in block #{}", self.block_index);
+        }
         let state = self.state;
         self.prev_state = state;
         self.state = TapeState::Stop;
@@ -253,6 +519,7 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
 
     fn play(&mut self) {
         if self.state == TapeState::Stop {
+            diag!(debug, TAPE, "playing from block #{}", self.block_index);
             if self.prev_state == TapeState::Stop {
                 self.state = TapeState::Play;
             } else {
@@ -261,15 +528,78 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
         }
     }
 
+    fn is_playing(&self) -> bool {
+        self.state != TapeState::Stop
+    }
+
     fn rewind(&mut self) -> Result<()> {
+        // Drop any in-progress pulse sequence; if tape is playing, it will
+        // continue from the first block
+        if self.state != TapeState::Stop {
+            self.state = TapeState::Play;
+        }
+        self.prev_state = TapeState::Stop;
         self.curr_bit = false;
         self.curr_byte = 0x00;
         self.block_bytes_read = 0;
         self.buffer_offset = 0;
         self.current_block_size = None;
         self.delay = 0;
+        self.block_clocks = 0;
+        self.block_index = 0;
+        self.block_offset = 0;
+        self.next_block_offset = 0;
         self.asset.seek(SeekFrom::Start(0))?;
         self.tape_ended = false;
         Ok(())
     }
+
+    fn progress(&self) -> Option<TapeProgress> {
+        if self.total_blocks == 0 {
+            return None;
+        }
+        let fraction = if self.tape_ended {
+            1.0
+        } else {
+            let position = if self.block_index == 0 {
+                0
+            } else {
+                self.block_offset + 2 + self.block_bytes_read
+            };
+            (position as f32 / self.total_size as f32).min(1.0)
+        };
+        Some(TapeProgress {
+            block: self.block_index.saturating_sub(1),
+            total_blocks: self.total_blocks,
+            block_bytes: self.block_bytes_read,
+            block_length: self.current_block_size.unwrap_or(0),
+            fraction,
+        })
+    }
+
+    fn machine_hint(&self) -> Option<MemoryModel> {
+        self.machine_hint
+    }
+
+    fn time_clocks(&self) -> (u64, u64) {
+        let total = self.block_durations.iter().sum();
+        if self.tape_ended {
+            return (total, total);
+        }
+        let current = match self.block_index.checked_sub(1) {
+            Some(current) => current,
+            None => return (0, total),
+        };
+        let played: u64 = self.block_durations[..current].iter().sum();
+        let duration = self.block_durations.get(current).copied().unwrap_or(0);
+        let in_block = match self.current_block_size {
+            // Block which was read without playback (e.g. by fast load) is
+            // assumed to be played proportionally to the read bytes
+            Some(size) if self.block_clocks == 0 && size > 0 => {
+                duration * self.block_bytes_read as u64 / size as u64
+            }
+            _ => self.block_clocks,
+        };
+        (played + in_block.min(duration), total)
+    }
 }
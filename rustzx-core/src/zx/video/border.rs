@@ -3,11 +3,12 @@
 use crate::{
     host::{FrameBuffer, FrameBufferSource},
     zx::{
-        constants::{
-            BORDER_COLS, BORDER_ROWS, CLOCKS_PER_COL, PIXELS_PER_CLOCK, SCREEN_HEIGHT, SCREEN_WIDTH,
+        constants::{PIXELS_PER_CLOCK, SCREEN_HEIGHT, SCREEN_WIDTH},
+        machine::ZXSpecs,
+        video::{
+            colors::{ZXBrightness, ZXColor},
+            orientation::Orientation,
         },
-        machine::ZXMachine,
-        video::colors::{ZXBrightness, ZXColor},
     },
 };
 
@@ -38,40 +39,48 @@ impl BeamInfo {
 
 /// ZX Spectrum Border Device
 pub struct ZXBorder<FB: FrameBuffer> {
-    machine: ZXMachine,
+    specs: ZXSpecs,
     buffer: FB,
     beam_last: BeamInfo,
     border_changed: bool,
     beam_block: bool,
+    rendering_enabled: bool,
+    rendering_skipped: bool,
+    orientation: Orientation,
 }
 impl<FB: FrameBuffer> ZXBorder<FB> {
-    /// Returns new instance of border device
-    pub fn new(machine: ZXMachine, context: FB::Context) -> Self {
+    /// Returns new instance of border device, frame buffer is created with
+    /// dimensions of the rotated image
+    pub fn new(specs: ZXSpecs, orientation: Orientation, context: FB::Context) -> Self {
+        let (width, height) = FrameBufferSource::Border.dimensions();
+        let (width, height) = orientation.dimensions(width, height);
         ZXBorder {
-            machine,
-            buffer: FB::new(
-                SCREEN_WIDTH,
-                SCREEN_HEIGHT,
-                FrameBufferSource::Border,
-                context,
-            ),
+            specs,
+            buffer: FB::new(width, height, FrameBufferSource::Border, context),
             beam_last: BeamInfo::first_pixel(ZXColor::White),
             border_changed: true,
             beam_block: false,
+            rendering_enabled: true,
+            rendering_skipped: false,
+            orientation,
         }
     }
 
+    /// Re-creates frame buffer with the new `context`, whole border is
+    /// rendered again during the next frame
+    pub fn recreate_buffer(&mut self, context: FB::Context) {
+        let (width, height) = FrameBufferSource::Border.dimensions();
+        let (width, height) = self.orientation.dimensions(width, height);
+        self.buffer = FB::new(width, height, FrameBufferSource::Border, context);
+    }
+
     /// ULA draws 2 pixels per TState.
     /// This function helps to determine pixel, which will be rendered at specific time
     /// and bool value, which signals end of frame
     fn next_border_pixel(&self, clocks: usize) -> (usize, usize, bool) {
-        let specs = self.machine.specs();
-        // beginning of the first line (first pixel timing minus border lines
-        // minus left border columns)
-        let clocks_origin = specs.clocks_first_pixel
-            - 8 * BORDER_ROWS * specs.clocks_line
-            - BORDER_COLS * CLOCKS_PER_COL
-            + specs.clocks_ula_beam_shift;
+        let specs = &self.specs;
+        // beginning of the first line of the top border
+        let clocks_origin = specs.clocks_beam_origin();
         // return first pixel pos
         if clocks < clocks_origin {
             return (0, 0, false);
@@ -96,16 +105,38 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
         }
     }
 
+    /// Enables or disables border pixels rendering to the frame buffer. When
+    /// disabled, frame buffer keeps its last content
+    pub fn set_rendering_enabled(&mut self, value: bool) {
+        self.rendering_enabled = value;
+    }
+
+    /// Enables or disables border rendering during the frames started after
+    /// the call, independently from [ZXBorder::set_rendering_enabled]
+    pub fn set_rendering_skipped(&mut self, value: bool) {
+        self.rendering_skipped = value;
+    }
+
+    /// Changes ULA timings used for the beam position
+    pub fn set_specs(&mut self, specs: ZXSpecs) {
+        self.specs = specs;
+    }
+
     /// fills pixels from last pos to passed by arguments with
     fn fill_to(&mut self, line: usize, pixel: usize) {
+        if !self.rendering_enabled || self.rendering_skipped {
+            return;
+        }
         let last = self.beam_last;
         for p in (last.line * SCREEN_WIDTH + last.pixel)..(line * SCREEN_WIDTH + pixel) {
-            self.buffer.set_color(
+            let (x, y) = self.orientation.transform(
                 p % SCREEN_WIDTH,
                 p / SCREEN_WIDTH,
-                last.color,
-                ZXBrightness::Normal,
+                SCREEN_WIDTH,
+                SCREEN_HEIGHT,
             );
+            self.buffer
+                .set_color(x, y, last.color, ZXBrightness::Normal);
         }
     }
 
@@ -0,0 +1,84 @@
+use crate::{
+    error::BorderSizeError,
+    zx::constants::{CANVAS_HEIGHT, CANVAS_WIDTH, CANVAS_X, CANVAS_Y, SCREEN_WIDTH},
+};
+
+/// Visible border thickness around the canvas in pixels. Used to crop full
+/// `SCREEN_WIDTH` x `SCREEN_HEIGHT` border buffer for display or capture
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BorderSize {
+    horizontal: usize,
+    vertical: usize,
+}
+
+impl BorderSize {
+    /// Full border as rendered by the emulator
+    pub const FULL: Self = Self {
+        horizontal: CANVAS_X,
+        vertical: CANVAS_Y,
+    };
+    /// Canvas only
+    pub const NONE: Self = Self {
+        horizontal: 0,
+        vertical: 0,
+    };
+
+    /// Creates border size with the given left/right and top/bottom thickness.
+    /// Thickness should not exceed the rendered border (`CANVAS_X`/`CANVAS_Y`)
+    pub fn new(horizontal: usize, vertical: usize) -> Result<Self, BorderSizeError> {
+        if horizontal > CANVAS_X || vertical > CANVAS_Y {
+            return Err(BorderSizeError::TooLarge);
+        }
+        Ok(Self {
+            horizontal,
+            vertical,
+        })
+    }
+
+    /// Left and right border thickness
+    pub fn horizontal(&self) -> usize {
+        self.horizontal
+    }
+
+    /// Top and bottom border thickness
+    pub fn vertical(&self) -> usize {
+        self.vertical
+    }
+
+    /// Width of the cropped image
+    pub fn width(&self) -> usize {
+        CANVAS_WIDTH + self.horizontal * 2
+    }
+
+    /// Height of the cropped image
+    pub fn height(&self) -> usize {
+        CANVAS_HEIGHT + self.vertical * 2
+    }
+
+    /// Position of the cropped area in the full border buffer
+    pub fn offset(&self) -> (usize, usize) {
+        (CANVAS_X - self.horizontal, CANVAS_Y - self.vertical)
+    }
+
+    /// Returns rows of the cropped area from the full border buffer with the
+    /// given pixel size in bytes (e.g. 4 for RGBA buffer)
+    pub fn crop_rows<'a>(
+        &self,
+        buffer: &'a [u8],
+        bytes_per_pixel: usize,
+    ) -> impl Iterator<Item = &'a [u8]> {
+        let (x, y) = self.offset();
+        let (width, height) = (self.width(), self.height());
+        buffer
+            .chunks_exact(SCREEN_WIDTH * bytes_per_pixel)
+            .skip(y)
+            .take(height)
+            .map(move |row| &row[x * bytes_per_pixel..(x + width) * bytes_per_pixel])
+    }
+}
+
+impl Default for BorderSize {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
@@ -7,7 +7,7 @@ pub enum ZXBrightness {
 
 /// ZX Spectrum color enum
 /// Constructs self from 3-bit value
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ZXColor {
     Black = 0,
     Blue = 1,
@@ -58,7 +58,7 @@ impl From<ZXColor> for u8 {
 /// It contains information about ink, paper color,
 /// flash attribute and brightness
 #[derive(Clone, Copy)]
-pub(crate) struct ZXAttribute {
+pub struct ZXAttribute {
     pub ink: ZXColor,
     pub paper: ZXColor,
     pub brightness: ZXBrightness,
@@ -80,6 +80,14 @@ impl ZXAttribute {
         }
     }
 
+    /// Converts self back to the attribute byte
+    pub fn to_byte(&self) -> u8 {
+        u8::from(self.ink)
+            | (u8::from(self.paper) << 3)
+            | ((matches!(self.brightness, ZXBrightness::Bright) as u8) << 6)
+            | ((self.flash as u8) << 7)
+    }
+
     /// Returns active color of pixel in current attribute
     pub fn active_color(&self, state: bool, enable_flash: bool) -> ZXColor {
         if state ^ (self.flash && enable_flash) {
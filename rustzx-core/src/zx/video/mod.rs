@@ -4,4 +4,10 @@
 pub(crate) mod border;
 pub(crate) mod screen;
 
+pub mod border_size;
 pub mod colors;
+pub mod orientation;
+pub mod screen_snapshot;
+pub mod ulaplus;
+
+pub use crate::utils::screen::bitmap_line_addr;
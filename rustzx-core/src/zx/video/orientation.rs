@@ -0,0 +1,92 @@
+//! Orientation of the rendered image for displays mounted rotated, e.g.
+//! portrait LCDs of handhelds. Orientation is applied when pixels are
+//! written to the frame buffers, so frontends get the image ready to be
+//! shown without an additional pass over it
+
+/// Clockwise rotation of the image
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+/// Horizontal mirroring of the image followed by the rotation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Orientation {
+    pub rotation: Rotation,
+    pub mirror: bool,
+}
+
+impl Orientation {
+    pub fn is_identity(self) -> bool {
+        self == Self::default()
+    }
+
+    /// Returns `(width, height)` of the `width` x `height` image after the
+    /// rotation
+    pub fn dimensions(self, width: usize, height: usize) -> (usize, usize) {
+        match self.rotation {
+            Rotation::None | Rotation::Cw180 => (width, height),
+            Rotation::Cw90 | Rotation::Cw270 => (height, width),
+        }
+    }
+
+    /// Returns position of the pixel (`x`, `y`) of the `width` x `height`
+    /// image after the transformation
+    pub fn transform(self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        let x = if self.mirror { width - 1 - x } else { x };
+        match self.rotation {
+            Rotation::None => (x, y),
+            Rotation::Cw90 => (height - 1 - y, x),
+            Rotation::Cw180 => (width - 1 - x, height - 1 - y),
+            Rotation::Cw270 => (y, width - 1 - x),
+        }
+    }
+
+    /// Returns `(x, y, width, height)` rect of the `width` x `height` image
+    /// after the transformation
+    pub fn transform_rect(
+        self,
+        (x, y, rect_width, rect_height): (usize, usize, usize, usize),
+        width: usize,
+        height: usize,
+    ) -> (usize, usize, usize, usize) {
+        let (x0, y0) = self.transform(x, y, width, height);
+        let (x1, y1) = self.transform(x + rect_width - 1, y + rect_height - 1, width, height);
+        (
+            x0.min(x1),
+            y0.min(y1),
+            x0.abs_diff(x1) + 1,
+            y0.abs_diff(y1) + 1,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corners_are_transformed() {
+        let orientation = |rotation, mirror| Orientation { rotation, mirror };
+        let cases = [
+            (orientation(Rotation::None, false), (0, 0), (3, 1)),
+            (orientation(Rotation::Cw90, false), (1, 0), (0, 3)),
+            (orientation(Rotation::Cw180, false), (3, 1), (0, 0)),
+            (orientation(Rotation::Cw270, false), (0, 3), (1, 0)),
+            (orientation(Rotation::None, true), (3, 0), (0, 1)),
+            (orientation(Rotation::Cw90, true), (1, 3), (0, 0)),
+        ];
+        // Top left and bottom right corners of 4x2 image
+        for (orientation, top_left, bottom_right) in cases {
+            assert_eq!(orientation.transform(0, 0, 4, 2), top_left);
+            assert_eq!(orientation.transform(3, 1, 4, 2), bottom_right);
+        }
+        let rotated = orientation(Rotation::Cw90, false);
+        assert_eq!(rotated.dimensions(4, 2), (2, 4));
+        assert_eq!(rotated.transform_rect((1, 0, 2, 1), 4, 2), (1, 1, 1, 2));
+    }
+}
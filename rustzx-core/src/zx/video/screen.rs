@@ -2,17 +2,30 @@
 //! *block* - is 8x1 pxels stripe.
 use crate::{
     host::{FrameBuffer, FrameBufferSource},
-    utils::screen::{attr_col_rel, attr_row_rel, bitmap_col_rel, bitmap_line_rel},
+    utils::{
+        boxed_array,
+        screen::{attr_col_rel, attr_row_rel, bitmap_col_rel, bitmap_line_rel},
+    },
     zx::{
         constants::{
             ATTR_BASE_REL, ATTR_COLS, ATTR_MAX_REL, ATTR_ROWS, BITMAP_MAX_REL, CANVAS_HEIGHT,
             CANVAS_WIDTH, CLOCKS_PER_COL,
         },
-        machine::ZXMachine,
-        video::colors::ZXAttribute,
+        machine::{MemoryModel, ZXMachine, ZXSpecs},
+        video::{
+            colors::{ZXAttribute, ZXBrightness, ZXColor},
+            orientation::Orientation,
+            screen_snapshot::ScreenSnapshot,
+            ulaplus::UlaPlus,
+        },
     },
 };
-use alloc::boxed::Box;
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+/// Size of the character cell of [ZXScreen::to_ascii] in pixels
+const ASCII_CELL_SIZE: usize = 4;
+/// Characters from the lightest to the darkest
+const ASCII_RAMP: &[u8] = b" .:-=+*#%@";
 
 /// Represents how much 8x1 have been already **rendered**.
 #[derive(PartialEq, Eq, Debug)]
@@ -27,10 +40,8 @@ impl BlocksCount {
         BlocksCount { lines, columns }
     }
 
-    /// Constructs self from clocks count, taking into account machine type
-    pub fn from_clocks(clocks: usize, machine: ZXMachine) -> BlocksCount {
-        // get reference to specs for less words
-        let specs = machine.specs();
+    /// Constructs self from clocks count, taking into account machine timings
+    pub fn from_clocks(clocks: usize, specs: &ZXSpecs) -> BlocksCount {
         let mut lines;
         let mut columns;
         if clocks < specs.clocks_ula_read_origin {
@@ -74,6 +85,93 @@ impl BlocksCount {
     }
 }
 
+/// Bounds of the changed blocks, in block coordinates (inclusive)
+#[derive(Clone, Copy)]
+struct DirtyBlocks {
+    first_line: usize,
+    last_line: usize,
+    first_col: usize,
+    last_col: usize,
+}
+
+impl DirtyBlocks {
+    fn new(line: usize, col: usize) -> Self {
+        Self {
+            first_line: line,
+            last_line: line,
+            first_col: col,
+            last_col: col,
+        }
+    }
+
+    fn extend(&mut self, line: usize, col: usize) {
+        self.first_line = self.first_line.min(line);
+        self.last_line = self.last_line.max(line);
+        self.first_col = self.first_col.min(col);
+        self.last_col = self.last_col.max(col);
+    }
+
+    /// Returns rect as (x, y, width, height) in pixels
+    fn to_rect(self) -> (usize, usize, usize, usize) {
+        (
+            self.first_col * 8,
+            self.first_line,
+            (self.last_col - self.first_col + 1) * 8,
+            self.last_line - self.first_line + 1,
+        )
+    }
+}
+
+/// Returns value which uniquely identifies rendered 8x1 block content.
+/// Zero is never returned, so it can be used for the not-yet-rendered block
+fn block_key(bitmap: u8, attr: &ZXAttribute, flash: bool) -> u32 {
+    const RENDERED: u32 = 1 << 24;
+    let paper = attr.active_color(false, flash) as u32;
+    let ink = attr.active_color(true, flash) as u32;
+    RENDERED | bitmap as u32 | (paper << 8) | (ink << 12) | ((attr.brightness as u32) << 16)
+}
+
+/// Same as [block_key], but for the block rendered via ULAplus palette
+fn ulaplus_block_key(bitmap: u8, attr: &ZXAttribute, ulaplus: &UlaPlus) -> u32 {
+    const RENDERED_ULAPLUS: u32 = 3 << 24;
+    let paper = ulaplus.active_color(attr, false) as u32;
+    let ink = ulaplus.active_color(attr, true) as u32;
+    RENDERED_ULAPLUS | bitmap as u32 | (paper << 8) | (ink << 16)
+}
+
+/// 4x4 masks of the paper pixels drawn with the flipped brightness when paper
+/// patterns are enabled, indexed by the paper color. Row 0 is in the highest
+/// nibble, column 0 in its highest bit. Black is the same in both brightness
+/// levels, so it has no pattern
+const PAPER_PATTERNS: [u16; 8] = [
+    0x0000, // black: none
+    0x8020, // blue: sparse dots
+    0xF000, // red: horizontal lines
+    0x8888, // magenta: vertical lines
+    0x8421, // green: diagonal lines
+    0x1248, // cyan: anti-diagonal lines
+    0xA0A0, // yellow: dense dots
+    0xF888, // white: grid
+];
+
+/// Returns brightness of the paper pixel at canvas position (`x`, `y`)
+/// with the pattern of the `paper` color applied
+fn patterned_paper_brightness(
+    paper: ZXColor,
+    brightness: ZXBrightness,
+    x: usize,
+    y: usize,
+) -> ZXBrightness {
+    let mask = 0x8000 >> ((y % 4) * 4 + x % 4);
+    if PAPER_PATTERNS[paper as usize] & mask == 0 {
+        return brightness;
+    }
+    match brightness {
+        ZXBrightness::Normal => ZXBrightness::Bright,
+        ZXBrightness::Bright => ZXBrightness::Normal,
+    }
+}
+
 /// Represents Single memory bank of screen
 struct ScreenBank {
     pub attributes: Box<[ZXAttribute; ATTR_COLS * ATTR_ROWS]>,
@@ -83,49 +181,135 @@ struct ScreenBank {
 /// Represents ZXSpectrum emulated mid part of screen (canvas)
 pub struct ZXScreen<FB: FrameBuffer> {
     machine: ZXMachine,
+    specs: ZXSpecs,
     last_blocks: BlocksCount,
     flash: bool,
-    frame_counter: usize,
+    // flash state used for the last completed frame in `buffer`
+    buffer_flash: bool,
+    frame_counter: u64,
     buffer: FB,
     back_buffer: FB,
     banks: [ScreenBank; 2],
     active_bank: usize,
+    // content of the blocks rendered during the last frame, see `block_key`
+    rendered_blocks: Box<[u32; ATTR_COLS * CANVAS_HEIGHT]>,
+    dirty_blocks: Option<DirtyBlocks>,
+    dirty_rect: Option<(usize, usize, usize, usize)>,
+    // frame is emulated without rendering, `buffer` keeps the last rendered frame
+    skip_rendering: bool,
+    // next rendered frame is reported as fully dirty
+    redraw_pending: bool,
+    ulaplus: Option<UlaPlus>,
+    orientation: Orientation,
+    paper_patterns: bool,
 }
 
 impl<FB: FrameBuffer> ZXScreen<FB> {
-    /// Constructs new canvas of `machine`
-    pub fn new(machine: ZXMachine, context: FB::Context) -> Self {
+    /// Constructs new canvas of `machine` with the given timings. Frame
+    /// buffers are created with dimensions of the rotated canvas
+    pub fn new(
+        machine: ZXMachine,
+        specs: ZXSpecs,
+        orientation: Orientation,
+        context: FB::Context,
+    ) -> Self {
+        let (width, height) = FrameBufferSource::Screen.dimensions();
+        let (width, height) = orientation.dimensions(width, height);
         Self {
             machine,
+            specs,
             last_blocks: BlocksCount::new(0, 0),
             flash: false,
+            buffer_flash: false,
             frame_counter: 0,
-            buffer: FB::new(
-                CANVAS_WIDTH,
-                CANVAS_HEIGHT,
-                FrameBufferSource::Screen,
-                context.clone(),
-            ),
-            back_buffer: FB::new(
-                CANVAS_WIDTH,
-                CANVAS_HEIGHT,
-                FrameBufferSource::Screen,
-                context,
-            ),
+            buffer: FB::new(width, height, FrameBufferSource::Screen, context.clone()),
+            back_buffer: FB::new(width, height, FrameBufferSource::Screen, context),
             banks: [
                 ScreenBank {
-                    attributes: Box::new([ZXAttribute::from_byte(0); ATTR_COLS * ATTR_ROWS]),
-                    bitmap: Box::new([0; ATTR_COLS * CANVAS_HEIGHT]),
+                    attributes: boxed_array(ZXAttribute::from_byte(0)),
+                    bitmap: boxed_array(0),
                 },
                 ScreenBank {
-                    attributes: Box::new([ZXAttribute::from_byte(0); ATTR_COLS * ATTR_ROWS]),
-                    bitmap: Box::new([0; ATTR_COLS * CANVAS_HEIGHT]),
+                    attributes: boxed_array(ZXAttribute::from_byte(0)),
+                    bitmap: boxed_array(0),
                 },
             ],
             active_bank: 0,
+            rendered_blocks: boxed_array(0),
+            dirty_blocks: None,
+            dirty_rect: None,
+            skip_rendering: false,
+            redraw_pending: false,
+            ulaplus: None,
+            orientation,
+            paper_patterns: false,
         }
     }
 
+    /// Returns width of the frame buffer, which is the canvas height when
+    /// the canvas is rotated by 90 or 270 degrees
+    pub fn width(&self) -> usize {
+        self.orientation.dimensions(CANVAS_WIDTH, CANVAS_HEIGHT).0
+    }
+
+    /// Returns height of the frame buffer, see [ZXScreen::width]
+    pub fn height(&self) -> usize {
+        self.orientation.dimensions(CANVAS_WIDTH, CANVAS_HEIGHT).1
+    }
+
+    /// Enables or disables ULAplus palette extension, palette is reset
+    pub fn set_ulaplus_enabled(&mut self, value: bool) {
+        self.ulaplus = value.then(UlaPlus::default);
+    }
+
+    /// Resets flash phase and ULAplus registers, as on the machine power on
+    pub fn reset(&mut self) {
+        self.flash = false;
+        self.frame_counter = 0;
+        self.set_ulaplus_enabled(self.ulaplus.is_some());
+    }
+
+    /// Enables or disables paper patterns, see
+    /// [crate::RustzxSettings::paper_patterns]. The next rendered frame is
+    /// reported as fully dirty
+    pub fn set_paper_patterns(&mut self, value: bool) {
+        self.paper_patterns = value;
+        self.redraw_pending = true;
+    }
+
+    pub fn ulaplus(&self) -> Option<&UlaPlus> {
+        self.ulaplus.as_ref()
+    }
+
+    /// Returns ULAplus registers for the port access. Screen should be
+    /// rendered up to the access time, so palette changes affect only the
+    /// pixels drawn after them
+    pub fn ulaplus_mut(&mut self) -> Option<&mut UlaPlus> {
+        self.ulaplus.as_mut()
+    }
+
+    /// Changes ULA timings used for the screen memory reads
+    pub fn set_specs(&mut self, specs: ZXSpecs) {
+        self.specs = specs;
+    }
+
+    /// Re-creates frame buffers with the new `context`. Frame buffer stays
+    /// blank until the next completed frame, which is reported as fully dirty
+    pub fn recreate_buffers(&mut self, context: FB::Context) {
+        let (width, height) = (self.width(), self.height());
+        self.buffer = FB::new(width, height, FrameBufferSource::Screen, context.clone());
+        self.back_buffer = FB::new(width, height, FrameBufferSource::Screen, context);
+        self.redraw_pending = true;
+    }
+
+    /// Enables or disables rendering of the frames started after the call.
+    /// Skipped frames keep the last rendered frame in the frame buffer, the
+    /// next rendered frame is reported as fully dirty (see
+    /// [ZXScreen::dirty_rect])
+    pub fn set_rendering_skipped(&mut self, value: bool) {
+        self.skip_rendering = value;
+    }
+
     /// changes flash switch
     fn switch_flash(&mut self) {
         self.flash = !self.flash;
@@ -133,10 +317,10 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
 
     /// transforms zx spectrum bank to local index
     fn local_bank(&self, bank: usize) -> Option<usize> {
-        match self.machine {
-            ZXMachine::Sinclair48K if bank == 0 => Some(0),
-            ZXMachine::Sinclair128K if bank == 5 => Some(0),
-            ZXMachine::Sinclair128K if bank == 7 => Some(1),
+        match self.machine.memory_model() {
+            MemoryModel::Sinclair48K if bank == 0 => Some(0),
+            MemoryModel::Sinclair128K | MemoryModel::SinclairPlus3 if bank == 5 => Some(0),
+            MemoryModel::Sinclair128K | MemoryModel::SinclairPlus3 if bank == 7 => Some(1),
             _ => None,
         }
     }
@@ -152,10 +336,12 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
     /// `clocks` - current  clocks count form frame start.
     /// if clocks < previous call clocks then discard processing
     pub fn process_clocks(&mut self, clocks: usize) {
-        let blocks = BlocksCount::from_clocks(clocks, self.machine);
+        let blocks = BlocksCount::from_clocks(clocks, &self.specs);
         // so, let's count of 8x1 blocks, which passed.
         let count = blocks.passed_from(&self.last_blocks);
-        if count > 0 {
+        if count > 0 && self.skip_rendering {
+            self.last_blocks = blocks;
+        } else if count > 0 {
             // fill pixels from prev to current
             let prev_block = self.last_blocks.lines * ATTR_COLS + self.last_blocks.columns;
             let curr_block = blocks.lines * ATTR_COLS + blocks.columns;
@@ -167,15 +353,58 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
                 let attr_row = block / (ATTR_COLS * 8);
                 let attr_col = block % ATTR_COLS;
                 let attr = self.banks[self.active_bank].attributes[attr_row * ATTR_COLS + attr_col];
-                for pixel in 0..8 {
-                    // from most significant bit
-                    let state = ((bitmap << pixel) & 0x80) != 0;
-                    self.back_buffer.set_color(
-                        (block % ATTR_COLS) * 8 + pixel,
-                        block / ATTR_COLS,
-                        attr.active_color(state, self.flash),
-                        attr.brightness,
-                    );
+                let ulaplus = self.ulaplus.as_ref().filter(|u| u.palette_enabled());
+                let key = match ulaplus {
+                    Some(ulaplus) => ulaplus_block_key(bitmap, &attr, ulaplus),
+                    None => block_key(bitmap, &attr, self.flash),
+                };
+                if self.rendered_blocks[block] != key {
+                    self.rendered_blocks[block] = key;
+                    let (line, col) = (block / ATTR_COLS, block % ATTR_COLS);
+                    match &mut self.dirty_blocks {
+                        Some(dirty) => dirty.extend(line, col),
+                        None => self.dirty_blocks = Some(DirtyBlocks::new(line, col)),
+                    }
+                }
+                let (x, y) = ((block % ATTR_COLS) * 8, block / ATTR_COLS);
+                let orientation = self.orientation;
+                let transform = |x| orientation.transform(x, y, CANVAS_WIDTH, CANVAS_HEIGHT);
+                match ulaplus {
+                    Some(ulaplus) => {
+                        for pixel in 0..8 {
+                            // from most significant bit
+                            let state = ((bitmap << pixel) & 0x80) != 0;
+                            let (x, y) = transform(x + pixel);
+                            self.back_buffer.set_ulaplus_color(
+                                x,
+                                y,
+                                ulaplus.active_color(&attr, state),
+                            );
+                        }
+                    }
+                    None if orientation.is_identity() && !self.paper_patterns => {
+                        self.back_buffer.set_block(
+                            x,
+                            y,
+                            bitmap,
+                            attr.active_color(true, self.flash),
+                            attr.active_color(false, self.flash),
+                            attr.brightness,
+                        );
+                    }
+                    None => {
+                        for pixel in 0..8 {
+                            let state = ((bitmap << pixel) & 0x80) != 0;
+                            let color = attr.active_color(state, self.flash);
+                            let brightness = if self.paper_patterns && !state {
+                                patterned_paper_brightness(color, attr.brightness, x + pixel, y)
+                            } else {
+                                attr.brightness
+                            };
+                            let (x, y) = transform(x + pixel);
+                            self.back_buffer.set_color(x, y, color, brightness);
+                        }
+                    }
                 }
             }
             // change last block to current
@@ -185,17 +414,28 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
 
     /// starts new frame
     pub fn new_frame(&mut self) {
-        // post finished bitmap to second buffer (all not-rendered part will be updated)
-        {
+        if self.skip_rendering {
+            self.dirty_rect = None;
+            self.redraw_pending = true;
+        } else {
+            // post finished bitmap to second buffer (all not-rendered part will be updated)
             let Self {
                 buffer,
                 back_buffer,
                 ..
             } = self;
             core::mem::swap(buffer, back_buffer);
+            let orientation = self.orientation;
+            self.dirty_rect = self.dirty_blocks.take().map(|dirty| {
+                orientation.transform_rect(dirty.to_rect(), CANVAS_WIDTH, CANVAS_HEIGHT)
+            });
+            if core::mem::take(&mut self.redraw_pending) {
+                self.dirty_rect = Some((0, 0, self.width(), self.height()));
+            }
+            self.buffer_flash = self.flash;
         }
         self.last_blocks = BlocksCount::new(0, 0);
-        if self.frame_counter % 16 == 0 {
+        if self.frame_counter.is_multiple_of(16) {
             self.switch_flash();
         }
         self.frame_counter += 1;
@@ -224,7 +464,81 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
         }
     }
 
+    /// Returns true while flashing attributes are shown with swapped ink
+    /// and paper. State toggles each 16 frames
+    pub fn flash_state(&self) -> bool {
+        self.flash
+    }
+
+    /// Returns flash state used for the last completed frame (see
+    /// [ZXScreen::frame_buffer])
+    pub fn frame_buffer_flash_state(&self) -> bool {
+        self.buffer_flash
+    }
+
+    /// Returns count of frames started since the screen creation
+    pub fn frame_counter(&self) -> u64 {
+        self.frame_counter
+    }
+
     pub fn frame_buffer(&self) -> &FB {
         &self.buffer
     }
+
+    /// Returns bounding rect (x, y, width, height) of the pixels which differ
+    /// between the last completed frame and the frame before it, in the
+    /// frame buffer coordinates
+    pub fn dirty_rect(&self) -> Option<(usize, usize, usize, usize)> {
+        self.dirty_rect
+    }
+
+    /// Copies bitmap and attributes of the displayed screen bank
+    pub fn snapshot(&self) -> ScreenSnapshot {
+        let bank = &self.banks[self.active_bank];
+        ScreenSnapshot::new(
+            &bank.bitmap[..],
+            bank.attributes.iter().map(ZXAttribute::to_byte),
+        )
+    }
+
+    /// Returns `(row, col)` of the 8x8 cells of the displayed screen bank
+    /// which differ from the `previous` snapshot, see
+    /// [ScreenSnapshot::diff_cells]
+    pub fn diff_cells(&self, previous: &ScreenSnapshot) -> Vec<(usize, usize)> {
+        self.snapshot().diff_cells(previous)
+    }
+
+    /// Renders screen memory as 64x48 characters, each one covers 4x4 pixels.
+    /// Darker cells are drawn with denser characters, so the default black
+    /// ink on white paper looks like text on a sheet of paper
+    pub fn to_ascii(&self) -> String {
+        let bank = &self.banks[self.active_bank];
+        let cols = CANVAS_WIDTH / ASCII_CELL_SIZE;
+        let rows = CANVAS_HEIGHT / ASCII_CELL_SIZE;
+        // brightness levels are 0..=15: color index is ordered by brightness
+        // already, bright attribute adds a half step
+        let max_sum = 15 * ASCII_CELL_SIZE * ASCII_CELL_SIZE;
+        let mut out = String::with_capacity((cols + 1) * rows);
+        for row in 0..rows {
+            for col in 0..cols {
+                let x_start = col * ASCII_CELL_SIZE;
+                let mut sum = 0;
+                for y in row * ASCII_CELL_SIZE..(row + 1) * ASCII_CELL_SIZE {
+                    let attr = bank.attributes[(y / 8) * ATTR_COLS + x_start / 8];
+                    let bitmap = bank.bitmap[y * ATTR_COLS + x_start / 8];
+                    for x in x_start..x_start + ASCII_CELL_SIZE {
+                        let state = bitmap & (0x80 >> (x % 8)) != 0;
+                        let color = u8::from(attr.active_color(state, self.flash)) as usize;
+                        let bright = color != 0 && matches!(attr.brightness, ZXBrightness::Bright);
+                        sum += color * 2 + bright as usize;
+                    }
+                }
+                let darkness = max_sum - sum;
+                let index = darkness * (ASCII_RAMP.len() - 1) / max_sum;
+                out.push(ASCII_RAMP[index] as char);
+            }
+            out.push('\n');
+        }
+        out
+    }
 }
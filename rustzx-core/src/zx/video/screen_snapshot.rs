@@ -0,0 +1,59 @@
+//! Cheap copies of the displayed screen contents for cell-level comparison
+//! between frames, e.g. to detect motion in the screen region in automated
+//! game tests. Snapshot takes ~7K, so hundreds of them can be kept
+use crate::{
+    utils::boxed_array,
+    zx::constants::{ATTR_COLS, ATTR_ROWS, CANVAS_HEIGHT},
+};
+use alloc::{boxed::Box, vec::Vec};
+
+const BITMAP_SIZE: usize = ATTR_COLS * CANVAS_HEIGHT;
+const SNAPSHOT_SIZE: usize = BITMAP_SIZE + ATTR_COLS * ATTR_ROWS;
+
+/// Bitmap and attributes of the screen. Unlike the screen memory, bitmap
+/// lines are stored in the top-to-bottom order
+#[derive(Clone, PartialEq, Eq)]
+pub struct ScreenSnapshot {
+    data: Box<[u8; SNAPSHOT_SIZE]>,
+}
+
+impl ScreenSnapshot {
+    pub(crate) fn new(bitmap: &[u8], attributes: impl Iterator<Item = u8>) -> Self {
+        let mut data = boxed_array::<u8, SNAPSHOT_SIZE>(0);
+        data[..BITMAP_SIZE].copy_from_slice(bitmap);
+        data[BITMAP_SIZE..]
+            .iter_mut()
+            .zip(attributes)
+            .for_each(|(dest, attr)| *dest = attr);
+        Self { data }
+    }
+
+    /// Returns 192 lines of 32 bytes, top to bottom
+    pub fn bitmap(&self) -> &[u8] {
+        &self.data[..BITMAP_SIZE]
+    }
+
+    /// Returns 24 rows of 32 attributes
+    pub fn attributes(&self) -> &[u8] {
+        &self.data[BITMAP_SIZE..]
+    }
+
+    /// Returns true if the 8x8 cell at (`row`, `col`) has the same bitmap
+    /// and attribute in both snapshots
+    pub fn cell_eq(&self, other: &ScreenSnapshot, row: usize, col: usize) -> bool {
+        let attr = BITMAP_SIZE + row * ATTR_COLS + col;
+        self.data[attr] == other.data[attr]
+            && (row * 8..row * 8 + 8)
+                .map(|line| line * ATTR_COLS + col)
+                .all(|offset| self.data[offset] == other.data[offset])
+    }
+
+    /// Returns `(row, col)` of the 8x8 cells which differ from the
+    /// `previous` snapshot, in row-major order
+    pub fn diff_cells(&self, previous: &ScreenSnapshot) -> Vec<(usize, usize)> {
+        (0..ATTR_ROWS)
+            .flat_map(|row| (0..ATTR_COLS).map(move |col| (row, col)))
+            .filter(|(row, col)| !self.cell_eq(previous, *row, *col))
+            .collect()
+    }
+}
@@ -0,0 +1,119 @@
+//! ULAplus palette extension: 64 colors selected from 256 (GRB 3:3:2),
+//! programmed via ports `0xBF3B` (register select) and `0xFF3B` (data).
+//! In palette mode FLASH and BRIGHT bits of the attribute select one of 4
+//! color lookup tables (16 entries, 8 ink and 8 paper colors) instead of
+//! flashing and brightness.
+//!
+//! Screen is rendered in sync with the beam, so palette changes made
+//! mid-frame affect only the part of the screen drawn after them, as on
+//! real hardware (e.g. gradient skies made by rewriting palette entries
+//! during the frame). Border keeps the standard colors
+use crate::zx::video::colors::{ZXAttribute, ZXBrightness, ZXColor};
+
+/// Register select port
+pub const PORT_ULAPLUS_REGISTER: u16 = 0xBF3B;
+/// Data port
+pub const PORT_ULAPLUS_DATA: u16 = 0xFF3B;
+/// Count of the palette entries
+pub const ULAPLUS_PALETTE_SIZE: usize = 64;
+
+const GROUP_MASK: u8 = 0xC0;
+const GROUP_PALETTE: u8 = 0x00;
+const GROUP_MODE: u8 = 0x40;
+const MODE_PALETTE_ENABLED: u8 = 0x01;
+/// Entries of single color lookup table
+const CLUT_SIZE: u8 = 16;
+/// Offset of the paper colors in the color lookup table
+const CLUT_PAPER: u8 = 8;
+
+/// State of the ULAplus registers
+#[derive(Clone)]
+pub(crate) struct UlaPlus {
+    register: u8,
+    mode: u8,
+    palette: [u8; ULAPLUS_PALETTE_SIZE],
+}
+
+impl Default for UlaPlus {
+    fn default() -> Self {
+        Self {
+            register: 0,
+            mode: 0,
+            palette: [0; ULAPLUS_PALETTE_SIZE],
+        }
+    }
+}
+
+impl UlaPlus {
+    pub fn handles_port(port: u16) -> bool {
+        port == PORT_ULAPLUS_REGISTER || port == PORT_ULAPLUS_DATA
+    }
+
+    pub fn write(&mut self, port: u16, data: u8) {
+        if port == PORT_ULAPLUS_REGISTER {
+            self.register = data;
+            return;
+        }
+        match self.register & GROUP_MASK {
+            GROUP_PALETTE => self.palette[(self.register & !GROUP_MASK) as usize] = data,
+            GROUP_MODE => self.mode = data,
+            _ => {}
+        }
+    }
+
+    pub fn read(&self, port: u16) -> u8 {
+        if port == PORT_ULAPLUS_REGISTER {
+            return self.register;
+        }
+        match self.register & GROUP_MASK {
+            GROUP_PALETTE => self.palette[(self.register & !GROUP_MASK) as usize],
+            GROUP_MODE => self.mode,
+            _ => 0xFF,
+        }
+    }
+
+    /// Returns true if attributes are rendered via palette
+    pub fn palette_enabled(&self) -> bool {
+        self.mode & MODE_PALETTE_ENABLED != 0
+    }
+
+    pub fn palette(&self) -> &[u8; ULAPLUS_PALETTE_SIZE] {
+        &self.palette
+    }
+
+    /// Returns GRB 3:3:2 color of the pixel with the given attribute
+    pub fn active_color(&self, attr: &ZXAttribute, state: bool) -> u8 {
+        let clut = ((attr.flash as u8) << 1) | attr.brightness as u8;
+        let index = if state {
+            clut * CLUT_SIZE + u8::from(attr.ink)
+        } else {
+            clut * CLUT_SIZE + CLUT_PAPER + u8::from(attr.paper)
+        };
+        self.palette[index as usize]
+    }
+}
+
+/// Converts GRB 3:3:2 color to 8-bit RGB. Missing low bit of the blue
+/// component is the OR of the other two, as in ULAplus specification
+pub fn grb_to_rgb(grb: u8) -> [u8; 3] {
+    let expand = |value: u8| (value << 5) | (value << 2) | (value >> 1);
+    let blue = grb & 0x03;
+    [
+        expand((grb >> 2) & 0x07),
+        expand(grb >> 5),
+        expand((blue << 1) | (blue != 0) as u8),
+    ]
+}
+
+/// Returns standard color closest to the GRB 3:3:2 color, used by frame
+/// buffers which support only the standard palette
+pub fn nearest_standard_color(grb: u8) -> (ZXColor, ZXBrightness) {
+    let [red, green, blue] = grb_to_rgb(grb);
+    let bits = ((green >= 0x80) as u8) << 2 | ((red >= 0x80) as u8) << 1 | (blue >= 0x80) as u8;
+    let brightness = if red.max(green).max(blue) >= 0xE0 {
+        ZXBrightness::Bright
+    } else {
+        ZXBrightness::Normal
+    };
+    (ZXColor::from_bits(bits), brightness)
+}
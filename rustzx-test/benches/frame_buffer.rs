@@ -0,0 +1,68 @@
+//! Compares per-pixel and per-block rendering of the full canvas into the
+//! frame buffer, block writes should stay several times faster
+use std::time::{Duration, Instant};
+
+use rustzx_core::{
+    host::{FrameBuffer, FrameBufferSource},
+    zx::{
+        constants::{CANVAS_HEIGHT, CANVAS_WIDTH},
+        video::colors::{ZXBrightness, ZXColor},
+    },
+};
+use rustzx_utils::indexed::{IndexedFrameBuffer, IndexedFrameBufferContext};
+
+const FRAMES: usize = 2000;
+
+fn redraw(
+    name: &str,
+    render_block: impl Fn(&mut IndexedFrameBuffer, usize, usize, u8),
+) -> Duration {
+    let mut buffer = IndexedFrameBuffer::new(
+        CANVAS_WIDTH,
+        CANVAS_HEIGHT,
+        FrameBufferSource::Screen,
+        IndexedFrameBufferContext::default(),
+    );
+    let start = Instant::now();
+    for frame in 0..FRAMES {
+        for y in 0..CANVAS_HEIGHT {
+            for x in (0..CANVAS_WIDTH).step_by(8) {
+                render_block(&mut buffer, x, y, (frame + x + y) as u8);
+            }
+        }
+    }
+    let duration = start.elapsed() / FRAMES as u32;
+    println!(
+        "`{}` full frame redraw took {}us",
+        name,
+        duration.as_micros()
+    );
+    duration
+}
+
+fn main() {
+    let per_pixel = redraw("per-pixel", |buffer, x, y, bitmap| {
+        for pixel in 0..8 {
+            let color = if (bitmap << pixel) & 0x80 != 0 {
+                ZXColor::White
+            } else {
+                ZXColor::Blue
+            };
+            buffer.set_color(x + pixel, y, color, ZXBrightness::Normal);
+        }
+    });
+    let per_block = redraw("per-block", |buffer, x, y, bitmap| {
+        buffer.set_block(
+            x,
+            y,
+            bitmap,
+            ZXColor::White,
+            ZXColor::Blue,
+            ZXBrightness::Normal,
+        );
+    });
+    println!(
+        "block writes are {:.1}x faster",
+        per_pixel.as_secs_f64() / per_block.as_secs_f64()
+    );
+}
@@ -1,6 +1,5 @@
 use std::time::Duration;
 
-use rustzx_core::zx::keys::ZXKey;
 use rustzx_test::framework::{presets, RustZXTester};
 
 fn z80test_setup() -> RustZXTester {
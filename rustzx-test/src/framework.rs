@@ -1,25 +1,35 @@
+use crate::sna::sna_48k;
 use expect_test::Expect;
 use rustzx_core::{
     host::{
         BufferCursor, DebugInterface, FrameBuffer, FrameBufferSource, Host, HostContext,
-        IoExtender, RomFormat, RomSet, Snapshot, Tape,
+        IoExtender, RomFormat, RomSet, Snapshot, StubBlockDevice, StubNetworkStack, Tape,
     },
+    idle::IdleConfig,
     poke,
     zx::{
         keys::ZXKey,
-        machine::ZXMachine,
-        sound::ay::ZXAYMode,
-        video::colors::{ZXBrightness, ZXColor},
+        machine::{MachinePolicy, Timings, UlaIssue, ZXMachine},
+        sound::{ay::ZXAYMode, filter::SoundFilters, sample::SoundSample},
+        video::{
+            border_size::BorderSize,
+            colors::{ZXBrightness, ZXColor},
+        },
     },
     EmulationMode, EmulationStopReason, Emulator, RustzxSettings,
 };
 use rustzx_utils::{
+    image::RgbaImage,
     io::{DynamicAsset, GzipAsset},
     palette::rgba::ORIGINAL as DEFAULT_PALETTE,
     stopwatch::InstantStopwatch,
+    text,
+    tui::{self, AnsiMode, IndexedScreen},
+    wav::WavSink,
 };
 use std::{
-    collections::VecDeque,
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
     io::Cursor,
     path::{Path, PathBuf},
     time::Duration,
@@ -29,6 +39,17 @@ const DEFAULT_SOUND_BITRATE: usize = 44100;
 const FRAME_HOST_DURATION_LIMIT: Duration = Duration::from_millis(100);
 const FRAME_EMULATED_DURATION: Duration = Duration::from_millis(20);
 const DEFAULT_SYNC_TIMEOUT: Duration = Duration::from_secs(3);
+/// Stack pointer of the snapshots built by [RustZXTester::load_code]
+const CODE_STACK: u16 = 0xFEFE;
+
+/// Builds TAP block: length, `flag`, `data` and XOR checksum
+pub fn tap_block(flag: u8, data: &[u8]) -> Vec<u8> {
+    let mut block = ((data.len() + 2) as u16).to_le_bytes().to_vec();
+    block.push(flag);
+    block.extend_from_slice(data);
+    block.push(data.iter().fold(flag, |acc, b| acc ^ b));
+    block
+}
 
 // TODO(#83): Add tests for gigascreen
 
@@ -74,6 +95,43 @@ impl FrameBuffer for FrameContent {
 }
 
 impl FrameContent {
+    fn color_index(&self, x: usize, y: usize) -> u8 {
+        let pixel_index = x + y * self.width;
+        let shift = (1 - pixel_index % 2) * 4;
+        (self.buffer[pixel_index / 2] >> shift) & 0x0F
+    }
+
+    fn set_color_index(&mut self, x: usize, y: usize, index: u8) {
+        let pixel_index = x + y * self.width;
+        let shift = (1 - pixel_index % 2) * 4;
+        let byte = &mut self.buffer[pixel_index / 2];
+        *byte = (*byte & !(0x0F << shift)) | (index << shift);
+    }
+
+    /// Composes border and canvas into the single image with cropped border
+    fn compose(border: &Self, canvas: &Self, size: BorderSize) -> Self {
+        let (width, height) = (size.width(), size.height());
+        let mut out = Self {
+            buffer: vec![0u8; (width * height) / 2],
+            width,
+            height,
+        };
+        let (offset_x, offset_y) = size.offset();
+        for y in 0..height {
+            for x in 0..width {
+                let index = border.color_index(x + offset_x, y + offset_y);
+                out.set_color_index(x, y, index);
+            }
+        }
+        for y in 0..canvas.height {
+            for x in 0..canvas.width {
+                let index = canvas.color_index(x, y);
+                out.set_color_index(x + size.horizontal(), y + size.vertical(), index);
+            }
+        }
+        out
+    }
+
     pub fn to_png(&self) -> Vec<u8> {
         let mut out = vec![];
 
@@ -92,6 +150,20 @@ impl FrameContent {
     }
 }
 
+impl IndexedScreen for FrameContent {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn color_index(&self, x: usize, y: usize) -> u8 {
+        FrameContent::color_index(self, x, y)
+    }
+}
+
 #[derive(Clone)]
 struct TesterFrameBufferContext {
     use_gigascreen: bool,
@@ -161,6 +233,7 @@ impl DebugPort {
 struct TestDebugInterface {
     breakpoints: std::collections::HashSet<u16>,
     last_hit: Option<u16>,
+    idle_changes: Vec<bool>,
 }
 
 impl TestDebugInterface {
@@ -186,6 +259,10 @@ impl DebugInterface for TestDebugInterface {
         }
         false
     }
+
+    fn idle_changed(&mut self, idle: bool) {
+        self.idle_changes.push(idle);
+    }
 }
 
 struct TesterHost;
@@ -197,11 +274,13 @@ impl Host for TesterHost {
     type FrameBuffer = FrameContent;
     type IoExtender = DebugPort;
     type TapeAsset = DynamicAsset;
+    type BlockDevice = StubBlockDevice;
+    type NetworkStack = StubNetworkStack;
 }
 
 pub struct RustZXTester {
     emulator: Emulator<TesterHost>,
-    sound_buffer: Option<Vec<i16>>,
+    sound_buffer: Option<Vec<SoundSample<f32>>>,
     test_name: String,
     sync_timeout: Duration,
 }
@@ -212,16 +291,29 @@ pub mod presets {
     pub fn settings_48k_nosound() -> RustzxSettings {
         RustzxSettings {
             machine: ZXMachine::Sinclair48K,
+            machine_policy: MachinePolicy::AutoSwitch,
+            timings: Timings::Late,
+            ula_issue: UlaIssue::Issue3,
+            interrupt_length: None,
             emulation_mode: EmulationMode::FrameCount(1),
             tape_fastload_enabled: true,
             kempston_enabled: false,
+            fuller_enabled: false,
             mouse_enabled: false,
+            mouse_config: Default::default(),
+            keyboard_ghosting: false,
+            ulaplus_enabled: false,
+            orientation: Default::default(),
+            paper_patterns: false,
+            ram_init_seed: None,
             ay_mode: ZXAYMode::ABC,
             ay_enabled: false,
+            fuller_ay_enabled: false,
             beeper_enabled: false,
             sound_enabled: false,
             sound_volume: 100,
             sound_sample_rate: DEFAULT_SOUND_BITRATE,
+            sound_filters: SoundFilters::NONE,
             load_default_rom: true,
             autoload_enabled: true,
         }
@@ -234,6 +326,13 @@ pub mod presets {
         }
     }
 
+    pub fn settings_plus3_nosound() -> RustzxSettings {
+        RustzxSettings {
+            machine: ZXMachine::SinclairPlus3,
+            ..settings_48k_nosound()
+        }
+    }
+
     pub fn settings_48k() -> RustzxSettings {
         RustzxSettings {
             sound_enabled: true,
@@ -253,8 +352,8 @@ pub mod presets {
 
 impl RustZXTester {
     pub fn new(test_name: &str, settings: RustzxSettings) -> Self {
-        let emulator = Emulator::new(settings, TesterContext::default())
-            .expect("Failed to initialize emulator");
+        let emulator =
+            Emulator::new(settings, TesterContext).expect("Failed to initialize emulator");
 
         Self {
             emulator,
@@ -264,6 +363,13 @@ impl RustZXTester {
         }
     }
 
+    /// Creates 48K tester without sound which runs `code` from 0x8000
+    pub fn with_code(test_name: &str, code: &[u8]) -> Self {
+        let mut tester = Self::new(test_name, presets::settings_48k_nosound());
+        tester.load_code(code);
+        tester
+    }
+
     fn assets_folder(&self) -> PathBuf {
         Path::new("test_data").to_owned()
     }
@@ -300,6 +406,11 @@ impl RustZXTester {
             .expect("Failed to load test TAP");
     }
 
+    /// Loads TAP built by the test, returns error of the emulator
+    pub fn load_tap_data(&mut self, data: Vec<u8>) -> rustzx_core::Result<()> {
+        self.emulator.load_tape(Tape::tap_from_bytes(data))
+    }
+
     pub fn load_sna(&mut self, name: impl AsRef<Path>) {
         let asset = self.load_asset(name);
         self.emulator
@@ -307,13 +418,25 @@ impl RustZXTester {
             .expect("Failed to load test SNA")
     }
 
+    /// Loads 48K snapshot which runs `code` from 0x8000, see [sna_48k]
+    pub fn load_code(&mut self, code: &[u8]) {
+        self.emulator
+            .load_snapshot(Snapshot::Sna(BufferCursor::new(sna_48k(CODE_STACK, code))))
+            .expect("Failed to load code snapshot")
+    }
+
     pub fn load_single_page_rom(&mut self, name: impl AsRef<Path>) {
         let rom_data = self.load_asset_data(name);
-        struct DiagRomSet {
+        self.load_rom_pages(vec![rom_data, vec![0u8; 16 * 1024]]);
+    }
+
+    /// Loads ROM from 16K pages, extra pages are ignored
+    pub fn load_rom_pages(&mut self, pages: Vec<Vec<u8>>) {
+        struct PagesRomSet {
             pages: VecDeque<Vec<u8>>,
         }
 
-        impl RomSet for DiagRomSet {
+        impl RomSet for PagesRomSet {
             type Asset = BufferCursor<Vec<u8>>;
 
             fn format(&self) -> RomFormat {
@@ -321,12 +444,12 @@ impl RustZXTester {
             }
 
             fn next_asset(&mut self) -> Option<Self::Asset> {
-                Some(BufferCursor::new(self.pages.pop_front().unwrap()))
+                self.pages.pop_front().map(BufferCursor::new)
             }
         }
 
-        let rom_set = DiagRomSet {
-            pages: VecDeque::from(vec![rom_data, vec![0u8; 16 * 1024]]),
+        let rom_set = PagesRomSet {
+            pages: VecDeque::from(pages),
         };
 
         self.emulator.load_rom(rom_set).unwrap();
@@ -340,12 +463,19 @@ impl RustZXTester {
         self.emulator.border_buffer().to_png()
     }
 
+    fn get_capture(&self, size: BorderSize) -> Vec<u8> {
+        FrameContent::compose(
+            self.emulator.border_buffer(),
+            self.emulator.screen_buffer(),
+            size,
+        )
+        .to_png()
+    }
+
     fn update_sound(&mut self) {
         if let Some(sound_buffer) = &mut self.sound_buffer {
             while let Some(sample) = self.emulator.next_audio_sample() {
-                let normalize = |s| (s * i16::MAX as f32) as i16;
-                sound_buffer.push(normalize(sample.left));
-                sound_buffer.push(normalize(sample.right));
+                sound_buffer.push(sample);
             }
         }
     }
@@ -437,10 +567,47 @@ impl RustZXTester {
         self.compare_buffer_with_file(self.get_border(), make_border_filename(name), expect);
     }
 
+    /// Compares canvas with the border cropped to the given size
+    pub fn expect_capture(&self, name: impl AsRef<Path>, size: BorderSize, expect: Expect) {
+        self.compare_buffer_with_file(self.get_capture(size), make_capture_filename(name), expect);
+    }
+
+    /// Renders screen canvas as ANSI-colored text, e.g. to print it to the test log
+    pub fn screen_ansi(&self, mode: AnsiMode) -> String {
+        tui::render_ansi(self.emulator.screen_buffer(), mode)
+    }
+
+    /// Returns screen canvas as RGBA image
+    pub fn screen_image(&self) -> RgbaImage {
+        to_rgba_image(self.emulator.screen_buffer())
+    }
+
+    /// Returns border frame buffer (whole rendered screen) as RGBA image
+    pub fn border_image(&self) -> RgbaImage {
+        to_rgba_image(self.emulator.border_buffer())
+    }
+
+    /// Returns stable hash of the screen canvas, see [RgbaImage::frame_hash]
+    pub fn frame_hash(&self) -> u64 {
+        self.screen_image().frame_hash()
+    }
+
+    /// Returns 24 lines of text recognized on the screen, see
+    /// [text::read_screen_text]
+    pub fn screen_text(&self) -> Vec<String> {
+        text::read_screen_text(&self.emulator)
+    }
+
     pub fn expect_text(&self, name: impl AsRef<Path>, text: String, expect: Expect) {
         self.compare_buffer_with_file(text.into_bytes(), make_text_filename(name), expect);
     }
 
+    /// Sets the host context again, which re-creates the frame buffers as
+    /// after the palette change of the frontend
+    pub fn reset_host_context(&mut self) {
+        self.emulator.set_context(TesterContext);
+    }
+
     pub fn emulator(&mut self) -> &mut Emulator<impl Host> {
         &mut self.emulator
     }
@@ -449,6 +616,18 @@ impl RustZXTester {
         self.emulator.send_key(key, true);
     }
 
+    /// Types characters via the ROM key buffer, waiting until each previous
+    /// character is taken
+    pub fn type_chars(&mut self, chars: &[u8]) {
+        for &ch in chars {
+            while self.emulator.is_key_char_pending() {
+                self.emulate_frame();
+            }
+            self.emulator.inject_key_char(ch);
+            self.emulate_frame();
+        }
+    }
+
     pub fn send_keystrokes(&mut self, keystrokes: &[&[ZXKey]], keystroke_delay: Duration) {
         let mut first = true;
         for keys in keystrokes {
@@ -471,7 +650,7 @@ impl RustZXTester {
 
     pub fn start_sound_capture(&mut self) {
         // Pre-allocate 1Mb of memory
-        self.sound_buffer.replace(Vec::with_capacity(1024 * 1024));
+        self.sound_buffer.replace(Vec::with_capacity(128 * 1024));
     }
 
     pub fn expect_sound(&mut self, name: impl AsRef<Path>, expect: Expect) {
@@ -480,17 +659,30 @@ impl RustZXTester {
             .take()
             .expect("Sound is not being recorded");
 
-        let mut wav_data = std::io::Cursor::new(vec![]);
-        let wav_header = wav::Header::new(
-            wav::header::WAV_FORMAT_PCM,
-            2,
-            DEFAULT_SOUND_BITRATE as u32,
-            16,
-        );
-        wav::write(wav_header, &wav::BitDepth::Sixteen(data), &mut wav_data)
+        let mut wav = WavSink::from_writer(Cursor::new(vec![]), DEFAULT_SOUND_BITRATE as u32, 2)
             .expect("Failed to generate wav");
+        for sample in data {
+            wav.write_sample(sample).expect("Failed to generate wav");
+        }
+        let wav_data = wav.finish().expect("Failed to generate wav").into_inner();
+
+        self.compare_buffer_with_file(wav_data, make_sound_filename(name), expect);
+    }
 
-        self.compare_buffer_with_file(wav_data.into_inner(), make_sound_filename(name), expect);
+    /// Returns hash of the state observable by the host: memory, screen, border
+    /// and sound samples produced since the last call (if not captured)
+    pub fn state_hash(&mut self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for addr in 0..=0xFFFF {
+            self.emulator.peek(addr).hash(&mut hasher);
+        }
+        self.emulator.screen_buffer().buffer.hash(&mut hasher);
+        self.emulator.border_buffer().buffer.hash(&mut hasher);
+        while let Some(sample) = self.emulator.next_audio_sample() {
+            sample.left.to_bits().hash(&mut hasher);
+            sample.right.to_bits().hash(&mut hasher);
+        }
+        hasher.finish()
     }
 
     pub fn enable_debug_port(&mut self) {
@@ -542,14 +734,28 @@ impl RustZXTester {
         self.emulator.execute_poke(poke::DisableScrollMessageRom48);
     }
 
-    pub fn add_breakpoint(&mut self, address: u16) {
-        if let Some(interface) = self.emulator.debug_interface() {
-            interface.add_breakpoint(address);
-        } else {
-            let mut interface = TestDebugInterface::default();
-            interface.add_breakpoint(address);
-            self.emulator.set_debug_interface(interface);
+    fn test_debug_interface(&mut self) -> &mut TestDebugInterface {
+        if self.emulator.debug_interface().is_none() {
+            self.emulator
+                .set_debug_interface(TestDebugInterface::default());
         }
+        self.emulator.debug_interface().unwrap()
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.test_debug_interface().add_breakpoint(address);
+    }
+
+    /// Enables idle detection, state changes are collected by
+    /// [RustZXTester::take_idle_changes]
+    pub fn enable_idle_detection(&mut self, config: IdleConfig) {
+        self.test_debug_interface();
+        self.emulator.set_idle_detection(Some(config));
+    }
+
+    /// Returns idle state changes reported since the last call
+    pub fn take_idle_changes(&mut self) -> Vec<bool> {
+        std::mem::take(&mut self.test_debug_interface().idle_changes)
     }
 
     pub fn clear_breakpoints(&mut self) {
@@ -571,6 +777,16 @@ impl TestEnv {
     }
 }
 
+fn to_rgba_image(buffer: &FrameContent) -> RgbaImage {
+    let mut image = RgbaImage::new(buffer.width(), buffer.height());
+    for y in 0..image.height {
+        for x in 0..image.width {
+            image.set_pixel(x, y, DEFAULT_PALETTE[buffer.color_index(x, y) as usize]);
+        }
+    }
+    image
+}
+
 fn make_png_palette() -> Vec<u8> {
     DEFAULT_PALETTE
         .iter()
@@ -588,6 +804,10 @@ fn make_border_filename(name: impl AsRef<Path>) -> PathBuf {
     name.as_ref().with_extension("border.png")
 }
 
+fn make_capture_filename(name: impl AsRef<Path>) -> PathBuf {
+    name.as_ref().with_extension("capture.png")
+}
+
 fn make_sound_filename(name: impl AsRef<Path>) -> PathBuf {
     name.as_ref().with_extension("wav")
 }
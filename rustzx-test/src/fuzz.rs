@@ -0,0 +1,146 @@
+//! Loader entry points fed with untrusted data, shared by the cargo-fuzz
+//! targets in `fuzz/` and the loader robustness tests. Loaders may reject
+//! the data with an error, but should never panic or hang
+
+use crate::framework::{presets, RustZXTester};
+use rustzx_core::host::{BufferCursor, Cartridge, Disk, Screen, Snapshot};
+use rustzx_utils::io::ZipArchive;
+use std::{io::Cursor, time::Duration};
+
+/// Sizes of 48K and 128K SNA snapshots, the latter includes repeated bank
+/// 2 or 5 when it is paged in
+const SNA_48K_SIZE: usize = 49179;
+const SNA_128K_SIZE: usize = 147487;
+/// CPU clocks executed from random snapshots, a few frames
+const RANDOM_SNAPSHOT_TSTATES: u64 = 4 * 70_000;
+/// Upper bound of CPU clocks by which [rustzx_core::Emulator::run_bounded]
+/// can exceed the budget: interrupt acceptance followed by `DD CB`
+/// instruction, both with the worst contention
+pub const MAX_BOUNDED_OVERRUN: u64 = 128;
+
+/// Frames emulated after successful load, enough to run a few instructions
+/// of the loaded program or to start playing the tape
+const FRAMES_AFTER_LOAD: usize = 5;
+const FRAME_HOST_DURATION_LIMIT: Duration = Duration::from_millis(100);
+
+fn tester() -> RustZXTester {
+    RustZXTester::new("fuzz", presets::settings_48k_nosound())
+}
+
+/// Emulates frames until emulation fails, e.g. on truncated tape block
+fn run(tester: &mut RustZXTester) {
+    for _ in 0..FRAMES_AFTER_LOAD {
+        if tester
+            .emulator()
+            .emulate_frames(FRAME_HOST_DURATION_LIMIT)
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+pub fn load_sna(data: &[u8]) {
+    let mut tester = tester();
+    let snapshot = Snapshot::sna_from_bytes(data);
+    if tester.emulator().load_snapshot(snapshot).is_ok() {
+        run(&mut tester);
+    }
+}
+
+/// Loads tape with autoload and fast load, then plays it in real time
+pub fn load_tap(data: &[u8]) {
+    let mut tester = tester();
+    if tester.load_tap_data(data.to_vec()).is_ok() {
+        run(&mut tester);
+        tester.emulator().stop_tape();
+        tester.emulator().rewind_tape().ok();
+        tester.emulator().play_tape();
+        run(&mut tester);
+    }
+}
+
+pub fn load_scr(data: &[u8]) {
+    let mut tester = tester();
+    let screen = Screen::scr_from_bytes(data);
+    if tester.emulator().load_screen(screen).is_ok() {
+        run(&mut tester);
+    }
+}
+
+/// Inserts Timex cartridge and runs the machine from it
+pub fn load_dck(data: &[u8]) {
+    let mut tester = tester();
+    let cartridge = Cartridge::Dck(BufferCursor::new(data));
+    if tester.emulator().insert_cartridge(cartridge).is_ok() {
+        run(&mut tester);
+    }
+}
+
+/// Inserts MGT, IMG and UDI disk images into +D drive and runs the machine
+pub fn load_plusd_disk(data: &[u8]) {
+    let mut tester = tester();
+    tester
+        .emulator()
+        .attach_plusd(BufferCursor::new([0xC9]))
+        .expect("Failed to attach +D");
+    for disk in [
+        Disk::Mgt(BufferCursor::new(data)),
+        Disk::Img(BufferCursor::new(data)),
+        Disk::Udi(BufferCursor::new(data)),
+    ] {
+        if tester.emulator().insert_plusd_disk(0, disk).is_ok() {
+            run(&mut tester);
+        }
+    }
+}
+
+/// Inserts TRD, SCL and UDI disk images into Beta Disk drive and runs the
+/// machine
+pub fn load_betadisk_disk(data: &[u8]) {
+    let mut tester = tester();
+    tester
+        .emulator()
+        .attach_betadisk(BufferCursor::new([0xC9]))
+        .expect("Failed to attach Beta Disk");
+    for disk in [
+        Disk::Trd(BufferCursor::new(data)),
+        Disk::Scl(BufferCursor::new(data)),
+        Disk::Udi(BufferCursor::new(data)),
+    ] {
+        if tester.emulator().insert_betadisk_disk(0, disk).is_ok() {
+            run(&mut tester);
+        }
+    }
+}
+
+/// Lists ZIP archive entries and unpacks each of them
+pub fn load_zip(data: &[u8]) {
+    if let Ok(mut archive) = ZipArchive::new(Cursor::new(data)) {
+        for entry in archive.entries() {
+            let _ = archive.load(&entry.name);
+        }
+    }
+}
+
+/// Builds snapshot of the exact 48K or 128K size (selected by the lowest bit
+/// of the first byte) from arbitrary data and runs it for a fixed CPU clocks
+/// budget, so any register and memory contents are executed
+pub fn run_random_snapshot(data: &[u8]) {
+    let (settings, size) = match data.first() {
+        Some(byte) if byte & 1 != 0 => (presets::settings_128k_nosound(), SNA_128K_SIZE),
+        _ => (presets::settings_48k_nosound(), SNA_48K_SIZE),
+    };
+    let mut sna = data.to_vec();
+    sna.resize(size, 0);
+    let mut tester = RustZXTester::new("fuzz", settings);
+    let snapshot = Snapshot::Sna(BufferCursor::new(sna));
+    // Header still could be invalid, e.g. with interrupt mode 3
+    if tester.emulator().load_snapshot(snapshot).is_err() {
+        return;
+    }
+    if let Ok(result) = tester.emulator().run_bounded(RANDOM_SNAPSHOT_TSTATES) {
+        let budget = RANDOM_SNAPSHOT_TSTATES..RANDOM_SNAPSHOT_TSTATES + MAX_BOUNDED_OVERRUN;
+        assert!(budget.contains(&result.tstates));
+    }
+}
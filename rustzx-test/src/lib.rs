@@ -1 +1,4 @@
 pub mod framework;
+pub mod fuzz;
+pub mod sna;
+pub mod udi;
@@ -0,0 +1,43 @@
+//! Builders for minimal hand-made SNA snapshots, laid out the same way as
+//! snapshots produced by other emulators (e.g. Fuse)
+
+const PAGE_SIZE: usize = 0x4000;
+const HEADER_SIZE: usize = 27;
+const CODE_ADDR: u16 = 0x8000;
+const RAM_START: usize = 0x4000;
+
+fn header(sp: u16) -> Vec<u8> {
+    let mut header = vec![0u8; HEADER_SIZE];
+    header[23..25].copy_from_slice(&sp.to_le_bytes());
+    // IM 1, interrupts disabled
+    header[25] = 1;
+    header
+}
+
+/// Builds 48K snapshot which starts executing `code` at 0x8000. As there is no
+/// PC field in the 48K format, 0x8000 is placed on the stack at `sp`
+pub fn sna_48k(sp: u16, code: &[u8]) -> Vec<u8> {
+    let mut sna = header(sp);
+    let mut ram = vec![0u8; 3 * PAGE_SIZE];
+    let code_offset = CODE_ADDR as usize - RAM_START;
+    ram[code_offset..code_offset + code.len()].copy_from_slice(code);
+    let sp_offset = sp as usize - RAM_START;
+    ram[sp_offset..sp_offset + 2].copy_from_slice(&CODE_ADDR.to_le_bytes());
+    sna.extend_from_slice(&ram);
+    sna
+}
+
+/// Builds 128K snapshot which starts executing `code` at 0x8000 with the given
+/// 0x7FFD port value. Paged bank should not be 2 or 5
+pub fn sna_128k(port_7ffd: u8, code: &[u8]) -> Vec<u8> {
+    let mut sna = header(0xFF00);
+    // Banks 5, 2 and the paged one
+    let mut head_banks = vec![0u8; 3 * PAGE_SIZE];
+    head_banks[PAGE_SIZE..PAGE_SIZE + code.len()].copy_from_slice(code);
+    sna.extend_from_slice(&head_banks);
+    sna.extend_from_slice(&CODE_ADDR.to_le_bytes());
+    sna.extend_from_slice(&[port_7ffd, 0]);
+    // Remaining 5 banks
+    sna.extend_from_slice(&vec![0u8; 5 * PAGE_SIZE]);
+    sna
+}
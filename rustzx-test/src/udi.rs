@@ -0,0 +1,83 @@
+//! UDI (Ultra Disk Image) fixtures with nonstandard sector layouts
+
+/// Address marks of the ID and data fields
+pub const MARK_ID: u8 = 0xFE;
+pub const MARK_DATA: u8 = 0xFB;
+
+/// Raw bytes of the MFM track at 250 kbit/s
+const TRACK_LENGTH: usize = 6250;
+const SYNC: u8 = 0xA1;
+const GAP: u8 = 0x4E;
+
+/// Field of the track, written after the sync bytes and followed by CRC
+pub struct Field<'a> {
+    pub mark: u8,
+    pub data: &'a [u8],
+    /// CRC is corrupted if false
+    pub crc_ok: bool,
+}
+
+impl<'a> Field<'a> {
+    /// ID field of the sector: track, side, sector and size code
+    pub fn id(id: &'a [u8; 4]) -> Self {
+        Self {
+            mark: MARK_ID,
+            data: id,
+            crc_ok: true,
+        }
+    }
+
+    pub fn data(data: &'a [u8], crc_ok: bool) -> Self {
+        Self {
+            mark: MARK_DATA,
+            data,
+            crc_ok,
+        }
+    }
+}
+
+fn crc16(field: impl IntoIterator<Item = u8>) -> u16 {
+    field.into_iter().fold(0xFFFF, |crc, byte| {
+        (0..8).fold(crc ^ ((byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Builds UDI image of the single-sided disk with one track of the given
+/// fields. Image CRC32 is not set, as it is only checked for diagnostics
+pub fn udi_image(fields: &[Field]) -> Vec<u8> {
+    let mut track = vec![GAP; 80];
+    let mut sync = Vec::new();
+    for field in fields {
+        track.extend([0x00; 12]);
+        sync.extend(track.len()..track.len() + 3);
+        let header = [SYNC, SYNC, SYNC, field.mark];
+        let crc = crc16(header.iter().chain(field.data).copied());
+        let crc = if field.crc_ok { crc } else { !crc };
+        track.extend(header);
+        track.extend(field.data);
+        track.extend(crc.to_be_bytes());
+        track.extend([GAP; 22]);
+    }
+    assert!(track.len() <= TRACK_LENGTH, "Track is too long");
+    track.resize(TRACK_LENGTH, GAP);
+    let mut clock = vec![0; track.len().div_ceil(8)];
+    for pos in sync {
+        clock[pos / 8] |= 1 << (pos % 8);
+    }
+    let mut image = b"UDI!".to_vec();
+    image.extend([0; 12]);
+    image.push(0x00);
+    image.extend((track.len() as u16).to_le_bytes());
+    image.extend(track);
+    image.extend(clock);
+    let size = image.len() as u32;
+    image[4..8].copy_from_slice(&size.to_le_bytes());
+    image.extend([0; 4]);
+    image
+}
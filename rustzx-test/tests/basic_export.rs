@@ -0,0 +1,97 @@
+use rustzx_core::error::{BasicExportError, Error};
+use rustzx_test::framework::{presets, tap_block, RustZXTester};
+use std::time::Duration;
+
+const TOKEN_PRINT: u8 = 0xF5;
+const TOKEN_LET: u8 = 0xF1;
+
+fn type_line(t: &mut RustZXTester, line: &[u8]) {
+    t.type_chars(line);
+    t.type_chars(&[0x0D]);
+    t.emulate_for(Duration::from_millis(100));
+}
+
+/// Boots 48K BASIC and enters `10 PRINT 42` and variable `a` = 5
+fn basic_tester(name: &str) -> RustZXTester {
+    let mut t = RustZXTester::new(name, presets::settings_48k_nosound());
+    t.emulate_for(Duration::from_millis(2000));
+    type_line(&mut t, &[b'1', b'0', TOKEN_PRINT, b'4', b'2']);
+    type_line(&mut t, &[TOKEN_LET, b'a', b'=', b'5']);
+    t
+}
+
+#[test]
+fn export_basic_program_with_variables() {
+    let mut t = basic_tester("export_basic_program_with_variables");
+    let mut tap = vec![];
+    t.emulator()
+        .export_basic_tap(&mut tap, "type-in")
+        .expect("Failed to export program");
+
+    // Line 10, 10 bytes long: PRINT 42 (with hidden number)
+    let line_header = [0x00, 0x0A, 0x0A, 0x00];
+    let line = [
+        TOKEN_PRINT,
+        b'4',
+        b'2',
+        0x0E,
+        0x00,
+        0x00,
+        0x2A,
+        0x00,
+        0x00,
+        0x0D,
+    ];
+    let variables = [b'a', 0x00, 0x00, 0x05, 0x00, 0x00];
+    let mut header = vec![0x00];
+    header.extend_from_slice(b"type-in   ");
+    header.extend_from_slice(&20u16.to_le_bytes());
+    header.extend_from_slice(&0x8000u16.to_le_bytes());
+    header.extend_from_slice(&14u16.to_le_bytes());
+    let mut expected = tap_block(0x00, &header);
+    expected.extend(tap_block(
+        0xFF,
+        &[&line_header[..], &line, &variables].concat(),
+    ));
+    assert_eq!(tap, expected);
+
+    // Exported tape is accepted by the tape loader
+    t.load_tap_data(tap).expect("Failed to load exported tape");
+}
+
+#[test]
+fn export_basic_name_validation() {
+    let mut t = basic_tester("export_basic_name_validation");
+    let mut tap = vec![];
+    for name in ["0123456789A", "caf\u{e9}", "line\n"] {
+        let result = t.emulator().export_basic_tap(&mut tap, name);
+        assert!(
+            matches!(
+                result,
+                Err(Error::BasicExport(BasicExportError::InvalidName))
+            ),
+            "{:?}",
+            name
+        );
+    }
+    assert!(tap.is_empty());
+
+    t.emulator()
+        .export_basic_tap(&mut tap, "0123456789")
+        .expect("Failed to export program");
+    assert_eq!(&tap[4..14], b"0123456789");
+}
+
+#[test]
+fn export_basic_requires_initialized_system_variables() {
+    // ROM did not run yet, system variables are zero
+    let mut t = RustZXTester::new(
+        "export_basic_requires_initialized_system_variables",
+        presets::settings_48k_nosound(),
+    );
+    let result = t.emulator().export_basic_tap(vec![], "empty");
+    assert!(matches!(
+        result,
+        Err(Error::BasicExport(BasicExportError::InvalidProgramArea))
+    ));
+}
@@ -0,0 +1,27 @@
+use expect_test::expect;
+use rustzx_core::BenchmarkReport;
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+#[test]
+fn benchmark_report() {
+    let mut tester = RustZXTester::new("benchmark_report", presets::settings_48k());
+    let report = tester.emulator().benchmark(100).expect("Benchmark failed");
+
+    assert_eq!(report.frames, 100);
+    // 100 frames of 69888 clocks at 3.5MHz
+    assert_eq!(report.emulated_time.as_micros(), 1_996_800);
+    expect![[r#"748682"#]].assert_eq(&report.instructions.to_string());
+}
+
+#[test]
+fn benchmark_report_without_measured_time() {
+    let report = BenchmarkReport {
+        frames: 0,
+        instructions: 0,
+        wall_time: Duration::ZERO,
+        emulated_time: Duration::ZERO,
+    };
+    assert_eq!(report.speed_factor(), None);
+    assert_eq!(report.instructions_per_frame(), None);
+}
@@ -0,0 +1,237 @@
+use rustzx_core::{
+    error::{DiskLoadError, Error},
+    host::{BufferCursor, Disk, Snapshot, SnapshotRecorder},
+    zx::BetaDiskState,
+};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    udi::{udi_image, Field},
+};
+
+const SECTOR_SIZE: usize = 256;
+const DISK_SIZE: usize = 80 * 2 * 16 * SECTOR_SIZE;
+const BUFFER_ADDR: u16 = 0x9000;
+/// Sectors 1..=16 of track 0 side 0
+const LOAD_SIZE: usize = 16 * SECTOR_SIZE;
+const TRDOS_ENTRY: usize = 0x3D00;
+const TRDOS_LOOP: core::ops::Range<u16> = 0x3D0F..0x3D1C;
+
+// LD A, 0x3C; OUT (0xFF), A  ; drive 0, side 0
+// LD A, 1; OUT (0x5F), A     ; sector 1
+// LD HL, 0x9000
+// LD A, 0x90; OUT (0x1F), A  ; multi-sector read
+// loop: IN A, (0xFF); ADD A, A; RET C ; INTRQ
+// JP P, loop                 ; DRQ
+// IN A, (0x7F); LD (HL), A; INC HL; JR loop
+const TRDOS_LOADER: [u8; 28] = [
+    0x3E, 0x3C, 0xD3, 0xFF, 0x3E, 0x01, 0xD3, 0x5F, 0x21, 0x00, 0x90, 0x3E, 0x90, 0xD3, 0x1F, 0xDB,
+    0xFF, 0x87, 0xD8, 0xF2, 0x0F, 0x3D, 0xDB, 0x7F, 0x77, 0x23, 0x18, 0xF3,
+];
+
+// DI; CALL 0x3D00; JR $
+const CALLER_CODE: [u8; 6] = [0xF3, 0xCD, 0x00, 0x3D, 0x18, 0xFE];
+
+/// TR-DOS ROM filled with 0xAB with the loader at the entry point
+fn trdos_rom() -> Vec<u8> {
+    let mut rom = vec![0xAB; 0x4000];
+    rom[TRDOS_ENTRY..TRDOS_ENTRY + TRDOS_LOADER.len()].copy_from_slice(&TRDOS_LOADER);
+    rom
+}
+
+fn disk_image(seed: u8) -> Vec<u8> {
+    (0..DISK_SIZE)
+        .map(|idx| (idx / SECTOR_SIZE) as u8 ^ idx as u8 ^ seed)
+        .collect()
+}
+
+fn tester(name: &str, disk: Option<Vec<u8>>) -> RustZXTester {
+    let mut t = RustZXTester::new(name, presets::settings_48k_nosound());
+    t.emulator()
+        .attach_betadisk(BufferCursor::new(trdos_rom()))
+        .expect("Failed to attach Beta Disk");
+    if let Some(disk) = disk {
+        t.emulator()
+            .insert_betadisk_disk(0, Disk::Trd(BufferCursor::new(disk)))
+            .expect("Failed to insert disk");
+    }
+    t
+}
+
+fn loaded_data(t: &mut RustZXTester) -> Vec<u8> {
+    (0..LOAD_SIZE)
+        .map(|offset| t.peek(BUFFER_ADDR + offset as u16))
+        .collect()
+}
+
+#[test]
+fn betadisk_state_resumes_multi_sector_load() {
+    let name = "betadisk_state_resumes_multi_sector_load";
+    let mut t = tester(name, Some(disk_image(0)));
+    t.load_code(&CALLER_CODE);
+    t.emulate_frame();
+    t.emulate_frame();
+    let mut sna = Vec::new();
+    t.emulator()
+        .save_snapshot(SnapshotRecorder::Sna(&mut sna))
+        .expect("Failed to save snapshot");
+    let mut state = Vec::new();
+    t.emulator()
+        .betadisk_state()
+        .expect("Beta Disk is attached")
+        .save(&mut state)
+        .unwrap();
+    // Load is in progress, TR-DOS loader is running
+    let loaded = loaded_data(&mut t);
+    assert!(loaded[SECTOR_SIZE] != 0 && loaded[LOAD_SIZE - 1] == 0);
+    assert!(TRDOS_LOOP.contains(&t.emulator().registers().pc));
+
+    // Multi-sector load ends with the record not found after searching for
+    // the sector 17 during 5 revolutions (1 second)
+    for _ in 0..60 {
+        t.emulate_frame();
+    }
+    let expected = disk_image(0)[..LOAD_SIZE].to_vec();
+    assert_eq!(loaded_data(&mut t), expected);
+    // Loader returned to the caller
+    assert!(t.emulator().registers().pc >= 0x4000);
+
+    let mut restored = tester(name, Some(disk_image(0)));
+    restored
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna)))
+        .unwrap();
+    let state = BetaDiskState::load(BufferCursor::new(state)).unwrap();
+    restored.emulator().set_betadisk_state(&state).unwrap();
+    for _ in 0..60 {
+        restored.emulate_frame();
+    }
+    assert_eq!(loaded_data(&mut restored), expected);
+}
+
+#[test]
+fn betadisk_state_requires_same_disks() {
+    let name = "betadisk_state_requires_same_disks";
+    let mut t = tester(name, Some(disk_image(0)));
+    let state = t.emulator().betadisk_state().unwrap();
+
+    let mut other = tester(name, Some(disk_image(1)));
+    let result = other.emulator().set_betadisk_state(&state);
+    assert!(matches!(
+        result,
+        Err(Error::DiskLoad(DiskLoadError::DiskMismatch))
+    ));
+    // Disk is not touched on failure
+    assert_eq!(other.emulator().eject_betadisk_disk(0), Some(disk_image(1)));
+
+    let mut empty = tester(name, None);
+    let result = empty.emulator().set_betadisk_state(&state);
+    assert!(matches!(
+        result,
+        Err(Error::DiskLoad(DiskLoadError::DiskMismatch))
+    ));
+
+    let mut detached = RustZXTester::new(name, presets::settings_48k_nosound());
+    assert!(detached.emulator().betadisk_state().is_none());
+    let result = detached.emulator().set_betadisk_state(&state);
+    assert!(matches!(
+        result,
+        Err(Error::DiskLoad(DiskLoadError::NoDiskInterface))
+    ));
+}
+
+#[test]
+fn betadisk_rejects_plusd_images() {
+    let mut t = tester("betadisk_rejects_plusd_images", None);
+    let result = t
+        .emulator()
+        .insert_betadisk_disk(0, Disk::Mgt(BufferCursor::new(vec![0; 819200])));
+    assert!(matches!(
+        result,
+        Err(Error::DiskLoad(DiskLoadError::UnsupportedFormat))
+    ));
+}
+
+// LD A, 0x3C; OUT (0xFF), A  ; drive 0, side 0
+// LD A, B; OUT (0x5F), A     ; sector
+// LD A, 0x80; OUT (0x1F), A  ; read sector
+// loop: IN A, (0xFF); ADD A, A; JR C, done ; INTRQ
+// JP P, loop                 ; DRQ
+// IN A, (0x7F); LD (HL), A; INC HL; JR loop
+// done: IN A, (0x1F); RET    ; status
+const TRDOS_READ_SECTOR: [u8; 28] = [
+    0x3E, 0x3C, 0xD3, 0xFF, 0x78, 0xD3, 0x5F, 0x3E, 0x80, 0xD3, 0x1F, 0xDB, 0xFF, 0x87, 0x38, 0x09,
+    0xF2, 0x0B, 0x3D, 0xDB, 0x7F, 0x77, 0x23, 0x18, 0xF2, 0xDB, 0x1F, 0xC9,
+];
+
+/// Reads sectors 1 to 4 to `0x9000 + 0x400 * (sector - 1)`, stores status
+/// of each read at `0x8F00 + sector - 1`
+fn read_sectors_code() -> Vec<u8> {
+    // DI
+    let mut code = vec![0xF3];
+    for sector in 1..=4u8 {
+        let buffer = 0x9000 + 0x400 * (sector as u16 - 1);
+        // LD HL, buffer; LD B, sector; CALL 0x3D00; LD (0x8F00 + sector - 1), A
+        code.extend([0x21]);
+        code.extend(buffer.to_le_bytes());
+        code.extend([0x06, sector, 0xCD, 0x00, 0x3D, 0x32, sector - 1, 0x8F]);
+    }
+    // JR $
+    code.extend([0x18, 0xFE]);
+    code
+}
+
+#[test]
+fn betadisk_protected_udi_sectors() {
+    let mut rom = vec![0xAB; 0x4000];
+    rom[TRDOS_ENTRY..TRDOS_ENTRY + TRDOS_READ_SECTOR.len()].copy_from_slice(&TRDOS_READ_SECTOR);
+    let mut t = RustZXTester::new(
+        "betadisk_protected_udi_sectors",
+        presets::settings_48k_nosound(),
+    );
+    t.emulator()
+        .attach_betadisk(BufferCursor::new(rom))
+        .expect("Failed to attach Beta Disk");
+    let data: [&[u8]; 3] = [&[0x11; 256], &[0x22; 256], &[0x44; 1024]];
+    let disk = udi_image(&[
+        Field::id(&[0, 0, 1, 1]),
+        Field::data(data[0], true),
+        // Data with CRC error
+        Field::id(&[0, 0, 2, 1]),
+        Field::data(data[1], false),
+        // Missing data address mark
+        Field::id(&[0, 0, 3, 1]),
+        // Nonstandard sector size
+        Field::id(&[0, 0, 4, 3]),
+        Field::data(data[2], true),
+    ]);
+    t.emulator()
+        .insert_betadisk_disk(0, Disk::Udi(BufferCursor::new(disk)))
+        .expect("Failed to insert disk");
+    let code = read_sectors_code();
+    t.load_code(&code);
+    let end = 0x8000 + code.len() as u16 - 2;
+
+    // Record not found is reported after searching during 5 revolutions
+    for _ in 0..45 {
+        t.emulate_frame();
+    }
+    assert_ne!(t.emulator().registers().pc, end);
+    for _ in 0..15 {
+        t.emulate_frame();
+    }
+    assert_eq!(t.emulator().registers().pc, end);
+
+    let status = (0..4).map(|idx| t.peek(0x8F00 + idx)).collect::<Vec<_>>();
+    // CRC error, record not found
+    assert_eq!(status, [0x00, 0x08, 0x10, 0x00]);
+    let sector = |t: &mut RustZXTester, idx: u16, len: u16| {
+        (0..len)
+            .map(|offset| t.peek(0x9000 + 0x400 * idx + offset))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(sector(&mut t, 0, 256), data[0]);
+    // Data of the sector with CRC error is transferred
+    assert_eq!(sector(&mut t, 1, 256), data[1]);
+    assert_eq!(sector(&mut t, 2, 1), [0]);
+    assert_eq!(sector(&mut t, 3, 1024), data[2]);
+}
@@ -0,0 +1,87 @@
+use rustzx_core::{
+    error::{BootError, Error},
+    host::{BufferCursor, Snapshot},
+};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    sna::{sna_128k, sna_48k},
+};
+
+/// Boots the machine, returns count of frames and hash of the next frame
+fn boot(t: &mut RustZXTester) -> (u32, u64) {
+    let frames = t.emulator().boot_to_basic().expect("Failed to boot");
+    t.emulate_frame();
+    (frames, t.frame_hash())
+}
+
+/// Loads snapshot which fills screen and attributes with garbage
+fn load_garbage(t: &mut RustZXTester, sna: Vec<u8>) {
+    t.emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna)))
+        .expect("Failed to load snapshot");
+    t.emulate_frame();
+}
+
+// LD HL, 0x4000; LD DE, 0x4001; LD BC, 0x1AFF; LD (HL), 0xA5; LDIR; JR $
+const FILL_SCREEN_CODE: [u8; 15] = [
+    0x21, 0x00, 0x40, 0x11, 0x01, 0x40, 0x01, 0xFF, 0x1A, 0x36, 0xA5, 0xED, 0xB0, 0x18, 0xFE,
+];
+
+#[test]
+fn boot_48k_is_reproducible() {
+    let mut t = RustZXTester::new("boot_48k_is_reproducible", presets::settings_48k_nosound());
+    let (frames, hash) = boot(&mut t);
+    assert!(frames > 0 && frames < 150, "{}", frames);
+    assert_eq!(
+        t.screen_text().last().unwrap().trim_end(),
+        "\u{a9} 1982 Sinclair Research Ltd"
+    );
+
+    load_garbage(&mut t, sna_48k(0xFEFE, &FILL_SCREEN_CODE));
+    assert_eq!(boot(&mut t), (frames, hash));
+}
+
+#[test]
+fn boot_128k_is_reproducible() {
+    let mut t = RustZXTester::new(
+        "boot_128k_is_reproducible",
+        presets::settings_128k_nosound(),
+    );
+    let (frames, hash) = boot(&mut t);
+    let text = t.screen_text();
+    assert!(text.iter().any(|line| line.contains("Tape Loader")));
+    assert_eq!(
+        text.last().unwrap().trim_end(),
+        "\u{a9} 1986 Sinclair Research Ltd"
+    );
+
+    // Snapshot with 48 BASIC ROM paged in and paging locked
+    load_garbage(&mut t, sna_128k(0x30, &FILL_SCREEN_CODE));
+    assert_eq!(boot(&mut t), (frames, hash));
+}
+
+#[test]
+fn boot_does_not_depend_on_frame_position() {
+    let mut t = RustZXTester::new(
+        "boot_does_not_depend_on_frame_position",
+        presets::settings_128k_nosound(),
+    );
+    let booted = boot(&mut t);
+    for instructions in [1, 1000, 3000] {
+        t.emulator().step_instructions(instructions).unwrap();
+        assert_eq!(boot(&mut t), booted);
+    }
+}
+
+#[test]
+fn boot_with_unknown_rom_fails() {
+    let mut t = RustZXTester::new(
+        "boot_with_unknown_rom_fails",
+        presets::settings_128k_nosound(),
+    );
+    t.load_rom_pages(vec![vec![0; 16 * 1024], vec![0; 16 * 1024]]);
+    assert!(matches!(
+        t.emulator().boot_to_basic(),
+        Err(Error::Boot(BootError::UnknownRom))
+    ));
+}
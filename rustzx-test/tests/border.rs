@@ -0,0 +1,206 @@
+use expect_test::expect;
+use rustzx_core::zx::{
+    constants::{CANVAS_HEIGHT, SCREEN_HEIGHT, SCREEN_WIDTH},
+    machine::ZXMachine,
+    video::colors::ZXColor,
+};
+use rustzx_test::framework::RustZXTester;
+use rustzx_utils::palette::rgba::ORIGINAL;
+
+// EI
+// loop: HALT
+//       LD A, 2; OUT (0xFE), A
+//       LD A, 5; OUT (0xFE), A
+//       JR loop
+const BORDER_SPLIT_CODE: [u8; 12] = [
+    0xFB, 0x76, 0x3E, 0x02, 0xD3, 0xFE, 0x3E, 0x05, 0xD3, 0xFE, 0x18, 0xF5,
+];
+
+fn border_split_tester(name: &str) -> RustZXTester {
+    RustZXTester::with_code(name, &BORDER_SPLIT_CODE)
+}
+
+#[test]
+fn border_events_of_last_frame() {
+    let mut tester = border_split_tester("border_events_of_last_frame");
+    tester.emulate_frame();
+    tester.emulate_frame();
+
+    let events = tester.emulator().border_events().to_vec();
+    assert_eq!(events[0], (0, ZXColor::Cyan));
+    assert!(events.windows(2).all(|w| w[0].0 <= w[1].0));
+    expect![[r#"[(0, Cyan), (973, Red), (991, Cyan)]"#]].assert_eq(&format!("{:?}", events));
+
+    // Events are collected per frame, not accumulated. Timings may differ by a
+    // few clocks, as HALT is left at the different frame position
+    tester.emulate_frame();
+    let colors = |events: &[(usize, ZXColor)]| events.iter().map(|e| e.1).collect::<Vec<_>>();
+    assert_eq!(colors(tester.emulator().border_events()), colors(&events));
+}
+
+#[test]
+fn isr_border_debug_shows_handler_duration() {
+    let mut tester = border_split_tester("isr_border_debug_shows_handler_duration");
+    tester.emulator().set_isr_border_debug(Some(6));
+    tester.emulate_frame();
+    tester.emulate_frame();
+
+    let events = tester.emulator().border_events().to_vec();
+    expect![[r#"[(0, Cyan), (16, Yellow), (958, Cyan), (973, Red), (991, Cyan)]"#]]
+        .assert_eq(&format!("{:?}", events));
+    assert_eq!(tester.emulator().border_color(), ZXColor::Cyan);
+
+    tester.emulator().set_isr_border_debug(None);
+    tester.emulate_frame();
+    tester.emulate_frame();
+    let colors = tester
+        .emulator()
+        .border_events()
+        .iter()
+        .map(|e| e.1)
+        .collect::<Vec<_>>();
+    assert_eq!(colors, [ZXColor::Cyan, ZXColor::Red, ZXColor::Cyan]);
+}
+
+/// Handler at 0x8300 (IM 2 vector at 0x81FF) waits for the next interrupt
+/// with interrupts enabled, nested handler returns immediately. Outer handler
+/// then spends ~650 clocks in a loop before the return
+fn nested_isr_code() -> Vec<u8> {
+    let mut code = vec![0; 0x320];
+    // DI; LD A, 0x81; LD I, A; IM 2
+    // LD A, 1; OUT (0xFE), A
+    // EI
+    // loop: HALT; JR loop
+    code[..15].copy_from_slice(&[
+        0xF3, 0x3E, 0x81, 0xED, 0x47, 0xED, 0x5E, 0x3E, 0x01, 0xD3, 0xFE, 0xFB, 0x76, 0x18, 0xFD,
+    ]);
+    code[0x1FF..0x201].copy_from_slice(&[0x00, 0x83]);
+    // LD A, (0x9000); OR A; RET NZ
+    // INC A; LD (0x9000), A
+    // EI; HALT
+    // LD B, 50; DJNZ $
+    // XOR A; LD (0x9000), A
+    // EI; RET
+    code[0x300..].copy_from_slice(&[
+        0x3A, 0x00, 0x90, 0xB7, 0xC0, 0x3C, 0x32, 0x00, 0x90, 0xFB, 0x76, 0x06, 0x32, 0x10, 0xFE,
+        0xAF, 0x32, 0x00, 0x90, 0xFB, 0xC9, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ]);
+    code
+}
+
+#[test]
+fn isr_border_debug_tracks_nested_handlers() {
+    let mut tester = RustZXTester::with_code(
+        "isr_border_debug_tracks_nested_handlers",
+        &nested_isr_code(),
+    );
+    tester.emulator().set_isr_border_debug(Some(6));
+    let events = (0..4)
+        .map(|_| {
+            tester.emulate_frame();
+            tester.emulator().border_events().to_vec()
+        })
+        .collect::<Vec<_>>();
+    expect![[r#"[[(0, Black), (0, Black), (43, Blue)], [(0, Blue), (21, Yellow)], [(0, Yellow), (730, Blue)], [(0, Blue), (21, Yellow)]]"#]].assert_eq(&format!("{:?}", events));
+}
+
+#[test]
+fn isr_border_debug_is_kept_on_machine_switch() {
+    let mut tester = border_split_tester("isr_border_debug_is_kept_on_machine_switch");
+    tester.emulator().set_isr_border_debug(Some(6));
+    tester
+        .emulator()
+        .switch_machine(ZXMachine::Sinclair48KNtsc)
+        .unwrap();
+    tester.load_code(&BORDER_SPLIT_CODE);
+    tester.emulate_frame();
+    tester.emulate_frame();
+    let colors = tester
+        .emulator()
+        .border_events()
+        .iter()
+        .map(|e| e.1)
+        .collect::<Vec<_>>();
+    assert!(colors.contains(&ZXColor::Yellow), "{:?}", colors);
+}
+
+#[test]
+fn border_rendering_can_be_disabled() {
+    let mut tester = border_split_tester("border_rendering_can_be_disabled");
+    tester.emulate_frame();
+    tester.expect_border(
+        "before",
+        expect![[r#"tmGY7e4h+XA3px6BcqnCXF83NEdBqVw8PW9sQtpMAvM="#]],
+    );
+
+    tester.emulator().set_border_rendering(false);
+    tester.load_code(&[0x3E, 0x04, 0xD3, 0xFE, 0x18, 0xFE]);
+    tester.emulate_frame();
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().border_color(), ZXColor::Green);
+    tester.expect_border(
+        "after",
+        expect![[r#"tmGY7e4h+XA3px6BcqnCXF83NEdBqVw8PW9sQtpMAvM="#]],
+    );
+}
+
+// Horizontal border stripes, color is changed each 224 clocks (48K line)
+// EI
+// HALT
+// DI
+// XOR A
+// loop: OUT (0xFE), A
+//       INC A
+//       AND 7
+//       INC HL
+//       LD B, 14
+// wait: DJNZ wait
+//       JR loop
+const BORDER_STRIPES_CODE: [u8; 16] = [
+    0xFB, 0x76, 0xF3, 0xAF, 0xD3, 0xFE, 0x3C, 0xE6, 0x07, 0x23, 0x06, 0x0E, 0x10, 0xFE, 0x18, 0xF4,
+];
+
+/// Count of the rendered top and bottom border lines
+const BORDER_LINES: usize = (SCREEN_HEIGHT - CANVAS_HEIGHT) / 2;
+/// Color of the first rendered line left of the edge
+const STRIPES_FIRST_COLOR: usize = 3;
+/// Position of the color change in each line
+const STRIPES_EDGE_X: usize = 202;
+
+#[test]
+fn border_stripes_in_top_and_bottom_border() {
+    let mut tester = RustZXTester::with_code("border_stripes", &BORDER_STRIPES_CODE);
+    tester.emulate_frame();
+    tester.emulate_frame();
+
+    // Color changes are not aligned to lines, so the top border has
+    // one stripe per line
+    assert!(tester.emulator().border_events().len() > SCREEN_HEIGHT);
+    // Loop takes 224 clocks, exactly one line, so the color changes at the
+    // same position of each line and the next line starts with that color
+    let border = tester.border_image();
+    let top_lines = 0..BORDER_LINES;
+    let bottom_lines = SCREEN_HEIGHT - BORDER_LINES..SCREEN_HEIGHT;
+    for line in top_lines.chain(bottom_lines) {
+        let color = (STRIPES_FIRST_COLOR + line) % 8;
+        for x in 0..SCREEN_WIDTH {
+            let expected = if x < STRIPES_EDGE_X {
+                color
+            } else {
+                (color + 1) % 8
+            };
+            assert_eq!(
+                border.pixel(x, line),
+                ORIGINAL[expected],
+                "line {}, pixel {}",
+                line,
+                x
+            );
+        }
+    }
+    tester.expect_border(
+        "stripes",
+        expect![[r#"G9jgPejxh6rK78n3AoqZe6aBrARho3++GRvc6CjL0mM="#]],
+    );
+}
@@ -0,0 +1,71 @@
+use expect_test::expect;
+use rustzx_core::{
+    error::BorderSizeError,
+    zx::{
+        constants::{SCREEN_HEIGHT, SCREEN_WIDTH},
+        video::border_size::BorderSize,
+    },
+};
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+#[test]
+fn border_size_validation() {
+    let full = BorderSize::new(32, 24).unwrap();
+    assert_eq!(full, BorderSize::FULL);
+    assert_eq!((full.width(), full.height()), (SCREEN_WIDTH, SCREEN_HEIGHT));
+    assert_eq!(full.offset(), (0, 0));
+
+    let canvas = BorderSize::new(0, 0).unwrap();
+    assert_eq!((canvas.width(), canvas.height()), (256, 192));
+    assert_eq!(canvas.offset(), (32, 24));
+
+    assert!(matches!(
+        BorderSize::new(33, 0),
+        Err(BorderSizeError::TooLarge)
+    ));
+    assert!(matches!(
+        BorderSize::new(0, 25),
+        Err(BorderSizeError::TooLarge)
+    ));
+}
+
+#[test]
+fn border_size_crop_rows() {
+    let buffer = (0..SCREEN_WIDTH * SCREEN_HEIGHT)
+        .map(|i| (i % 251) as u8)
+        .collect::<Vec<_>>();
+    let size = BorderSize::new(8, 4).unwrap();
+    let rows = size.crop_rows(&buffer, 1).collect::<Vec<_>>();
+    assert_eq!(rows.len(), 200);
+    assert!(rows.iter().all(|row| row.len() == 272));
+    let first = 20 * SCREEN_WIDTH + 24;
+    assert_eq!(rows[0], &buffer[first..first + 272]);
+}
+
+#[test]
+fn cropped_capture() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+
+    let mut tester = RustZXTester::new("cropped_capture", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulator().play_tape();
+    tester.emulate_for(Duration::from_millis(2000));
+
+    tester.expect_capture(
+        "full",
+        BorderSize::FULL,
+        expect![[r#"+8nk3R8rAhgjDWs30MEcyN5dCE+OYDAEM3wBDrO6rgo="#]],
+    );
+    tester.expect_capture(
+        "thin",
+        BorderSize::new(8, 8).unwrap(),
+        expect![[r#"31fu/+UMT+DVTSYwOTo9n2KqWgVwtEY7q9y5QFc190A="#]],
+    );
+    tester.expect_capture(
+        "canvas",
+        BorderSize::NONE,
+        expect![[r#"nI+vo8GaRwKwWTPTP2f22Wcgm9nEwMlm16+Cmzird2w="#]],
+    );
+}
@@ -0,0 +1,132 @@
+use rustzx_core::{
+    error::{CartridgeLoadError, Error},
+    host::{BufferCursor, Cartridge},
+    zx::{machine::ZXMachine, video::colors::ZXColor},
+};
+use rustzx_test::framework::{presets, RustZXTester};
+
+const CHUNK_SIZE: usize = 8 * 1024;
+const BANK_DOCK: u8 = 0;
+const BANK_HOME: u8 = 255;
+const CHUNK_ABSENT: u8 = 0;
+const CHUNK_RAM_EMPTY: u8 = 1;
+const CHUNK_ROM: u8 = 2;
+
+// DI
+// LD A, 2; OUT (0xFE), A
+// LD A, 0x42; LD (0x8000), A; LD (0x0100), A; LD (0xE000), A
+// JR $
+const CARTRIDGE_CODE: [u8; 18] = [
+    0xF3, 0x3E, 0x02, 0xD3, 0xFE, 0x3E, 0x42, 0x32, 0x00, 0x80, 0x32, 0x00, 0x01, 0x32, 0x00, 0xE0,
+    0x18, 0xFE,
+];
+
+/// Returns bank block with the given chunk types, chunks with contents
+/// are filled with `data` padded with zeroes
+fn bank(id: u8, chunks: [u8; 8], data: &[u8]) -> Vec<u8> {
+    let mut block = vec![id];
+    block.extend_from_slice(&chunks);
+    let mut contents = vec![0; CHUNK_SIZE];
+    contents[..data.len()].copy_from_slice(data);
+    for _ in chunks.iter().filter(|chunk| **chunk >= CHUNK_ROM) {
+        block.extend_from_slice(&contents);
+    }
+    block
+}
+
+fn insert(t: &mut RustZXTester, image: Vec<u8>) -> rustzx_core::Result<()> {
+    t.emulator()
+        .insert_cartridge(Cartridge::Dck(BufferCursor::new(image)))
+}
+
+#[test]
+fn rom_cartridge_runs_from_dock_bank() {
+    let mut t = RustZXTester::new(
+        "rom_cartridge_runs_from_dock_bank",
+        presets::settings_48k_nosound(),
+    );
+    let chunks = [
+        CHUNK_ROM,
+        CHUNK_ABSENT,
+        CHUNK_ABSENT,
+        CHUNK_ABSENT,
+        CHUNK_ABSENT,
+        CHUNK_ABSENT,
+        CHUNK_ABSENT,
+        CHUNK_RAM_EMPTY,
+    ];
+    insert(&mut t, bank(BANK_DOCK, chunks, &CARTRIDGE_CODE)).expect("Failed to insert cartridge");
+    let rom_byte = t.emulator().peek(0x0100);
+    t.emulate_frame();
+
+    assert_eq!(t.emulator().registers().pc, 0x0010);
+    assert_eq!(t.emulator().border_color(), ZXColor::Red);
+    assert_eq!(t.emulator().peek(0x8000), 0x42);
+    // Cartridge ROM is read-only, its RAM chunk hides the RAM under it
+    assert_eq!(t.emulator().peek(0x0100), rom_byte);
+    assert_eq!(t.emulator().peek(0xE000), 0x42);
+
+    assert!(t.emulator().eject_cartridge());
+    assert!(!t.emulator().eject_cartridge());
+    assert_eq!(t.emulator().peek(0x0000), 0xF3);
+    assert_eq!(t.emulator().peek(0xE000), 0x00);
+}
+
+#[test]
+fn cartridge_is_kept_on_machine_switch() {
+    let mut t = RustZXTester::new(
+        "cartridge_is_kept_on_machine_switch",
+        presets::settings_48k_nosound(),
+    );
+    let chunks = [CHUNK_ROM, 0, 0, 0, 0, 0, 0, 0];
+    insert(&mut t, bank(BANK_DOCK, chunks, &CARTRIDGE_CODE)).expect("Failed to insert cartridge");
+    t.emulator()
+        .switch_machine(ZXMachine::Sinclair48KNtsc)
+        .unwrap();
+    t.emulate_frame();
+
+    // Restarted from the cartridge
+    assert_eq!(t.emulator().registers().pc, 0x0010);
+    assert_eq!(t.emulator().border_color(), ZXColor::Red);
+    assert!(t.emulator().eject_cartridge());
+}
+
+#[test]
+fn invalid_cartridges_are_rejected() {
+    let mut t = RustZXTester::new(
+        "invalid_cartridges_are_rejected",
+        presets::settings_48k_nosound(),
+    );
+    let rom_chunks = [CHUNK_ROM, CHUNK_ROM, 0, 0, 0, 0, 0, 0];
+    let valid = bank(BANK_DOCK, rom_chunks, &[]);
+    let mut truncated = valid.clone();
+    truncated.truncate(valid.len() - 1);
+    let mut partial_header = valid.clone();
+    partial_header.extend_from_slice(&[BANK_HOME, 0, 0]);
+    let mut duplicate = valid.clone();
+    duplicate.extend(bank(BANK_DOCK, [0; 8], &[]));
+    let mut home = valid.clone();
+    home.extend(bank(BANK_HOME, [0; 8], &[]));
+
+    let cases = [
+        (vec![], CartridgeLoadError::EmptyImage),
+        (truncated, CartridgeLoadError::TruncatedImage),
+        (partial_header, CartridgeLoadError::TruncatedImage),
+        (bank(1, [0; 8], &[]), CartridgeLoadError::InvalidBank),
+        (
+            bank(BANK_DOCK, [4; 8], &[]),
+            CartridgeLoadError::InvalidChunkType,
+        ),
+        (duplicate, CartridgeLoadError::DuplicateBank),
+        (home, CartridgeLoadError::UnsupportedBank),
+    ];
+    for (image, expected) in cases {
+        match insert(&mut t, image) {
+            Err(Error::CartridgeLoad(e)) => assert_eq!(e, expected),
+            other => panic!("expected {:?}, got {:?}", expected, other.err()),
+        }
+    }
+    // Nothing is paged in by the failed attempts
+    assert!(!t.emulator().eject_cartridge());
+    insert(&mut t, valid).expect("Failed to insert cartridge");
+}
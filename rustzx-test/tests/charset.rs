@@ -0,0 +1,61 @@
+use rustzx_core::zx::video::colors::ZXAttribute;
+use rustzx_test::framework::{presets, RustZXTester};
+use rustzx_utils::{
+    charset::{render_charset, render_udg, CHAR_SIZE},
+    palette::rgba::{DEUTERANOPIA, ORIGINAL as PALETTE},
+};
+use std::time::Duration;
+
+/// Character set in 48K ROM, starting from space
+const ROM_FONT_ADDR: u16 = 0x3D00;
+/// Black ink on white paper
+const ATTR: u8 = 0x38;
+
+#[test]
+fn render_rom_font() {
+    let mut tester = RustZXTester::new("render_rom_font", presets::settings_48k_nosound());
+    tester.emulate_for(Duration::from_millis(100));
+    let image = render_charset(
+        tester.emulator(),
+        ROM_FONT_ADDR,
+        96,
+        ZXAttribute::from_byte(ATTR),
+        &PALETTE,
+    );
+    assert_eq!((image.width, image.height), (128, 48));
+    assert_eq!(image.rgba.len(), 128 * 48 * 4);
+
+    // `!` is the second character, its vertical bar is drawn at x = 3
+    assert_eq!(image.pixel(CHAR_SIZE + 3, 2), PALETTE[0]);
+    assert_eq!(image.pixel(CHAR_SIZE, 2), PALETTE[7]);
+
+    let image = render_charset(
+        tester.emulator(),
+        ROM_FONT_ADDR,
+        96,
+        ZXAttribute::from_byte(ATTR),
+        &DEUTERANOPIA,
+    );
+    assert_eq!(image.pixel(CHAR_SIZE + 3, 2), DEUTERANOPIA[0]);
+    assert_eq!(image.pixel(CHAR_SIZE, 2), DEUTERANOPIA[7]);
+}
+
+#[test]
+fn udg_defaults_to_letters() {
+    let mut tester = RustZXTester::new("udg_defaults_to_letters", presets::settings_48k_nosound());
+    // UDGs are initialized by ROM on boot as copies of `A`..=`U`
+    tester.emulate_for(Duration::from_millis(2000));
+    let attr = ZXAttribute::from_byte(ATTR);
+    let udg = render_udg(tester.emulator(), attr, &PALETTE);
+    assert_eq!((udg.width, udg.height), (128, 16));
+    let letters = render_charset(
+        tester.emulator(),
+        ROM_FONT_ADDR + 33 * 8,
+        21,
+        attr,
+        &PALETTE,
+    );
+    assert_eq!(udg.rgba, letters.rgba);
+    // Last row is padded with paper
+    assert_eq!(udg.pixel(udg.width - 1, udg.height - 1), PALETTE[7]);
+}
@@ -0,0 +1,68 @@
+use expect_test::expect;
+use rustzx_core::{input::InputEvent, zx::keys::ZXKey};
+use rustzx_test::framework::{presets, RustZXTester};
+use rustzx_utils::image::RgbaImage;
+
+const FRAMES: u64 = 200;
+
+/// Boots ROM from seeded RAM and types `PRINT` keyword via the input queue,
+/// returning state hash for each frame
+fn run_scenario(seed: u64) -> Vec<u64> {
+    let mut settings = presets::settings_48k();
+    settings.ram_init_seed = Some(seed);
+    let mut tester = RustZXTester::new("determinism", settings);
+
+    let emulator = tester.emulator();
+    emulator.queue_input(120, InputEvent::Key(ZXKey::P, true));
+    emulator.queue_input(125, InputEvent::Key(ZXKey::P, false));
+    emulator.queue_input(140, InputEvent::Key(ZXKey::Enter, true));
+    emulator.queue_input(145, InputEvent::Key(ZXKey::Enter, false));
+
+    let mut hashes = vec![tester.state_hash()];
+    for _ in 0..FRAMES {
+        tester.emulate_frame();
+        hashes.push(tester.state_hash());
+    }
+    assert_eq!(tester.emulator().frame_number(), FRAMES);
+    hashes
+}
+
+#[test]
+fn same_scenario_gives_same_state() {
+    let first = run_scenario(42);
+    let second = run_scenario(42);
+    for (frame, (a, b)) in first.iter().zip(second.iter()).enumerate() {
+        assert_eq!(a, b, "state mismatch at frame {}", frame);
+    }
+}
+
+#[test]
+fn ram_init_seed_changes_initial_state() {
+    let first = run_scenario(42);
+    let second = run_scenario(43);
+    assert_ne!(first[0], second[0]);
+}
+
+#[test]
+fn frame_hash_is_stable() {
+    let mut tester = RustZXTester::new("frame_hash_is_stable", presets::settings_48k_nosound());
+    for _ in 0..100 {
+        tester.emulate_frame();
+    }
+    let boot_hash = tester.frame_hash();
+    expect![[r#"B7B545F77222A09D"#]].assert_eq(&format!("{:016X}", boot_hash));
+
+    tester.emulator().send_key(ZXKey::P, true);
+    for _ in 0..10 {
+        tester.emulate_frame();
+    }
+    assert_ne!(tester.frame_hash(), boot_hash);
+}
+
+#[test]
+fn frame_hash_reference_value() {
+    // FNV-1a of 8 bytes of size (1x1) and 4 bytes of the pixel
+    let mut image = RgbaImage::new(1, 1);
+    image.set_pixel(0, 0, [0xFF, 0x00, 0x00, 0xFF]);
+    expect![[r#"9B04381CFA76BEE3"#]].assert_eq(&format!("{:016X}", image.frame_hash()));
+}
@@ -0,0 +1,114 @@
+use rustzx_core::{
+    poke::{Poke, PokeAction},
+    trap::TrapAction,
+};
+use rustzx_test::framework::{presets, RustZXTester};
+use std::{thread, time::Duration};
+
+struct MemPokes(Vec<PokeAction>);
+
+impl Poke for MemPokes {
+    fn actions(&self) -> &[PokeAction] {
+        &self.0
+    }
+}
+
+fn booted_tester(name: &str) -> RustZXTester {
+    let mut tester = RustZXTester::new(name, presets::settings_48k_nosound());
+    tester.emulate_for(Duration::from_millis(2000));
+    tester
+}
+
+fn poke(tester: &mut RustZXTester, addr: u16, value: u8) {
+    tester
+        .emulator()
+        .execute_poke(MemPokes(vec![PokeAction::mem(addr, value)]));
+}
+
+#[test]
+fn dirty_rect_is_empty_for_static_screen() {
+    let mut tester = booted_tester("dirty_rect_is_empty_for_static_screen");
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().dirty_rect(), None);
+}
+
+#[test]
+fn dirty_rect_covers_changed_block() {
+    let mut tester = booted_tester("dirty_rect_covers_changed_block");
+    // Pixel line 9, columns 32..40
+    poke(&mut tester, 0x4124, 0xAA);
+    // Pixel line 28, columns 200..208
+    poke(&mut tester, 0x4479, 0x01);
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().dirty_rect(), Some((32, 9, 176, 20)));
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().dirty_rect(), None);
+}
+
+#[test]
+fn dirty_rect_includes_flashing_cells() {
+    let mut tester = booted_tester("dirty_rect_includes_flashing_cells");
+    // Flashing black on white attribute at row 2, column 3
+    poke(&mut tester, 0x5800 + 2 * 32 + 3, 0xB8);
+    // Some pixels are needed to see flashing
+    poke(&mut tester, 0x4043, 0xF0);
+
+    let rects = (0..40)
+        .filter_map(|_| {
+            tester.emulate_frame();
+            tester.emulator().dirty_rect()
+        })
+        .collect::<Vec<_>>();
+    // Flash state changes each 16 frames
+    assert!(rects.len() >= 2, "{:?}", rects);
+    assert!(
+        rects.iter().all(|&rect| rect == (24, 16, 8, 8)),
+        "{:?}",
+        rects
+    );
+}
+
+/// EI; HALT; JR to HALT
+const HALT_LOOP_CODE: [u8; 4] = [0xFB, 0x76, 0x18, 0xFD];
+
+#[test]
+fn frame_rendered_after_skipped_frames_is_fully_dirty() {
+    let mut tester = RustZXTester::with_code(
+        "frame_rendered_after_skipped_frames_is_fully_dirty",
+        &HALT_LOOP_CODE,
+    );
+    // Slow down each frame after the HALT, so the host falls behind the
+    // emulated frame duration
+    tester.emulator().set_trap(
+        0x8002,
+        Box::new(|_, _| {
+            thread::sleep(Duration::from_millis(40));
+            TrapAction::Continue
+        }),
+    );
+    tester.emulator().set_auto_frameskip(1);
+
+    let mut rects = vec![];
+    while tester.emulator().skipped_frames() < 2 {
+        assert!(rects.len() < 64, "frames were not skipped");
+        tester.emulate_frame();
+        rects.push(tester.emulator().dirty_rect());
+    }
+    // Skipping is decided when the frame starts, emulate the skipped frame
+    // and the rendered one
+    for _ in 0..2 {
+        tester.emulate_frame();
+        rects.push(tester.emulator().dirty_rect());
+    }
+
+    // Screen is static, only the frame rendered after a skipped one is dirty
+    let full = Some((0, 0, 256, 192));
+    assert_eq!(rects[rects.len() - 2..], [None, full]);
+    assert!(
+        rects[1..]
+            .iter()
+            .all(|rect| rect.is_none() || *rect == full),
+        "{:?}",
+        rects
+    );
+}
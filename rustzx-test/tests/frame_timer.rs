@@ -0,0 +1,111 @@
+use rustzx_core::zx::machine::ZXMachine;
+use rustzx_utils::frame_timer::FrameTimer;
+use std::time::{Duration, Instant};
+
+/// Simulates frame loop with the given production time, waiting is simulated
+/// by advancing the clock by the time left until the deadline
+fn run_frame(timer: &mut FrameTimer, now: &mut Instant, production_time: Duration) {
+    timer.begin_frame(*now);
+    *now += production_time;
+    *now += timer.end_frame(*now);
+}
+
+#[test]
+fn frame_timer_keeps_exact_machine_rate() {
+    let start = Instant::now();
+    let mut now = start;
+    let mut timer = FrameTimer::for_machine(ZXMachine::Sinclair48K, start);
+    assert_eq!(timer.frame_length(), Duration::from_micros(19968));
+
+    let mut frames = 0;
+    while now - start < Duration::from_secs(60) {
+        run_frame(&mut timer, &mut now, Duration::from_millis(5));
+        frames += 1;
+    }
+    // 50.08 Hz * 60 s
+    assert_eq!(frames, 3005);
+    let stats = timer.stats();
+    assert_eq!(stats.frames, 3005);
+    assert_eq!(stats.late_frames, 0);
+    // Measured at the frame end, so emulation is ahead by the time left until deadline
+    assert!(
+        (stats.drift - 0.014968).abs() < 1e-6,
+        "drift: {}",
+        stats.drift
+    );
+    assert_eq!(stats.average_frame_time, Duration::from_millis(5));
+    assert_eq!(stats.max_frame_time, Duration::from_millis(5));
+}
+
+#[test]
+fn frame_timer_catches_up_after_late_frame() {
+    let start = Instant::now();
+    let mut now = start;
+    let mut timer = FrameTimer::new(Duration::from_millis(20), start);
+
+    run_frame(&mut timer, &mut now, Duration::from_millis(5));
+    run_frame(&mut timer, &mut now, Duration::from_millis(30));
+    let stats = timer.stats();
+    assert_eq!(stats.late_frames, 1);
+    assert!((stats.drift + 0.010).abs() < 1e-6, "drift: {}", stats.drift);
+
+    // Next frames are produced without waiting until the schedule is restored
+    run_frame(&mut timer, &mut now, Duration::from_millis(5));
+    assert_eq!(now - start, Duration::from_millis(60));
+    run_frame(&mut timer, &mut now, Duration::from_millis(5));
+    assert_eq!(now - start, Duration::from_millis(80));
+
+    let stats = timer.stats();
+    assert_eq!(stats.frames, 4);
+    assert_eq!(stats.late_frames, 1);
+    assert_eq!(stats.resyncs, 0);
+    assert!((stats.drift - 0.015).abs() < 1e-6, "drift: {}", stats.drift);
+    assert_eq!(stats.p95_frame_time, Duration::from_millis(5));
+    assert_eq!(stats.max_frame_time, Duration::from_millis(30));
+}
+
+#[test]
+fn frame_timer_restarts_schedule_after_stall() {
+    let start = Instant::now();
+    let mut now = start;
+    let mut timer = FrameTimer::new(Duration::from_millis(20), start);
+
+    run_frame(&mut timer, &mut now, Duration::from_millis(500));
+    let stats = timer.stats();
+    assert_eq!(stats.resyncs, 1);
+    assert_eq!(stats.drift, 0.0);
+
+    // Schedule continues from the stall end without burst of frames
+    run_frame(&mut timer, &mut now, Duration::from_millis(5));
+    assert_eq!(now - start, Duration::from_millis(520));
+    assert_eq!(timer.stats().late_frames, 1);
+}
+
+#[test]
+fn due_frames_paced_by_display_rate() {
+    let start = Instant::now();
+    let mut display = FrameTimer::new(Duration::from_micros(16_667), start);
+    let mut machine = FrameTimer::new(Duration::from_millis(20), start);
+    let mut frames = Vec::new();
+    for _ in 0..60 {
+        frames.push(machine.take_due_frames(display.deadline()));
+        display.end_frame(display.deadline());
+    }
+    // 50 frames in 60 display frames, every 6th display frame repeats
+    // the previous emulated frame
+    assert_eq!(frames.iter().sum::<u32>(), 50);
+    assert_eq!(frames[..6], [0, 1, 1, 1, 1, 1]);
+}
+
+#[test]
+fn due_frames_follow_restarted_schedule() {
+    let start = Instant::now();
+    let frame = Duration::from_millis(20);
+    let mut machine = FrameTimer::new(frame, start);
+    assert_eq!(machine.take_due_frames(start + frame), 1);
+    // Display was stalled for a second and restarted its schedule
+    let deadline = start + Duration::from_secs(1);
+    assert_eq!(machine.take_due_frames(deadline), 1);
+    assert_eq!(machine.take_due_frames(deadline + frame), 1);
+    assert_eq!(machine.stats().resyncs, 1);
+}
@@ -0,0 +1,83 @@
+use rustzx_core::{zx::ports::Peripheral, RustzxSettings};
+use rustzx_test::framework::{presets, RustZXTester};
+
+// XOR A      ; OUT (0x3F), A
+// LD A, 0x80 ; OUT (0x5F), A   -> channel A tone period
+// LD A, 0x07 ; OUT (0x3F), A   -> select mixer register
+// LD A, 0x3E ; OUT (0x5F), A   -> tone A enabled
+// LD A, 0x08 ; OUT (0x3F), A
+// LD A, 0x0F ; OUT (0x5F), A   -> channel A volume
+// LD A, 0x07 ; OUT (0x3F), A
+// IN A, (0x3F)
+// LD (0x9000), A
+// JR $
+const FULLER_AY_CODE: [u8; 34] = [
+    0xAF, 0xD3, 0x3F, 0x3E, 0x80, 0xD3, 0x5F, 0x3E, 0x07, 0xD3, 0x3F, 0x3E, 0x3E, 0xD3, 0x5F, 0x3E,
+    0x08, 0xD3, 0x3F, 0x3E, 0x0F, 0xD3, 0x5F, 0x3E, 0x07, 0xD3, 0x3F, 0xDB, 0x3F, 0x32, 0x00, 0x90,
+    0x18, 0xFE,
+];
+
+fn fuller_tester(name: &str, fuller_ay_enabled: bool) -> RustZXTester {
+    let settings = RustzxSettings {
+        ay_enabled: false,
+        fuller_enabled: true,
+        fuller_ay_enabled,
+        ..presets::settings_48k()
+    };
+    let mut tester = RustZXTester::new(name, settings);
+    tester.load_code(&FULLER_AY_CODE);
+    tester
+}
+
+#[test]
+fn fuller_ay_registers() {
+    let mut tester = fuller_tester("fuller_ay_registers", true);
+    tester.emulate_frame();
+    assert_eq!(tester.peek(0x9000), 0x3E);
+}
+
+/// Returns silence flag of the second frame, samples are taken after each frame
+fn second_frame_silent(tester: &mut RustZXTester) -> bool {
+    for _ in 0..2 {
+        tester.emulate_frame();
+        while tester.emulator().next_audio_sample().is_some() {}
+    }
+    tester.emulator().was_silent_last_frame()
+}
+
+#[test]
+fn fuller_ay_sound() {
+    let mut tester = fuller_tester("fuller_ay_sound", true);
+    assert!(!second_frame_silent(&mut tester));
+    // Without the Fuller AY its ports are not decoded and tone is not started
+    let mut tester = fuller_tester("fuller_ay_sound", false);
+    assert!(second_frame_silent(&mut tester));
+}
+
+#[test]
+fn fuller_box_port_conflicts() {
+    let tester = |kempston_enabled, mouse_enabled| {
+        let settings = RustzxSettings {
+            kempston_enabled,
+            mouse_enabled,
+            fuller_enabled: true,
+            fuller_ay_enabled: true,
+            ..presets::settings_48k()
+        };
+        RustZXTester::new("fuller_box_port_conflicts", settings)
+    };
+
+    assert_eq!(tester(true, false).emulator().port_conflicts(), []);
+    // Mouse buttons port decoding (A0 set, A5 and A8 reset) catches 0x5F
+    assert_eq!(
+        tester(false, true).emulator().port_conflicts(),
+        [(Peripheral::KempstonMouse, Peripheral::FullerAy)]
+    );
+    assert_eq!(
+        tester(true, true).emulator().port_conflicts(),
+        [
+            (Peripheral::KempstonJoystick, Peripheral::KempstonMouse),
+            (Peripheral::KempstonMouse, Peripheral::FullerAy),
+        ]
+    );
+}
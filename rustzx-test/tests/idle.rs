@@ -0,0 +1,77 @@
+use rustzx_core::{host::BufferCursor, idle::IdleConfig, zx::keys::ZXKey, ReloadMode};
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+const CONFIG: IdleConfig = IdleConfig {
+    window_frames: 50,
+    max_pc_pages: 8,
+    max_port_writes: 0,
+};
+
+fn tester(name: &str, code: &[u8]) -> RustZXTester {
+    let mut tester = RustZXTester::with_code(name, code);
+    tester.enable_idle_detection(CONFIG);
+    tester
+}
+
+fn emulate_frames(tester: &mut RustZXTester, frames: usize) {
+    for _ in 0..frames {
+        tester.emulate_frame();
+    }
+}
+
+#[test]
+fn idle_loop() {
+    // 0x8000: JR 0x8000
+    let mut tester = tester("idle_loop", &[0x18, 0xFE]);
+    emulate_frames(&mut tester, 45);
+    assert!(!tester.emulator().is_idle());
+    emulate_frames(&mut tester, 10);
+    assert!(tester.emulator().is_idle());
+    assert_eq!(tester.take_idle_changes(), [true]);
+
+    // Loop is patched to write the border
+    // 0x8000: JR 0x8002
+    // 0x8002: OUT (0xFE), A
+    // 0x8004: JR 0x8002
+    tester
+        .emulator()
+        .reload_binary(
+            BufferCursor::new(vec![0x18, 0x00, 0xD3, 0xFE, 0x18, 0xFC]),
+            0x8000,
+            ReloadMode::PreserveState,
+        )
+        .unwrap();
+    emulate_frames(&mut tester, 2);
+    assert!(!tester.emulator().is_idle());
+    assert_eq!(tester.take_idle_changes(), [false]);
+}
+
+#[test]
+fn port_writes_are_activity() {
+    // 0x8000: OUT (0xFE), A
+    // 0x8002: JR 0x8000
+    let mut tester = tester("port_writes_are_activity", &[0xD3, 0xFE, 0x18, 0xFC]);
+    emulate_frames(&mut tester, 100);
+    assert!(!tester.emulator().is_idle());
+    assert!(tester.take_idle_changes().is_empty());
+}
+
+#[test]
+fn rom_waiting_for_key() {
+    let mut tester = RustZXTester::new("rom_waiting_for_key", presets::settings_48k_nosound());
+    tester.emulate_for(Duration::from_millis(2000));
+    tester.enable_idle_detection(IdleConfig {
+        window_frames: 50,
+        ..Default::default()
+    });
+    emulate_frames(&mut tester, 60);
+    assert!(tester.emulator().is_idle());
+    assert_eq!(tester.take_idle_changes(), [true]);
+
+    // Key press starts the BASIC editor
+    tester.send_keypress(ZXKey::Enter);
+    emulate_frames(&mut tester, 10);
+    assert!(!tester.emulator().is_idle());
+    assert_eq!(tester.take_idle_changes(), [false]);
+}
@@ -0,0 +1,128 @@
+use rustzx_core::{
+    host::{FrameBuffer, FrameBufferSource},
+    zx::video::colors::{ZXBrightness, ZXColor},
+};
+use rustzx_utils::{
+    indexed::{FrameBufferPool, IndexedFrameBuffer, IndexedFrameBufferContext},
+    palette::{rgb, rgba, to_rgb},
+    tui::IndexedScreen,
+};
+
+#[test]
+fn indexed_frame_buffer_stores_palette_indices() {
+    let mut buffer = IndexedFrameBuffer::new(
+        4,
+        2,
+        FrameBufferSource::Screen,
+        IndexedFrameBufferContext::default(),
+    );
+    buffer.set_color(1, 0, ZXColor::Blue, ZXBrightness::Bright);
+    buffer.set_color(3, 1, ZXColor::Yellow, ZXBrightness::Normal);
+    // ULAplus colors are reduced to the standard ones
+    buffer.set_ulaplus_color(0, 1, 0xFF);
+
+    let (pixels, palette) = buffer.clone_indexed();
+    assert_eq!(pixels, [0, 9, 0, 0, 15, 0, 0, 6]);
+    assert_eq!(palette, &rgb::ORIGINAL);
+    assert_eq!(palette[9], [0x00, 0x00, 0xFF]);
+    assert_eq!(buffer.color_index(3, 1), 6);
+}
+
+#[test]
+fn indexed_frame_buffer_block_writes() {
+    let buffer = || {
+        IndexedFrameBuffer::new(
+            16,
+            1,
+            FrameBufferSource::Screen,
+            IndexedFrameBufferContext::default(),
+        )
+    };
+    for bitmap in 0..=255u8 {
+        let mut block = buffer();
+        block.set_block(
+            8,
+            0,
+            bitmap,
+            ZXColor::Red,
+            ZXColor::Cyan,
+            ZXBrightness::Bright,
+        );
+        let mut pixels = buffer();
+        for pixel in 0..8 {
+            let color = if bitmap & (0x80 >> pixel) != 0 {
+                ZXColor::Red
+            } else {
+                ZXColor::Cyan
+            };
+            pixels.set_color(8 + pixel, 0, color, ZXBrightness::Bright);
+        }
+        assert_eq!(block.clone_indexed().0, pixels.clone_indexed().0);
+    }
+}
+
+#[test]
+fn indexed_frame_buffer_rgb565_palette() {
+    let buffer = IndexedFrameBuffer::new(
+        1,
+        1,
+        FrameBufferSource::Screen,
+        IndexedFrameBufferContext {
+            palette: to_rgb(&rgba::ORIGINAL),
+            pool: None,
+        },
+    );
+    let palette = buffer.palette_rgb565();
+    assert_eq!(palette[0], 0x0000);
+    assert_eq!(palette[9], 0x001F);
+    assert_eq!(palette[10], 0xF800);
+    assert_eq!(palette[12], 0x07E0);
+    assert_eq!(palette[15], 0xFFFF);
+    assert_eq!(palette[7], 0xCE79);
+}
+
+#[test]
+fn indexed_frame_buffers_use_pool_storage() {
+    let mut storage = Vec::new();
+    let pool = FrameBufferPool::for_emulator(|len| {
+        let buffer = vec![0xFF; len].into_boxed_slice();
+        storage.push(buffer.as_ptr());
+        buffer
+    });
+    assert_eq!(pool.len(), 3);
+    let context = IndexedFrameBufferContext {
+        pool: Some(pool.clone()),
+        ..Default::default()
+    };
+    let buffers = [
+        FrameBufferSource::Screen,
+        FrameBufferSource::Screen,
+        FrameBufferSource::Border,
+    ]
+    .map(|source| {
+        let (width, height) = source.dimensions();
+        IndexedFrameBuffer::new(width, height, source, context.clone())
+    });
+    assert!(pool.is_empty());
+    for buffer in &buffers {
+        let (pixels, _) = buffer.clone_indexed();
+        assert!(storage.contains(&pixels.as_ptr()));
+        assert_eq!(pixels.len(), buffer.width() * buffer.height());
+        assert!(pixels.iter().all(|&pixel| pixel == 0));
+    }
+    // Empty pool falls back to the internal allocation
+    let buffer = IndexedFrameBuffer::new(8, 8, FrameBufferSource::Screen, context);
+    assert_eq!(buffer.clone_indexed().0.len(), 64);
+    // Only the pool storage is returned on drop
+    drop(buffer);
+    assert!(pool.is_empty());
+    drop(buffers);
+    assert_eq!(pool.len(), 3);
+}
+
+#[test]
+fn indexed_frame_buffers_are_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<IndexedFrameBuffer>();
+    assert_send::<FrameBufferPool>();
+}
@@ -2,8 +2,10 @@ use expect_test::expect;
 use rustzx_core::{
     zx::{
         joy::{
+            fuller::FullerKey,
             kempston::KempstonKey,
             sinclair::{SinclairJoyNum, SinclairKey},
+            JoyButton, JoyInterface,
         },
         keys::{CompoundKey, ZXKey},
     },
@@ -47,6 +49,113 @@ fn kempston_joy() {
     expect![[r#"00,01,03,07,0F,1F,3F,7F,FF,FE,FC,F8,F0,E0,C0,80,00,"#]].assert_eq(&out);
 }
 
+// loop: IN A, (0x7F)
+//       LD (0x9000), A
+//       JR loop
+const FULLER_READ_CODE: [u8; 7] = [0xDB, 0x7F, 0x32, 0x00, 0x90, 0x18, 0xF9];
+
+#[test]
+fn fuller_joy() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.fuller_enabled = true;
+    let mut t = RustZXTester::new("fuller_joy", settings);
+    t.load_code(&FULLER_READ_CODE);
+
+    let mut out = String::new();
+    let mut read_port = |t: &mut RustZXTester| {
+        t.emulate_frame();
+        out += &format!("{:02X},", t.peek(0x9000));
+    };
+
+    // Released keys are read as set bits
+    read_port(&mut t);
+
+    let keys = FullerKey::iter().collect::<Vec<_>>();
+    for k in &keys {
+        t.emulator().send_fuller_key(*k, true);
+        read_port(&mut t);
+    }
+    for k in &keys {
+        t.emulator().send_fuller_key(*k, false);
+        read_port(&mut t);
+    }
+
+    expect![[r#"FF,FE,FC,F8,F0,70,71,73,77,7F,FF,"#]].assert_eq(&out);
+}
+
+// loop: IN A, (0x1F)
+//       LD (0x9000), A
+//       IN A, (0x7F)
+//       LD (0x9001), A
+//       LD A, 0xEF
+//       IN A, (0xFE)
+//       LD (0x9002), A
+//       LD A, 0xF7
+//       IN A, (0xFE)
+//       LD (0x9003), A
+//       JR loop
+const ALL_JOYS_READ_CODE: [u8; 26] = [
+    0xDB, 0x1F, 0x32, 0x00, 0x90, 0xDB, 0x7F, 0x32, 0x01, 0x90, 0x3E, 0xEF, 0xDB, 0xFE, 0x32, 0x02,
+    0x90, 0x3E, 0xF7, 0xDB, 0xFE, 0x32, 0x03, 0x90, 0x18, 0xE6,
+];
+
+#[test]
+fn simultaneous_joys() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.kempston_enabled = true;
+    settings.fuller_enabled = true;
+    let mut t = RustZXTester::new("simultaneous_joys", settings);
+    t.load_code(&ALL_JOYS_READ_CODE);
+
+    let mut out = String::new();
+    let mut read_ports = |t: &mut RustZXTester| {
+        t.emulate_frame();
+        // Kempston, Fuller, keyboard half-rows `6`..`0` and `1`..`5`
+        out += &format!(
+            "{:02X},{:02X},{:02X},{:02X}\n",
+            t.peek(0x9000),
+            t.peek(0x9001),
+            t.peek(0x9002) & 0x1F,
+            t.peek(0x9003) & 0x1F,
+        );
+    };
+
+    let presses = [
+        (JoyInterface::Kempston, JoyButton::Left),
+        (JoyInterface::Fuller, JoyButton::Right),
+        (
+            JoyInterface::Sinclair(SinclairJoyNum::Fist),
+            JoyButton::Fire,
+        ),
+        (
+            JoyInterface::Sinclair(SinclairJoyNum::Second),
+            JoyButton::Up,
+        ),
+    ];
+
+    read_ports(&mut t);
+    for (interface, button) in presses {
+        t.emulator().send_joy_button(interface, button, true);
+    }
+    read_ports(&mut t);
+    // Releasing one joystick does not affect the others
+    t.emulator()
+        .send_joy_button(JoyInterface::Kempston, JoyButton::Left, false);
+    read_ports(&mut t);
+    for (interface, button) in presses {
+        t.emulator().send_joy_button(interface, button, false);
+    }
+    read_ports(&mut t);
+
+    expect![[r#"
+        00,FF,1F,1F
+        02,F7,1E,17
+        00,F7,1E,17
+        00,FF,1F,1F
+    "#]]
+    .assert_eq(&out);
+}
+
 #[test]
 fn sinclair_joy() {
     let mut t = RustZXTester::new("sinclair_joy", presets::settings_48k_nosound());
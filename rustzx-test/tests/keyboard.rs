@@ -1,9 +1,10 @@
 use expect_test::expect;
 use rustzx_core::{
     zx::keys::{CompoundKey, ZXKey},
-    IterableEnum,
+    IterableEnum, RustzxSettings,
 };
 use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
 
 #[test]
 fn standard_keys() {
@@ -115,3 +116,163 @@ fn compound_keys() {
         expect![[r#"v01HM6RHAtHfvFEnvCXae4dl1FrHEISrnDgljzvMcoE="#]],
     );
 }
+
+#[test]
+fn inject_key_chars() {
+    let mut t = RustZXTester::new("inject_key_chars", presets::settings_48k_nosound());
+    // Wait for ROM to boot
+    t.emulate_for(Duration::from_millis(2000));
+
+    // PRINT 6*7 <ENTER>; PRINT is passed as a keyword token, as keyboard
+    // decoding is performed before LAST K is set
+    for ch in [0xF5, b'6', b'*', b'7', 0x0D] {
+        assert!(!t.emulator().is_key_char_pending());
+        t.emulator().inject_key_char(ch);
+        assert!(t.emulator().is_key_char_pending());
+        t.emulate_frame();
+    }
+    t.emulate_for(Duration::from_millis(100));
+    t.expect_screen(
+        "result",
+        expect![[r#"tlYIrPkuIu/qbxnoOMlI6VAPUqbNQQ7uxjvYcyBzHOE="#]],
+    );
+}
+
+// loop: LD BC, 0xFDFE; IN A, (C); LD (0x9000), A
+//       JR loop
+const READ_ASDFG_ROW_CODE: [u8; 10] = [0x01, 0xFE, 0xFD, 0xED, 0x78, 0x32, 0x00, 0x90, 0x18, 0xF6];
+
+/// Returns keys of A..G half-row (active low) read with `keys` pressed
+fn read_asdfg_row(keyboard_ghosting: bool, keys: &[ZXKey]) -> u8 {
+    let settings = RustzxSettings {
+        keyboard_ghosting,
+        ..presets::settings_48k_nosound()
+    };
+    let mut t = RustZXTester::new("keyboard_ghosting", settings);
+    t.load_code(&READ_ASDFG_ROW_CODE);
+    for key in keys {
+        t.emulator().send_key(*key, true);
+    }
+    t.emulate_frame();
+    t.peek(0x9000) & 0x1F
+}
+
+#[test]
+fn keyboard_ghosting() {
+    // Q, W and A form an L-shape, S is read as pressed too
+    assert_eq!(
+        read_asdfg_row(true, &[ZXKey::Q, ZXKey::W, ZXKey::A]),
+        0b11100
+    );
+    assert_eq!(
+        read_asdfg_row(false, &[ZXKey::Q, ZXKey::W, ZXKey::A]),
+        0b11110
+    );
+    // Ghosting propagates through several half-rows: S-W, Q-P, so U (same
+    // column as F) is read as pressed
+    assert_eq!(
+        read_asdfg_row(true, &[ZXKey::S, ZXKey::W, ZXKey::Q, ZXKey::P, ZXKey::U]),
+        0b10100
+    );
+    // Keys in the same column only do not produce ghosts
+    assert_eq!(
+        read_asdfg_row(true, &[ZXKey::Q, ZXKey::A, ZXKey::P]),
+        0b11110
+    );
+    // Unselected half-row without shared columns is not read
+    assert_eq!(read_asdfg_row(true, &[ZXKey::W, ZXKey::P]), 0b11111);
+}
+
+// start: LD HL, 0x9000; LD BC, 0x00FE
+// loop:  IN A, (C); LD (HL), A; INC L; INC B; JR NZ, loop
+//        JR start
+const READ_ALL_HALF_ROWS_CODE: [u8; 15] = [
+    0x21, 0x00, 0x90, 0x01, 0xFE, 0x00, 0xED, 0x78, 0x77, 0x2C, 0x04, 0x20, 0xF9, 0x18, 0xF1,
+];
+
+/// Keys of the half-rows selected by address lines A8..A15, from bit 0
+const HALF_ROWS: [[ZXKey; 5]; 8] = [
+    [ZXKey::Shift, ZXKey::Z, ZXKey::X, ZXKey::C, ZXKey::V],
+    [ZXKey::A, ZXKey::S, ZXKey::D, ZXKey::F, ZXKey::G],
+    [ZXKey::Q, ZXKey::W, ZXKey::E, ZXKey::R, ZXKey::T],
+    [ZXKey::N1, ZXKey::N2, ZXKey::N3, ZXKey::N4, ZXKey::N5],
+    [ZXKey::N0, ZXKey::N9, ZXKey::N8, ZXKey::N7, ZXKey::N6],
+    [ZXKey::P, ZXKey::O, ZXKey::I, ZXKey::U, ZXKey::Y],
+    [ZXKey::Enter, ZXKey::L, ZXKey::K, ZXKey::J, ZXKey::H],
+    [ZXKey::Space, ZXKey::SymShift, ZXKey::M, ZXKey::N, ZXKey::B],
+];
+
+/// Returns expected keys (active low) of all half-rows selected by zero bits
+/// of `high` address byte
+fn expected_half_rows(high: u8, pressed: &[ZXKey]) -> u8 {
+    let mut value = 0x1F;
+    for (row, keys) in HALF_ROWS.iter().enumerate() {
+        if high & (1 << row) != 0 {
+            continue;
+        }
+        for (column, key) in keys.iter().enumerate() {
+            if pressed.contains(key) {
+                value &= !(1 << column);
+            }
+        }
+    }
+    value
+}
+
+/// Checks port 0xFE reads with each of 256 high address bytes, stored by
+/// [READ_ALL_HALF_ROWS_CODE] at 0x9000
+fn check_half_rows(t: &mut RustZXTester, pressed: &[ZXKey]) {
+    for key in pressed {
+        t.emulator().send_key(*key, true);
+    }
+    t.emulate_frame();
+    for high in 0..=0xFFu8 {
+        assert_eq!(
+            t.peek(0x9000 + high as u16) & 0x1F,
+            expected_half_rows(high, pressed),
+            "port {:02X}FE with {:?} pressed",
+            high,
+            pressed
+        );
+    }
+    for key in pressed {
+        t.emulator().send_key(*key, false);
+    }
+}
+
+fn half_rows_tester(name: &str) -> RustZXTester {
+    RustZXTester::with_code(name, &READ_ALL_HALF_ROWS_CODE)
+}
+
+#[test]
+fn single_key_half_rows() {
+    let mut t = half_rows_tester("single_key_half_rows");
+    check_half_rows(&mut t, &[]);
+    for key in ZXKey::iter() {
+        check_half_rows(&mut t, &[key]);
+    }
+}
+
+#[test]
+fn multiple_keys_half_rows() {
+    let mut t = half_rows_tester("multiple_keys_half_rows");
+    // Different columns in different half-rows
+    check_half_rows(&mut t, &[ZXKey::Z, ZXKey::G]);
+    // Same column in different half-rows
+    check_half_rows(&mut t, &[ZXKey::A, ZXKey::Q, ZXKey::Space]);
+    // Keys in every half-row, all rows (high byte 0x00) read combined
+    check_half_rows(
+        &mut t,
+        &[
+            ZXKey::Shift,
+            ZXKey::S,
+            ZXKey::E,
+            ZXKey::N4,
+            ZXKey::N6,
+            ZXKey::P,
+            ZXKey::L,
+            ZXKey::M,
+        ],
+    );
+    assert_eq!(t.peek(0x9000) & 0x1F, 0b00000);
+}
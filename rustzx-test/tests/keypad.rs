@@ -0,0 +1,56 @@
+use rustzx_core::zx::keypad::KeypadKey;
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+const KEY_DOWN: u8 = 0x0A;
+const KEY_ENTER: u8 = 0x0D;
+
+fn press_keypad_keys(t: &mut RustZXTester, keys: &[KeypadKey]) {
+    for &key in keys {
+        t.emulator().keypad_key_down(key);
+        t.emulate_for(Duration::from_millis(100));
+        t.emulator().keypad_key_up(key);
+        t.emulate_for(Duration::from_millis(100));
+    }
+}
+
+/// Boots 128K with attached keypad and enters the calculator
+fn boot_128_calculator(test_name: &str) -> RustZXTester {
+    let mut t = RustZXTester::new(test_name, presets::settings_128k_nosound());
+    t.emulator().attach_keypad();
+    t.emulate_for(Duration::from_millis(3000));
+    t.type_chars(&[KEY_DOWN, KEY_DOWN, KEY_ENTER]);
+    // Keypad is detected by ROM in about 3 seconds
+    t.emulate_for(Duration::from_millis(4000));
+    t
+}
+
+#[test]
+fn keypad_calculator() {
+    let mut t = boot_128_calculator("keypad_calculator");
+    press_keypad_keys(
+        &mut t,
+        &[
+            KeypadKey::N1,
+            KeypadKey::Plus,
+            KeypadKey::N2,
+            KeypadKey::Multiply,
+            KeypadKey::N3,
+            KeypadKey::Enter,
+        ],
+    );
+    t.emulate_for(Duration::from_millis(500));
+    let text = t.screen_text();
+    assert_eq!(text[0].trim_end(), "1+2*3");
+    assert_eq!(text[1].trim_end(), "7");
+}
+
+#[test]
+fn keypad_detached() {
+    let mut t = boot_128_calculator("keypad_detached");
+    assert!(t.emulator().detach_keypad());
+    t.emulator().keypad_key_down(KeypadKey::N5);
+    t.emulate_for(Duration::from_millis(500));
+    assert!(t.screen_text()[0].trim().is_empty());
+    assert!(!t.emulator().detach_keypad());
+}
@@ -0,0 +1,222 @@
+//! Feeds damaged variants of valid assets to the loaders, same entry points
+//! are used by the cargo-fuzz targets in `fuzz/`
+
+use rustzx_core::{
+    error::Error,
+    host::{Screen, Snapshot},
+};
+use rustzx_test::{
+    framework::{presets, tap_block, RustZXTester},
+    fuzz,
+    sna::{sna_128k, sna_48k},
+};
+use rustzx_utils::io::{MemoryAsset, MAX_ASSET_SIZE};
+use std::{
+    io::{self, Read},
+    time::Duration,
+};
+
+const MUTATIONS: usize = 32;
+
+/// xorshift64, deterministic mutations keep failures reproducible
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+}
+
+fn mutate(rng: &mut Rng, data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    for _ in 0..=rng.below(4) {
+        match rng.below(5) {
+            0 => {
+                let len = rng.below(data.len() + 1);
+                data.truncate(len);
+            }
+            1 if !data.is_empty() => {
+                let offset = rng.below(data.len());
+                data[offset] ^= 1 << rng.below(8);
+            }
+            // Length fields set to extreme values
+            2 if data.len() >= 2 => {
+                let offset = rng.below(data.len() - 1);
+                let value = [0x0000u16, 0x0001, 0x7FFF, 0xFFFF][rng.below(4)];
+                data[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+            }
+            3 => {
+                let extra = rng.below(64);
+                data.extend((0..extra).map(|_| rng.next() as u8));
+            }
+            _ if !data.is_empty() => {
+                let offset = rng.below(data.len());
+                data[offset] = rng.next() as u8;
+            }
+            _ => {}
+        }
+    }
+    data
+}
+
+fn check_loader(seed: u64, valid: &[u8], load: fn(&[u8])) {
+    let mut rng = Rng(seed);
+    load(valid);
+    load(&[]);
+    for _ in 0..MUTATIONS {
+        load(&mutate(&mut rng, valid));
+    }
+}
+
+/// BASIC program `10 RANDOMIZE USR 32768` followed by a code block
+fn basic_loader_tap() -> Vec<u8> {
+    let program = [
+        0x00, 0x0A, 0x0E, 0x00, 0xF9, 0xC0, b'3', b'2', b'7', b'6', b'8', 0x0E, 0x00, 0x00, 0x00,
+        0x80, 0x00, 0x0D,
+    ];
+    let mut header = vec![0x00];
+    header.extend_from_slice(b"loader    ");
+    header.extend_from_slice(&(program.len() as u16).to_le_bytes());
+    header.extend_from_slice(&10u16.to_le_bytes());
+    header.extend_from_slice(&(program.len() as u16).to_le_bytes());
+    let mut tap = tap_block(0x00, &header);
+    tap.extend(tap_block(0xFF, &program));
+    // JR $
+    tap.extend(tap_block(0xFF, &[0x18, 0xFE]));
+    tap
+}
+
+/// Archive with a single stored entry
+fn zip_with_entry(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    let mut common = vec![0, 0, 0, 0, 0, 0, 0, 0];
+    common.extend(crc.sum().to_le_bytes());
+    common.extend((data.len() as u32).to_le_bytes());
+    common.extend((data.len() as u32).to_le_bytes());
+    common.extend((name.len() as u16).to_le_bytes());
+    common.extend([0, 0]);
+
+    let mut zip = 0x04034B50u32.to_le_bytes().to_vec();
+    zip.extend([20, 0]);
+    zip.extend(&common);
+    zip.extend(name.as_bytes());
+    zip.extend(data);
+    let central_dir_offset = zip.len() as u32;
+    zip.extend(0x02014B50u32.to_le_bytes());
+    zip.extend([20, 0, 20, 0]);
+    zip.extend(&common);
+    zip.extend([0; 14]);
+    zip.extend(name.as_bytes());
+    let central_dir_size = zip.len() as u32 - central_dir_offset;
+    zip.extend(0x06054B50u32.to_le_bytes());
+    zip.extend([0, 0, 0, 0, 1, 0, 1, 0]);
+    zip.extend(central_dir_size.to_le_bytes());
+    zip.extend(central_dir_offset.to_le_bytes());
+    zip.extend([0, 0]);
+    zip
+}
+
+#[test]
+fn damaged_sna_files() {
+    // JR $
+    check_loader(1, &sna_48k(0xFEFE, &[0x18, 0xFE]), fuzz::load_sna);
+    check_loader(2, &sna_128k(0x10, &[0x18, 0xFE]), fuzz::load_sna);
+}
+
+#[test]
+fn damaged_tap_files() {
+    check_loader(3, &basic_loader_tap(), fuzz::load_tap);
+}
+
+#[test]
+fn damaged_scr_files() {
+    check_loader(4, &[0x38; 6912], fuzz::load_scr);
+}
+
+#[test]
+fn damaged_dck_files() {
+    // DOCK bank with ROM chunk 0 and empty RAM chunk 7, JR $
+    let mut dck = vec![0, 2, 0, 0, 0, 0, 0, 0, 1, 0x18, 0xFE];
+    dck.resize(9 + 8192, 0);
+    check_loader(7, &dck, fuzz::load_dck);
+}
+
+#[test]
+fn damaged_plusd_disk_images() {
+    check_loader(5, &vec![0xE5; 819200], fuzz::load_plusd_disk);
+}
+
+#[test]
+fn damaged_betadisk_disk_images() {
+    // SCL archive with a single one-sector code file
+    let mut scl = b"SINCLAIR\x01test    C".to_vec();
+    scl.extend([0x00, 0x80, 0x00, 0x01, 0x01]);
+    scl.extend([0xC9; 256]);
+    let checksum = scl.iter().map(|&byte| byte as u32).sum::<u32>();
+    scl.extend(checksum.to_le_bytes());
+    check_loader(8, &scl, fuzz::load_betadisk_disk);
+}
+
+#[test]
+fn damaged_zip_archives() {
+    check_loader(
+        6,
+        &zip_with_entry("game.tap", &basic_loader_tap()),
+        fuzz::load_zip,
+    );
+}
+
+fn assert_not_read_error(result: rustzx_core::Result<()>) {
+    if let Err(Error::AssetRead(e)) = result {
+        panic!("Damaged data in memory is reported as read error {:?}", e);
+    }
+}
+
+#[test]
+fn damaged_data_in_memory_is_not_read_error() {
+    let mut rng = Rng(8);
+    let sna = sna_48k(0xFEFE, &[0x18, 0xFE]);
+    let tap = basic_loader_tap();
+    let scr = [0x38; 6912];
+    for _ in 0..MUTATIONS {
+        let mut tester =
+            RustZXTester::new("damaged_data_in_memory", presets::settings_48k_nosound());
+        let emulator = tester.emulator();
+        let sna = mutate(&mut rng, &sna);
+        assert_not_read_error(emulator.load_snapshot(Snapshot::sna_from_bytes(sna)));
+        let scr = mutate(&mut rng, &scr);
+        assert_not_read_error(emulator.load_screen(Screen::scr_from_bytes(scr)));
+        let result = tester.load_tap_data(mutate(&mut rng, &tap));
+        assert_not_read_error(result);
+        for _ in 0..5 {
+            let result = tester.emulator().emulate_frames(Duration::from_millis(100));
+            assert_not_read_error(result.map(|_| ()));
+        }
+    }
+}
+
+#[test]
+fn memory_asset_from_reader() {
+    // LD A, 0x5A; LD (0x9000), A; JR $
+    let sna = sna_48k(0xFEFE, &[0x3E, 0x5A, 0x32, 0x00, 0x90, 0x18, 0xFE]);
+    let asset = MemoryAsset::from_reader(sna.as_slice()).expect("Failed to read asset");
+    let mut tester = RustZXTester::new("memory_asset_from_reader", presets::settings_48k_nosound());
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(asset))
+        .expect("Failed to load snapshot");
+    tester.emulate_frame();
+    assert_eq!(tester.peek(0x9000), 0x5A);
+
+    let oversized = io::repeat(0).take(MAX_ASSET_SIZE as u64 + 1);
+    let error = MemoryAsset::from_reader(oversized).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,173 @@
+use rustzx_core::{
+    error::{Error, MachineMismatchError},
+    host::{BufferCursor, Snapshot},
+    zx::machine::{MachinePolicy, ZXMachine},
+    RustzxSettings,
+};
+use rustzx_test::{
+    framework::{presets, tap_block, RustZXTester},
+    sna::{sna_128k, sna_48k},
+};
+
+// 0x8000: JR 0x8000
+const LOOP_CODE: [u8; 2] = [0x18, 0xFE];
+
+fn policy_tester(name: &str, settings: RustzxSettings, policy: MachinePolicy) -> RustZXTester {
+    RustZXTester::new(
+        name,
+        RustzxSettings {
+            machine_policy: policy,
+            ..settings
+        },
+    )
+}
+
+fn load_sna(tester: &mut RustZXTester, sna: Vec<u8>) -> rustzx_core::Result<()> {
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna)))
+}
+
+/// Builds tape with a BASIC program of the single line 10
+fn basic_tap(line: &[u8]) -> Vec<u8> {
+    let mut program = vec![0x00, 0x0A];
+    program.extend_from_slice(&(line.len() as u16 + 1).to_le_bytes());
+    program.extend_from_slice(line);
+    program.push(0x0D);
+
+    let mut header = vec![0x00];
+    header.extend_from_slice(b"loader    ");
+    header.extend_from_slice(&(program.len() as u16).to_le_bytes());
+    header.extend_from_slice(&10u16.to_le_bytes());
+    header.extend_from_slice(&(program.len() as u16).to_le_bytes());
+    let mut tap = tap_block(0x00, &header);
+    tap.extend(tap_block(0xFF, &program));
+    tap
+}
+
+#[test]
+fn snapshot_auto_switch() {
+    let mut tester = policy_tester(
+        "snapshot_auto_switch",
+        presets::settings_48k_nosound(),
+        MachinePolicy::AutoSwitch,
+    );
+    load_sna(&mut tester, sna_128k(0x18, &LOOP_CODE)).unwrap();
+    assert_eq!(tester.emulator().machine(), ZXMachine::Sinclair128K);
+    assert_eq!(tester.emulator().registers().pc, 0x8000);
+    assert_eq!(tester.emulator().display_bank(), 7);
+
+    load_sna(&mut tester, sna_48k(0xFEFE, &LOOP_CODE)).unwrap();
+    assert_eq!(tester.emulator().machine(), ZXMachine::Sinclair48K);
+    assert_eq!(tester.emulator().registers().pc, 0x8000);
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().registers().pc, 0x8000);
+}
+
+#[test]
+fn snapshot_strict() {
+    let mut tester = policy_tester(
+        "snapshot_strict",
+        presets::settings_48k_nosound(),
+        MachinePolicy::Strict,
+    );
+    assert!(matches!(
+        load_sna(&mut tester, sna_128k(0x11, &LOOP_CODE)),
+        Err(Error::MachineMismatch(MachineMismatchError::Requires128K))
+    ));
+    assert_eq!(tester.emulator().machine(), ZXMachine::Sinclair48K);
+    load_sna(&mut tester, sna_48k(0xFEFE, &LOOP_CODE)).unwrap();
+}
+
+#[test]
+fn snapshot_warn_only() {
+    // Visible banks of 128K snapshot are loaded into 48K machine
+    let mut tester = policy_tester(
+        "snapshot_warn_only_48k",
+        presets::settings_48k_nosound(),
+        MachinePolicy::WarnOnly,
+    );
+    load_sna(&mut tester, sna_128k(0x11, &LOOP_CODE)).unwrap();
+    assert_eq!(tester.emulator().machine(), ZXMachine::Sinclair48K);
+    assert_eq!(tester.emulator().registers().pc, 0x8000);
+    assert_eq!(tester.peek(0x8000), LOOP_CODE[0]);
+
+    // 48K snapshot runs on 128K machine with 48K ROM and locked paging
+    let mut tester = policy_tester(
+        "snapshot_warn_only_128k",
+        presets::settings_128k_nosound(),
+        MachinePolicy::WarnOnly,
+    );
+    load_sna(&mut tester, sna_48k(0xFEFE, &LOOP_CODE)).unwrap();
+    assert_eq!(tester.emulator().machine(), ZXMachine::Sinclair128K);
+    let rom_48k = tester.emulator().rom_page(1).unwrap()[..0x100].to_vec();
+    assert!((0..0x100).all(|addr| tester.peek(addr) == rom_48k[addr as usize]));
+    assert_eq!(tester.emulator().registers().pc, 0x8000);
+    assert_eq!(tester.peek(0x8000), LOOP_CODE[0]);
+}
+
+#[test]
+fn tape_128k_basic_hint() {
+    // 10 PLAY "c"
+    let tap = basic_tap(&[0xA4, b'"', b'c', b'"']);
+    let mut tester = policy_tester(
+        "tape_128k_basic_hint",
+        presets::settings_48k_nosound(),
+        MachinePolicy::AutoSwitch,
+    );
+    tester.load_tap_data(tap.clone()).unwrap();
+    assert_eq!(tester.emulator().machine(), ZXMachine::Sinclair128K);
+
+    // Machine state is not replaced without autoload
+    let mut settings = presets::settings_48k_nosound();
+    settings.autoload_enabled = false;
+    let mut tester = policy_tester(
+        "tape_128k_basic_hint_no_autoload",
+        settings,
+        MachinePolicy::AutoSwitch,
+    );
+    tester.load_tap_data(tap.clone()).unwrap();
+    assert_eq!(tester.emulator().machine(), ZXMachine::Sinclair48K);
+
+    let mut tester = policy_tester(
+        "tape_128k_basic_hint_strict",
+        presets::settings_48k_nosound(),
+        MachinePolicy::Strict,
+    );
+    assert!(matches!(
+        tester.load_tap_data(tap),
+        Err(Error::MachineMismatch(MachineMismatchError::Requires128K))
+    ));
+}
+
+#[test]
+fn tape_paging_port_hint() {
+    // 10 OUT 32765,16
+    let mut line = vec![0xDF];
+    line.extend_from_slice(b"32765");
+    line.extend_from_slice(&[0x0E, 0x00, 0x00, 0xFD, 0x7F, 0x00]);
+    line.extend_from_slice(b",16");
+    line.extend_from_slice(&[0x0E, 0x00, 0x00, 0x10, 0x00, 0x00]);
+    let mut tester = policy_tester(
+        "tape_paging_port_hint",
+        presets::settings_48k_nosound(),
+        MachinePolicy::AutoSwitch,
+    );
+    tester.load_tap_data(basic_tap(&line)).unwrap();
+    assert_eq!(tester.emulator().machine(), ZXMachine::Sinclair128K);
+}
+
+#[test]
+fn tape_48k_basic_no_hint() {
+    // PLAY token is UDG character in strings and comments of 48K programs
+    let lines: [&[u8]; 2] = [&[0xF5, b'"', 0xA4, b'"'], &[0xEA, 0xA4]];
+    for line in lines {
+        let mut tester = policy_tester(
+            "tape_48k_basic_no_hint",
+            presets::settings_48k_nosound(),
+            MachinePolicy::Strict,
+        );
+        tester.load_tap_data(basic_tap(line)).unwrap();
+        assert_eq!(tester.emulator().machine(), ZXMachine::Sinclair48K);
+    }
+}
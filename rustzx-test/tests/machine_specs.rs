@@ -0,0 +1,235 @@
+use rustzx_core::{
+    error::{Error, MachineSpecsError},
+    host::{BufferCursor, Snapshot},
+    zx::machine::{
+        MemoryModel, UlaFetch, ZXMachine, ZXSpecsBuilder, SPECS_128K, SPECS_48K, SPECS_48K_EARLY,
+        SPECS_48K_NTSC, SPECS_PLUS3,
+    },
+    RustzxSettings,
+};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    sna::sna_128k,
+};
+use std::time::Duration;
+
+/// JR $ (12 clocks per iteration)
+const LOOP_CODE: [u8; 2] = [0x18, 0xFE];
+const LOOP_CLOCKS: u64 = 12;
+
+/// Pentagon-like clone: longer frame and no contention
+fn pentagon_builder() -> ZXSpecsBuilder {
+    ZXSpecsBuilder::new()
+        .freq_cpu(3_500_000)
+        .clocks_first_pixel(17988)
+        .clocks_ula_read_shift(2)
+        .clocks_ula_beam_shift(1)
+        .clocks_row(24, 128, 24, 48)
+        .lines(64, 192, 48, 16)
+        .contention([0; 8])
+        .contention_offset(0)
+        .frames_per_second(49)
+        .interrupt_length(32)
+        .memory_model(MemoryModel::Sinclair128K)
+}
+
+/// Returns count of instructions executed during one frame of `JR $` loop
+fn loop_instructions_per_frame(settings: RustzxSettings) -> u64 {
+    let mut t = RustZXTester::new("machine_specs_loop", settings);
+    t.emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna_128k(0x10, &LOOP_CODE))))
+        .expect("Failed to load snapshot");
+    t.emulator().benchmark(1).unwrap().instructions
+}
+
+#[test]
+fn builtin_specs_are_valid() {
+    for specs in [
+        SPECS_48K,
+        SPECS_48K_EARLY,
+        SPECS_48K_NTSC,
+        SPECS_128K,
+        SPECS_PLUS3,
+    ] {
+        assert_eq!(specs.validate(), Ok(()));
+    }
+    assert_eq!(SPECS_48K.clocks_frame, 69888);
+    assert_eq!(SPECS_128K.clocks_frame, 70908);
+    assert_eq!(ZXMachine::Sinclair128K.specs(), &SPECS_128K);
+    assert_eq!(ZXMachine::SinclairPlus3.specs(), &SPECS_PLUS3);
+}
+
+#[test]
+fn ntsc_specs_match_hardware() {
+    // NTSC 48K: 264 lines of 224 clocks at 3.5275 MHz (59.65 Hz), first
+    // pixel is displayed 40 lines after the interrupt
+    let specs = SPECS_48K_NTSC;
+    assert_eq!(specs.lines_all + specs.lines_vsync, 264);
+    assert_eq!(specs.lines_screen, 192);
+    assert_eq!(specs.clocks_line, 224);
+    assert_eq!(specs.clocks_frame, 59136);
+    assert_eq!(specs.clocks_first_pixel, 8960);
+    assert_eq!(specs.freq_cpu, 3_527_500);
+    assert_eq!(specs.frames_per_second, 60);
+}
+
+#[test]
+fn custom_machine_with_builtin_specs() {
+    let builtin = presets::settings_128k_nosound();
+    let custom = RustzxSettings {
+        machine: ZXMachine::custom(SPECS_128K).unwrap(),
+        ..presets::settings_128k_nosound()
+    };
+    let mut builtin = RustZXTester::new("machine_specs_builtin", builtin);
+    let mut custom = RustZXTester::new("machine_specs_custom", custom);
+    builtin.emulate_for(Duration::from_secs(1));
+    custom.emulate_for(Duration::from_secs(1));
+    assert_eq!(builtin.state_hash(), custom.state_hash());
+}
+
+#[test]
+fn custom_machine_timings() {
+    let specs = pentagon_builder().try_build().unwrap();
+    assert_eq!(specs.clocks_frame, 320 * 224);
+    let settings = || RustzxSettings {
+        machine: ZXMachine::custom(specs).unwrap(),
+        ..presets::settings_128k_nosound()
+    };
+    let instructions = loop_instructions_per_frame(settings());
+    assert!(instructions.abs_diff(specs.clocks_frame as u64 / LOOP_CLOCKS) <= 1);
+    let instructions_128k = loop_instructions_per_frame(presets::settings_128k_nosound());
+    assert!(instructions_128k.abs_diff(SPECS_128K.clocks_frame as u64 / LOOP_CLOCKS) <= 1);
+
+    // Custom machine boots with 128K ROM
+    let mut t = RustZXTester::new("machine_specs_boot", settings());
+    t.emulate_for(Duration::from_secs(3));
+    assert!(t
+        .screen_text()
+        .iter()
+        .any(|line| line.contains("128 BASIC")));
+}
+
+#[test]
+fn invalid_specs() {
+    assert_eq!(
+        pentagon_builder().frames_per_second(50).try_build(),
+        Err(MachineSpecsError::InvalidFrameRate)
+    );
+    assert_eq!(
+        pentagon_builder().clocks_row(24, 120, 24, 56).try_build(),
+        Err(MachineSpecsError::InvalidScreenSize)
+    );
+    assert_eq!(
+        pentagon_builder().clocks_first_pixel(100).try_build(),
+        Err(MachineSpecsError::FirstPixelOutOfFrame)
+    );
+    assert_eq!(
+        pentagon_builder().interrupt_length(0).try_build(),
+        Err(MachineSpecsError::InvalidInterruptLength)
+    );
+
+    let mut specs = pentagon_builder().build();
+    specs.clocks_frame += 1;
+    assert_eq!(
+        ZXMachine::custom(specs),
+        Err(MachineSpecsError::InvalidFrameClocks)
+    );
+
+    let mut specs = pentagon_builder().build();
+    specs.clocks_first_pixel += 1;
+    assert_eq!(specs.validate(), Err(MachineSpecsError::InvalidUlaOrigins));
+
+    // Unchecked custom machine is rejected by the emulator
+    let mut t = RustZXTester::new("machine_specs_invalid", presets::settings_128k_nosound());
+    assert!(matches!(
+        t.emulator().switch_machine(ZXMachine::Custom(specs)),
+        Err(Error::MachineSpecs(MachineSpecsError::InvalidUlaOrigins))
+    ));
+    assert_eq!(t.emulator().machine(), ZXMachine::Sinclair128K);
+}
+
+#[test]
+fn ula_fetch_in_display_area() {
+    let specs = SPECS_48K;
+    let origin = specs.clocks_ula_read_origin;
+    let fetches = (0..10)
+        .map(|clocks| specs.ula_fetch(origin + clocks))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fetches,
+        [
+            Some(UlaFetch::Bitmap(0x4000)),
+            Some(UlaFetch::Attribute(0x5800)),
+            Some(UlaFetch::Bitmap(0x4001)),
+            Some(UlaFetch::Attribute(0x5801)),
+            None,
+            None,
+            None,
+            None,
+            Some(UlaFetch::Bitmap(0x4002)),
+            Some(UlaFetch::Attribute(0x5802)),
+        ]
+    );
+    // Last column of the first line, second line and second attribute row
+    assert_eq!(
+        specs.ula_fetch(origin + 123),
+        Some(UlaFetch::Attribute(0x581F))
+    );
+    assert_eq!(
+        specs.ula_fetch(origin + specs.clocks_line),
+        Some(UlaFetch::Bitmap(0x4100))
+    );
+    assert_eq!(
+        specs.ula_fetch(origin + 8 * specs.clocks_line + 1),
+        Some(UlaFetch::Attribute(0x5820))
+    );
+    assert_eq!(
+        specs.ula_fetch(origin + 191 * specs.clocks_line + 122),
+        Some(UlaFetch::Bitmap(0x57FF))
+    );
+}
+
+#[test]
+fn machine_switch_returns_video_params() {
+    let mut t = RustZXTester::new(
+        "machine_switch_returns_video_params",
+        presets::settings_48k_nosound(),
+    );
+    let params = t
+        .emulator()
+        .switch_machine(ZXMachine::Sinclair48KNtsc)
+        .unwrap();
+    assert_eq!(params.frames_per_second, 60);
+    assert_eq!(params.clocks_frame, SPECS_48K_NTSC.clocks_frame);
+    assert_eq!(
+        params.screen_dimensions,
+        t.emulator().screen_buffer_dimensions()
+    );
+    assert_eq!(t.emulator().video_params(), params);
+}
+
+#[test]
+fn contention_offset_out_of_frame_is_rejected() {
+    let mut specs = SPECS_48K;
+    assert_eq!(
+        specs.set_contention_offset(i32::MIN),
+        Err(MachineSpecsError::FirstPixelOutOfFrame)
+    );
+    assert_eq!(
+        specs.set_contention_offset(specs.clocks_frame as i32),
+        Err(MachineSpecsError::FirstPixelOutOfFrame)
+    );
+    assert_eq!(specs, SPECS_48K);
+    assert_eq!(specs.set_contention_offset(-1), Ok(()));
+    assert_eq!(
+        specs.clocks_ula_read_origin,
+        SPECS_48K.clocks_ula_read_origin - 1
+    );
+
+    let mut t = RustZXTester::new(
+        "contention_offset_out_of_frame_is_rejected",
+        presets::settings_48k_nosound(),
+    );
+    assert!(t.emulator().set_contention_offset(-100_000).is_err());
+    assert_eq!(t.emulator().contention_offset(), 0);
+}
@@ -0,0 +1,69 @@
+use rustzx_core::{host::Snapshot, search::RamAddress};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    sna::sna_128k,
+};
+
+// LD A, 5; LD (0x9001), A; LD (0x9000), A; EI
+// loop: HALT; LD HL, 0x9000; DEC (HL); JR loop
+const LIVES_COUNTER_CODE: [u8; 16] = [
+    0x3E, 0x05, 0x32, 0x01, 0x90, 0x32, 0x00, 0x90, 0xFB, 0x76, 0x21, 0x00, 0x90, 0x35, 0x18, 0xF9,
+];
+
+#[test]
+fn search_finds_lives_counter() {
+    let mut tester = RustZXTester::with_code("search_finds_lives_counter", &LIVES_COUNTER_CODE);
+    tester.emulate_frame();
+    assert_eq!(tester.peek(0x9000), 5);
+
+    // Counter, constant at 0x9001 and the operand of LD A, 5
+    let found = tester.emulator().search_equal(5);
+    assert_eq!(found, [0x8001, 0x9000, 0x9001]);
+    assert_eq!(tester.emulator().search_candidates().len(), 3);
+
+    // Nothing changes between the steps without emulation
+    assert_eq!(tester.emulator().search_refine_unchanged(), found);
+
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().search_refine_changed(), [0x9000]);
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().search_refine_equal(3), [0x9000]);
+    assert_eq!(
+        tester.emulator().search_candidates(),
+        [RamAddress {
+            bank: 1,
+            offset: 0x1000
+        }]
+    );
+    tester.emulate_frame();
+    assert!(tester.emulator().search_refine_unchanged().is_empty());
+}
+
+#[test]
+fn search_includes_paged_out_banks() {
+    let mut tester = RustZXTester::new(
+        "search_includes_paged_out_banks",
+        presets::settings_128k_nosound(),
+    );
+    // DI; LD A, 0x13; LD BC, 0x7FFD; OUT (C), A; LD A, 0xA5; LD (0xC123), A
+    // LD A, 0x10; OUT (C), A; HALT
+    let code = [
+        0xF3, 0x3E, 0x13, 0x01, 0xFD, 0x7F, 0xED, 0x79, 0x3E, 0xA5, 0x32, 0x23, 0xC1, 0x3E, 0x10,
+        0xED, 0x79, 0x76,
+    ];
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::sna_from_bytes(sna_128k(0x10, &code)))
+        .expect("Failed to load snapshot");
+    tester.emulate_frame();
+
+    let found = tester.emulator().search_equal(0xA5);
+    assert!(!found.contains(&0xC123));
+    let paged_out = RamAddress {
+        bank: 3,
+        offset: 0x0123,
+    };
+    assert!(tester.emulator().search_candidates().contains(&paged_out));
+    // Byte of the LD A, 0xA5 instruction
+    assert!(found.contains(&0x8009));
+}
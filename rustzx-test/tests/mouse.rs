@@ -1,8 +1,44 @@
 use expect_test::expect;
-use rustzx_core::zx::mouse::kempston::{KempstonMouseButton, KempstonMouseWheelDirection};
+use rustzx_core::{
+    input::InputEvent,
+    zx::mouse::kempston::{KempstonMouseButton, KempstonMouseConfig, KempstonMouseWheelDirection},
+    RustzxSettings,
+};
 use rustzx_test::framework::{presets, RustZXTester};
 use std::time::Duration;
 
+// DI
+// loop:
+// LD BC, 0xFADF; IN A, (C); LD (0x9000), A
+// LD B, 0xFB; IN A, (C); LD (0x9001), A
+// LD B, 0xFF; IN A, (C); LD (0x9002), A
+// JR loop
+const READ_MOUSE_CODE: [u8; 25] = [
+    0xF3, 0x01, 0xDF, 0xFA, 0xED, 0x78, 0x32, 0x00, 0x90, 0x06, 0xFB, 0xED, 0x78, 0x32, 0x01, 0x90,
+    0x06, 0xFF, 0xED, 0x78, 0x32, 0x02, 0x90, 0x18, 0xE8,
+];
+
+fn mouse_tester(name: &str, config: KempstonMouseConfig) -> RustZXTester {
+    let settings = RustzxSettings {
+        mouse_enabled: true,
+        mouse_config: config,
+        ..presets::settings_48k_nosound()
+    };
+    let mut tester = RustZXTester::new(name, settings);
+    tester.load_code(&READ_MOUSE_CODE);
+    tester
+}
+
+/// Returns `(buttons, x, y)` ports read by the emulated code
+fn read_ports(tester: &mut RustZXTester) -> (u8, u8, u8) {
+    tester.emulator().step_instructions(11).unwrap();
+    (
+        tester.peek(0x9000),
+        tester.peek(0x9001),
+        tester.peek(0x9002),
+    )
+}
+
 #[test]
 fn kempston_mouse() {
     let mut settings = presets::settings_48k_nosound();
@@ -112,3 +148,63 @@ fn kempston_mouse() {
         expect![[r#"I+2mija0+YU60eHjAehkN9MpfgMli2ym7pMoChVbcFo="#]],
     );
 }
+
+#[test]
+fn kempston_mouse_sensitivity_and_invert() {
+    let mut tester = mouse_tester("kempston_mouse_sensitivity", KempstonMouseConfig::default());
+    tester.emulator().send_mouse_pos_diff(3, 2);
+    // Host Y axis points down, Kempston Y axis points up
+    assert_eq!(read_ports(&mut tester), (0xFF, 0x02, 0xFD));
+
+    let config = KempstonMouseConfig {
+        x_sensitivity: 50,
+        y_sensitivity: 200,
+        invert_x: true,
+        invert_y: true,
+    };
+    let mut tester = mouse_tester("kempston_mouse_invert", config);
+    tester.emulator().send_mouse_pos_diff(3, 2);
+    assert_eq!(read_ports(&mut tester), (0xFF, 0xFE, 0x03));
+    // Fractional part of the scaled movement is not lost
+    tester.emulator().send_mouse_pos_diff(1, 0);
+    assert_eq!(read_ports(&mut tester), (0xFF, 0xFD, 0x03));
+}
+
+#[test]
+fn kempston_mouse_wheel_delta() {
+    let mut tester = mouse_tester("kempston_mouse_wheel_delta", KempstonMouseConfig::default());
+    tester.emulator().send_mouse_wheel_delta(3);
+    assert_eq!(read_ports(&mut tester).0, 0x2F);
+    // 4-bit counter wraps around
+    tester.emulator().send_mouse_wheel_delta(-5);
+    assert_eq!(read_ports(&mut tester).0, 0xDF);
+    tester
+        .emulator()
+        .send_mouse_wheel(KempstonMouseWheelDirection::Up);
+    assert_eq!(read_ports(&mut tester).0, 0xEF);
+}
+
+#[test]
+fn kempston_mouse_queued_input_is_deterministic() {
+    let config = KempstonMouseConfig {
+        x_sensitivity: 30,
+        ..Default::default()
+    };
+    let run = || {
+        let mut tester = mouse_tester("kempston_mouse_queued_input", config);
+        for frame in 0..4 {
+            tester
+                .emulator()
+                .queue_input(frame, InputEvent::MouseMove { x: 5, y: -1 });
+            tester
+                .emulator()
+                .queue_input(frame, InputEvent::MouseWheelDelta(-2));
+        }
+        (0..4).for_each(|_| tester.emulate_frame());
+        read_ports(&mut tester)
+    };
+    let ports = run();
+    // 4 * 5 * 30% = 6 steps, wheel counter 0xF - 8 = 0x7
+    assert_eq!(ports, (0x7F, 0x05, 0x03));
+    assert_eq!(run(), ports);
+}
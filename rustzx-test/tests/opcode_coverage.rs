@@ -0,0 +1,32 @@
+use rustzx_core::zx::machine::ZXMachine;
+use rustzx_test::framework::RustZXTester;
+
+// EI
+// loop: HALT
+//       JR loop
+const IDLE_CODE: [u8; 4] = [0xFB, 0x76, 0x18, 0xFD];
+
+#[test]
+fn opcode_coverage_is_kept_on_reset_and_machine_switch() {
+    let mut t = RustZXTester::with_code(
+        "opcode_coverage_is_kept_on_reset_and_machine_switch",
+        &IDLE_CODE,
+    );
+    t.emulate_frame();
+    let halts = t.emulator().opcode_coverage().normal[0x76];
+    assert!(halts > 0);
+
+    t.emulator().boot_to_basic().unwrap();
+    let coverage = t.emulator().opcode_coverage();
+    assert!(coverage.normal[0x76] >= halts);
+    assert!(coverage.normal[0xFB] > 0);
+
+    let covered = coverage.covered_count();
+    t.emulator()
+        .switch_machine(ZXMachine::Sinclair128K)
+        .unwrap();
+    assert_eq!(t.emulator().opcode_coverage().covered_count(), covered);
+
+    t.emulator().reset_opcode_coverage();
+    assert_eq!(t.emulator().opcode_coverage().covered_count(), 0);
+}
@@ -0,0 +1,134 @@
+use rustzx_core::{
+    host::Snapshot,
+    poke::{Poke, PokeAction},
+    zx::video::orientation::{Orientation, Rotation},
+    RustzxSettings,
+};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    sna::sna_48k,
+};
+use rustzx_utils::image::RgbaImage;
+use std::time::Duration;
+
+const ORIENTATIONS: [Orientation; 5] = [
+    Orientation {
+        rotation: Rotation::Cw90,
+        mirror: false,
+    },
+    Orientation {
+        rotation: Rotation::Cw180,
+        mirror: false,
+    },
+    Orientation {
+        rotation: Rotation::Cw270,
+        mirror: false,
+    },
+    Orientation {
+        rotation: Rotation::None,
+        mirror: true,
+    },
+    Orientation {
+        rotation: Rotation::Cw90,
+        mirror: true,
+    },
+];
+
+fn tester(name: &str, orientation: Orientation) -> RustZXTester {
+    let settings = RustzxSettings {
+        orientation,
+        ..presets::settings_48k_nosound()
+    };
+    RustZXTester::new(name, settings)
+}
+
+fn assert_transformed(reference: &RgbaImage, image: &RgbaImage, orientation: Orientation) {
+    let (width, height) = (reference.width, reference.height);
+    assert_eq!(
+        (image.width, image.height),
+        orientation.dimensions(width, height)
+    );
+    for y in 0..height {
+        for x in 0..width {
+            let (tx, ty) = orientation.transform(x, y, width, height);
+            assert_eq!(
+                image.pixel(tx, ty),
+                reference.pixel(x, y),
+                "{:?} ({}, {})",
+                orientation,
+                x,
+                y
+            );
+        }
+    }
+}
+
+#[test]
+fn screen_is_rendered_rotated() {
+    let mut reference = tester("screen_is_rendered_rotated", Orientation::default());
+    reference.emulate_for(Duration::from_millis(2000));
+    for orientation in ORIENTATIONS {
+        let mut tester = tester("screen_is_rendered_rotated", orientation);
+        tester.emulate_for(Duration::from_millis(2000));
+        assert_transformed(
+            &reference.screen_image(),
+            &tester.screen_image(),
+            orientation,
+        );
+        let (width, height) = tester.emulator().screen_buffer_dimensions();
+        assert_eq!((width, height), orientation.dimensions(256, 192));
+    }
+}
+
+#[test]
+fn border_is_rendered_rotated() {
+    // loop: OUT (0xFE), A; INC A; JR loop
+    let sna = sna_48k(0xFEFE, &[0xD3, 0xFE, 0x3C, 0x18, 0xFB]);
+    let run = |orientation| {
+        let mut tester = tester("border_is_rendered_rotated", orientation);
+        tester
+            .emulator()
+            .load_snapshot(Snapshot::sna_from_bytes(&sna[..]))
+            .expect("Failed to load snapshot");
+        tester.emulate_frame();
+        tester
+    };
+    let reference = run(Orientation::default()).border_image();
+    for orientation in ORIENTATIONS {
+        let mut tester = run(orientation);
+        assert_transformed(&reference, &tester.border_image(), orientation);
+        let (width, height) = tester.emulator().border_buffer_dimensions();
+        assert_eq!(
+            (width, height),
+            orientation.dimensions(reference.width, reference.height)
+        );
+    }
+}
+
+struct MemPokes(Vec<PokeAction>);
+
+impl Poke for MemPokes {
+    fn actions(&self) -> &[PokeAction] {
+        &self.0
+    }
+}
+
+#[test]
+fn dirty_rect_is_rotated() {
+    let mut tester = tester(
+        "dirty_rect_is_rotated",
+        Orientation {
+            rotation: Rotation::Cw90,
+            mirror: false,
+        },
+    );
+    tester.emulate_for(Duration::from_millis(2000));
+    // Pixel lines 9 and 28, columns 32..40 and 200..208
+    tester.emulator().execute_poke(MemPokes(vec![
+        PokeAction::mem(0x4124, 0xAA),
+        PokeAction::mem(0x4479, 0x01),
+    ]));
+    tester.emulate_frame();
+    // (32, 9, 176, 20) of the canvas rotated clockwise
+    assert_eq!(tester.emulator().dirty_rect(), Some((163, 32, 20, 176)));
+}
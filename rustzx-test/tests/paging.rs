@@ -0,0 +1,130 @@
+use rustzx_core::host::{BufferCursor, Snapshot};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    sna::sna_128k,
+};
+
+#[test]
+fn display_bank_follows_7ffd() {
+    let mut tester = RustZXTester::new(
+        "display_bank_follows_7ffd",
+        presets::settings_128k_nosound(),
+    );
+    assert_eq!(tester.emulator().display_bank(), 5);
+
+    // LD BC, 0x7FFD; LD A, 0x18; OUT (C), A; JR $
+    let code = [0x01, 0xFD, 0x7F, 0x3E, 0x18, 0xED, 0x79, 0x18, 0xFE];
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna_128k(0x10, &code))))
+        .expect("Failed to load snapshot");
+    assert_eq!(tester.emulator().display_bank(), 5);
+
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().display_bank(), 7);
+}
+
+#[test]
+fn display_bank_restored_from_snapshot() {
+    let mut tester = RustZXTester::new(
+        "display_bank_restored_from_snapshot",
+        presets::settings_128k_nosound(),
+    );
+    // JR $
+    let code = [0x18, 0xFE];
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna_128k(0x18, &code))))
+        .expect("Failed to load snapshot");
+    assert_eq!(tester.emulator().display_bank(), 7);
+}
+
+#[test]
+fn display_bank_48k() {
+    let mut tester = RustZXTester::new("display_bank_48k", presets::settings_48k_nosound());
+    assert_eq!(tester.emulator().display_bank(), 0);
+}
+
+/// Runs code on +3 which puts `0x40 + bank` marker at the start of each RAM
+/// bank, writes `port_7ffd` and `port_1ffd` and returns first bytes of the
+/// four memory blocks. ROM page `n` is filled with `0xE0 + n`
+fn plus3_blocks_after(test_name: &str, port_7ffd: u8, port_1ffd: u8) -> [u8; 4] {
+    let mut tester = RustZXTester::new(test_name, presets::settings_plus3_nosound());
+    tester.load_rom_pages((0..4).map(|page| vec![0xE0 + page; 0x4000]).collect());
+
+    #[rustfmt::skip]
+    let code = [
+        // LD BC, 0x7FFD; XOR A
+        0x01, 0xFD, 0x7F, 0xAF,
+        // loop: OUT (C), A; LD E, A; ADD A, 0x40; LD (0xC000), A; LD A, E
+        0xED, 0x79, 0x5F, 0xC6, 0x40, 0x32, 0x00, 0xC0, 0x7B,
+        // INC A; CP 8; JR NZ, loop
+        0x3C, 0xFE, 0x08, 0x20, 0xF2,
+        // LD A, port_7ffd; OUT (C), A
+        0x3E, port_7ffd, 0xED, 0x79,
+        // LD B, 0x1F; LD A, port_1ffd; OUT (C), A; JR $
+        0x06, 0x1F, 0x3E, port_1ffd, 0xED, 0x79, 0x18, 0xFE,
+    ];
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna_128k(0x10, &code))))
+        .expect("Failed to load snapshot");
+    tester.emulate_frame();
+    [0x0000, 0x4000, 0x8000, 0xC000].map(|addr| tester.peek(addr))
+}
+
+#[test]
+fn plus3_rom_selected_by_7ffd_and_1ffd() {
+    for (port_7ffd, port_1ffd, rom) in [
+        (0x00, 0x00, 0),
+        (0x10, 0x00, 1),
+        (0x00, 0x04, 2),
+        (0x10, 0x04, 3),
+    ] {
+        assert_eq!(
+            plus3_blocks_after("plus3_rom_selected_by_7ffd_and_1ffd", port_7ffd, port_1ffd),
+            [0xE0 + rom, 0x45, 0x42, 0x40],
+            "7FFD={:#04X} 1FFD={:#04X}",
+            port_7ffd,
+            port_1ffd
+        );
+    }
+}
+
+#[test]
+fn plus3_special_paging() {
+    let configs = [
+        [0x40, 0x41, 0x42, 0x43],
+        [0x44, 0x45, 0x46, 0x47],
+        [0x44, 0x45, 0x46, 0x43],
+        [0x44, 0x47, 0x46, 0x43],
+    ];
+    for (config, banks) in configs.iter().enumerate() {
+        let port_1ffd = ((config as u8) << 1) | 0x01;
+        assert_eq!(
+            &plus3_blocks_after("plus3_special_paging", 0x10, port_1ffd),
+            banks,
+            "special paging config {}",
+            config
+        );
+    }
+}
+
+#[test]
+fn plus3_paging_lock_covers_1ffd() {
+    // 7FFD bit 5 locks paging before 1FFD is written, so 48K BASIC ROM
+    // selected on the snapshot load stays paged in
+    assert_eq!(
+        plus3_blocks_after("plus3_paging_lock_covers_1ffd", 0x30, 0x05),
+        [0xE3, 0x45, 0x42, 0x40]
+    );
+}
+
+#[test]
+fn plus3_1ffd_does_not_reach_7ffd() {
+    // On 128K 0x1FFD is a mirror of 0x7FFD, +2A/+3 decodes A14 as well
+    assert_eq!(
+        plus3_blocks_after("plus3_1ffd_does_not_reach_7ffd", 0x10, 0x03 << 1),
+        [0xE3, 0x45, 0x42, 0x40]
+    );
+}
@@ -0,0 +1,21 @@
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+#[test]
+fn context_change_redraws_screen() {
+    let mut tester = RustZXTester::new(
+        "context_change_redraws_screen",
+        presets::settings_48k_nosound(),
+    );
+    tester.emulate_for(Duration::from_millis(2000));
+    tester.emulate_frame();
+    let hash = tester.frame_hash();
+    assert_eq!(tester.emulator().dirty_rect(), None);
+
+    tester.reset_host_context();
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().dirty_rect(), Some((0, 0, 256, 192)));
+    assert_eq!(tester.frame_hash(), hash);
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().dirty_rect(), None);
+}
@@ -0,0 +1,73 @@
+use rustzx_core::{host::Snapshot, RustzxSettings};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    sna::sna_48k,
+};
+use rustzx_utils::palette::rgba::ORIGINAL;
+
+const SNA_HEADER_SIZE: usize = 27;
+const RED: [u8; 4] = ORIGINAL[2];
+const BRIGHT_RED: [u8; 4] = ORIGINAL[10];
+const BLUE: [u8; 4] = ORIGINAL[1];
+const BRIGHT_BLUE: [u8; 4] = ORIGINAL[9];
+
+fn tester(name: &str, paper_patterns: bool) -> RustZXTester {
+    // JR $
+    let mut sna = sna_48k(0xFEFE, &[0x18, 0xFE]);
+    let screen = &mut sna[SNA_HEADER_SIZE..];
+    // Red paper, bright red paper, red ink on red paper, blue paper
+    screen[0x1800..0x1804].copy_from_slice(&[0x10, 0x50, 0x12, 0x08]);
+    // Ink in the top line of the third cell
+    screen[2] = 0xFF;
+    let settings = RustzxSettings {
+        paper_patterns,
+        ..presets::settings_48k_nosound()
+    };
+    let mut tester = RustZXTester::new(name, settings);
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::sna_from_bytes(sna))
+        .expect("Failed to load snapshot");
+    tester.emulate_frame();
+    tester.emulate_frame();
+    tester
+}
+
+#[test]
+fn paper_is_drawn_with_patterns() {
+    let image = tester("paper_is_drawn_with_patterns", true).screen_image();
+    // Red pattern is the horizontal line in every 4th row
+    assert_eq!(image.pixel(0, 0), BRIGHT_RED);
+    assert_eq!(image.pixel(7, 4), BRIGHT_RED);
+    assert_eq!(image.pixel(3, 1), RED);
+    // Brightness of the bright paper is flipped too
+    assert_eq!(image.pixel(8, 0), RED);
+    assert_eq!(image.pixel(8, 1), BRIGHT_RED);
+    // Ink is not patterned
+    assert_eq!(image.pixel(16, 0), RED);
+    assert_eq!(image.pixel(16, 4), BRIGHT_RED);
+    // Blue pattern is sparse dots
+    assert_eq!(image.pixel(24, 0), BRIGHT_BLUE);
+    assert_eq!(image.pixel(26, 2), BRIGHT_BLUE);
+    assert_eq!(image.pixel(25, 0), BLUE);
+
+    let image = tester("paper_is_drawn_with_patterns", false).screen_image();
+    for x in [0, 7, 16, 24, 26] {
+        assert_eq!(image.pixel(x, 0) == RED, x < 24);
+        assert_eq!(image.pixel(x, 4) == RED, x < 24);
+    }
+}
+
+#[test]
+fn paper_patterns_are_switched_at_runtime() {
+    let mut tester = tester("paper_patterns_are_switched_at_runtime", false);
+    let plain = tester.frame_hash();
+    tester.emulator().set_paper_patterns(true);
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().dirty_rect(), Some((0, 0, 256, 192)));
+    assert_ne!(tester.frame_hash(), plain);
+    assert_eq!(tester.screen_image().pixel(0, 0), BRIGHT_RED);
+    tester.emulator().set_paper_patterns(false);
+    tester.emulate_frame();
+    assert_eq!(tester.frame_hash(), plain);
+}
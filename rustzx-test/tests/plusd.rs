@@ -0,0 +1,262 @@
+use rustzx_core::{
+    error::{DiskLoadError, Error},
+    host::{BufferCursor, Disk, Snapshot, SnapshotRecorder},
+    input::InputState,
+    zx::{
+        storage::{DriveActivity, DriveStatus, StorageActivity, StorageDevice},
+        PlusDState,
+    },
+};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    udi::{self, Field},
+};
+
+const SECTOR_SIZE: usize = 512;
+const DISK_SIZE: usize = 80 * 2 * 10 * SECTOR_SIZE;
+const BUFFER_ADDR: u16 = 0x9000;
+/// Sectors 1..=10 of track 0 side 0
+const LOAD_SIZE: usize = 10 * SECTOR_SIZE;
+
+// DI
+// LD A, 1; OUT (0xEF), A     ; drive 1, side 0
+// LD A, 1; OUT (0xF3), A     ; sector 1
+// LD HL, 0x9000
+// LD A, 0x90; OUT (0xE3), A  ; multi-sector read
+// loop: IN A, (0xE3); AND 2; JR Z, done
+// IN A, (0xFB); LD (HL), A; INC HL; JR loop
+// done: JR $
+const LOADER_CODE: [u8; 30] = [
+    0xF3, 0x3E, 0x01, 0xD3, 0xEF, 0x3E, 0x01, 0xD3, 0xF3, 0x21, 0x00, 0x90, 0x3E, 0x90, 0xD3, 0xE3,
+    0xDB, 0xE3, 0xE6, 0x02, 0x28, 0x06, 0xDB, 0xFB, 0x77, 0x23, 0x18, 0xF4, 0x18, 0xFE,
+];
+
+fn disk_image(seed: u8) -> Vec<u8> {
+    (0..DISK_SIZE)
+        .map(|idx| (idx / SECTOR_SIZE) as u8 ^ idx as u8 ^ seed)
+        .collect()
+}
+
+fn tester(name: &str, disk: Option<Vec<u8>>) -> RustZXTester {
+    let mut t = RustZXTester::new(name, presets::settings_48k_nosound());
+    t.emulator()
+        .attach_plusd(BufferCursor::new(vec![0; 0x2000]))
+        .expect("Failed to attach +D");
+    if let Some(disk) = disk {
+        t.emulator()
+            .insert_plusd_disk(0, Disk::Mgt(BufferCursor::new(disk)))
+            .expect("Failed to insert disk");
+    }
+    t
+}
+
+/// Builds UDI image of a single track with sectors 1 and 2 of track 0, the
+/// second one with data CRC error
+fn udi_image() -> Vec<u8> {
+    let data = [[1; SECTOR_SIZE], [2; SECTOR_SIZE]];
+    udi::udi_image(&[
+        Field::id(&[0, 0, 1, 2]),
+        Field::data(&data[0], true),
+        Field::id(&[0, 0, 2, 2]),
+        Field::data(&data[1], false),
+    ])
+}
+
+fn loaded_data(t: &mut RustZXTester) -> Vec<u8> {
+    (0..LOAD_SIZE)
+        .map(|offset| t.peek(BUFFER_ADDR + offset as u16))
+        .collect()
+}
+
+#[test]
+fn plusd_state_resumes_multi_sector_load() {
+    let mut t = tester("plusd_state_resumes_multi_sector_load", Some(disk_image(0)));
+    t.load_code(&LOADER_CODE);
+    t.emulate_frame();
+    t.emulate_frame();
+    let mut sna = Vec::new();
+    t.emulator()
+        .save_snapshot(SnapshotRecorder::Sna(&mut sna))
+        .expect("Failed to save snapshot");
+    let mut state = Vec::new();
+    t.emulator()
+        .plusd_state()
+        .expect("+D is attached")
+        .save(&mut state)
+        .unwrap();
+    // Load is in progress
+    let loaded = loaded_data(&mut t);
+    assert!(loaded[SECTOR_SIZE] != 0 && loaded[LOAD_SIZE - 1] == 0);
+
+    for _ in 0..5 {
+        t.emulate_frame();
+    }
+    let expected = disk_image(0)[..LOAD_SIZE].to_vec();
+    assert_eq!(loaded_data(&mut t), expected);
+
+    let mut restored = tester("plusd_state_resumes_multi_sector_load", Some(disk_image(0)));
+    restored
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna.clone())))
+        .unwrap();
+    let state = PlusDState::load(BufferCursor::new(state)).unwrap();
+    restored.emulator().set_plusd_state(&state).unwrap();
+    for _ in 0..5 {
+        restored.emulate_frame();
+    }
+    assert_eq!(loaded_data(&mut restored), expected);
+}
+
+#[test]
+fn plusd_state_requires_same_disks() {
+    let mut t = tester("plusd_state_requires_same_disks", Some(disk_image(0)));
+    let state = t.emulator().plusd_state().unwrap();
+
+    let mut other = tester("plusd_state_requires_same_disks", Some(disk_image(1)));
+    let result = other.emulator().set_plusd_state(&state);
+    assert!(matches!(
+        result,
+        Err(Error::DiskLoad(DiskLoadError::DiskMismatch))
+    ));
+    // Disk is not touched on failure
+    assert_eq!(other.emulator().eject_plusd_disk(0), Some(disk_image(1)));
+
+    let mut empty = tester("plusd_state_requires_same_disks", None);
+    let result = empty.emulator().set_plusd_state(&state);
+    assert!(matches!(
+        result,
+        Err(Error::DiskLoad(DiskLoadError::DiskMismatch))
+    ));
+
+    let mut detached = RustZXTester::new(
+        "plusd_state_requires_same_disks",
+        presets::settings_48k_nosound(),
+    );
+    assert!(detached.emulator().plusd_state().is_none());
+    let result = detached.emulator().set_plusd_state(&state);
+    assert!(matches!(
+        result,
+        Err(Error::DiskLoad(DiskLoadError::NoDiskInterface))
+    ));
+}
+
+#[test]
+fn disk_activity_events() {
+    let mut t = tester("disk_activity_events", Some(disk_image(0)));
+    t.load_code(&LOADER_CODE);
+    let input = InputState::new();
+    let mut events = Vec::new();
+    // Multi-sector load ends with the record not found after searching for
+    // 5 revolutions (1 second), motor stops 10 revolutions (2 seconds) later
+    for _ in 0..200 {
+        let output = t.emulator().run_frame(&input).unwrap();
+        assert!(output.storage_events.len() <= 1);
+        events.extend_from_slice(output.storage_events);
+    }
+    let drive_0 = |motor_on, activity| StorageActivity {
+        device: StorageDevice::PlusD(0),
+        status: DriveStatus { motor_on, activity },
+    };
+    assert_eq!(
+        events,
+        [
+            drive_0(true, DriveActivity::Read),
+            drive_0(true, DriveActivity::Idle),
+            drive_0(false, DriveActivity::Idle),
+        ]
+    );
+    assert_eq!(
+        t.emulator().drive_status(StorageDevice::PlusD(1)),
+        Some(DriveStatus::default())
+    );
+    assert_eq!(
+        t.emulator().drive_status(StorageDevice::Tape),
+        Some(DriveStatus::default())
+    );
+}
+
+// DI
+// IN A, (0xE7)               ; page in
+// LD A, (0x1000); LD (0x9000), A
+// LD A, 0x5A; LD (0x3000), A ; +D RAM
+// LD A, (0x3000); LD (0x9001), A
+// OUT (0xE7), A              ; page out
+// LD A, (0x1000); LD (0x9002), A
+// LD A, (0x3000); LD (0x9003), A
+// LD A, 5; OUT (0xEB), A     ; track register
+// IN A, (0xEB); LD (0x9004), A
+// JR $
+const PAGING_CODE: [u8; 45] = [
+    0xF3, 0xDB, 0xE7, 0x3A, 0x00, 0x10, 0x32, 0x00, 0x90, 0x3E, 0x5A, 0x32, 0x00, 0x30, 0x3A, 0x00,
+    0x30, 0x32, 0x01, 0x90, 0xD3, 0xE7, 0x3A, 0x00, 0x10, 0x32, 0x02, 0x90, 0x3A, 0x00, 0x30, 0x32,
+    0x03, 0x90, 0x3E, 0x05, 0xD3, 0xEB, 0xDB, 0xEB, 0x32, 0x04, 0x90, 0x18, 0xFE,
+];
+
+/// +D ROM filled with 0xAB, NMI handler counts calls at 0x9010:
+/// LD HL, 0x9010; INC (HL); JR $
+fn plusd_rom() -> Vec<u8> {
+    let mut rom = vec![0xAB; 0x2000];
+    rom[0x66..0x6C].copy_from_slice(&[0x21, 0x10, 0x90, 0x34, 0x18, 0xFE]);
+    rom
+}
+
+#[test]
+fn plusd_paging_and_ports() {
+    let mut t = RustZXTester::new("plusd_paging_and_ports", presets::settings_48k_nosound());
+    t.emulator()
+        .attach_plusd(BufferCursor::new(plusd_rom()))
+        .expect("Failed to attach +D");
+    t.load_code(&PAGING_CODE);
+    t.emulate_frame();
+    let results = (0..5)
+        .map(|offset| t.peek(0x9000 + offset))
+        .collect::<Vec<_>>();
+    // +D ROM and RAM while paged in, 48K ROM after paging out
+    assert_eq!(results, [0xAB, 0x5A, 0x6D, 0x11, 0x05]);
+}
+
+#[test]
+fn plusd_snapshot_button_pages_in_once() {
+    let mut t = RustZXTester::new(
+        "plusd_snapshot_button_pages_in_once",
+        presets::settings_48k_nosound(),
+    );
+    t.emulator()
+        .attach_plusd(BufferCursor::new(plusd_rom()))
+        .expect("Failed to attach +D");
+    // JR $
+    t.load_code(&[0x18, 0xFE]);
+    t.emulate_frame();
+    assert_eq!(t.peek(0x9010), 0);
+    t.emulator().press_plusd_snapshot_button();
+    for _ in 0..3 {
+        t.emulate_frame();
+    }
+    assert_eq!(t.peek(0x9010), 1);
+    assert_eq!(t.emulator().registers().pc, 0x006A);
+}
+
+#[test]
+fn udi_disk_sector_with_crc_error() {
+    let mut t = tester("udi_disk_sector_with_crc_error", None);
+    t.emulator()
+        .insert_plusd_disk(0, Disk::Udi(BufferCursor::new(udi_image())))
+        .expect("Failed to insert disk");
+    t.load_code(&LOADER_CODE);
+    for _ in 0..5 {
+        t.emulate_frame();
+    }
+    // Data of the sector with CRC error is loaded, then the command stops
+    let mut expected = vec![1; SECTOR_SIZE];
+    expected.extend([2; SECTOR_SIZE]);
+    expected.resize(LOAD_SIZE, 0);
+    assert_eq!(loaded_data(&mut t), expected);
+
+    let result = t
+        .emulator()
+        .insert_plusd_disk(0, Disk::Udi(BufferCursor::new(disk_image(0))));
+    assert!(matches!(
+        result,
+        Err(Error::DiskLoad(DiskLoadError::InvalidImage))
+    ));
+}
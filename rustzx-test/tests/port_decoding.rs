@@ -0,0 +1,299 @@
+use rustzx_core::{
+    host::{BufferCursor, Snapshot},
+    input::InputEvent,
+    zx::{
+        joy::kempston::KempstonKey, keys::ZXKey, machine::UlaIssue,
+        mouse::kempston::KempstonMouseButton,
+    },
+    RustzxSettings,
+};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    sna::{sna_128k, sna_48k},
+};
+
+const RESULT_ADDR: u16 = 0x9000;
+
+enum Access {
+    In(u16),
+    Out(u16, u8),
+}
+
+/// Builds code which performs port accesses in order, values read are
+/// stored starting from `RESULT_ADDR`. With `wait_frame` accesses start
+/// right after the interrupt, in the top border
+fn port_access_code(accesses: &[Access], wait_frame: bool) -> Vec<u8> {
+    let mut code = if wait_frame {
+        // EI; HALT; DI
+        vec![0xFB, 0x76, 0xF3]
+    } else {
+        // DI
+        vec![0xF3]
+    };
+    let mut result_addr = RESULT_ADDR;
+    for access in accesses {
+        match *access {
+            Access::In(port) => {
+                let [port_l, port_h] = port.to_le_bytes();
+                let [addr_l, addr_h] = result_addr.to_le_bytes();
+                // LD BC, port; IN A, (C); LD (addr), A
+                code.extend([0x01, port_l, port_h, 0xED, 0x78, 0x32, addr_l, addr_h]);
+                result_addr += 1;
+            }
+            Access::Out(port, value) => {
+                let [port_l, port_h] = port.to_le_bytes();
+                // LD BC, port; LD A, value; OUT (C), A
+                code.extend([0x01, port_l, port_h, 0x3E, value, 0xED, 0x79]);
+            }
+        }
+    }
+    // JR $
+    code.extend([0x18, 0xFE]);
+    code
+}
+
+fn port_tester(name: &str, settings: RustzxSettings, code: &[u8]) -> RustZXTester {
+    let snapshot = if settings.machine.is_48k() {
+        sna_48k(0xFEFE, code)
+    } else {
+        sna_128k(0, code)
+    };
+    let mut tester = RustZXTester::new(name, settings);
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(snapshot)))
+        .expect("Failed to load snapshot");
+    tester
+}
+
+fn results(tester: &mut RustZXTester, count: u16) -> Vec<u8> {
+    (0..count).map(|n| tester.peek(RESULT_ADDR + n)).collect()
+}
+
+#[test]
+fn ula_decodes_any_even_port() {
+    let code = port_access_code(
+        &[
+            Access::In(0x7FFE),
+            Access::In(0x7F00),
+            Access::In(0x7F7A),
+            Access::In(0xFFFE),
+        ],
+        false,
+    );
+    let mut tester = port_tester(
+        "ula_decodes_any_even_port",
+        presets::settings_48k_nosound(),
+        &code,
+    );
+    tester.emulator().send_key(ZXKey::Space, true);
+    tester.emulate_frame();
+    let keys = results(&mut tester, 4)
+        .into_iter()
+        .map(|value| value & 0x1F)
+        .collect::<Vec<_>>();
+    // Space is in the 0x7F half-row, no half-rows are selected by 0xFF
+    assert_eq!(keys, [0x1E, 0x1E, 0x1E, 0x1F]);
+}
+
+#[test]
+fn kempston_joystick_ignores_high_address_lines() {
+    let code = port_access_code(&[Access::In(0x001F), Access::In(0xFF1F)], false);
+    let settings = RustzxSettings {
+        kempston_enabled: true,
+        ..presets::settings_48k_nosound()
+    };
+    let mut tester = port_tester(
+        "kempston_joystick_ignores_high_address_lines",
+        settings,
+        &code,
+    );
+    tester.emulator().send_kempston_key(KempstonKey::Fire, true);
+    tester.emulate_frame();
+    assert_eq!(results(&mut tester, 2), [0x10, 0x10]);
+}
+
+#[test]
+fn kempston_mouse_and_joystick_share_bus() {
+    let code = port_access_code(
+        &[Access::In(0xFADF), Access::In(0x00DF), Access::In(0x001F)],
+        false,
+    );
+    let settings = RustzxSettings {
+        kempston_enabled: true,
+        mouse_enabled: true,
+        ..presets::settings_48k_nosound()
+    };
+    let mut tester = port_tester("kempston_mouse_and_joystick_share_bus", settings, &code);
+    tester
+        .emulator()
+        .send_input(InputEvent::MouseButton(KempstonMouseButton::Left, true));
+    tester.emulator().send_kempston_key(KempstonKey::Fire, true);
+    tester
+        .emulator()
+        .send_kempston_key(KempstonKey::Right, true);
+    tester.emulate_frame();
+    let [buttons, buttons_mirror, shared] = results(&mut tester, 3)[..] else {
+        unreachable!()
+    };
+    // Mouse buttons port decodes only A0, A5 and A8
+    assert_eq!(buttons_mirror, buttons);
+    assert_eq!(buttons & KempstonMouseButton::Left as u8, 0);
+    // Both devices respond to 0x1F, values are combined
+    assert_eq!(shared, buttons & 0x11);
+}
+
+#[test]
+fn ay_ports_are_mirrored() {
+    let code = port_access_code(
+        &[
+            // Select register 2 via 0xFEFD, write via 0x80FD
+            Access::Out(0xFEFD, 0x02),
+            Access::Out(0x80FD, 0x5A),
+            // Select register 4 via 0xFFFD, write via 0xBFFD
+            Access::Out(0xFFFD, 0x04),
+            Access::Out(0xBFFD, 0xA5),
+            Access::Out(0xC0FD, 0x02),
+            Access::In(0xFFFD),
+            Access::Out(0xFFFD, 0x04),
+            Access::In(0xC0FD),
+        ],
+        false,
+    );
+    let settings = RustzxSettings {
+        ay_enabled: true,
+        ..presets::settings_128k_nosound()
+    };
+    let mut tester = port_tester("ay_ports_are_mirrored", settings, &code);
+    tester.emulate_frame();
+    assert_eq!(results(&mut tester, 2), [0x5A, 0xA5]);
+}
+
+#[test]
+fn ay_ports_are_ignored_without_ay() {
+    let run = |settings: RustzxSettings, wait_frame| {
+        let code = port_access_code(
+            &[
+                Access::Out(0xFFFD, 0x02),
+                Access::Out(0xBFFD, 0x5A),
+                Access::In(0xFFFD),
+            ],
+            wait_frame,
+        );
+        let mut tester = port_tester("ay_ports_are_ignored_without_ay", settings, &code);
+        tester.emulate_frame();
+        tester.emulate_frame();
+        (tester.emulator().has_ay(), results(&mut tester, 1)[0])
+    };
+    // Write is ignored, read returns the floating bus value of the top border
+    assert_eq!(run(presets::settings_48k_nosound(), true), (false, 0xFF));
+    // AY attached to 48K machine
+    let settings = RustzxSettings {
+        ay_enabled: true,
+        ..presets::settings_48k_nosound()
+    };
+    assert_eq!(run(settings, true), (true, 0x5A));
+    // Built-in AY of 128K is decoded regardless of its sound output. Ports are
+    // accessed before the interrupt, as the ROM handler selects other registers
+    assert!(!presets::settings_128k_nosound().ay_enabled);
+    assert_eq!(run(presets::settings_128k_nosound(), false), (true, 0x5A));
+}
+
+#[test]
+fn floating_bus_when_no_device_responds() {
+    // Odd port, not decoded by AY or 128K paging
+    let code = port_access_code(&[Access::In(0x40FF), Access::In(0x40FE)], true);
+    let mut tester = port_tester(
+        "floating_bus_when_no_device_responds",
+        presets::settings_48k_nosound(),
+        &code,
+    );
+    tester.emulate_frame();
+    tester.emulate_frame();
+    let [floating, keyboard] = results(&mut tester, 2)[..] else {
+        unreachable!()
+    };
+    // ULA does not fetch screen data in the top border
+    assert_eq!(floating, 0xFF);
+    // Bits 5 and 7 are unused, EAR is 0 without tape
+    assert_eq!(keyboard & 0x1F, 0x1F);
+}
+
+#[test]
+fn ear_input_follows_outputs_per_issue() {
+    let code = port_access_code(
+        &[0x00, 0x08, 0x10, 0x18]
+            .iter()
+            .flat_map(|&value| [Access::Out(0x00FE, value), Access::In(0xFFFE)])
+            .collect::<Vec<_>>(),
+        false,
+    );
+    let ear_levels = |settings: RustzxSettings| {
+        let mut tester = port_tester("ear_input_follows_outputs", settings, &code);
+        tester.emulate_frame();
+        results(&mut tester, 4)
+            .into_iter()
+            .map(|value| value & 0x40 != 0)
+            .collect::<Vec<_>>()
+    };
+    let issue2 = RustzxSettings {
+        ula_issue: UlaIssue::Issue2,
+        ..presets::settings_48k_nosound()
+    };
+    assert_eq!(ear_levels(issue2), [false, true, true, true]);
+    assert_eq!(
+        ear_levels(presets::settings_48k_nosound()),
+        [false, false, true, true]
+    );
+    // 128K machines behave as issue 3
+    let issue2_128k = RustzxSettings {
+        ula_issue: UlaIssue::Issue2,
+        ..presets::settings_128k_nosound()
+    };
+    assert_eq!(ear_levels(issue2_128k), [false, false, true, true]);
+}
+
+#[test]
+fn ear_input_is_stable_with_stopped_tape() {
+    // DI; LD A, 0x10; OUT (0xFE), A; LD HL, RESULT_ADDR
+    // loop: IN A, (0xFE); LD (HL), A; INC L; JR loop
+    let code = [
+        0xF3, 0x3E, 0x10, 0xD3, 0xFE, 0x21, 0x00, 0x90, 0xDB, 0xFE, 0x77, 0x2C, 0x18, 0xFA,
+    ];
+    let settings = RustzxSettings {
+        tape_fastload_enabled: false,
+        autoload_enabled: false,
+        ..presets::settings_48k_nosound()
+    };
+    let mut tester = port_tester("ear_input_is_stable_with_stopped_tape", settings, &code);
+    // Header block with some data, enough for a few frames of the signal
+    let mut tap = vec![19, 0, 0x00];
+    tap.extend([0x55; 17]);
+    tap.push(tap[2..].iter().fold(0, |sum, byte| sum ^ byte));
+    tester.load_tap_data(tap).expect("Failed to load tape");
+    let ear_levels = |tester: &mut RustZXTester| {
+        results(tester, 256)
+            .into_iter()
+            .map(|value| value & 0x40)
+            .collect::<Vec<_>>()
+    };
+
+    tester.emulate_frame();
+    assert!(ear_levels(&mut tester).iter().all(|&level| level == 0x40));
+
+    let mut tape_signal_seen = false;
+    for frames in 1..=8 {
+        tester.emulator().play_tape();
+        for _ in 0..frames {
+            tester.emulate_frame();
+            let levels = ear_levels(&mut tester);
+            tape_signal_seen |= levels.iter().any(|&level| level != levels[0]);
+        }
+        // EAR output is set, input reads high regardless of the level at
+        // which the tape signal has stopped
+        tester.emulator().stop_tape();
+        tester.emulate_frame();
+        assert!(ear_levels(&mut tester).iter().all(|&level| level == 0x40));
+    }
+    assert!(tape_signal_seen);
+}
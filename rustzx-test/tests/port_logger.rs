@@ -0,0 +1,66 @@
+use rustzx_core::zx::{
+    machine::ZXMachine,
+    ports::{PortAccess, PortDirection},
+};
+use rustzx_test::framework::{presets, RustZXTester};
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+// LD A, 0x05 ; OUT (0xFE), A
+// LD A, 0x7F ; IN A, (0xFE)
+// LD (0x9000), A
+// JR $
+const PORT_ACCESS_CODE: [u8; 13] = [
+    0x3E, 0x05, 0xD3, 0xFE, 0x3E, 0x7F, 0xDB, 0xFE, 0x32, 0x00, 0x90, 0x18, 0xFE,
+];
+
+#[test]
+fn port_logger_records_in_and_out() {
+    let mut tester = RustZXTester::with_code("port_logger_records_in_and_out", &PORT_ACCESS_CODE);
+    let log = Rc::new(RefCell::new(Vec::<PortAccess>::new()));
+    let logger_log = log.clone();
+    tester
+        .emulator()
+        .set_port_logger(Some(Box::new(move |access| {
+            logger_log.borrow_mut().push(access)
+        })));
+    tester.emulate_frame();
+
+    let log = log.take();
+    let accesses = log
+        .iter()
+        .map(|access| (access.port, access.value, access.direction))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        accesses,
+        [
+            (0x05FE, 0x05, PortDirection::Out),
+            (0x7FFE, tester.peek(0x9000), PortDirection::In),
+        ]
+    );
+    assert!(log[0].clocks < log[1].clocks);
+}
+
+#[test]
+fn port_logger_is_kept_on_machine_switch() {
+    let mut tester = RustZXTester::new(
+        "port_logger_is_kept_on_machine_switch",
+        presets::settings_48k_nosound(),
+    );
+    let log = Rc::new(RefCell::new(Vec::<PortAccess>::new()));
+    let logger_log = log.clone();
+    tester
+        .emulator()
+        .set_port_logger(Some(Box::new(move |access| {
+            logger_log.borrow_mut().push(access)
+        })));
+    tester
+        .emulator()
+        .switch_machine(ZXMachine::Sinclair128K)
+        .unwrap();
+    tester.emulate_for(Duration::from_secs(1));
+    // ROM clears the border on boot
+    assert!(log
+        .borrow()
+        .iter()
+        .any(|access| access.direction == PortDirection::Out));
+}
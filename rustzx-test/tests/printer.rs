@@ -0,0 +1,137 @@
+use rustzx_core::host::{BufferCursor, Snapshot};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    sna::sna_128k,
+};
+use rustzx_utils::printer::{CaptureMode, EscpTextFilter, PrinterCapture};
+
+const DATA_ADDR: usize = 0x8100;
+
+/// Listing printed in condensed bold mode with a bit image separator
+const PRINTED: &[u8] = b"\x1B@\x1B!\x0C10 PRINT \"HI\"\r\n\
+    \x1BK\x04\x00\xFF\x81\x0D\xFF\r\n\
+    \x1B-\x0120 GO TO 10\x1B-\x00\r\n\x0C";
+
+// DI
+// LD HL, 0x8100; LD B, len
+// loop: IN A, (0xF7); RLA; JR C, loop  ; wait while printer is busy
+// LD A, (HL); OUT (0xF7), A
+// LD A, 0x40; OUT (0xEF), A; XOR A; OUT (0xEF), A  ; strobe
+// INC HL; DJNZ loop
+// JR $
+fn printer_code(data: &[u8]) -> Vec<u8> {
+    let mut code = vec![
+        0xF3,
+        0x21,
+        0x00,
+        0x81,
+        0x06,
+        data.len() as u8,
+        0xDB,
+        0xF7,
+        0x17,
+        0x38,
+        0xFB,
+        0x7E,
+        0xD3,
+        0xF7,
+        0x3E,
+        0x40,
+        0xD3,
+        0xEF,
+        0xAF,
+        0xD3,
+        0xEF,
+        0x23,
+        0x10,
+        0xEE,
+        0x18,
+        0xFE,
+    ];
+    code.resize(DATA_ADDR - 0x8000, 0);
+    code.extend_from_slice(data);
+    code
+}
+
+#[test]
+fn plusd_printer_text_capture() {
+    let mut tester = RustZXTester::new(
+        "plusd_printer_text_capture",
+        presets::settings_48k_nosound(),
+    );
+    tester
+        .emulator()
+        .attach_plusd(BufferCursor::new(vec![0; 0x2000]))
+        .expect("Failed to attach +D");
+    tester.load_code(&printer_code(PRINTED));
+    tester.emulate_frame();
+
+    let raw = tester.emulator().take_plusd_printer_output();
+    assert_eq!(raw, PRINTED);
+
+    let mut filter = EscpTextFilter::new();
+    assert_eq!(filter.push(&raw), "10 PRINT \"HI\"\n\n20 GO TO 10\n\n");
+}
+
+/// Sends data to the +2A/+3 printer port the way `LLIST` of +3 BASIC does,
+/// with paging locked, so only the strobe bit of 0x1FFD takes effect
+fn plus3_printer_code(data: &[u8]) -> Vec<u8> {
+    #[rustfmt::skip]
+    let mut code = vec![
+        // DI; LD BC, 0x7FFD; LD A, 0x30; OUT (C), A
+        0xF3, 0x01, 0xFD, 0x7F, 0x3E, 0x30, 0xED, 0x79,
+        // LD HL, 0x8100; LD E, len
+        0x21, 0x00, 0x81, 0x1E, data.len() as u8,
+        // loop: LD B, 0x0F; IN A, (C); RRA; JR C, loop  ; wait while printer is busy
+        0x06, 0x0F, 0xED, 0x78, 0x1F, 0x38, 0xF9,
+        // LD A, (HL); OUT (C), A
+        0x7E, 0xED, 0x79,
+        // LD B, 0x1F; LD A, 0x14; OUT (C), A; LD A, 0x04; OUT (C), A  ; strobe
+        0x06, 0x1F, 0x3E, 0x14, 0xED, 0x79, 0x3E, 0x04, 0xED, 0x79,
+        // INC HL; DEC E; JR NZ, loop; JR $
+        0x23, 0x1D, 0x20, 0xE8, 0x18, 0xFE,
+    ];
+    code.resize(DATA_ADDR - 0x8000, 0);
+    code.extend_from_slice(data);
+    code
+}
+
+#[test]
+fn plus3_printer_capture() {
+    let mut tester = RustZXTester::new("plus3_printer_capture", presets::settings_plus3_nosound());
+    tester.load_rom_pages((0..4).map(|_| vec![0; 0x4000]).collect());
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna_128k(
+            0x10,
+            &plus3_printer_code(PRINTED),
+        ))))
+        .expect("Failed to load snapshot");
+    let mut raw = PrinterCapture::new(Vec::new(), CaptureMode::Raw);
+    let mut text = PrinterCapture::new(Vec::new(), CaptureMode::Text);
+    for _ in 0..2 {
+        tester.emulate_frame();
+        let output = tester.emulator().take_printer_output();
+        raw.write(&output).unwrap();
+        text.write(&output).unwrap();
+    }
+    assert_eq!(raw.into_inner(), PRINTED);
+    assert_eq!(text.into_inner(), b"10 PRINT \"HI\"\n\n20 GO TO 10\n\n");
+}
+
+#[test]
+fn printer_port_only_on_plus3() {
+    let mut tester = RustZXTester::new(
+        "printer_port_only_on_plus3",
+        presets::settings_128k_nosound(),
+    );
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna_128k(
+            0x10,
+            &plus3_printer_code(PRINTED),
+        ))))
+        .expect("Failed to load snapshot");
+    tester.emulate_frame();
+    assert!(tester.emulator().take_printer_output().is_empty());
+}
@@ -0,0 +1,25 @@
+use rustzx_test::framework::RustZXTester;
+
+#[test]
+fn profile_buckets_by_pc() {
+    // 0x8000: loop: CALL 0x9000; JR loop     ; 17 + 12 clocks
+    // 0x9000: NOP x 10; RET                  ; 40 + 10 clocks
+    let mut code = vec![0xCD, 0x00, 0x90, 0x18, 0xFB];
+    code.resize(0x1000, 0x00);
+    code.extend_from_slice(&[0x00; 10]);
+    code.push(0xC9);
+
+    let mut tester = RustZXTester::with_code("profile_buckets_by_pc", &code);
+    let emulator = tester.emulator();
+    emulator.reset_profile();
+
+    // 100 loop iterations, 13 instructions each
+    let clocks = emulator.step_instructions(1300).unwrap();
+    let buckets = emulator.profile_buckets();
+    assert_eq!(buckets[0x80], 100 * 29);
+    assert_eq!(buckets[0x90], 100 * 50);
+    assert_eq!(buckets.iter().sum::<u64>(), clocks);
+
+    emulator.reset_profile();
+    assert!(emulator.profile_buckets().iter().all(|&clocks| clocks == 0));
+}
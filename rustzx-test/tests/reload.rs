@@ -0,0 +1,104 @@
+use rustzx_core::{
+    error::{BinaryLoadError, Error},
+    host::BufferCursor,
+    ReloadMode,
+};
+use rustzx_test::framework::RustZXTester;
+use rustzx_utils::{palette::rgba::ORIGINAL, watch::BinaryWatcher};
+use std::time::{Duration, SystemTime};
+
+// EI
+// loop: HALT
+//       JR loop
+const IDLE_CODE: [u8; 4] = [0xFB, 0x76, 0x18, 0xFD];
+
+fn idle_tester(name: &str) -> RustZXTester {
+    let mut tester = RustZXTester::with_code(name, &IDLE_CODE);
+    tester.emulate_frame();
+    tester
+}
+
+#[test]
+fn reload_preserve_state_applied_at_frame_start() {
+    let mut tester = idle_tester("reload_preserve_state_applied_at_frame_start");
+    // First line of the first char cell set, white paper and black ink
+    let mut binary = vec![0u8; 0x1801];
+    binary[0] = 0xFF;
+    binary[0x1800] = 0x38;
+    tester
+        .emulator()
+        .reload_binary(BufferCursor::new(binary), 0x4000, ReloadMode::PreserveState)
+        .unwrap();
+    tester.emulator().step_instructions(10).unwrap();
+    assert!(tester.emulator().is_reload_pending());
+    assert_eq!(tester.peek(0x5800), 0x00);
+
+    tester.emulate_frame();
+    assert!(!tester.emulator().is_reload_pending());
+    assert_eq!(tester.peek(0x4000), 0xFF);
+    assert_eq!(tester.peek(0x5800), 0x38);
+    // Code keeps running
+    let pc = tester.emulator().registers().pc;
+    assert!((0x8000..0x8004).contains(&pc), "PC {:#06X}", pc);
+
+    tester.emulate_frame();
+    let image = tester.screen_image();
+    assert_eq!(image.pixel(0, 0), ORIGINAL[0]);
+    assert_eq!(image.pixel(0, 1), ORIGINAL[7]);
+}
+
+#[test]
+fn reload_restart_jumps_to_load_address() {
+    let mut tester = idle_tester("reload_restart_jumps_to_load_address");
+    // LD A, 0x42; LD (0x9100), A
+    // loop: JR loop
+    let binary = vec![0x3E, 0x42, 0x32, 0x00, 0x91, 0x18, 0xFE];
+    tester
+        .emulator()
+        .reload_binary(BufferCursor::new(binary), 0x9000, ReloadMode::Restart)
+        .unwrap();
+    tester.emulate_frame();
+    tester.emulate_frame();
+    assert_eq!(tester.peek(0x9100), 0x42);
+    let registers = tester.emulator().registers();
+    assert_eq!(registers.pc, 0x9005);
+    assert!(!registers.iff1);
+}
+
+#[test]
+fn reload_binary_too_large() {
+    let mut tester = idle_tester("reload_binary_too_large");
+    let result = tester.emulator().reload_binary(
+        BufferCursor::new(vec![0u8; 0x1001]),
+        0xF000,
+        ReloadMode::PreserveState,
+    );
+    assert!(matches!(
+        result,
+        Err(Error::BinaryLoad(BinaryLoadError::TooLarge))
+    ));
+    assert!(!tester.emulator().is_reload_pending());
+}
+
+#[test]
+fn binary_watcher_reloads_modified_file() {
+    let mut tester = idle_tester("binary_watcher_reloads_modified_file");
+    let path = std::env::temp_dir().join(format!("rustzx_reload_{}.bin", std::process::id()));
+    std::fs::write(&path, [0x11]).unwrap();
+    let mut watcher = BinaryWatcher::new(&path, 0x9000, ReloadMode::PreserveState);
+    assert!(!watcher.poll(tester.emulator()).unwrap());
+
+    std::fs::write(&path, [0x22, 0x33]).unwrap();
+    let file = std::fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+    // Modification is picked up when the file settles
+    assert!(!watcher.poll(tester.emulator()).unwrap());
+    assert!(watcher.poll(tester.emulator()).unwrap());
+    assert!(!watcher.poll(tester.emulator()).unwrap());
+    tester.emulate_frame();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(tester.peek(0x9000), 0x22);
+    assert_eq!(tester.peek(0x9001), 0x33);
+}
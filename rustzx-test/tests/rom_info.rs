@@ -0,0 +1,56 @@
+use rustzx_core::zx::rom_info::{KnownRom, RomTraps};
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+fn known_roms(tester: &mut RustZXTester) -> Vec<Option<KnownRom>> {
+    let info = tester.emulator().rom_info();
+    info.pages().iter().map(|page| page.rom).collect()
+}
+
+#[test]
+fn builtin_roms_are_known() {
+    let mut tester = RustZXTester::new("rom_info_48k", presets::settings_48k_nosound());
+    assert_eq!(known_roms(&mut tester), vec![Some(KnownRom::Sinclair48K)]);
+    assert!(tester.emulator().rom_info().is_known());
+    assert_eq!(tester.emulator().rom_info().traps(0), RomTraps::BASIC_48K);
+
+    let mut tester = RustZXTester::new("rom_info_128k", presets::settings_128k_nosound());
+    assert_eq!(
+        known_roms(&mut tester),
+        vec![
+            Some(KnownRom::Sinclair128KEditor),
+            Some(KnownRom::Sinclair128KBasic)
+        ]
+    );
+    let info = tester.emulator().rom_info();
+    assert_eq!(info.basic_page(), 1);
+    assert_eq!(info.traps(0), RomTraps::EDITOR_128K);
+    assert_eq!(info.traps(1), RomTraps::BASIC_48K);
+}
+
+#[test]
+fn diag_rom_has_no_traps() {
+    let mut tester = RustZXTester::new("rom_info_diag", presets::settings_48k_nosound());
+    tester.load_single_page_rom("diag_rom_v56.gz");
+    assert_eq!(known_roms(&mut tester), vec![Some(KnownRom::DiagRom)]);
+    assert_eq!(tester.emulator().rom_info().traps(0), RomTraps::NONE);
+}
+
+#[test]
+fn patched_rom_falls_back_to_48k_traps() {
+    let mut tester = RustZXTester::new("rom_info_patched", presets::settings_48k_nosound());
+    let mut rom = tester.emulator().rom_page(0).unwrap().to_vec();
+    // Unused area before the character set
+    rom[0x3CFF] = 0x00;
+    tester.load_rom_pages(vec![rom]);
+
+    let info = tester.emulator().rom_info();
+    assert!(!info.is_known());
+    assert_eq!(info.pages()[0].rom, None);
+    assert_eq!(info.traps(0), RomTraps::BASIC_48K);
+
+    // Fast load still works, autoload types LOAD "" after boot
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(5000));
+    assert_eq!(tester.emulator().tape_progress().unwrap().fraction, 1.0);
+}
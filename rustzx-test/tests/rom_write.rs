@@ -0,0 +1,67 @@
+use rustzx_core::host::{BufferCursor, Snapshot};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    sna::sna_128k,
+};
+
+// DI
+// LD A, 0xAA
+// LD (0x1000), A
+// LD (0x9000), A
+// LD HL, 0x8000; LD DE, 0x0000; LD BC, 0x4000; LDIR
+// HALT
+const ROM_WRITE_CODE: [u8; 21] = [
+    0xF3, 0x3E, 0xAA, 0x32, 0x00, 0x10, 0x32, 0x00, 0x90, 0x21, 0x00, 0x80, 0x11, 0x00, 0x00, 0x01,
+    0x00, 0x40, 0xED, 0xB0, 0x76,
+];
+
+#[test]
+fn rom_writes_ignored_48k() {
+    let mut tester = RustZXTester::with_code("rom_writes_ignored_48k", &ROM_WRITE_CODE);
+    let rom = tester.emulator().rom_page(0).unwrap().to_vec();
+    let mut step = |count| tester.emulator().step_instructions(count).unwrap();
+
+    assert_eq!(step(2), 4 + 7);
+    // Discarded write to ROM takes the same time as write to uncontended RAM
+    let rom_write = step(1);
+    let ram_write = step(1);
+    assert_eq!(rom_write, 13);
+    assert_eq!(rom_write, ram_write);
+
+    // Copy of 16K over the whole ROM
+    step(3);
+    assert_eq!(step(0x4000), 0x3FFF * 21 + 16);
+    assert_eq!(tester.peek(0x1000), rom[0x1000]);
+    assert_eq!(tester.peek(0x9000), 0xAA);
+    assert!(tester.emulator().rom_page(0).unwrap() == rom.as_slice());
+}
+
+#[test]
+fn rom_writes_ignored_128k() {
+    for port_7ffd in [0x00, 0x10] {
+        let mut tester =
+            RustZXTester::new("rom_writes_ignored_128k", presets::settings_128k_nosound());
+        tester
+            .emulator()
+            .load_snapshot(Snapshot::Sna(BufferCursor::new(sna_128k(
+                port_7ffd,
+                &ROM_WRITE_CODE,
+            ))))
+            .expect("Failed to load snapshot");
+        let roms = [0, 1].map(|page| tester.emulator().rom_page(page).unwrap().to_vec());
+        let mut step = |count| tester.emulator().step_instructions(count).unwrap();
+
+        step(2);
+        let rom_write = step(1);
+        let ram_write = step(1);
+        assert_eq!(rom_write, ram_write, "7FFD {:#04X}", port_7ffd);
+        step(3 + 0x4000);
+
+        let rom_page = (port_7ffd >> 4) as usize;
+        assert_eq!(tester.peek(0x1000), roms[rom_page][0x1000]);
+        assert_eq!(tester.peek(0x9000), 0xAA);
+        for (page, rom) in roms.iter().enumerate() {
+            assert!(tester.emulator().rom_page(page as u8).unwrap() == rom.as_slice());
+        }
+    }
+}
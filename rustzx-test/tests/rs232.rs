@@ -0,0 +1,62 @@
+use rustzx_core::zx::rs232::DEFAULT_BAUD_RATE;
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+const KEY_DOWN: u8 = 0x0A;
+const KEY_ENTER: u8 = 0x0D;
+const PROGRAM: &[u8] = b"10 print \"hello\"\r20 goto 10\r";
+const PROGRAM_LISTING: &[u8] = b"  10 PRINT \"hello\"\r\n  20 GO TO 10\r\n";
+
+/// Boots 128K with attached RS232 device and enters 128 BASIC
+fn boot_128_basic(test_name: &str, baud_rate: u32) -> RustZXTester {
+    let mut t = RustZXTester::new(test_name, presets::settings_128k());
+    t.emulator().attach_rs232(baud_rate);
+    t.emulate_for(Duration::from_millis(3000));
+    t.type_chars(&[KEY_DOWN, KEY_ENTER]);
+    t.emulate_for(Duration::from_millis(500));
+    t
+}
+
+#[test]
+fn rs232_llist() {
+    let mut t = boot_128_basic("rs232_llist", DEFAULT_BAUD_RATE);
+    t.type_chars(PROGRAM);
+    t.type_chars(b"llist\r");
+    t.emulate_for(Duration::from_millis(1000));
+    assert_eq!(t.emulator().take_rs232_output(), PROGRAM_LISTING);
+    assert_eq!(t.emulator().rs232_framing_errors(), 0);
+    assert!(t.emulator().take_rs232_output().is_empty());
+}
+
+#[test]
+fn rs232_text_channel() {
+    // Printer channel of 128 BASIC is a text channel (as "t" of Interface 1):
+    // tokens are expanded and control codes are not sent
+    let mut t = boot_128_basic("rs232_text_channel", DEFAULT_BAUD_RATE);
+    t.type_chars(b"lprint chr$ 245;chr$ 1;\"x\"\r");
+    t.emulate_for(Duration::from_millis(1000));
+    assert_eq!(t.emulator().take_rs232_output(), b"PRINT x\r\n");
+}
+
+#[test]
+fn rs232_llist_custom_baud_rate() {
+    let mut t = boot_128_basic("rs232_llist_custom_baud_rate", 2400);
+    t.type_chars(b"format \"p\";2400\r");
+    // Wait until the editor accepts the next line
+    t.emulate_for(Duration::from_millis(500));
+    t.type_chars(PROGRAM);
+    t.type_chars(b"llist\r");
+    t.emulate_for(Duration::from_millis(1000));
+    assert_eq!(t.emulator().take_rs232_output(), PROGRAM_LISTING);
+    assert_eq!(t.emulator().rs232_framing_errors(), 0);
+}
+
+#[test]
+fn rs232_baud_rate_mismatch() {
+    let mut t = boot_128_basic("rs232_baud_rate_mismatch", 2400);
+    t.type_chars(PROGRAM);
+    t.type_chars(b"llist\r");
+    t.emulate_for(Duration::from_millis(1000));
+    assert_ne!(t.emulator().take_rs232_output(), PROGRAM_LISTING);
+    assert!(t.emulator().rs232_framing_errors() > 0);
+}
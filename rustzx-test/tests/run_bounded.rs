@@ -0,0 +1,64 @@
+use rustzx_test::{
+    framework::RustZXTester,
+    fuzz::{self, MAX_BOUNDED_OVERRUN},
+};
+
+#[test]
+fn run_bounded_consumes_budget() {
+    // loop: LD A, (0x4000); JR loop
+    let mut tester = RustZXTester::with_code(
+        "run_bounded_consumes_budget",
+        &[0x3A, 0x00, 0x40, 0x18, 0xFB],
+    );
+    let result = tester.emulator().run_bounded(0).unwrap();
+    assert_eq!((result.tstates, result.instructions), (0, 0));
+
+    let result = tester.emulator().run_bounded(100_000).unwrap();
+    assert!((100_000..100_000 + MAX_BOUNDED_OVERRUN).contains(&result.tstates));
+    assert!(result.frames >= 1);
+    assert!(result.instructions > 100_000 / 30);
+}
+
+#[test]
+fn run_bounded_halt_with_interrupts_disabled() {
+    // DI; HALT
+    let mut tester =
+        RustZXTester::with_code("run_bounded_halt_with_interrupts_disabled", &[0xF3, 0x76]);
+    let result = tester.emulator().run_bounded(1_000_000).unwrap();
+    assert!((1_000_000..1_000_000 + MAX_BOUNDED_OVERRUN).contains(&result.tstates));
+    // HALT executes NOPs forever
+    assert_eq!(tester.emulator().peek(0x8001), 0x76);
+}
+
+#[test]
+fn run_bounded_endless_prefix_chain() {
+    // DI, then RAM is filled with DD prefixes which never complete an
+    // instruction until the ROM is reached
+    let mut code = vec![0xF3];
+    code.resize(0x8000, 0xDD);
+    let mut tester = RustZXTester::with_code("run_bounded_endless_prefix_chain", &code);
+    let result = tester.emulator().run_bounded(1_000).unwrap();
+    assert!((1_000..1_000 + MAX_BOUNDED_OVERRUN).contains(&result.tstates));
+    // Each prefix is executed as a separate 4-clock instruction
+    assert!(result.instructions > 1_000 / 5);
+}
+
+#[test]
+fn run_bounded_random_snapshots() {
+    let mut state = 0x2545F4914F6CDD1Du64;
+    for size in [49179, 147487] {
+        for seed_byte in [0u8, 1] {
+            let data = (0..size)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .enumerate()
+                .map(|(i, byte)| if i == 0 { byte & !1 | seed_byte } else { byte })
+                .collect::<Vec<_>>();
+            fuzz::run_random_snapshot(&data);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use expect_test::expect;
+use rustzx_core::{
+    input::{InputEvent, InputState},
+    zx::{keys::ZXKey, machine::ZXMachine},
+    RustzxSettings,
+};
+use rustzx_test::framework::{presets, RustZXTester};
+
+// EI
+// loop: HALT
+//       LD A, 0x10; OUT (0xFE), A
+//       LD D, 3
+// l1:   LD B, 0
+// l2:   DJNZ l2
+//       DEC D; JR NZ, l1
+//       XOR A; OUT (0xFE), A
+//       JR loop
+// Pulse ends in the top border, so both OUTs are not delayed by contention
+// and the edges have the same timing in each frame
+const BEEPER_PULSE_CODE: [u8; 20] = [
+    0xFB, 0x76, 0x3E, 0x10, 0xD3, 0xFE, 0x16, 0x03, 0x06, 0x00, 0x10, 0xFE, 0x15, 0x20, 0xF9, 0xAF,
+    0xD3, 0xFE, 0x18, 0xED,
+];
+
+#[test]
+fn run_frame_audio_in_lockstep() {
+    let mut tester = RustZXTester::new("run_frame_audio_in_lockstep", presets::settings_48k());
+    tester.load_code(&BEEPER_PULSE_CODE);
+
+    let input = InputState::new();
+    let mut frames = vec![];
+    for n in 1..=5 {
+        let output = tester.emulator().run_frame(&input).unwrap();
+        assert_eq!(output.frame_number, n);
+        assert_eq!(output.audio.len(), 44100 / 50);
+        let audio = output
+            .audio
+            .iter()
+            .map(|s| (s.left, s.right))
+            .collect::<Vec<_>>();
+        frames.push(audio);
+    }
+
+    // Same pulse is produced in each frame, so frames after the first one
+    // (which starts in the middle of the program) should be identical
+    let pulse = &frames[1];
+    assert!(pulse.iter().any(|sample| *sample != pulse[0]));
+    assert!(frames[2..].iter().all(|frame| frame == pulse));
+}
+
+#[test]
+fn run_frame_audio_carries_fractional_samples() {
+    // 32000 / 60 = 533.33 samples per frame
+    let settings = RustzxSettings {
+        machine: ZXMachine::Sinclair48KNtsc,
+        sound_sample_rate: 32000,
+        ..presets::settings_48k()
+    };
+    let mut tester = RustZXTester::new("run_frame_audio_carries_fractional_samples", settings);
+    let input = InputState::new();
+    let counts = (0..120)
+        .map(|_| tester.emulator().run_frame(&input).unwrap().audio.len())
+        .collect::<Vec<_>>();
+    assert_eq!(counts[..3], [533, 533, 534]);
+    assert!(counts
+        .chunks(60)
+        .all(|second| second.iter().sum::<usize>() == 32000));
+}
+
+#[test]
+fn run_frame_applies_input() {
+    let mut tester = RustZXTester::new("run_frame_applies_input", presets::settings_48k_nosound());
+    let mut input = InputState::new();
+    for _ in 0..100 {
+        tester.emulator().run_frame(&input).unwrap();
+    }
+
+    // Press `P` (PRINT keyword in K mode) for a few frames
+    input.push(InputEvent::Key(ZXKey::P, true));
+    tester.emulator().run_frame(&input).unwrap();
+    input.clear();
+    for _ in 0..5 {
+        let output = tester.emulator().run_frame(&input).unwrap();
+        assert!(output.audio.is_empty());
+    }
+    input.push(InputEvent::Key(ZXKey::P, false));
+    tester.emulator().run_frame(&input).unwrap();
+    input.clear();
+    for _ in 0..5 {
+        tester.emulator().run_frame(&input).unwrap();
+    }
+
+    tester.expect_screen(
+        "print",
+        expect![[r#"VIGdLZbs0tu+q//u5d+LSDVu1S85ncxy6alp7voj6Zw="#]],
+    );
+}
+
+#[test]
+fn run_frame_flash_state() {
+    let mut tester = RustZXTester::new("run_frame_flash_state", presets::settings_48k_nosound());
+    assert!(!tester.emulator().flash_state());
+    assert_eq!(tester.emulator().screen_frame_counter(), 0);
+
+    let input = InputState::new();
+    let mut rendered_flash = false;
+    for _ in 0..40 {
+        let output = tester.emulator().run_frame(&input).unwrap();
+        // Output frame was rendered with the state before the toggle
+        assert_eq!(output.flash_state, rendered_flash);
+        let counter = tester.emulator().screen_frame_counter();
+        let flash = tester.emulator().flash_state();
+        assert_eq!(flash, counter.div_ceil(16) % 2 == 1, "frame {}", counter);
+        rendered_flash = flash;
+    }
+    assert_eq!(tester.emulator().screen_frame_counter(), 40);
+
+    // Snapshot load resets frame number, but not the ULA flash cadence
+    tester.load_code(&BEEPER_PULSE_CODE);
+    tester.emulator().run_frame(&input).unwrap();
+    assert_eq!(tester.emulator().frame_number(), 1);
+    assert_eq!(tester.emulator().screen_frame_counter(), 41);
+}
+
+#[test]
+fn run_frame_perf_stats() {
+    let mut tester = RustZXTester::new("run_frame_perf_stats", presets::settings_48k_nosound());
+    assert_eq!(tester.emulator().perf_stats().fps, 0.0);
+    let input = InputState::new();
+    for _ in 0..3 {
+        tester.emulator().run_frame(&input).unwrap();
+    }
+    let stats = tester.emulator().perf_stats();
+    assert!(stats.avg_ms > 0.0);
+    assert!(stats.max_ms >= stats.avg_ms);
+    assert!(stats.fps > 0.0);
+}
@@ -0,0 +1,80 @@
+use rustzx_core::zx::video::colors::ZXAttribute;
+use rustzx_test::framework::RustZXTester;
+use rustzx_utils::image::{diff_frames, FrameDiffError, RgbaImage, DISPLAY_FILE_SIZE};
+
+const ATTRIBUTES_OFFSET: usize = 6144;
+
+// DI
+// LD A, 0xFF; LD (0x43AA), A (line 3 of the cell at row 5, column 10)
+// LD A, 0x47; LD (0x5A9F), A (attribute of the cell at row 20, column 31)
+// HALT
+const DRAW_CODE: [u8; 12] = [
+    0xF3, 0x3E, 0xFF, 0x32, 0xAA, 0x43, 0x3E, 0x47, 0x32, 0x9F, 0x5A, 0x76,
+];
+
+#[test]
+fn screen_diff_reports_changed_cells() {
+    let mut tester = RustZXTester::with_code("screen_diff_reports_changed_cells", &DRAW_CODE);
+    let before = tester.emulator().screen_snapshot();
+    assert!(tester.emulator().diff_screen_cells(&before).is_empty());
+
+    tester.emulate_frame();
+    let after = tester.emulator().screen_snapshot();
+    assert_eq!(
+        tester.emulator().diff_screen_cells(&before),
+        [(5, 10), (20, 31)]
+    );
+    assert_eq!(after.diff_cells(&before), [(5, 10), (20, 31)]);
+    assert!(after.diff_cells(&after).is_empty());
+
+    // Bitmap lines are stored top to bottom
+    assert_eq!(after.bitmap()[(5 * 8 + 3) * 32 + 10], 0xFF);
+    assert_eq!(after.attributes()[20 * 32 + 31], 0x47);
+    assert!(after.cell_eq(&before, 0, 0));
+}
+
+#[test]
+fn attribute_byte_round_trip() {
+    for byte in 0..=255u8 {
+        assert_eq!(ZXAttribute::from_byte(byte).to_byte(), byte);
+    }
+}
+
+#[test]
+fn frame_diff_reports_changed_pixels() {
+    // Black ink on white paper
+    let mut display_file = vec![0; DISPLAY_FILE_SIZE];
+    display_file[ATTRIBUTES_OFFSET..].fill(0x38);
+    let before = RgbaImage::from_display_file(&display_file).unwrap();
+
+    // 8 pixels of the first line of the cell at row 0, column 1
+    display_file[1] = 0xFF;
+    // Paper of the cell at row 23, column 31 is changed to red
+    display_file[DISPLAY_FILE_SIZE - 1] = 0x10;
+    let after = RgbaImage::from_display_file(&display_file).unwrap();
+
+    let (changed_pixels, mask) = diff_frames(&before.rgba, &after.rgba).unwrap();
+    assert_eq!(changed_pixels, 8 + 64);
+    assert_eq!(mask.len(), before.width * before.height);
+    let changed = |x: usize, y: usize| mask[y * before.width + x];
+    assert!((8..16).all(|x| changed(x, 0)));
+    assert!(!changed(7, 0) && !changed(16, 0) && !changed(8, 1));
+    assert!(changed(248, 184) && changed(255, 191));
+    assert!(!changed(247, 191));
+
+    assert_eq!(diff_frames(&after.rgba, &after.rgba).unwrap().0, 0);
+}
+
+#[test]
+fn frame_diff_rejects_mismatched_frames() {
+    assert_eq!(
+        diff_frames(&[0; 8], &[0; 12]),
+        Err(FrameDiffError::SizeMismatch { a: 8, b: 12 })
+    );
+    let error = diff_frames(&[0; 6], &[0; 6]).unwrap_err();
+    assert_eq!(error, FrameDiffError::PartialPixel(6));
+    assert_eq!(
+        error.to_string(),
+        "frame size 6 is not a multiple of 4 bytes"
+    );
+}
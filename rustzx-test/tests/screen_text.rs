@@ -0,0 +1,73 @@
+use rustzx_test::framework::{presets, RustZXTester};
+use rustzx_utils::{
+    image::DISPLAY_FILE_SIZE,
+    text::{read_display_file_text, GlyphSet, TEXT_COLUMNS, TEXT_ROWS, UNKNOWN_CHAR},
+};
+use std::time::Duration;
+
+const TIMEOUT_BOOT: Duration = Duration::from_secs(3);
+
+/// Returns display file offset of the pixel line of the character cell
+fn cell_offset(row: usize, col: usize, line: usize) -> usize {
+    ((row & 0x18) << 8) | (line << 8) | ((row & 0x07) << 5) | col
+}
+
+#[test]
+fn boot_copyright_48k() {
+    let mut t = RustZXTester::new("screen_text_48k", presets::settings_48k_nosound());
+    t.emulate_for(TIMEOUT_BOOT);
+    let text = t.screen_text();
+    assert_eq!(text.len(), TEXT_ROWS);
+    assert!(text.iter().all(|line| line.chars().count() == TEXT_COLUMNS));
+    assert_eq!(text[23].trim_end(), "© 1982 Sinclair Research Ltd");
+    assert!(text[..23].iter().all(|line| line.trim().is_empty()));
+}
+
+#[test]
+fn boot_menu_128k() {
+    let mut t = RustZXTester::new("screen_text_128k", presets::settings_128k_nosound());
+    t.emulate_for(TIMEOUT_BOOT);
+    let text = t.screen_text();
+    // Menu items are printed in inverse video
+    assert!(text.iter().any(|line| line.contains("Tape Loader")));
+    assert!(text.iter().any(|line| line.contains("48 BASIC")));
+    assert_eq!(text[23].trim_end(), "© 1986 Sinclair Research Ltd");
+}
+
+#[test]
+fn inverse_and_unknown_cells() {
+    let mut t = RustZXTester::new("screen_text_cells", presets::settings_48k_nosound());
+    t.emulate_for(Duration::from_millis(100));
+    let glyphs = GlyphSet::rom(t.emulator());
+
+    let mut display_file = vec![0; DISPLAY_FILE_SIZE];
+    // `A` at row 10, column 3
+    for line in 0..8 {
+        display_file[cell_offset(10, 3, line)] = t.peek(0x3D00 + 33 * 8 + line as u16);
+    }
+    // inverse `A` at row 10, column 4
+    for line in 0..8 {
+        display_file[cell_offset(10, 4, line)] = !t.peek(0x3D00 + 33 * 8 + line as u16);
+    }
+    // checkerboard is not a character
+    for line in 0..8 {
+        display_file[cell_offset(10, 5, line)] = if line % 2 == 0 { 0xAA } else { 0x55 };
+    }
+
+    let text = read_display_file_text(&display_file, &glyphs);
+    let expected = format!("   AA{}", UNKNOWN_CHAR);
+    assert_eq!(text[10].trim_end(), expected);
+}
+
+#[test]
+fn custom_glyphs() {
+    let mut glyphs = GlyphSet::new();
+    glyphs.add_glyphs(&[0x18; 8], '|');
+    let mut display_file = vec![0; DISPLAY_FILE_SIZE];
+    for line in 0..8 {
+        display_file[cell_offset(23, 31, line)] = 0x18;
+    }
+    let text = read_display_file_text(&display_file, &glyphs);
+    // space is unknown too as it was not added
+    assert!(text[23].ends_with(&format!("{}|", UNKNOWN_CHAR)));
+}
@@ -0,0 +1,160 @@
+use rustzx_core::{
+    error::{Error, SnapshotLoadError, SnapshotSaveError},
+    host::{BufferCursor, Snapshot, SnapshotRecorder},
+};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    sna::{sna_128k, sna_48k},
+};
+use std::time::Duration;
+
+const SNA_48K_SIZE: usize = 49179;
+const SNA_128K_SIZE: usize = 131103;
+const SNA_128K_DUPLICATED_BANK_SIZE: usize = 147487;
+
+fn save(tester: &mut RustZXTester) -> Vec<u8> {
+    let mut sna = Vec::new();
+    tester
+        .emulator()
+        .save_snapshot(SnapshotRecorder::Sna(&mut sna))
+        .expect("Failed to save snapshot");
+    sna
+}
+
+fn load(tester: &mut RustZXTester, sna: Vec<u8>) {
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna)))
+        .expect("Failed to load snapshot");
+}
+
+#[test]
+fn sna_48k_fuse_layout_load() {
+    let mut tester = RustZXTester::new("sna_48k_fuse_layout_load", presets::settings_48k_nosound());
+    // LD A, 0x42; LD (0x9000), A; JR $
+    let code = [0x3E, 0x42, 0x32, 0x00, 0x90, 0x18, 0xFE];
+    load(&mut tester, sna_48k(0xFEFE, &code));
+    tester.emulate_frame();
+    assert_eq!(tester.peek(0x9000), 0x42);
+
+    // PC is pushed back to the same location on save
+    let sna = save(&mut tester);
+    assert_eq!(sna.len(), SNA_48K_SIZE);
+    assert_eq!(&sna[23..25], &0xFEFEu16.to_le_bytes());
+    let stack = 27 + 0xFEFE - 0x4000;
+    assert_eq!(&sna[stack..stack + 2], &0x8005u16.to_le_bytes());
+    // ...but emulated memory is left intact
+    assert_eq!([tester.peek(0xFEFE), tester.peek(0xFEFF)], [0x00, 0x80]);
+}
+
+#[test]
+fn sna_48k_round_trip() {
+    let mut tester = RustZXTester::new("sna_48k_round_trip", presets::settings_48k_nosound());
+    tester.emulate_for(Duration::from_secs(2));
+    let first = save(&mut tester);
+    assert_eq!(first.len(), SNA_48K_SIZE);
+    // Saving does not change running emulator state
+    assert_eq!(save(&mut tester), first);
+
+    let mut restored = RustZXTester::new("sna_48k_round_trip", presets::settings_48k_nosound());
+    load(&mut restored, first.clone());
+    assert_eq!(save(&mut restored), first);
+}
+
+#[test]
+fn sna_48k_stack_in_rom_is_refused() {
+    let mut tester = RustZXTester::new(
+        "sna_48k_stack_in_rom_is_refused",
+        presets::settings_48k_nosound(),
+    );
+    // JR $
+    load(&mut tester, sna_48k(0x4002, &[0x18, 0xFE]));
+    // SP is 0x4004 after load, pushing PC is still possible (screen is clobbered)
+    assert_eq!(save(&mut tester).len(), SNA_48K_SIZE);
+
+    // LD SP, 0x4000; JR $
+    load(
+        &mut tester,
+        sna_48k(0x8100, &[0x31, 0x00, 0x40, 0x18, 0xFE]),
+    );
+    tester.emulate_frame();
+    let mut sna = Vec::new();
+    let result = tester
+        .emulator()
+        .save_snapshot(SnapshotRecorder::Sna(&mut sna));
+    assert!(matches!(
+        result,
+        Err(Error::SnapshotSave(SnapshotSaveError::StackInRom))
+    ));
+}
+
+#[test]
+fn sna_128k_round_trip() {
+    let mut tester = RustZXTester::new("sna_128k_round_trip", presets::settings_128k_nosound());
+    // LD BC, 0x7FFD; LD A, 0x3F; OUT (C), A; JR $
+    // Pages bank 7, shows shadow screen, selects ROM 1 and locks paging
+    let code = [0x01, 0xFD, 0x7F, 0x3E, 0x3F, 0xED, 0x79, 0x18, 0xFE];
+    load(&mut tester, sna_128k(0x10, &code));
+    tester.emulate_frame();
+
+    let first = save(&mut tester);
+    assert_eq!(first.len(), SNA_128K_SIZE);
+    // PC, 0x7FFD and TR-DOS flag
+    assert_eq!(&first[49179..49183], &[0x07, 0x80, 0x3F, 0x00]);
+
+    // Loading into the machine with locked paging restores memory map too
+    load(&mut tester, first.clone());
+    assert_eq!(tester.emulator().display_bank(), 7);
+    assert_eq!(save(&mut tester), first);
+}
+
+#[test]
+fn sna_128k_paged_bank_duplicated() {
+    let mut tester = RustZXTester::new(
+        "sna_128k_paged_bank_duplicated",
+        presets::settings_128k_nosound(),
+    );
+    // LD BC, 0x7FFD; LD A, 0x12; OUT (C), A; JR $
+    let code = [0x01, 0xFD, 0x7F, 0x3E, 0x12, 0xED, 0x79, 0x18, 0xFE];
+    load(&mut tester, sna_128k(0x10, &code));
+    tester.emulate_frame();
+
+    // Bank 2 is stored twice, so all 6 other banks are in the tail
+    let sna = save(&mut tester);
+    assert_eq!(sna.len(), SNA_128K_DUPLICATED_BANK_SIZE);
+    load(&mut tester, sna.clone());
+    assert_eq!(save(&mut tester), sna);
+}
+
+#[test]
+fn sna_invalid_interrupt_mode() {
+    let mut tester = RustZXTester::new(
+        "sna_invalid_interrupt_mode",
+        presets::settings_48k_nosound(),
+    );
+    let mut sna = sna_48k(0xFEFE, &[0x18, 0xFE]);
+    sna[25] = 3;
+    let result = tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna)));
+    assert!(matches!(
+        result,
+        Err(Error::SnapshotLoad(SnapshotLoadError::InvalidInterruptMode))
+    ));
+}
+
+#[test]
+fn sna_truncated() {
+    let mut tester = RustZXTester::new("sna_truncated", presets::settings_128k_nosound());
+    let sna_48k = sna_48k(0xFEFE, &[0x18, 0xFE]);
+    let sna_128k = sna_128k(0x10, &[0x18, 0xFE]);
+    for sna in [&sna_48k[..100], &sna_128k[..SNA_48K_SIZE + 100]] {
+        let result = tester
+            .emulator()
+            .load_snapshot(Snapshot::sna_from_bytes(sna));
+        assert!(matches!(
+            result,
+            Err(Error::SnapshotLoad(SnapshotLoadError::TruncatedSnapshot))
+        ));
+    }
+}
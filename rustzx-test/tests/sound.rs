@@ -1,4 +1,8 @@
 use expect_test::expect;
+use rustzx_core::{
+    zx::sound::filter::{SoundFilterState, SoundFilters, TV_SPEAKER_CUTOFF},
+    RustzxSettings,
+};
 use rustzx_test::framework::{presets, RustZXTester};
 use std::time::Duration;
 
@@ -10,7 +14,7 @@ fn sound_48k() {
     tester.emulate_for(Duration::from_secs(2));
     tester.expect_sound(
         "beeper_plus_ay",
-        expect![[r#"bEAIV7LVE7591l6VUrCE1ySbEyBSNeVwLjWhDm9Jlpc="#]],
+        expect![[r#"ojJDvCsSARkmj/s9iIBJalthNJA/bdPdm19Lr0KHfwY="#]],
     );
 }
 
@@ -22,6 +26,190 @@ fn sound_128k() {
     tester.emulate_for(Duration::from_secs(2));
     tester.expect_sound(
         "beeper_plus_ay",
-        expect![[r#"u8WCHu89dFvnMInLGaDFV4ha6FatBtXLJ6szqiUg+ys="#]],
+        expect![[r#"e0QFvLK1Q/bOsiZF+h0ZvWxUa9HIVlOus2YWK8CBTJA="#]],
     );
 }
+
+/// Returns samples of the first second of the `sna` sound
+fn capture_samples(
+    name: &str,
+    settings: RustzxSettings,
+    sna: &str,
+    invert_beeper: bool,
+    invert_ay: bool,
+) -> Vec<(f32, f32)> {
+    let mut tester = RustZXTester::new(name, settings);
+    tester.load_sna(sna);
+    tester.emulator().set_beeper_polarity(invert_beeper);
+    tester.emulator().set_ay_polarity(invert_ay);
+    let mut samples = vec![];
+    for _ in 0..50 {
+        tester.emulate_frame();
+        while let Some(sample) = tester.emulator().next_audio_sample() {
+            samples.push((sample.left, sample.right));
+        }
+    }
+    assert!(samples.iter().any(|&(left, _)| left != 0.0));
+    samples
+}
+
+fn negated(samples: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    samples
+        .iter()
+        .map(|&(left, right)| (-left, -right))
+        .collect()
+}
+
+#[test]
+fn beeper_polarity() {
+    let settings = || RustzxSettings {
+        ay_enabled: false,
+        ..presets::settings_48k()
+    };
+    let name = "beeper_polarity";
+    let normal = capture_samples(name, settings(), "sound.48k.sna.gz", false, false);
+    let inverted = capture_samples(name, settings(), "sound.48k.sna.gz", true, false);
+    assert_eq!(inverted, negated(&normal));
+    // AY is disabled, so its polarity does not matter
+    let ay_inverted = capture_samples(name, settings(), "sound.48k.sna.gz", false, true);
+    assert_eq!(ay_inverted, normal);
+}
+
+#[test]
+fn ay_polarity() {
+    let settings = || RustzxSettings {
+        beeper_enabled: false,
+        ..presets::settings_128k()
+    };
+    let name = "ay_polarity";
+    let normal = capture_samples(name, settings(), "sound.128k.sna.gz", false, false);
+    let inverted = capture_samples(name, settings(), "sound.128k.sna.gz", false, true);
+    assert_eq!(inverted, negated(&normal));
+}
+
+/// Emulates frames taking their samples, returns silence flag of each frame
+fn silent_frames(tester: &mut RustZXTester, frames: usize) -> Vec<bool> {
+    (0..frames)
+        .map(|_| {
+            tester.emulate_frame();
+            while tester.emulator().next_audio_sample().is_some() {}
+            tester.emulator().was_silent_last_frame()
+        })
+        .collect()
+}
+
+#[test]
+fn silence_detection_of_playing_sound() {
+    let mut tester = RustZXTester::new("silence_detection", presets::settings_48k());
+    tester.load_sna("sound.48k.sna.gz");
+    assert!(silent_frames(&mut tester, 50).iter().any(|silent| !silent));
+}
+
+#[test]
+fn silence_detection_ignores_dc_offset() {
+    let mut tester = RustZXTester::new("silence_detection", presets::settings_48k());
+    // LD A, 0x10; OUT (0xFE), A; loop: JR loop
+    let code = [0x3E, 0x10, 0xD3, 0xFE, 0x18, 0xFE];
+    tester.load_code(&code);
+    // Level change right after the load is a click, which band-limited
+    // synthesis outputs a few samples later within the first frame
+    let silent = silent_frames(&mut tester, 10);
+    assert!(!silent[0]);
+    assert!(silent[1..].iter().all(|&silent| silent));
+    // Beeper output is still non-zero
+    tester.emulate_frame();
+    let sample = tester.emulator().next_audio_sample().unwrap();
+    assert!(sample.left > 0.0);
+}
+
+/// Returns samples of the frames emulated until the next frame start
+fn frame_samples(tester: &mut RustZXTester, frames: usize) -> Vec<f32> {
+    let mut samples = vec![];
+    for _ in 0..frames {
+        tester.emulate_frame();
+        while let Some(sample) = tester.emulator().next_audio_sample() {
+            samples.push(sample.left);
+        }
+    }
+    samples
+}
+
+/// Beeper with EAR bit set and never changed afterwards
+fn constant_beeper_tester(name: &str, filters: SoundFilters) -> RustZXTester {
+    let settings = RustzxSettings {
+        sound_filters: filters,
+        ..presets::settings_48k()
+    };
+    let mut tester = RustZXTester::new(name, settings);
+    // LD A, 0x10; OUT (0xFE), A; loop: JR loop
+    let code = [0x3E, 0x10, 0xD3, 0xFE, 0x18, 0xFE];
+    tester.load_code(&code);
+    tester
+}
+
+#[test]
+fn dc_blocker_removes_constant_level() {
+    // Band-limited synthesis delays beeper output by 8 samples (half of its
+    // kernel), the edge of the level change rings for 8 more samples
+    let mut raw = constant_beeper_tester("dc_blocker", SoundFilters::NONE);
+    let samples = frame_samples(&mut raw, 50);
+    assert!(samples[..8].iter().all(|sample| sample.abs() < 0.01));
+    assert!(samples[16..].iter().all(|&sample| sample > 0.2));
+
+    let filters = SoundFilters {
+        dc_blocker: true,
+        low_pass_cutoff: None,
+    };
+    let mut filtered = constant_beeper_tester("dc_blocker", filters);
+    let samples = frame_samples(&mut filtered, 50);
+    assert!(samples[9] > 0.2);
+    assert!(samples.last().unwrap().abs() < 0.001);
+}
+
+#[test]
+fn low_pass_smooths_beeper_edges() {
+    // Energy of the differences of the adjacent samples, square edges have
+    // the biggest ones
+    let edges = |filters| {
+        let settings = RustzxSettings {
+            ay_enabled: false,
+            sound_filters: filters,
+            ..presets::settings_48k()
+        };
+        let mut tester = RustZXTester::new("low_pass", settings);
+        tester.load_sna("sound.48k.sna.gz");
+        let samples = frame_samples(&mut tester, 50);
+        samples
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).powi(2))
+            .sum::<f32>()
+    };
+    let raw = edges(SoundFilters::NONE);
+    let filtered = edges(SoundFilters {
+        dc_blocker: false,
+        low_pass_cutoff: Some(TV_SPEAKER_CUTOFF),
+    });
+    assert!(raw > 0.0);
+    assert!(filtered < raw * 0.5, "{} {}", filtered, raw);
+}
+
+#[test]
+fn sound_filter_state_restore_does_not_click() {
+    let mut tester = constant_beeper_tester("filter_state", SoundFilters::TV_SPEAKER);
+    frame_samples(&mut tester, 50);
+    let state = tester.emulator().sound_filter_state();
+
+    // Filters forget the constant level, output jumps
+    tester
+        .emulator()
+        .set_sound_filter_state(SoundFilterState::default());
+    assert!(frame_samples(&mut tester, 1)
+        .iter()
+        .any(|&sample| sample > 0.1));
+
+    tester.emulator().set_sound_filter_state(state);
+    assert!(frame_samples(&mut tester, 1)
+        .iter()
+        .all(|sample| sample.abs() < 0.001));
+    assert_eq!(tester.emulator().sound_filters(), SoundFilters::TV_SPEAKER);
+}
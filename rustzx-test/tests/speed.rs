@@ -0,0 +1,42 @@
+use rustzx_core::EmulationMode;
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+// FRAMES system variable, incremented by 48K ROM on each frame interrupt
+const SYSVAR_FRAMES: u16 = 23672;
+
+fn frames_counter(tester: &mut RustZXTester) -> u8 {
+    tester.peek(SYSVAR_FRAMES)
+}
+
+#[test]
+fn slow_motion_emulates_single_frame_per_n_calls() {
+    let mut tester = RustZXTester::new(
+        "slow_motion_emulates_single_frame_per_n_calls",
+        presets::settings_48k(),
+    );
+    tester.emulate_for(Duration::from_millis(2000));
+    assert!(tester.emulator().have_sound());
+
+    tester.emulator().set_speed(EmulationMode::SlowMotion(4));
+    assert!(
+        !tester.emulator().have_sound(),
+        "sound is muted in slow motion"
+    );
+    let start = frames_counter(&mut tester);
+    for _ in 0..3 {
+        tester.emulate_frame();
+    }
+    assert_eq!(frames_counter(&mut tester), start);
+    tester.emulate_frame();
+    assert_eq!(frames_counter(&mut tester), start.wrapping_add(1));
+    for _ in 0..8 {
+        tester.emulate_frame();
+    }
+    assert_eq!(frames_counter(&mut tester), start.wrapping_add(3));
+
+    tester.emulator().set_speed(EmulationMode::FrameCount(1));
+    assert!(tester.emulator().have_sound());
+    tester.emulate_frame();
+    assert_eq!(frames_counter(&mut tester), start.wrapping_add(4));
+}
@@ -0,0 +1,83 @@
+use expect_test::expect;
+use rustzx_core::state::{DiffOptions, MemoryRange};
+use rustzx_test::framework::RustZXTester;
+
+// DI
+// LD HL, 0x9000; INC (HL); INC HL; INC (HL)
+// LD HL, 0x4000; INC (HL)
+// LD HL, 0x5C80; INC (HL)
+// HALT
+const CHANGE_MEMORY_CODE: [u8; 16] = [
+    0xF3, 0x21, 0x00, 0x90, 0x34, 0x23, 0x34, 0x21, 0x00, 0x40, 0x34, 0x21, 0x80, 0x5C, 0x34, 0x76,
+];
+
+#[test]
+fn state_diff_reports_changed_ranges_and_registers() {
+    let mut tester = RustZXTester::with_code(
+        "state_diff_reports_changed_ranges_and_registers",
+        &CHANGE_MEMORY_CODE,
+    );
+    let before = tester.emulator().capture_state();
+    tester.emulate_frame();
+    let after = tester.emulator().capture_state();
+
+    let diff = before.diff(&after, DiffOptions::default());
+    assert_eq!(
+        diff.memory,
+        [
+            MemoryRange {
+                bank: 0,
+                start: 0x0000,
+                length: 1
+            },
+            MemoryRange {
+                bank: 0,
+                start: 0x1C80,
+                length: 1
+            },
+            MemoryRange {
+                bank: 1,
+                start: 0x1000,
+                length: 2
+            },
+        ]
+    );
+    assert_eq!(
+        tester.emulator().ram_bank(1).unwrap()[0x1000..0x1002],
+        [0x01, 0x01]
+    );
+    assert!(tester.emulator().ram_bank(3).is_none());
+    expect![[r#"
+        HL   0000 -> 5C80
+        PC   8000 -> 800F
+        R    0000 -> 0034
+        bank 0 0000..0001 (1 bytes)
+        bank 0 1C80..1C81 (1 bytes)
+        bank 1 1000..1002 (2 bytes)
+    "#]]
+    .assert_eq(&diff.to_string());
+}
+
+#[test]
+fn state_diff_ignores_screen_and_sysvars() {
+    let mut tester =
+        RustZXTester::with_code("state_diff_ignores_screen_and_sysvars", &CHANGE_MEMORY_CODE);
+    let before = tester.emulator().capture_state();
+    tester.emulate_frame();
+    let after = tester.emulator().capture_state();
+
+    let options = DiffOptions {
+        ignore_display_file: true,
+        ignore_sysvars: true,
+    };
+    let diff = before.diff(&after, options);
+    assert_eq!(
+        diff.memory,
+        [MemoryRange {
+            bank: 1,
+            start: 0x1000,
+            length: 2
+        }]
+    );
+    assert!(before.diff(&before, options).is_empty());
+}
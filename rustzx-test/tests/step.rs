@@ -0,0 +1,89 @@
+use rustzx_test::framework::RustZXTester;
+
+#[test]
+fn step_instructions_clocks() {
+    // NOP
+    // LD A, 1
+    // LD IX, 0
+    // DD FD 21 00 00 (LD IY, 0 with ignored DD prefix)
+    // HALT
+    let code = [
+        0x00, 0x3E, 0x01, 0xDD, 0x21, 0x00, 0x00, 0xDD, 0xFD, 0x21, 0x00, 0x00, 0x76,
+    ];
+    let mut tester = RustZXTester::with_code("step_instructions_clocks", &code);
+    let mut step = |count| tester.emulator().step_instructions(count).unwrap();
+
+    assert_eq!(step(1), 4);
+    assert_eq!(step(1), 7);
+    assert_eq!(step(1), 14);
+    assert_eq!(step(1), 18);
+    // HALT itself and then internal NOPs
+    assert_eq!(step(1), 4);
+    assert_eq!(step(3), 12);
+}
+
+#[test]
+fn step_instructions_with_interrupts() {
+    // EI
+    // loop: INC HL
+    //       JR loop
+    let code = [0xFB, 0x23, 0x18, 0xFD];
+    const INSTRUCTIONS: u64 = 50_000;
+
+    // Interrupts are handled by ROM routine in between, so the same clocks
+    // are expected regardless of the split into multiple calls
+    let mut tester = RustZXTester::with_code("step_instructions_with_interrupts", &code);
+    let clocks_batch = tester.emulator().step_instructions(INSTRUCTIONS).unwrap();
+
+    let mut tester = RustZXTester::with_code("step_instructions_with_interrupts", &code);
+    let clocks_single: u64 = (0..INSTRUCTIONS)
+        .map(|_| tester.emulator().step_instructions(1).unwrap())
+        .sum();
+
+    assert_eq!(clocks_batch, clocks_single);
+    // ~6.4 frames, including ROM interrupt handler instructions
+    assert_eq!(clocks_batch, 449_813);
+    // Interrupt handler updates FRAMES system variable once per frame start
+    assert_eq!(tester.peek(23672), 7);
+}
+
+#[test]
+fn current_instruction_at_pc() {
+    // LD A, (HL)
+    // DD FD 21 00 00 (LD IY, 0 with ignored DD prefix)
+    // JP 0x0000
+    let code = [0x7E, 0xDD, 0xFD, 0x21, 0x00, 0x00, 0xC3, 0x00, 0x00];
+    let mut tester = RustZXTester::with_code("current_instruction_at_pc", &code);
+    let registers = tester.emulator().registers();
+    assert_eq!(
+        tester.emulator().current_instruction(),
+        ("LD A,(HL)".to_string(), 1)
+    );
+    // Disassembly does not execute anything
+    assert_eq!(tester.emulator().registers(), registers);
+
+    tester.emulator().step_instructions(1).unwrap();
+    // Ignored prefix is shown on its own
+    assert_eq!(
+        tester.emulator().current_instruction(),
+        ("NOP".to_string(), 1)
+    );
+    // Stop after the first prefix, the second one is already fetched
+    tester.emulator().run_bounded(1).unwrap();
+    assert_eq!(tester.emulator().registers().pc, 0x8003);
+    assert_eq!(
+        tester.emulator().current_instruction(),
+        ("LD IY,0x0000".to_string(), 3)
+    );
+    tester.emulator().step_instructions(1).unwrap();
+    assert_eq!(
+        tester.emulator().current_instruction(),
+        ("JP 0x0000".to_string(), 3)
+    );
+    tester.emulator().step_instructions(1).unwrap();
+    // 48K ROM starts with DI
+    assert_eq!(
+        tester.emulator().current_instruction(),
+        ("DI".to_string(), 1)
+    );
+}
@@ -0,0 +1,101 @@
+use rustzx_core::{host::BufferCursor, EmulationStopReason};
+use rustzx_test::framework::RustZXTester;
+
+// 0x8000: DI
+// 0x8001: LD IY, 0x5C3A
+// 0x8005: CALL 0x8010
+// 0x8008: JR 0x8008
+// 0x8010: PUSH BC
+// 0x8011: EI
+// 0x8012: CALL 0x8020
+// 0x8015: HALT (ROM interrupt handler is called)
+// 0x8016: DI
+// 0x8017: POP BC
+// 0x8018: XOR A
+// 0x8019: RET NZ
+// 0x801A: RET
+// 0x8020: RET
+const ROUTINE_CODE: [u8; 33] = [
+    0xF3, 0xFD, 0x21, 0x3A, 0x5C, 0xCD, 0x10, 0x80, 0x18, 0xFE, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xC5, 0xFB, 0xCD, 0x20, 0x80, 0x76, 0xF3, 0xC1, 0xAF, 0xC0, 0xC9, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xC9,
+];
+
+// 0x8000: DI
+// 0x8001: IN A, (0xE7) ; pages in +D
+// 0x8003: JP 0x1000
+const PLUSD_ENTRY_CODE: [u8; 6] = [0xF3, 0xDB, 0xE7, 0xC3, 0x00, 0x10];
+
+/// +D ROM with CALL 0x1040 at 0x1000 followed by JR $, the called routine is
+/// LD A, 0x42; RET. There is no RET at 0x1042 in the 48K ROM under it
+fn plusd_rom() -> Vec<u8> {
+    let mut rom = vec![0; 0x2000];
+    rom[0x1000..0x1005].copy_from_slice(&[0xCD, 0x40, 0x10, 0x18, 0xFE]);
+    rom[0x1040..0x1043].copy_from_slice(&[0x3E, 0x42, 0xC9]);
+    rom
+}
+
+/// Loads the code and steps into the routine after PUSH BC
+fn tester(name: &str) -> RustZXTester {
+    let mut tester = RustZXTester::with_code(name, &ROUTINE_CODE);
+    tester.emulator().step_instructions(4).unwrap();
+    assert_eq!(tester.emulator().registers().pc, 0x8011);
+    tester
+}
+
+#[test]
+fn step_out_of_routine() {
+    let mut tester = tester("step_out_of_routine");
+    let frame = tester.emulator().frame_number();
+    assert_eq!(
+        tester.emulator().step_out().unwrap(),
+        EmulationStopReason::Completed
+    );
+    let regs = tester.emulator().registers();
+    assert_eq!(regs.pc, 0x8008);
+    assert_eq!(regs.sp, 0xFF00);
+    // Nested call, interrupt and not taken RET NZ did not stop the run
+    assert!(tester.emulator().frame_number() > frame);
+}
+
+#[test]
+fn step_out_of_nested_call() {
+    let mut tester = tester("step_out_of_nested_call");
+    tester.emulator().step_instructions(2).unwrap();
+    assert_eq!(tester.emulator().registers().pc, 0x8020);
+    assert_eq!(
+        tester.emulator().step_out().unwrap(),
+        EmulationStopReason::Completed
+    );
+    assert_eq!(tester.emulator().registers().pc, 0x8015);
+}
+
+#[test]
+fn step_out_stops_at_breakpoint() {
+    let mut tester = tester("step_out_stops_at_breakpoint");
+    tester.add_breakpoint(0x8016);
+    assert_eq!(
+        tester.emulator().step_out().unwrap(),
+        EmulationStopReason::Breakpoint
+    );
+    assert_eq!(tester.last_breakpoint(), 0x8016);
+}
+
+#[test]
+fn step_out_of_routine_in_paged_in_rom() {
+    let mut tester =
+        RustZXTester::with_code("step_out_of_routine_in_paged_in_rom", &PLUSD_ENTRY_CODE);
+    tester
+        .emulator()
+        .attach_plusd(BufferCursor::new(plusd_rom()))
+        .expect("Failed to attach +D");
+    tester.emulator().step_instructions(5).unwrap();
+    assert_eq!(tester.emulator().registers().pc, 0x1042);
+    assert_eq!(
+        tester.emulator().step_out().unwrap(),
+        EmulationStopReason::Completed
+    );
+    let regs = tester.emulator().registers();
+    assert_eq!(regs.pc, 0x1003);
+    assert_eq!(regs.af >> 8, 0x42);
+}
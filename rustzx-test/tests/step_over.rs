@@ -0,0 +1,118 @@
+use rustzx_core::{host::BufferCursor, EmulationStopReason};
+use rustzx_test::framework::RustZXTester;
+
+// 0x8000: DI
+// 0x8001: LD B, 3
+// 0x8003: CALL 0x8010
+// 0x8006: LD B, 1
+// 0x8008: CALL 0x8010 (with ignored DD prefix)
+// 0x800C: JR 0x800C
+// 0x8010: DEC B
+// 0x8011: JR Z, 0x8016
+// 0x8013: CALL 0x8010
+// 0x8016: RET
+const RECURSIVE_CALL_CODE: [u8; 23] = [
+    0xF3, 0x06, 0x03, 0xCD, 0x10, 0x80, 0x06, 0x01, 0xDD, 0xCD, 0x10, 0x80, 0x18, 0xFE, 0x00, 0x00,
+    0x05, 0x28, 0x03, 0xCD, 0x10, 0x80, 0xC9,
+];
+
+// 0x8000: DI
+// 0x8001: IN A, (0xE7) ; pages in +D
+// 0x8003: JP 0x1000
+const PLUSD_ENTRY_CODE: [u8; 6] = [0xF3, 0xDB, 0xE7, 0xC3, 0x00, 0x10];
+
+/// +D ROM with CALL 0x1040 at 0x1000 followed by JR $, the called routine is
+/// LD A, 0x42; RET. There is neither CALL nor RET at these addresses in the
+/// 48K ROM under it
+fn plusd_rom() -> Vec<u8> {
+    let mut rom = vec![0; 0x2000];
+    rom[0x1000..0x1005].copy_from_slice(&[0xCD, 0x40, 0x10, 0x18, 0xFE]);
+    rom[0x1040..0x1043].copy_from_slice(&[0x3E, 0x42, 0xC9]);
+    rom
+}
+
+fn tester(name: &str) -> RustZXTester {
+    let mut tester = RustZXTester::with_code(name, &RECURSIVE_CALL_CODE);
+    tester.emulator().step_instructions(2).unwrap();
+    tester
+}
+
+#[test]
+fn step_over_calls() {
+    let mut tester = tester("step_over_calls");
+    let mut step_over = || {
+        let reason = tester.emulator().step_over().unwrap();
+        (reason, tester.emulator().registers())
+    };
+
+    let (reason, regs) = step_over();
+    assert_eq!(reason, EmulationStopReason::Completed);
+    assert_eq!(regs.pc, 0x8006);
+    assert_eq!(regs.sp, 0xFF00);
+    assert_eq!(regs.bc >> 8, 0);
+
+    // Not a call, single step
+    assert_eq!(step_over().1.pc, 0x8008);
+    assert_eq!(step_over().1.pc, 0x800C);
+    assert_eq!(step_over().1.pc, 0x800C);
+}
+
+#[test]
+fn step_over_recursive_call() {
+    let mut tester = tester("step_over_recursive_call");
+    // Enter the routine, stop at the recursive call site
+    tester.emulator().step_instructions(3).unwrap();
+    let regs = tester.emulator().registers();
+    assert_eq!(regs.pc, 0x8013);
+
+    // Nested calls return to the same address, but deeper in the stack
+    assert_eq!(
+        tester.emulator().step_over().unwrap(),
+        EmulationStopReason::Completed
+    );
+    let after = tester.emulator().registers();
+    assert_eq!(after.pc, 0x8016);
+    assert_eq!(after.sp, regs.sp);
+    assert_eq!(after.bc >> 8, 0);
+}
+
+#[test]
+fn step_over_stops_at_breakpoint() {
+    let mut tester = tester("step_over_stops_at_breakpoint");
+    tester.add_breakpoint(0x8016);
+    assert_eq!(
+        tester.emulator().step_over().unwrap(),
+        EmulationStopReason::Breakpoint
+    );
+    assert_eq!(tester.last_breakpoint(), 0x8016);
+}
+
+#[test]
+fn step_over_ignores_breakpoint_at_call() {
+    let mut tester = tester("step_over_ignores_breakpoint_at_call");
+    tester.add_breakpoint(0x8003);
+    tester.add_breakpoint(0x8006);
+    assert_eq!(
+        tester.emulator().step_over().unwrap(),
+        EmulationStopReason::Completed
+    );
+    assert_eq!(tester.emulator().registers().pc, 0x8006);
+}
+
+#[test]
+fn step_over_call_in_paged_in_rom() {
+    let mut tester = RustZXTester::with_code("step_over_call_in_paged_in_rom", &PLUSD_ENTRY_CODE);
+    tester
+        .emulator()
+        .attach_plusd(BufferCursor::new(plusd_rom()))
+        .expect("Failed to attach +D");
+    tester.emulator().step_instructions(3).unwrap();
+    assert_eq!(tester.emulator().current_instruction().0, "CALL 0x1040");
+    assert_eq!(
+        tester.emulator().step_over().unwrap(),
+        EmulationStopReason::Completed
+    );
+    let regs = tester.emulator().registers();
+    assert_eq!(regs.pc, 0x1003);
+    assert_eq!(regs.af >> 8, 0x42);
+}
@@ -1,5 +1,12 @@
 use expect_test::expect;
-use rustzx_core::zx::keys::ZXKey;
+use rustzx_core::{
+    error::{Error, TapeLoadError},
+    zx::{
+        keys::ZXKey,
+        machine::ZXMachine,
+        storage::{DriveActivity, DriveStatus, StorageDevice},
+    },
+};
 use rustzx_test::framework::{presets, RustZXTester};
 use std::time::Duration;
 
@@ -37,6 +44,9 @@ fn no_fastload() {
 
     tester.emulator().play_tape();
     tester.emulate_for(Duration::from_millis(2000));
+    let status = tester.emulator().drive_status(StorageDevice::Tape).unwrap();
+    assert!(status.motor_on);
+    assert_eq!(status.activity, DriveActivity::Read);
 
     // Check tack tape is started loading
     tester.expect_border(
@@ -155,6 +165,8 @@ fn tape_stop() {
     // Check that stop actually stopped tape loading
     tester.emulator().stop_tape();
     tester.emulate_for(Duration::from_millis(100));
+    let status = tester.emulator().drive_status(StorageDevice::Tape).unwrap();
+    assert_eq!(status, DriveStatus::default());
     tester.expect_border(
         "stopped",
         expect![[r#"rIAW+jIqzRy5w0Xd+cqKAa6JVIgUhU5eTCvJ/kTmLuw="#]],
@@ -227,3 +239,245 @@ fn fastload_128k() {
         expect![[r#"tmGY7e4h+XA3px6BcqnCXF83NEdBqVw8PW9sQtpMAvM="#]],
     );
 }
+
+#[test]
+fn machine_switch_keeps_tape() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    settings.autoload_enabled = false;
+
+    let mut tester = RustZXTester::new("machine_switch_keeps_tape", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(500));
+
+    tester
+        .emulator()
+        .switch_machine(ZXMachine::Sinclair128K)
+        .unwrap();
+    // Wait for 128K ROM to load
+    tester.emulate_for(Duration::from_millis(3000));
+
+    // Emulate Enter keypress to load tape
+    tester.send_keystrokes(&[&[ZXKey::Enter]], Duration::from_millis(100));
+    tester.emulate_for(Duration::from_millis(100));
+    tester.expect_screen(
+        "ready_to_load",
+        expect![[r#"jSZUNHDpTpRwuQydjVmchehIKSlgP+bhcKE8bi+yZoc="#]],
+    );
+
+    // Tape inserted before the switch should still be there
+    tester.emulator().play_tape();
+    tester.emulate_for(Duration::from_millis(5100));
+    tester.expect_screen(
+        "block_1",
+        expect![[r#"FfpfX8Nl7RPODebDhyDPqEpNWieSSG7PYBXvg9ty7k0="#]],
+    );
+}
+
+#[test]
+fn tape_hot_swap() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    settings.autoload_enabled = false;
+
+    let mut tester = RustZXTester::new("tape_hot_swap", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    // Wait for ROM to load
+    tester.emulate_for(Duration::from_millis(2000));
+    // Emulate LOAD ""
+    tester.send_keystrokes(
+        &[
+            &[ZXKey::J],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::Enter],
+        ],
+        Duration::from_millis(100),
+    );
+
+    // Interrupt first tape in the middle of the pilot tone
+    tester.emulator().play_tape();
+    tester.emulate_for(Duration::from_millis(2000));
+    tester.emulator().stop_tape();
+
+    // Machine should keep waiting for the header, now from another tape
+    tester.load_tap("z80ccf.tap.gz");
+    tester.emulator().play_tape();
+    tester.emulate_for(Duration::from_millis(5500));
+    tester.expect_screen(
+        "second_tape_header",
+        expect![[r#"0zL7hsvtgGIEJwMyE+ihpM9jFncDuFwMQgBIBqR8bvo="#]],
+    );
+}
+
+#[test]
+fn fastload_48k_ntsc() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.machine = ZXMachine::Sinclair48KNtsc;
+
+    let mut tester = RustZXTester::new("fastload_48k_ntsc", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(100));
+    tester.expect_screen(
+        "loaded",
+        expect![[r#"zDQzdQr19uTYaZouk7ex+pkylk2TRFAuenooMVFjkyQ="#]],
+    );
+}
+
+#[test]
+fn tape_progress_fastload() {
+    let mut tester = RustZXTester::new("tape_progress_fastload", presets::settings_48k_nosound());
+    assert_eq!(tester.emulator().tape_progress(), None);
+
+    tester.load_tap("simple_tape.tap.gz");
+    expect![[r#"Some(TapeProgress { block: 0, total_blocks: 4, block_bytes: 0, block_length: 0, fraction: 0.0 })"#]].assert_eq(&format!("{:?}", tester.emulator().tape_progress()));
+
+    tester.emulate_for(Duration::from_millis(100));
+    expect![[r#"Some(TapeProgress { block: 3, total_blocks: 4, block_bytes: 6914, block_length: 6914, fraction: 1.0 })"#]].assert_eq(&format!("{:?}", tester.emulator().tape_progress()));
+}
+
+#[test]
+fn tape_progress_playback() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    settings.autoload_enabled = false;
+
+    let mut tester = RustZXTester::new("tape_progress_playback", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulator().play_tape();
+
+    // Header block pilot and data
+    tester.emulate_for(Duration::from_millis(5000));
+    expect![[r#"Some(TapeProgress { block: 0, total_blocks: 4, block_bytes: 1, block_length: 19, fraction: 0.00042857142 })"#]].assert_eq(&format!("{:?}", tester.emulator().tape_progress()));
+
+    // Position only moves forward during the data block
+    let mut last = tester.emulator().tape_progress().unwrap();
+    for _ in 0..20 {
+        tester.emulate_for(Duration::from_millis(500));
+        let progress = tester.emulator().tape_progress().unwrap();
+        assert!(progress.fraction >= last.fraction);
+        last = progress;
+    }
+    expect![[r#"TapeProgress { block: 2, total_blocks: 4, block_bytes: 19, block_length: 19, fraction: 0.012 }"#]].assert_eq(&format!("{:?}", last));
+}
+
+#[test]
+fn tape_time_playback() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    settings.autoload_enabled = false;
+
+    let mut tester = RustZXTester::new("tape_time_playback", settings);
+    assert_eq!(tester.emulator().tape_time(), (0.0, 0.0));
+
+    tester.load_tap("simple_tape.tap.gz");
+    let (elapsed, total) = tester.emulator().tape_time();
+    assert_eq!(elapsed, 0.0);
+    expect![[r#"49.160065"#]].assert_eq(&total.to_string());
+
+    tester.emulator().play_tape();
+    tester.emulate_for(Duration::from_millis(3000));
+    let (elapsed, _) = tester.emulator().tape_time();
+    assert!((elapsed - 3.0).abs() < 0.05, "elapsed {}", elapsed);
+
+    // Counter does not move while the tape is stopped
+    tester.emulator().stop_tape();
+    tester.emulate_for(Duration::from_millis(1000));
+    assert_eq!(tester.emulator().tape_time().0, elapsed);
+}
+
+#[test]
+fn tape_time_fastload() {
+    let mut tester = RustZXTester::new("tape_time_fastload", presets::settings_48k_nosound());
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(100));
+    let (elapsed, total) = tester.emulator().tape_time();
+    assert_eq!(elapsed, total);
+}
+
+/// Loads built-in ROM with a byte changed in the unused area of 48 BASIC
+/// page, so it is not recognized and autoload snapshot can't be used
+fn load_patched_rom(tester: &mut RustZXTester) {
+    let mut pages = (0..)
+        .map_while(|page| tester.emulator().rom_page(page).map(<[u8]>::to_vec))
+        .collect::<Vec<_>>();
+    pages.last_mut().unwrap()[0x3CFF] ^= 0xFF;
+    tester.load_rom_pages(pages);
+    assert!(!tester.emulator().rom_info().is_known());
+}
+
+#[test]
+fn autoload_typed_48k() {
+    let mut tester = RustZXTester::new("autoload_typed_48k", presets::settings_48k_nosound());
+    load_patched_rom(&mut tester);
+    // Machine state before tape insertion does not matter
+    tester.emulate_for(Duration::from_millis(1234));
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(5000));
+    assert_eq!(tester.emulator().tape_progress().unwrap().fraction, 1.0);
+    tester.expect_screen(
+        "loaded",
+        expect![[r#"zDQzdQr19uTYaZouk7ex+pkylk2TRFAuenooMVFjkyQ="#]],
+    );
+}
+
+#[test]
+fn autoload_typed_48k_ntsc_random_ram() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.machine = ZXMachine::Sinclair48KNtsc;
+    settings.ram_init_seed = Some(42);
+    let mut tester = RustZXTester::new("autoload_typed_48k_ntsc", settings);
+    load_patched_rom(&mut tester);
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(5000));
+    assert_eq!(tester.emulator().tape_progress().unwrap().fraction, 1.0);
+}
+
+#[test]
+fn autoload_typed_128k() {
+    let mut tester = RustZXTester::new("autoload_typed_128k", presets::settings_128k_nosound());
+    load_patched_rom(&mut tester);
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(5000));
+    assert_eq!(tester.emulator().tape_progress().unwrap().fraction, 1.0);
+    tester.expect_screen(
+        "loaded",
+        expect![[r#"zDQzdQr19uTYaZouk7ex+pkylk2TRFAuenooMVFjkyQ="#]],
+    );
+}
+
+#[test]
+fn shutdown_stops_tape() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    settings.autoload_enabled = false;
+
+    let mut tester = RustZXTester::new("shutdown_stops_tape", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulator().play_tape();
+    tester.emulate_for(Duration::from_millis(3000));
+    tester.emulator().shutdown().expect("Shutdown failed");
+    let progress = tester
+        .emulator()
+        .tape_progress()
+        .expect("Tape is not inserted");
+    assert!(progress.fraction > 0.0);
+    tester.emulate_for(Duration::from_millis(1000));
+    assert_eq!(tester.emulator().tape_progress(), Some(progress));
+}
+
+#[test]
+fn truncated_tap_block() {
+    let mut tester = RustZXTester::new("truncated_tap_block", presets::settings_48k_nosound());
+    // Header block of 19 bytes, tape ends after its flag byte
+    tester
+        .load_tap_data(vec![0x13, 0x00, 0x00])
+        .expect("Failed to load tape");
+    let error = (0..100)
+        .find_map(|_| tester.emulator().emulate_frames(Duration::MAX).err())
+        .expect("Truncated block was not reported");
+    assert!(matches!(
+        error,
+        Error::TapeLoad(TapeLoadError::TruncatedBlock)
+    ));
+}
@@ -0,0 +1,156 @@
+use rustzx_core::host::{BufferCursor, Snapshot};
+use rustzx_test::{
+    framework::{presets, tap_block, RustZXTester},
+    sna::sna_128k,
+};
+use rustzx_utils::{
+    image::{DebugOverlay, RgbaImage, DISPLAY_FILE_SIZE},
+    palette::rgba::ORIGINAL as PALETTE,
+    thumbnail::thumbnail,
+};
+use std::{path::PathBuf, time::Duration};
+
+/// Writes `data` to the temporary file and returns its path
+fn temp_file(name: &str, data: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rustzx_thumbnail_{}", name));
+    std::fs::write(&path, data).expect("Failed to write temporary file");
+    path
+}
+
+/// Screen with black/white stripes on the left and bright blue paper
+fn test_display_file() -> Vec<u8> {
+    let mut data = vec![0u8; DISPLAY_FILE_SIZE];
+    for line in data[..6144].chunks_mut(32) {
+        line[0] = 0xAA;
+    }
+    data[6144..].fill(0x4F);
+    data
+}
+
+fn tap_header(kind: u8, name: &[u8; 10], length: u16, param1: u16) -> Vec<u8> {
+    let mut header = vec![kind];
+    header.extend_from_slice(name);
+    header.extend_from_slice(&length.to_le_bytes());
+    header.extend_from_slice(&param1.to_le_bytes());
+    header.extend_from_slice(&0x8000u16.to_le_bytes());
+    tap_block(0x00, &header)
+}
+
+#[test]
+fn thumbnail_from_scr() {
+    let path = temp_file("screen.scr", &test_display_file());
+    let image = thumbnail(&path).expect("No thumbnail");
+    assert_eq!((image.width, image.height), (256, 192));
+    // Bright white ink, bright blue paper
+    assert_eq!(image.pixel(0, 0), PALETTE[15]);
+    assert_eq!(image.pixel(1, 100), PALETTE[9]);
+    assert_eq!(image.pixel(200, 191), PALETTE[9]);
+
+    // Truncated screen
+    let path = temp_file("truncated.scr", &[0; 100]);
+    assert!(thumbnail(&path).is_none());
+}
+
+#[test]
+fn thumbnail_from_tap() {
+    let screen = test_display_file();
+    let mut tap = vec![];
+    // BASIC loader and non-screen code block come before the screen
+    tap.extend(tap_header(0, b"loader    ", 4, 10));
+    tap.extend(tap_block(0xFF, &[0, 10, 0, 0]));
+    tap.extend(tap_header(3, b"code      ", 16, 0x8000));
+    tap.extend(tap_block(0xFF, &[0; 16]));
+    let tap_without_screen = tap.clone();
+    tap.extend(tap_header(3, b"screen    ", 6912, 0x4000));
+    tap.extend(tap_block(0xFF, &screen));
+
+    let path = temp_file("game.tap", &tap);
+    let image = thumbnail(&path).expect("No thumbnail");
+    assert_eq!(
+        image.rgba,
+        RgbaImage::from_display_file(&screen).unwrap().rgba
+    );
+
+    // Tape without screen
+    let path = temp_file("no_screen.tap", &tap_without_screen);
+    assert!(thumbnail(&path).is_none());
+}
+
+#[test]
+fn thumbnail_from_gzipped_sna() {
+    let mut tester = RustZXTester::new(
+        "thumbnail_from_gzipped_sna",
+        presets::settings_48k_nosound(),
+    );
+    tester.load_sna("keyboard.48k.sna.gz");
+    let screen = (0..DISPLAY_FILE_SIZE as u16)
+        .map(|offset| tester.peek(0x4000 + offset))
+        .collect::<Vec<_>>();
+
+    let image = thumbnail("test_data/keyboard.48k.sna.gz").expect("No thumbnail");
+    assert_eq!(
+        image.rgba,
+        RgbaImage::from_display_file(&screen).unwrap().rgba
+    );
+}
+
+#[test]
+fn thumbnail_from_128k_sna_shadow_screen() {
+    // Bank 0 is paged, screen is switched to bank 7
+    let mut sna = sna_128k(0x18, &[0xF3, 0x76]);
+    // Bank 7 is the last of the tail banks 1, 3, 4, 6, 7
+    let bank_7 = sna.len() - 0x4000;
+    sna[bank_7..bank_7 + DISPLAY_FILE_SIZE].copy_from_slice(&test_display_file());
+    let path = temp_file("shadow.128k.sna", &sna);
+    let image = thumbnail(&path).expect("No thumbnail");
+
+    // Thumbnail matches the screen rendered by emulator
+    let mut tester = RustZXTester::new(
+        "thumbnail_from_128k_sna_shadow_screen",
+        presets::settings_128k_nosound(),
+    );
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna)))
+        .expect("Failed to load snapshot");
+    tester.emulate_for(Duration::from_millis(100));
+    assert_eq!(image.rgba, tester.screen_image().rgba);
+}
+
+#[test]
+fn thumbnail_of_unsupported_file() {
+    let path = temp_file("screen.bin", &test_display_file());
+    assert!(thumbnail(&path).is_none());
+    assert!(thumbnail("test_data/missing.sna").is_none());
+}
+
+#[test]
+fn flash_cells_overlay() {
+    let mut screen = test_display_file();
+    // Flashing cell at row 1, column 2
+    screen[6144 + 32 + 2] |= 0x80;
+    let normal = RgbaImage::from_display_file(&screen).unwrap();
+    assert_eq!(
+        RgbaImage::from_display_file_with_overlay(&screen, DebugOverlay::None)
+            .unwrap()
+            .rgba,
+        normal.rgba
+    );
+
+    let overlay =
+        RgbaImage::from_display_file_with_overlay(&screen, DebugOverlay::FlashCells).unwrap();
+    for y in 0..192 {
+        for x in 0..256 {
+            let flashing = (8..16).contains(&y) && (16..24).contains(&x);
+            assert_eq!(
+                overlay.pixel(x, y) != normal.pixel(x, y),
+                flashing,
+                "pixel ({}, {})",
+                x,
+                y
+            );
+        }
+    }
+    // Bright blue paper is inverted to bright yellow
+    assert_eq!(overlay.pixel(16, 8), [0xFF, 0xFF, 0x00, 0xFF]);
+}
@@ -0,0 +1,205 @@
+use expect_test::expect;
+use rustzx_core::{
+    host::{BufferCursor, Snapshot},
+    zx::machine::Timings,
+    RustzxSettings,
+};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    sna::{sna_128k, sna_48k},
+};
+
+const SAMPLES: u16 = 200;
+
+// Screen memory is zeroed by the snapshot, so floating bus reads either 0x00
+// (ULA fetches screen data) or 0xFF (idle bus)
+//       EI; HALT                           ; sync with the frame start
+//       LD D, 3
+// l1:   LD B, 0
+// l2:   DJNZ l2
+//       DEC D; JR NZ, l1                   ; wait for the screen area
+//       LD HL, 0x9000; LD B, 200
+// s:    IN A, (0xFF); LD (HL), A; INC HL
+//       DJNZ s                             ; sample floating bus
+//       DI; HALT
+const FLOATING_BUS_CODE: [u8; 24] = [
+    0xFB, 0x76, 0x16, 0x03, 0x06, 0x00, 0x10, 0xFE, 0x15, 0x20, 0xF9, 0x21, 0x00, 0x90, 0x06, 0xC8,
+    0xDB, 0xFF, 0x77, 0x23, 0x10, 0xFA, 0xF3, 0x76,
+];
+
+fn sample_floating_bus(timings: Timings) -> String {
+    let mut settings = presets::settings_48k_nosound();
+    settings.timings = timings;
+    let mut tester = RustZXTester::new("floating_bus_timings", settings);
+    tester.load_code(&FLOATING_BUS_CODE);
+    tester.emulate_for(std::time::Duration::from_millis(100));
+
+    (0..SAMPLES)
+        .map(|offset| {
+            if tester.peek(0x9000 + offset) == 0xFF {
+                '.'
+            } else {
+                'x'
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn early_and_late_timings_differ_by_one_clock() {
+    let late = sample_floating_bus(Timings::Late);
+    let early = sample_floating_bus(Timings::Early);
+    assert_ne!(late, early);
+    expect![[r#"...........................................................................................xx....x....x.....x.x....xx....x.x..x..x..x.x....xx....x.x..x..x..x.x....xx....x.x.....x....x....xx....x.x...."#]].assert_eq(&late);
+    expect![[r#"............................................................................................x....x....xx....x.x.....x....x....xx.x..x.xx....x....x....xx.x..x.xx....x....x.....x.x....xx....x....x.....x"#]].assert_eq(&early);
+}
+
+// Same as FLOATING_BUS_CODE, but screen memory is filled with 0x11 bitmap
+// and 0x22 attributes first, and the floating bus is also sampled right
+// after the interrupt, in the top border
+//       LD HL, 0x4000; LD DE, 0x4001; LD BC, 0x17FF; LD (HL), 0x11; LDIR
+//       LD HL, 0x5800; LD DE, 0x5801; LD BC, 0x02FF; LD (HL), 0x22; LDIR
+//       EI; HALT
+//       IN A, (0xFF); LD (0x8FFF), A
+//       ...
+const FLOATING_BUS_SCREEN_CODE: [u8; 55] = [
+    0x21, 0x00, 0x40, 0x11, 0x01, 0x40, 0x01, 0xFF, 0x17, 0x36, 0x11, 0xED, 0xB0, 0x21, 0x00, 0x58,
+    0x11, 0x01, 0x58, 0x01, 0xFF, 0x02, 0x36, 0x22, 0xED, 0xB0, 0xFB, 0x76, 0xDB, 0xFF, 0x32, 0xFF,
+    0x8F, 0x16, 0x03, 0x06, 0x00, 0x10, 0xFE, 0x15, 0x20, 0xF9, 0x21, 0x00, 0x90, 0x06, 0xC8, 0xDB,
+    0xFF, 0x77, 0x23, 0x10, 0xFA, 0xF3, 0x76,
+];
+
+#[test]
+fn floating_bus_reads_screen_bytes_only_in_display_area() {
+    let mut tester =
+        RustZXTester::with_code("floating_bus_screen_bytes", &FLOATING_BUS_SCREEN_CODE);
+    tester.emulate_for(std::time::Duration::from_millis(100));
+
+    assert_eq!(tester.peek(0x8FFF), 0xFF);
+    let samples = (0..SAMPLES)
+        .map(|offset| tester.peek(0x9000 + offset))
+        .collect::<Vec<_>>();
+    assert!(samples
+        .iter()
+        .all(|value| [0xFF, 0x11, 0x22].contains(value)));
+    assert!(samples.contains(&0x11));
+    assert!(samples.contains(&0x22));
+    // Sampling starts in the top border
+    assert_eq!(samples[0], 0xFF);
+}
+
+// Same as FLOATING_BUS_CODE, but shadow screen in bank 7 (paged at 0xC000
+// and displayed) is filled with 0x33 first, while bank 5 at 0x4000 stays
+// zeroed
+//       LD HL, 0xC000; LD DE, 0xC001; LD BC, 0x1AFF; LD (HL), 0x33; LDIR
+//       ...
+const FLOATING_BUS_SHADOW_SCREEN_CODE: [u8; 37] = [
+    0x21, 0x00, 0xC0, 0x11, 0x01, 0xC0, 0x01, 0xFF, 0x1A, 0x36, 0x33, 0xED, 0xB0, 0xFB, 0x76, 0x16,
+    0x03, 0x06, 0x00, 0x10, 0xFE, 0x15, 0x20, 0xF9, 0x21, 0x00, 0x90, 0x06, 0xC8, 0xDB, 0xFF, 0x77,
+    0x23, 0x10, 0xFA, 0xF3, 0x76,
+];
+
+#[test]
+fn floating_bus_reads_displayed_screen_bank() {
+    let mut tester = RustZXTester::new(
+        "floating_bus_displayed_bank",
+        presets::settings_128k_nosound(),
+    );
+    // Bank 7 paged and displayed, 48 BASIC ROM
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna_128k(
+            0x1F,
+            &FLOATING_BUS_SHADOW_SCREEN_CODE,
+        ))))
+        .expect("Failed to load snapshot");
+    tester.emulate_for(std::time::Duration::from_millis(100));
+
+    let samples = (0..SAMPLES)
+        .map(|offset| tester.peek(0x9000 + offset))
+        .collect::<Vec<_>>();
+    assert!(samples.iter().all(|value| [0xFF, 0x33].contains(value)));
+    assert!(samples.contains(&0x33));
+}
+
+/// Returns clocks passed until the end of the first instruction delayed by
+/// contention. CPU executes NOPs from the contended memory at 0x7000 with
+/// interrupts disabled
+fn first_contended_fetch(contention_offset: i32) -> u64 {
+    let mut sna = sna_48k(0xFEFE, &[0xC3, 0x00, 0x70]);
+    // Skip SNA header, RAM starts at 0x4000
+    let nops = 27 + 0x3000..27 + 0x4000;
+    sna[nops].fill(0x00);
+    let mut tester = RustZXTester::new("contention_offset", presets::settings_48k_nosound());
+    let emulator = tester.emulator();
+    emulator
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna)))
+        .expect("Failed to load snapshot");
+    emulator.set_contention_offset(contention_offset).unwrap();
+
+    // JP 0x7000
+    let mut clocks = emulator.step_instructions(1).unwrap();
+    loop {
+        let nop_clocks = emulator.step_instructions(1).unwrap();
+        clocks += nop_clocks;
+        if nop_clocks > 4 {
+            return clocks;
+        }
+    }
+}
+
+#[test]
+fn contention_offset_shifts_contention_window() {
+    let base = first_contended_fetch(0);
+    expect![[r#"14345"#]].assert_eq(&base.to_string());
+    assert_eq!(first_contended_fetch(4), base + 4);
+    assert_eq!(first_contended_fetch(-8), base - 8);
+}
+
+/// Returns true if the frame interrupt is accepted when `nops` NOPs are
+/// executed before `EI; NOP` at the beginning of the frame. The interrupt is
+/// checked after the instruction following `EI`, i.e. `4 * nops + 8` clocks
+/// after the interrupt pulse start
+fn interrupt_caught(settings: RustzxSettings, nops: usize) -> bool {
+    // NOP x `nops`; EI; NOP; JR $
+    let mut code = vec![0x00; nops];
+    code.extend_from_slice(&[0xFB, 0x00, 0x18, 0xFE]);
+    let sna = if settings.machine.is_48k() {
+        sna_48k(0xFEFE, &code)
+    } else {
+        // 48K BASIC ROM
+        sna_128k(0x10, &code)
+    };
+    let mut tester = RustZXTester::new("interrupt_length", settings);
+    let emulator = tester.emulator();
+    emulator
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna)))
+        .expect("Failed to load snapshot");
+    emulator.step_instructions(nops as u64 + 2).unwrap();
+    // Either `JR $` or interrupt acceptance with `PUSH AF` at 0x0038
+    match emulator.step_instructions(1).unwrap() {
+        12 => false,
+        24 => true,
+        clocks => panic!("Unexpected instruction clocks: {}", clocks),
+    }
+}
+
+#[test]
+fn interrupt_accepted_only_during_pulse() {
+    // 32 clocks long pulse: last check at 28 clocks, first miss at 32 clocks
+    assert!(interrupt_caught(presets::settings_48k_nosound(), 5));
+    assert!(!interrupt_caught(presets::settings_48k_nosound(), 6));
+    // 36 clocks long pulse
+    assert!(interrupt_caught(presets::settings_128k_nosound(), 6));
+    assert!(!interrupt_caught(presets::settings_128k_nosound(), 7));
+}
+
+#[test]
+fn interrupt_length_override() {
+    let settings = || RustzxSettings {
+        interrupt_length: Some(36),
+        ..presets::settings_48k_nosound()
+    };
+    assert!(interrupt_caught(settings(), 6));
+    assert!(!interrupt_caught(settings(), 7));
+}
@@ -0,0 +1,94 @@
+use rustzx_core::{host::BufferCursor, trap::TrapAction};
+use rustzx_test::framework::RustZXTester;
+use std::{cell::Cell, rc::Rc};
+
+const ROUTINE_ADDR: u16 = 0x8010;
+
+// 0x8000: CALL 0x8010
+// 0x8003: LD (0x9000), A
+// 0x8006: JR $
+// 0x8010: LD A, 0x01
+// 0x8012: RET
+const CALL_CODE: [u8; 19] = [
+    0xCD, 0x10, 0x80, 0x32, 0x00, 0x90, 0x18, 0xFE, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x3E, 0x01, 0xC9,
+];
+
+fn trap_tester(name: &str) -> RustZXTester {
+    RustZXTester::with_code(name, &CALL_CODE)
+}
+
+#[test]
+fn trap_replaces_routine() {
+    let mut tester = trap_tester("trap_replaces_routine");
+    let calls = Rc::new(Cell::new(0));
+    let trap_calls = calls.clone();
+    tester.emulator().set_trap(
+        ROUTINE_ADDR,
+        Box::new(move |registers, _| {
+            assert_eq!(registers.pc, ROUTINE_ADDR);
+            trap_calls.set(trap_calls.get() + 1);
+            TrapAction::ReplaceWithValueInA(0x42)
+        }),
+    );
+    tester.emulate_frame();
+    assert_eq!(tester.peek(0x9000), 0x42);
+    assert_eq!(calls.get(), 1);
+    assert_eq!(tester.emulator().registers().sp, 0xFF00);
+}
+
+#[test]
+fn trap_patches_state() {
+    let mut tester = trap_tester("trap_patches_state");
+    tester.emulator().set_trap(
+        ROUTINE_ADDR,
+        Box::new(|registers, memory| {
+            // Operand of `LD A, n`
+            memory.write(ROUTINE_ADDR + 1, 0x07);
+            registers.bc = 0x1234;
+            TrapAction::Continue
+        }),
+    );
+    tester.emulate_frame();
+    assert_eq!(tester.peek(0x9000), 0x07);
+    assert_eq!(tester.emulator().registers().bc, 0x1234);
+}
+
+#[test]
+fn removed_trap_is_not_called() {
+    let mut tester = trap_tester("removed_trap_is_not_called");
+    tester
+        .emulator()
+        .set_trap(ROUTINE_ADDR, Box::new(|_, _| TrapAction::Skip));
+    assert!(tester.emulator().remove_trap(ROUTINE_ADDR));
+    assert!(!tester.emulator().remove_trap(ROUTINE_ADDR));
+    tester.emulate_frame();
+    assert_eq!(tester.peek(0x9000), 0x01);
+}
+
+#[test]
+fn trap_memory_sees_paged_in_interface() {
+    // 0x8000: IN A, (0xE7)  ; page +D in
+    // 0x8002: CALL 0x8010
+    // 0x8005: JR $
+    let mut code = CALL_CODE;
+    code[..7].copy_from_slice(&[0xDB, 0xE7, 0xCD, 0x10, 0x80, 0x18, 0xFE]);
+    let mut tester = RustZXTester::with_code("trap_memory_sees_paged_in_interface", &code);
+    tester
+        .emulator()
+        .attach_plusd(BufferCursor::new(vec![0xAB; 0x2000]))
+        .expect("Failed to attach +D");
+    let read = Rc::new(Cell::new((0, 0)));
+    let trap_read = read.clone();
+    tester.emulator().set_trap(
+        ROUTINE_ADDR,
+        Box::new(move |_, memory| {
+            // +D ROM and RAM
+            memory.write(0x3000, 0x5A);
+            trap_read.set((memory.read(0x0000), memory.read(0x3000)));
+            TrapAction::Continue
+        }),
+    );
+    tester.emulate_frame();
+    assert_eq!(read.get(), (0xAB, 0x5A));
+}
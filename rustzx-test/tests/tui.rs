@@ -0,0 +1,73 @@
+use expect_test::expect;
+use rustzx_test::framework::{presets, RustZXTester};
+use rustzx_utils::tui::AnsiMode;
+use std::time::Duration;
+
+const RESET: &str = "\x1b[0m";
+
+fn boot_48k(name: &str) -> RustZXTester {
+    let mut tester = RustZXTester::new(name, presets::settings_48k_nosound());
+    tester.emulate_for(Duration::from_millis(2000));
+    tester
+}
+
+#[test]
+fn ansi_half_blocks_cover_whole_screen() {
+    let tester = boot_48k("ansi_half_blocks_cover_whole_screen");
+    let text = tester.screen_ansi(AnsiMode::HalfBlocks);
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 96);
+    for line in &lines {
+        assert!(line.ends_with(RESET));
+        assert_eq!(line.matches('▀').count(), 256);
+    }
+    // White paper without text, colors are set only once per line
+    let white = "\x1b[38;2;205;205;205m\x1b[48;2;205;205;205m";
+    assert_eq!(lines[0], format!("{}{}{}", white, "▀".repeat(256), RESET));
+    tester.expect_text(
+        "half_blocks",
+        text,
+        expect![[r#"076mP1hTiFWn9sU/8usn+s4N34fDGRBhXB1EwMtoaaQ="#]],
+    );
+}
+
+#[test]
+fn ansi_compact_uses_standard_colors() {
+    let tester = boot_48k("ansi_compact_uses_standard_colors");
+    let text = tester.screen_ansi(AnsiMode::Compact);
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 48);
+    assert!(lines.iter().all(|line| line.matches('▀').count() == 128));
+    assert_eq!(
+        lines[0],
+        format!("\x1b[37m\x1b[47m{}{}", "▀".repeat(128), RESET)
+    );
+    // Copyright message at the bottom line is black on white
+    assert!(lines[46].contains("\x1b[30m"));
+    assert!(!text.contains("\x1b[38;2"));
+    tester.expect_text(
+        "compact",
+        text,
+        expect![[r#"qqjX3/mCZNkj+2bg/FabcFBLbCV+/Op/6KwvMloBkpA="#]],
+    );
+}
+
+#[test]
+fn ascii_art_of_boot_screen() {
+    let mut tester = boot_48k("ascii_art_of_boot_screen");
+    let text = tester.emulator().to_ascii();
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 48);
+    assert!(lines.iter().all(|line| line.len() == 64));
+    // Only the copyright message is printed on white paper
+    assert!(lines[..46].iter().all(|line| line.trim().is_empty()));
+    let copyright = lines[46..]
+        .iter()
+        .map(|line| line.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n");
+    expect![[r#"
+        =-  .:::--::  -:: :...: ..: ..  -::.:.:.......-.  : :..:
+        =-  .-:-::--  :::.:::...--:.:   -:-:-:-:--: :.::  -:..::"#]]
+    .assert_eq(&copyright);
+}
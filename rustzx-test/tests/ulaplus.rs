@@ -0,0 +1,84 @@
+use rustzx_core::{zx::video::ulaplus::grb_to_rgb, RustzxSettings};
+use rustzx_test::framework::{presets, RustZXTester};
+use rustzx_utils::palette::rgba::ORIGINAL;
+
+/// GRB 3:3:2 colors
+const RED: u8 = 0x1C;
+const BLUE: u8 = 0x03;
+
+//       DI
+//       LD HL, 0x5800
+//       LD DE, 0x5801
+//       LD BC, 767
+//       LD (HL), 0
+//       LDIR           ; paper 0 of CLUT 0 (palette entry 8) everywhere
+//       LD BC, 0xBF3B
+//       LD A, 0x40
+//       OUT (C), A
+//       LD B, 0xFF
+//       LD A, 1
+//       OUT (C), A     ; palette mode on
+//       EI
+// loop: HALT
+//       LD B, 0xBF
+//       LD A, 8
+//       OUT (C), A
+//       LD B, 0xFF
+//       LD A, RED
+//       OUT (C), A     ; entry 8 = red at the frame start
+//       LD HL, 1300
+// wait: DEC HL
+//       LD A, H
+//       OR L
+//       JR NZ, wait    ; wait until the middle of the screen
+//       LD A, BLUE
+//       OUT (C), A     ; entry 8 = blue
+//       JR loop
+const PALETTE_SPLIT_CODE: [u8; 55] = [
+    0xF3, 0x21, 0x00, 0x58, 0x11, 0x01, 0x58, 0x01, 0xFF, 0x02, 0x36, 0x00, 0xED, 0xB0, 0x01, 0x3B,
+    0xBF, 0x3E, 0x40, 0xED, 0x79, 0x06, 0xFF, 0x3E, 0x01, 0xED, 0x79, 0xFB, 0x76, 0x06, 0xBF, 0x3E,
+    0x08, 0xED, 0x79, 0x06, 0xFF, 0x3E, RED, 0xED, 0x79, 0x21, 0x14, 0x05, 0x2B, 0x7C, 0xB5, 0x20,
+    0xFB, 0x3E, BLUE, 0xED, 0x79, 0x18, 0xE5,
+];
+
+#[test]
+fn ulaplus_palette_split_mid_screen() {
+    let settings = RustzxSettings {
+        ulaplus_enabled: true,
+        ..presets::settings_48k_nosound()
+    };
+    let mut tester = RustZXTester::new("ulaplus_palette_split_mid_screen", settings);
+    tester.load_code(&PALETTE_SPLIT_CODE);
+    tester.emulate_frame();
+    tester.emulate_frame();
+    tester.emulate_frame();
+
+    let palette = tester.emulator().ulaplus_palette().unwrap();
+    assert_eq!(palette[8], BLUE);
+    // Test frame buffer has standard colors only, so the closest ones are
+    // used (bright red and bright blue)
+    let image = tester.screen_image();
+    for line in [0, 40, 80] {
+        assert_eq!(image.pixel(128, line), ORIGINAL[8 + 2], "line {}", line);
+    }
+    for line in [120, 160, 191] {
+        assert_eq!(image.pixel(128, line), ORIGINAL[8 + 1], "line {}", line);
+    }
+}
+
+#[test]
+fn ulaplus_disabled() {
+    let settings = presets::settings_48k_nosound();
+    let mut tester = RustZXTester::new("ulaplus_disabled", settings);
+    assert!(tester.emulator().ulaplus_palette().is_none());
+}
+
+#[test]
+fn ulaplus_grb_to_rgb() {
+    assert_eq!(grb_to_rgb(0x00), [0x00, 0x00, 0x00]);
+    assert_eq!(grb_to_rgb(0xFF), [0xFF, 0xFF, 0xFF]);
+    assert_eq!(grb_to_rgb(RED), [0xFF, 0x00, 0x00]);
+    assert_eq!(grb_to_rgb(BLUE), [0x00, 0x00, 0xFF]);
+    // Low blue bit is the OR of the other two
+    assert_eq!(grb_to_rgb(0x01), [0x00, 0x00, 0x6D]);
+}
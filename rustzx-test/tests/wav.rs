@@ -0,0 +1,98 @@
+use rustzx_test::framework::{presets, RustZXTester};
+use rustzx_utils::wav::{WavSink, WAV_HEADER_SIZE};
+use std::{io::Cursor, time::Duration};
+
+const SAMPLE_RATE: u32 = 44100;
+const CAPTURE_TIME: Duration = Duration::from_secs(5);
+/// Allowed difference between mixed down and reference samples, in PCM units
+const TOLERANCE: i32 = 1;
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// Checks WAV header and returns PCM samples
+fn parse_wav(data: &[u8], channels: u16) -> Vec<i16> {
+    assert_eq!(&data[0..4], b"RIFF");
+    assert_eq!(read_u32(data, 4) as usize, data.len() - 8);
+    assert_eq!(&data[8..16], b"WAVEfmt ");
+    assert_eq!(read_u16(data, 22), channels);
+    assert_eq!(read_u32(data, 24), SAMPLE_RATE);
+    assert_eq!(read_u16(data, 34), 16);
+    assert_eq!(&data[36..40], b"data");
+    assert_eq!(read_u32(data, 40) as usize, data.len() - WAV_HEADER_SIZE);
+    data[WAV_HEADER_SIZE..]
+        .chunks_exact(2)
+        .map(|pcm| i16::from_le_bytes([pcm[0], pcm[1]]))
+        .collect()
+}
+
+/// Records AY tune to stereo and mono WAV streams at once
+fn record_tune() -> (Vec<u8>, Vec<u8>) {
+    let mut t = RustZXTester::new("wav_record", presets::settings_128k());
+    t.load_sna("sound.128k.sna.gz");
+    let mut stereo = WavSink::from_writer(Cursor::new(vec![]), SAMPLE_RATE, 2).unwrap();
+    let mut mono = WavSink::from_writer(Cursor::new(vec![]), SAMPLE_RATE, 1).unwrap();
+    let frames = CAPTURE_TIME.as_secs() * 50;
+    for _ in 0..frames {
+        t.emulate_frame();
+        while let Some(sample) = t.emulator().next_audio_sample() {
+            stereo.write_sample(sample).unwrap();
+            mono.write_sample(sample).unwrap();
+        }
+    }
+    assert_eq!(stereo.samples_written(), mono.samples_written());
+    let expected_samples = SAMPLE_RATE as u64 * CAPTURE_TIME.as_secs();
+    assert!(stereo.samples_written().abs_diff(expected_samples) < SAMPLE_RATE as u64 / 10);
+    (
+        stereo.finish().unwrap().into_inner(),
+        mono.finish().unwrap().into_inner(),
+    )
+}
+
+#[test]
+fn mono_is_mixed_down_stereo() {
+    let (stereo, mono) = record_tune();
+    let stereo = parse_wav(&stereo, 2);
+    let mono = parse_wav(&mono, 1);
+    assert_eq!(stereo.len(), mono.len() * 2);
+    assert!(mono.iter().any(|&sample| sample != 0));
+    for (pair, &mixed) in stereo.chunks_exact(2).zip(&mono) {
+        let reference = (pair[0] as i32 + pair[1] as i32) / 2;
+        assert!(
+            (reference - mixed as i32).abs() <= TOLERANCE,
+            "{:?} mixed to {}",
+            pair,
+            mixed
+        );
+    }
+}
+
+#[test]
+fn header_finalized_on_drop() {
+    let path = std::env::temp_dir().join(format!("rustzx_wav_{}.wav", std::process::id()));
+    {
+        let mut t = RustZXTester::new("wav_drop", presets::settings_48k());
+        t.load_sna("sound.48k.sna.gz");
+        t.emulate_frame();
+        let mut sink = WavSink::new(&path, SAMPLE_RATE, 2).unwrap();
+        while let Some(sample) = t.emulator().next_audio_sample() {
+            sink.write_sample(sample).unwrap();
+        }
+        assert!(sink.samples_written() > 0);
+        // dropped without `finish`, e.g. when frontend is closed
+    }
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(parse_wav(&data, 2).len() > WAV_HEADER_SIZE);
+}
+
+#[test]
+fn unsupported_channel_count() {
+    assert!(WavSink::from_writer(Cursor::new(vec![]), SAMPLE_RATE, 0).is_err());
+    assert!(WavSink::from_writer(Cursor::new(vec![]), SAMPLE_RATE, 6).is_err());
+}
@@ -24,7 +24,7 @@ fn search_pattern(
 
     for mem_offset in min_search_range..max_search_range {
         let mut found = true;
-        for (pattern_offset, expected) in pattern.into_iter().copied().enumerate() {
+        for (pattern_offset, expected) in pattern.iter().copied().enumerate() {
             if t.peek(mem_offset + pattern_offset as u16) != expected {
                 found = false;
                 break;
@@ -0,0 +1,237 @@
+use flate2::{write::DeflateEncoder, Compression, Crc};
+use rustzx_core::host::Snapshot;
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    sna::sna_48k,
+};
+use rustzx_utils::io::{
+    archive_entries, load_archive_entry, ArchiveEntry, ArchiveEntryKind, ZipArchive, ZipError,
+};
+use std::{
+    io::{Cursor, Write},
+    path::PathBuf,
+};
+
+struct ZipEntry<'a> {
+    name: &'a str,
+    data: &'a [u8],
+    deflate: bool,
+    flags: u16,
+}
+
+impl<'a> ZipEntry<'a> {
+    fn stored(name: &'a str, data: &'a [u8]) -> Self {
+        Self {
+            name,
+            data,
+            deflate: false,
+            flags: 0,
+        }
+    }
+
+    fn deflated(name: &'a str, data: &'a [u8]) -> Self {
+        Self {
+            deflate: true,
+            ..Self::stored(name, data)
+        }
+    }
+}
+
+/// Builds ZIP archive in memory
+fn zip_archive(entries: &[ZipEntry]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut central_dir = vec![];
+    for entry in entries {
+        let mut crc = Crc::new();
+        crc.update(entry.data);
+        let (method, packed) = if entry.deflate {
+            let mut encoder = DeflateEncoder::new(vec![], Compression::best());
+            encoder.write_all(entry.data).unwrap();
+            (8u16, encoder.finish().unwrap())
+        } else {
+            (0u16, entry.data.to_vec())
+        };
+        // Fields common to local and central headers, starting from flags
+        let mut common = vec![];
+        common.extend(entry.flags.to_le_bytes());
+        common.extend(method.to_le_bytes());
+        common.extend([0; 4]);
+        common.extend(crc.sum().to_le_bytes());
+        common.extend((packed.len() as u32).to_le_bytes());
+        common.extend((entry.data.len() as u32).to_le_bytes());
+        common.extend((entry.name.len() as u16).to_le_bytes());
+        common.extend([0; 2]);
+
+        central_dir.extend(0x02014B50u32.to_le_bytes());
+        central_dir.extend([20, 0, 20, 0]);
+        central_dir.extend(&common);
+        central_dir.extend([0; 10]);
+        central_dir.extend((out.len() as u32).to_le_bytes());
+        central_dir.extend(entry.name.as_bytes());
+
+        out.extend(0x04034B50u32.to_le_bytes());
+        out.extend([20, 0]);
+        out.extend(&common);
+        out.extend(entry.name.as_bytes());
+        out.extend(packed);
+    }
+    let central_dir_offset = out.len() as u32;
+    out.extend(&central_dir);
+    out.extend(0x06054B50u32.to_le_bytes());
+    out.extend([0; 4]);
+    out.extend((entries.len() as u16).to_le_bytes());
+    out.extend((entries.len() as u16).to_le_bytes());
+    out.extend((central_dir.len() as u32).to_le_bytes());
+    out.extend(central_dir_offset.to_le_bytes());
+    out.extend([0; 2]);
+    out
+}
+
+/// Writes `data` to the temporary file and returns its path
+fn temp_file(name: &str, data: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rustzx_zip_{}", name));
+    std::fs::write(&path, data).expect("Failed to write temporary file");
+    path
+}
+
+#[test]
+fn archive_entries_lists_recognized_files() {
+    let readme = b"Side A first".repeat(10);
+    let path = temp_file(
+        "entries.zip",
+        &zip_archive(&[
+            ZipEntry::stored("README.TXT", &readme),
+            ZipEntry::stored("game/", &[]),
+            ZipEntry::deflated("game/Side A.tap", &[0xAA; 300]),
+            ZipEntry::deflated("game/Side B.TAP", &[0xBB; 200]),
+            ZipEntry::stored("game/loading.scr", &[0; 6912]),
+            ZipEntry::stored("extras.zip", &[0; 22]),
+            ZipEntry::stored("game.sna", &[0; 49179]),
+            ZipEntry::stored("game.dck", &[0; 9]),
+            ZipEntry::stored("disk.img", &[0; 16]),
+            ZipEntry::stored("disk.mgt", &[0; 16]),
+        ]),
+    );
+    let entries = archive_entries(&path).expect("Failed to read archive");
+    let entry = |name: &str, size, kind| ArchiveEntry {
+        name: name.to_string(),
+        size,
+        kind,
+    };
+    assert_eq!(
+        entries,
+        [
+            entry("game/Side A.tap", 300, ArchiveEntryKind::Tape),
+            entry("game/Side B.TAP", 200, ArchiveEntryKind::Tape),
+            entry("game/loading.scr", 6912, ArchiveEntryKind::Screen),
+            entry("game.sna", 49179, ArchiveEntryKind::Snapshot),
+            entry("game.dck", 9, ArchiveEntryKind::Cartridge),
+            entry("disk.mgt", 16, ArchiveEntryKind::Disk),
+        ]
+    );
+}
+
+#[test]
+fn load_chosen_archive_entry() {
+    let side_a = (0..=255).cycle().take(1000).collect::<Vec<u8>>();
+    let side_b = b"side B".repeat(50);
+    let path = temp_file(
+        "load.zip",
+        &zip_archive(&[
+            ZipEntry::stored("side_a.tap", &side_a),
+            ZipEntry::deflated("side_b.tap", &side_b),
+        ]),
+    );
+    let load = |name| load_archive_entry(&path, name).map(|asset| asset.into_vec());
+    assert_eq!(load("side_a.tap").unwrap(), side_a);
+    assert_eq!(load("side_b.tap").unwrap(), side_b);
+    assert!(matches!(
+        load("side_c.tap"),
+        Err(ZipError::EntryNotFound(name)) if name == "side_c.tap"
+    ));
+
+    let mut archive = ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let first = archive.load_first(Some(ArchiveEntryKind::Tape)).unwrap();
+    assert_eq!(first.into_vec(), side_a);
+    assert!(matches!(
+        archive.load_first(Some(ArchiveEntryKind::Snapshot)),
+        Err(ZipError::InvalidArchive(_))
+    ));
+}
+
+#[test]
+fn loaded_archive_entry_is_usable_as_asset() {
+    // LD A, 0x5A; LD (0x9000), A; JR $
+    let sna = sna_48k(0xFEFE, &[0x3E, 0x5A, 0x32, 0x00, 0x90, 0x18, 0xFE]);
+    let archive = zip_archive(&[ZipEntry::deflated("game.sna", &sna)]);
+    let asset = ZipArchive::new(Cursor::new(archive))
+        .unwrap()
+        .load("game.sna")
+        .unwrap();
+    let mut tester = RustZXTester::new(
+        "loaded_archive_entry_is_usable_as_asset",
+        presets::settings_48k_nosound(),
+    );
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(asset))
+        .expect("Failed to load snapshot");
+    tester.emulate_frame();
+    assert_eq!(tester.peek(0x9000), 0x5A);
+}
+
+#[test]
+fn encrypted_entry_fails_to_load() {
+    let archive = zip_archive(&[ZipEntry {
+        flags: 0x0001,
+        ..ZipEntry::stored("secret.tap", &[0; 16])
+    }]);
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    assert_eq!(archive.entries().len(), 1);
+    let error = archive.load("secret.tap").err().unwrap();
+    assert!(matches!(error, ZipError::Encrypted(ref name) if name == "secret.tap"));
+    assert_eq!(
+        error.to_string(),
+        "archive entry 'secret.tap' is password-protected"
+    );
+}
+
+#[test]
+fn zip64_archive_is_rejected() {
+    let mut archive = zip_archive(&[ZipEntry::stored("game.tap", &[0; 16])]);
+    // Central directory offset is moved to ZIP64 end of central directory
+    let len = archive.len();
+    archive[len - 6..len - 2].fill(0xFF);
+    let error = ZipArchive::new(Cursor::new(archive)).err().unwrap();
+    assert!(matches!(error, ZipError::Zip64));
+    assert_eq!(error.to_string(), "ZIP64 archives are not supported");
+}
+
+#[test]
+fn damaged_archives_are_rejected() {
+    assert!(matches!(
+        ZipArchive::new(Cursor::new(vec![0; 100])).err().unwrap(),
+        ZipError::InvalidArchive(_)
+    ));
+
+    let mut archive = zip_archive(&[ZipEntry::stored("game.tap", b"tape data")]);
+    // Corrupt stored data
+    archive[30 + "game.tap".len()] ^= 0xFF;
+    let result = ZipArchive::new(Cursor::new(archive))
+        .unwrap()
+        .load("game.tap");
+    assert!(matches!(result, Err(ZipError::ChecksumMismatch(_))));
+}
+
+#[test]
+fn oversized_entry_is_rejected_before_unpacking() {
+    let mut archive = zip_archive(&[ZipEntry::stored("game.tap", &[0; 16])]);
+    // Unpacked size in the central directory header
+    let central_dir_offset = 30 + "game.tap".len() + 16;
+    let size_offset = central_dir_offset + 24;
+    archive[size_offset..size_offset + 4].copy_from_slice(&0x7FFF_FFFFu32.to_le_bytes());
+    let result = ZipArchive::new(Cursor::new(archive))
+        .unwrap()
+        .load("game.tap");
+    assert!(matches!(result, Err(ZipError::EntryTooLarge(_))));
+}
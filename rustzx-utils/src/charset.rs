@@ -0,0 +1,67 @@
+//! Rendering of the memory contents as 8x8 1bpp characters (fonts, UDGs,
+//! level graphics) to RGBA images, e.g. for font rippers or graphics viewers
+use crate::{
+    image::{palette_color, RgbaImage},
+    palette::PaletteRgba,
+};
+use rustzx_core::{host::Host, zx::video::colors::ZXAttribute, Emulator};
+
+/// Characters per row of the rendered grid
+pub const CHARSET_COLUMNS: usize = 16;
+/// Width and height of the single character in pixels
+pub const CHAR_SIZE: usize = 8;
+/// Address of the UDG system variable, which points to the user defined graphics
+pub const ADDR_SYSVAR_UDG: u16 = 0x5C7B;
+/// Count of user defined graphics (`A`..=`U`) in 48K BASIC
+pub const UDG_COUNT: usize = 21;
+
+/// Renders `count` characters starting from `addr` to the grid of
+/// [CHARSET_COLUMNS] characters per row. Set bits are drawn with ink of
+/// `attr`, reset bits with paper, colors are taken from `palette`. Flash is
+/// ignored
+pub fn render_charset<H: Host>(
+    emulator: &Emulator<H>,
+    addr: u16,
+    count: usize,
+    attr: ZXAttribute,
+    palette: &PaletteRgba,
+) -> RgbaImage {
+    let columns = count.min(CHARSET_COLUMNS);
+    let rows = count.div_ceil(CHARSET_COLUMNS);
+    let ink = palette_color(palette, attr.ink, attr.brightness);
+    let paper = palette_color(palette, attr.paper, attr.brightness);
+
+    let mut image = RgbaImage::new(columns * CHAR_SIZE, rows * CHAR_SIZE);
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let index = (y / CHAR_SIZE) * CHARSET_COLUMNS + x / CHAR_SIZE;
+            let color = if index < count {
+                let offset = index * CHAR_SIZE + y % CHAR_SIZE;
+                let byte = emulator.peek(addr.wrapping_add(offset as u16));
+                if byte & (0x80 >> (x % CHAR_SIZE)) != 0 {
+                    ink
+                } else {
+                    paper
+                }
+            } else {
+                // incomplete last row
+                paper
+            };
+            image.set_pixel(x, y, color);
+        }
+    }
+    image
+}
+
+/// Renders user defined graphics from the address in the UDG system variable
+pub fn render_udg<H: Host>(
+    emulator: &Emulator<H>,
+    attr: ZXAttribute,
+    palette: &PaletteRgba,
+) -> RgbaImage {
+    let addr = u16::from_le_bytes([
+        emulator.peek(ADDR_SYSVAR_UDG),
+        emulator.peek(ADDR_SYSVAR_UDG + 1),
+    ]);
+    render_charset(emulator, addr, UDG_COUNT, attr, palette)
+}
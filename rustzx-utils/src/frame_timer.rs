@@ -0,0 +1,191 @@
+//! Frame pacing for frontends. Frame deadlines are derived from the absolute
+//! schedule start (`start + n * frame_length`) instead of the previous frame
+//! end, so sleep inaccuracy does not accumulate and the long-term frame rate
+//! matches the emulated machine exactly
+use rustzx_core::zx::machine::ZXMachine;
+use std::{
+    collections::VecDeque,
+    thread,
+    time::{Duration, Instant},
+    vec::Vec,
+};
+
+/// Count of last frames used for frame time statistics
+const FRAME_TIME_WINDOW: usize = 256;
+/// Remaining time before the deadline which is spent in busy loop instead of sleeping
+const DEFAULT_SPIN_THRESHOLD: Duration = Duration::from_millis(2);
+/// When emulation is late by more than this count of frames, schedule is restarted
+/// instead of trying to catch up
+const DEFAULT_MAX_LAG_FRAMES: u32 = 5;
+
+/// Frame timing statistics, e.g. for OSD display
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// Count of frames since the timer start
+    pub frames: u64,
+    /// Count of frames which were finished after their deadline
+    pub late_frames: u64,
+    /// Count of schedule restarts because emulation was too far behind
+    pub resyncs: u64,
+    /// Emulated time minus real time since the last (re)start in seconds,
+    /// measured at the end of the last frame. Positive value means that
+    /// emulation is ahead of the real time
+    pub drift: f64,
+    /// Average frame production time over the last frames
+    pub average_frame_time: Duration,
+    /// 95th percentile of frame production time over the last frames
+    pub p95_frame_time: Duration,
+    /// Maximal frame production time over the last frames
+    pub max_frame_time: Duration,
+}
+
+pub struct FrameTimer {
+    frame_length: Duration,
+    spin_threshold: Duration,
+    max_lag: Duration,
+    schedule_start: Instant,
+    scheduled_frames: u32,
+    frame_start: Option<Instant>,
+    frame_times: VecDeque<Duration>,
+    stats: FrameStats,
+}
+
+impl FrameTimer {
+    /// Creates timer with the given frame length, schedule starts at `now`
+    pub fn new(frame_length: Duration, now: Instant) -> Self {
+        Self {
+            frame_length,
+            spin_threshold: DEFAULT_SPIN_THRESHOLD,
+            max_lag: frame_length * DEFAULT_MAX_LAG_FRAMES,
+            schedule_start: now,
+            scheduled_frames: 0,
+            frame_start: None,
+            frame_times: VecDeque::with_capacity(FRAME_TIME_WINDOW),
+            stats: FrameStats::default(),
+        }
+    }
+
+    /// Creates timer with the exact frame rate of the given machine (e.g.
+    /// 50.08 Hz for 48K rather than 50 Hz)
+    pub fn for_machine(machine: ZXMachine, now: Instant) -> Self {
+        let specs = machine.specs();
+        let frame_length =
+            Duration::from_secs_f64(specs.clocks_frame as f64 / specs.freq_cpu as f64);
+        Self::new(frame_length, now)
+    }
+
+    /// Sets how long before the deadline [FrameTimer::wait] switches from
+    /// sleeping to busy waiting. Bigger values give better precision at the
+    /// cost of CPU usage
+    pub fn set_spin_threshold(&mut self, threshold: Duration) {
+        self.spin_threshold = threshold;
+    }
+
+    pub fn frame_length(&self) -> Duration {
+        self.frame_length
+    }
+
+    /// Restarts schedule at `now`, e.g. after emulation pause. Statistics are kept
+    pub fn restart(&mut self, now: Instant) {
+        self.schedule_start = now;
+        self.scheduled_frames = 0;
+        self.frame_start = None;
+    }
+
+    /// Returns deadline of the frame which is currently being produced
+    pub fn deadline(&self) -> Instant {
+        self.schedule_start + self.frame_length * (self.scheduled_frames + 1)
+    }
+
+    /// Marks start of the frame production (emulation and rendering)
+    pub fn begin_frame(&mut self, now: Instant) {
+        self.frame_start = Some(now);
+    }
+
+    /// Marks end of the frame production and advances the schedule. Returns
+    /// time left until the frame deadline, zero if frame is late
+    pub fn end_frame(&mut self, now: Instant) -> Duration {
+        if let Some(start) = self.frame_start.take() {
+            if self.frame_times.len() == FRAME_TIME_WINDOW {
+                self.frame_times.pop_front();
+            }
+            self.frame_times
+                .push_back(now.saturating_duration_since(start));
+        }
+
+        let deadline = self.deadline();
+        self.stats.frames += 1;
+        self.scheduled_frames += 1;
+        if now > deadline {
+            self.stats.late_frames += 1;
+            if now - deadline > self.max_lag {
+                // Catching up would result in a burst of frames, so start over
+                self.stats.resyncs += 1;
+                self.restart(now);
+            }
+        }
+        self.update_drift(now);
+        deadline.saturating_duration_since(now)
+    }
+
+    /// Returns count of frames of this schedule which are due by `deadline`
+    /// and were not taken yet, and advances the schedule past them. Used to
+    /// pace emulated frames by the deadlines of another timer, e.g. when
+    /// host display refresh rate differs from the machine frame rate
+    pub fn take_due_frames(&mut self, deadline: Instant) -> u32 {
+        let elapsed = deadline.saturating_duration_since(self.schedule_start);
+        let due = (elapsed.as_nanos() / self.frame_length.as_nanos()) as u32;
+        let mut count = due.saturating_sub(self.scheduled_frames);
+        if self.frame_length * count > self.max_lag {
+            // Pacing timer has restarted its schedule, follow it
+            self.stats.resyncs += 1;
+            self.restart(deadline.checked_sub(self.frame_length).unwrap_or(deadline));
+            count = 1;
+        }
+        self.stats.frames += count as u64;
+        self.scheduled_frames += count;
+        count
+    }
+
+    /// Waits until the current frame deadline. Most of the interval is spent
+    /// sleeping, the rest is spent in busy loop to compensate sleep inaccuracy
+    pub fn wait(&self) {
+        let deadline = self.last_deadline();
+        let now = Instant::now();
+        if deadline <= now {
+            return;
+        }
+        let remaining = deadline - now;
+        if remaining > self.spin_threshold {
+            thread::sleep(remaining - self.spin_threshold);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+    }
+
+    fn last_deadline(&self) -> Instant {
+        self.schedule_start + self.frame_length * self.scheduled_frames
+    }
+
+    fn update_drift(&mut self, now: Instant) {
+        let emulated = (self.frame_length * self.scheduled_frames).as_secs_f64();
+        let real = now
+            .saturating_duration_since(self.schedule_start)
+            .as_secs_f64();
+        self.stats.drift = emulated - real;
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        let mut stats = self.stats;
+        if !self.frame_times.is_empty() {
+            let mut sorted = self.frame_times.iter().copied().collect::<Vec<_>>();
+            sorted.sort_unstable();
+            let total: Duration = sorted.iter().sum();
+            stats.average_frame_time = total / sorted.len() as u32;
+            stats.p95_frame_time = sorted[(sorted.len() - 1) * 95 / 100];
+            stats.max_frame_time = *sorted.last().unwrap();
+        }
+        stats
+    }
+}
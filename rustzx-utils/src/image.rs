@@ -0,0 +1,184 @@
+//! RGBA images produced by the rendering helpers
+use crate::palette::{rgba::ORIGINAL as DEFAULT_PALETTE, PaletteRgba};
+use alloc::{vec, vec::Vec};
+use core::fmt;
+use rustzx_core::zx::{
+    constants::{CANVAS_HEIGHT, CANVAS_WIDTH},
+    video::{
+        bitmap_line_addr,
+        colors::{ZXAttribute, ZXBrightness, ZXColor},
+    },
+};
+
+/// Size of the bitmap and attributes of the ZX Spectrum screen
+pub const DISPLAY_FILE_SIZE: usize = 6912;
+const ATTRIBUTES_OFFSET: usize = 6144;
+/// Address of the display file in the memory
+const DISPLAY_FILE_ADDR: u16 = 0x4000;
+const ATTR_COLS: usize = CANVAS_WIDTH / 8;
+const ROW_SIZE: usize = CANVAS_WIDTH * 4;
+/// Mask XOR-ed with the colors of the highlighted cells, inverts RGB and
+/// keeps alpha
+const HIGHLIGHT_XOR: [u8; 4] = [0xFF, 0xFF, 0xFF, 0x00];
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+/// Debugging aid drawn over the rendered screen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DebugOverlay {
+    #[default]
+    None,
+    /// Cells with the flash attribute bit set are drawn in inverted colors
+    FlashCells,
+}
+
+pub struct RgbaImage {
+    /// Width in pixels
+    pub width: usize,
+    /// Height in pixels
+    pub height: usize,
+    /// RGBA pixels, row by row (`width * 4` bytes per row)
+    pub rgba: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Returns new image filled with transparent black
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            rgba: vec![0; width * height * 4],
+        }
+    }
+
+    /// Renders 256x192 canvas from the screen memory contents (bitmap followed
+    /// by attributes, as in `*.scr` files). Flashing cells are drawn in their
+    /// normal state. Returns `None` if `data` is shorter than 6912 bytes
+    pub fn from_display_file(data: &[u8]) -> Option<Self> {
+        Self::from_display_file_with_overlay(data, DebugOverlay::None)
+    }
+
+    /// Same as [RgbaImage::from_display_file], with `overlay` drawn over
+    /// the screen
+    pub fn from_display_file_with_overlay(data: &[u8], overlay: DebugOverlay) -> Option<Self> {
+        let data = data.get(..DISPLAY_FILE_SIZE)?;
+        let mut image = Self::new(CANVAS_WIDTH, CANVAS_HEIGHT);
+        render_rows(data, &mut image.rgba, overlay);
+        Some(image)
+    }
+
+    /// Returns FNV-1a hash of the image size and pixels. Unlike `std` hashers
+    /// it is stable across platforms and Rust versions, so it can be stored
+    /// in tests to detect rendering regressions
+    pub fn frame_hash(&self) -> u64 {
+        let size = [self.width as u32, self.height as u32];
+        size.iter()
+            .flat_map(|value| value.to_le_bytes())
+            .chain(self.rgba.iter().copied())
+            .fold(FNV_OFFSET_BASIS, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let offset = (y * self.width + x) * 4;
+        let mut color = [0; 4];
+        color.copy_from_slice(&self.rgba[offset..offset + 4]);
+        color
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: [u8; 4]) {
+        let offset = (y * self.width + x) * 4;
+        self.rgba[offset..offset + 4].copy_from_slice(&color);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameDiffError {
+    /// Frames have different sizes in bytes
+    SizeMismatch { a: usize, b: usize },
+    /// Frame size in bytes is not a multiple of the RGBA pixel size
+    PartialPixel(usize),
+}
+
+impl fmt::Display for FrameDiffError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SizeMismatch { a, b } => {
+                write!(f, "frames have different sizes ({} and {} bytes)", a, b)
+            }
+            Self::PartialPixel(size) => {
+                write!(f, "frame size {} is not a multiple of 4 bytes", size)
+            }
+        }
+    }
+}
+
+/// Compares two RGBA frames (e.g. [RgbaImage::rgba]) pixel by pixel. Returns
+/// count of changed pixels and mask with `true` for each changed pixel, in
+/// the same order as the pixels of the frame. Use [RgbaImage::frame_hash]
+/// to check whether frames differ at all, and this function to find out
+/// how much and where
+pub fn diff_frames(a: &[u8], b: &[u8]) -> Result<(usize, Vec<bool>), FrameDiffError> {
+    if a.len() != b.len() {
+        return Err(FrameDiffError::SizeMismatch {
+            a: a.len(),
+            b: b.len(),
+        });
+    }
+    if !a.len().is_multiple_of(4) {
+        return Err(FrameDiffError::PartialPixel(a.len()));
+    }
+    let mask = a
+        .chunks_exact(4)
+        .zip(b.chunks_exact(4))
+        .map(|(a, b)| a != b)
+        .collect::<Vec<_>>();
+    let changed_pixels = mask.iter().filter(|changed| **changed).count();
+    Ok((changed_pixels, mask))
+}
+
+/// Renders canvas rows into `rgba` row by row
+fn render_rows(data: &[u8], rgba: &mut [u8], overlay: DebugOverlay) {
+    for (y, line) in rgba.chunks_exact_mut(ROW_SIZE).enumerate() {
+        let line_offset = (bitmap_line_addr(y) - DISPLAY_FILE_ADDR) as usize;
+        for (col, block) in line.chunks_exact_mut(8 * 4).enumerate() {
+            let bitmap = data[line_offset + col];
+            let attr = ZXAttribute::from_byte(data[ATTRIBUTES_OFFSET + (y / 8) * ATTR_COLS + col]);
+            let mut ink = rgba_color(attr.ink, attr.brightness);
+            let mut paper = rgba_color(attr.paper, attr.brightness);
+            if overlay == DebugOverlay::FlashCells && attr.flash {
+                ink = highlighted(ink);
+                paper = highlighted(paper);
+            }
+            for (bit, pixel) in block.chunks_exact_mut(4).enumerate() {
+                let color = if bitmap & (0x80 >> bit) != 0 {
+                    ink
+                } else {
+                    paper
+                };
+                pixel.copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+/// Returns inverted `color`, which stands out on any palette
+fn highlighted(color: [u8; 4]) -> [u8; 4] {
+    core::array::from_fn(|n| color[n] ^ HIGHLIGHT_XOR[n])
+}
+
+/// Returns color from the default palette
+pub(crate) fn rgba_color(color: ZXColor, brightness: ZXBrightness) -> [u8; 4] {
+    palette_color(&DEFAULT_PALETTE, color, brightness)
+}
+
+/// Returns color from `palette`
+pub(crate) fn palette_color(
+    palette: &PaletteRgba,
+    color: ZXColor,
+    brightness: ZXBrightness,
+) -> [u8; 4] {
+    let bright = matches!(brightness, ZXBrightness::Bright) as usize * 8;
+    palette[u8::from(color) as usize + bright]
+}
@@ -0,0 +1,197 @@
+//! Frame buffer with one byte per pixel indexing the 16-color palette, for
+//! frontends driving small displays (e.g. ILI9341 TFTs) where RGBA buffers
+//! take 4x more memory and bandwidth. Indices are `color + brightness * 8`,
+//! the same as in [crate::palette]
+//!
+//! Pixel storage can be allocated by the caller (e.g. placed into a
+//! dedicated RAM region), see [FrameBufferPool]
+use crate::palette::{PaletteRgb, PALETTE_SIZE};
+use alloc::{boxed::Box, sync::Arc, vec, vec::Vec};
+use rustzx_core::{
+    host::{FrameBuffer, FrameBufferSource},
+    zx::video::colors::{ZXBrightness, ZXColor},
+};
+use spin::Mutex;
+
+/// Caller-owned pixel storage for the frame buffers created by the
+/// emulator. Each buffer created with the pool in its context takes the
+/// smallest stored buffer which is large enough, see
+/// [IndexedFrameBuffer::required_len]; buffers are allocated as usual when
+/// none fits. Taken storage is returned to the pool when the frame buffer is
+/// dropped (e.g. on machine switch)
+#[derive(Clone, Default)]
+pub struct FrameBufferPool(Arc<Mutex<Vec<Box<[u8]>>>>);
+
+impl FrameBufferPool {
+    /// Creates pool with enough storage for all frame buffers of the
+    /// emulator, allocated by `alloc` from the required size in bytes
+    pub fn for_emulator(mut alloc: impl FnMut(usize) -> Box<[u8]>) -> Self {
+        let pool = Self::default();
+        for source in [FrameBufferSource::Screen, FrameBufferSource::Border] {
+            let (width, height) = source.dimensions();
+            for _ in 0..source.buffer_count() {
+                pool.add(alloc(IndexedFrameBuffer::required_len(width, height)));
+            }
+        }
+        pool
+    }
+
+    pub fn add(&self, buffer: Box<[u8]>) {
+        self.0.lock().push(buffer);
+    }
+
+    /// Returns count of the buffers not taken by frame buffers yet
+    pub fn len(&self) -> usize {
+        self.0.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn take(&self, len: usize) -> Option<Box<[u8]>> {
+        let mut buffers = self.0.lock();
+        let (index, _) = buffers
+            .iter()
+            .enumerate()
+            .filter(|(_, buffer)| buffer.len() >= len)
+            .min_by_key(|(_, buffer)| buffer.len())?;
+        Some(buffers.swap_remove(index))
+    }
+}
+
+#[derive(Clone)]
+pub struct IndexedFrameBufferContext {
+    pub palette: PaletteRgb,
+    /// Storage for the created frame buffers, allocated internally if not set
+    pub pool: Option<FrameBufferPool>,
+}
+
+impl Default for IndexedFrameBufferContext {
+    fn default() -> Self {
+        Self {
+            palette: crate::palette::rgb::ORIGINAL,
+            pool: None,
+        }
+    }
+}
+
+/// Palette-indexed frame buffer. ULAplus colors are reduced to the closest
+/// standard ones
+pub struct IndexedFrameBuffer {
+    // May be longer than `width * height` if taken from the pool
+    buffer: Box<[u8]>,
+    width: usize,
+    height: usize,
+    palette: PaletteRgb,
+    // Pool which the buffer storage was taken from
+    pool: Option<FrameBufferPool>,
+}
+
+impl FrameBuffer for IndexedFrameBuffer {
+    type Context = IndexedFrameBufferContext;
+
+    fn new(
+        width: usize,
+        height: usize,
+        _source: FrameBufferSource,
+        context: Self::Context,
+    ) -> Self {
+        let len = Self::required_len(width, height);
+        let pooled = context.pool.and_then(|pool| Some((pool.take(len)?, pool)));
+        let (buffer, pool) = match pooled {
+            Some((mut buffer, pool)) => {
+                buffer.fill(0);
+                (buffer, Some(pool))
+            }
+            None => (vec![0; len].into_boxed_slice(), None),
+        };
+        Self {
+            buffer,
+            width,
+            height,
+            palette: context.palette,
+            pool,
+        }
+    }
+
+    fn set_color(&mut self, x: usize, y: usize, color: ZXColor, brightness: ZXBrightness) {
+        self.buffer[y * self.width + x] = color as u8 + brightness as u8 * 8;
+    }
+
+    #[inline]
+    fn set_block(
+        &mut self,
+        x: usize,
+        y: usize,
+        bitmap: u8,
+        ink: ZXColor,
+        paper: ZXColor,
+        brightness: ZXBrightness,
+    ) {
+        let ink = ink as u8 + brightness as u8 * 8;
+        let paper = paper as u8 + brightness as u8 * 8;
+        // Byte N of the mask is 0xFF if pixel N is set (bits from the most
+        // significant one), computed for all pixels at once
+        let bits = (bitmap as u64 * 0x0101_0101_0101_0101) & 0x0102_0408_1020_4080;
+        let set = (bits + 0x7F7F_7F7F_7F7F_7F7F) & 0x8080_8080_8080_8080;
+        let mask = (set >> 7) * 0xFF;
+        let block = ((u64::from_le_bytes([ink; 8]) & mask)
+            | (u64::from_le_bytes([paper; 8]) & !mask))
+            .to_le_bytes();
+        let pos = y * self.width + x;
+        self.buffer[pos..pos + 8].copy_from_slice(&block);
+    }
+}
+
+impl Drop for IndexedFrameBuffer {
+    fn drop(&mut self) {
+        if let Some(pool) = &self.pool {
+            pool.add(core::mem::take(&mut self.buffer));
+        }
+    }
+}
+
+impl IndexedFrameBuffer {
+    /// Size in bytes of the pixel storage of the buffer with given
+    /// dimensions, see [FrameBufferSource::dimensions]
+    pub const fn required_len(width: usize, height: usize) -> usize {
+        width * height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns pixel indices (row by row, `width` bytes per row) and the
+    /// palette they refer to
+    pub fn clone_indexed(&self) -> (&[u8], &PaletteRgb) {
+        (&self.buffer[..self.width * self.height], &self.palette)
+    }
+
+    /// Returns palette converted to RGB565, the native format of most small
+    /// TFT controllers
+    pub fn palette_rgb565(&self) -> [u16; PALETTE_SIZE] {
+        self.palette
+            .map(|[r, g, b]| ((r as u16 & 0xF8) << 8) | ((g as u16 & 0xFC) << 3) | (b as u16 >> 3))
+    }
+}
+
+#[cfg(feature = "tui")]
+impl crate::tui::IndexedScreen for IndexedFrameBuffer {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn color_index(&self, x: usize, y: usize) -> u8 {
+        self.buffer[y * self.width + x]
+    }
+}
@@ -0,0 +1,74 @@
+use rustzx_core::{
+    error::IoError,
+    host::{BlockDevice, SECTOR_SIZE},
+};
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+/// Hard disk image file with raw 512-byte sectors
+pub struct FileBlockDevice {
+    file: File,
+    sector_count: u32,
+    read_only: bool,
+}
+
+impl FileBlockDevice {
+    /// Creates block device from the image file. Trailing incomplete sector is
+    /// ignored. If `read_only` is set, all writes will fail
+    pub fn new(file: File, read_only: bool) -> std::io::Result<Self> {
+        let sector_count = (file.metadata()?.len() / SECTOR_SIZE as u64) as u32;
+        Ok(Self {
+            file,
+            sector_count,
+            read_only,
+        })
+    }
+
+    fn seek_to_sector(&mut self, lba: u32) -> Result<(), IoError> {
+        if lba >= self.sector_count {
+            return Err(IoError::SeekBeforeStart);
+        }
+        self.file
+            .seek(SeekFrom::Start(lba as u64 * SECTOR_SIZE as u64))
+            .map_err(|e| {
+                log::error!("Failed to seek block device: {}", e);
+                IoError::HostAssetImplFailed
+            })?;
+        Ok(())
+    }
+}
+
+impl BlockDevice for FileBlockDevice {
+    fn sector_count(&self) -> u32 {
+        self.sector_count
+    }
+
+    fn read_sector(&mut self, lba: u32, buf: &mut [u8; SECTOR_SIZE]) -> Result<(), IoError> {
+        self.seek_to_sector(lba)?;
+        self.file.read_exact(buf).map_err(|e| {
+            log::error!("Failed to read block device sector: {}", e);
+            IoError::HostAssetImplFailed
+        })
+    }
+
+    fn write_sector(&mut self, lba: u32, buf: &[u8; SECTOR_SIZE]) -> Result<(), IoError> {
+        if self.read_only {
+            return Err(IoError::WriteZero);
+        }
+        self.seek_to_sector(lba)?;
+        self.file.write_all(buf).map_err(|e| {
+            log::error!("Failed to write block device sector: {}", e);
+            IoError::HostAssetImplFailed
+        })
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        self.file.sync_data().map_err(|e| {
+            log::error!("Failed to flush block device: {}", e);
+            IoError::HostAssetImplFailed
+        })
+    }
+}
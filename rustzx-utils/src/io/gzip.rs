@@ -1,4 +1,4 @@
-use super::DynamicAssetImpl;
+use super::{read_to_end_limited, DynamicAssetImpl};
 
 use rustzx_core::{
     error::IoError,
@@ -7,7 +7,6 @@ use rustzx_core::{
 
 use std::{
     io::{self, Read},
-    vec,
     vec::Vec,
 };
 
@@ -20,8 +19,7 @@ pub struct GzipAsset {
 impl GzipAsset {
     pub fn new(file: impl Read) -> Result<Self, io::Error> {
         // ZX Spectrum assets are small enough to use RAM for unpacked data
-        let mut buffer = vec![];
-        let _ = GzDecoder::new(file).read_to_end(&mut buffer)?;
+        let buffer = read_to_end_limited(GzDecoder::new(file))?;
         Ok(Self {
             buffer: BufferCursor::new(buffer),
         })
@@ -0,0 +1,45 @@
+use super::{read_to_end_limited, DynamicAssetImpl};
+
+use rustzx_core::{
+    error::IoError,
+    host::{BufferCursor, LoadableAsset, SeekFrom, SeekableAsset},
+};
+
+use std::{
+    io::{self, Read},
+    vec::Vec,
+};
+
+/// Asset read into memory from any reader (network response, embedded data,
+/// etc.), so it can be loaded without writing a temporary file
+pub struct MemoryAsset {
+    buffer: BufferCursor<Vec<u8>>,
+}
+
+impl MemoryAsset {
+    /// Reads `reader` to the end, data larger than [super::MAX_ASSET_SIZE]
+    /// is rejected
+    pub fn from_reader(reader: impl Read) -> Result<Self, io::Error> {
+        Ok(Self {
+            buffer: BufferCursor::new(read_to_end_limited(reader)?),
+        })
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.buffer.into_inner()
+    }
+}
+
+impl SeekableAsset for MemoryAsset {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, IoError> {
+        self.buffer.seek(pos)
+    }
+}
+
+impl LoadableAsset for MemoryAsset {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        self.buffer.read(buf)
+    }
+}
+
+impl DynamicAssetImpl for MemoryAsset {}
@@ -1,15 +1,42 @@
+#[cfg(feature = "ide")]
+mod block_device;
 mod file;
 mod gzip;
+mod memory;
+#[cfg(feature = "spectranet")]
+mod network;
+#[cfg(feature = "zip")]
+mod zip;
 
 use rustzx_core::{
     error::IoError,
     host::{BufferCursor, DataRecorder, LoadableAsset, SeekFrom, SeekableAsset},
 };
 
-use std::boxed::Box;
+use std::{
+    boxed::Box,
+    format,
+    io::{self, Read},
+    vec::Vec,
+};
+
+/// Size limit of the assets unpacked or read into memory. ZX Spectrum assets
+/// are much smaller, the limit protects from damaged or malicious files
+/// (e.g. decompression bombs)
+pub const MAX_ASSET_SIZE: usize = 16 * 1024 * 1024;
 
+#[cfg(feature = "ide")]
+pub use block_device::FileBlockDevice;
 pub use file::FileAsset;
 pub use gzip::GzipAsset;
+pub use memory::MemoryAsset;
+#[cfg(feature = "spectranet")]
+pub use network::StdNetworkStack;
+#[cfg(feature = "zip")]
+pub use zip::{
+    archive_entries, load_archive_entry, ArchiveEntry, ArchiveEntryKind, ZipArchive, ZipAsset,
+    ZipError,
+};
 
 pub trait DynamicAssetImpl: LoadableAsset + SeekableAsset {}
 
@@ -58,3 +85,19 @@ pub(crate) fn into_std_seek_pos(pos: SeekFrom) -> std::io::SeekFrom {
         SeekFrom::Current(offset) => std::io::SeekFrom::Current(offset as i64),
     }
 }
+
+/// Reads `reader` to the end, failing with [io::ErrorKind::InvalidData] when
+/// the data is larger than [MAX_ASSET_SIZE]
+pub(crate) fn read_to_end_limited(reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    reader
+        .take(MAX_ASSET_SIZE as u64 + 1)
+        .read_to_end(&mut buffer)?;
+    if buffer.len() > MAX_ASSET_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("asset is larger than {} bytes", MAX_ASSET_SIZE),
+        ));
+    }
+    Ok(buffer)
+}
@@ -0,0 +1,158 @@
+use rustzx_core::{
+    error::IoError,
+    host::{NetworkStack, SocketHandle},
+};
+
+use std::{
+    collections::HashMap,
+    io::{ErrorKind, Read, Write},
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener, TcpStream, UdpSocket},
+};
+
+enum Socket {
+    Listener(TcpListener),
+    Stream(TcpStream),
+    Datagram(UdpSocket),
+}
+
+/// Network stack backed by host OS sockets in non-blocking mode
+#[derive(Default)]
+pub struct StdNetworkStack {
+    sockets: HashMap<SocketHandle, Socket>,
+    next_handle: SocketHandle,
+}
+
+impl StdNetworkStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn insert(&mut self, socket: Socket) -> SocketHandle {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.sockets.insert(handle, socket);
+        handle
+    }
+
+    fn socket(&mut self, handle: SocketHandle) -> Result<&mut Socket, IoError> {
+        self.sockets.get_mut(&handle).ok_or_else(|| {
+            log::error!("Invalid network socket handle {}", handle);
+            IoError::HostAssetImplFailed
+        })
+    }
+}
+
+fn map_error(e: std::io::Error) -> IoError {
+    log::error!("Network operation failed: {}", e);
+    IoError::HostAssetImplFailed
+}
+
+/// Converts non-blocking operation result, `None` means "try again later"
+fn non_blocking<T>(result: std::io::Result<T>) -> Result<Option<T>, IoError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+        Err(e) => Err(map_error(e)),
+    }
+}
+
+fn local_addr(port: u16) -> SocketAddr {
+    SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port).into()
+}
+
+impl NetworkStack for StdNetworkStack {
+    fn tcp_connect(&mut self, ip: [u8; 4], port: u16) -> Result<SocketHandle, IoError> {
+        let stream = TcpStream::connect(SocketAddrV4::new(ip.into(), port)).map_err(map_error)?;
+        stream.set_nonblocking(true).map_err(map_error)?;
+        Ok(self.insert(Socket::Stream(stream)))
+    }
+
+    fn tcp_listen(&mut self, port: u16) -> Result<SocketHandle, IoError> {
+        let listener = TcpListener::bind(local_addr(port)).map_err(map_error)?;
+        listener.set_nonblocking(true).map_err(map_error)?;
+        Ok(self.insert(Socket::Listener(listener)))
+    }
+
+    fn tcp_accept(&mut self, socket: SocketHandle) -> Result<bool, IoError> {
+        let accepted = match self.socket(socket)? {
+            Socket::Listener(listener) => non_blocking(listener.accept())?,
+            Socket::Stream(_) => return Ok(true),
+            Socket::Datagram(_) => return Err(IoError::HostAssetImplFailed),
+        };
+        match accepted {
+            Some((stream, _)) => {
+                stream.set_nonblocking(true).map_err(map_error)?;
+                self.sockets.insert(socket, Socket::Stream(stream));
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn udp_bind(&mut self, port: u16) -> Result<SocketHandle, IoError> {
+        let socket = UdpSocket::bind(local_addr(port)).map_err(map_error)?;
+        socket.set_nonblocking(true).map_err(map_error)?;
+        Ok(self.insert(Socket::Datagram(socket)))
+    }
+
+    fn send(&mut self, socket: SocketHandle, data: &[u8]) -> Result<(), IoError> {
+        match self.socket(socket)? {
+            Socket::Stream(stream) => {
+                // Emulated send buffer is small, so wait for the host to take all of it
+                stream.set_nonblocking(false).map_err(map_error)?;
+                let result = stream.write_all(data).map_err(map_error);
+                stream.set_nonblocking(true).map_err(map_error)?;
+                result
+            }
+            _ => Err(IoError::HostAssetImplFailed),
+        }
+    }
+
+    fn send_to(
+        &mut self,
+        socket: SocketHandle,
+        ip: [u8; 4],
+        port: u16,
+        data: &[u8],
+    ) -> Result<(), IoError> {
+        match self.socket(socket)? {
+            Socket::Datagram(udp) => {
+                udp.send_to(data, SocketAddrV4::new(ip.into(), port))
+                    .map_err(map_error)?;
+                Ok(())
+            }
+            _ => Err(IoError::HostAssetImplFailed),
+        }
+    }
+
+    fn recv(&mut self, socket: SocketHandle, buf: &mut [u8]) -> Result<Option<usize>, IoError> {
+        match self.socket(socket)? {
+            Socket::Stream(stream) => non_blocking(stream.read(buf)),
+            Socket::Listener(_) => Ok(None),
+            Socket::Datagram(_) => Err(IoError::HostAssetImplFailed),
+        }
+    }
+
+    fn recv_from(
+        &mut self,
+        socket: SocketHandle,
+        buf: &mut [u8],
+    ) -> Result<Option<(usize, [u8; 4], u16)>, IoError> {
+        match self.socket(socket)? {
+            Socket::Datagram(udp) => {
+                Ok(
+                    non_blocking(udp.recv_from(buf))?.and_then(|(size, addr)| match addr {
+                        SocketAddr::V4(addr) => Some((size, addr.ip().octets(), addr.port())),
+                        // Emulated hardware is IPv4-only
+                        SocketAddr::V6(_) => None,
+                    }),
+                )
+            }
+            _ => Err(IoError::HostAssetImplFailed),
+        }
+    }
+
+    fn close(&mut self, socket: SocketHandle) {
+        self.sockets.remove(&socket);
+    }
+}
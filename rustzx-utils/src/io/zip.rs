@@ -0,0 +1,390 @@
+use super::{DynamicAssetImpl, MAX_ASSET_SIZE};
+
+use rustzx_core::{
+    error::IoError,
+    host::{BufferCursor, LoadableAsset, SeekFrom, SeekableAsset},
+};
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read, Seek},
+    path::Path,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use flate2::{read::DeflateDecoder, Crc};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034B50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014B50;
+const END_OF_CENTRAL_DIR_SIGNATURE: u32 = 0x06054B50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x07064B50;
+
+const LOCAL_HEADER_SIZE: usize = 30;
+const CENTRAL_HEADER_SIZE: usize = 46;
+const END_OF_CENTRAL_DIR_SIZE: usize = 22;
+const ZIP64_LOCATOR_SIZE: usize = 20;
+const MAX_COMMENT_SIZE: usize = 0xFFFF;
+
+const FLAG_ENCRYPTED: u16 = 0x0001;
+const FLAG_UTF8_NAME: u16 = 0x0800;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+#[derive(Debug)]
+pub enum ZipError {
+    Io(io::Error),
+    /// File is not a ZIP archive or its structure is damaged
+    InvalidArchive(&'static str),
+    /// Archive uses ZIP64 extensions for large files
+    Zip64,
+    /// Entry is password-protected
+    Encrypted(String),
+    /// Entry is compressed with a method other than store or deflate
+    UnsupportedCompression {
+        name: String,
+        method: u16,
+    },
+    /// Archive has no entry with the requested name
+    EntryNotFound(String),
+    /// Unpacked entry data does not match its CRC32
+    ChecksumMismatch(String),
+    /// Entry is larger than [MAX_ASSET_SIZE]
+    EntryTooLarge(String),
+}
+
+impl fmt::Display for ZipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read archive: {}", e),
+            Self::InvalidArchive(reason) => write!(f, "invalid ZIP archive: {}", reason),
+            Self::Zip64 => write!(f, "ZIP64 archives are not supported"),
+            Self::Encrypted(name) => {
+                write!(f, "archive entry '{}' is password-protected", name)
+            }
+            Self::UnsupportedCompression { name, method } => write!(
+                f,
+                "archive entry '{}' uses unsupported compression method {}",
+                name, method
+            ),
+            Self::EntryNotFound(name) => write!(f, "archive has no entry '{}'", name),
+            Self::ChecksumMismatch(name) => {
+                write!(f, "archive entry '{}' is corrupted (CRC mismatch)", name)
+            }
+            Self::EntryTooLarge(name) => write!(f, "archive entry '{}' is too large", name),
+        }
+    }
+}
+
+impl std::error::Error for ZipError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ZipError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Kind of the asset stored in the archive, detected by the entry extension
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveEntryKind {
+    /// `*.tap`
+    Tape,
+    /// `*.sna`
+    Snapshot,
+    /// `*.scr`
+    Screen,
+    /// `*.dck` Timex cartridge
+    Cartridge,
+    /// `*.mgt` +D floppy disk image
+    Disk,
+}
+
+impl ArchiveEntryKind {
+    /// Returns kind of the asset with the given file name, `None` if it is
+    /// not recognized (documentation, nested archives, etc.)
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (_, ext) = name.rsplit_once('.')?;
+        match ext.to_ascii_lowercase().as_str() {
+            "tap" => Some(Self::Tape),
+            "sna" => Some(Self::Snapshot),
+            "scr" => Some(Self::Screen),
+            "dck" => Some(Self::Cartridge),
+            "mgt" => Some(Self::Disk),
+            _ => None,
+        }
+    }
+}
+
+/// Recognized file stored in the ZIP archive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Full name of the entry, including directories
+    pub name: String,
+    /// Unpacked size in bytes
+    pub size: u64,
+    pub kind: ArchiveEntryKind,
+}
+
+struct CentralEntry {
+    name: String,
+    flags: u16,
+    method: u16,
+    crc32: u32,
+    compressed_size: u64,
+    size: u64,
+    local_header_offset: u64,
+}
+
+impl CentralEntry {
+    fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+/// Read-only ZIP archive with stored or deflated entries. Spanned, ZIP64
+/// and encrypted archives are rejected
+pub struct ZipArchive<R> {
+    reader: R,
+    entries: Vec<CentralEntry>,
+}
+
+impl<R: Read + Seek> ZipArchive<R> {
+    pub fn new(mut reader: R) -> Result<Self, ZipError> {
+        let len = reader.seek(io::SeekFrom::End(0))?;
+        let tail_len = len.min((END_OF_CENTRAL_DIR_SIZE + MAX_COMMENT_SIZE) as u64) as usize;
+        if tail_len < END_OF_CENTRAL_DIR_SIZE {
+            return Err(ZipError::InvalidArchive("file is too short"));
+        }
+        let tail_offset = len - tail_len as u64;
+        reader.seek(io::SeekFrom::Start(tail_offset))?;
+        let mut tail = vec![0; tail_len];
+        reader.read_exact(&mut tail)?;
+
+        let eocd_pos = (0..=tail_len - END_OF_CENTRAL_DIR_SIZE)
+            .rev()
+            .find(|&pos| read_u32(&tail, pos) == END_OF_CENTRAL_DIR_SIGNATURE)
+            .ok_or(ZipError::InvalidArchive(
+                "end of central directory not found",
+            ))?;
+        if eocd_pos >= ZIP64_LOCATOR_SIZE
+            && read_u32(&tail, eocd_pos - ZIP64_LOCATOR_SIZE) == ZIP64_LOCATOR_SIGNATURE
+        {
+            return Err(ZipError::Zip64);
+        }
+        let eocd = &tail[eocd_pos..];
+        let disk = read_u16(eocd, 4);
+        let central_dir_disk = read_u16(eocd, 6);
+        let disk_entries = read_u16(eocd, 8);
+        let total_entries = read_u16(eocd, 10);
+        let central_dir_size = read_u32(eocd, 12);
+        let central_dir_offset = read_u32(eocd, 16);
+        if total_entries == 0xFFFF
+            || central_dir_size == 0xFFFFFFFF
+            || central_dir_offset == 0xFFFFFFFF
+        {
+            return Err(ZipError::Zip64);
+        }
+        if disk != 0 || central_dir_disk != 0 || disk_entries != total_entries {
+            return Err(ZipError::InvalidArchive(
+                "spanned archives are not supported",
+            ));
+        }
+        if central_dir_offset as u64 + central_dir_size as u64 > tail_offset + eocd_pos as u64 {
+            return Err(ZipError::InvalidArchive(
+                "central directory is out of bounds",
+            ));
+        }
+
+        reader.seek(io::SeekFrom::Start(central_dir_offset as u64))?;
+        let mut central_dir = vec![0; central_dir_size as usize];
+        reader.read_exact(&mut central_dir)?;
+
+        let mut entries = Vec::with_capacity(total_entries as usize);
+        let mut pos = 0;
+        for _ in 0..total_entries {
+            let header = central_dir
+                .get(pos..pos + CENTRAL_HEADER_SIZE)
+                .filter(|header| read_u32(header, 0) == CENTRAL_HEADER_SIGNATURE)
+                .ok_or(ZipError::InvalidArchive("damaged central directory"))?;
+            let name_len = read_u16(header, 28) as usize;
+            let extra_len = read_u16(header, 30) as usize;
+            let comment_len = read_u16(header, 32) as usize;
+            let compressed_size = read_u32(header, 20);
+            let size = read_u32(header, 24);
+            let local_header_offset = read_u32(header, 42);
+            if [compressed_size, size, local_header_offset].contains(&0xFFFFFFFF) {
+                return Err(ZipError::Zip64);
+            }
+            let flags = read_u16(header, 8);
+            let name_start = pos + CENTRAL_HEADER_SIZE;
+            let name = central_dir
+                .get(name_start..name_start + name_len)
+                .ok_or(ZipError::InvalidArchive("damaged central directory"))?;
+            // Names without the UTF-8 flag are CP437, which matches ASCII for
+            // the usual file names
+            let name = if flags & FLAG_UTF8_NAME != 0 {
+                String::from_utf8_lossy(name).into_owned()
+            } else {
+                name.iter()
+                    .map(|&b| if b.is_ascii() { b as char } else { '?' })
+                    .collect()
+            };
+            entries.push(CentralEntry {
+                name,
+                flags,
+                method: read_u16(header, 10),
+                crc32: read_u32(header, 16),
+                compressed_size: compressed_size as u64,
+                size: size as u64,
+                local_header_offset: local_header_offset as u64,
+            });
+            pos = name_start + name_len + extra_len + comment_len;
+        }
+
+        Ok(Self { reader, entries })
+    }
+
+    /// Returns recognized entries in the archive order
+    pub fn entries(&self) -> Vec<ArchiveEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.is_dir())
+            .filter_map(|entry| {
+                Some(ArchiveEntry {
+                    name: entry.name.clone(),
+                    size: entry.size,
+                    kind: ArchiveEntryKind::from_name(&entry.name)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Unpacks entry with the given name
+    pub fn load(&mut self, name: &str) -> Result<ZipAsset, ZipError> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| !entry.is_dir() && entry.name == name)
+            .ok_or_else(|| ZipError::EntryNotFound(name.to_string()))?;
+        self.load_entry(index)
+    }
+
+    /// Unpacks first recognized entry of the given kind, or of any kind if
+    /// `kind` is `None`
+    pub fn load_first(&mut self, kind: Option<ArchiveEntryKind>) -> Result<ZipAsset, ZipError> {
+        let entry = self
+            .entries()
+            .into_iter()
+            .find(|entry| kind.is_none() || kind == Some(entry.kind))
+            .ok_or(ZipError::InvalidArchive(
+                "no supported files in the archive",
+            ))?;
+        self.load(&entry.name)
+    }
+
+    fn load_entry(&mut self, index: usize) -> Result<ZipAsset, ZipError> {
+        let entry = &self.entries[index];
+        if entry.flags & FLAG_ENCRYPTED != 0 {
+            return Err(ZipError::Encrypted(entry.name.clone()));
+        }
+        if entry.size > MAX_ASSET_SIZE as u64 {
+            return Err(ZipError::EntryTooLarge(entry.name.clone()));
+        }
+        if ![METHOD_STORED, METHOD_DEFLATED].contains(&entry.method) {
+            return Err(ZipError::UnsupportedCompression {
+                name: entry.name.clone(),
+                method: entry.method,
+            });
+        }
+
+        self.reader
+            .seek(io::SeekFrom::Start(entry.local_header_offset))?;
+        let mut header = [0; LOCAL_HEADER_SIZE];
+        self.reader.read_exact(&mut header)?;
+        if read_u32(&header, 0) != LOCAL_HEADER_SIGNATURE {
+            return Err(ZipError::InvalidArchive("damaged local file header"));
+        }
+        let data_offset = read_u16(&header, 26) as i64 + read_u16(&header, 28) as i64;
+        self.reader.seek(io::SeekFrom::Current(data_offset))?;
+
+        // ZX Spectrum assets are small enough to use RAM for unpacked data
+        let compressed = (&mut self.reader).take(entry.compressed_size);
+        let mut buffer = Vec::with_capacity(entry.size as usize);
+        if entry.method == METHOD_STORED {
+            compressed.take(entry.size).read_to_end(&mut buffer)?;
+        } else {
+            DeflateDecoder::new(compressed)
+                .take(entry.size)
+                .read_to_end(&mut buffer)?;
+        }
+
+        let mut crc = Crc::new();
+        crc.update(&buffer);
+        if buffer.len() as u64 != entry.size || crc.sum() != entry.crc32 {
+            return Err(ZipError::ChecksumMismatch(entry.name.clone()));
+        }
+
+        Ok(ZipAsset {
+            buffer: BufferCursor::new(buffer),
+        })
+    }
+}
+
+/// Lists recognized entries of the ZIP archive, so frontends can offer a
+/// choice between e.g. side A and side B tapes
+pub fn archive_entries(path: impl AsRef<Path>) -> Result<Vec<ArchiveEntry>, ZipError> {
+    Ok(ZipArchive::new(File::open(path)?)?.entries())
+}
+
+/// Unpacks entry `name` (as returned by [archive_entries]) of the ZIP archive
+pub fn load_archive_entry(path: impl AsRef<Path>, name: &str) -> Result<ZipAsset, ZipError> {
+    ZipArchive::new(File::open(path)?)?.load(name)
+}
+
+/// Unpacked ZIP archive entry
+pub struct ZipAsset {
+    buffer: BufferCursor<Vec<u8>>,
+}
+
+impl ZipAsset {
+    pub fn into_vec(self) -> Vec<u8> {
+        self.buffer.into_inner()
+    }
+}
+
+impl SeekableAsset for ZipAsset {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, IoError> {
+        self.buffer.seek(pos)
+    }
+}
+
+impl LoadableAsset for ZipAsset {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        self.buffer.read(buf)
+    }
+}
+
+impl DynamicAssetImpl for ZipAsset {}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
@@ -1,11 +1,27 @@
 #![no_std]
 
+extern crate alloc;
 #[cfg(feature = "std")]
 extern crate std;
 
+pub mod charset;
+#[cfg(feature = "std")]
+pub mod frame_timer;
+pub mod image;
+pub mod indexed;
 pub mod palette;
+pub mod printer;
 #[cfg(feature = "std")]
 pub mod stopwatch;
+pub mod text;
+#[cfg(feature = "std")]
+pub mod thumbnail;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "watch")]
+pub mod watch;
+#[cfg(feature = "std")]
+pub mod wav;
 
-#[cfg(all(feature = "std"))]
+#[cfg(feature = "std")]
 pub mod io;
@@ -1,5 +1,19 @@
+//! Palettes of the 16 standard colors. Colors are indexed as
+//! `color + brightness * 8`: 0-7 are normal colors and 8-15 are bright ones
+use core::fmt;
+
+/// Count of colors in the palette
+pub const PALETTE_SIZE: usize = 16;
+
+/// RGBA colors of the palette
+pub type PaletteRgba = [[u8; 4]; PALETTE_SIZE];
+/// RGB colors of the palette
+pub type PaletteRgb = [[u8; 3]; PALETTE_SIZE];
+
 pub mod rgba {
-    pub const ORIGINAL: [[u8; 4]; 16] = [
+    use super::PaletteRgba;
+
+    pub const ORIGINAL: PaletteRgba = [
         // normal
         0x000000FF_u32.to_be_bytes(),
         0x0000CDFF_u32.to_be_bytes(),
@@ -19,4 +33,407 @@ pub mod rgba {
         0xFFFF00FF_u32.to_be_bytes(),
         0xFFFFFFFF_u32.to_be_bytes(),
     ];
+
+    /// Colors distinguishable with deuteranopia (green-blind): hues are moved
+    /// apart along the blue-yellow axis and in lightness, so red and green do
+    /// not collapse
+    pub const DEUTERANOPIA: PaletteRgba = [
+        // normal
+        0x000000FF_u32.to_be_bytes(),
+        0x0A3C82FF_u32.to_be_bytes(),
+        0xFA6414FF_u32.to_be_bytes(),
+        0xD246D2FF_u32.to_be_bytes(),
+        0x056E41FF_u32.to_be_bytes(),
+        0x3282FFFF_u32.to_be_bytes(),
+        0xF0FF1EFF_u32.to_be_bytes(),
+        0xCDCDCDFF_u32.to_be_bytes(),
+        // bright
+        0x000000FF_u32.to_be_bytes(),
+        0x0C4BA2FF_u32.to_be_bytes(),
+        0xFF7D19FF_u32.to_be_bytes(),
+        0xFF58FFFF_u32.to_be_bytes(),
+        0x068A51FF_u32.to_be_bytes(),
+        0x3EA2FFFF_u32.to_be_bytes(),
+        0xFFFF26FF_u32.to_be_bytes(),
+        0xFFFFFFFF_u32.to_be_bytes(),
+    ];
+
+    /// Colors distinguishable with protanopia (red-blind): as [DEUTERANOPIA],
+    /// but red is shifted to ochre and magenta to violet, as protanopes see
+    /// reds darkened
+    pub const PROTANOPIA: PaletteRgba = [
+        // normal
+        0x000000FF_u32.to_be_bytes(),
+        0x284B82FF_u32.to_be_bytes(),
+        0xB46E19FF_u32.to_be_bytes(),
+        0x9141E6FF_u32.to_be_bytes(),
+        0x1EA073FF_u32.to_be_bytes(),
+        0x5F96FFFF_u32.to_be_bytes(),
+        0xCDDC32FF_u32.to_be_bytes(),
+        0xCDCDCDFF_u32.to_be_bytes(),
+        // bright
+        0x000000FF_u32.to_be_bytes(),
+        0x325EA2FF_u32.to_be_bytes(),
+        0xE18A1FFF_u32.to_be_bytes(),
+        0xB551FFFF_u32.to_be_bytes(),
+        0x26C890FF_u32.to_be_bytes(),
+        0x77BCFFFF_u32.to_be_bytes(),
+        0xFFFF3EFF_u32.to_be_bytes(),
+        0xFFFFFFFF_u32.to_be_bytes(),
+    ];
+
+    /// Colors distinguishable with tritanopia (blue-blind): hues are moved
+    /// apart along the red-green axis, cyan is shifted to mint and yellow to
+    /// salmon, so they do not collapse with green and white
+    pub const TRITANOPIA: PaletteRgba = [
+        // normal
+        0x000000FF_u32.to_be_bytes(),
+        0x1E239BFF_u32.to_be_bytes(),
+        0xE60000FF_u32.to_be_bytes(),
+        0xB41E9BFF_u32.to_be_bytes(),
+        0x0AAF37FF_u32.to_be_bytes(),
+        0x6EEBAFFF_u32.to_be_bytes(),
+        0xFF9178FF_u32.to_be_bytes(),
+        0xCDCDCDFF_u32.to_be_bytes(),
+        // bright
+        0x000000FF_u32.to_be_bytes(),
+        0x262CC2FF_u32.to_be_bytes(),
+        0xFF0000FF_u32.to_be_bytes(),
+        0xE126C2FF_u32.to_be_bytes(),
+        0x0CDB45FF_u32.to_be_bytes(),
+        0x8AFFDBFF_u32.to_be_bytes(),
+        0xFFB596FF_u32.to_be_bytes(),
+        0xFFFFFFFF_u32.to_be_bytes(),
+    ];
+}
+
+pub mod rgb {
+    use super::PaletteRgb;
+
+    /// Same colors as [super::rgba::ORIGINAL]
+    pub const ORIGINAL: PaletteRgb = super::to_rgb(&super::rgba::ORIGINAL);
+}
+
+/// Drops alpha component of the palette colors
+pub const fn to_rgb(palette: &PaletteRgba) -> PaletteRgb {
+    let mut rgb = [[0; 3]; PALETTE_SIZE];
+    let mut index = 0;
+    while index < PALETTE_SIZE {
+        let [r, g, b, _] = palette[index];
+        rgb[index] = [r, g, b];
+        index += 1;
+    }
+    rgb
+}
+
+/// Picture adjustments of the palette colors, e.g. to compensate for the
+/// display or to make the dark blue ink readable on black paper. Adjustments
+/// are applied to each color component in order: contrast around mid-gray,
+/// brightness offset, then gamma
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adjustments {
+    /// Offset of the component values, `-1.0..=1.0` of the full range
+    pub brightness: f32,
+    /// Multiplier of the component distance from mid-gray, `1.0` keeps it
+    pub contrast: f32,
+    /// Output gamma, values above `1.0` lighten dark colors. Should be positive
+    pub gamma: f32,
+}
+
+impl Default for Adjustments {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: 1.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl Adjustments {
+    /// Returns lookup table of the adjusted component values, indexed by the
+    /// source value. Default adjustments produce the identity table
+    pub fn lut(&self) -> [u8; 256] {
+        let mut lut = [0; 256];
+        for (value, adjusted) in lut.iter_mut().enumerate() {
+            let value = value as f32 / 255.0;
+            let value = ((value - 0.5) * self.contrast + 0.5 + self.brightness).clamp(0.0, 1.0);
+            let value = libm::powf(value, 1.0 / self.gamma);
+            *adjusted = libm::roundf(value * 255.0) as u8;
+        }
+        lut
+    }
+
+    /// Returns `palette` with the adjusted colors, alpha is kept
+    pub fn apply(&self, palette: &PaletteRgba) -> PaletteRgba {
+        let lut = self.lut();
+        palette.map(|[r, g, b, a]| [lut[r as usize], lut[g as usize], lut[b as usize], a])
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteError {
+    /// Line (1-based) is not a valid `R G B` triple
+    InvalidEntry { line: usize },
+    /// File does not contain exactly 16 colors
+    InvalidColorCount(usize),
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidEntry { line } => {
+                write!(f, "line {} is not a valid RGB triple", line)
+            }
+            Self::InvalidColorCount(count) => {
+                write!(f, "palette has {} colors, {} expected", count, PALETTE_SIZE)
+            }
+        }
+    }
+}
+
+/// Parses palette text with one color per line as three decimal `0..=255`
+/// components separated by spaces or commas (`205 0 205`), optionally
+/// followed by the color name. Empty lines and lines starting with `#` are
+/// ignored, as well as the header of GIMP `*.gpl` palettes, so these can be
+/// used directly. Exactly 16 colors are required
+pub fn parse_palette(text: &str) -> Result<PaletteRgba, PaletteError> {
+    let mut palette = [[0, 0, 0, 0xFF]; PALETTE_SIZE];
+    let mut count = 0;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let is_gpl_header =
+            line == "GIMP Palette" || line.starts_with("Name:") || line.starts_with("Columns:");
+        if line.is_empty() || line.starts_with('#') || is_gpl_header {
+            continue;
+        }
+        let invalid_entry = PaletteError::InvalidEntry { line: index + 1 };
+        let mut components = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|component| !component.is_empty());
+        let mut rgb = [0u8; 3];
+        for component in &mut rgb {
+            *component = components
+                .next()
+                .and_then(|value| value.parse().ok())
+                .ok_or(invalid_entry)?;
+        }
+        if let Some(color) = palette.get_mut(count) {
+            color[..3].copy_from_slice(&rgb);
+        }
+        count += 1;
+    }
+    if count != PALETTE_SIZE {
+        return Err(PaletteError::InvalidColorCount(count));
+    }
+    Ok(palette)
+}
+
+/// Loads palette file, see [parse_palette] for the format
+#[cfg(feature = "std")]
+pub fn load_palette_file(path: impl AsRef<std::path::Path>) -> std::io::Result<PaletteRgba> {
+    let text = std::fs::read_to_string(path)?;
+    parse_palette(&text)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, std::format!("{}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rgba::*, *};
+    use alloc::{format, string::String, vec::Vec};
+
+    fn palette_text(palette: &[[u8; 4]]) -> String {
+        palette
+            .iter()
+            .map(|[r, g, b, _]| format!("{} {} {}\n", r, g, b))
+            .collect()
+    }
+
+    #[test]
+    fn parse_original_palette() {
+        assert_eq!(parse_palette(&palette_text(&ORIGINAL)), Ok(ORIGINAL));
+    }
+
+    #[test]
+    fn parse_gimp_palette() {
+        let mut text = String::from("GIMP Palette\nName: Test\nColumns: 8\n#\n");
+        for index in 0..PALETTE_SIZE {
+            text += &format!(
+                "{:3} {:3} {:3}\tColor {}\n",
+                index,
+                index * 2,
+                index * 3,
+                index
+            );
+        }
+        let palette = parse_palette(&text).unwrap();
+        assert_eq!(palette[0], [0, 0, 0, 0xFF]);
+        // Bright blue
+        assert_eq!(palette[9], [9, 18, 27, 0xFF]);
+    }
+
+    #[test]
+    fn parse_comma_separated_palette() {
+        let text = palette_text(&ORIGINAL).replace(' ', ", ");
+        assert_eq!(parse_palette(&text), Ok(ORIGINAL));
+    }
+
+    #[test]
+    fn palette_color_count_is_validated() {
+        let text = palette_text(&ORIGINAL[..15]);
+        assert_eq!(
+            parse_palette(&text),
+            Err(PaletteError::InvalidColorCount(15))
+        );
+        let text = palette_text(&ORIGINAL) + "1 2 3\n";
+        assert_eq!(
+            parse_palette(&text),
+            Err(PaletteError::InvalidColorCount(17))
+        );
+    }
+
+    #[test]
+    fn palette_entries_are_validated() {
+        let text = palette_text(&ORIGINAL).replacen("205", "256", 1);
+        assert_eq!(
+            parse_palette(&text),
+            Err(PaletteError::InvalidEntry { line: 2 })
+        );
+        let text = palette_text(&ORIGINAL).replacen("0 0 205", "0 205", 1);
+        assert_eq!(
+            parse_palette(&text),
+            Err(PaletteError::InvalidEntry { line: 2 })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn load_palette_from_file() {
+        let path =
+            std::env::temp_dir().join(std::format!("rustzx_palette_{}.txt", std::process::id()));
+        std::fs::write(&path, palette_text(&ORIGINAL)).unwrap();
+        let palette = load_palette_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(palette.unwrap(), ORIGINAL);
+
+        let err = load_palette_file(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn default_adjustments_keep_colors() {
+        let lut = Adjustments::default().lut();
+        assert!(lut
+            .iter()
+            .enumerate()
+            .all(|(value, &adjusted)| adjusted as usize == value));
+        assert_eq!(Adjustments::default().apply(&ORIGINAL), ORIGINAL);
+    }
+
+    #[test]
+    fn adjustments_are_applied_to_components() {
+        let adjust = |brightness, contrast, gamma| {
+            Adjustments {
+                brightness,
+                contrast,
+                gamma,
+            }
+            .lut()
+        };
+        // Brightness is clamped to the component range
+        let brighter = adjust(0.2, 1.0, 1.0);
+        assert_eq!(
+            (brighter[0], brighter[0x80], brighter[0xFF]),
+            (51, 179, 0xFF)
+        );
+        // Contrast keeps mid-gray
+        let flat = adjust(0.0, 0.5, 1.0);
+        assert_eq!((flat[0], flat[0x80], flat[0xFF]), (64, 128, 191));
+        // Gamma keeps black and white, but lightens dark colors
+        let lighter = adjust(0.0, 1.0, 2.0);
+        assert_eq!((lighter[0], lighter[0x40], lighter[0xFF]), (0, 128, 0xFF));
+
+        let palette = Adjustments {
+            brightness: 0.2,
+            ..Default::default()
+        }
+        .apply(&ORIGINAL);
+        // Blue ink on black paper, alpha is kept
+        assert_eq!(palette[0], [51, 51, 51, 0xFF]);
+        assert_eq!(palette[1], [51, 51, 0xFF, 0xFF]);
+    }
+
+    /// Machado et al. (2009) simulation matrices of dichromacy in linear RGB
+    const PROTANOPIA_SIMULATION: [[f32; 3]; 3] = [
+        [0.152286, 1.052583, -0.204868],
+        [0.114503, 0.786281, 0.099216],
+        [-0.003882, -0.048116, 1.051998],
+    ];
+    const DEUTERANOPIA_SIMULATION: [[f32; 3]; 3] = [
+        [0.367322, 0.860646, -0.227968],
+        [0.280085, 0.672501, 0.047413],
+        [-0.011820, 0.042940, 0.968881],
+    ];
+    const TRITANOPIA_SIMULATION: [[f32; 3]; 3] = [
+        [1.255528, -0.076749, -0.178779],
+        [-0.078411, 0.930809, 0.147602],
+        [0.004733, 0.691367, 0.303900],
+    ];
+
+    /// Returns minimal distance between normal colors of the palette, as seen
+    /// with the simulated dichromacy
+    fn min_simulated_distance(palette: &PaletteRgba, simulation: &[[f32; 3]; 3]) -> f32 {
+        let to_linear = |c: u8| {
+            let c = c as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                libm::powf((c + 0.055) / 1.055, 2.4)
+            }
+        };
+        let to_srgb = |v: f32| {
+            let v = v.clamp(0.0, 1.0);
+            let v = if v <= 0.0031308 {
+                v * 12.92
+            } else {
+                1.055 * libm::powf(v, 1.0 / 2.4) - 0.055
+            };
+            v * 255.0
+        };
+        let simulated: Vec<[f32; 3]> = palette[..8]
+            .iter()
+            .map(|[r, g, b, _]| {
+                let linear = [to_linear(*r), to_linear(*g), to_linear(*b)];
+                simulation.map(|row| to_srgb(row.iter().zip(linear).map(|(m, c)| m * c).sum()))
+            })
+            .collect();
+        let mut min = f32::MAX;
+        for (index, a) in simulated.iter().enumerate() {
+            for b in &simulated[index + 1..] {
+                let distance = a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum::<f32>();
+                min = min.min(libm::sqrtf(distance));
+            }
+        }
+        min
+    }
+
+    #[test]
+    fn color_blind_presets_are_distinguishable() {
+        let presets = [
+            (&PROTANOPIA, &PROTANOPIA_SIMULATION),
+            (&DEUTERANOPIA, &DEUTERANOPIA_SIMULATION),
+            (&TRITANOPIA, &TRITANOPIA_SIMULATION),
+        ];
+        for (preset, simulation) in presets {
+            let original = min_simulated_distance(&ORIGINAL, simulation);
+            let adjusted = min_simulated_distance(preset, simulation);
+            assert!(adjusted > original * 2.0, "{} -> {}", original, adjusted);
+            // Bright colors are brighter, black stays black
+            assert_eq!(preset[0], preset[8]);
+            for index in 1..8 {
+                let sum = |color: [u8; 4]| color[..3].iter().map(|&c| c as u32).sum::<u32>();
+                assert!(sum(preset[index + 8]) > sum(preset[index]));
+            }
+        }
+    }
 }
@@ -0,0 +1,237 @@
+//! Conversion of the bytes sent to the printer into plain text. Spectrum
+//! printer drivers (e.g. Tasword, G+DOS, +3 BASIC `LLIST`) target Epson
+//! compatible printers, so their output mixes text with ESC/P control
+//! sequences for fonts, spacing and graphics
+use alloc::string::String;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+const ESC: u8 = 0x1B;
+const BS: u8 = 0x08;
+const HT: u8 = 0x09;
+const LF: u8 = 0x0A;
+const FF: u8 = 0x0C;
+const CR: u8 = 0x0D;
+const NUL: u8 = 0x00;
+
+/// Parsing state of the ESC/P stream
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum State {
+    #[default]
+    Text,
+    /// Command byte after ESC
+    Command,
+    /// Parameter bytes to skip
+    Skip(usize),
+    /// Skipping parameters until NUL (tab stops lists)
+    UntilNul,
+    /// Graphics mode byte of `ESC *`, or of `ESC ^` with the given bytes
+    /// per column
+    GraphicsMode(Option<usize>),
+    /// Two-byte little-endian count of data bytes, which is multiplied by
+    /// the given bytes per column
+    Count { bytes: usize, low: Option<u8> },
+    /// Parameter of `ESC C`, zero is followed by the page length in inches
+    PageLength,
+    /// Command byte of `ESC (` followed by the count of parameter bytes
+    ExtendedCommand,
+}
+
+/// Filters printer output into plain text. Epson ESC/P sequences (including
+/// bit image graphics data) and control codes are removed, line feeds and
+/// carriage returns become `'\n'`. Output may be passed in arbitrary chunks,
+/// as sequences split between them are tracked
+#[derive(Clone, Debug, Default)]
+pub struct EscpTextFilter {
+    state: State,
+    /// Last byte was CR, so the following LF is a part of the same line end
+    after_cr: bool,
+    /// Last byte was BS, so the following character is printed over the
+    /// previous one (underline or bold) and is dropped
+    overstrike: bool,
+}
+
+impl EscpTextFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns text of the next chunk of the printer output
+    pub fn push(&mut self, data: &[u8]) -> String {
+        let mut text = String::new();
+        for byte in data {
+            self.push_byte(*byte, &mut text);
+        }
+        text
+    }
+
+    fn push_byte(&mut self, byte: u8, text: &mut String) {
+        self.state = match self.state {
+            State::Text => {
+                let after_cr = core::mem::replace(&mut self.after_cr, byte == CR);
+                let overstrike = core::mem::take(&mut self.overstrike);
+                match byte {
+                    ESC => {
+                        self.state = State::Command;
+                        return;
+                    }
+                    CR | FF => text.push('\n'),
+                    LF if !after_cr => text.push('\n'),
+                    HT => text.push('\t'),
+                    BS => self.overstrike = true,
+                    0x20..=0x7E if !overstrike => text.push(byte as char),
+                    _ => {}
+                }
+                State::Text
+            }
+            State::Command => command_parameters(byte),
+            State::Skip(left) => skip(left - 1),
+            State::UntilNul if byte == NUL => State::Text,
+            State::UntilNul => State::UntilNul,
+            State::GraphicsMode(Some(bytes)) => State::Count { bytes, low: None },
+            State::GraphicsMode(None) => {
+                // 8-dot, 24-dot and 48-dot column modes
+                let bytes = match byte {
+                    0..=31 => 1,
+                    32..=63 => 3,
+                    _ => 6,
+                };
+                State::Count { bytes, low: None }
+            }
+            State::Count { bytes, low: None } => State::Count {
+                bytes,
+                low: Some(byte),
+            },
+            State::Count {
+                bytes,
+                low: Some(low),
+            } => skip(u16::from_le_bytes([low, byte]) as usize * bytes),
+            State::PageLength if byte == 0 => State::Skip(1),
+            State::PageLength => State::Text,
+            State::ExtendedCommand => State::Count {
+                bytes: 1,
+                low: None,
+            },
+        };
+    }
+}
+
+fn skip(count: usize) -> State {
+    if count == 0 {
+        State::Text
+    } else {
+        State::Skip(count)
+    }
+}
+
+/// Returns state after the ESC command byte
+fn command_parameters(command: u8) -> State {
+    match command {
+        b'*' => State::GraphicsMode(None),
+        b'^' => State::GraphicsMode(Some(2)),
+        b'K' | b'L' | b'Y' | b'Z' => State::Count {
+            bytes: 1,
+            low: None,
+        },
+        b'(' => State::ExtendedCommand,
+        b'C' => State::PageLength,
+        b'B' | b'D' | b'b' => State::UntilNul,
+        b'&' | b':' | b'X' => State::Skip(3),
+        b'$' | b'\\' | b'?' | b'c' | b'e' | b'f' => State::Skip(2),
+        b'!' | b'-' | b'3' | b'A' | b'J' | b'j' | b'Q' | b'l' | b'N' | b'W' | b'S' | b'x'
+        | b'k' | b'p' | b'U' | b'w' | b'R' | b't' | b'a' | b's' | b'q' | b'r' | b'I' | b'i'
+        | b'/' | b'%' | b'+' | b'\x19' => State::Skip(1),
+        _ => State::Text,
+    }
+}
+
+/// Format of the captured printer output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureMode {
+    /// Bytes as taken by the printer
+    Raw,
+    /// Plain text produced by [EscpTextFilter]
+    Text,
+}
+
+/// Sink of the printer output, writes it to `W` in the given capture mode
+#[cfg(feature = "std")]
+pub struct PrinterCapture<W: Write> {
+    writer: W,
+    filter: Option<EscpTextFilter>,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> PrinterCapture<W> {
+    pub fn new(writer: W, mode: CaptureMode) -> Self {
+        Self {
+            writer,
+            filter: (mode == CaptureMode::Text).then(EscpTextFilter::new),
+        }
+    }
+
+    /// Writes the next chunk of the printer output
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        match self.filter.as_mut() {
+            Some(filter) => self.writer.write_all(filter.push(data).as_bytes()),
+            None => self.writer.write_all(data),
+        }
+    }
+
+    /// Returns the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Listing printed in condensed bold mode with a bit image separator
+    const PRINTED: &[u8] = b"\x1B@\x1B!\x0C10 PRINT \"HI\"\r\n\
+        \x1BK\x04\x00\xFF\x81\x0D\xFF\r\n\
+        \x1B-\x0120 GO TO 10\x1B-\x00\r\n\x0C";
+
+    #[test]
+    fn escp_sequences_split_between_chunks() {
+        let mut filter = EscpTextFilter::new();
+        let mut text = String::new();
+        for chunk in PRINTED.chunks(3) {
+            text += &filter.push(chunk);
+        }
+        assert_eq!(text, "10 PRINT \"HI\"\n\n20 GO TO 10\n\n");
+    }
+
+    #[test]
+    fn escp_parameters_skipped() {
+        // Graphics data of 24-dot mode, page length in inches, tab stops
+        let mut filter = EscpTextFilter::new();
+        let text = filter.push(
+            b"A\x1B*\x27\x02\x00\x0D\x0A\x1B\x0D\x0A\x1B\x1BC\x00\x0BB\x1BD\x08\x10\x00\tC\rD\nE",
+        );
+        assert_eq!(text, "AB\tC\nD\nE");
+    }
+
+    #[test]
+    fn overstrike_keeps_first_character() {
+        let mut filter = EscpTextFilter::new();
+        assert_eq!(filter.push(b"A\x08_B\x08BC"), "ABC");
+        // Backspace split between chunks
+        assert_eq!(filter.push(b"D\x08"), "D");
+        assert_eq!(filter.push(b"_E"), "E");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn capture_modes() {
+        let mut raw = PrinterCapture::new(std::vec::Vec::new(), CaptureMode::Raw);
+        let mut text = PrinterCapture::new(std::vec::Vec::new(), CaptureMode::Text);
+        for chunk in PRINTED.chunks(5) {
+            raw.write(chunk).unwrap();
+            text.write(chunk).unwrap();
+        }
+        assert_eq!(raw.into_inner(), PRINTED);
+        assert_eq!(text.into_inner(), b"10 PRINT \"HI\"\n\n20 GO TO 10\n\n");
+    }
+}
@@ -0,0 +1,141 @@
+//! Recognition of the text printed on the ZX Spectrum screen by matching each
+//! 8x8 character cell against known glyphs. Useful for automated tests, e.g.
+//! checking that a test suite printed its summary or that a game reached its
+//! menu
+use crate::charset::{ADDR_SYSVAR_UDG, CHAR_SIZE, UDG_COUNT};
+use alloc::{string::String, vec::Vec};
+use rustzx_core::{host::Host, Emulator};
+
+/// Text rows of the screen
+pub const TEXT_ROWS: usize = 24;
+/// Text columns of the screen
+pub const TEXT_COLUMNS: usize = 32;
+/// Character returned for cells which do not match any known glyph
+pub const UNKNOWN_CHAR: char = '\u{FFFD}';
+/// Address of the CHARS system variable, which points 256 bytes below the
+/// current character set
+pub const ADDR_SYSVAR_CHARS: u16 = 0x5C36;
+/// Offset of the character set in 48K BASIC ROM
+const ROM_FONT_OFFSET: usize = 0x3D00;
+/// Count of printable characters (`' '..='©'`) in the character set
+const FONT_CHARS: usize = 96;
+/// First address after the ROM in the memory map
+const ROM_END: u16 = 0x4000;
+
+type Glyph = [u8; CHAR_SIZE];
+
+/// Known glyphs with their characters. When the same glyph is added twice,
+/// the first added character wins
+#[derive(Clone, Default)]
+pub struct GlyphSet {
+    glyphs: Vec<(Glyph, char)>,
+}
+
+impl GlyphSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns set with the character set of 48K BASIC ROM, taken from the
+    /// last ROM page of the emulated machine (works for both 48K and 128K)
+    pub fn rom<H: Host>(emulator: &Emulator<H>) -> Self {
+        let mut set = Self::new();
+        let page = (0..=u8::MAX)
+            .map_while(|page| emulator.rom_page(page))
+            .last();
+        if let Some(page) = page {
+            set.add_font(&page[ROM_FONT_OFFSET..ROM_FONT_OFFSET + FONT_CHARS * CHAR_SIZE]);
+        }
+        set
+    }
+
+    /// Returns set with the ROM character set, the RAM character set currently
+    /// pointed by CHARS system variable (if any) and the UDGs, which are
+    /// recognized as characters `'A'..='U'` in the same way as BASIC lists
+    /// them in `GRAPHICS` mode
+    pub fn from_emulator<H: Host>(emulator: &Emulator<H>) -> Self {
+        let mut set = Self::rom(emulator);
+        let chars = read_sysvar_word(emulator, ADDR_SYSVAR_CHARS).wrapping_add(256);
+        // ROM font is already added, and on 128K the paged ROM may be the
+        // editor ROM without any font at all
+        if chars >= ROM_END {
+            set.add_font(&peek_bytes(emulator, chars, FONT_CHARS * CHAR_SIZE));
+        }
+        let udg = read_sysvar_word(emulator, ADDR_SYSVAR_UDG);
+        set.add_glyphs(&peek_bytes(emulator, udg, UDG_COUNT * CHAR_SIZE), 'A');
+        set
+    }
+
+    /// Adds 96-character font in the Spectrum layout (starting from space,
+    /// 8 bytes per character). Character 0x7F is recognized as `'©'`
+    pub fn add_font(&mut self, font: &[u8]) {
+        self.add_glyphs(font, ' ');
+        if let Some(copyright) = self.glyphs.iter_mut().find(|(_, ch)| *ch == '\u{7F}') {
+            copyright.1 = '©';
+        }
+    }
+
+    /// Adds glyphs (8 bytes per character) for the consecutive characters
+    /// starting from `first`. Incomplete trailing glyph is ignored
+    pub fn add_glyphs(&mut self, data: &[u8], first: char) {
+        for (chunk, code) in data.chunks_exact(CHAR_SIZE).zip(first as u32..) {
+            let mut glyph = Glyph::default();
+            glyph.copy_from_slice(chunk);
+            if let Some(ch) = char::from_u32(code) {
+                if !self.glyphs.iter().any(|(known, _)| *known == glyph) {
+                    self.glyphs.push((glyph, ch));
+                }
+            }
+        }
+    }
+
+    /// Returns character for the cell bitmap. Inverse video cells (ink and
+    /// paper swapped) are matched too
+    pub fn recognize(&self, cell: &[u8; CHAR_SIZE]) -> Option<char> {
+        let inverse = cell.map(|byte| !byte);
+        self.glyphs
+            .iter()
+            .find(|(glyph, _)| glyph == cell)
+            .or_else(|| self.glyphs.iter().find(|(glyph, _)| *glyph == inverse))
+            .map(|(_, ch)| *ch)
+    }
+}
+
+fn read_sysvar_word<H: Host>(emulator: &Emulator<H>, addr: u16) -> u16 {
+    u16::from_le_bytes([emulator.peek(addr), emulator.peek(addr.wrapping_add(1))])
+}
+
+fn peek_bytes<H: Host>(emulator: &Emulator<H>, addr: u16, count: usize) -> Vec<u8> {
+    (0..count)
+        .map(|offset| emulator.peek(addr.wrapping_add(offset as u16)))
+        .collect()
+}
+
+/// Reads text from the screen bitmap (first 6144 bytes of the display file, as
+/// in `*.scr` files). Returns 24 lines of 32 characters, cells which do not
+/// match any glyph of `glyphs` are returned as [UNKNOWN_CHAR]. Display file
+/// shorter than bitmap is treated as zero-padded
+pub fn read_display_file_text(display_file: &[u8], glyphs: &GlyphSet) -> Vec<String> {
+    (0..TEXT_ROWS)
+        .map(|row| {
+            (0..TEXT_COLUMNS)
+                .map(|col| {
+                    let mut cell = Glyph::default();
+                    for (line, byte) in cell.iter_mut().enumerate() {
+                        // bitmap is split to 3 thirds of 8 character rows,
+                        // pixel lines of the character are 256 bytes apart
+                        let offset = ((row & 0x18) << 8) | (line << 8) | ((row & 0x07) << 5) | col;
+                        *byte = display_file.get(offset).copied().unwrap_or(0);
+                    }
+                    glyphs.recognize(&cell).unwrap_or(UNKNOWN_CHAR)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Reads text from the currently displayed screen, recognizing ROM font, the
+/// current CHARS font and UDGs, see [GlyphSet::from_emulator]
+pub fn read_screen_text<H: Host>(emulator: &Emulator<H>) -> Vec<String> {
+    read_display_file_text(emulator.display_file(), &GlyphSet::from_emulator(emulator))
+}
@@ -0,0 +1,125 @@
+//! Extraction of the screen image from snapshot, tape and screen files without
+//! emulation, e.g. for thumbnails in game launchers
+use crate::{
+    image::{RgbaImage, DISPLAY_FILE_SIZE},
+    io::GzipAsset,
+};
+use std::{fs::File, path::Path};
+
+const SNA_HEADER_SIZE: usize = 27;
+const SNA_48K_SIZE: usize = SNA_HEADER_SIZE + 3 * BANK_SIZE;
+const SNA_128K_PORT_7FFD_OFFSET: usize = SNA_48K_SIZE + 2;
+const SNA_128K_TAIL_BANKS_OFFSET: usize = SNA_48K_SIZE + 4;
+const SNA_128K_TAIL_BANKS: [usize; 6] = [0, 1, 3, 4, 6, 7];
+const BANK_SIZE: usize = 0x4000;
+const SHADOW_SCREEN_BANK: usize = 7;
+const SHADOW_SCREEN_MASK: u8 = 0x08;
+
+const TAP_FLAG_HEADER: u8 = 0x00;
+const TAP_FLAG_DATA: u8 = 0xFF;
+const TAP_HEADER_TYPE_CODE: u8 = 3;
+const TAP_HEADER_LENGTH: usize = 17;
+const SCREEN_ADDR: u16 = 0x4000;
+
+/// Returns 256x192 screen image of the `*.sna`, `*.tap` or `*.scr` file
+/// (optionally gzipped). Snapshot screen is decoded from its memory image,
+/// tape screen is the first `SCREEN$` code block (6912 bytes loaded to
+/// 16384). Returns `None` if the file can't be read or has no screen
+pub fn thumbnail(path: impl AsRef<Path>) -> Option<RgbaImage> {
+    let mut path = path.as_ref().to_path_buf();
+    let data = if has_extension(&path, "gz") {
+        let data = GzipAsset::new(File::open(&path).ok()?).ok()?.into_vec();
+        path.set_extension("");
+        data
+    } else {
+        std::fs::read(&path).ok()?
+    };
+
+    let display_file = if has_extension(&path, "sna") {
+        sna_display_file(&data)?
+    } else if has_extension(&path, "tap") {
+        tap_display_file(&data)?
+    } else if has_extension(&path, "scr") {
+        &data
+    } else {
+        return None;
+    };
+    RgbaImage::from_display_file(display_file)
+}
+
+/// Returns displayed screen memory of the 48K or 128K SNA snapshot
+pub fn sna_display_file(data: &[u8]) -> Option<&[u8]> {
+    if data.len() < SNA_48K_SIZE {
+        return None;
+    }
+    // 0x4000 (bank 5 on 128K) is the first bank in the snapshot
+    let normal_screen = &data[SNA_HEADER_SIZE..][..DISPLAY_FILE_SIZE];
+    if data.len() <= SNA_128K_TAIL_BANKS_OFFSET {
+        return Some(normal_screen);
+    }
+
+    let port_7ffd = data[SNA_128K_PORT_7FFD_OFFSET];
+    if port_7ffd & SHADOW_SCREEN_MASK == 0 {
+        return Some(normal_screen);
+    }
+    let paged_bank = (port_7ffd & 0x07) as usize;
+    let offset = if paged_bank == SHADOW_SCREEN_BANK {
+        SNA_HEADER_SIZE + 2 * BANK_SIZE
+    } else {
+        let index = SNA_128K_TAIL_BANKS
+            .iter()
+            .filter(|&&bank| bank != paged_bank)
+            .position(|&bank| bank == SHADOW_SCREEN_BANK)?;
+        SNA_128K_TAIL_BANKS_OFFSET + index * BANK_SIZE
+    };
+    data.get(offset..offset + DISPLAY_FILE_SIZE)
+}
+
+/// Returns data of the first code block on tape which is loaded as a screen
+/// (6912 bytes to 16384)
+pub fn tap_display_file(data: &[u8]) -> Option<&[u8]> {
+    let mut blocks = TapBlocks { data };
+    while let Some(block) = blocks.next() {
+        let is_screen_header = block.len() == TAP_HEADER_LENGTH + 2
+            && block[0] == TAP_FLAG_HEADER
+            && block[1] == TAP_HEADER_TYPE_CODE
+            && u16::from_le_bytes([block[12], block[13]]) as usize == DISPLAY_FILE_SIZE
+            && u16::from_le_bytes([block[14], block[15]]) == SCREEN_ADDR;
+        if !is_screen_header {
+            continue;
+        }
+        match blocks.next() {
+            Some(block) if block.len() == DISPLAY_FILE_SIZE + 2 && block[0] == TAP_FLAG_DATA => {
+                // skip flag and checksum
+                return Some(&block[1..=DISPLAY_FILE_SIZE]);
+            }
+            _ => continue,
+        }
+    }
+    None
+}
+
+/// Iterator over blocks of TAP file (including flag and checksum bytes)
+struct TapBlocks<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for TapBlocks<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.len() < 2 {
+            return None;
+        }
+        let length = u16::from_le_bytes([self.data[0], self.data[1]]) as usize;
+        let block = self.data.get(2..2 + length)?;
+        self.data = &self.data[2 + length..];
+        Some(block)
+    }
+}
+
+fn has_extension(path: &Path, expected: &str) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(expected))
+}
@@ -0,0 +1,106 @@
+//! Rendering of the emulator screen to the text with ANSI escape sequences,
+//! which can be printed directly to the terminal or CI log. Each character
+//! is an upper half block (`▀`) with foreground color set to the upper pixel
+//! and background color set to the lower pixel
+use crate::palette::rgba::ORIGINAL as DEFAULT_PALETTE;
+use alloc::string::String;
+use core::fmt::Write;
+
+const HALF_BLOCK: char = '▀';
+const RESET: &str = "\x1b[0m";
+
+/// Screen image with pixels represented as indices in 16-color palette
+/// (`color + brightness * 8`, same order as in [crate::palette::rgba::ORIGINAL])
+pub trait IndexedScreen {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn color_index(&self, x: usize, y: usize) -> u8;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnsiMode {
+    /// 24-bit colors, one character per 1x2 pixels
+    HalfBlocks,
+    /// 24-bit colors, one character per 2x4 pixels
+    HalfBlocksDownscaled,
+    /// 16 standard ANSI colors, one character per 2x4 pixels. Suitable for
+    /// terminals without true color support
+    Compact,
+}
+
+impl AnsiMode {
+    fn scale(self) -> usize {
+        match self {
+            Self::HalfBlocks => 1,
+            Self::HalfBlocksDownscaled | Self::Compact => 2,
+        }
+    }
+
+    fn write_color(self, out: &mut String, index: u8, background: bool) {
+        match self {
+            Self::HalfBlocks | Self::HalfBlocksDownscaled => {
+                let [r, g, b, _] = DEFAULT_PALETTE[index as usize];
+                let layer = if background { 48 } else { 38 };
+                let _ = write!(out, "\x1b[{};2;{};{};{}m", layer, r, g, b);
+            }
+            Self::Compact => {
+                // ZX color bits are GRB, ANSI color bits are BGR
+                let color = index & 0x07;
+                let ansi = ((color >> 1) & 0x01) | ((color >> 1) & 0x02) | ((color & 0x01) << 2);
+                // Bright black is still black on ZX Spectrum
+                let base = match (index >= 8 && color != 0, background) {
+                    (false, false) => 30,
+                    (false, true) => 40,
+                    (true, false) => 90,
+                    (true, true) => 100,
+                };
+                let _ = write!(out, "\x1b[{}m", base + ansi);
+            }
+        }
+    }
+}
+
+/// Returns color of the downscaled pixel. Least frequent color of the block is
+/// chosen, so thin ink lines (e.g. text) are still visible after downscaling
+fn sample(screen: &impl IndexedScreen, x: usize, y: usize, scale: usize) -> u8 {
+    let mut counts = [0u8; 16];
+    for by in y..(y + scale).min(screen.height()) {
+        for bx in x..(x + scale).min(screen.width()) {
+            counts[screen.color_index(bx, by) as usize] += 1;
+        }
+    }
+    (0..16u8)
+        .filter(|&index| counts[index as usize] != 0)
+        .min_by_key(|&index| counts[index as usize])
+        .unwrap_or(0)
+}
+
+/// Renders screen to the string with ANSI-colored half blocks, one line of
+/// text per two pixel rows (per four rows in downscaled modes)
+pub fn render_ansi(screen: &impl IndexedScreen, mode: AnsiMode) -> String {
+    let scale = mode.scale();
+    let mut out = String::new();
+    for y in (0..screen.height()).step_by(scale * 2) {
+        let mut last_colors = None;
+        for x in (0..screen.width()).step_by(scale) {
+            let upper = sample(screen, x, y, scale);
+            let lower = if y + scale < screen.height() {
+                sample(screen, x, y + scale, scale)
+            } else {
+                upper
+            };
+            let (last_upper, last_lower) = last_colors.unzip();
+            if last_upper != Some(upper) {
+                mode.write_color(&mut out, upper, false);
+            }
+            if last_lower != Some(lower) {
+                mode.write_color(&mut out, lower, true);
+            }
+            last_colors = Some((upper, lower));
+            out.push(HALF_BLOCK);
+        }
+        out.push_str(RESET);
+        out.push('\n');
+    }
+    out
+}
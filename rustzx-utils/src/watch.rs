@@ -0,0 +1,67 @@
+//! Automatic reload of the development binary when its file is modified,
+//! see [rustzx_core::Emulator::reload_binary]. File is polled by its
+//! modification time, so no platform-specific notification API is required
+use rustzx_core::{error::IoError, host::Host, Emulator, ReloadMode};
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::io::FileAsset;
+
+/// Watches binary file and reloads it into the emulator on modification
+pub struct BinaryWatcher {
+    path: PathBuf,
+    addr: u16,
+    mode: ReloadMode,
+    /// Modification time of the last reloaded file version
+    reloaded: Option<SystemTime>,
+    /// Modification time seen on the last poll
+    last_seen: Option<SystemTime>,
+}
+
+impl BinaryWatcher {
+    /// Creates watcher for the binary loaded at `addr`. Current version of
+    /// the file is considered already loaded
+    pub fn new(path: impl AsRef<Path>, addr: u16, mode: ReloadMode) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let modified = modification_time(&path);
+        Self {
+            path,
+            addr,
+            mode,
+            reloaded: modified,
+            last_seen: modified,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Checks the file and reloads it if it was modified. Reload is
+    /// requested only when modification time did not change since the
+    /// previous poll, so the file which is still being written by the
+    /// compiler is not loaded partially. Returns true if reload was requested
+    pub fn poll<H: Host>(&mut self, emulator: &mut Emulator<H>) -> rustzx_core::Result<bool> {
+        let modified = modification_time(&self.path);
+        let settled = modified.is_some() && modified == self.last_seen;
+        self.last_seen = modified;
+        if !settled || modified == self.reloaded {
+            return Ok(false);
+        }
+        self.reloaded = modified;
+        let file = File::open(&self.path).map_err(|e| {
+            log::error!("Failed to open {}: {}", self.path.display(), e);
+            IoError::HostAssetImplFailed
+        })?;
+        emulator.reload_binary(FileAsset::from(file), self.addr, self.mode)?;
+        log::info!("Reloading {}", self.path.display());
+        Ok(true)
+    }
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
@@ -0,0 +1,129 @@
+//! Recording of the emulator sound to 16-bit PCM `*.wav` files, e.g. for
+//! headless captures or sound regression tests
+use rustzx_core::zx::sound::sample::SoundSample;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+    vec::Vec,
+};
+
+/// Size of the RIFF header with `fmt ` and `data` chunk headers
+pub const WAV_HEADER_SIZE: usize = 44;
+const WAV_FORMAT_PCM: u16 = 1;
+const BITS_PER_SAMPLE: u16 = 16;
+/// Offset of the RIFF chunk size field
+const RIFF_SIZE_OFFSET: u64 = 4;
+/// Offset of the `data` chunk size field
+const DATA_SIZE_OFFSET: u64 = 40;
+/// RIFF sizes are 32-bit, samples beyond this limit are dropped
+const MAX_DATA_SIZE: u64 = u32::MAX as u64 - WAV_HEADER_SIZE as u64;
+
+/// Writes sound samples to the WAV stream. Header sizes are written when the
+/// sink is finished via [WavSink::finish] or dropped, so the file is valid
+/// even if the capture was interrupted by closing the frontend
+pub struct WavSink<W: Write + Seek> {
+    writer: Option<W>,
+    channels: u16,
+    data_size: u64,
+}
+
+impl WavSink<BufWriter<File>> {
+    /// Creates `*.wav` file with the given sample rate and channel count (1
+    /// channel is mixed down from stereo samples)
+    pub fn new(path: impl AsRef<Path>, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        Self::from_writer(BufWriter::new(File::create(path)?), sample_rate, channels)
+    }
+}
+
+impl<W: Write + Seek> WavSink<W> {
+    pub fn from_writer(mut writer: W, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        if !(1..=2).contains(&channels) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only mono and stereo WAV files are supported",
+            ));
+        }
+        let block_align = channels * BITS_PER_SAMPLE / 8;
+        let mut header = Vec::with_capacity(WAV_HEADER_SIZE);
+        header.extend_from_slice(b"RIFF");
+        // RIFF and data sizes are written on finish
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&WAV_FORMAT_PCM.to_le_bytes());
+        header.extend_from_slice(&channels.to_le_bytes());
+        header.extend_from_slice(&sample_rate.to_le_bytes());
+        header.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        header.extend_from_slice(&BITS_PER_SAMPLE.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&0u32.to_le_bytes());
+        writer.write_all(&header)?;
+
+        Ok(Self {
+            writer: Some(writer),
+            channels,
+            data_size: 0,
+        })
+    }
+
+    /// Writes single sample, `-1.0..=1.0` range is mapped to the full range
+    /// of 16-bit PCM, values out of the range are saturated
+    pub fn write_sample(&mut self, sample: SoundSample<f32>) -> io::Result<()> {
+        let block_align = (self.channels * BITS_PER_SAMPLE / 8) as u64;
+        if self.data_size + block_align > MAX_DATA_SIZE {
+            return Ok(());
+        }
+        let writer = match self.writer.as_mut() {
+            Some(writer) => writer,
+            None => return Ok(()),
+        };
+        if self.channels == 1 {
+            writer.write_all(&to_pcm((sample.left + sample.right) / 2.0).to_le_bytes())?;
+        } else {
+            writer.write_all(&to_pcm(sample.left).to_le_bytes())?;
+            writer.write_all(&to_pcm(sample.right).to_le_bytes())?;
+        }
+        self.data_size += block_align;
+        Ok(())
+    }
+
+    /// Returns count of the written samples per channel
+    pub fn samples_written(&self) -> u64 {
+        self.data_size / (self.channels * BITS_PER_SAMPLE / 8) as u64
+    }
+
+    /// Writes header sizes and returns underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.finalize()?;
+        Ok(self.writer.take().expect("WAV writer is taken only once"))
+    }
+
+    fn finalize(&mut self) -> io::Result<()> {
+        let writer = match self.writer.as_mut() {
+            Some(writer) => writer,
+            None => return Ok(()),
+        };
+        let riff_size = (self.data_size + WAV_HEADER_SIZE as u64 - 8) as u32;
+        writer.seek(SeekFrom::Start(RIFF_SIZE_OFFSET))?;
+        writer.write_all(&riff_size.to_le_bytes())?;
+        writer.seek(SeekFrom::Start(DATA_SIZE_OFFSET))?;
+        writer.write_all(&(self.data_size as u32).to_le_bytes())?;
+        writer.seek(SeekFrom::End(0))?;
+        writer.flush()
+    }
+}
+
+impl<W: Write + Seek> Drop for WavSink<W> {
+    fn drop(&mut self) {
+        if let Err(e) = self.finalize() {
+            log::error!("Failed to finalize WAV file: {}", e);
+        }
+    }
+}
+
+fn to_pcm(value: f32) -> i16 {
+    // float to int cast saturates
+    (value * i16::MAX as f32) as i16
+}
@@ -63,8 +63,16 @@ pub trait Z80Bus {
     fn int_active(&self) -> bool;
     /// Checks nmi signal
     fn nmi_active(&self) -> bool;
+    /// Method, invoked by Z80 when NMI was accepted, before the jump to the
+    /// handler. Edge-triggered NMI sources should clear their request here.
+    /// Default implementation is empty
+    fn nmi_accepted(&mut self) {}
     /// invokes breakpoints check on bus device
     fn pc_callback(&mut self, addr: u16);
+    /// Method, invoked by Z80 when maskable or non-maskable interrupt was
+    /// accepted, after the jump to the handler. `return_addr` was pushed to
+    /// the stack at `sp`. Default implementation is empty
+    fn interrupt_accepted(&mut self, _return_addr: u16, _sp: u16) {}
     fn process_unknown_opcode(&mut self, _prefix: Prefix, _opcode: Opcode) {}
 }
 
@@ -0,0 +1,92 @@
+//! Opcode execution counters, used to find instructions not exercised by test suites
+
+use crate::Prefix;
+
+const TABLE_SIZE: usize = 256;
+
+/// Per-opcode execution counters. Each table is indexed by the opcode byte
+/// which follows the corresponding prefix sequence
+#[derive(Clone)]
+pub struct OpcodeCoverage {
+    /// Non-prefixed opcodes
+    pub normal: [u64; TABLE_SIZE],
+    /// `CB xx` opcodes
+    pub cb: [u64; TABLE_SIZE],
+    /// `ED xx` opcodes
+    pub ed: [u64; TABLE_SIZE],
+    /// `DD xx` opcodes
+    pub dd: [u64; TABLE_SIZE],
+    /// `FD xx` opcodes
+    pub fd: [u64; TABLE_SIZE],
+    /// `DD CB dd xx` opcodes
+    pub ddcb: [u64; TABLE_SIZE],
+    /// `FD CB dd xx` opcodes
+    pub fdcb: [u64; TABLE_SIZE],
+}
+
+impl Default for OpcodeCoverage {
+    fn default() -> Self {
+        Self {
+            normal: [0; TABLE_SIZE],
+            cb: [0; TABLE_SIZE],
+            ed: [0; TABLE_SIZE],
+            dd: [0; TABLE_SIZE],
+            fd: [0; TABLE_SIZE],
+            ddcb: [0; TABLE_SIZE],
+            fdcb: [0; TABLE_SIZE],
+        }
+    }
+}
+
+impl OpcodeCoverage {
+    /// Records execution of non-prefixed or `DD`/`FD` prefixed opcode
+    pub(crate) fn record_normal(&mut self, prefix: Prefix, opcode: u8) {
+        let table = match prefix {
+            Prefix::DD => &mut self.dd,
+            Prefix::FD => &mut self.fd,
+            _ => &mut self.normal,
+        };
+        table[opcode as usize] += 1;
+    }
+
+    /// Records execution of `CB`, `DDCB` or `FDCB` opcode
+    pub(crate) fn record_bits(&mut self, prefix: Prefix, opcode: u8) {
+        let table = match prefix {
+            Prefix::DD => &mut self.ddcb,
+            Prefix::FD => &mut self.fdcb,
+            _ => &mut self.cb,
+        };
+        table[opcode as usize] += 1;
+    }
+
+    /// Records execution of `ED` opcode
+    pub(crate) fn record_extended(&mut self, opcode: u8) {
+        self.ed[opcode as usize] += 1;
+    }
+
+    /// Returns all tables paired with their prefix bytes
+    pub fn tables(&self) -> [(&'static [u8], &[u64; TABLE_SIZE]); 7] {
+        [
+            (&[], &self.normal),
+            (&[0xCB], &self.cb),
+            (&[0xED], &self.ed),
+            (&[0xDD], &self.dd),
+            (&[0xFD], &self.fd),
+            (&[0xDD, 0xCB], &self.ddcb),
+            (&[0xFD, 0xCB], &self.fdcb),
+        ]
+    }
+
+    /// Returns count of distinct opcodes which were executed at least once
+    pub fn covered_count(&self) -> usize {
+        self.tables()
+            .iter()
+            .map(|(_, table)| table.iter().filter(|count| **count != 0).count())
+            .sum()
+    }
+
+    /// Resets all counters to zero
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
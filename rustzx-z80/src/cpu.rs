@@ -8,6 +8,9 @@ use crate::{
     RegName16, Regs, Z80Bus,
 };
 
+#[cfg(feature = "opcode-coverage")]
+use crate::OpcodeCoverage;
+
 /// Interrupt mode enum
 #[derive(Debug, Clone, Copy)]
 pub enum IntMode {
@@ -37,6 +40,8 @@ pub struct Z80 {
     /// type of interrupt
     pub(crate) int_mode: IntMode,
     active_prefix: Prefix,
+    #[cfg(feature = "opcode-coverage")]
+    pub(crate) coverage: OpcodeCoverage,
 }
 
 impl Default for Z80 {
@@ -47,6 +52,8 @@ impl Default for Z80 {
             skip_interrupt: false,
             int_mode: IntMode::Im0,
             active_prefix: Prefix::None,
+            #[cfg(feature = "opcode-coverage")]
+            coverage: OpcodeCoverage::default(),
         }
     }
 }
@@ -77,6 +84,27 @@ impl Z80 {
         self.halted
     }
 
+    /// Checks if prefix chain (e.g. `DD FD`) was interrupted in the middle and
+    /// the next emulation step will continue the same instruction
+    pub fn is_prefix_pending(&self) -> bool {
+        self.active_prefix != Prefix::None
+    }
+
+    /// Returns prefix byte which was already fetched, but not executed yet,
+    /// see [Z80::is_prefix_pending]
+    pub fn pending_prefix(&self) -> Option<u8> {
+        self.active_prefix.to_byte()
+    }
+
+    /// Resets CPU to the power-on state, opcode coverage is kept
+    pub fn reset(&mut self) {
+        *self = Self {
+            #[cfg(feature = "opcode-coverage")]
+            coverage: core::mem::take(&mut self.coverage),
+            ..Default::default()
+        };
+    }
+
     /// Returns current interrupt mode
     pub fn get_im(&self) -> IntMode {
         self.int_mode
@@ -105,8 +133,21 @@ impl Z80 {
         execute_push_16(self, bus, RegName16::PC, 0);
     }
 
+    /// Returns per-opcode execution counters
+    #[cfg(feature = "opcode-coverage")]
+    pub fn opcode_coverage(&self) -> &OpcodeCoverage {
+        &self.coverage
+    }
+
+    /// Resets per-opcode execution counters, e.g. between test runs
+    #[cfg(feature = "opcode-coverage")]
+    pub fn reset_opcode_coverage(&mut self) {
+        self.coverage.reset();
+    }
+
     fn handle_interrupt(&mut self, bus: &mut impl Z80Bus) {
         if bus.nmi_active() {
+            bus.nmi_accepted();
             // q resets during interrupt
             self.regs.clear_q();
             // Release halt line on the bus
@@ -116,11 +157,13 @@ impl Z80 {
                 self.regs.inc_pc();
             }
             // push pc and set pc to 0x0066
-            bus.wait_loop(self.regs.get_pc(), 5);
+            let return_addr = self.regs.get_pc();
+            bus.wait_loop(return_addr, 5);
             self.regs.set_iff1(false);
             // 3 x 2 clocks consumed
             execute_push_16(self, bus, RegName16::PC, 3);
             self.regs.set_pc(0x0066);
+            bus.interrupt_accepted(return_addr, self.regs.get_sp());
 
             // mem_ptr is set to PC
             self.regs.set_mem_ptr(self.regs.get_pc());
@@ -139,6 +182,7 @@ impl Z80 {
             self.regs.inc_r();
             self.regs.set_iff1(false);
             self.regs.set_iff2(false);
+            let return_addr = self.regs.get_pc();
             match self.int_mode {
                 // For zx spectrum both Im0 and Im1 are same
                 IntMode::Im0 | IntMode::Im1 => {
@@ -162,6 +206,7 @@ impl Z80 {
             }
             // mem_ptr is set to PC
             self.regs.set_mem_ptr(self.regs.get_pc());
+            bus.interrupt_accepted(return_addr, self.regs.get_sp());
         }
     }
 
@@ -208,6 +253,8 @@ impl Z80 {
                         }
                         Prefix::None => {
                             let opcode = Opcode::from_byte(byte2);
+                            #[cfg(feature = "opcode-coverage")]
+                            self.coverage.record_normal(prefix_single, byte2);
                             before_execute_opcode(self);
                             execute_normal(self, bus, opcode, prefix_single);
                         }
@@ -222,6 +269,8 @@ impl Z80 {
                     let byte2 = self.fetch_byte(bus, 4);
                     self.regs.inc_r();
                     let opcode = Opcode::from_byte(byte2);
+                    #[cfg(feature = "opcode-coverage")]
+                    self.coverage.record_extended(byte2);
                     before_execute_opcode(self);
                     execute_extended(self, bus, opcode);
                 }
@@ -229,6 +278,8 @@ impl Z80 {
             };
         } else {
             let opcode = Opcode::from_byte(byte1);
+            #[cfg(feature = "opcode-coverage")]
+            self.coverage.record_normal(Prefix::None, byte1);
             before_execute_opcode(self);
             execute_normal(self, bus, opcode, Prefix::None);
         };
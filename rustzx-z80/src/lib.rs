@@ -4,6 +4,8 @@
 
 mod bus;
 mod codegen;
+#[cfg(feature = "opcode-coverage")]
+mod coverage;
 mod cpu;
 mod opcode;
 mod registers;
@@ -12,6 +14,8 @@ mod tables;
 
 pub use bus::Z80Bus;
 pub use codegen::{CodeGenerator, CodegenMemorySpace};
+#[cfg(feature = "opcode-coverage")]
+pub use coverage::OpcodeCoverage;
 pub use cpu::{IntMode, Z80};
 pub use opcode::{Opcode, Prefix};
 pub use registers::{
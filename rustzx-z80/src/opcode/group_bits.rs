@@ -31,6 +31,9 @@ pub fn execute_bits(cpu: &mut Z80, bus: &mut impl Z80Bus, prefix: Prefix) {
         (opcode, BitOperand8::Indirect(addr))
     };
 
+    #[cfg(feature = "opcode-coverage")]
+    cpu.coverage.record_bits(prefix, opcode.byte);
+
     let result = match opcode.x {
         // Rotate group. 0x00...0x3F
         U2::N0 => execute_rot(cpu, bus, opcode.y, operand),
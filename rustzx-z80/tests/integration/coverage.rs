@@ -0,0 +1,36 @@
+use crate::TestingBus;
+use rustzx_z80::Z80;
+
+#[test]
+fn opcode_coverage_counts_prefixed_groups() {
+    let program = [
+        0x00, // NOP
+        0x00, // NOP
+        0xCB, 0x07, // RLC A
+        0xED, 0x44, // NEG
+        0xDD, 0x23, // INC IX
+        0xFD, 0xCB, 0x01, 0xC6, // SET 0, (IY+1)
+        0xDD, 0xFD, 0x23, // Chained prefixes: INC IY
+    ];
+
+    let mut cpu = Z80::default();
+    let mut bus = TestingBus::new(0x10000);
+    bus.load_to_memory(&program, 0);
+    cpu.regs.set_iy(0x8000);
+
+    while cpu.regs.get_pc() < program.len() as u16 {
+        cpu.emulate(&mut bus);
+    }
+
+    let coverage = cpu.opcode_coverage();
+    assert_eq!(coverage.normal[0x00], 2);
+    assert_eq!(coverage.cb[0x07], 1);
+    assert_eq!(coverage.ed[0x44], 1);
+    assert_eq!(coverage.dd[0x23], 1);
+    assert_eq!(coverage.fd[0x23], 1);
+    assert_eq!(coverage.fdcb[0xC6], 1);
+    assert_eq!(coverage.covered_count(), 6);
+
+    cpu.reset_opcode_coverage();
+    assert_eq!(cpu.opcode_coverage().covered_count(), 0);
+}
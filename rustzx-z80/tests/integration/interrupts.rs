@@ -0,0 +1,99 @@
+use crate::TestingBus;
+use rustzx_z80::Z80;
+
+const INT_HANDLER_ADDRESS: u16 = 0x0038;
+
+fn setup(program: &[u8]) -> (Z80, TestingBus) {
+    let mut cpu = Z80::default();
+    let mut bus = TestingBus::new(0x10000);
+    bus.load_to_memory(program, 0);
+    cpu.regs.set_sp(0x8000);
+    (cpu, bus)
+}
+
+#[test]
+fn int_line_accepted_when_enabled() {
+    // IM 1; EI; NOP; NOP
+    let (mut cpu, mut bus) = setup(&[0xED, 0x56, 0xFB, 0x00, 0x00]);
+    cpu.emulate(&mut bus);
+    bus.set_int_line(true);
+    // EI and the following instruction are executed without interruption
+    cpu.emulate(&mut bus);
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_pc(), 0x0004);
+    // Interrupt accepted, next NOP is executed from the handler
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_pc(), INT_HANDLER_ADDRESS + 1);
+    assert!(!cpu.regs.get_iff1());
+}
+
+#[test]
+fn int_line_ignored_when_disabled() {
+    // IM 1; DI; NOP; NOP
+    let (mut cpu, mut bus) = setup(&[0xED, 0x56, 0xF3, 0x00, 0x00]);
+    bus.set_int_line(true);
+    for _ in 0..4 {
+        cpu.emulate(&mut bus);
+    }
+    assert_eq!(cpu.regs.get_pc(), 0x0005);
+}
+
+fn stack_top(cpu: &Z80, bus: &mut TestingBus) -> u16 {
+    let sp = cpu.regs.get_sp();
+    u16::from_le_bytes([bus.read_memory(sp), bus.read_memory(sp + 1)])
+}
+
+#[test]
+fn int_deferred_until_instruction_after_ei() {
+    // IM 1; EI; LD A, 0x42; NOP
+    let (mut cpu, mut bus) = setup(&[0xED, 0x56, 0xFB, 0x3E, 0x42, 0x00]);
+    // INT is already asserted when EI is executed
+    bus.set_int_line(true);
+    cpu.emulate(&mut bus);
+    cpu.emulate(&mut bus);
+    assert!(cpu.regs.get_iff1());
+    assert_eq!(cpu.regs.get_pc(), 0x0003);
+    // Instruction after EI completes before the interrupt is accepted
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_pc(), 0x0005);
+    assert_eq!(cpu.regs.get_acc(), 0x42);
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_pc(), INT_HANDLER_ADDRESS + 1);
+    assert_eq!(stack_top(&cpu, &mut bus), 0x0005);
+}
+
+#[test]
+fn ei_reti_returns_before_next_int() {
+    // Interrupt handler tail: EI; RETI
+    let (mut cpu, mut bus) = setup(&[0xED, 0x56]);
+    bus.load_to_memory(&[0xFB, 0xED, 0x4D], INT_HANDLER_ADDRESS);
+    cpu.emulate(&mut bus);
+    cpu.regs.set_pc(INT_HANDLER_ADDRESS);
+    // Return address 0x1234 is on the stack
+    cpu.regs.set_sp(0x7FFE);
+    bus.load_to_memory(&[0x34, 0x12], 0x7FFE);
+    bus.set_int_line(true);
+    cpu.emulate(&mut bus);
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_pc(), 0x1234);
+    assert_eq!(cpu.regs.get_sp(), 0x8000);
+    // Next interrupt is accepted only after RETI, so the stack does not grow
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_pc(), INT_HANDLER_ADDRESS + 1);
+    assert_eq!(cpu.regs.get_sp(), 0x7FFE);
+    assert_eq!(stack_top(&cpu, &mut bus), 0x1234);
+}
+
+#[test]
+fn consecutive_ei_keep_int_deferred() {
+    // IM 1; EI; EI; EI; NOP; NOP
+    let (mut cpu, mut bus) = setup(&[0xED, 0x56, 0xFB, 0xFB, 0xFB, 0x00, 0x00]);
+    bus.set_int_line(true);
+    for _ in 0..5 {
+        cpu.emulate(&mut bus);
+    }
+    assert_eq!(cpu.regs.get_pc(), 0x0006);
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_pc(), INT_HANDLER_ADDRESS + 1);
+    assert_eq!(stack_top(&cpu, &mut bus), 0x0006);
+}
@@ -1,3 +1,8 @@
+#[cfg(feature = "opcode-coverage")]
+mod coverage;
+mod interrupts;
+mod prefixes;
+mod registers;
 mod zexall;
 
 use rustzx_z80::Z80Bus;
@@ -7,14 +12,18 @@ pub struct TestingBus {
     memory: Vec<u8>,
     breakpoints: HashSet<u16>,
     last_breakpoint: Option<u16>,
+    clocks: usize,
+    int_line: bool,
 }
 
 impl TestingBus {
     pub fn new(memory_size: usize) -> Self {
         Self {
-            memory: vec![0; memory_size as usize],
+            memory: vec![0; memory_size],
             breakpoints: Default::default(),
             last_breakpoint: None,
+            clocks: 0,
+            int_line: false,
         }
     }
 
@@ -39,6 +48,16 @@ impl TestingBus {
     pub fn last_breakpoint(&mut self) -> Option<u16> {
         self.last_breakpoint.take()
     }
+
+    /// Returns T-states passed since the bus creation
+    pub fn clocks(&self) -> usize {
+        self.clocks
+    }
+
+    /// Changes state of the INT line
+    pub fn set_int_line(&mut self, active: bool) {
+        self.int_line = active;
+    }
 }
 
 impl Z80Bus for TestingBus {
@@ -62,11 +81,17 @@ impl Z80Bus for TestingBus {
 
     fn write_io(&mut self, _port: u16, _data: u8) {}
 
-    fn wait_mreq(&mut self, _addr: u16, _clk: usize) {}
+    fn wait_mreq(&mut self, _addr: u16, clk: usize) {
+        self.clocks += clk;
+    }
 
-    fn wait_no_mreq(&mut self, _addr: u16, _clk: usize) {}
+    fn wait_no_mreq(&mut self, _addr: u16, clk: usize) {
+        self.clocks += clk;
+    }
 
-    fn wait_internal(&mut self, _clk: usize) {}
+    fn wait_internal(&mut self, clk: usize) {
+        self.clocks += clk;
+    }
 
     fn read_interrupt(&mut self) -> u8 {
         0
@@ -77,7 +102,7 @@ impl Z80Bus for TestingBus {
     fn halt(&mut self, _halted: bool) {}
 
     fn int_active(&self) -> bool {
-        false
+        self.int_line
     }
 
     fn nmi_active(&self) -> bool {
@@ -0,0 +1,131 @@
+use crate::TestingBus;
+use rustzx_z80::Z80;
+
+fn setup(program: &[u8]) -> (Z80, TestingBus) {
+    let mut cpu = Z80::default();
+    let mut bus = TestingBus::new(0x10000);
+    bus.load_to_memory(program, 0);
+    cpu.regs.set_sp(0x8000);
+    (cpu, bus)
+}
+
+/// Emulates until PC reaches `pc`, returns count of emulation steps
+fn run_to(cpu: &mut Z80, bus: &mut TestingBus, pc: u16) -> usize {
+    let mut steps = 0;
+    while cpu.regs.get_pc() != pc || cpu.is_prefix_pending() {
+        cpu.emulate(bus);
+        steps += 1;
+        assert!(steps < 100, "PC 0x{:04X} was not reached", pc);
+    }
+    steps
+}
+
+#[test]
+fn dd_fd_chain_uses_last_prefix() {
+    // DD FD 21 34 12: LD IY, 0x1234 with the ignored DD prefix
+    let (mut cpu, mut bus) = setup(&[0xDD, 0xFD, 0x21, 0x34, 0x12]);
+    cpu.regs.set_ix(0xAAAA);
+    cpu.emulate(&mut bus);
+    // Chain is interrupted after DD, which behaves as NOP
+    assert!(cpu.is_prefix_pending());
+    assert_eq!(cpu.pending_prefix(), Some(0xFD));
+    cpu.emulate(&mut bus);
+    assert!(!cpu.is_prefix_pending());
+    assert_eq!(cpu.regs.get_pc(), 5);
+    assert_eq!(cpu.regs.get_iy(), 0x1234);
+    assert_eq!(cpu.regs.get_ix(), 0xAAAA);
+    assert_eq!(cpu.regs.get_hl(), 0);
+    // 14 T-states of LD IY, nn and 4 T-states of DD
+    assert_eq!(bus.clocks(), 18);
+    assert_eq!(cpu.regs.get_r(), 3);
+}
+
+#[test]
+fn repeated_prefixes_take_4_clocks_each() {
+    // DD DD DD 23: INC IX
+    let (mut cpu, mut bus) = setup(&[0xDD, 0xDD, 0xDD, 0x23]);
+    run_to(&mut cpu, &mut bus, 4);
+    assert_eq!(cpu.regs.get_ix(), 1);
+    assert_eq!(bus.clocks(), 10 + 2 * 4);
+    assert_eq!(cpu.regs.get_r(), 4);
+
+    // FD DD 23: INC IX, the last prefix wins
+    let (mut cpu, mut bus) = setup(&[0xFD, 0xDD, 0x23]);
+    run_to(&mut cpu, &mut bus, 3);
+    assert_eq!((cpu.regs.get_ix(), cpu.regs.get_iy()), (1, 0));
+    assert_eq!(bus.clocks(), 10 + 4);
+}
+
+#[test]
+fn prefix_without_hl_substitution_is_ignored() {
+    // DD 04: INC B
+    let (mut cpu, mut bus) = setup(&[0xDD, 0x04]);
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_pc(), 2);
+    assert_eq!(cpu.regs.get_b(), 1);
+    assert_eq!(bus.clocks(), 8);
+    assert_eq!(cpu.regs.get_r(), 2);
+}
+
+#[test]
+fn index_register_halves_arithmetic() {
+    let program = [
+        0xDD, 0x21, 0x34, 0x12, // LD IX, 0x1234
+        0xFD, 0x21, 0x78, 0x56, // LD IY, 0x5678
+        0x3E, 0x10, // LD A, 0x10
+        0xDD, 0x84, // ADD A, IXH
+        0xFD, 0x85, // ADD A, IYL
+        0xDD, 0x94, // SUB IXH
+        0xFD, 0xAC, // XOR IYH
+        0xDD, 0xBD, // CP IXL
+    ];
+    let (mut cpu, mut bus) = setup(&program);
+    run_to(&mut cpu, &mut bus, 12);
+    assert_eq!(cpu.regs.get_acc(), 0x22);
+    run_to(&mut cpu, &mut bus, 14);
+    assert_eq!(cpu.regs.get_acc(), 0x9A);
+    run_to(&mut cpu, &mut bus, 16);
+    assert_eq!(cpu.regs.get_acc(), 0x88);
+    run_to(&mut cpu, &mut bus, 18);
+    assert_eq!(cpu.regs.get_acc(), 0xDE);
+    let clocks = bus.clocks();
+    run_to(&mut cpu, &mut bus, 20);
+    assert_eq!(cpu.regs.get_acc(), 0xDE);
+    // Z flag of CP 0x34
+    assert_eq!(cpu.regs.get_flags() & 0x40, 0);
+    assert_eq!(bus.clocks() - clocks, 8);
+    // H and L are not affected
+    assert_eq!(cpu.regs.get_hl(), 0);
+}
+
+#[test]
+fn index_register_halves_loads() {
+    let program = [
+        0xDD, 0x26, 0x12, // LD IXH, 0x12
+        0xDD, 0x2E, 0x34, // LD IXL, 0x34
+        0xFD, 0x67, // LD IYH, A
+        0xDD, 0x65, // LD IXH, IXL
+        0xDD, 0x44, // LD B, IXH
+        0xFD, 0x24, // INC IYH
+        0xDD, 0x2D, // DEC IXL
+        // H is not substituted when the indexed memory operand is used
+        0xDD, 0x66, 0x01, // LD H, (IX + 1)
+    ];
+    let (mut cpu, mut bus) = setup(&program);
+    bus.patch_memory(0x3434, 0x99);
+    cpu.regs.set_acc(0x56);
+    run_to(&mut cpu, &mut bus, 6);
+    assert_eq!(cpu.regs.get_ix(), 0x1234);
+    run_to(&mut cpu, &mut bus, 8);
+    assert_eq!(cpu.regs.get_iy(), 0x5600);
+    run_to(&mut cpu, &mut bus, 10);
+    assert_eq!(cpu.regs.get_ix(), 0x3434);
+    run_to(&mut cpu, &mut bus, 12);
+    assert_eq!(cpu.regs.get_b(), 0x34);
+    run_to(&mut cpu, &mut bus, 16);
+    assert_eq!(cpu.regs.get_iy(), 0x5700);
+    assert_eq!(cpu.regs.get_ix(), 0x3433);
+    run_to(&mut cpu, &mut bus, 19);
+    assert_eq!(cpu.regs.get_h(), 0x99);
+    assert_eq!(cpu.regs.get_ix(), 0x3433);
+}
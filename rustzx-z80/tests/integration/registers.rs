@@ -0,0 +1,13 @@
+use rustzx_z80::{RegName16, Regs};
+
+#[test]
+fn alt_hl_halves_are_read_from_alt_registers() {
+    let mut regs = Regs::default();
+    regs.set_reg_16(RegName16::HL, 0x1234);
+    regs.exx();
+    regs.set_reg_16(RegName16::HL, 0x5678);
+    assert_eq!(regs.get_h_alt(), 0x12);
+    assert_eq!(regs.get_l_alt(), 0x34);
+    assert_eq!(regs.get_h(), 0x56);
+    assert_eq!(regs.get_l(), 0x78);
+}
@@ -1,14 +1,15 @@
 //! Real events SDL backend
 use super::{Event, EventDevice};
-use crate::{app::settings::Settings, backends::SDL_CONTEXT};
+use crate::{
+    app::settings::{JoyLayout, Settings},
+    backends::SDL_CONTEXT,
+};
 use rustzx_core::{
     zx::{
-        joy::{
-            kempston::KempstonKey,
-            sinclair::{SinclairJoyNum, SinclairKey},
-        },
+        joy::{JoyButton, JoyInterface},
+        keypad::KeypadKey,
         keys::{CompoundKey, ZXKey},
-        mouse::kempston::{KempstonMouseButton, KempstonMouseWheelDirection},
+        mouse::kempston::KempstonMouseButton,
     },
     EmulationMode,
 };
@@ -23,7 +24,10 @@ use sdl2::{
 pub struct EventsSdl {
     event_pump: EventPump,
     mouse: MouseUtil,
-    kempston_enabled: bool,
+    joy_arrows: Option<JoyInterface>,
+    joy_wasd: Option<JoyInterface>,
+    joy_ijkl: Option<JoyInterface>,
+    keypad_enabled: bool,
     mouse_enabled: bool,
     mouse_locked: bool,
     mouse_sensitivity: usize,
@@ -51,11 +55,14 @@ impl EventsSdl {
         EventsSdl {
             event_pump,
             mouse,
+            keypad_enabled: settings.enable_keypad,
             mouse_enabled: settings.enable_mouse,
             mouse_locked: false,
-            kempston_enabled: !settings.disable_kempston,
+            joy_arrows: settings.joy_interface(JoyLayout::Arrows),
+            joy_wasd: settings.joy_interface(JoyLayout::Wasd),
+            joy_ijkl: settings.joy_interface(JoyLayout::Ijkl),
             enable_joy_keyaboard_layer: false,
-            mouse_sensitivity: settings.mouse_sensitivity,
+            mouse_sensitivity: settings.mouse_sensitivity(),
             mouse_x_counter: 0,
             mouse_y_counter: 0,
         }
@@ -153,54 +160,66 @@ impl EventsSdl {
         compound_key_event.map(|k| Event::CompoundKey(k, pressed))
     }
 
-    /// returns kempston key form scancode of None if not found
-    fn scancode_to_kempston_event(
-        &self,
-        scancode: Option<Scancode>,
-        pressed: bool,
-    ) -> Option<Event> {
-        if !(self.kempston_enabled && self.enable_joy_keyaboard_layer) {
+    /// returns joystick event if the key belongs to one of the joy keyboard
+    /// layouts and the layout has joystick interface assigned
+    fn scancode_to_joy_event(&self, scancode: Option<Scancode>, pressed: bool) -> Option<Event> {
+        if !self.enable_joy_keyaboard_layer {
             return None;
         }
 
-        let kempston_event = match scancode? {
-            Scancode::LAlt | Scancode::RAlt => Some(KempstonKey::Fire),
-            Scancode::Up => Some(KempstonKey::Up),
-            Scancode::Down => Some(KempstonKey::Down),
-            Scancode::Left => Some(KempstonKey::Left),
-            Scancode::Right => Some(KempstonKey::Right),
-            _ => None,
+        let (layout, button) = match scancode? {
+            Scancode::LAlt | Scancode::RAlt => (JoyLayout::Arrows, JoyButton::Fire),
+            Scancode::Up => (JoyLayout::Arrows, JoyButton::Up),
+            Scancode::Down => (JoyLayout::Arrows, JoyButton::Down),
+            Scancode::Left => (JoyLayout::Arrows, JoyButton::Left),
+            Scancode::Right => (JoyLayout::Arrows, JoyButton::Right),
+            Scancode::A => (JoyLayout::Wasd, JoyButton::Left),
+            Scancode::W => (JoyLayout::Wasd, JoyButton::Up),
+            Scancode::S => (JoyLayout::Wasd, JoyButton::Down),
+            Scancode::D => (JoyLayout::Wasd, JoyButton::Right),
+            Scancode::CapsLock => (JoyLayout::Wasd, JoyButton::Fire),
+            Scancode::J => (JoyLayout::Ijkl, JoyButton::Left),
+            Scancode::I => (JoyLayout::Ijkl, JoyButton::Up),
+            Scancode::K => (JoyLayout::Ijkl, JoyButton::Down),
+            Scancode::L => (JoyLayout::Ijkl, JoyButton::Right),
+            Scancode::Return => (JoyLayout::Ijkl, JoyButton::Fire),
+            _ => return None,
         };
 
-        kempston_event.map(|k| Event::Kempston(k, pressed))
+        let interface = match layout {
+            JoyLayout::Arrows => self.joy_arrows,
+            JoyLayout::Wasd => self.joy_wasd,
+            JoyLayout::Ijkl => self.joy_ijkl,
+        }?;
+        Some(Event::Joy(interface, button, pressed))
     }
 
-    fn scancode_to_sinclair_event(
-        &self,
-        scancode: Option<Scancode>,
-        pressed: bool,
-    ) -> Option<Event> {
-        if !self.enable_joy_keyaboard_layer {
+    fn scancode_to_keypad_event(&self, scancode: Option<Scancode>, pressed: bool) -> Option<Event> {
+        if !self.keypad_enabled {
             return None;
         }
-
-        let sinclair_event = match scancode? {
-            // Joy 1
-            Scancode::A => Some((SinclairJoyNum::Fist, SinclairKey::Left)),
-            Scancode::W => Some((SinclairJoyNum::Fist, SinclairKey::Up)),
-            Scancode::S => Some((SinclairJoyNum::Fist, SinclairKey::Down)),
-            Scancode::D => Some((SinclairJoyNum::Fist, SinclairKey::Right)),
-            Scancode::CapsLock => Some((SinclairJoyNum::Fist, SinclairKey::Fire)),
-            // Joy 2
-            Scancode::J => Some((SinclairJoyNum::Second, SinclairKey::Left)),
-            Scancode::I => Some((SinclairJoyNum::Second, SinclairKey::Up)),
-            Scancode::K => Some((SinclairJoyNum::Second, SinclairKey::Down)),
-            Scancode::L => Some((SinclairJoyNum::Second, SinclairKey::Right)),
-            Scancode::Return => Some((SinclairJoyNum::Second, SinclairKey::Fire)),
-            _ => None,
+        let key = match scancode? {
+            Scancode::Kp0 => KeypadKey::N0,
+            Scancode::Kp1 => KeypadKey::N1,
+            Scancode::Kp2 => KeypadKey::N2,
+            Scancode::Kp3 => KeypadKey::N3,
+            Scancode::Kp4 => KeypadKey::N4,
+            Scancode::Kp5 => KeypadKey::N5,
+            Scancode::Kp6 => KeypadKey::N6,
+            Scancode::Kp7 => KeypadKey::N7,
+            Scancode::Kp8 => KeypadKey::N8,
+            Scancode::Kp9 => KeypadKey::N9,
+            Scancode::KpPeriod => KeypadKey::Dot,
+            Scancode::KpEnter => KeypadKey::Enter,
+            Scancode::KpPlus => KeypadKey::Plus,
+            Scancode::KpMinus => KeypadKey::Minus,
+            Scancode::KpMultiply => KeypadKey::Multiply,
+            Scancode::KpDivide => KeypadKey::Divide,
+            Scancode::KpLeftParen => KeypadKey::OpenParen,
+            Scancode::KpRightParen => KeypadKey::CloseParen,
+            _ => return None,
         };
-
-        sinclair_event.map(|(n, k)| Event::Sinclair(n, k, pressed))
+        Some(Event::KeypadKey(key, pressed))
     }
 
     fn scancode_to_emulator_event(
@@ -216,12 +235,17 @@ impl EventsSdl {
                 Scancode::F4 => Some(Event::ChangeSpeed(EmulationMode::FrameCount(2))),
                 Scancode::F5 => Some(Event::ChangeSpeed(EmulationMode::Max)),
                 Scancode::F6 => Some(Event::SwitchFrameTrace),
+                Scancode::F7 => Some(Event::ChangeSpeed(EmulationMode::SlowMotion(4))),
+                Scancode::F8 => Some(Event::ChangeBrightness(-1)),
                 Scancode::F9 => {
                     self.enable_joy_keyaboard_layer = !self.enable_joy_keyaboard_layer;
                     Some(Event::ChangeJoyKeyboardLayer(
                         self.enable_joy_keyaboard_layer,
                     ))
                 }
+                Scancode::F10 => Some(Event::ChangeBrightness(1)),
+                Scancode::F11 => Some(Event::ChangeAudioLatency(-1)),
+                Scancode::F12 => Some(Event::ChangeAudioLatency(1)),
                 Scancode::Insert => Some(Event::InsertTape),
                 Scancode::Delete => Some(Event::StopTape),
                 Scancode::Escape => {
@@ -255,8 +279,8 @@ impl EventDevice for EventsSdl {
 
                     // Form highest priority event to lowest
                     self.scancode_to_emulator_event(scancode, pressed)
-                        .or_else(|| self.scancode_to_kempston_event(scancode, pressed))
-                        .or_else(|| self.scancode_to_sinclair_event(scancode, pressed))
+                        .or_else(|| self.scancode_to_joy_event(scancode, pressed))
+                        .or_else(|| self.scancode_to_keypad_event(scancode, pressed))
                         .or_else(|| self.scancode_to_zxkey_event(scancode, pressed))
                         .or_else(|| self.scancode_to_compound_key_event(scancode, pressed))
                 }
@@ -314,12 +338,9 @@ impl EventDevice for EventsSdl {
                     }
                 }
                 SdlEvent::MouseWheel { y, .. } => {
-                    if self.mouse_locked {
-                        if y > 0 {
-                            Some(Event::MouseWheel(KempstonMouseWheelDirection::Up))
-                        } else {
-                            Some(Event::MouseWheel(KempstonMouseWheelDirection::Down))
-                        }
+                    if self.mouse_locked && y != 0 {
+                        let delta = y.clamp(i8::MIN as i32, i8::MAX as i32) as i8;
+                        Some(Event::MouseWheel(delta))
                     } else {
                         None
                     }
@@ -4,12 +4,10 @@ mod events_sdl;
 
 use rustzx_core::{
     zx::{
-        joy::{
-            kempston::KempstonKey,
-            sinclair::{SinclairJoyNum, SinclairKey},
-        },
+        joy::{JoyButton, JoyInterface},
+        keypad::KeypadKey,
         keys::{CompoundKey, ZXKey},
-        mouse::kempston::{KempstonMouseButton, KempstonMouseWheelDirection},
+        mouse::kempston::KempstonMouseButton,
     },
     EmulationMode,
 };
@@ -21,14 +19,16 @@ pub use events_sdl::EventsSdl;
 pub enum Event {
     ZXKey(ZXKey, bool),
     CompoundKey(CompoundKey, bool),
-    Kempston(KempstonKey, bool),
-    Sinclair(SinclairJoyNum, SinclairKey, bool),
+    Joy(JoyInterface, JoyButton, bool),
+    KeypadKey(KeypadKey, bool),
     MouseMove { x: i8, y: i8 },
     MouseButton(KempstonMouseButton, bool),
-    MouseWheel(KempstonMouseWheelDirection),
+    MouseWheel(i8),
     SwitchFrameTrace,
     ChangeJoyKeyboardLayer(bool),
     ChangeSpeed(EmulationMode),
+    ChangeAudioLatency(i32),
+    ChangeBrightness(i32),
     InsertTape,
     StopTape,
     QuickSave,
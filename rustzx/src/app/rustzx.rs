@@ -6,33 +6,53 @@ use crate::{
     app::{
         events::{Event, EventDevice, EventsSdl},
         settings::{Settings, SoundBackend},
-        sound::{SoundDevice, DEFAULT_SAMPLE_RATE},
-        video::{Rect, TextureInfo, VideoDevice, VideoSdl},
+        sound::{
+            SoundDevice, DEFAULT_AUDIO_LATENCY_FRAMES, DEFAULT_SAMPLE_RATE,
+            MAX_AUDIO_LATENCY_FRAMES,
+        },
+        video::{Palette, Rect, TextureInfo, VideoDevice, VideoSdl},
     },
     host::{self, AppHost, AppHostContext, DetectedFileKind},
 };
 use anyhow::{anyhow, Context};
+#[cfg(feature = "betadisk")]
+use rustzx_core::zx::storage::BETADISK_DRIVE_COUNT;
+#[cfg(feature = "plusd")]
+use rustzx_core::zx::storage::PLUSD_DRIVE_COUNT;
+#[cfg(feature = "watch")]
+use rustzx_core::ReloadMode;
 use rustzx_core::{
     host::SnapshotRecorder,
-    zx::constants::{
-        CANVAS_HEIGHT, CANVAS_WIDTH, CANVAS_X, CANVAS_Y, FPS, SCREEN_HEIGHT, SCREEN_WIDTH,
+    zx::{
+        constants::{CANVAS_HEIGHT, CANVAS_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH},
+        machine::MemoryModel,
+        storage::StorageDevice,
     },
     Emulator,
 };
-use rustzx_utils::io::FileAsset;
+#[cfg(feature = "watch")]
+use rustzx_utils::watch::BinaryWatcher;
+use rustzx_utils::{
+    frame_timer::FrameTimer,
+    io::FileAsset,
+    printer::{CaptureMode, PrinterCapture},
+    wav::WavSink,
+};
 use std::{
     fs::{self, File},
+    io::{BufWriter, Write},
     path::{Path, PathBuf},
-    thread,
     time::{Duration, Instant},
 };
 
 /// max 100 ms interval in `max frames` speed mode
 const MAX_FRAME_TIME: Duration = Duration::from_millis(100);
+/// Brightness change per `F8`/`F10` key press
+const BRIGHTNESS_STEP: f32 = 0.05;
 
 /// returns frame length from given `fps`
 fn frame_length(fps: usize) -> Duration {
-    Duration::from_millis((1000_f64 / fps as f64) as u64)
+    Duration::from_secs_f64(1f64 / fps as f64)
 }
 
 /// Application instance type
@@ -41,15 +61,32 @@ pub struct RustzxApp {
     emulator: Emulator<AppHost>,
     /// Sound rendering in a separate thread
     snd: Option<Box<dyn SoundDevice>>,
+    /// Sound recording, finalized on drop
+    wav: Option<WavSink<BufWriter<File>>>,
+    /// Output of the RS232 device
+    rs232_out: Option<File>,
+    /// Output of the +2A/+3 printer port
+    printer_out: Option<PrinterCapture<File>>,
+    #[cfg(feature = "plusd")]
+    plusd_printer_out: Option<PrinterCapture<File>>,
+    /// Development binary reloaded on modification
+    #[cfg(feature = "watch")]
+    binary_watcher: Option<BinaryWatcher>,
     video: Box<dyn VideoDevice>,
     events: Box<dyn EventDevice>,
     tex_border: TextureInfo,
     tex_canvas: TextureInfo,
     scale: u32,
     settings: Settings,
+    /// Palette with the current adjustments from `settings`
+    palette: Palette,
 
     enable_frame_trace: bool,
     enable_joy_keyaboard_layer: bool,
+    title_updated: Instant,
+    /// Frames of sound buffered ahead
+    audio_latency: u32,
+    shut_down: bool,
 }
 
 impl RustzxApp {
@@ -74,13 +111,36 @@ impl RustzxApp {
             .map(|s| s.sample_rate())
             .unwrap_or(DEFAULT_SAMPLE_RATE);
 
-        let mut emulator = Emulator::new(settings.to_rustzx_settings(sample_rate), AppHostContext)
-            .map_err(|e| anyhow!("Failed to construct emulator: {}", e))?;
+        let wav = settings
+            .record_audio
+            .as_ref()
+            .map(|path| {
+                WavSink::new(path, sample_rate as u32, 2)
+                    .with_context(|| format!("Failed to create {}", path.display()))
+            })
+            .transpose()?;
+
+        let mut palette = match (settings.palette.as_ref(), settings.palette_preset) {
+            (Some(path), _) => Palette::load_palette_file(path)?,
+            (None, Some(preset)) => Palette::from_colors(preset.colors()),
+            (None, None) => Palette::default(),
+        };
+        palette.set_adjustments(settings.adjustments());
+
+        let mut emulator = Emulator::new(
+            settings.to_rustzx_settings(sample_rate),
+            AppHostContext {
+                palette: palette.clone(),
+            },
+        )
+        .map_err(|e| anyhow!("Failed to construct emulator: {}", e))?;
 
         if let Some(rom) = settings.rom.as_ref() {
             emulator
                 .load_rom(host::load_rom(rom, settings.machine)?)
                 .map_err(|e| anyhow!("Emulator failed to load rom: {}", e))?;
+        } else if settings.machine.memory_model() == MemoryModel::SinclairPlus3 {
+            return Err(anyhow!("+3 ROM is not embedded, provide it with `--rom`"));
         }
         if let Some(snapshot) = settings.snap.as_ref() {
             emulator
@@ -92,25 +152,110 @@ impl RustzxApp {
                 .load_tape(host::load_tape(tape)?)
                 .map_err(|e| anyhow!("Emulator failed to load tape: {}", e))?;
         }
+        #[cfg(feature = "ide")]
+        if let Some(hdd) = settings.hdd.as_ref() {
+            emulator.attach_hard_disk(host::load_hard_disk(hdd)?);
+        }
+        #[cfg(feature = "spectranet")]
+        if let Some(rom) = settings.spectranet_rom.as_ref() {
+            emulator
+                .attach_spectranet(
+                    host::load_spectranet_rom(rom)?,
+                    rustzx_utils::io::StdNetworkStack::new(),
+                )
+                .map_err(|e| anyhow!("Emulator failed to load Spectranet flash ROM: {}", e))?;
+        }
+        #[cfg(feature = "plusd")]
+        if let Some(rom) = settings.plusd_rom.as_ref() {
+            emulator
+                .attach_plusd(host::load_plusd_rom(rom)?)
+                .map_err(|e| anyhow!("Emulator failed to load +D ROM: {}", e))?;
+            if let Some(disk) = settings.disk.as_ref() {
+                emulator
+                    .insert_plusd_disk(0, host::load_disk(disk)?)
+                    .map_err(|e| anyhow!("Emulator failed to insert disk: {}", e))?;
+            }
+        }
+        #[cfg(feature = "betadisk")]
+        if let Some(rom) = settings.trdos_rom.as_ref() {
+            emulator
+                .attach_betadisk(host::load_trdos_rom(rom)?)
+                .map_err(|e| anyhow!("Emulator failed to load TR-DOS ROM: {}", e))?;
+            if let Some(disk) = settings.trd.as_ref() {
+                emulator
+                    .insert_betadisk_disk(0, host::load_trdos_disk(disk)?)
+                    .map_err(|e| anyhow!("Emulator failed to insert disk: {}", e))?;
+            }
+        }
+        let printer_out = printer_capture(settings.printer_out.as_ref(), settings.printer_text)?;
+        #[cfg(feature = "plusd")]
+        let plusd_printer_out = printer_capture(
+            settings.plusd_printer_out.as_ref(),
+            settings.plusd_printer_text,
+        )?;
+        let rs232_out = settings
+            .rs232_out
+            .as_ref()
+            .map(|path| {
+                File::create(path).with_context(|| format!("Failed to create {}", path.display()))
+            })
+            .transpose()?;
+        if rs232_out.is_some() || settings.rs232_in.is_some() {
+            emulator.attach_rs232(settings.rs232_baud);
+        }
+        if settings.enable_keypad {
+            emulator.attach_keypad();
+        }
+        emulator.set_auto_frameskip(settings.auto_frameskip);
+        emulator.set_isr_border_debug(settings.isr_border);
+        if let Some(path) = settings.rs232_in.as_ref() {
+            let input =
+                fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            emulator.send_rs232_input(&input);
+        }
         if let Some(screen) = settings.screen.as_ref() {
             emulator
                 .load_screen(host::load_screen(screen)?)
                 .map_err(|e| anyhow!("Emulator failed to load screen: {}", e))?;
         }
 
+        #[cfg(feature = "watch")]
+        let binary_watcher = settings.watch_binary.as_ref().map(|path| {
+            let mode = if settings.watch_restart {
+                ReloadMode::Restart
+            } else {
+                ReloadMode::PreserveState
+            };
+            BinaryWatcher::new(path, settings.watch_addr, mode)
+        });
+
         let file_autodetect = settings.file_autodetect.clone();
+        let audio_latency = settings
+            .audio_latency
+            .unwrap_or(DEFAULT_AUDIO_LATENCY_FRAMES);
 
         let mut app = RustzxApp {
             emulator,
             snd,
+            wav,
+            rs232_out,
+            printer_out,
+            #[cfg(feature = "plusd")]
+            plusd_printer_out,
+            #[cfg(feature = "watch")]
+            binary_watcher,
             video,
             events,
             tex_border,
             tex_canvas,
             scale,
             settings,
+            palette,
             enable_frame_trace: cfg!(debug_assertions),
             enable_joy_keyaboard_layer: false,
+            title_updated: Instant::now(),
+            audio_latency,
+            shut_down: false,
         };
 
         if let Some(file) = file_autodetect.as_ref() {
@@ -129,32 +274,77 @@ impl RustzxApp {
             title.push_str(" [JOY]");
         }
 
+        let motor_on = |device| {
+            self.emulator
+                .drive_status(device)
+                .is_some_and(|status| status.motor_on)
+        };
+        if motor_on(StorageDevice::Tape) {
+            title.push_str(" [TAPE]");
+        }
+        #[cfg(feature = "plusd")]
+        if (0..PLUSD_DRIVE_COUNT).any(|drive| motor_on(StorageDevice::PlusD(drive))) {
+            title.push_str(" [DISK]");
+        }
+        #[cfg(feature = "betadisk")]
+        if (0..BETADISK_DRIVE_COUNT).any(|drive| motor_on(StorageDevice::BetaDisk(drive))) {
+            title.push_str(" [DISK]");
+        }
+
         if self.enable_frame_trace {
-            title.push_str(" [FRAME_TRACE]");
+            let stats = self.emulator.perf_stats();
+            title.push_str(&format!(
+                " [FRAME_TRACE {:.1} FPS, avg {:.2}ms, max {:.2}ms, skipped {}]",
+                stats.fps,
+                stats.avg_ms,
+                stats.max_ms,
+                self.emulator.skipped_frames()
+            ));
         }
 
         self.video.set_title(&title);
+        self.title_updated = Instant::now();
     }
 
     pub fn start(&mut self) -> anyhow::Result<()> {
         let scale = self.scale;
+        let border = self.settings.border;
+        let (border_x, border_y) = border.offset();
+        let now = Instant::now();
+        let mut timer = match self.settings.refresh_rate {
+            Some(fps) => FrameTimer::new(frame_length(fps), now),
+            None => FrameTimer::for_machine(self.settings.machine, now),
+        };
+        // When display refresh rate differs from the machine frame rate, some
+        // emulated frames are presented twice (or skipped), but overall count
+        // of emulated frames per second stays the same, so sound pitch is not
+        // affected
+        let mut machine_timer = self
+            .settings
+            .refresh_rate
+            .map(|_| FrameTimer::for_machine(self.settings.machine, now));
         'emulator: loop {
-            let frame_target_dt = frame_length(FPS);
-            // absolute start time
-            let frame_start = Instant::now();
+            timer.begin_frame(Instant::now());
+            #[cfg(feature = "watch")]
+            if let Some(watcher) = self.binary_watcher.as_mut() {
+                if let Err(e) = watcher.poll(&mut self.emulator) {
+                    log::error!("Failed to reload {}: {}", watcher.path().display(), e);
+                }
+            }
+            let mut emulator_dt = Duration::ZERO;
             // Emulate all requested frames
-            let emulator_dt = self
-                .emulator
-                .emulate_frames(MAX_FRAME_TIME)
-                .map_err(|e| anyhow!("Emulation step failed: {:#?}", e))?
-                .duration;
-            // if sound enabled sound ganeration allowed then move samples to sound thread
-            if let Some(ref mut snd) = self.snd {
-                // if can be turned off even on speed change, so check it everytime
-                if self.emulator.have_sound() {
-                    while let Some(sample) = self.emulator.next_audio_sample() {
-                        snd.send_sample(sample);
-                    }
+            let frames = machine_timer
+                .as_mut()
+                .map_or(1, |t| t.take_due_frames(timer.deadline()));
+            for _ in 0..frames {
+                emulator_dt += self
+                    .emulator
+                    .emulate_frames(MAX_FRAME_TIME)
+                    .map_err(|e| anyhow!("Emulation step failed: {:#?}", e))?
+                    .duration;
+                self.write_outputs()?;
+                if !self.emulator.storage_events().is_empty() {
+                    self.update_window_title();
                 }
             }
 
@@ -166,9 +356,10 @@ impl RustzxApp {
             self.video.begin();
             self.video.draw_texture_2d(
                 self.tex_border,
+                // Border texture is shifted out of the window to crop it
                 Some(Rect::new(
-                    0,
-                    0,
+                    -(border_x as i32 * scale as i32),
+                    -(border_y as i32 * scale as i32),
                     SCREEN_WIDTH as u32 * scale,
                     SCREEN_HEIGHT as u32 * scale,
                 )),
@@ -176,8 +367,8 @@ impl RustzxApp {
             self.video.draw_texture_2d(
                 self.tex_canvas,
                 Some(Rect::new(
-                    CANVAS_X as i32 * scale as i32,
-                    CANVAS_Y as i32 * scale as i32,
+                    border.horizontal() as i32 * scale as i32,
+                    border.vertical() as i32 * scale as i32,
                     CANVAS_WIDTH as u32 * scale,
                     CANVAS_HEIGHT as u32 * scale,
                 )),
@@ -203,11 +394,28 @@ impl RustzxApp {
                     Event::ChangeSpeed(speed) => {
                         self.emulator.set_speed(speed);
                     }
-                    Event::Kempston(key, state) => {
-                        self.emulator.send_kempston_key(key, state);
+                    Event::ChangeAudioLatency(delta) => {
+                        self.audio_latency = self
+                            .audio_latency
+                            .saturating_add_signed(delta)
+                            .clamp(1, MAX_AUDIO_LATENCY_FRAMES);
+                        if let Some(snd) = self.snd.as_mut() {
+                            snd.set_audio_latency(self.audio_latency);
+                        }
+                        log::info!("Audio latency: {} frames", self.audio_latency);
+                    }
+                    Event::ChangeBrightness(delta) => {
+                        self.settings.brightness = (self.settings.brightness
+                            + delta as f32 * BRIGHTNESS_STEP)
+                            .clamp(-1.0, 1.0);
+                        self.palette.set_adjustments(self.settings.adjustments());
+                        self.emulator.set_context(AppHostContext {
+                            palette: self.palette.clone(),
+                        });
+                        log::info!("Brightness: {:+.2}", self.settings.brightness);
                     }
-                    Event::Sinclair(num, key, state) => {
-                        self.emulator.send_sinclair_key(num, key, state);
+                    Event::Joy(interface, button, state) => {
+                        self.emulator.send_joy_button(interface, button, state);
                     }
                     Event::CompoundKey(key, state) => {
                         self.emulator.send_compound_key(key, state);
@@ -218,8 +426,15 @@ impl RustzxApp {
                     Event::MouseButton(button, pressed) => {
                         self.emulator.send_mouse_button(button, pressed);
                     }
-                    Event::MouseWheel(direction) => {
-                        self.emulator.send_mouse_wheel(direction);
+                    Event::KeypadKey(key, pressed) => {
+                        if pressed {
+                            self.emulator.keypad_key_down(key);
+                        } else {
+                            self.emulator.keypad_key_up(key);
+                        }
+                    }
+                    Event::MouseWheel(delta) => {
+                        self.emulator.send_mouse_wheel_delta(delta);
                     }
                     Event::InsertTape => self.emulator.play_tape(),
                     Event::StopTape => self.emulator.stop_tape(),
@@ -228,27 +443,122 @@ impl RustzxApp {
                     Event::QuickLoad => self.quick_load()?,
                 }
             }
-            // how long emulation iteration was
-            let emulation_dt = frame_start.elapsed();
-            if emulation_dt < frame_target_dt {
-                let wait_koef = if self.emulator.have_sound() { 9 } else { 10 };
-                // sleep until frame sync
-                thread::sleep((frame_target_dt - emulation_dt) * wait_koef / 10);
-            };
-            // get exceed clocks and use them on next iteration
-            let frame_dt = frame_start.elapsed();
-            // change window header
+            timer.end_frame(Instant::now());
+            timer.wait();
             if self.enable_frame_trace {
+                let stats = timer.stats();
                 log::trace!(
-                    "EMUALTOR: {:7.3}ms; FRAME:{:7.3}ms",
-                    emulator_dt.as_millis(),
-                    frame_dt.as_millis()
+                    "EMUALTOR: {:7.3}ms; FRAME: avg {:7.3}ms, p95 {:7.3}ms; LATE: {}; DRIFT: {:+7.3}ms",
+                    emulator_dt.as_secs_f64() * 1000.0,
+                    stats.average_frame_time.as_secs_f64() * 1000.0,
+                    stats.p95_frame_time.as_secs_f64() * 1000.0,
+                    stats.late_frames,
+                    stats.drift * 1000.0,
                 );
+                // Refresh frame time statistics shown in the title
+                if self.title_updated.elapsed() >= Duration::from_secs(1) {
+                    self.update_window_title();
+                }
+            }
+        }
+        self.shutdown()
+    }
+
+    /// Moves data produced by the emulator to the devices and files
+    fn write_outputs(&mut self) -> anyhow::Result<()> {
+        if let Some(ref mut rs232_out) = self.rs232_out {
+            let output = self.emulator.take_rs232_output();
+            if !output.is_empty() {
+                rs232_out
+                    .write_all(&output)
+                    .context("Failed to write RS232 output")?;
+            }
+        }
+        if let Some(ref mut printer_out) = self.printer_out {
+            printer_out
+                .write(&self.emulator.take_printer_output())
+                .context("Failed to write printer output")?;
+        }
+        #[cfg(feature = "plusd")]
+        if let Some(ref mut printer_out) = self.plusd_printer_out {
+            printer_out
+                .write(&self.emulator.take_plusd_printer_output())
+                .context("Failed to write +D printer output")?;
+        }
+        // if sound enabled sound ganeration allowed then move samples to sound thread
+        if self.snd.is_some() || self.wav.is_some() {
+            // if can be turned off even on speed change, so check it everytime
+            if self.emulator.have_sound() {
+                while let Some(sample) = self.emulator.next_audio_sample() {
+                    if let Some(ref mut wav) = self.wav {
+                        wav.write_sample(sample)
+                            .context("Failed to write sound recording")?;
+                    }
+                    if let Some(ref mut snd) = self.snd {
+                        snd.send_sample(sample);
+                    }
+                }
             }
         }
         Ok(())
     }
 
+    /// Writes remaining outputs, finalizes recordings and closes output
+    /// files. Called on exit and, if exit was not clean, on drop
+    pub fn shutdown(&mut self) -> anyhow::Result<()> {
+        if self.shut_down {
+            return Ok(());
+        }
+        self.shut_down = true;
+        // Each output is finalized even if the previous ones failed
+        let mut errors = Vec::new();
+        if let Err(e) = self.emulator.shutdown() {
+            errors.push(anyhow!("Failed to shut down emulator: {}", e));
+        }
+        if let Err(e) = self.write_outputs() {
+            errors.push(e);
+        }
+        if let Some(wav) = self.wav.take() {
+            if let Err(e) = wav.finish().context("Failed to finalize sound recording") {
+                errors.push(e);
+            }
+        }
+        // Output files are unbuffered, closing them is enough
+        self.rs232_out = None;
+        self.printer_out = None;
+        #[cfg(feature = "plusd")]
+        {
+            self.plusd_printer_out = None;
+        }
+        combine_errors(errors)
+    }
+
+    /// Runs emulator benchmark and prints report instead of starting interactive session
+    pub fn benchmark(&mut self, frames: u64) -> anyhow::Result<()> {
+        let report = self
+            .emulator
+            .benchmark(frames)
+            .map_err(|e| anyhow!("Emulator benchmark failed: {}", e))?;
+
+        println!("Frames:                 {}", report.frames);
+        println!(
+            "Emulated time:          {:.3}s",
+            report.emulated_time.as_secs_f64()
+        );
+        println!(
+            "Wall time:              {:.3}s",
+            report.wall_time.as_secs_f64()
+        );
+        match report.speed_factor() {
+            Some(speed) => println!("Speed:                  {:.2}x", speed),
+            None => println!("Speed:                  n/a"),
+        }
+        if let Some(instructions) = report.instructions_per_frame() {
+            println!("Instructions per frame: {:.1}", instructions);
+        }
+        Ok(())
+    }
+
     fn load_file_autodetect(&mut self, path: &Path) -> anyhow::Result<()> {
         match host::detect_file_type(path)? {
             DetectedFileKind::Snapshot => {
@@ -267,6 +577,10 @@ impl RustzxApp {
                 .emulator
                 .load_screen(host::load_screen(path)?)
                 .map_err(|e| anyhow!("Emulator failed load screen via auto-detect: {}", e))?,
+            DetectedFileKind::Cartridge => self
+                .emulator
+                .insert_cartridge(host::load_cartridge(path)?)
+                .map_err(|e| anyhow!("Emulator failed to insert auto-detected cartridge: {}", e))?,
         }
         Ok(())
     }
@@ -316,6 +630,14 @@ impl RustzxApp {
     }
 }
 
+impl Drop for RustzxApp {
+    fn drop(&mut self) {
+        if let Err(e) = self.shutdown() {
+            log::error!("{:#}", e);
+        }
+    }
+}
+
 fn create_sound_backend(settings: &Settings) -> anyhow::Result<Box<dyn SoundDevice>> {
     use crate::app::sound;
 
@@ -326,3 +648,52 @@ fn create_sound_backend(settings: &Settings) -> anyhow::Result<Box<dyn SoundDevi
     };
     Ok(backend)
 }
+
+/// Creates printer output file at `path` if set, with ESC/P filtering
+/// to plain text if `text` is set
+fn printer_capture(
+    path: Option<&PathBuf>,
+    text: bool,
+) -> anyhow::Result<Option<PrinterCapture<File>>> {
+    let mode = if text {
+        CaptureMode::Text
+    } else {
+        CaptureMode::Raw
+    };
+    path.map(|path| {
+        File::create(path)
+            .map(|file| PrinterCapture::new(file, mode))
+            .with_context(|| format!("Failed to create {}", path.display()))
+    })
+    .transpose()
+}
+
+/// Returns the only error as is, or all of them joined into one message
+fn combine_errors(mut errors: Vec<anyhow::Error>) -> anyhow::Result<()> {
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0)),
+        _ => {
+            let messages = errors
+                .iter()
+                .map(|e| format!("{:#}", e))
+                .collect::<Vec<_>>();
+            Err(anyhow!("{}", messages.join("; ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_shutdown_errors_are_reported() {
+        assert!(combine_errors(Vec::new()).is_ok());
+        let error = combine_errors(vec![anyhow!("first")]).unwrap_err();
+        assert_eq!(error.to_string(), "first");
+        let error =
+            combine_errors(vec![anyhow!("first"), anyhow!("cause").context("second")]).unwrap_err();
+        assert_eq!(error.to_string(), "first; second: cause");
+    }
+}
@@ -1,7 +1,16 @@
+use crate::app::sound::MAX_AUDIO_LATENCY_FRAMES;
+use log::LevelFilter;
 use rustzx_core::{
-    zx::{machine::ZXMachine, sound::ay::ZXAYMode},
+    zx::{
+        joy::{sinclair::SinclairJoyNum, JoyInterface},
+        machine::{MachinePolicy, Timings, UlaIssue, ZXMachine},
+        mouse::kempston::KempstonMouseConfig,
+        sound::{ay::ZXAYMode, filter::SoundFilters},
+        video::border_size::BorderSize,
+    },
     EmulationMode, RustzxSettings,
 };
+use rustzx_utils::palette::{rgba, Adjustments, PaletteRgba};
 use std::path::PathBuf;
 use structopt::StructOpt;
 use strum::{EnumString, EnumVariantNames, VariantNames};
@@ -12,6 +21,8 @@ const DEFAULT_SOUND_BACKEND_VALUE: &str = "cpal";
 #[cfg(not(feature = "sound-cpal"))]
 const DEFAULT_SOUND_BACKEND_VALUE: &str = "sdl";
 
+const DEFAULT_MOUSE_SENSITIVITY: usize = 20;
+
 #[derive(Clone, Copy, Debug, EnumString, EnumVariantNames)]
 #[strum(serialize_all = "snake_case")]
 pub enum SoundBackend {
@@ -20,6 +31,61 @@ pub enum SoundBackend {
     Cpal,
 }
 
+/// Built-in palette, see [rustzx_utils::palette::rgba]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "snake_case")]
+pub enum PalettePreset {
+    Original,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+impl PalettePreset {
+    pub fn colors(self) -> PaletteRgba {
+        match self {
+            Self::Original => rgba::ORIGINAL,
+            Self::Deuteranopia => rgba::DEUTERANOPIA,
+            Self::Protanopia => rgba::PROTANOPIA,
+            Self::Tritanopia => rgba::TRITANOPIA,
+        }
+    }
+}
+
+/// Keyboard keys group, which acts as a joystick in joy keyboard layer mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoyLayout {
+    /// Arrow keys and `Alt`
+    Arrows,
+    /// `WASD` and `Caps Lock`
+    Wasd,
+    /// `IJKL` and `Enter`
+    Ijkl,
+}
+
+/// Joystick interface assigned to the keyboard layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "snake_case")]
+pub enum JoyAssignment {
+    None,
+    Kempston,
+    Fuller,
+    Sinclair1,
+    Sinclair2,
+}
+
+impl JoyAssignment {
+    pub fn interface(self) -> Option<JoyInterface> {
+        match self {
+            Self::None => None,
+            Self::Kempston => Some(JoyInterface::Kempston),
+            Self::Fuller => Some(JoyInterface::Fuller),
+            Self::Sinclair1 => Some(JoyInterface::Sinclair(SinclairJoyNum::Fist)),
+            Self::Sinclair2 => Some(JoyInterface::Sinclair(SinclairJoyNum::Second)),
+        }
+    }
+}
+
 /// Structure to handle all emulator runtime settings
 #[derive(StructOpt)]
 #[structopt(about = env!("CARGO_PKG_DESCRIPTION"))]
@@ -27,11 +93,32 @@ pub enum SoundBackend {
 pub struct Settings {
     /// Specify machine type for launch. Possible values:
     ///   [`48k`, `48`] - Sinclair ZX Spectrum 48K
+    ///   [`48k-ntsc`] - Sinclair ZX Spectrum 48K (NTSC, 60Hz)
     ///   [`128k`, `128`] - Sinclair ZX Spectrum 128K
+    ///   [`plus3`, `+3`, `+2a`] - Amstrad ZX Spectrum +2A/+3, requires `--rom`
     #[structopt(verbatim_doc_comment, short, long, default_value = "48k", parse(try_from_str = machine_from_str))]
     pub machine: ZXMachine,
+    /// Action taken when loaded snapshot or tape requires other machine. Possible values:
+    ///   [`auto`] - switch to the required machine
+    ///   [`warn`] - load into the selected machine anyway
+    ///   [`strict`] - refuse to load
+    #[structopt(verbatim_doc_comment, long = "machine-policy", default_value = "auto", parse(try_from_str = machine_policy_from_str))]
+    pub machine_policy: MachinePolicy,
+    /// Use early ULA timings on 48K machine (contention and screen reads start one clock
+    /// earlier). Late timings are used by default
+    #[structopt(long = "early-timings")]
+    pub early_timings: bool,
+    /// Emulate issue 2 board of 48K machine: EAR input reads high when EAR or MIC output
+    /// is set (only EAR output on issue 3, which is used by default)
+    #[structopt(long = "issue2")]
+    pub issue2: bool,
+    /// Override length of the ULA interrupt pulse in clocks (32 on 48K and 36 on 128K
+    /// by default)
+    #[structopt(long = "int-length")]
+    pub interrupt_length: Option<usize>,
     /// Set emulation speed at emualtor start-up. Can be specified as deciamal non-zero
-    /// value or as a special value `MAX` to run emulator as fast as possible
+    /// value, as fraction `1/N` for slow motion (sound is muted) or as a special
+    /// value `MAX` to run emulator as fast as possible
     #[structopt(long, default_value = "1", parse(try_from_str = emulation_speed_from_str))]
     pub speed: EmulationMode,
     /// Disable fast tape loading
@@ -41,19 +128,82 @@ pub struct Settings {
     /// after launch
     #[structopt(long = "noautoload")]
     pub disable_autoload: bool,
+    /// Set host display refresh rate in Hz. When it differs from the emulated machine frame
+    /// rate, frames are paced to the display (e.g. 50 emulated frames shown in 60 display
+    /// frames by presenting some of them twice), while emulation and sound keep the original
+    /// speed. Defaults to the machine frame rate
+    #[structopt(long, parse(try_from_str = refresh_rate_from_str))]
+    pub refresh_rate: Option<usize>,
+    /// Skip screen rendering of up to the given count of consecutive frames when the host
+    /// can't keep up with emulation, sound is still produced for the skipped frames
+    #[structopt(long = "auto-frameskip", default_value = "0")]
+    pub auto_frameskip: u32,
+    /// Show border in the given color (0-7) while the emulated CPU runs an interrupt
+    /// handler, to visualise its duration
+    #[structopt(long = "isr-border", parse(try_from_str = border_color_from_str))]
+    pub isr_border: Option<u8>,
     /// Set windows scale for emulator. Can be set as decimal non-zero value. Defaults to 2
     #[structopt(short, long, default_value = "2", parse(try_from_str = scale_from_str))]
     pub scale: usize,
+    /// Set visible border size in pixels as `<size>` or `<horizontal>x<vertical>`.
+    /// Maximum (and default) is the full rendered border, 32x24. `0` shows canvas only
+    #[structopt(long, default_value = "32x24", parse(try_from_str = border_from_str))]
+    pub border: BorderSize,
     /// Disable kempston joy support. If enabled, arrow and `Alt` keys are bound by default
     /// to the kempston joy
     #[structopt(long = "nokempston")]
     pub disable_kempston: bool,
+    /// Enable Fuller Box support: joystick on port 0x7F and AY chip on ports 0x3F and
+    /// 0x5F. If enabled, arrow and `Alt` keys are bound to the Fuller joy instead of the
+    /// kempston joy
+    #[structopt(long = "fuller")]
+    pub enable_fuller: bool,
+    /// Set joystick interface for arrow keys and `Alt` in joy keyboard layer mode.
+    /// Defaults to `kempston` (`fuller` if `--fuller` is used, `none` if `--nokempston`
+    /// is used)
+    #[structopt(long = "joy-arrows", possible_values = &JoyAssignment::VARIANTS)]
+    pub joy_arrows: Option<JoyAssignment>,
+    /// Set joystick interface for `WASD` and `Caps Lock` in joy keyboard layer mode.
+    /// Defaults to `sinclair1`
+    #[structopt(long = "joy-wasd", possible_values = &JoyAssignment::VARIANTS)]
+    pub joy_wasd: Option<JoyAssignment>,
+    /// Set joystick interface for `IJKL` and `Enter` in joy keyboard layer mode.
+    /// Defaults to `sinclair2`
+    #[structopt(long = "joy-ijkl", possible_values = &JoyAssignment::VARIANTS)]
+    pub joy_ijkl: Option<JoyAssignment>,
     /// Enables kempston mouse support. If enabled, locks mouse in application
     #[structopt(long = "mouse")]
     pub enable_mouse: bool,
-    /// Sets mouse sensitivity [1..=100]. Defaults to 20
-    #[structopt(long = "mouse-sensitivity", default_value = "20")]
-    pub mouse_sensitivity: usize,
+    /// Disables emulation of ghost key presses, which appear on the real keyboard
+    /// when three pressed keys form an L-shape in the key matrix
+    #[structopt(long = "noghosting")]
+    pub disable_keyboard_ghosting: bool,
+    /// Enables ULAplus palette extension (64 colors out of 256)
+    #[structopt(long = "ulaplus")]
+    pub enable_ulaplus: bool,
+    /// Fills RAM with pseudo-random values generated from the given seed on power on,
+    /// instead of zeroes
+    #[structopt(long = "ram-seed")]
+    pub ram_seed: Option<u64>,
+    /// Sets mouse sensitivity [1..=100]. Defaults to 20. Can't be combined with
+    /// per-axis `--mouse-scale-x` and `--mouse-scale-y`
+    #[structopt(
+        long = "mouse-sensitivity",
+        conflicts_with_all = &["mouse-scale-x", "mouse-scale-y"]
+    )]
+    pub mouse_sensitivity: Option<usize>,
+    /// Scales horizontal mouse movement, in percents. Defaults to 100
+    #[structopt(long = "mouse-scale-x")]
+    pub mouse_scale_x: Option<u16>,
+    /// Scales vertical mouse movement, in percents. Defaults to 100
+    #[structopt(long = "mouse-scale-y")]
+    pub mouse_scale_y: Option<u16>,
+    /// Inverts horizontal mouse movement
+    #[structopt(long = "mouse-invert-x")]
+    pub mouse_invert_x: bool,
+    /// Inverts vertical mouse movement
+    #[structopt(long = "mouse-invert-y")]
+    pub mouse_invert_y: bool,
     /// Set AY-3-8910 sound chip mode. Can be set to `mono`, `abc`(stereo) or `acb`(stereo)
     /// Defaults to `abc`
     #[structopt(long, default_value = "abc", parse(try_from_str = ay_mode_from_str))]
@@ -74,9 +224,22 @@ pub struct Settings {
     /// Set custom sound latency
     #[structopt(long, parse(try_from_str = sound_latency_from_str))]
     pub sound_latency: Option<usize>,
+    /// Count of frames of sound buffered ahead (1-16, default is 3). Bigger values
+    /// increase sound delay, but help against crackling on slow hosts. Can be changed
+    /// at runtime with `F11`/`F12`
+    #[structopt(long, parse(try_from_str = audio_latency_from_str))]
+    pub audio_latency: Option<u32>,
+    /// Output sound filter: `none` (raw square waves), `dc` (DC blocker only) or
+    /// `tv` (DC blocker and low-pass filter approximating TV speaker)
+    #[structopt(long, default_value = "none", parse(try_from_str = sound_filter_from_str))]
+    pub sound_filter: SoundFilters,
     /// Set custom sound sample rate
     #[structopt(long, parse(try_from_str = sound_sample_rate_from_str))]
     pub sound_sample_rate: Option<usize>,
+    /// Record emulator sound to the given `*.wav` file (stereo, 16 bit). Works with
+    /// `--nosound` too
+    #[structopt(long = "record-audio")]
+    pub record_audio: Option<PathBuf>,
     /// Sound backend to use
     #[structopt(
         long,
@@ -84,8 +247,51 @@ pub struct Settings {
         possible_values = &SoundBackend::VARIANTS
     )]
     pub sound_backend: SoundBackend,
-    /// Set path to custom rom file. in case of multipart ROMs for 128k, the first part file,
-    /// extension of which should end with `.0`
+    /// Attach serial device to the RS232 port of 128K models and write bytes sent by
+    /// the Spectrum (e.g. `LLIST` in 128 BASIC) to the given file
+    #[structopt(long = "rs232-out")]
+    pub rs232_out: Option<PathBuf>,
+    /// Attach serial device to the RS232 port of 128K models and send the given file
+    /// to the Spectrum when it is ready to receive
+    #[structopt(long = "rs232-in")]
+    pub rs232_in: Option<PathBuf>,
+    /// Baud rate of the RS232 device, should match `FORMAT "p";baud` in 128 BASIC
+    #[structopt(long = "rs232-baud", default_value = "9600")]
+    pub rs232_baud: u32,
+    /// Write bytes printed via the +2A/+3 printer port (e.g. by `LLIST`) to the given file
+    #[structopt(long = "printer-out")]
+    pub printer_out: Option<PathBuf>,
+    /// Write +2A/+3 printer output as plain text, with Epson ESC/P control sequences and
+    /// graphics removed, instead of the raw bytes
+    #[structopt(long = "printer-text", requires = "printer-out")]
+    pub printer_text: bool,
+    /// Attach numeric keypad of the Spectrum 128, mapped to the host numeric keypad
+    #[structopt(long = "keypad")]
+    pub enable_keypad: bool,
+    /// Load palette of 16 colors (8 normal, then 8 bright) from the text file with one
+    /// `R G B` triple per line. GIMP `*.gpl` palettes are supported too
+    #[structopt(long, conflicts_with = "palette-preset")]
+    pub palette: Option<PathBuf>,
+    /// Use built-in palette: `original` (default), or one of the presets for color
+    /// blindness types, which keep ink and paper colors distinguishable
+    #[structopt(long, possible_values = &PalettePreset::VARIANTS)]
+    pub palette_preset: Option<PalettePreset>,
+    /// Draw paper with subtle per-color patterns, so colors stay distinguishable
+    /// for color-blind users and in grayscale
+    #[structopt(long)]
+    pub paper_patterns: bool,
+    /// Brightness offset of the palette colors in `-1.0..=1.0` range of the full
+    /// component value. Can be changed at runtime with `F8`/`F10`
+    #[structopt(long, default_value = "0", parse(try_from_str = brightness_from_str))]
+    pub brightness: f32,
+    /// Contrast of the palette colors around mid-gray, `1.0` keeps the original one
+    #[structopt(long, default_value = "1", parse(try_from_str = contrast_from_str))]
+    pub contrast: f32,
+    /// Gamma of the palette colors, values above `1.0` lighten dark colors
+    #[structopt(long, default_value = "1", parse(try_from_str = gamma_from_str))]
+    pub gamma: f32,
+    /// Set path to custom rom file. in case of multipart ROMs for 128k and +3, the first part
+    /// file, extension of which should end with `.0`
     #[structopt(long, conflicts_with = "file-autodetect")]
     pub rom: Option<PathBuf>,
     /// Set tape file path. Only `.tap` files are supported currently
@@ -94,9 +300,67 @@ pub struct Settings {
     /// Set snapshot file path. Only `.sna` files are supported currently
     #[structopt(long, conflicts_with = "file-autodetect")]
     pub snap: Option<PathBuf>,
+    /// Set hard disk image path. Image is attached to DivIDE-compatible IDE interface
+    #[cfg(feature = "ide")]
+    #[structopt(long)]
+    pub hdd: Option<PathBuf>,
+    /// Set Spectranet flash ROM image path. Enables Spectranet network interface
+    #[cfg(feature = "spectranet")]
+    #[structopt(long)]
+    pub spectranet_rom: Option<PathBuf>,
+    /// Set +D ROM image path (e.g. G+DOS). Enables MGT +D disk and printer interface
+    #[cfg(feature = "plusd")]
+    #[structopt(long = "plusd-rom")]
+    pub plusd_rom: Option<PathBuf>,
+    /// Insert disk image into the first +D drive. `.mgt`, `.img` and `.udi` files are supported
+    #[cfg(feature = "plusd")]
+    #[structopt(long, requires = "plusd-rom")]
+    pub disk: Option<PathBuf>,
+    /// Write bytes sent to the +D printer port to the given file
+    #[cfg(feature = "plusd")]
+    #[structopt(long = "plusd-printer-out", requires = "plusd-rom")]
+    pub plusd_printer_out: Option<PathBuf>,
+    /// Write +D printer output as plain text, with Epson ESC/P control sequences and
+    /// graphics removed, instead of the raw bytes
+    #[cfg(feature = "plusd")]
+    #[structopt(long = "plusd-printer-text", requires = "plusd-printer-out")]
+    pub plusd_printer_text: bool,
+    /// Set TR-DOS ROM image path. Enables Beta Disk interface
+    #[cfg(feature = "betadisk")]
+    #[structopt(long = "trdos-rom")]
+    pub trdos_rom: Option<PathBuf>,
+    /// Insert disk image into the first Beta Disk drive. `.trd`, `.scl` and `.udi` files are
+    /// supported
+    #[cfg(feature = "betadisk")]
+    #[structopt(long, requires = "trdos-rom")]
+    pub trd: Option<PathBuf>,
     /// Set screen file to load. Only `.scr` files are supported currently
     #[structopt(long, conflicts_with = "file-autodetect")]
     pub screen: Option<PathBuf>,
+    /// Watch development binary and patch it into memory each time the file is
+    /// modified, while the machine keeps running
+    #[cfg(feature = "watch")]
+    #[structopt(long = "watch-binary")]
+    pub watch_binary: Option<PathBuf>,
+    /// Load address of the watched binary, decimal or hex with `0x` prefix
+    #[cfg(feature = "watch")]
+    #[structopt(long = "watch-addr", default_value = "0x8000", parse(try_from_str = address_from_str))]
+    pub watch_addr: u16,
+    /// Reset CPU and jump to the load address after each reload of the watched binary
+    #[cfg(feature = "watch")]
+    #[structopt(long = "watch-restart")]
+    pub watch_restart: bool,
+
+    /// Run emulation of the given count of frames as fast as possible, print performance
+    /// report and exit. Input is not processed during benchmark
+    #[structopt(long)]
+    pub benchmark: Option<u64>,
+
+    /// Set log level for the specific emulator subsystem, e.g. `rustzx::tape=debug`.
+    /// Available subsystems: tape, snapshot, screen, rom, memory, machine, disk, network.
+    /// Can be specified multiple times
+    #[structopt(long = "log", number_of_values = 1, parse(try_from_str = log_filter_from_str))]
+    pub log_filters: Vec<(String, LevelFilter)>,
 
     /// Load provided file to emulator. Emulator will perform autodetect of format if possible
     pub file_autodetect: Option<PathBuf>,
@@ -105,14 +369,52 @@ pub struct Settings {
 fn machine_from_str(s: &str) -> Result<ZXMachine, anyhow::Error> {
     match s.to_lowercase().as_str() {
         "48k" | "48" => Ok(ZXMachine::Sinclair48K),
+        "48k-ntsc" => Ok(ZXMachine::Sinclair48KNtsc),
         "128k" | "128" => Ok(ZXMachine::Sinclair128K),
+        "plus3" | "+3" | "+2a" => Ok(ZXMachine::SinclairPlus3),
         s => Err(anyhow::anyhow!("Invalid machine type `{}`", s)),
     }
 }
 
+fn machine_policy_from_str(s: &str) -> Result<MachinePolicy, anyhow::Error> {
+    match s.to_lowercase().as_str() {
+        "auto" => Ok(MachinePolicy::AutoSwitch),
+        "warn" => Ok(MachinePolicy::WarnOnly),
+        "strict" => Ok(MachinePolicy::Strict),
+        s => Err(anyhow::anyhow!("Invalid machine policy `{}`", s)),
+    }
+}
+
+#[cfg(feature = "watch")]
+fn address_from_str(s: &str) -> Result<u16, anyhow::Error> {
+    let address = match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    address.map_err(|_| anyhow::anyhow!("Invalid address `{}`", s))
+}
+
+fn log_filter_from_str(s: &str) -> Result<(String, LevelFilter), anyhow::Error> {
+    let (target, level) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Invalid log filter `{}`, expected `target=level`", s))?;
+    let level = level
+        .parse::<LevelFilter>()
+        .map_err(|_| anyhow::anyhow!("Invalid log level `{}`", level))?;
+
+    Ok((target.to_owned(), level))
+}
+
 fn emulation_speed_from_str(s: &str) -> Result<EmulationMode, anyhow::Error> {
     match s.to_lowercase().as_str() {
         "max" => Ok(EmulationMode::Max),
+        s if s.starts_with("1/") => {
+            let divisor: std::num::NonZeroUsize = s[2..]
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid slow motion speed `{}`", s))?;
+
+            Ok(EmulationMode::SlowMotion(divisor.into()))
+        }
         s => {
             let speed: std::num::NonZeroUsize = s
                 .parse()
@@ -131,6 +433,47 @@ fn scale_from_str(s: &str) -> Result<usize, anyhow::Error> {
     Ok(scale.into())
 }
 
+fn border_from_str(s: &str) -> Result<BorderSize, anyhow::Error> {
+    let parse = |value: &str| {
+        value
+            .parse::<usize>()
+            .map_err(|_| anyhow::anyhow!("Invalid border size `{}`", s))
+    };
+    let (horizontal, vertical) = match s.split_once('x') {
+        Some((horizontal, vertical)) => (parse(horizontal)?, parse(vertical)?),
+        None => {
+            let size = parse(s)?;
+            (size, size)
+        }
+    };
+
+    BorderSize::new(horizontal, vertical).map_err(|e| anyhow::anyhow!("`{}`: {}", s, e))
+}
+
+fn refresh_rate_from_str(s: &str) -> Result<usize, anyhow::Error> {
+    let rate = s
+        .parse::<usize>()
+        .map_err(|_| anyhow::anyhow!("Invalid refresh rate `{}`", s))?;
+
+    if !(24..=240).contains(&rate) {
+        anyhow::bail!("Refresh rate `{}` is out of supported range 24..=240", rate);
+    }
+
+    Ok(rate)
+}
+
+fn border_color_from_str(s: &str) -> Result<u8, anyhow::Error> {
+    let color = s
+        .parse::<u8>()
+        .map_err(|_| anyhow::anyhow!("Invalid border color `{}`", s))?;
+
+    if color > 7 {
+        anyhow::bail!("Border color `{}` is out of supported range 0..=7", color);
+    }
+
+    Ok(color)
+}
+
 fn ay_mode_from_str(s: &str) -> Result<ZXAYMode, anyhow::Error> {
     match s.to_lowercase().as_str() {
         "mono" => Ok(ZXAYMode::Mono),
@@ -140,6 +483,18 @@ fn ay_mode_from_str(s: &str) -> Result<ZXAYMode, anyhow::Error> {
     }
 }
 
+fn sound_filter_from_str(s: &str) -> Result<SoundFilters, anyhow::Error> {
+    match s.to_lowercase().as_str() {
+        "none" => Ok(SoundFilters::NONE),
+        "dc" => Ok(SoundFilters {
+            dc_blocker: true,
+            low_pass_cutoff: None,
+        }),
+        "tv" => Ok(SoundFilters::TV_SPEAKER),
+        s => Err(anyhow::anyhow!("Invalid sound filter `{}`", s)),
+    }
+}
+
 fn sound_latency_from_str(s: &str) -> Result<usize, anyhow::Error> {
     let latency = s
         .parse::<usize>()
@@ -155,6 +510,57 @@ fn sound_latency_from_str(s: &str) -> Result<usize, anyhow::Error> {
     Ok(latency)
 }
 
+fn audio_latency_from_str(s: &str) -> Result<u32, anyhow::Error> {
+    let frames = s
+        .parse::<u32>()
+        .map_err(|_| anyhow::anyhow!("Invalid audio latency `{}`", s))?;
+
+    if !(1..=MAX_AUDIO_LATENCY_FRAMES).contains(&frames) {
+        anyhow::bail!(
+            "Audio latency should be in 1..={} frames range",
+            MAX_AUDIO_LATENCY_FRAMES
+        );
+    }
+
+    Ok(frames)
+}
+
+fn brightness_from_str(s: &str) -> Result<f32, anyhow::Error> {
+    let brightness = s
+        .parse::<f32>()
+        .map_err(|_| anyhow::anyhow!("Invalid brightness `{}`", s))?;
+
+    if !(-1.0..=1.0).contains(&brightness) {
+        anyhow::bail!("Brightness should be in -1.0..=1.0 range");
+    }
+
+    Ok(brightness)
+}
+
+fn contrast_from_str(s: &str) -> Result<f32, anyhow::Error> {
+    let contrast = s
+        .parse::<f32>()
+        .map_err(|_| anyhow::anyhow!("Invalid contrast `{}`", s))?;
+
+    if !(contrast >= 0.0 && contrast.is_finite()) {
+        anyhow::bail!("Contrast should be a non-negative number");
+    }
+
+    Ok(contrast)
+}
+
+fn gamma_from_str(s: &str) -> Result<f32, anyhow::Error> {
+    let gamma = s
+        .parse::<f32>()
+        .map_err(|_| anyhow::anyhow!("Invalid gamma `{}`", s))?;
+
+    if !(gamma > 0.0 && gamma.is_finite()) {
+        anyhow::bail!("Gamma should be a positive number");
+    }
+
+    Ok(gamma)
+}
+
 fn sound_sample_rate_from_str(s: &str) -> Result<usize, anyhow::Error> {
     let sample_rate = s
         .parse::<usize>()
@@ -172,23 +578,85 @@ fn sound_sample_rate_from_str(s: &str) -> Result<usize, anyhow::Error> {
 }
 
 impl Settings {
+    /// Returns joystick interface assigned to the keyboard layout
+    pub fn joy_interface(&self, layout: JoyLayout) -> Option<JoyInterface> {
+        let assignment = match layout {
+            JoyLayout::Arrows => self.joy_arrows.unwrap_or(if self.enable_fuller {
+                JoyAssignment::Fuller
+            } else if self.disable_kempston {
+                JoyAssignment::None
+            } else {
+                JoyAssignment::Kempston
+            }),
+            JoyLayout::Wasd => self.joy_wasd.unwrap_or(JoyAssignment::Sinclair1),
+            JoyLayout::Ijkl => self.joy_ijkl.unwrap_or(JoyAssignment::Sinclair2),
+        };
+        assignment.interface()
+    }
+
+    fn joy_interface_used(&self, interface: JoyInterface) -> bool {
+        [JoyLayout::Arrows, JoyLayout::Wasd, JoyLayout::Ijkl]
+            .iter()
+            .any(|&layout| self.joy_interface(layout) == Some(interface))
+    }
+
+    pub fn mouse_sensitivity(&self) -> usize {
+        self.mouse_sensitivity.unwrap_or(DEFAULT_MOUSE_SENSITIVITY)
+    }
+
+    pub fn adjustments(&self) -> Adjustments {
+        Adjustments {
+            brightness: self.brightness,
+            contrast: self.contrast,
+            gamma: self.gamma,
+        }
+    }
+
     pub fn to_rustzx_settings(&self, sound_sample_rate: usize) -> RustzxSettings {
-        let ay_enabled = (matches!(self.machine, ZXMachine::Sinclair128K) || self.force_enable_ay)
-            && (!self.force_disable_ay);
+        let ay_enabled =
+            (!self.machine.is_48k() || self.force_enable_ay) && (!self.force_disable_ay);
+        let mouse_defaults = KempstonMouseConfig::default();
 
         RustzxSettings {
             machine: self.machine,
+            machine_policy: self.machine_policy,
+            timings: if self.early_timings {
+                Timings::Early
+            } else {
+                Timings::Late
+            },
+            ula_issue: if self.issue2 {
+                UlaIssue::Issue2
+            } else {
+                UlaIssue::Issue3
+            },
+            interrupt_length: self.interrupt_length,
             emulation_mode: self.speed,
             tape_fastload_enabled: !self.disable_fastload,
-            kempston_enabled: !self.disable_kempston,
+            kempston_enabled: !self.disable_kempston
+                || self.joy_interface_used(JoyInterface::Kempston),
+            fuller_enabled: self.enable_fuller || self.joy_interface_used(JoyInterface::Fuller),
             mouse_enabled: self.enable_mouse,
+            mouse_config: KempstonMouseConfig {
+                x_sensitivity: self.mouse_scale_x.unwrap_or(mouse_defaults.x_sensitivity),
+                y_sensitivity: self.mouse_scale_y.unwrap_or(mouse_defaults.y_sensitivity),
+                invert_x: self.mouse_invert_x,
+                invert_y: self.mouse_invert_y,
+            },
+            keyboard_ghosting: !self.disable_keyboard_ghosting,
+            ulaplus_enabled: self.enable_ulaplus,
+            orientation: Default::default(),
+            paper_patterns: self.paper_patterns,
+            ram_init_seed: self.ram_seed,
             ay_mode: self.ay_mode,
             ay_enabled,
+            fuller_ay_enabled: self.enable_fuller,
             beeper_enabled: !self.disable_beeper,
-            sound_enabled: !self.disable_sound,
+            sound_enabled: !self.disable_sound || self.record_audio.is_some(),
             sound_volume: 100,
             load_default_rom: self.rom.is_none(),
             sound_sample_rate,
+            sound_filters: self.sound_filter,
             autoload_enabled: !self.disable_autoload,
         }
     }
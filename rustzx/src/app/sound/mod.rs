@@ -2,6 +2,10 @@
 mod sound_cpal;
 mod sound_sdl;
 use rustzx_core::zx::sound::sample::SoundSample;
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
 
 #[cfg(feature = "sound-cpal")]
 pub use sound_cpal::SoundCpal;
@@ -10,6 +14,12 @@ pub use sound_sdl::SoundSdl;
 pub const CHANNEL_COUNT: usize = 2;
 pub const DEFAULT_SAMPLE_RATE: usize = 44100;
 pub const DEFAULT_LATENCY: usize = 512;
+/// Frames of samples buffered ahead by default. 2-3 frames work for most
+/// hosts, 5-8 frames help on slow or heavily loaded ones
+pub const DEFAULT_AUDIO_LATENCY_FRAMES: u32 = 3;
+pub const MAX_AUDIO_LATENCY_FRAMES: u32 = 16;
+/// Length of the crossfade over the samples skipped on latency decrease
+const CROSSFADE_SAMPLES: usize = 256;
 
 pub type ZXSample = SoundSample<f32>;
 
@@ -18,10 +28,181 @@ pub trait SoundDevice {
     fn send_sample(&mut self, sample: ZXSample);
     /// Return selected device sample rate
     fn sample_rate(&self) -> usize;
+    /// Change count of frames buffered ahead, see [SampleSender::set_latency]
+    fn set_audio_latency(&mut self, frames: u32);
 }
 
-pub fn ringbuf_size_from_sample_rate(sample_rate: usize) -> usize {
-    // Around 5 frames of buffering is available
-    const RINGBUF_LENGTH_MS: usize = 100;
-    (sample_rate as f32 * (RINGBUF_LENGTH_MS as f32 / 1000f32)) as usize
+/// Creates queue which passes samples from the emulation to the audio
+/// callback, `latency` frames of `frames_per_second` rate are buffered ahead
+pub fn sample_queue(
+    sample_rate: usize,
+    frames_per_second: usize,
+    latency: u32,
+) -> (SampleSender, SampleReceiver) {
+    let frame_samples = sample_rate / frames_per_second.max(1);
+    // Room for the emulation running slightly ahead of the callback
+    let capacity = (MAX_AUDIO_LATENCY_FRAMES as usize + 2) * frame_samples;
+    let (tx, rx) = ringbuf::HeapRb::<ZXSample>::new(capacity).split();
+    let latency = Arc::new(AtomicU32::new(latency.min(MAX_AUDIO_LATENCY_FRAMES)));
+    let sender = SampleSender {
+        tx,
+        latency: latency.clone(),
+        frame_samples,
+    };
+    let receiver = SampleReceiver {
+        rx,
+        latency,
+        active_latency: None,
+        frame_samples,
+        buffering: true,
+        fade_out: Vec::with_capacity(CROSSFADE_SAMPLES),
+        fade_pos: 0,
+    };
+    (sender, receiver)
+}
+
+/// Emulation side of the [sample_queue]
+pub struct SampleSender {
+    tx: ringbuf::Producer<ZXSample, Arc<ringbuf::HeapRb<ZXSample>>>,
+    latency: Arc<AtomicU32>,
+    frame_samples: usize,
+}
+
+impl SampleSender {
+    pub fn send(&mut self, sample: ZXSample) {
+        // Samples produced while the queue holds more than the latency plus
+        // a frame of slack are dropped, so latency does not grow when the
+        // emulation runs faster than the audio device
+        let latency = self.latency.load(Ordering::Relaxed) as usize;
+        if self.tx.len() < (latency + 1) * self.frame_samples {
+            let _ = self.tx.push(sample);
+        }
+    }
+
+    /// Changes count of frames buffered ahead. Larger values increase
+    /// latency but make underruns less likely. The change may be applied
+    /// at any time; the callback outputs brief silence while the new
+    /// amount is buffered, or skips the excess samples with a crossfade
+    pub fn set_latency(&mut self, frames: u32) {
+        self.latency
+            .store(frames.min(MAX_AUDIO_LATENCY_FRAMES), Ordering::Relaxed);
+    }
+}
+
+/// Audio callback side of the [sample_queue]
+pub struct SampleReceiver {
+    rx: ringbuf::Consumer<ZXSample, Arc<ringbuf::HeapRb<ZXSample>>>,
+    latency: Arc<AtomicU32>,
+    active_latency: Option<u32>,
+    frame_samples: usize,
+    // Silence is played until the queue is filled up to the latency
+    buffering: bool,
+    // Samples before the skipped ones, faded out while the samples after
+    // them are faded in
+    fade_out: Vec<ZXSample>,
+    fade_pos: usize,
+}
+
+impl SampleReceiver {
+    /// Returns next sample, or `None` if silence should be played
+    pub fn pop(&mut self) -> Option<ZXSample> {
+        let latency = self.latency.load(Ordering::Relaxed);
+        let target = (latency as usize * self.frame_samples).max(1);
+        if self.active_latency != Some(latency) {
+            if self.active_latency.is_some() {
+                let excess = self.rx.len().saturating_sub(target);
+                self.skip_with_crossfade(excess, target);
+            }
+            self.active_latency = Some(latency);
+        }
+        if self.buffering {
+            if self.rx.len() < target {
+                return None;
+            }
+            self.buffering = false;
+        }
+        let sample = self.rx.pop();
+        // Buffer again after underrun instead of playing samples one by one
+        self.buffering = sample.is_none();
+        sample.map(|sample| self.crossfade(sample))
+    }
+
+    /// Skips `count` samples, `remaining` samples are left in the queue.
+    /// Instead of the jump in the waveform, the samples before the skipped
+    /// ones fade out while the following ones fade in, over up to
+    /// [CROSSFADE_SAMPLES]
+    fn skip_with_crossfade(&mut self, count: usize, remaining: usize) {
+        // Unfinished crossfade is dropped, the jump is hardly audible
+        // after the whole latency change
+        self.fade_out.clear();
+        self.fade_pos = 0;
+        let fade_len = count.min(remaining).min(CROSSFADE_SAMPLES);
+        self.fade_out.extend(self.rx.pop_iter().take(fade_len));
+        self.rx.skip(count - fade_len);
+    }
+
+    fn crossfade(&mut self, sample: ZXSample) -> ZXSample {
+        let Some(&old) = self.fade_out.get(self.fade_pos) else {
+            return sample;
+        };
+        let t = (self.fade_pos + 1) as f32 / (self.fade_out.len() + 1) as f32;
+        self.fade_pos += 1;
+        if self.fade_pos == self.fade_out.len() {
+            self.fade_out.clear();
+            self.fade_pos = 0;
+        }
+        ZXSample::new(
+            old.left * (1.0 - t) + sample.left * t,
+            old.right * (1.0 - t) + sample.right * t,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pop_left(rx: &mut SampleReceiver) -> Option<f32> {
+        rx.pop().map(|sample| sample.left)
+    }
+
+    #[test]
+    fn frame_samples_follow_frame_rate() {
+        let (tx, rx) = sample_queue(48000, 60, 2);
+        assert_eq!(tx.frame_samples, 800);
+        assert_eq!(rx.frame_samples, 800);
+        let (tx, _) = sample_queue(48000, 50, 2);
+        assert_eq!(tx.frame_samples, 960);
+    }
+
+    #[test]
+    fn playback_starts_after_latency_is_buffered() {
+        // 20 samples per frame
+        let (mut tx, mut rx) = sample_queue(1000, 50, 2);
+        for _ in 0..39 {
+            tx.send(ZXSample::new(0.5, 0.5));
+        }
+        assert_eq!(pop_left(&mut rx), None);
+        tx.send(ZXSample::new(0.5, 0.5));
+        assert_eq!(pop_left(&mut rx), Some(0.5));
+    }
+
+    #[test]
+    fn latency_decrease_crossfades_over_skipped_samples() {
+        let (mut tx, mut rx) = sample_queue(1000, 50, 16);
+        for n in 0..320 {
+            let value = if n < 300 { 0.0 } else { 1.0 };
+            tx.send(ZXSample::new(value, value));
+        }
+        assert_eq!(pop_left(&mut rx), Some(0.0));
+        tx.set_latency(1);
+        // 20 of the remaining 319 samples are kept, the last 20 skipped
+        // samples fade out while the kept ones fade in
+        let played = std::iter::from_fn(|| pop_left(&mut rx)).collect::<Vec<_>>();
+        assert_eq!(played.len(), 20);
+        assert_eq!(played[0], 1.0 / 21.0);
+        assert_eq!(played[19], 20.0 / 21.0);
+        assert!(played.windows(2).all(|pair| pair[1] > pair[0]));
+        assert!(rx.fade_out.is_empty());
+    }
 }
@@ -1,13 +1,15 @@
 //! Real Audio SDL backend
 use crate::app::{
     settings::Settings,
-    sound::{ringbuf_size_from_sample_rate, SoundDevice, ZXSample, CHANNEL_COUNT},
+    sound::{
+        sample_queue, SampleReceiver, SampleSender, SoundDevice, ZXSample, CHANNEL_COUNT,
+        DEFAULT_AUDIO_LATENCY_FRAMES,
+    },
 };
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use std::sync::Arc;
 
 pub struct SoundCpal {
-    tx: ringbuf::Producer<ZXSample, Arc<ringbuf::HeapRb<ZXSample>>>,
+    tx: SampleSender,
     sample_rate: usize,
     // Keep stream alive until Drop
     _stream: cpal::Stream,
@@ -32,7 +34,7 @@ impl SoundCpal {
                 }
 
                 // Find any stereo config
-                (c.channels() as usize % CHANNEL_COUNT == 0) && c.channels() != 0
+                (c.channels() as usize).is_multiple_of(CHANNEL_COUNT) && c.channels() != 0
             })
             .ok_or_else(|| {
                 anyhow::anyhow!("Sound device does not support required configuration")
@@ -46,9 +48,13 @@ impl SoundCpal {
 
         let sample_rate = config.sample_rate().0 as usize;
 
-        let ringbuf_size = ringbuf_size_from_sample_rate(sample_rate);
-        let ringbuf = ringbuf::HeapRb::<ZXSample>::new(ringbuf_size);
-        let (tx, rx) = ringbuf.split();
+        let (tx, rx) = sample_queue(
+            sample_rate,
+            settings.machine.specs().frames_per_second,
+            settings
+                .audio_latency
+                .unwrap_or(DEFAULT_AUDIO_LATENCY_FRAMES),
+        );
 
         let stream = match config.sample_format() {
             cpal::SampleFormat::I16 => create_stream::<i16>(&device, &config.into(), rx)?,
@@ -76,19 +82,22 @@ impl SoundCpal {
 
 impl SoundDevice for SoundCpal {
     fn send_sample(&mut self, sample: ZXSample) {
-        // Ignore buffer overflows
-        let _ = self.tx.push(sample);
+        self.tx.send(sample);
     }
 
     fn sample_rate(&self) -> usize {
         self.sample_rate
     }
+
+    fn set_audio_latency(&mut self, frames: u32) {
+        self.tx.set_latency(frames);
+    }
 }
 
 fn create_stream<T>(
     device: &cpal::Device,
     config: &cpal::StreamConfig,
-    mut samples_rx: ringbuf::Consumer<ZXSample, Arc<ringbuf::HeapRb<ZXSample>>>,
+    mut samples_rx: SampleReceiver,
 ) -> anyhow::Result<cpal::Stream>
 where
     T: cpal::FromSample<f32> + cpal::SizedSample,
@@ -2,18 +2,17 @@ use crate::{
     app::{
         settings::Settings,
         sound::{
-            ringbuf_size_from_sample_rate, SoundDevice, ZXSample, CHANNEL_COUNT, DEFAULT_LATENCY,
-            DEFAULT_SAMPLE_RATE,
+            sample_queue, SampleReceiver, SampleSender, SoundDevice, ZXSample, CHANNEL_COUNT,
+            DEFAULT_AUDIO_LATENCY_FRAMES, DEFAULT_LATENCY, DEFAULT_SAMPLE_RATE,
         },
     },
     backends::SDL_CONTEXT,
 };
 use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
-use std::sync::Arc;
 
 /// Struct which used in SDL audio callback
 struct SdlCallback {
-    samples: ringbuf::Consumer<ZXSample, Arc<ringbuf::HeapRb<ZXSample>>>,
+    samples: SampleReceiver,
 }
 
 impl AudioCallback for SdlCallback {
@@ -36,7 +35,7 @@ impl AudioCallback for SdlCallback {
 
 /// Represents SDL audio backend
 pub struct SoundSdl {
-    sender: ringbuf::Producer<ZXSample, Arc<ringbuf::HeapRb<ZXSample>>>,
+    sender: SampleSender,
     sample_rate: usize,
     _device: AudioDevice<SdlCallback>, // Should be alive until Drop invocation
 }
@@ -62,9 +61,13 @@ impl SoundSdl {
             channels: Some(CHANNEL_COUNT as u8),
             samples: Some(latency as u16),
         };
-        let ringbuf_size = ringbuf_size_from_sample_rate(sample_rate);
-        let ringbuf = ringbuf::HeapRb::<ZXSample>::new(ringbuf_size);
-        let (tx, rx) = ringbuf.split();
+        let (tx, rx) = sample_queue(
+            sample_rate,
+            settings.machine.specs().frames_per_second,
+            settings
+                .audio_latency
+                .unwrap_or(DEFAULT_AUDIO_LATENCY_FRAMES),
+        );
 
         let device_handle = audio
             .open_playback(None, &desired_spec, |_| SdlCallback { samples: rx })
@@ -81,11 +84,14 @@ impl SoundSdl {
 
 impl SoundDevice for SoundSdl {
     fn send_sample(&mut self, sample: ZXSample) {
-        // Ignore sample push errors
-        let _ = self.sender.push(sample);
+        self.sender.send(sample);
     }
 
     fn sample_rate(&self) -> usize {
         self.sample_rate
     }
+
+    fn set_audio_latency(&mut self, frames: u32) {
+        self.sender.set_latency(frames);
+    }
 }
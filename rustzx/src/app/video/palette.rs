@@ -1,26 +1,57 @@
 use rustzx_core::zx::video::colors::{ZXBrightness, ZXColor};
-use rustzx_utils::palette::rgba::ORIGINAL as DEFAULT_PALETTE;
+use rustzx_utils::palette::{
+    rgba::ORIGINAL as DEFAULT_PALETTE, Adjustments, PaletteRgba, PALETTE_SIZE,
+};
+use std::path::Path;
 
 type ColorRgba = [u8; 4];
 
-const MAX_COLORS: usize = 16;
-
+#[derive(Clone)]
 pub struct Palette {
-    colors: [ColorRgba; MAX_COLORS],
+    /// Colors before the adjustments
+    base: PaletteRgba,
+    colors: PaletteRgba,
+    /// Adjusted component values, applied to the ULAplus colors
+    lut: [u8; 256],
 }
 
 impl Default for Palette {
     fn default() -> Self {
-        Palette {
-            colors: DEFAULT_PALETTE,
-        }
+        Self::from_colors(DEFAULT_PALETTE)
     }
 }
 
 impl Palette {
+    pub fn from_colors(colors: PaletteRgba) -> Self {
+        Self {
+            base: colors,
+            colors,
+            lut: Adjustments::default().lut(),
+        }
+    }
+
+    /// Loads palette from file with 16 RGB triples, see
+    /// [rustzx_utils::palette::parse_palette]
+    pub fn load_palette_file(path: &Path) -> anyhow::Result<Self> {
+        let colors = rustzx_utils::palette::load_palette_file(path)
+            .map_err(|e| anyhow::anyhow!("Failed to load palette {}: {}", path.display(), e))?;
+        Ok(Self::from_colors(colors))
+    }
+
+    /// Rebuilds colors from the loaded ones with the given adjustments
+    pub fn set_adjustments(&mut self, adjustments: Adjustments) {
+        self.colors = adjustments.apply(&self.base);
+        self.lut = adjustments.lut();
+    }
+
     pub fn get_rgba(&self, color: ZXColor, brightness: ZXBrightness) -> ColorRgba {
         let index = ((color as u8) + (brightness as u8) * 8) as usize;
-        assert!(index < MAX_COLORS);
+        assert!(index < PALETTE_SIZE);
         self.colors[index]
     }
+
+    /// Returns adjusted RGB color, used for colors outside of the palette
+    pub fn adjust_rgb(&self, [r, g, b]: [u8; 3]) -> [u8; 3] {
+        [r, g, b].map(|component| self.lut[component as usize])
+    }
 }
@@ -1,13 +1,18 @@
 use crate::app::video::Palette;
 use rustzx_core::{
     host::{FrameBuffer, FrameBufferSource},
-    zx::video::colors::{ZXBrightness, ZXColor},
+    zx::video::{
+        colors::{ZXBrightness, ZXColor},
+        ulaplus,
+    },
 };
 
 const RGBA_PIXEL_SIZE: usize = 4;
 
 #[derive(Clone)]
-pub struct FrameBufferContext;
+pub struct FrameBufferContext {
+    pub palette: Palette,
+}
 
 pub struct RgbaFrameBuffer {
     buffer: Vec<u8>,
@@ -22,11 +27,11 @@ impl FrameBuffer for RgbaFrameBuffer {
         width: usize,
         height: usize,
         _source: FrameBufferSource,
-        _context: Self::Context,
+        context: Self::Context,
     ) -> Self {
         Self {
             buffer: vec![0u8; width * height * RGBA_PIXEL_SIZE],
-            palette: Palette::default(),
+            palette: context.palette,
             buffer_row_size: width * RGBA_PIXEL_SIZE,
         }
     }
@@ -41,6 +46,36 @@ impl FrameBuffer for RgbaFrameBuffer {
             .zip(&mut self.buffer[buffer_pos..buffer_pos + RGBA_PIXEL_SIZE])
             .for_each(|(source, dest)| *dest = source);
     }
+
+    fn set_block(
+        &mut self,
+        x: usize,
+        y: usize,
+        bitmap: u8,
+        ink: ZXColor,
+        paper: ZXColor,
+        brightness: ZXBrightness,
+    ) {
+        let ink = self.palette.get_rgba(ink, brightness);
+        let paper = self.palette.get_rgba(paper, brightness);
+        let mut block = [0u8; 8 * RGBA_PIXEL_SIZE];
+        for (pixel, dest) in block.chunks_exact_mut(RGBA_PIXEL_SIZE).enumerate() {
+            let color = if (bitmap << pixel) & 0x80 != 0 {
+                ink
+            } else {
+                paper
+            };
+            dest.copy_from_slice(&color);
+        }
+        let buffer_pos = y * self.buffer_row_size + x * RGBA_PIXEL_SIZE;
+        self.buffer[buffer_pos..buffer_pos + block.len()].copy_from_slice(&block);
+    }
+
+    fn set_ulaplus_color(&mut self, x: usize, y: usize, color: u8) {
+        let buffer_pos = y * self.buffer_row_size + x * RGBA_PIXEL_SIZE;
+        let [r, g, b] = self.palette.adjust_rgb(ulaplus::grb_to_rgb(color));
+        self.buffer[buffer_pos..buffer_pos + RGBA_PIXEL_SIZE].copy_from_slice(&[r, g, b, 0xFF]);
+    }
 }
 
 impl RgbaFrameBuffer {
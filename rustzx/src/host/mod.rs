@@ -1,16 +1,19 @@
 mod frame_buffer;
 
+use crate::app::video::Palette;
 use anyhow::{anyhow, bail, Context};
 use frame_buffer::{FrameBufferContext, RgbaFrameBuffer};
 use rustzx_core::{
     host::{
-        FrameBuffer, Host, HostContext, RomFormat, RomSet, Screen, Snapshot, StubDebugInterface,
-        StubIoExtender, Tape,
+        BufferCursor, Cartridge, FrameBuffer, Host, HostContext, RomFormat, RomSet, Screen,
+        Snapshot, StubDebugInterface, StubIoExtender, Tape,
     },
-    zx::machine::ZXMachine,
+    zx::machine::{MemoryModel, ZXMachine},
 };
+#[cfg(feature = "zip")]
+use rustzx_utils::io::{archive_entries, ArchiveEntryKind, ZipArchive, ZipAsset, ZipError};
 use rustzx_utils::{
-    io::{DynamicAsset, FileAsset, GzipAsset},
+    io::{DynamicAsset, FileAsset, GzipAsset, MemoryAsset},
     stopwatch::InstantStopwatch,
 };
 use std::{collections::VecDeque, fs::File, path::Path};
@@ -18,6 +21,7 @@ use std::{collections::VecDeque, fs::File, path::Path};
 const SUPPORTED_SNAPSHOT_FORMATS: [&str; 1] = ["sna"];
 const SUPPORTED_TAPE_FORMATS: [&str; 1] = ["tap"];
 const SUPPORTED_SCREEN_FORMATS: [&str; 1] = ["scr"];
+const SUPPORTED_CARTRIDGE_FORMATS: [&str; 1] = ["dck"];
 
 pub struct AppHost;
 
@@ -28,13 +32,25 @@ impl Host for AppHost {
     type FrameBuffer = RgbaFrameBuffer;
     type IoExtender = StubIoExtender;
     type TapeAsset = DynamicAsset;
+    #[cfg(feature = "ide")]
+    type BlockDevice = rustzx_utils::io::FileBlockDevice;
+    #[cfg(not(feature = "ide"))]
+    type BlockDevice = rustzx_core::host::StubBlockDevice;
+    #[cfg(feature = "spectranet")]
+    type NetworkStack = rustzx_utils::io::StdNetworkStack;
+    #[cfg(not(feature = "spectranet"))]
+    type NetworkStack = rustzx_core::host::StubNetworkStack;
 }
 
-pub struct AppHostContext;
+pub struct AppHostContext {
+    pub palette: Palette,
+}
 
 impl HostContext<AppHost> for AppHostContext {
     fn frame_buffer_context(&self) -> <<AppHost as Host>::FrameBuffer as FrameBuffer>::Context {
-        FrameBufferContext
+        FrameBufferContext {
+            palette: self.palette.clone(),
+        }
     }
 }
 
@@ -58,14 +74,22 @@ pub enum DetectedFileKind {
     Tape,
     Snapshot,
     Screen,
+    Cartridge,
 }
 
 pub enum DetectedContainerKind {
     None,
     Gzip,
+    /// First recognized file of the archive with the expected format is used
+    #[cfg(feature = "zip")]
+    Zip,
 }
 
-pub fn load_asset(path: &Path) -> anyhow::Result<DynamicAsset> {
+/// Opens the asset, unpacking gzip and zip containers. First entry of the
+/// zip archive with one of the `formats` extensions is used, or the first
+/// recognized entry if `formats` is empty
+#[cfg_attr(not(feature = "zip"), allow(unused_variables))]
+pub fn load_asset(path: &Path, formats: &[&str]) -> anyhow::Result<DynamicAsset> {
     let container_kind = detect_container(path);
 
     let file = File::open(path).with_context(|| "Failed to open tape file")?;
@@ -76,10 +100,42 @@ pub fn load_asset(path: &Path) -> anyhow::Result<DynamicAsset> {
             let gzip = GzipAsset::new(file)?;
             Ok(gzip.into())
         }
+        #[cfg(feature = "zip")]
+        DetectedContainerKind::Zip => Ok(load_archive_entry(file, formats)?.into()),
+    }
+}
+
+/// Reads the whole asset into memory, unpacking gzip and zip containers the
+/// same way as [load_asset]
+#[cfg_attr(not(feature = "zip"), allow(unused_variables))]
+pub fn load_bytes(path: &Path, formats: &[&str]) -> anyhow::Result<Vec<u8>> {
+    let container_kind = detect_container(path);
+
+    let file = File::open(path).with_context(|| "Failed to open file")?;
+
+    match container_kind {
+        DetectedContainerKind::None => Ok(MemoryAsset::from_reader(file)?.into_vec()),
+        DetectedContainerKind::Gzip => Ok(GzipAsset::new(file)?.into_vec()),
+        #[cfg(feature = "zip")]
+        DetectedContainerKind::Zip => Ok(load_archive_entry(file, formats)?.into_vec()),
     }
 }
 
-pub fn load_tape(path: &Path) -> anyhow::Result<Tape<DynamicAsset>> {
+#[cfg(feature = "zip")]
+fn load_archive_entry(file: File, formats: &[&str]) -> anyhow::Result<ZipAsset> {
+    let mut archive = ZipArchive::new(file)?;
+    let name = archive
+        .entries()
+        .into_iter()
+        .map(|entry| entry.name)
+        .find(|name| formats.is_empty() || file_extension_matches_one_of(Path::new(name), formats))
+        .ok_or(ZipError::InvalidArchive(
+            "no supported files in the archive",
+        ))?;
+    Ok(archive.load(&name)?)
+}
+
+pub fn load_tape(path: &Path) -> anyhow::Result<Tape<BufferCursor<Vec<u8>>>> {
     if !file_extension_matches_one_of(path, &SUPPORTED_TAPE_FORMATS) {
         bail!("Invalid tape format");
     }
@@ -88,12 +144,12 @@ pub fn load_tape(path: &Path) -> anyhow::Result<Tape<DynamicAsset>> {
         bail!("Provided tape file does not exist");
     }
 
-    load_asset(path)
-        .map(Tape::Tap)
+    load_bytes(path, &SUPPORTED_TAPE_FORMATS)
+        .map(Tape::tap_from_bytes)
         .with_context(|| "Failed to load tape file")
 }
 
-pub fn load_snapshot(path: &Path) -> anyhow::Result<Snapshot<DynamicAsset>> {
+pub fn load_snapshot(path: &Path) -> anyhow::Result<Snapshot<BufferCursor<Vec<u8>>>> {
     if !file_extension_matches_one_of(path, &SUPPORTED_SNAPSHOT_FORMATS) {
         bail!("Invalid snapshot format");
     }
@@ -102,12 +158,23 @@ pub fn load_snapshot(path: &Path) -> anyhow::Result<Snapshot<DynamicAsset>> {
         bail!("Provided snapshot file does not exist");
     }
 
-    load_asset(path)
-        .map(Snapshot::Sna)
+    load_bytes(path, &SUPPORTED_SNAPSHOT_FORMATS)
+        .map(Snapshot::sna_from_bytes)
         .with_context(|| "Failed to load snapshot file")
 }
 
-pub fn load_screen(path: &Path) -> anyhow::Result<Screen<DynamicAsset>> {
+#[cfg(feature = "ide")]
+pub fn load_hard_disk(path: &Path) -> anyhow::Result<rustzx_utils::io::FileBlockDevice> {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| "Failed to open hard disk image")?;
+    rustzx_utils::io::FileBlockDevice::new(file, false)
+        .with_context(|| "Failed to load hard disk image")
+}
+
+pub fn load_screen(path: &Path) -> anyhow::Result<Screen<BufferCursor<Vec<u8>>>> {
     if !file_extension_matches_one_of(path, &SUPPORTED_SCREEN_FORMATS) {
         bail!("Invalid screen format");
     }
@@ -116,18 +183,93 @@ pub fn load_screen(path: &Path) -> anyhow::Result<Screen<DynamicAsset>> {
         bail!("Provided screen file does not exist");
     }
 
-    load_asset(path)
-        .map(Screen::Scr)
+    load_bytes(path, &SUPPORTED_SCREEN_FORMATS)
+        .map(Screen::scr_from_bytes)
         .with_context(|| "Failed to load screen file")
 }
 
+pub fn load_cartridge(path: &Path) -> anyhow::Result<Cartridge<DynamicAsset>> {
+    if !file_extension_matches_one_of(path, &SUPPORTED_CARTRIDGE_FORMATS) {
+        bail!("Invalid cartridge format");
+    }
+
+    if !path.exists() {
+        bail!("Provided cartridge file does not exist");
+    }
+
+    load_asset(path, &SUPPORTED_CARTRIDGE_FORMATS)
+        .map(Cartridge::Dck)
+        .with_context(|| "Failed to load cartridge file")
+}
+
+#[cfg(feature = "plusd")]
+pub fn load_plusd_rom(path: &Path) -> anyhow::Result<DynamicAsset> {
+    load_asset(path, &[]).with_context(|| "Failed to load +D ROM")
+}
+
+#[cfg(feature = "plusd")]
+pub fn load_disk(path: &Path) -> anyhow::Result<rustzx_core::host::Disk<DynamicAsset>> {
+    use rustzx_core::host::Disk;
+
+    let make_disk = if file_extension_matches_one_of(path, &["mgt"]) {
+        Disk::Mgt
+    } else if file_extension_matches_one_of(path, &["img"]) {
+        Disk::Img
+    } else if file_extension_matches_one_of(path, &["udi"]) {
+        Disk::Udi
+    } else {
+        bail!("Invalid disk image format");
+    };
+
+    if !path.exists() {
+        bail!("Provided disk image file does not exist");
+    }
+
+    load_asset(path, &["mgt", "img", "udi"])
+        .map(make_disk)
+        .with_context(|| "Failed to load disk image")
+}
+
+#[cfg(feature = "betadisk")]
+pub fn load_trdos_rom(path: &Path) -> anyhow::Result<DynamicAsset> {
+    load_asset(path, &[]).with_context(|| "Failed to load TR-DOS ROM")
+}
+
+#[cfg(feature = "betadisk")]
+pub fn load_trdos_disk(path: &Path) -> anyhow::Result<rustzx_core::host::Disk<DynamicAsset>> {
+    use rustzx_core::host::Disk;
+
+    let make_disk = if file_extension_matches_one_of(path, &["trd"]) {
+        Disk::Trd
+    } else if file_extension_matches_one_of(path, &["scl"]) {
+        Disk::Scl
+    } else if file_extension_matches_one_of(path, &["udi"]) {
+        Disk::Udi
+    } else {
+        bail!("Invalid TR-DOS disk image format");
+    };
+
+    if !path.exists() {
+        bail!("Provided disk image file does not exist");
+    }
+
+    load_asset(path, &["trd", "scl", "udi"])
+        .map(make_disk)
+        .with_context(|| "Failed to load disk image")
+}
+
+#[cfg(feature = "spectranet")]
+pub fn load_spectranet_rom(path: &Path) -> anyhow::Result<DynamicAsset> {
+    load_asset(path, &[]).with_context(|| "Failed to load Spectranet flash ROM")
+}
+
 fn load_rom_asset(path: &Path) -> anyhow::Result<DynamicAsset> {
-    load_asset(path).with_context(|| "Failed to load rom asset")
+    load_asset(path, &[]).with_context(|| "Failed to load rom asset")
 }
 
 pub fn load_rom(path: &Path, machine: ZXMachine) -> anyhow::Result<FileRomSet> {
-    match machine {
-        ZXMachine::Sinclair48K => {
+    match machine.memory_model() {
+        MemoryModel::Sinclair48K => {
             if !path.exists() {
                 bail!("Provided 48K ROM file does not exist")
             }
@@ -138,38 +280,54 @@ pub fn load_rom(path: &Path, machine: ZXMachine) -> anyhow::Result<FileRomSet> {
                 ]),
             })
         }
-        ZXMachine::Sinclair128K => {
-            let rom0_path = path;
-            if !file_extension_matches(rom0_path, "0") {
-                bail!("128K ROM filename should end with '.0' extension");
-            }
-            if !rom0_path.exists() {
-                bail!("Provided 128K ROM0 file does not exist");
-            }
-            let rom1_path = if is_container(rom0_path) {
-                let container_ext = rom0_path.extension().unwrap().to_string_lossy();
-                let mut new_path = rom0_path.to_owned();
-                new_path.set_extension(""); // removes just container extension
-                new_path.with_extension(format!("1.{}", container_ext))
-            } else {
-                rom0_path.to_owned().with_extension("1")
-            };
-
-            if !rom1_path.exists() {
-                bail!("Provided 128K ROM1 file does not exist");
-            }
+        MemoryModel::Sinclair128K => load_rom_pages(path, "128K", 2),
+        MemoryModel::SinclairPlus3 => load_rom_pages(path, "+3", 4),
+    }
+}
 
-            Ok(FileRomSet {
-                pages: VecDeque::from(vec![
-                    load_rom_asset(rom0_path).with_context(|| "128K ROM0 load failed")?,
-                    load_rom_asset(&rom1_path).with_context(|| "128K ROM1 load failed")?,
-                ]),
-            })
+/// Loads multipart ROM, `rom0_path` should have `.0` extension, next pages
+/// are loaded from the files with `.1`, `.2`... extensions
+fn load_rom_pages(rom0_path: &Path, model: &str, count: usize) -> anyhow::Result<FileRomSet> {
+    if !file_extension_matches(rom0_path, "0") {
+        bail!("{} ROM filename should end with '.0' extension", model);
+    }
+    let mut pages = VecDeque::new();
+    for page in 0..count {
+        let page_path = if page == 0 {
+            rom0_path.to_owned()
+        } else if is_container(rom0_path) {
+            let container_ext = rom0_path.extension().unwrap().to_string_lossy();
+            let mut new_path = rom0_path.to_owned();
+            new_path.set_extension(""); // removes just container extension
+            new_path.with_extension(format!("{}.{}", page, container_ext))
+        } else {
+            rom0_path.to_owned().with_extension(page.to_string())
+        };
+        if !page_path.exists() {
+            bail!("Provided {} ROM{} file does not exist", model, page);
         }
+        pages.push_back(
+            load_rom_asset(&page_path)
+                .with_context(|| format!("{} ROM{} load failed", model, page))?,
+        );
     }
+    Ok(FileRomSet { pages })
 }
 
 pub fn detect_file_type(path: &Path) -> anyhow::Result<DetectedFileKind> {
+    #[cfg(feature = "zip")]
+    if let DetectedContainerKind::Zip = detect_container(path) {
+        // Entry is validated when the archive is loaded
+        let entry = archive_entries(path)?.into_iter().next();
+        return match entry.map(|entry| entry.kind) {
+            Some(ArchiveEntryKind::Tape) => Ok(DetectedFileKind::Tape),
+            Some(ArchiveEntryKind::Snapshot) => Ok(DetectedFileKind::Snapshot),
+            Some(ArchiveEntryKind::Screen) => Ok(DetectedFileKind::Screen),
+            Some(ArchiveEntryKind::Cartridge) => Ok(DetectedFileKind::Cartridge),
+            _ => Err(anyhow!("Not supported file format")),
+        };
+    }
+
     if file_extension_matches_one_of(path, &SUPPORTED_TAPE_FORMATS) {
         load_tape(path)?;
         Ok(DetectedFileKind::Tape)
@@ -178,6 +336,8 @@ pub fn detect_file_type(path: &Path) -> anyhow::Result<DetectedFileKind> {
         Ok(DetectedFileKind::Snapshot)
     } else if file_extension_matches_one_of(path, &SUPPORTED_SCREEN_FORMATS) {
         Ok(DetectedFileKind::Screen)
+    } else if file_extension_matches_one_of(path, &SUPPORTED_CARTRIDGE_FORMATS) {
+        Ok(DetectedFileKind::Cartridge)
     } else {
         Err(anyhow!("Not supported file format"))
     }
@@ -197,11 +357,20 @@ fn detect_container(path: &Path) -> DetectedContainerKind {
 
     match ext.as_str() {
         "gz" => DetectedContainerKind::Gzip,
+        #[cfg(feature = "zip")]
+        "zip" => DetectedContainerKind::Zip,
         _ => DetectedContainerKind::None,
     }
 }
 
+/// Checks extension of the file inside gzip container. Zip archives match
+/// any extension, the format of the entry is checked when it is loaded
 fn file_extension_matches(path: &Path, expected: &str) -> bool {
+    #[cfg(feature = "zip")]
+    if let DetectedContainerKind::Zip = detect_container(path) {
+        return true;
+    }
+
     let mut path = path.to_owned();
     // Ignore outer container extension during comparison
     if is_container(&path) {
@@ -232,15 +401,15 @@ mod tests {
 
     #[test]
     fn file_extension_matches_returns_true() {
-        assert!(file_extension_matches(&Path::new("test.tap"), "tap"));
-        assert!(file_extension_matches(&Path::new("test.TAP"), "tap"));
-        assert!(file_extension_matches(&Path::new("test.tAp"), "tap"));
-        assert!(file_extension_matches(&Path::new("test.tap.gz"), "tap"));
-        assert!(file_extension_matches(&Path::new("test.tap.gZ"), "tap"));
+        assert!(file_extension_matches(Path::new("test.tap"), "tap"));
+        assert!(file_extension_matches(Path::new("test.TAP"), "tap"));
+        assert!(file_extension_matches(Path::new("test.tAp"), "tap"));
+        assert!(file_extension_matches(Path::new("test.tap.gz"), "tap"));
+        assert!(file_extension_matches(Path::new("test.tap.gZ"), "tap"));
     }
 
     #[test]
     fn file_extension_matches_returns_false() {
-        assert!(!file_extension_matches(&Path::new("test.tap"), "sna"));
+        assert!(!file_extension_matches(Path::new("test.tap"), "sna"));
     }
 }
@@ -3,14 +3,45 @@ mod backends;
 mod host;
 
 use app::{RustzxApp, Settings};
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use std::collections::HashMap;
 use structopt::StructOpt;
 
-fn main() {
-    simple_logger::init_with_env().expect("Failed to initialize logger");
+const CORE_LOG_TARGETS: &[&str] = &[
+    "rustzx::tape",
+    "rustzx::snapshot",
+    "rustzx::screen",
+    "rustzx::rom",
+    "rustzx::memory",
+    "rustzx::machine",
+    "rustzx::disk",
+    "rustzx::network",
+];
 
+fn main() {
     let settings = Settings::from_args();
+
+    // Emulator core diagnostics are quiet unless explicitly enabled via `--log`
+    let mut log_filters = CORE_LOG_TARGETS
+        .iter()
+        .map(|target| (target.to_string(), LevelFilter::Warn))
+        .collect::<HashMap<_, _>>();
+    log_filters.extend(settings.log_filters.iter().cloned());
+    log_filters
+        .into_iter()
+        .fold(SimpleLogger::new().env(), |logger, (target, level)| {
+            logger.with_module_level(&target, level)
+        })
+        .init()
+        .expect("Failed to initialize logger");
+
+    let benchmark_frames = settings.benchmark;
     let result = RustzxApp::from_config(settings)
-        .and_then(|mut emulator| emulator.start())
+        .and_then(|mut emulator| match benchmark_frames {
+            Some(frames) => emulator.benchmark(frames),
+            None => emulator.start(),
+        })
         .map_err(|e| {
             log::error!("ERROR: {:#}", e);
         });
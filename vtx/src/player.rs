@@ -178,7 +178,7 @@ mod tests {
     use expect_test::expect;
     use sha2::{Digest, Sha256};
 
-    fn generate_samples_hash_for_vtx<B: AymBackend>(data: &[u8]) -> String {
+    fn generate_samples_hash_for_vtx(data: &[u8]) -> String {
         const SAMPLE_RATE: usize = 44100;
 
         let vtx = Vtx::load(std::io::Cursor::new(data)).unwrap();
@@ -203,7 +203,7 @@ mod tests {
     fn precise_player_works_normally() {
         // This test also doubles as test for `aym` crate with AymPrecise backend selected
         expect![[r#"65127ad0e493b23b43e838b6281d7f8d07a47549efd0c9d1eda3d3ccaf0b7e5c"#]].assert_eq(
-            &generate_samples_hash_for_vtx::<aym::AymPrecise>(include_bytes!("test/csoon.vtx")),
+            &generate_samples_hash_for_vtx(include_bytes!("test/csoon.vtx")),
         )
     }
 }
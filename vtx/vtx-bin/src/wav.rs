@@ -1,4 +1,4 @@
-use std::{fs::File, path::PathBuf, usize};
+use std::{fs::File, path::PathBuf};
 use structopt::StructOpt;
 use vtx::{player::PrecisePlayer, Vtx};
 